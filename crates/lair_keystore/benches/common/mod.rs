@@ -0,0 +1,119 @@
+//! The harness shared by the benchmarks: a keystore server
+//! with clients connected to it, all running in this process.
+#![allow(dead_code)]
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+pub type ApiSend = ghost_actor::GhostSender<LairClientApi>;
+
+/// The runtime the benchmarks block on.
+pub fn new_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+pub struct BenchStatic<T> {
+    pub tokio: tokio::runtime::Runtime,
+    pub tmpdir: tempfile::TempDir,
+    /// Each of these is a connection of its own to the same server.
+    pub api_sends: Vec<ApiSend>,
+    /// What the benchmark set up on the server to work with.
+    pub fixture: T,
+}
+
+impl<T> BenchStatic<T> {
+    /// A server with the default config and `client_count` clients,
+    /// set up over the first of them by `setup`.
+    pub fn new<S, Fut>(client_count: usize, setup: S) -> Self
+    where
+        S: FnOnce(ApiSend) -> Fut,
+        Fut: std::future::Future<Output = T>,
+    {
+        Self::with_config(client_count, |config| config, setup)
+    }
+
+    /// A server with the config `f` builds and `client_count` clients,
+    /// set up over the first of them by `setup`.
+    pub fn with_config<F, S, Fut>(client_count: usize, f: F, setup: S) -> Self
+    where
+        F: FnOnce(ConfigBuilder) -> ConfigBuilder,
+        S: FnOnce(ApiSend) -> Fut,
+        Fut: std::future::Future<Output = T>,
+    {
+        let tokio = new_runtime();
+
+        let (tmpdir, api_sends, fixture) = tokio.block_on(async move {
+            let tmpdir = tempfile::tempdir().unwrap();
+
+            let config =
+                f(Config::builder().set_root_path(tmpdir.path())).build();
+
+            let _shutdown = lair_keystore::execute_lair_with_config(
+                config.clone(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+            let mut api_sends = Vec::new();
+            for _ in 0..client_count {
+                api_sends.push(connect(config.clone()).await);
+            }
+
+            let fixture = setup(api_sends[0].clone()).await;
+
+            (tmpdir, api_sends, fixture)
+        });
+
+        Self {
+            tokio,
+            tmpdir,
+            api_sends,
+            fixture,
+        }
+    }
+
+    /// The client the single client benchmarks use.
+    pub fn api_send(&self) -> &ApiSend {
+        &self.api_sends[0]
+    }
+}
+
+/// Connect a client to the server of `config`, answering its events.
+pub async fn connect(config: Arc<Config>) -> ApiSend {
+    let (api_send, evt_recv) = ipc::spawn_client_ipc(config).await.unwrap();
+    tokio::task::spawn(respond_to_events(evt_recv));
+    api_send
+}
+
+/// Unlock with "passphrase", deny signing approvals,
+/// and acknowledge everything else.
+pub async fn respond_to_events(mut evt_recv: LairClientEventReceiver) {
+    while let Some(msg) = evt_recv.next().await {
+        match msg {
+            LairClientEvent::RequestUnlockPassphrase { respond, .. } => {
+                respond.respond(Ok(async move {
+                    Ok("passphrase".to_string().into())
+                }
+                .boxed()
+                .into()));
+            }
+            LairClientEvent::RequestSigningApproval { respond, .. } => {
+                respond.respond(Ok(async move { Ok(false) }.boxed().into()));
+            }
+            LairClientEvent::Reconnected { respond, .. }
+            | LairClientEvent::EntryCreated { respond, .. }
+            | LairClientEvent::EntryDeleted { respond, .. }
+            | LairClientEvent::Locked { respond, .. }
+            | LairClientEvent::Unlocked { respond, .. } => {
+                respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, Criterion,
+};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::{crypto_box, x25519};
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+mod common;
+use common::*;
+
+/// A server with a crypto_box cache of `crypto_box_cache_size`,
+/// and an x25519 key of its own to box with.
+fn new_keystore(crypto_box_cache_size: usize) -> BenchStatic<KeystoreIndex> {
+    BenchStatic::with_config(
+        1,
+        |config| config.set_crypto_box_cache_size(crypto_box_cache_size),
+        |api_send| async move {
+            let (x25519_idx, _pub_key) =
+                api_send.x25519_new_from_entropy().await.unwrap();
+            x25519_idx
+        },
+    )
+}
+
+static CACHED: Lazy<BenchStatic<KeystoreIndex>> =
+    Lazy::new(|| new_keystore(crypto_box::DEFAULT_CACHE_SIZE));
+
+static UNCACHED: Lazy<BenchStatic<KeystoreIndex>> =
+    Lazy::new(|| new_keystore(0));
+
+static RECIPIENT: Lazy<x25519::X25519PubKey> =
+    Lazy::new(|| CACHED.tokio.block_on(new_recipient()));
+
+async fn new_recipient() -> x25519::X25519PubKey {
+    x25519::x25519_keypair_new_from_entropy()
+        .await
+        .unwrap()
+        .pub_key
+}
+
+fn box_to(
+    keystore: &'static BenchStatic<KeystoreIndex>,
+    recipient: x25519::X25519PubKey,
+) {
+    keystore.tokio.block_on(async move {
+        let _result = keystore
+            .api_send()
+            .crypto_box_by_index(
+                keystore.fixture,
+                recipient,
+                black_box(Arc::new(vec![0xdb; 32].into())),
+            )
+            .await
+            .unwrap();
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    // boxing to the same recipient over and over,
+    // only the first box misses the cache
+    let mut group = c.benchmark_group("crypto_box_repeat_recipient");
+    group.bench_function("cached", |b| {
+        b.iter(|| box_to(&CACHED, RECIPIENT.clone()))
+    });
+    group.bench_function("uncached", |b| {
+        b.iter(|| box_to(&UNCACHED, RECIPIENT.clone()))
+    });
+    group.finish();
+
+    // boxing to a recipient never seen before always misses the cache
+    let mut group = c.benchmark_group("crypto_box_first_use");
+    group.bench_function("cached", |b| {
+        b.iter_batched(
+            || CACHED.tokio.block_on(new_recipient()),
+            |recipient| box_to(&CACHED, recipient),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("uncached", |b| {
+        b.iter_batched(
+            || CACHED.tokio.block_on(new_recipient()),
+            |recipient| box_to(&UNCACHED, recipient),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
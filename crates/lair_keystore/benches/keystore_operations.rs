@@ -0,0 +1,168 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+    Throughput,
+};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::{crypto_box, x25519};
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+mod common;
+use common::*;
+
+/// How many clients the multi-client benchmarks connect.
+const CLIENT_COUNT: usize = 4;
+
+/// How many signatures the multi-client benchmarks make in all.
+const SIGNATURE_COUNT: usize = 100;
+
+/// The crypto_box payload sizes benchmarked.
+const BOX_SIZES: &[(&str, usize)] = &[
+    ("1_kib", 1024),
+    ("64_kib", 64 * 1024),
+    ("1_mib", 1024 * 1024),
+];
+
+struct Keys {
+    pub sign_idx: KeystoreIndex,
+    pub x25519_idx: KeystoreIndex,
+    pub x25519_pub_key: x25519::X25519PubKey,
+}
+
+static STATIC: Lazy<BenchStatic<Keys>> = Lazy::new(|| {
+    BenchStatic::new(CLIENT_COUNT, |api_send| async move {
+        let (sign_idx, _sign_pub_key) =
+            api_send.sign_ed25519_new_from_entropy().await.unwrap();
+        let (x25519_idx, x25519_pub_key) =
+            api_send.x25519_new_from_entropy().await.unwrap();
+        Keys {
+            sign_idx,
+            x25519_idx,
+            x25519_pub_key,
+        }
+    })
+});
+
+/// Box `data` to our own x25519 key.
+async fn box_to_self(
+    data: Arc<crypto_box::CryptoBoxData>,
+) -> crypto_box::CryptoBoxEncryptedData {
+    STATIC
+        .api_send()
+        .crypto_box_by_index(
+            STATIC.fixture.x25519_idx,
+            STATIC.fixture.x25519_pub_key.clone(),
+            data,
+        )
+        .await
+        .unwrap()
+}
+
+fn box_payload(data: &Arc<crypto_box::CryptoBoxData>) {
+    STATIC.tokio.block_on(async move {
+        let _result = box_to_self(black_box(data.clone())).await;
+    });
+}
+
+fn open_payload(encrypted_data: &Arc<crypto_box::CryptoBoxEncryptedData>) {
+    STATIC.tokio.block_on(async move {
+        let result = STATIC
+            .api_send()
+            .crypto_box_open_by_index(
+                STATIC.fixture.x25519_idx,
+                STATIC.fixture.x25519_pub_key.clone(),
+                black_box(encrypted_data.clone()),
+            )
+            .await
+            .unwrap();
+        assert!(result.is_some());
+    });
+}
+
+fn x25519_new_from_entropy() {
+    STATIC.tokio.block_on(async move {
+        let _result =
+            STATIC.api_send().x25519_new_from_entropy().await.unwrap();
+    });
+}
+
+fn tls_cert_new_self_signed_from_entropy() {
+    STATIC.tokio.block_on(async move {
+        let _result = STATIC
+            .api_send()
+            .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+            .await
+            .unwrap();
+    });
+}
+
+/// Sign `count` small messages one after the other over `api_send`.
+async fn sign_sequential(api_send: &ApiSend, count: usize) {
+    for _ in 0..count {
+        let _result = api_send
+            .sign_ed25519_sign_by_index(
+                STATIC.fixture.sign_idx,
+                black_box(vec![0xdb; 32].into()),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+fn sign_one_client() {
+    STATIC.tokio.block_on(async move {
+        sign_sequential(STATIC.api_send(), SIGNATURE_COUNT).await;
+    });
+}
+
+/// The same signatures, split over all the clients signing at once.
+fn sign_all_clients() {
+    STATIC.tokio.block_on(async move {
+        futures::future::join_all(STATIC.api_sends.iter().map(|api_send| {
+            sign_sequential(api_send, SIGNATURE_COUNT / CLIENT_COUNT)
+        }))
+        .await;
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crypto_box_payload_size");
+    group.sample_size(20);
+    for (label, size) in BOX_SIZES {
+        let data = Arc::new(crypto_box::CryptoBoxData::from(vec![0xdb; *size]));
+        let encrypted_data =
+            Arc::new(STATIC.tokio.block_on(box_to_self(data.clone())));
+        group.throughput(Throughput::Bytes(*size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("crypto_box", label),
+            &data,
+            |b, data| b.iter(|| box_payload(data)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("crypto_box_open", label),
+            &encrypted_data,
+            |b, encrypted_data| b.iter(|| open_payload(encrypted_data)),
+        );
+    }
+    group.finish();
+
+    // each iteration stores a new entry
+    let mut group = c.benchmark_group("keypair_generation");
+    group.sample_size(20);
+    group.bench_function("x25519_new_from_entropy", |b| {
+        b.iter(x25519_new_from_entropy)
+    });
+    group.bench_function("tls_cert_new_self_signed_from_entropy", |b| {
+        b.iter(tls_cert_new_self_signed_from_entropy)
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("multi_client_signature_generation");
+    group.sample_size(20);
+    group.bench_function("sign_100_one_client", |b| b.iter(sign_one_client));
+    group.bench_function("sign_100_four_clients", |b| b.iter(sign_all_clients));
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,99 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion, Throughput,
+};
+use lair_keystore_api::actor::*;
+use once_cell::sync::Lazy;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::*;
+
+/// Counts allocations (of client and server alike, both run in this
+/// process), to report what signing a large message costs besides time.
+struct CountingAlloc;
+
+static ALLOC_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        ALLOC_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        ALLOC_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// The size of the messages signed.
+const MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// The key signed with, and the message signed.
+static STATIC: Lazy<BenchStatic<(KeystoreIndex, bytes::Bytes)>> =
+    Lazy::new(|| {
+        BenchStatic::new(1, |api_send| async move {
+            let (sign_idx, _sign_pub_key) =
+                api_send.sign_ed25519_new_from_entropy().await.unwrap();
+            (sign_idx, vec![0xdb; MESSAGE_SIZE].into())
+        })
+    });
+
+fn sign_large() {
+    STATIC.tokio.block_on(async move {
+        let (sign_idx, message) = &STATIC.fixture;
+        let _result = STATIC
+            .api_send()
+            .sign_ed25519_sign_by_index(*sign_idx, black_box(message.clone()))
+            .await
+            .unwrap();
+    });
+}
+
+/// Print the allocations made per signature, averaged over a few.
+fn report_allocations() {
+    const SIGNATURES: usize = 20;
+    // warm up the connection and the keypair cache first
+    sign_large();
+    let count = ALLOC_COUNT.load(Ordering::Relaxed);
+    let bytes = ALLOC_BYTES.load(Ordering::Relaxed);
+    for _ in 0..SIGNATURES {
+        sign_large();
+    }
+    let count = ALLOC_COUNT.load(Ordering::Relaxed) - count;
+    let bytes = ALLOC_BYTES.load(Ordering::Relaxed) - bytes;
+    println!(
+        "sign_1_mib_message: {} allocations ({} KiB) per signature",
+        count / SIGNATURES,
+        bytes / SIGNATURES / 1024,
+    );
+}
+
+fn bench(c: &mut Criterion) {
+    report_allocations();
+
+    let mut group = c.benchmark_group("large_message_signing");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(MESSAGE_SIZE as u64));
+    group.bench_function("sign_1_mib_message", |b| b.iter(sign_large));
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
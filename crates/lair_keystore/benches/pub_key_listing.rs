@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use lair_keystore_api::actor::*;
+use once_cell::sync::Lazy;
+
+mod common;
+use common::*;
+
+const KEY_COUNT: usize = 100;
+
+static STATIC: Lazy<BenchStatic<Vec<KeystoreIndex>>> = Lazy::new(|| {
+    BenchStatic::new(1, |api_send| async move {
+        let mut sign_idxs = Vec::new();
+        for _ in 0..KEY_COUNT {
+            let (sign_idx, _sign_pub_key) =
+                api_send.sign_ed25519_new_from_entropy().await.unwrap();
+            sign_idxs.push(sign_idx);
+        }
+        sign_idxs
+    })
+});
+
+fn list_pub_keys() {
+    STATIC.tokio.block_on(async move {
+        let pub_keys = STATIC
+            .api_send()
+            .sign_ed25519_list_pub_keys()
+            .await
+            .unwrap();
+        assert_eq!(KEY_COUNT, pub_keys.len());
+    });
+}
+
+fn get_pub_keys() {
+    STATIC.tokio.block_on(async move {
+        for sign_idx in STATIC.fixture.iter() {
+            let _pub_key =
+                STATIC.api_send().sign_ed25519_get(*sign_idx).await.unwrap();
+        }
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pub_key_listing");
+    group.bench_function("list_100_pub_keys", |b| b.iter(list_pub_keys));
+    group.bench_function("get_100_pub_keys", |b| b.iter(get_pub_keys));
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::sign_ed25519::SignEd25519PubKey;
+use once_cell::sync::Lazy;
+
+mod common;
+use common::*;
+
+/// Enough keys that scanning them would show against a lookup.
+const KEY_COUNT: usize = 5_000;
+
+static STATIC: Lazy<BenchStatic<Vec<(KeystoreIndex, SignEd25519PubKey)>>> =
+    Lazy::new(|| {
+        BenchStatic::new(1, |api_send| async move {
+            let mut keys = Vec::new();
+            for _ in 0..KEY_COUNT {
+                keys.push(
+                    api_send.sign_ed25519_new_from_entropy().await.unwrap(),
+                );
+            }
+            keys
+        })
+    });
+
+/// The keys signed with, from all over the store.
+fn sample_keys(
+) -> impl Iterator<Item = &'static (KeystoreIndex, SignEd25519PubKey)> {
+    STATIC.fixture.iter().step_by(KEY_COUNT / 10)
+}
+
+fn sign_by_index() {
+    STATIC.tokio.block_on(async move {
+        for (sign_idx, _) in sample_keys() {
+            let _result = STATIC
+                .api_send()
+                .sign_ed25519_sign_by_index(
+                    *sign_idx,
+                    black_box(vec![0xdb; 32].into()),
+                )
+                .await
+                .unwrap();
+        }
+    });
+}
+
+fn sign_by_pub_key() {
+    STATIC.tokio.block_on(async move {
+        for (_, pub_key) in sample_keys() {
+            let _result = STATIC
+                .api_send()
+                .sign_ed25519_sign_by_pub_key(
+                    pub_key.clone(),
+                    black_box(vec![0xdb; 32].into()),
+                )
+                .await
+                .unwrap();
+        }
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    // same latency either way, however many keys
+    let mut group = c.benchmark_group("pub_key_lookup");
+    group.bench_function("sign_10_of_5000_by_index", |b| b.iter(sign_by_index));
+    group.bench_function("sign_10_of_5000_by_pub_key", |b| {
+        b.iter(sign_by_pub_key)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
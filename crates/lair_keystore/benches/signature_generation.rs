@@ -1,101 +1,148 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use futures::{future::FutureExt, stream::StreamExt};
 use lair_keystore_api::actor::*;
 use lair_keystore_api::*;
 use once_cell::sync::Lazy;
-use std::sync::Arc;
 
-struct BenchStatic {
-    pub tokio: tokio::runtime::Handle,
-    pub tmpdir: tempfile::TempDir,
-    pub api_send: ghost_actor::GhostSender<LairClientApi>,
-    pub sign_idx: KeystoreIndex,
-}
-
-impl BenchStatic {
-    pub fn new() -> Self {
-        let mut runtime = tokio::runtime::Builder::new()
-            .enable_all()
-            .threaded_scheduler()
-            .build()
-            .unwrap();
-
-        let tokio = runtime.handle().clone();
-
-        std::thread::spawn(move || {
-            runtime.block_on(async move {
-                futures::future::pending::<()>().await;
-            });
-        });
-
-        let (tmpdir, api_send, sign_idx) = tokio.enter(move || {
-            futures::executor::block_on(async move {
-                let tmpdir = tempfile::tempdir().unwrap();
-                std::env::set_var("LAIR_DIR", tmpdir.path());
+mod common;
+use common::*;
 
-                lair_keystore::execute_lair().await.unwrap();
+/// Two keys to sign with.
+static STATIC: Lazy<BenchStatic<[KeystoreIndex; 2]>> = Lazy::new(|| {
+    BenchStatic::new(1, |api_send| async move {
+        let (sign_idx, _) =
+            api_send.sign_ed25519_new_from_entropy().await.unwrap();
+        let (other_sign_idx, _) =
+            api_send.sign_ed25519_new_from_entropy().await.unwrap();
+        [sign_idx, other_sign_idx]
+    })
+});
 
-                let config =
-                    Config::builder().set_root_path(tmpdir.path()).build();
+/// How many messages the batch benchmarks sign.
+const BATCH_SIZE: usize = 500;
 
-                let (api_send, mut evt_recv) =
-                    ipc::spawn_client_ipc(config).await.unwrap();
+/// How many requests the pipelining benchmarks have in flight.
+const PIPELINE_SIZE: usize = 100;
 
-                tokio::task::spawn(async move {
-                    while let Some(msg) = evt_recv.next().await {
-                        match msg {
-                            LairClientEvent::RequestUnlockPassphrase {
-                                respond,
-                                ..
-                            } => {
-                                respond.respond(Ok(async move {
-                                    Ok("passphrase".to_string())
-                                }
-                                .boxed()
-                                .into()));
-                            }
-                        }
-                    }
-                });
-
-                let info = api_send.lair_get_server_info().await.unwrap();
-                assert_eq!("lair-keystore", &info.name);
-
-                let (sign_idx, _sign_pub_key) =
-                    api_send.sign_ed25519_new_from_entropy().await.unwrap();
-
-                (tmpdir, api_send, sign_idx)
-            })
-        });
+fn sign_small() {
+    STATIC.tokio.block_on(async move {
+        let _result = STATIC
+            .api_send()
+            .sign_ed25519_sign_by_index(
+                STATIC.fixture[0],
+                black_box(vec![0xdb; 32].into()),
+            )
+            .await
+            .unwrap();
+    });
+}
 
-        Self {
-            tokio,
-            tmpdir,
-            api_send,
-            sign_idx,
+/// Signs with each of two keys in turn, so neither
+/// is signed with twice in a row.
+fn sign_small_alternating() {
+    STATIC.tokio.block_on(async move {
+        for sign_idx in STATIC.fixture.iter() {
+            let _result = STATIC
+                .api_send()
+                .sign_ed25519_sign_by_index(
+                    *sign_idx,
+                    black_box(vec![0xdb; 32].into()),
+                )
+                .await
+                .unwrap();
         }
-    }
+    });
 }
 
-static STATIC: Lazy<Arc<BenchStatic>> =
-    Lazy::new(|| Arc::new(BenchStatic::new()));
+fn sign_sequential() {
+    STATIC.tokio.block_on(async move {
+        for _ in 0..BATCH_SIZE {
+            let _result = STATIC
+                .api_send()
+                .sign_ed25519_sign_by_index(
+                    STATIC.fixture[0],
+                    black_box(vec![0xdb; 32].into()),
+                )
+                .await
+                .unwrap();
+        }
+    });
+}
 
-fn sign_small() {
+fn sign_batch() {
     STATIC.tokio.block_on(async move {
         let _result = STATIC
-            .api_send
-            .sign_ed25519_sign_by_index(
-                STATIC.sign_idx,
-                black_box(vec![0xdb; 32].into()),
+            .api_send()
+            .sign_ed25519_sign_batch_by_index(
+                STATIC.fixture[0],
+                black_box(
+                    (0..BATCH_SIZE).map(|_| vec![0xdb; 32].into()).collect(),
+                ),
             )
             .await
             .unwrap();
     });
 }
 
+fn sign_awaiting_each() {
+    STATIC.tokio.block_on(async move {
+        for _ in 0..PIPELINE_SIZE {
+            let _result = STATIC
+                .api_send()
+                .sign_ed25519_sign_by_index(
+                    STATIC.fixture[0],
+                    black_box(vec![0xdb; 32].into()),
+                )
+                .await
+                .unwrap();
+        }
+    });
+}
+
+/// The same requests, all in flight over the connection at once.
+fn sign_join_all() {
+    STATIC.tokio.block_on(async move {
+        let results = futures::future::join_all((0..PIPELINE_SIZE).map(|_| {
+            STATIC.api_send().sign_ed25519_sign_by_index(
+                STATIC.fixture[0],
+                black_box(vec![0xdb; 32].into()),
+            )
+        }))
+        .await;
+        for result in results {
+            result.unwrap();
+        }
+    });
+}
+
 fn bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("signature_generation");
-    group.bench_function("sign_small_message", |b| b.iter(|| sign_small()));
+    group.bench_function("sign_small_message", |b| b.iter(sign_small));
+    group.bench_function("sign_small_message_alternating_keys", |b| {
+        b.iter(sign_small_alternating)
+    });
+    group.finish();
+
+    // small messages are signed inline (see `inline_crypto_threshold`),
+    // compare with handing each to the crypto thread pool and back
+    let mut group = c.benchmark_group("inline_crypto_threshold");
+    group.bench_function("sign_small_message_inline", |b| b.iter(sign_small));
+    set_inline_crypto_threshold(0);
+    group.bench_function("sign_small_message_pooled", |b| b.iter(sign_small));
+    set_inline_crypto_threshold(DEFAULT_INLINE_CRYPTO_THRESHOLD);
+    group.finish();
+
+    let mut group = c.benchmark_group("batch_signature_generation");
+    group.sample_size(20);
+    group.bench_function("sign_500_sequential", |b| b.iter(sign_sequential));
+    group.bench_function("sign_500_batch", |b| b.iter(sign_batch));
+    group.finish();
+
+    let mut group = c.benchmark_group("pipelined_signature_generation");
+    group.sample_size(20);
+    group.bench_function("sign_100_awaiting_each", |b| {
+        b.iter(sign_awaiting_each)
+    });
+    group.bench_function("sign_100_join_all", |b| b.iter(sign_join_all));
     group.finish();
 }
 
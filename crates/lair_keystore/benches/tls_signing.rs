@@ -0,0 +1,59 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lair_keystore_api::actor::*;
+use once_cell::sync::Lazy;
+
+mod common;
+use common::*;
+
+static STATIC: Lazy<BenchStatic<Vec<(TlsCertSignatureScheme, KeystoreIndex)>>> =
+    Lazy::new(|| {
+        BenchStatic::new(1, |api_send| async move {
+            let mut certs = Vec::new();
+            for scheme in [
+                TlsCertSignatureScheme::Ed25519,
+                TlsCertSignatureScheme::EcdsaSecp256r1Sha256,
+                TlsCertSignatureScheme::EcdsaSecp384r1Sha384,
+            ] {
+                let mut options = TlsCertOptions::default();
+                options.alg = scheme.alg();
+                options.exportable = Some(false);
+                let (index, _, _) = api_send
+                    .tls_cert_new_self_signed_from_entropy(options)
+                    .await
+                    .unwrap();
+                certs.push((scheme, index));
+            }
+            certs
+        })
+    });
+
+/// A tls 1.3 CertificateVerify signs 64 pad bytes, a 33 byte context
+/// string, a zero byte and the transcript hash.
+const MESSAGE_SIZE: usize = 64 + 33 + 1 + 48;
+
+fn sign(scheme: TlsCertSignatureScheme, index: KeystoreIndex) {
+    STATIC.tokio.block_on(async move {
+        let _result = STATIC
+            .api_send()
+            .tls_cert_sign_by_index(
+                index,
+                scheme,
+                black_box(vec![0xdb; MESSAGE_SIZE].into()),
+            )
+            .await
+            .unwrap();
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tls_signing");
+    for (scheme, index) in STATIC.fixture.iter().copied() {
+        group.bench_function(format!("sign_{:?}", scheme), |b| {
+            b.iter(|| sign(scheme, index))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lair_keystore_api::entry::{EntryUnlock, UnlockKdf};
+use once_cell::sync::Lazy;
+
+mod common;
+use common::*;
+
+static TOKIO: Lazy<tokio::runtime::Runtime> = Lazy::new(new_runtime);
+
+/// The unlock entry of a store with the passphrase "passphrase".
+static UNLOCK: Lazy<EntryUnlock> = Lazy::new(|| {
+    let (unlock, _key) = TOKIO
+        .block_on(EntryUnlock::new_from_passphrase(
+            b"passphrase".to_vec().into(),
+            UnlockKdf::default(),
+        ))
+        .unwrap();
+    unlock
+});
+
+/// Derive the store key from the passphrase, as unlocking a store does,
+/// with the default argon2id parameters.
+fn unlock_default_kdf() {
+    let unlock = &*UNLOCK;
+    TOKIO.block_on(async move {
+        let key = unlock.unlock(b"passphrase".to_vec().into()).await.unwrap();
+        black_box(key);
+    });
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("unlock");
+    // deliberately slow, a few samples do
+    group.sample_size(10);
+    group.bench_function("unlock_default_kdf", |b| b.iter(unlock_default_kdf));
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
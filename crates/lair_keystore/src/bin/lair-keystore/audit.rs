@@ -0,0 +1,89 @@
+//! `lair-keystore audit` subcommands
+
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::internal::audit::{self, AuditEntry, AuditRecord};
+use lair_keystore_api::*;
+use std::sync::Arc;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum AuditCmd {
+    /// Print the newest audit log records, oldest first.
+    Tail {
+        /// How many records to print.
+        #[structopt(short = "n", long, default_value = "10")]
+        lines: usize,
+    },
+
+    /// Check no audit log record was changed, removed or inserted,
+    /// exiting non-zero if one was.
+    Verify,
+}
+
+/// Execute an `audit` subcommand. The audit log is read, never written,
+/// so these run beside the server.
+pub fn exec(
+    config: Arc<Config>,
+    cmd: &AuditCmd,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let path = config.get_audit_log_path();
+    match cmd {
+        AuditCmd::Tail { lines } => {
+            let records = audit::tail(path, *lines)?;
+            output::print(
+                output,
+                &serde_json::json!({ "records": records }),
+                || {
+                    records
+                        .iter()
+                        .map(record_line)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                },
+            )
+        }
+        AuditCmd::Verify => {
+            let summary = audit::verify(path)?;
+            output::print(output, &summary, || {
+                match (summary.first_seq, summary.last_seq) {
+                    (Some(first), Some(last)) => format!(
+                        "audit log ok: {} records, {} to {}",
+                        summary.records, first, last
+                    ),
+                    _ => "audit log is empty".to_string(),
+                }
+            })
+        }
+    }
+}
+
+/// One tab separated line per record.
+fn record_line(record: &AuditRecord) -> String {
+    let entry = match &record.entry {
+        AuditEntry::Index(index) => format!("index:{}", index),
+        AuditEntry::PubKey(pub_key) => format!("pub_key:{}", pub_key),
+        AuditEntry::Tag(tag) => format!("tag:{}", tag),
+        AuditEntry::CertDigest(digest) => format!("cert_digest:{}", digest),
+        AuditEntry::CertSni(sni) => format!("cert_sni:{}", sni),
+        AuditEntry::Session(session) => format!("session:{}", session),
+    };
+    let outcome = match &record.error {
+        Some(error) => format!("error: {}", error),
+        None => "ok".to_string(),
+    };
+    let op = serde_json::to_value(record.op)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+    [
+        record.seq.to_string(),
+        record.time.clone(),
+        record.client.clone(),
+        op,
+        record.request.clone(),
+        entry,
+        outcome,
+    ]
+    .join("\t")
+}
@@ -0,0 +1,93 @@
+//! `lair-keystore backup` and `lair-keystore restore` subcommands
+
+use crate::output::{self, OutputFormat};
+use lair_keystore::store::StoreBackup;
+use lair_keystore_api::*;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Write an encrypted backup of all entries to `out`.
+pub async fn exec_backup(
+    config: Arc<Config>,
+    out: &Path,
+    piped: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    if out.exists() {
+        return Err(format!("{} already exists", out.display()).into());
+    }
+
+    let kdf = config.get_unlock_kdf();
+    let backup = lair_keystore::backup_lair(config).await?;
+
+    let passphrase = read_passphrase(piped, true)?;
+    let data = backup.encrypt(passphrase, kdf)?;
+
+    let mut file = std::fs::OpenOptions::new();
+    file.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        file.mode(0o600);
+    }
+    let mut file = file.open(out).map_err(LairError::other)?;
+    use std::io::Write;
+    file.write_all(&data).map_err(LairError::other)?;
+    file.sync_all().map_err(LairError::other)?;
+
+    let out = out.display();
+    let entries = backup.entries.len();
+    output::print(
+        output,
+        &serde_json::json!({
+            "out": out.to_string(),
+            "entries": entries,
+        }),
+        || format!("backed up {} entries to {}", entries, out),
+    )
+}
+
+/// Restore the entries of the encrypted backup at `input`.
+pub async fn exec_restore(
+    config: Arc<Config>,
+    input: &Path,
+    merge: bool,
+    piped: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let data = std::fs::read(input)
+        .map_err(|e| LairError::other(format!("{}: {}", input.display(), e)))?;
+
+    let passphrase = read_passphrase(piped, false)?;
+    let backup = StoreBackup::decrypt(&data, passphrase)?;
+
+    let res = lair_keystore::restore_lair(config, backup, merge).await?;
+
+    output::print(
+        output,
+        &serde_json::json!({
+            "restored": res.restored,
+            "skipped": res.skipped,
+        }),
+        || {
+            format!(
+                "restored {} entries ({} skipped, already present)",
+                res.restored, res.skipped
+            )
+        },
+    )
+}
+
+fn read_passphrase(piped: bool, confirm: bool) -> LairResult<Vec<u8>> {
+    if piped {
+        let mut passphrase = crate::read_piped_passphrase()?;
+        // move the allocation out, decrypt / encrypt zeroizes it
+        Ok(std::mem::take(&mut *passphrase))
+    } else if confirm {
+        crate::init::read_new_passphrase("backup passphrase")
+    } else {
+        rpassword::read_password_from_tty(Some("backup passphrase: "))
+            .map(String::into_bytes)
+            .map_err(LairError::other)
+    }
+}
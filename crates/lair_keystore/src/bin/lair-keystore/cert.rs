@@ -0,0 +1,234 @@
+//! `lair-keystore cert` subcommands
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+use structopt::StructOpt;
+
+/// Certificate file encoding.
+#[derive(Debug, Clone, Copy)]
+pub enum CertFormat {
+    Pem,
+    Der,
+}
+
+impl std::str::FromStr for CertFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pem" => Ok(CertFormat::Pem),
+            "der" => Ok(CertFormat::Der),
+            _ => Err(format!("invalid cert format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum CertCmd {
+    /// Export a tls certificate (and optionally its private key).
+    Export {
+        /// Keystore index of the tls cert entry.
+        #[structopt(long, required_unless = "digest")]
+        index: Option<u32>,
+
+        /// Find the tls cert entry by (a unique prefix of) its digest
+        /// in hex, colons allowed, e.g. an openssl sha256 fingerprint.
+        #[structopt(long, conflicts_with = "index")]
+        digest: Option<String>,
+
+        /// Output encoding ("pem" or "der").
+        #[structopt(
+            long,
+            default_value = "pem",
+            possible_values = &["pem", "der"]
+        )]
+        format: CertFormat,
+
+        /// Write to this file instead of stdout.
+        #[structopt(long, parse(from_os_str))]
+        out: Option<std::path::PathBuf>,
+
+        /// Also export the private key (pem only).
+        /// Requires --insecure-export-key.
+        #[structopt(long)]
+        with_key: bool,
+
+        /// Acknowledge that the private key will leave the keystore.
+        #[structopt(long)]
+        insecure_export_key: bool,
+    },
+}
+
+/// Execute a `cert` subcommand.
+/// In json mode, the certificate is either written to `--out` (and the
+/// path reported), or embedded in the json document in `--format`.
+pub async fn exec(
+    config: Arc<Config>,
+    cmd: &CertCmd,
+    output: OutputFormat,
+) -> LairResult<()> {
+    match cmd {
+        CertCmd::Export {
+            index,
+            digest,
+            format,
+            out,
+            with_key,
+            insecure_export_key,
+        } => {
+            if *with_key && !*insecure_export_key {
+                return Err("--with-key requires --insecure-export-key".into());
+            }
+            if *with_key {
+                if let CertFormat::Der = format {
+                    return Err("--with-key requires --format pem".into());
+                }
+            }
+
+            let digest = digest.as_deref().map(parse_hex).transpose()?;
+
+            let keystore = Keystore::connect(config).await?;
+            let res = export(&keystore, *index, digest, *with_key).await;
+            keystore.close().await?;
+            let (index, cert, priv_key) = res?;
+
+            let data = match format {
+                CertFormat::Der => cert.to_vec(),
+                CertFormat::Pem => {
+                    let mut data = pem_encode("CERTIFICATE", &cert);
+                    if let Some(priv_key) = priv_key {
+                        data.push_str(&pem_encode("PRIVATE KEY", &priv_key));
+                    }
+                    data.into_bytes()
+                }
+            };
+
+            match (output, out) {
+                (OutputFormat::Text, out) => {
+                    write_out(out.as_deref(), &data, *with_key)
+                }
+                (OutputFormat::Json, Some(out)) => {
+                    write_out(Some(out), &data, *with_key)?;
+                    output::print(
+                        output,
+                        &serde_json::json!({
+                            "index": index,
+                            "out": out.display().to_string(),
+                        }),
+                        String::new,
+                    )
+                }
+                (OutputFormat::Json, None) => {
+                    let data = match format {
+                        CertFormat::Der => serde_json::json!({
+                            "index": index,
+                            "cert_der_b64": base64::encode(&data),
+                        }),
+                        CertFormat::Pem => serde_json::json!({
+                            "index": index,
+                            "pem": String::from_utf8_lossy(&data),
+                        }),
+                    };
+                    output::print(output, &data, String::new)
+                }
+            }
+        }
+    }
+}
+
+async fn export(
+    keystore: &Keystore,
+    index: Option<u32>,
+    digest: Option<Vec<u8>>,
+    with_key: bool,
+) -> LairResult<(u32, Cert, Option<CertPrivKey>)> {
+    let index = match (index, digest) {
+        (Some(index), _) => index.into(),
+        (None, Some(digest)) => {
+            keystore
+                .tls_cert_find_by_digest_prefix(Arc::new(digest))
+                .await?
+                .0
+        }
+        (None, None) => return Err("--index or --digest required".into()),
+    };
+    let cert = keystore.tls_cert_get_cert_by_index(index).await?;
+    let priv_key = if with_key {
+        Some(keystore.tls_cert_get_priv_key_by_index(index).await?)
+    } else {
+        None
+    };
+    Ok((index.0, cert, priv_key))
+}
+
+/// Parse hex digits, optionally colon separated.
+fn parse_hex(s: &str) -> LairResult<Vec<u8>> {
+    let digits = s.replace(':', "");
+    if digits.is_empty() {
+        return Err(format!("invalid hex digest: {:?}", s).into());
+    }
+    digits
+        .as_bytes()
+        .chunks(2)
+        .map(|b| {
+            std::str::from_utf8(b)
+                .ok()
+                .filter(|b| b.len() == 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| format!("invalid hex digest: {:?}", s).into())
+        })
+        .collect()
+}
+
+/// Encode der bytes as a pem block with given label.
+fn pem_encode(label: &str, der: &[u8]) -> String {
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    let b64 = base64::encode(der);
+    for line in b64.as_bytes().chunks(64) {
+        out.push_str(&String::from_utf8_lossy(line));
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+    out
+}
+
+/// Write data to given file, or stdout.
+/// If `is_secret`, the file is restricted to owner read/write on unix.
+fn write_out(
+    out: Option<&std::path::Path>,
+    data: &[u8],
+    is_secret: bool,
+) -> LairResult<()> {
+    use std::io::Write;
+
+    let out = match out {
+        None => {
+            return std::io::stdout().write_all(data).map_err(LairError::other)
+        }
+        Some(out) => out,
+    };
+
+    let mut file = std::fs::OpenOptions::new();
+    file.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if is_secret {
+        use std::os::unix::fs::OpenOptionsExt;
+        file.mode(0o600);
+    }
+    let mut file = file.open(out).map_err(LairError::other)?;
+
+    // make sure pre-existing files are restricted too
+    #[cfg(unix)]
+    if is_secret {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(LairError::other)?;
+    }
+    #[cfg(not(unix))]
+    let _ = is_secret;
+
+    file.write_all(data).map_err(LairError::other)
+}
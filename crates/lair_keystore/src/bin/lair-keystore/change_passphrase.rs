@@ -0,0 +1,54 @@
+//! `lair-keystore change-passphrase` subcommand
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Prompt for the current and a new passphrase and rotate the
+/// store unlock passphrase, through the running server if any.
+pub async fn exec(
+    config: Arc<Config>,
+    piped: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let (old_passphrase, new_passphrase) = if piped {
+        // one passphrase per line
+        let mut old_passphrase = crate::read_piped_passphrase()?;
+        let mut new_passphrase = crate::read_piped_passphrase()?;
+        (
+            std::mem::take(&mut *old_passphrase),
+            std::mem::take(&mut *new_passphrase),
+        )
+    } else {
+        let old_passphrase =
+            rpassword::read_password_from_tty(Some("current passphrase: "))
+                .map_err(LairError::other)?;
+        let new_passphrase =
+            crate::init::read_new_passphrase("new passphrase")?;
+        (old_passphrase.into_bytes(), new_passphrase)
+    };
+    let old_passphrase =
+        String::from_utf8(old_passphrase).map_err(LairError::other)?;
+    let new_passphrase =
+        String::from_utf8(new_passphrase).map_err(LairError::other)?;
+
+    // a store file opened directly is unlocked with the current passphrase
+    let unlock_passphrase = old_passphrase.clone().into_bytes();
+    let keystore =
+        Keystore::connect_with_unlock(config, move || Ok(unlock_passphrase))
+            .await?;
+
+    let res = keystore
+        .lair_change_unlock_passphrase(old_passphrase, new_passphrase)
+        .await;
+
+    keystore.close().await?;
+
+    res?;
+
+    output::print(output, &serde_json::json!({ "changed": true }), || {
+        "unlock passphrase changed".to_string()
+    })
+}
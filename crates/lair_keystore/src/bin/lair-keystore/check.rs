@@ -0,0 +1,101 @@
+//! `lair-keystore check` subcommand
+
+use crate::output::{self, OutputFormat};
+use lair_keystore::store::{RepairResult, StoreCheck};
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Check (and optionally repair) the store file.
+pub async fn exec(
+    config: Arc<Config>,
+    repair: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let check = lair_keystore::store::check_store(&config).await?;
+    let problems = check.problem_count();
+
+    let repaired = if repair && problems > 0 {
+        Some(lair_keystore::repair_lair(config).await?)
+    } else {
+        None
+    };
+
+    let mut json = check_json(&check);
+    if let Some(repaired) = &repaired {
+        json["repair"] = repair_json(repaired);
+    }
+    output::print(output, &json, || check_text(&check, repaired.as_ref()))?;
+
+    if problems > 0 && repaired.is_none() {
+        return Err(format!("store check found {} problem(s)", problems).into());
+    }
+
+    Ok(())
+}
+
+fn check_json(check: &StoreCheck) -> serde_json::Value {
+    serde_json::json!({
+        "version": check.version.0,
+        "ok": check.problem_count() == 0,
+        "unlock_error": check.unlock_error,
+        "entries": check.entries.iter().map(|e| serde_json::json!({
+            "index": e.index.0,
+            "type": e.entry_type.map(|t| format!("{:?}", t)),
+            "ok": e.error.is_none(),
+            "sealed": e.sealed,
+            "error": e.error,
+        })).collect::<Vec<_>>(),
+        "trailing_bytes": check.trailing_bytes,
+    })
+}
+
+fn repair_json(repaired: &RepairResult) -> serde_json::Value {
+    serde_json::json!({
+        "dropped": repaired.dropped.iter().map(|i| i.0).collect::<Vec<_>>(),
+        "trailing_bytes": repaired.trailing_bytes,
+        "quarantine": repaired
+            .quarantine
+            .as_ref()
+            .map(|q| q.display().to_string()),
+    })
+}
+
+fn check_text(check: &StoreCheck, repaired: Option<&RepairResult>) -> String {
+    let mut lines = vec![format!("store format: {}", check.version)];
+    lines.push(match &check.unlock_error {
+        None => "0\tUnlock\tok".to_string(),
+        Some(e) => format!("0\tUnlock\tERROR: {}", e),
+    });
+    for e in &check.entries {
+        let entry_type = e
+            .entry_type
+            .map(|t| format!("{:?}", t))
+            .unwrap_or_else(|| "Unknown".to_string());
+        lines.push(match &e.error {
+            None if e.sealed => {
+                format!("{}\t{}\tok (sealed)", e.index.0, entry_type)
+            }
+            None => format!("{}\t{}\tok", e.index.0, entry_type),
+            Some(err) => {
+                format!("{}\t{}\tERROR: {}", e.index.0, entry_type, err)
+            }
+        });
+    }
+    if check.trailing_bytes > 0 {
+        lines.push(format!(
+            "ERROR: {} trailing bytes after the last entry",
+            check.trailing_bytes
+        ));
+    }
+    if let Some(repaired) = repaired {
+        lines.push(format!(
+            "repaired: dropped {} entries and {} trailing bytes",
+            repaired.dropped.len(),
+            repaired.trailing_bytes
+        ));
+        if let Some(quarantine) = &repaired.quarantine {
+            lines.push(format!("quarantine: {}", quarantine.display()));
+        }
+    }
+    lines.join("\n")
+}
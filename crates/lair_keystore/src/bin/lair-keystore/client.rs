@@ -0,0 +1,132 @@
+//! keystore access for one-off subcommands
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Api access to the keystore, either through the ipc socket of a
+/// running lair server, or directly against the store file if
+/// no server is running.
+pub struct Keystore {
+    config: Arc<Config>,
+    api: ghost_actor::GhostSender<LairClientApi>,
+    is_local: bool,
+}
+
+impl std::ops::Deref for Keystore {
+    type Target = ghost_actor::GhostSender<LairClientApi>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.api
+    }
+}
+
+impl Keystore {
+    /// Connect to the running lair server, falling back to opening
+    /// the store file directly, prompting for its unlock passphrase
+    /// if its entries are sealed.
+    pub async fn connect(config: Arc<Config>) -> LairResult<Self> {
+        Self::connect_with_unlock(config, || {
+            let passphrase =
+                rpassword::read_password_from_tty(Some("unlock passphrase: "))
+                    .map_err(LairError::other)?;
+            Ok(passphrase.into_bytes())
+        })
+        .await
+    }
+
+    /// Like `connect`, but if the store file is opened directly,
+    /// `unlock_passphrase` supplies its unlock passphrase.
+    pub async fn connect_with_unlock<P>(
+        config: Arc<Config>,
+        unlock_passphrase: P,
+    ) -> LairResult<Self>
+    where
+        P: FnOnce() -> LairResult<Vec<u8>>,
+    {
+        match ipc::spawn_client_ipc(config.clone()).await {
+            Ok((api, evt_recv)) => {
+                tokio::task::spawn(decline_events(evt_recv));
+                Ok(Self {
+                    config,
+                    api,
+                    is_local: false,
+                })
+            }
+            Err(LairError::IpcClientConnectError(..)) => {
+                let api = lair_keystore::spawn_local_lair(
+                    config.clone(),
+                    unlock_passphrase,
+                )
+                .await?;
+                Ok(Self {
+                    config,
+                    api,
+                    is_local: true,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Connect to the running lair server only.
+    pub async fn connect_server(config: Arc<Config>) -> LairResult<Self> {
+        match ipc::spawn_client_ipc(config.clone()).await {
+            Ok((api, evt_recv)) => {
+                tokio::task::spawn(decline_events(evt_recv));
+                Ok(Self {
+                    config,
+                    api,
+                    is_local: false,
+                })
+            }
+            Err(LairError::IpcClientConnectError(_, e)) => {
+                Err(LairError::IpcClientConnectError(
+                    format!(
+                        "lair-keystore is not reachable at {}:",
+                        config.get_socket_path().display()
+                    ),
+                    e,
+                ))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Release the keystore.
+    /// If we opened the store file directly, let go of the pidfile.
+    pub async fn close(self) -> LairResult<()> {
+        use ghost_actor::GhostControlSender;
+        if self.is_local {
+            self.api.ghost_actor_shutdown().await?;
+            lair_keystore::internal::pid_check::pid_release(&self.config)?;
+        }
+        Ok(())
+    }
+}
+
+/// One-off commands are not passphrase providers.
+async fn decline_events(mut evt_recv: LairClientEventReceiver) {
+    while let Some(msg) = evt_recv.next().await {
+        match msg {
+            LairClientEvent::RequestUnlockPassphrase { respond, .. } => {
+                respond.respond(Ok(async move {
+                    Err("not a passphrase provider".into())
+                }
+                .boxed()
+                .into()));
+            }
+            LairClientEvent::RequestSigningApproval { respond, .. } => {
+                respond.respond(Ok(async move { Ok(false) }.boxed().into()));
+            }
+            LairClientEvent::Reconnected { respond, .. }
+            | LairClientEvent::EntryCreated { respond, .. }
+            | LairClientEvent::EntryDeleted { respond, .. }
+            | LairClientEvent::Locked { respond, .. }
+            | LairClientEvent::Unlocked { respond, .. } => {
+                respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+            }
+        }
+    }
+}
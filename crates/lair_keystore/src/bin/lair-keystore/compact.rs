@@ -0,0 +1,29 @@
+//! `lair-keystore compact` subcommand
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Rewrite the store without its deleted entries.
+pub async fn exec(config: Arc<Config>, output: OutputFormat) -> LairResult<()> {
+    let keystore = Keystore::connect(config).await?;
+    let res = keystore.lair_compact_store().await;
+    keystore.close().await?;
+    let info = res?;
+
+    output::print(
+        output,
+        &serde_json::json!({
+            "entries_removed": info.entries_removed,
+            "bytes_reclaimed": info.bytes_reclaimed,
+        }),
+        || {
+            format!(
+                "removed {} deleted entries, reclaiming {} bytes",
+                info.entries_removed, info.bytes_reclaimed
+            )
+        },
+    )
+}
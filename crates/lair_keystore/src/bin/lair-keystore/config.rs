@@ -0,0 +1,23 @@
+//! `lair-keystore config` subcommands
+
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::*;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum ConfigCmd {
+    /// Print a commented `lair-config.toml` template with all options
+    /// at their default values.
+    PrintDefault,
+}
+
+/// Execute a `config` subcommand.
+pub fn exec(cmd: &ConfigCmd, output: OutputFormat) -> LairResult<()> {
+    match cmd {
+        ConfigCmd::PrintDefault => output::print(
+            output,
+            &serde_json::json!({ "config_toml": DEFAULT_CONFIG_TOML }),
+            || DEFAULT_CONFIG_TOML.trim_end().to_string(),
+        ),
+    }
+}
@@ -0,0 +1,125 @@
+//! `--daemon`, `--pid-file` and `--log-file` support
+
+use lair_keystore_api::*;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Tracing writer appending to the `--log-file`.
+#[derive(Clone)]
+pub struct LogWriter(Arc<std::fs::File>);
+
+impl LogWriter {
+    /// Open (or create) the log file for appending.
+    pub fn open(path: &Path) -> LairResult<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(LairError::other)?;
+        Ok(Self(Arc::new(file)))
+    }
+
+    fn into_stdio(self) -> LairResult<std::process::Stdio> {
+        let file = self.0.try_clone().map_err(LairError::other)?;
+        Ok(file.into())
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        (&*self.0).flush()
+    }
+}
+
+/// Re-exec this binary (minus `--daemon`) as a detached background
+/// process. Relays the child's startup markers to our stdout, returning
+/// once it reports ready, so wrapper scripts can wait on the marker.
+/// We re-exec rather than fork, forking a running tokio runtime
+/// is not safe.
+pub fn spawn_daemon(piped: bool, log_file: Option<&Path>) -> LairResult<()> {
+    let exe = std::env::current_exe().map_err(LairError::other)?;
+    let args = std::env::args_os()
+        .skip(1)
+        .filter(|a| a != "--daemon")
+        .collect::<Vec<_>>();
+
+    let mut cmd = std::process::Command::new(exe);
+    cmd.args(args);
+    cmd.stdout(std::process::Stdio::piped());
+    // the child reads the --piped passphrase from our stdin
+    cmd.stdin(if piped {
+        std::process::Stdio::inherit()
+    } else {
+        std::process::Stdio::null()
+    });
+    cmd.stderr(match log_file {
+        Some(log_file) => LogWriter::open(log_file)?.into_stdio()?,
+        None => std::process::Stdio::null(),
+    });
+    #[cfg(unix)]
+    {
+        // don't receive signals aimed at our terminal's process group
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = cmd.spawn().map_err(LairError::other)?;
+
+    let stdout = child.stdout.take().expect("child stdout is piped");
+    let mut stdout = std::io::BufReader::new(stdout);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = stdout.read_line(&mut line).map_err(LairError::other)?;
+        if read == 0 {
+            let status = child.wait().map_err(LairError::other)?;
+            return Err(format!(
+                "lair-keystore daemon failed to start ({})",
+                status
+            )
+            .into());
+        }
+        print!("{}", line);
+        // the version marker is the last line printed at startup
+        if line.starts_with("#lair-keystore-version:") {
+            break;
+        }
+    }
+    std::io::stdout().flush().map_err(LairError::other)?;
+
+    Ok(())
+}
+
+/// Write our process id to the `--pid-file`.
+pub fn write_pid_file(path: &Path) -> LairResult<()> {
+    std::fs::write(path, format!("{}\n", std::process::id()))
+        .map_err(LairError::other)
+}
+
+/// Remove the `--pid-file`, logging any failure.
+pub fn remove_pid_file(path: &Path) {
+    if let Err(err) = std::fs::remove_file(path) {
+        tracing::warn!(?err, "failed to remove pid file");
+    }
+}
+
+/// Resolves on ctrl-c, or SIGTERM on unix.
+pub async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut term) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = term.recv() => (),
+                _ = tokio::signal::ctrl_c() => (),
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,23 @@
+//! `lair-keystore delete` subcommand
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Delete the entry at `index`, erasing its key material.
+pub async fn exec(
+    config: Arc<Config>,
+    index: u32,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let keystore = Keystore::connect(config).await?;
+    let res = keystore.lair_delete_entry(KeystoreIndex(index)).await;
+    keystore.close().await?;
+    res?;
+
+    output::print(output, &serde_json::json!({ "deleted": index }), || {
+        format!("entry {} deleted", index)
+    })
+}
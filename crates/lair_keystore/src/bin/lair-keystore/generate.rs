@@ -0,0 +1,133 @@
+//! `lair-keystore generate` subcommands
+
+use crate::client::Keystore;
+use crate::output::{self, EntryInfo, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub enum GenerateCmd {
+    /// Generate a new ed25519 signature keypair.
+    Ed25519 {
+        /// Tag the entry for lookup by name (must be unique).
+        #[structopt(long)]
+        tag: Option<String>,
+    },
+
+    /// Generate a new x25519 ECDH keypair.
+    X25519,
+
+    /// Generate a new self-signed tls certificate.
+    TlsCert {
+        /// Tag the entry for lookup by name (must be unique).
+        #[structopt(long)]
+        tag: Option<String>,
+
+        /// Cert digest algorithm ("sha256" digests are the fingerprints
+        /// other tools print).
+        #[structopt(
+            long,
+            default_value = "blake2b256",
+            possible_values = &["blake2b256", "sha256"],
+            parse(try_from_str = parse_digest_alg)
+        )]
+        digest_alg: TlsCertDigestAlg,
+
+        /// Keep the private key in the keystore: it can sign tls
+        /// handshakes, but not be fetched or exported.
+        /// The default if `exportable_by_default` is configured false.
+        #[structopt(long, conflicts_with = "exportable")]
+        not_exportable: bool,
+
+        /// Let the private key be fetched and exported, the default
+        /// unless `exportable_by_default` is configured false.
+        #[structopt(long)]
+        exportable: bool,
+    },
+}
+
+fn parse_digest_alg(s: &str) -> Result<TlsCertDigestAlg, String> {
+    match s {
+        "blake2b256" => Ok(TlsCertDigestAlg::Blake2b256),
+        "sha256" => Ok(TlsCertDigestAlg::Sha256),
+        _ => Err(format!("invalid digest alg: {}", s)),
+    }
+}
+
+/// Execute a `generate` subcommand, printing the new index
+/// and public material.
+pub async fn exec(
+    config: Arc<Config>,
+    cmd: &GenerateCmd,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let keystore = Keystore::connect_server(config).await?;
+    let res = generate(&keystore, cmd).await;
+    keystore.close().await?;
+    let item = res?;
+    output::print(output, &item, || {
+        format!("{}\t{}", item.index, item.material())
+    })
+}
+
+async fn generate(
+    keystore: &Keystore,
+    cmd: &GenerateCmd,
+) -> LairResult<EntryInfo> {
+    Ok(match cmd {
+        GenerateCmd::Ed25519 { tag } => {
+            let (index, pub_key) = match tag {
+                Some(tag) => {
+                    keystore
+                        .sign_ed25519_new_from_entropy_tagged(tag.clone())
+                        .await?
+                }
+                None => keystore.sign_ed25519_new_from_entropy().await?,
+            };
+            let mut item = EntryInfo::new(index, "SignEd25519");
+            item.pub_key_b64 = Some(base64::encode(&**pub_key));
+            item.tag = tag.clone();
+            item
+        }
+        GenerateCmd::X25519 => {
+            let (index, pub_key) = keystore.x25519_new_from_entropy().await?;
+            let mut item = EntryInfo::new(index, "X25519");
+            item.pub_key_b64 = Some(base64::encode(pub_key.to_bytes()));
+            item
+        }
+        GenerateCmd::TlsCert {
+            tag,
+            digest_alg,
+            not_exportable,
+            exportable,
+        } => {
+            let mut options = TlsCertOptions::default();
+            options.digest_alg = *digest_alg;
+            // left to the server's `exportable_by_default` otherwise
+            if *not_exportable {
+                options.exportable = Some(false);
+            } else if *exportable {
+                options.exportable = Some(true);
+            }
+            let (index, sni, digest) = match tag {
+                Some(tag) => {
+                    keystore
+                        .tls_cert_new_self_signed_tagged(options, tag.clone())
+                        .await?
+                }
+                None => {
+                    keystore
+                        .tls_cert_new_self_signed_from_entropy(options)
+                        .await?
+                }
+            };
+            let mut item = EntryInfo::new(index, "TlsCert");
+            item.sni = Some(sni.0.to_string());
+            item.set_cert_digest(&digest, *digest_alg);
+            item.tag = tag.clone();
+            item
+        }
+    })
+}
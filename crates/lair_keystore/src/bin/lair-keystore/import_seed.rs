@@ -0,0 +1,93 @@
+//! `lair-keystore import-seed` subcommand
+
+use crate::output::{self, EntryInfo, OutputFormat};
+use lair_keystore::{internal::pid_check, store, store::EntryStoreSender};
+use lair_keystore_api::entry::LairEntry;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Import an ed25519 seed read from `file` or stdin into the store.
+pub async fn exec(
+    config: Arc<Config>,
+    file: Option<std::path::PathBuf>,
+    allow_duplicate: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let encoded = crate::read_input(file.as_deref())?;
+    let seed = decode_seed(&encoded)?;
+
+    let pid_check::PidCheckResult { store_file } =
+        match pid_check::pid_check(&config) {
+            Err(LairError::ProcessAlreadyExists(_)) => {
+                return Err(
+                    "stop the running lair-keystore before importing seeds"
+                        .into(),
+                )
+            }
+            oth => oth?,
+        };
+
+    let res = import(config.clone(), store_file, seed, allow_duplicate).await;
+
+    pid_check::pid_release(&config)?;
+
+    let (index, pub_key) = res?;
+    let mut item = EntryInfo::new(index, "SignEd25519");
+    item.pub_key_b64 = Some(base64::encode(&**pub_key));
+
+    output::print(output, &item, || {
+        format!("{}\t{}", item.index, item.material())
+    })
+}
+
+async fn import(
+    config: Arc<Config>,
+    store_file: tokio::fs::File,
+    seed: Vec<u8>,
+    allow_duplicate: bool,
+) -> LairResult<(
+    actor::KeystoreIndex,
+    internal::sign_ed25519::SignEd25519PubKey,
+)> {
+    let store = store::spawn_entry_store_actor(config, store_file).await?;
+
+    let res = async {
+        if store.lock_state().await? == store::StoreLockState::Locked {
+            let passphrase =
+                rpassword::read_password_from_tty(Some("unlock passphrase: "))
+                    .map_err(LairError::other)?;
+            store.unlock(passphrase.into_bytes().into()).await?;
+        }
+        store
+            .sign_ed25519_keypair_new_from_seed(seed, allow_duplicate)
+            .await
+    }
+    .await;
+
+    use ghost_actor::GhostControlSender;
+    store.ghost_actor_shutdown().await?;
+
+    let (index, entry) = res?;
+    match &*entry {
+        LairEntry::SignEd25519(entry) => Ok((index, entry.pub_key.clone())),
+        _ => Err("invalid entry type".into()),
+    }
+}
+
+/// Decode a 32 byte seed given as either hex or base64 text.
+fn decode_seed(encoded: &[u8]) -> LairResult<Vec<u8>> {
+    let encoded = String::from_utf8_lossy(encoded);
+    let encoded = encoded.trim();
+
+    let seed = match hex::decode(encoded) {
+        Ok(seed) => seed,
+        Err(_) => base64::decode(encoded)
+            .map_err(|_| "seed must be hex or base64 encoded")?,
+    };
+
+    if seed.len() != 32 {
+        return Err(format!("seed must be 32 bytes, got {}", seed.len()).into());
+    }
+
+    Ok(seed)
+}
@@ -0,0 +1,61 @@
+//! `lair-keystore init` subcommand
+
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Prompt for a new passphrase (with confirmation) and initialize the store.
+pub async fn exec(
+    config: Arc<Config>,
+    force: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    // fail early, before bothering the user for a passphrase
+    // (init_lair will check again once it has exclusive store access)
+    let store_exists = std::fs::metadata(config.get_store_path())
+        .map(|m| m.len() > 0)
+        .unwrap_or(false);
+    if store_exists && !force {
+        return Err(LairError::StoreAlreadyInitialized);
+    }
+
+    let passphrase = read_new_passphrase("passphrase")?;
+
+    lair_keystore::init_lair(config.clone(), passphrase, force).await?;
+
+    let store = config.get_store_path().display();
+    let socket = config.get_socket_path().display();
+    output::print(
+        output,
+        &serde_json::json!({
+            "store": store.to_string(),
+            "socket": socket.to_string(),
+        }),
+        || {
+            format!(
+                "lair-keystore initialized\nstore: {}\nsocket: {}",
+                store, socket
+            )
+        },
+    )
+}
+
+/// Read a new passphrase from the tty, asking for it twice.
+/// `what` names the passphrase in the prompts.
+pub fn read_new_passphrase(what: &str) -> LairResult<Vec<u8>> {
+    let passphrase =
+        rpassword::read_password_from_tty(Some(&format!("{}: ", what)))
+            .map_err(LairError::other)?;
+    if passphrase.is_empty() {
+        return Err(format!("{} cannot be empty", what).into());
+    }
+
+    let confirm =
+        rpassword::read_password_from_tty(Some(&format!("confirm {}: ", what)))
+            .map_err(LairError::other)?;
+    if passphrase != confirm {
+        return Err("passphrases do not match".into());
+    }
+
+    Ok(passphrase.into_bytes())
+}
@@ -0,0 +1,68 @@
+//! `lair-keystore list` subcommand
+
+use crate::client::Keystore;
+use crate::output::{self, EntryInfo, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Print out all entries in the keystore, one per line
+/// (or as a single json array).
+pub async fn exec(config: Arc<Config>, output: OutputFormat) -> LairResult<()> {
+    let keystore = Keystore::connect(config).await?;
+
+    let res = list(&keystore).await;
+
+    keystore.close().await?;
+
+    let items = res?;
+
+    output::print(output, &items, || {
+        items
+            .iter()
+            .map(|item| {
+                format!(
+                    "{}\t{}\t{}",
+                    item.index,
+                    item.entry_type,
+                    item.material()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+async fn list(keystore: &Keystore) -> LairResult<Vec<EntryInfo>> {
+    let entries = keystore.lair_list_entries().await?;
+
+    let mut out = Vec::new();
+
+    for entry in entries {
+        let mut item = EntryInfo::new(
+            entry.keystore_index,
+            &format!("{:?}", entry.entry_type),
+        );
+        item.tag = entry.tag;
+        item.created_at = entry.created_at;
+
+        match entry.entry_type {
+            LairEntryType::TlsCert => {
+                item.sni = entry.cert_sni.map(|sni| sni.to_string());
+                item.set_cert_digest(
+                    &entry.pub_id,
+                    entry.cert_digest_alg.unwrap_or_default(),
+                );
+            }
+            LairEntryType::SignEd25519 | LairEntryType::X25519 => {
+                item.pub_key_b64 = Some(base64::encode(&*entry.pub_id));
+            }
+            // skip anything this cli doesn't know how to display
+            _ => continue,
+        }
+
+        out.push(item);
+    }
+
+    Ok(out)
+}
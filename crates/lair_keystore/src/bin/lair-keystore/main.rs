@@ -2,9 +2,67 @@
 #![deny(missing_docs)]
 //! main entry point
 
+use lair_keystore_api::*;
+use std::sync::Arc;
 use structopt::StructOpt;
 use tracing::*;
 
+mod audit;
+mod backup;
+mod cert;
+mod change_passphrase;
+mod check;
+mod client;
+mod compact;
+mod config;
+mod daemon;
+mod delete;
+mod generate;
+mod import_seed;
+mod init;
+mod list;
+mod migrate;
+mod output;
+mod paths;
+mod shutdown;
+mod sign;
+mod status;
+
+use output::OutputFormat;
+
+/// Read all input bytes from given file, or stdin if `None`.
+fn read_input(file: Option<&std::path::Path>) -> LairResult<Vec<u8>> {
+    match file {
+        Some(file) => std::fs::read(file).map_err(LairError::other),
+        None => {
+            use std::io::Read;
+            let mut buf = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut buf)
+                .map_err(LairError::other)?;
+            Ok(buf)
+        }
+    }
+}
+
+/// Read a single line passphrase from stdin, stripping the
+/// trailing newline. The returned buffer is zeroized on drop.
+fn read_piped_passphrase() -> LairResult<zeroize::Zeroizing<Vec<u8>>> {
+    use std::io::BufRead;
+    let mut buf = zeroize::Zeroizing::new(Vec::with_capacity(512));
+    std::io::stdin()
+        .lock()
+        .read_until(b'\n', &mut buf)
+        .map_err(LairError::other)?;
+    while let Some(b'\n') | Some(b'\r') = buf.last() {
+        buf.pop();
+    }
+    if buf.is_empty() {
+        return Err("no passphrase supplied on stdin".into());
+    }
+    Ok(buf)
+}
+
 static LAIR_KEYSTORE_ABOUT: &str = r#"A secure storage system for Holochain cryptographic keys and secrets.
 
 - one `lair-keystore` per `holochain`
@@ -34,18 +92,263 @@ directory in order to run multiple
 instances or for other purposes"
     )]
     lair_dir: Option<std::path::PathBuf>,
+
+    /// Read config from this file instead of
+    /// `lair-config.toml` in the lair directory.
+    #[structopt(long, env = "LAIR_CONFIG", parse(from_os_str))]
+    config: Option<std::path::PathBuf>,
+
+    /// Use this named profile, with its own store, passphrase and socket
+    /// ("store-<profile>", "socket-<profile>") in the lair directory.
+    /// The server may be given several, serving them all (with --piped,
+    /// reading one passphrase line per profile, in order).
+    #[structopt(
+        long = "profile",
+        env = "LAIR_PROFILE",
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    profiles: Vec<String>,
+
+    /// Serve the store without writing to it, e.g. for inspection or
+    /// as a hot standby: creating, importing or deleting entries fails,
+    /// usage is not recorded, and no pid file is taken. Listens on
+    /// "socket-read-only" unless a socket path is configured.
+    #[structopt(long)]
+    read_only: bool,
+
+    /// Read the unlock passphrase from stdin at startup
+    /// instead of requesting it from connecting clients.
+    /// For `backup` / `restore`, read the backup passphrase from stdin.
+    /// For `change-passphrase`, read the current then the new passphrase.
+    #[structopt(long)]
+    piped: bool,
+
+    /// Run the keystore server as a detached background process.
+    /// Returns once the server is ready.
+    #[structopt(long)]
+    daemon: bool,
+
+    /// Write the server process id to this file,
+    /// removing it again on clean shutdown.
+    #[structopt(long, parse(from_os_str))]
+    pid_file: Option<std::path::PathBuf>,
+
+    /// Append server logs to this file instead of stdout.
+    #[structopt(long, parse(from_os_str))]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Print subcommand results (and errors, on stderr)
+    /// as a single json document.
+    #[structopt(long)]
+    json: bool,
+
+    /// Run a one-off command instead of the keystore server.
+    #[structopt(subcommand)]
+    cmd: Option<Cmd>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Cmd {
+    /// Initialize a new lair store, prompting for an unlock passphrase.
+    /// The ipc socket is not started.
+    Init {
+        /// Overwrite an existing store. All existing entries will be lost!
+        #[structopt(long)]
+        force: bool,
+    },
+
+    /// Upgrade the store to the current on-disk format, in place.
+    /// A backup copy of the original store is written first.
+    /// With `--to`, the store is then copied into a new store of that
+    /// kind, the original is left in place.
+    /// The lair server must not be running.
+    Migrate {
+        /// Convert the store to this kind of store.
+        #[structopt(long, possible_values = &["file", "sqlite"])]
+        to: Option<StoreKind>,
+
+        /// Where to write the converted store, relative to the lair dir.
+        /// Defaults to the default store path for its kind.
+        #[structopt(long, parse(from_os_str), requires = "to")]
+        to_path: Option<std::path::PathBuf>,
+    },
+
+    /// Rotate the store unlock passphrase, prompting for the current
+    /// and the new passphrase.
+    /// Goes through the running server, or opens the store directly.
+    ChangePassphrase,
+
+    /// Check every entry in the store decodes and is consistent,
+    /// exiting non-zero if any problems are found.
+    /// Reads the store file without modifying it, unless `--repair`.
+    Check {
+        /// Move invalid entries (and any partially written entry) into a
+        /// quarantine file next to the store. Later entries move down
+        /// to fill the gaps. The lair server must not be running.
+        #[structopt(long)]
+        repair: bool,
+    },
+
+    /// Write all entries to a single file,
+    /// encrypted with a backup passphrase.
+    /// The lair server must not be running.
+    Backup {
+        /// Write the backup to this file (must not exist).
+        #[structopt(long, parse(from_os_str))]
+        out: std::path::PathBuf,
+    },
+
+    /// Restore the entries from a `backup` file.
+    /// The lair server must not be running.
+    Restore {
+        /// Read the backup from this file.
+        #[structopt(long = "in", parse(from_os_str))]
+        input: std::path::PathBuf,
+
+        /// Add the backup entries to a non-empty store, skipping
+        /// those whose pub key is already present. The store keeps
+        /// its own unlock passphrase.
+        #[structopt(long)]
+        merge: bool,
+    },
+
+    /// List the entries in the keystore, one per line.
+    /// Connects to the running server, or opens the store directly.
+    List {
+        /// Output format ("text" or "json"), same as the global --json.
+        #[structopt(
+            long,
+            default_value = "text",
+            possible_values = &["text", "json"]
+        )]
+        output: OutputFormat,
+    },
+
+    /// Delete the entry at this index, erasing its key material.
+    /// The index is not reused. This cannot be undone.
+    /// Connects to the running server, or opens the store directly.
+    Delete {
+        /// Keystore index of the entry, as shown by `list`.
+        index: u32,
+    },
+
+    /// Rewrite the store without its deleted entries, reclaiming their
+    /// space. Every other entry keeps its index.
+    /// Connects to the running server, or opens the store directly.
+    Compact,
+
+    /// Import a 32 byte ed25519 seed (hex or base64 encoded) as a new
+    /// signature keypair. The lair server must not be running.
+    ImportSeed {
+        /// Read the seed from this file instead of stdin.
+        #[structopt(long, parse(from_os_str))]
+        file: Option<std::path::PathBuf>,
+
+        /// Import even if an entry with the same pub key already exists.
+        #[structopt(long)]
+        allow_duplicate: bool,
+    },
+
+    /// Tls certificate commands.
+    Cert(cert::CertCmd),
+
+    /// Generate a new entry in the running lair server.
+    Generate(generate::GenerateCmd),
+
+    /// Sign a message (stdin or file) with an ed25519 key
+    /// held by the running lair server.
+    Sign {
+        /// Base64 encoded ed25519 pub key of the signing keypair.
+        #[structopt(long)]
+        pub_key: String,
+
+        /// Read the message from this file instead of stdin.
+        #[structopt(long, parse(from_os_str))]
+        file: Option<std::path::PathBuf>,
+
+        /// Write the raw 64 signature bytes instead of base64.
+        #[structopt(long)]
+        raw: bool,
+    },
+
+    /// Verify an ed25519 signature on a message (stdin or file).
+    /// Does not require a running lair server.
+    Verify {
+        /// Base64 encoded ed25519 pub key of the signer.
+        #[structopt(long)]
+        pub_key: String,
+
+        /// Base64 encoded signature.
+        #[structopt(long)]
+        signature: String,
+
+        /// Read the message from this file instead of stdin.
+        #[structopt(long, parse(from_os_str))]
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Probe the running lair server, exiting non-zero if it is
+    /// not reachable or does not respond in time.
+    Status {
+        /// How long to wait for the server to respond.
+        #[structopt(long, default_value = "5000")]
+        timeout_ms: u64,
+    },
+
+    /// Ask the running lair server to shut down gracefully.
+    Shutdown,
+
+    /// Print the lair dir, config file, store, socket and pid file
+    /// paths exactly as the server would resolve them.
+    Paths,
+
+    /// Configuration file helpers.
+    Config(config::ConfigCmd),
+
+    /// Read and verify the audit log of secret key operations.
+    Audit(audit::AuditCmd),
+}
+
+impl Opt {
+    /// Build the lair config specified by these options,
+    /// layered over the config file, if any.
+    /// Subcommands act on a single profile, the server on the first
+    /// of any others, see `profile_config()`.
+    fn config(&self) -> LairResult<Arc<Config>> {
+        if self.cmd.is_some() && self.profiles.len() > 1 {
+            return Err("only the server takes more than one --profile".into());
+        }
+        self.profile_config(self.profiles.first().map(String::as_str))
+    }
+
+    /// Build the lair config for `profile`, see `config()`.
+    fn profile_config(&self, profile: Option<&str>) -> LairResult<Arc<Config>> {
+        let mut config = lair_keystore::load_config_builder(
+            self.config.as_deref(),
+            self.lair_dir.as_deref(),
+            profile,
+        )?;
+        // the flag adds to, rather than overrides, the config file
+        if self.read_only {
+            config = config.set_read_only(true);
+        }
+        Ok(config.build())
+    }
+
+    /// Output format for subcommand results.
+    fn output(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Text
+        }
+    }
 }
 
 /// main entry point
 #[tokio::main(flavor = "multi_thread")]
 pub async fn main() -> lair_keystore_api::LairResult<()> {
-    let _ = subscriber::set_global_default(
-        tracing_subscriber::FmtSubscriber::builder()
-            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-            .finish(),
-    );
-    trace!("tracing initialized");
-
     let opt = Opt::from_args();
 
     if opt.version {
@@ -53,12 +356,152 @@ pub async fn main() -> lair_keystore_api::LairResult<()> {
         return Ok(());
     }
 
-    if let Some(lair_dir) = opt.lair_dir {
-        std::env::set_var("LAIR_DIR", lair_dir);
+    // don't let a broken config file get in the way of these
+    if let Some(Cmd::Config(cmd)) = &opt.cmd {
+        if let Err(e) = config::exec(cmd, opt.output()) {
+            output::print_error(opt.output(), &e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // we don't know the configured log level yet,
+    // but still want to see config file warnings (e.g. unknown keys)
+    let config_subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_writer(std::io::stderr)
+        .with_max_level(Level::WARN)
+        .finish();
+    let config = subscriber::with_default(config_subscriber, || opt.config());
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            output::print_error(opt.output(), &e);
+            std::process::exit(1);
+        }
+    };
+
+    // RUST_LOG takes precedence over the config file log_level
+    let filter = match (std::env::var_os("RUST_LOG"), config.get_log_level()) {
+        (None, Some(log_level)) => {
+            tracing_subscriber::EnvFilter::new(log_level)
+        }
+        _ => tracing_subscriber::EnvFilter::from_default_env(),
+    };
+    let subscriber =
+        tracing_subscriber::FmtSubscriber::builder().with_env_filter(filter);
+    let _ = match &opt.log_file {
+        Some(log_file) if opt.cmd.is_none() => {
+            let writer = daemon::LogWriter::open(log_file)?;
+            subscriber::set_global_default(
+                subscriber
+                    .with_ansi(false)
+                    .with_writer(move || writer.clone())
+                    .finish(),
+            )
+        }
+        _ => subscriber::set_global_default(subscriber.finish()),
+    };
+    trace!("tracing initialized");
+
+    if let Some(cmd) = &opt.cmd {
+        let output = opt.output();
+        let res = match cmd {
+            Cmd::Init { force } => init::exec(config, *force, output).await,
+            Cmd::Migrate { to, to_path } => {
+                migrate::exec(config, *to, to_path.clone(), output).await
+            }
+            Cmd::ChangePassphrase => {
+                change_passphrase::exec(config, opt.piped, output).await
+            }
+            Cmd::Check { repair } => check::exec(config, *repair, output).await,
+            Cmd::Backup { out } => {
+                backup::exec_backup(config, out, opt.piped, output).await
+            }
+            Cmd::Restore { input, merge } => {
+                backup::exec_restore(config, input, *merge, opt.piped, output)
+                    .await
+            }
+            Cmd::List {
+                output: list_output,
+            } => {
+                let output = if opt.json { output } else { *list_output };
+                list::exec(config, output).await
+            }
+            Cmd::Delete { index } => delete::exec(config, *index, output).await,
+            Cmd::Compact => compact::exec(config, output).await,
+            Cmd::ImportSeed {
+                file,
+                allow_duplicate,
+            } => {
+                import_seed::exec(
+                    config,
+                    file.clone(),
+                    *allow_duplicate,
+                    output,
+                )
+                .await
+            }
+            Cmd::Cert(cmd) => cert::exec(config, cmd, output).await,
+            Cmd::Generate(cmd) => generate::exec(config, cmd, output).await,
+            Cmd::Sign { pub_key, file, raw } => {
+                sign::exec_sign(config, pub_key, file.as_deref(), *raw, output)
+                    .await
+            }
+            Cmd::Verify {
+                pub_key,
+                signature,
+                file,
+            } => {
+                sign::exec_verify(pub_key, signature, file.as_deref(), output)
+                    .await
+            }
+            Cmd::Status { timeout_ms } => {
+                status::exec(config, *timeout_ms, output).await
+            }
+            Cmd::Shutdown => shutdown::exec(config, output).await,
+            Cmd::Paths => {
+                let config_file = lair_keystore::find_config_file(
+                    opt.config.as_deref(),
+                    opt.lair_dir.as_deref(),
+                );
+                paths::exec(config, config_file.as_deref(), output)
+            }
+            Cmd::Audit(cmd) => audit::exec(config, cmd, output),
+            Cmd::Config(_) => unreachable!("handled above"),
+        };
+        if let Err(e) = res {
+            output::print_error(output, &e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if opt.daemon {
+        return daemon::spawn_daemon(opt.piped, opt.log_file.as_deref());
     }
 
     trace!("executing lair main tasks");
-    lair_keystore::execute_lair().await?;
+    let mut profiles = vec![config];
+    for profile in opt.profiles.iter().skip(1) {
+        profiles.push(opt.profile_config(Some(profile))?);
+    }
+    let profiles = profiles
+        .into_iter()
+        .map(|config| {
+            if !opt.piped {
+                return Ok((config, None));
+            }
+            let mut passphrase = read_piped_passphrase()?;
+            // move the allocation out, the store zeroizes it once verified
+            let passphrase = std::mem::take(&mut *passphrase);
+            Ok((config, Some(passphrase)))
+        })
+        .collect::<LairResult<Vec<_>>>()?;
+    let shutdown = lair_keystore::execute_lair_profiles(profiles, None).await?;
+
+    if let Some(pid_file) = &opt.pid_file {
+        daemon::write_pid_file(pid_file)?;
+    }
 
     info!("lair-keystore up and running");
 
@@ -66,8 +509,24 @@ pub async fn main() -> lair_keystore_api::LairResult<()> {
     println!("#lair-keystore-ready#");
     println!("#lair-keystore-version:{}#", lair_keystore::LAIR_VER);
 
-    // wait forever... i.e. until a ctrl-c
-    futures::future::pending::<()>().await;
+    // wait until a client asks us to shut down (or a ctrl-c / SIGTERM)
+    tokio::select! {
+        res = shutdown => {
+            if res.is_err() {
+                daemon::termination_signal().await;
+            }
+        }
+        _ = daemon::termination_signal() => (),
+    }
+
+    #[cfg(unix)]
+    let _ = lair_keystore::internal::sd_notify::notify("STOPPING=1");
+
+    if let Some(pid_file) = &opt.pid_file {
+        daemon::remove_pid_file(pid_file);
+    }
+
+    info!("lair-keystore shut down");
 
     Ok(())
 }
@@ -0,0 +1,72 @@
+//! `lair-keystore migrate` subcommand
+
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Upgrade the store file to the current format,
+/// then convert it to the `to` kind of store, if given.
+pub async fn exec(
+    config: Arc<Config>,
+    to: Option<StoreKind>,
+    to_path: Option<std::path::PathBuf>,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let to = match to {
+        Some(kind) if kind == config.get_store_kind() && to_path.is_none() => {
+            return Err(format!("the store is already a {} store", kind).into())
+        }
+        Some(kind) => {
+            let mut to = Config::builder()
+                .set_root_path(config.get_root_path())
+                .set_store_kind(kind);
+            if let Some(to_path) = to_path {
+                to = to.set_store_path(to_path);
+            }
+            Some(to.build())
+        }
+        None => None,
+    };
+
+    let res = lair_keystore::migrate_lair(config.clone()).await?;
+    let converted = match to {
+        Some(to) => Some(lair_keystore::convert_lair(config, to).await?),
+        None => None,
+    };
+
+    let backup = res.backup.as_ref().map(|b| b.display().to_string());
+    output::print(
+        output,
+        &serde_json::json!({
+            "from": res.from.0,
+            "to": res.to.0,
+            "backup": backup,
+            "converted": converted.as_ref().map(|c| serde_json::json!({
+                "kind": c.to.to_string(),
+                "path": c.path.display().to_string(),
+                "entries": c.entries,
+            })),
+        }),
+        || {
+            let mut out = match &backup {
+                Some(backup) => format!(
+                    "migrated store from {} to {}\nbackup: {}",
+                    res.from, res.to, backup
+                ),
+                None => format!("store is already at {}", res.to),
+            };
+            if let Some(c) = &converted {
+                out.push_str(&format!(
+                    "\ncopied {} entries into a {} store: {}\n\
+                    set `kind = \"{}\"` and `path` under [store] in \
+                    lair-config.toml to use it",
+                    c.entries,
+                    c.to,
+                    c.path.display(),
+                    c.to,
+                ));
+            }
+            out
+        },
+    )
+}
@@ -0,0 +1,138 @@
+//! text / json output for one-off subcommands
+
+use lair_keystore_api::*;
+
+/// Output format for subcommands that print structured data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("invalid output format: {}", s)),
+        }
+    }
+}
+
+/// Public information about a single keystore entry.
+/// These field names are stable, scripts rely on them.
+#[derive(Debug, serde::Serialize)]
+pub struct EntryInfo {
+    pub index: u32,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pub_key_b64: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_digest_b64: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_digest_alg: Option<String>,
+    /// The cert digest as colon separated hex, as openssl prints
+    /// fingerprints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_fingerprint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Unix epoch milliseconds, json output only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl EntryInfo {
+    /// Info with only the index and type filled in.
+    pub fn new(index: actor::KeystoreIndex, entry_type: &str) -> Self {
+        Self {
+            index: index.0,
+            entry_type: entry_type.to_string(),
+            pub_key_b64: None,
+            sni: None,
+            cert_digest_b64: None,
+            cert_digest_alg: None,
+            cert_fingerprint: None,
+            tag: None,
+            created_at: None,
+        }
+    }
+
+    /// Fill in the digest fields of a tls cert entry.
+    pub fn set_cert_digest(
+        &mut self,
+        digest: &[u8],
+        digest_alg: actor::TlsCertDigestAlg,
+    ) {
+        self.cert_digest_b64 = Some(base64::encode(digest));
+        self.cert_digest_alg = serde_json::to_value(digest_alg)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()));
+        self.cert_fingerprint = Some(
+            digest
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<_>>()
+                .join(":"),
+        );
+    }
+
+    /// Tab separated public material and tag, without index or type.
+    pub fn material(&self) -> String {
+        [
+            &self.pub_key_b64,
+            &self.sni,
+            &self.cert_digest_b64,
+            &self.cert_digest_alg,
+            &self.cert_fingerprint,
+            &self.tag,
+        ]
+        .iter()
+        .copied()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\t")
+    }
+}
+
+/// Print `value` as a single json document on stdout,
+/// or the human readable `text` (built lazily) in text mode.
+pub fn print<T: serde::Serialize>(
+    format: OutputFormat,
+    value: &T,
+    text: impl FnOnce() -> String,
+) -> LairResult<()> {
+    match format {
+        OutputFormat::Text => {
+            let text = text();
+            if !text.is_empty() {
+                println!("{}", text);
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(value).map_err(LairError::other)?
+        ),
+    }
+    Ok(())
+}
+
+/// Print a subcommand error on stderr.
+pub fn print_error(format: OutputFormat, err: &LairError) {
+    match format {
+        OutputFormat::Text => eprintln!("Error: {}", err),
+        OutputFormat::Json => eprintln!(
+            "{}",
+            serde_json::json!({
+                "code": err.code(),
+                "error": err.to_string(),
+            })
+        ),
+    }
+}
@@ -0,0 +1,54 @@
+//! `lair-keystore paths` subcommand
+
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::*;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Print the paths resolved from the current config.
+pub fn exec(
+    config: Arc<Config>,
+    config_file: Option<&Path>,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let lair_dir = config.get_root_path().display();
+    let profile = config.get_profile();
+    let config_file = config_file.map(|p| p.display().to_string());
+    let store = config.get_store_path().display();
+    let store_kind = config.get_store_kind();
+    let socket = config.get_socket_path().display();
+    let tcp = config.get_tcp_bind().map(|addr| addr.to_string());
+    let pid = config.get_pid_path().display();
+    let token = config.get_token_path().display();
+    let audit_log = config.get_audit_log_path().display();
+    output::print(
+        output,
+        &serde_json::json!({
+            "lair_dir": lair_dir.to_string(),
+            "config_file": config_file,
+            "profile": profile,
+            "store": store.to_string(),
+            "store_kind": store_kind.to_string(),
+            "socket": socket.to_string(),
+            "tcp": tcp,
+            "pid": pid.to_string(),
+            "token": token.to_string(),
+            "audit_log": audit_log.to_string(),
+        }),
+        || {
+            format!(
+                "lair_dir: {}\nconfig_file: {}\nprofile: {}\nstore: {} ({})\nsocket: {}\ntcp: {}\npid: {}\ntoken: {}\naudit_log: {}",
+                lair_dir,
+                config_file.as_deref().unwrap_or("(none)"),
+                profile.unwrap_or("(none)"),
+                store,
+                store_kind,
+                socket,
+                tcp.as_deref().unwrap_or("(none)"),
+                pid,
+                token,
+                audit_log
+            )
+        },
+    )
+}
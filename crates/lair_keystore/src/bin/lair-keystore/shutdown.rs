@@ -0,0 +1,39 @@
+//! `lair-keystore shutdown` subcommand
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// How long to wait for the server to release its pidfile.
+const EXIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Request a graceful shutdown of the running server,
+/// waiting until it has released the store.
+pub async fn exec(config: Arc<Config>, output: OutputFormat) -> LairResult<()> {
+    let keystore = Keystore::connect_server(config.clone()).await?;
+    let res = keystore.lair_shutdown().await;
+    keystore.close().await?;
+    res?;
+
+    // the server acks before it exits, wait for the pidfile to go away
+    // so callers can safely start a new server right after we return
+    let pid_path = config.get_pid_path().to_owned();
+    let wait = async {
+        while pid_path.exists() {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    };
+    if tokio::time::timeout(EXIT_TIMEOUT, wait).await.is_err() {
+        return Err(format!(
+            "lair-keystore did not exit within {}s",
+            EXIT_TIMEOUT.as_secs()
+        )
+        .into());
+    }
+
+    output::print(output, &serde_json::json!({ "shut_down": true }), || {
+        "lair-keystore shut down".to_string()
+    })
+}
@@ -0,0 +1,82 @@
+//! `lair-keystore sign` and `lair-keystore verify` subcommands
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::internal::sign_ed25519::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Sign the input message with the keypair identified by `pub_key`.
+pub async fn exec_sign(
+    config: Arc<Config>,
+    pub_key: &str,
+    file: Option<&std::path::Path>,
+    raw: bool,
+    output: OutputFormat,
+) -> LairResult<()> {
+    if raw && output == OutputFormat::Json {
+        return Err("--raw cannot be combined with --json".into());
+    }
+
+    let pub_key = decode_b64("pub key", pub_key, 32)?;
+    let message = crate::read_input(file)?;
+
+    let keystore = Keystore::connect_server(config).await?;
+    let res = keystore
+        .sign_ed25519_sign_by_pub_key(pub_key.into(), message.into())
+        .await;
+    keystore.close().await?;
+    let signature = res?;
+
+    if raw {
+        use std::io::Write;
+        std::io::stdout()
+            .write_all(&signature)
+            .map_err(LairError::other)
+    } else {
+        let signature = base64::encode(&**signature);
+        output::print(
+            output,
+            &serde_json::json!({ "signature_b64": signature }),
+            || signature.clone(),
+        )
+    }
+}
+
+/// Verify the signature on the input message, exiting with
+/// an error if it is invalid.
+pub async fn exec_verify(
+    pub_key: &str,
+    signature: &str,
+    file: Option<&std::path::Path>,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let pub_key: SignEd25519PubKey = decode_b64("pub key", pub_key, 32)?.into();
+    let signature = decode_b64("signature", signature, 64)?;
+    let message = crate::read_input(file)?;
+
+    if pub_key.verify(message.into(), signature.into()).await? {
+        output::print(output, &serde_json::json!({ "valid": true }), || {
+            "valid".to_string()
+        })
+    } else {
+        Err("invalid signature".into())
+    }
+}
+
+/// Decode a base64 string, checking the decoded length.
+fn decode_b64(name: &str, data: &str, len: usize) -> LairResult<Vec<u8>> {
+    let data = base64::decode(data.trim())
+        .map_err(|_| format!("{} must be base64 encoded", name))?;
+    if data.len() != len {
+        return Err(format!(
+            "{} must be {} bytes, got {}",
+            name,
+            len,
+            data.len()
+        )
+        .into());
+    }
+    Ok(data)
+}
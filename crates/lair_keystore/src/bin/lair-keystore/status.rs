@@ -0,0 +1,87 @@
+//! `lair-keystore status` subcommand
+
+use crate::client::Keystore;
+use crate::output::{self, OutputFormat};
+use lair_keystore_api::actor::*;
+use lair_keystore_api::*;
+use std::sync::Arc;
+
+/// Probe the running server and print its status.
+pub async fn exec(
+    config: Arc<Config>,
+    timeout_ms: u64,
+    output: OutputFormat,
+) -> LairResult<()> {
+    let socket_path = config.get_socket_path().to_owned();
+
+    let (info, last_index) = match tokio::time::timeout(
+        std::time::Duration::from_millis(timeout_ms),
+        probe(config),
+    )
+    .await
+    {
+        Ok(res) => res?,
+        Err(_) => {
+            return Err(format!(
+                "lair-keystore at {} did not respond within {}ms",
+                socket_path.display(),
+                timeout_ms
+            )
+            .into())
+        }
+    };
+
+    let socket = socket_path.display();
+    output::print(
+        output,
+        &serde_json::json!({
+            "name": info.name,
+            "version": info.version,
+            "entries": last_index.0,
+            "corrupt_entries": info.corrupt_entry_count,
+            "connections": info.connection_count,
+            "failed_unlocks": info.failed_unlock_count,
+            "secrets_mlocked": info.secrets_mlocked,
+            "core_dumps_disabled": info.core_dumps_disabled,
+            "ptrace_disabled": info.ptrace_disabled,
+            "umask_restricted": info.umask_restricted,
+            "crypto_threads": info.crypto_threads,
+            "crypto_queue_depth": info.crypto_queue_depth,
+            "crypto_busy_threads": info.crypto_busy_threads,
+            "socket": socket.to_string(),
+        }),
+        || {
+            format!(
+                "name: {}\nversion: {}\nentries: {}\ncorrupt entries: {}\n\
+                connections: {}\nfailed unlocks: {}\nsecrets mlocked: {}\n\
+                core dumps disabled: {}\nptrace disabled: {}\n\
+                umask restricted: {}\ncrypto threads: {} \
+                ({} busy, {} queued)\nsocket: {}",
+                info.name,
+                info.version,
+                last_index.0,
+                info.corrupt_entry_count,
+                info.connection_count,
+                info.failed_unlock_count,
+                info.secrets_mlocked,
+                info.core_dumps_disabled,
+                info.ptrace_disabled,
+                info.umask_restricted,
+                info.crypto_threads,
+                info.crypto_busy_threads,
+                info.crypto_queue_depth,
+                socket
+            )
+        },
+    )
+}
+
+async fn probe(
+    config: Arc<Config>,
+) -> LairResult<(LairServerInfo, KeystoreIndex)> {
+    let keystore = Keystore::connect_server(config).await?;
+    let info = keystore.lair_get_server_info().await;
+    let last_index = keystore.lair_get_last_entry_index().await;
+    keystore.close().await?;
+    Ok((info?, last_index?))
+}
@@ -2,3 +2,11 @@
 //! is unstable and may change even for patch versions of this library.
 
 pub mod pid_check;
+
+#[cfg(unix)]
+pub mod socket_activation;
+
+#[cfg(unix)]
+pub mod sd_notify;
+
+pub mod hardening;
@@ -0,0 +1,130 @@
+//! Process hardening, applied on startup before any store file
+//! or socket is created.
+//!
+//! The server holds long lived private keys, so by default it:
+//! - sets `RLIMIT_CORE` to 0, so crashes dump no cores holding them
+//! - marks itself not dumpable (linux `PR_SET_DUMPABLE`), which also
+//!   keeps other processes of the same (non-root) user from ptracing it
+//! - sets a `0o077` umask, so the files it creates are its own
+//!
+//! Each measure can be disabled in the config. Measures are process-wide
+//! and stay applied once applied.
+
+use crate::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CORE_DUMPS_DISABLED: AtomicBool = AtomicBool::new(false);
+static PTRACE_DISABLED: AtomicBool = AtomicBool::new(false);
+static UMASK_RESTRICTED: AtomicBool = AtomicBool::new(false);
+
+/// The umask set by `harden_process()`.
+pub const RESTRICTED_UMASK: u32 = 0o077;
+
+/// Apply the hardening measures enabled in `config`, logging those
+/// applied. Failing to apply one is logged, not an error.
+pub fn harden_process(config: &Config) {
+    if config.get_disable_core_dumps()
+        && !CORE_DUMPS_DISABLED.load(Ordering::SeqCst)
+    {
+        match sys::disable_core_dumps() {
+            Ok(()) => {
+                CORE_DUMPS_DISABLED.store(true, Ordering::SeqCst);
+                tracing::info!("core dumps disabled (RLIMIT_CORE = 0)");
+            }
+            Err(err) => {
+                tracing::warn!(%err, "failed to disable core dumps")
+            }
+        }
+    }
+
+    if config.get_disable_ptrace() && !PTRACE_DISABLED.load(Ordering::SeqCst) {
+        match sys::disable_ptrace() {
+            Ok(()) => {
+                PTRACE_DISABLED.store(true, Ordering::SeqCst);
+                tracing::info!("ptrace disabled (not dumpable)");
+            }
+            Err(err) => tracing::warn!(%err, "failed to disable ptrace"),
+        }
+    }
+
+    if config.get_restrict_umask() && !UMASK_RESTRICTED.load(Ordering::SeqCst) {
+        match sys::restrict_umask() {
+            Ok(()) => {
+                UMASK_RESTRICTED.store(true, Ordering::SeqCst);
+                tracing::info!("umask set to {:03o}", RESTRICTED_UMASK);
+            }
+            Err(err) => tracing::warn!(%err, "failed to restrict umask"),
+        }
+    }
+}
+
+/// Whether core dumps of this process are disabled.
+pub fn core_dumps_disabled() -> bool {
+    CORE_DUMPS_DISABLED.load(Ordering::SeqCst)
+}
+
+/// Whether this process is marked not dumpable, and so not ptraceable
+/// by non-root processes.
+pub fn ptrace_disabled() -> bool {
+    PTRACE_DISABLED.load(Ordering::SeqCst)
+}
+
+/// Whether this process has set the restricted umask.
+pub fn umask_restricted() -> bool {
+    UMASK_RESTRICTED.load(Ordering::SeqCst)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn unsupported(what: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Other,
+        format!("{} is not supported on this platform", what),
+    )
+}
+
+#[cfg(unix)]
+mod sys {
+    pub(super) fn disable_core_dumps() -> std::io::Result<()> {
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        match unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(super) fn disable_ptrace() -> std::io::Result<()> {
+        match unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub(super) fn disable_ptrace() -> std::io::Result<()> {
+        Err(super::unsupported("PR_SET_DUMPABLE"))
+    }
+
+    pub(super) fn restrict_umask() -> std::io::Result<()> {
+        unsafe { libc::umask(super::RESTRICTED_UMASK as libc::mode_t) };
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub(super) fn disable_core_dumps() -> std::io::Result<()> {
+        Err(super::unsupported("RLIMIT_CORE"))
+    }
+
+    pub(super) fn disable_ptrace() -> std::io::Result<()> {
+        Err(super::unsupported("PR_SET_DUMPABLE"))
+    }
+
+    pub(super) fn restrict_umask() -> std::io::Result<()> {
+        Err(super::unsupported("umask"))
+    }
+}
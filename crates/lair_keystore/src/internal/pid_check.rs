@@ -1,11 +1,22 @@
 //! Utilities for dealing with pid files.
+//!
+//! The pidfile doubles as the lock file of its store: a lair process holds
+//! an exclusive advisory lock on it for as long as it has the store open.
+//! The OS releases the lock when the process exits, however it exits,
+//! so the pidfile of a crashed process is simply taken over.
 
 use crate::*;
 use std::{
-    io::{Read, Write},
-    str::FromStr,
+    collections::BTreeMap,
+    io::{Read, Seek, Write},
+    path::PathBuf,
+    sync::Mutex,
 };
-use sysinfo::SystemExt;
+
+/// The locked pidfiles of this process, by path,
+/// held until `pid_release()`.
+static LOCKED: Mutex<BTreeMap<PathBuf, std::fs::File>> =
+    Mutex::new(BTreeMap::new());
 
 /// Result from invoking `pid_check()` function.
 pub struct PidCheckResult {
@@ -17,8 +28,21 @@ pub struct PidCheckResult {
 /// with access to given store / pidfile.
 /// This is sync instead of async as it is intended to be used at
 /// lair process startup, before we agree to acquire access to the store file.
+/// A read-only lair takes no pidfile, it only opens the (existing)
+/// store file for reading, so it can run beside the one true Lair process.
+/// Ephemeral configs have no store file to access.
 pub fn pid_check(config: &Config) -> LairResult<PidCheckResult> {
-    let mut sys = sysinfo::System::new();
+    if config.get_ephemeral() {
+        return Err("an ephemeral lair store only exists in its server".into());
+    }
+
+    if config.get_read_only() {
+        let store_file = std::fs::File::open(config.get_store_path())
+            .map_err(LairError::other)?;
+        return Ok(PidCheckResult {
+            store_file: tokio::fs::File::from_std(store_file),
+        });
+    }
 
     let mut last_err = None;
 
@@ -27,11 +51,17 @@ pub fn pid_check(config: &Config) -> LairResult<PidCheckResult> {
         if i != 0 {
             std::thread::sleep(std::time::Duration::from_millis(10));
         }
-        match pid_check_write(&config, &mut sys) {
-            Ok(_) => {
+        match pid_check_write(config) {
+            Ok(pid_file) => {
+                LOCKED
+                    .lock()
+                    .unwrap()
+                    .insert(config.get_pid_path().to_owned(), pid_file);
                 last_err = None;
                 break;
             }
+            // no use retrying, it won't let go of the lock
+            Err(e @ LairError::ProcessAlreadyExists(_)) => return Err(e),
             Err(e) => {
                 last_err = Some(e);
             }
@@ -55,56 +85,75 @@ pub fn pid_check(config: &Config) -> LairResult<PidCheckResult> {
     })
 }
 
-/// only returns success if we were able to write pidfile with our pid
-fn pid_check_write(
-    config: &Config,
-    sys: &mut sysinfo::System,
-) -> LairResult<()> {
+/// Remove the pidfile written by `pid_check()` and release its lock,
+/// for short-lived commands that need exclusive store access but do not
+/// run the ipc server.
+pub fn pid_release(config: &Config) -> LairResult<()> {
+    if config.get_read_only() || config.get_ephemeral() {
+        return Ok(());
+    }
+    // removed before it is unlocked, see `pid_check_write()`
+    let res =
+        std::fs::remove_file(config.get_pid_path()).map_err(LairError::other);
+    LOCKED.lock().unwrap().remove(config.get_pid_path());
+    res
+}
+
+/// only returns success if we were able to lock the pidfile
+/// and write our pid to it, returning the locked pidfile
+fn pid_check_write(config: &Config) -> LairResult<std::fs::File> {
     std::fs::create_dir_all(config.get_root_path())
         .map_err(LairError::other)?;
 
+    let mut pid_file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(config.get_pid_path())
+        .map_err(LairError::other)?;
+
+    match pid_file.try_lock() {
+        Ok(()) => (),
+        Err(std::fs::TryLockError::WouldBlock) => {
+            // a lair process is already running-abort running this one
+            // (it may not have written its pid yet)
+            let mut buf = String::new();
+            let _ = pid_file.read_to_string(&mut buf);
+            return Err(LairError::ProcessAlreadyExists(
+                buf.trim().parse().ok(),
+            ));
+        }
+        Err(std::fs::TryLockError::Error(e)) => {
+            return Err(LairError::other(e))
+        }
+    }
+
+    // the process we waited on may have removed the pidfile
+    // between our opening and locking it, leaving us the lock
+    // of a file no other process can find
+    #[cfg(unix)]
     {
-        let mut read_pid = std::fs::OpenOptions::new();
-        read_pid.read(true);
-        let mut buf = Vec::new();
-
-        match read_pid.open(config.get_pid_path()) {
-            Ok(mut read_pid) => {
-                read_pid.read_to_end(&mut buf).map_err(LairError::other)?;
-                let pid =
-                    sysinfo::Pid::from_str(&String::from_utf8_lossy(&buf))
-                        .map_err(LairError::other)?;
-                sys.refresh_process(pid);
-                if sys.get_process(pid).is_some() {
-                    // a lair process is already running-abort running this one
-                    // note - after a system restart the pid may have been
-                    // reused perhaps we should check the unix socket for a
-                    // valid lair process on the other end??
-                    return Err(LairError::ProcessAlreadyExists);
-                } else {
-                    // there was not a process running under this pid
-                    // we can remove it as stale.
-                    std::fs::remove_file(config.get_pid_path())
-                        .map_err(LairError::other)?;
-                }
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                // ok to proceed, as there shouldn't be a process running
-            }
-            Err(e) => return Err(LairError::other(e)),
+        use std::os::unix::fs::MetadataExt;
+        let locked = pid_file.metadata().map_err(LairError::other)?;
+        let current = std::fs::metadata(config.get_pid_path());
+        if !matches!(
+            current,
+            Ok(current) if current.dev() == locked.dev()
+                && current.ino() == locked.ino()
+        ) {
+            return Err("pidfile was removed while locking it".into());
         }
     }
 
-    let mut write_pid = std::fs::OpenOptions::new();
-    let mut write_pid = write_pid
-        .write(true)
-        .create_new(true)
-        .open(config.get_pid_path())
+    // whatever pid is left in there is stale
+    pid_file.set_len(0).map_err(LairError::other)?;
+    pid_file
+        .seek(std::io::SeekFrom::Start(0))
         .map_err(LairError::other)?;
-
-    write_pid
-        .write_all(format!("{}", sysinfo::get_current_pid()?).as_bytes())
+    pid_file
+        .write_all(format!("{}", std::process::id()).as_bytes())
         .map_err(LairError::other)?;
 
-    Ok(())
+    Ok(pid_file)
 }
@@ -0,0 +1,54 @@
+//! Utilities for systemd readiness notification.
+//! See `sd_notify(3)`.
+
+use crate::*;
+
+/// Send a state update (e.g. `READY=1`) to the service manager.
+/// Does nothing (returning false) if `NOTIFY_SOCKET` is not set.
+pub fn notify(state: &str) -> LairResult<bool> {
+    let path = match std::env::var_os("NOTIFY_SOCKET") {
+        None => return Ok(false),
+        Some(path) => path,
+    };
+
+    let socket = std::os::unix::net::UnixDatagram::unbound()
+        .map_err(LairError::other)?;
+
+    use std::os::unix::ffi::OsStrExt;
+    match path.as_bytes() {
+        // a leading '@' denotes a linux abstract namespace socket
+        #[cfg(target_os = "linux")]
+        [b'@', name @ ..] => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)
+                .map_err(LairError::other)?;
+            socket.send_to_addr(state.as_bytes(), &addr)
+        }
+        _ => socket.send_to(state.as_bytes(), &path),
+    }
+    .map_err(LairError::other)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_sends_state_to_notify_socket() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("notify");
+        let recv = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+
+        std::env::set_var("NOTIFY_SOCKET", &path);
+        assert!(notify("READY=1").unwrap());
+        std::env::remove_var("NOTIFY_SOCKET");
+
+        let mut buf = [0; 64];
+        let len = recv.recv(&mut buf).unwrap();
+        assert_eq!(b"READY=1", &buf[..len]);
+
+        assert!(!notify("READY=1").unwrap());
+    }
+}
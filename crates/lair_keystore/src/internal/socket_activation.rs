@@ -0,0 +1,86 @@
+//! Utilities for systemd socket activation.
+//! See `sd_listen_fds(3)`.
+
+use crate::*;
+use lair_keystore_api::ipc::IpcListener;
+
+/// The first file descriptor passed by systemd.
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// The `FileDescriptorName=` to select if we are passed more than one fd.
+pub const LISTEN_FD_NAME: &str = "lair-keystore";
+
+/// If we were started via systemd socket activation, take ownership of
+/// the passed, already bound and listening, unix socket.
+/// The `LISTEN_*` environment variables are cleared so they are not
+/// inherited by any child processes.
+/// If more than one fd is passed, the one named `lair-keystore` is used,
+/// and the others are left untouched.
+pub fn take_listener() -> LairResult<Option<IpcListener>> {
+    let res = listen_fd();
+
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    let fd = match res? {
+        None => return Ok(None),
+        Some(fd) => fd,
+    };
+
+    use std::os::unix::io::FromRawFd;
+    // SAFETY: systemd hands us ownership of this fd, and we have just
+    //         cleared the environment, so nothing else will claim it
+    let listener = unsafe { IpcListener::from_raw_fd(fd) };
+
+    // make sure it is actually a bound unix socket
+    listener.local_addr().map_err(|e| {
+        LairError::other(format!(
+            "LISTEN_FDS fd {} is not a unix socket: {}",
+            fd, e
+        ))
+    })?;
+
+    Ok(Some(listener))
+}
+
+fn listen_fd() -> LairResult<Option<i32>> {
+    let fd_count = match std::env::var("LISTEN_FDS") {
+        Err(_) => return Ok(None),
+        Ok(fd_count) => fd_count,
+    };
+
+    // the fds are meant for the process systemd started,
+    // not for any of its children
+    if let Ok(pid) = std::env::var("LISTEN_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return Ok(None);
+        }
+    }
+
+    let fd_count = fd_count
+        .parse::<i32>()
+        .map_err(|_| format!("invalid LISTEN_FDS: {}", fd_count))?;
+
+    let names = std::env::var("LISTEN_FDNAMES").ok();
+    let names = names
+        .as_deref()
+        .map(|names| names.split(':').collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let offset =
+        match (fd_count, names.iter().position(|n| *n == LISTEN_FD_NAME)) {
+            (0, _) => return Ok(None),
+            (_, Some(offset)) if (offset as i32) < fd_count => offset as i32,
+            (1, _) => 0,
+            _ => {
+                return Err(format!(
+                    "LISTEN_FDS passed {} fds, but none named {}",
+                    fd_count, LISTEN_FD_NAME
+                )
+                .into())
+            }
+        };
+
+    Ok(Some(SD_LISTEN_FDS_START + offset))
+}
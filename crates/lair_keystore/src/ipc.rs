@@ -4,16 +4,51 @@ use crate::entry::LairEntry;
 use crate::store::EntryStoreSender;
 use crate::*;
 use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::internal::util::SecretBytes;
 use lair_keystore_api::{actor::*, internal::*};
 
+/// How long to give in-flight requests (including the ack of the
+/// shutdown request itself) to complete before closing the store.
+const SHUTDOWN_GRACE: std::time::Duration =
+    std::time::Duration::from_millis(100);
+
+/// Resolves once the lair server has shut down
+/// in response to a `lair_shutdown` api request.
+pub type LairShutdownReceiver = futures::channel::oneshot::Receiver<()>;
+
 /// Spawn a new IPC server binding to serve out the Lair client api.
+/// If `passphrase` is supplied, the store is unlocked before the socket
+/// is bound. Otherwise a store with sealed entries is unlocked with the
+/// passphrase of the first client registering as a passphrase provider
+/// (see `LairClientApi::lair_register_passphrase_provider`), until then
+/// entry requests fail with `LairError::StoreLocked`.
+/// If `listener` is supplied (systemd socket activation), it is served
+/// on instead, and the socket file is left alone on shutdown.
 pub async fn spawn_bind_server_ipc(
     config: Arc<Config>,
     store_file: tokio::fs::File,
-) -> LairResult<()> {
+    passphrase: Option<Vec<u8>>,
+    listener: Option<lair_keystore_api::ipc::IpcListener>,
+) -> LairResult<LairShutdownReceiver> {
     let store_actor =
         store::spawn_entry_store_actor(config.clone(), store_file).await?;
 
+    spawn_bind_server_ipc_with_store(config, store_actor, passphrase, listener)
+        .await
+}
+
+/// Like `spawn_bind_server_ipc()`, serving an already spawned store
+/// actor, e.g. from `store::spawn_ephemeral_entry_store_actor()`.
+pub async fn spawn_bind_server_ipc_with_store(
+    config: Arc<Config>,
+    store_actor: ghost_actor::GhostSender<store::EntryStore>,
+    passphrase: Option<Vec<u8>>,
+    listener: Option<lair_keystore_api::ipc::IpcListener>,
+) -> LairResult<LairShutdownReceiver> {
+    if let Some(passphrase) = passphrase {
+        store_actor.unlock(passphrase.into()).await?;
+    }
+
     let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
     let api_sender = builder
@@ -26,11 +61,15 @@ pub async fn spawn_bind_server_ipc(
         .create_channel::<InternalApi>()
         .await?;
 
-    let mut con_recv = lair_keystore_api::ipc::spawn_bind_server_ipc(
-        config.clone(),
-        api_sender,
-    )
-    .await?;
+    let owns_socket = listener.is_none();
+
+    let mut con_recv =
+        lair_keystore_api::ipc::spawn_bind_server_ipc_with_listener(
+            config.clone(),
+            api_sender,
+            listener,
+        )
+        .await?;
 
     tokio::task::spawn(async move {
         while let Some(con) = con_recv.next().await {
@@ -39,11 +78,52 @@ pub async fn spawn_bind_server_ipc(
         LairResult::<()>::Ok(())
     });
 
-    tokio::task::spawn(
-        builder.spawn(Internal::new(config.clone(), store_actor)?),
-    );
+    let (shutdown_send, shutdown_recv) = futures::channel::oneshot::channel();
 
-    Ok(())
+    tokio::task::spawn(builder.spawn(Internal::new(
+        config.clone(),
+        store_actor,
+        owns_socket,
+        Some(shutdown_send),
+    )?));
+
+    Ok(shutdown_recv)
+}
+
+/// Spawn the Lair client api directly against the store file,
+/// without binding an ipc socket. Useful for one-off commands
+/// that run while no lair server is up.
+/// `unlock_passphrase` is only called if the store entries are sealed.
+pub async fn spawn_local_api<P>(
+    config: Arc<Config>,
+    store_file: tokio::fs::File,
+    unlock_passphrase: P,
+) -> LairResult<ghost_actor::GhostSender<LairClientApi>>
+where
+    P: FnOnce() -> LairResult<Vec<u8>>,
+{
+    let store_actor =
+        store::spawn_entry_store_actor(config.clone(), store_file).await?;
+
+    if store_actor.lock_state().await? == store::StoreLockState::Locked {
+        store_actor.unlock(unlock_passphrase()?.into()).await?;
+    }
+
+    let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+
+    let api_sender = builder
+        .channel_factory()
+        .create_channel::<LairClientApi>()
+        .await?;
+
+    tokio::task::spawn(builder.spawn(Internal::new(
+        config,
+        store_actor,
+        false,
+        None,
+    )?));
+
+    Ok(api_sender)
 }
 
 ghost_actor::ghost_chan! {
@@ -53,16 +133,195 @@ ghost_actor::ghost_chan! {
 }
 
 struct Internal {
-    #[allow(dead_code)]
+    config: Arc<Config>,
     store_actor: ghost_actor::GhostSender<store::EntryStore>,
+    owns_socket: bool,
+    shutdown_send: Option<futures::channel::oneshot::Sender<()>>,
+    secretstream_sessions: secretstream::SecretstreamSessions,
+    crypto_box_cache: crypto_box::CryptoBoxCache,
+    keypair_cache: sign_ed25519::SignEd25519KeypairCache,
 }
 
 impl Internal {
     pub fn new(
-        _config: Arc<Config>,
+        config: Arc<Config>,
         store_actor: ghost_actor::GhostSender<store::EntryStore>,
+        owns_socket: bool,
+        shutdown_send: Option<futures::channel::oneshot::Sender<()>>,
     ) -> LairResult<Self> {
-        Ok(Internal { store_actor })
+        let crypto_box_cache =
+            crypto_box::CryptoBoxCache::new(config.get_crypto_box_cache_size());
+        let keypair_cache = sign_ed25519::SignEd25519KeypairCache::new();
+        tokio::task::spawn(invalidate_caches(
+            store_actor.clone(),
+            crypto_box_cache.clone(),
+            keypair_cache.clone(),
+        ));
+        Ok(Internal {
+            config,
+            store_actor,
+            owns_socket,
+            shutdown_send,
+            secretstream_sessions: secretstream::SecretstreamSessions::new(),
+            crypto_box_cache,
+            keypair_cache,
+        })
+    }
+
+    fn new_tls_cert(
+        &mut self,
+        options: TlsCertOptions,
+        tag: Option<String>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self
+            .store_actor
+            .tls_cert_self_signed_new_from_entropy(options, tag);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    keystore_index,
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                )),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn new_sign_ed25519(
+        &mut self,
+        tag: Option<String>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let fut = self.store_actor.sign_ed25519_keypair_new_from_entropy(tag);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+}
+
+fn entry_type(entry: &LairEntry) -> LairResult<LairEntryType> {
+    match entry {
+        LairEntry::TlsCert(_) => Ok(LairEntryType::TlsCert),
+        LairEntry::SignEd25519(_) => Ok(LairEntryType::SignEd25519),
+        LairEntry::X25519(_) => Ok(LairEntryType::X25519),
+        LairEntry::Seed(_) => Ok(LairEntryType::Seed),
+        LairEntry::SecretKey(_) => Ok(LairEntryType::SecretKey),
+        LairEntry::SignSecp256k1(_) => Ok(LairEntryType::SignSecp256k1),
+        #[cfg(feature = "bls")]
+        LairEntry::SignBls12381(_) => Ok(LairEntryType::SignBls12381),
+        _ => Err(format!("unhandled entry type {:?}", entry).into()),
+    }
+}
+
+/// Wait until the rate limits allow `count` signatures with the key of
+/// `index`, before they are made. Fails with `LairError::RateLimited`
+/// if too many requests are waiting already.
+async fn rate_limit(
+    store_actor: &ghost_actor::GhostSender<store::EntryStore>,
+    index: KeystoreIndex,
+    count: usize,
+) -> LairResult<()> {
+    let count = std::cmp::min(count, u32::MAX as usize) as u32;
+    let wait = store_actor.reserve_signatures(index, count).await?;
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+    Ok(())
+}
+
+/// Forward store changes to a connection as events, until it closes.
+/// The ipc layer only passes entry changes on to clients that
+/// subscribed, lock state changes go to every client.
+async fn forward_store_changes(
+    store_actor: ghost_actor::GhostSender<store::EntryStore>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+) {
+    let mut changes = match store_actor.subscribe_changes().await {
+        Ok(changes) => changes,
+        Err(err) => {
+            tracing::warn!(?err, "failed to subscribe to store changes");
+            return;
+        }
+    };
+    loop {
+        let res = match changes.recv().await {
+            Ok(store::StoreChange::EntryCreated(index, entry)) => {
+                match entry_type(&entry) {
+                    Ok(t) => evt_send.entry_created(index, t).await,
+                    Err(err) => {
+                        tracing::warn!(?err, "unreported store change");
+                        continue;
+                    }
+                }
+            }
+            Ok(store::StoreChange::EntryDeleted(index)) => {
+                evt_send.entry_deleted(index).await
+            }
+            Ok(store::StoreChange::Locked) => evt_send.locked().await,
+            Ok(store::StoreChange::Unlocked) => evt_send.unlocked().await,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(count)) => {
+                tracing::warn!(count, "dropped store change events");
+                continue;
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if res.is_err() {
+            // the connection is gone
+            return;
+        }
+    }
+}
+
+/// Drop cached keyed boxes and keypairs as their entries are deleted,
+/// and all of them as the store is locked (also when it locks itself
+/// while idle), until the store is gone.
+async fn invalidate_caches(
+    store_actor: ghost_actor::GhostSender<store::EntryStore>,
+    crypto_box_cache: crypto_box::CryptoBoxCache,
+    keypair_cache: sign_ed25519::SignEd25519KeypairCache,
+) {
+    let changes = store_actor.subscribe_changes().await;
+    // not keeping the store alive
+    drop(store_actor);
+    let mut changes = match changes {
+        Ok(changes) => changes,
+        Err(err) => {
+            tracing::warn!(?err, "failed to subscribe to store changes");
+            return;
+        }
+    };
+    loop {
+        match changes.recv().await {
+            Ok(store::StoreChange::EntryDeleted(index)) => {
+                crypto_box_cache.invalidate(index).await;
+                keypair_cache.invalidate(index).await;
+            }
+            Ok(store::StoreChange::Locked)
+            | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                crypto_box_cache.clear().await;
+                keypair_cache.clear().await;
+            }
+            Ok(_) => (),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                crypto_box_cache.clear().await;
+                keypair_cache.clear().await;
+                return;
+            }
+        }
     }
 }
 
@@ -75,9 +334,11 @@ impl InternalApiHandler for Internal {
         &mut self,
         evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
     ) -> InternalApiHandlerResult<()> {
-        tokio::task::spawn(async move {
-            let _passphrase = evt_send.request_unlock_passphrase().await;
-        });
+        // passphrase providers are asked by the ipc layer as they register
+        tokio::task::spawn(forward_store_changes(
+            self.store_actor.clone(),
+            evt_send,
+        ));
         Ok(async move { Ok(()) }.boxed().into())
     }
 }
@@ -89,11 +350,72 @@ impl lair_keystore_api::actor::LairClientApiHandler for Internal {
     fn handle_lair_get_server_info(
         &mut self,
     ) -> LairClientApiHandlerResult<LairServerInfo> {
-        let mut out = LairServerInfo::default();
-        out.name = "lair-keystore".to_string();
-        out.version = crate::LAIR_VER.to_string();
+        let store_actor = self.store_actor.clone();
+        Ok(async move {
+            let mut out = LairServerInfo::default();
+            out.name = "lair-keystore".to_string();
+            out.version = crate::LAIR_VER.to_string();
+            out.corrupt_entry_count = store_actor.corrupt_entry_count().await?;
+            out.failed_unlock_count = store_actor.failed_unlock_count().await?;
+            out.secrets_mlocked = util::mem_lock_active();
+            use crate::internal::hardening;
+            out.core_dumps_disabled = hardening::core_dumps_disabled();
+            out.ptrace_disabled = hardening::ptrace_disabled();
+            out.umask_restricted = hardening::umask_restricted();
+            let crypto_pool = crypto_pool_stats();
+            out.crypto_threads = crypto_pool.threads as u64;
+            out.crypto_queue_depth = crypto_pool.queue_depth as u64;
+            out.crypto_busy_threads = crypto_pool.busy_threads as u64;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_ping(
+        &mut self,
+        nonce: u64,
+    ) -> LairClientApiHandlerResult<u64> {
+        Ok(async move { Ok(nonce) }.boxed().into())
+    }
+
+    fn handle_lair_lock(&mut self) -> LairClientApiHandlerResult<()> {
+        Ok(self.store_actor.lock().boxed().into())
+    }
+
+    fn handle_lair_unlock(
+        &mut self,
+        passphrase: SecretBytes,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self.store_actor.unlock(passphrase).boxed().into())
+    }
 
-        Ok(async move { Ok(out) }.boxed().into())
+    fn handle_lair_get_lock_state(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairLockState> {
+        let fut = self.store_actor.lock_state();
+        Ok(async move { Ok(fut.await?.into()) }.boxed().into())
+    }
+
+    fn handle_lair_register_passphrase_provider(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        // the ipc layer asks the connection, see `handle_incoming_con`
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_register_signing_approver(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        // the ipc layer asks the approver before forwarding signatures
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_subscribe_store_events(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        // every connection is sent store changes, see `handle_incoming_con`
+        Ok(async move { Ok(()) }.boxed().into())
     }
 
     fn handle_lair_get_last_entry_index(
@@ -109,365 +431,1727 @@ impl lair_keystore_api::actor::LairClientApiHandler for Internal {
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             match fut.await {
+                Err(LairError::EntryDeleted(_)) => Ok(LairEntryType::Deleted),
                 Err(_) => Ok(LairEntryType::Invalid),
-                Ok(entry) => match &*entry {
-                    LairEntry::TlsCert(_) => Ok(LairEntryType::TlsCert),
-                    LairEntry::SignEd25519(_) => Ok(LairEntryType::SignEd25519),
-                    LairEntry::X25519(_) => Ok(LairEntryType::X25519),
-                    _ => {
-                        Err(format!("unhandled entry type {:?}", entry).into())
-                    }
-                },
+                Ok(entry) => entry_type(&entry),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_list_entries(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<EntryListItem>> {
+        let fut = self.store_actor.list_entries();
+        Ok(async move {
+            Ok(fut
+                .await?
+                .into_iter()
+                .map(|(idx, entry, meta)| entry.list_item(idx, &meta))
+                .collect())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)> {
+        let fut = self.store_actor.get_entry_by_tag(tag);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            Ok((keystore_index, entry_type(&entry)?))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_meta(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<entry::EntryMeta> {
+        Ok(self
+            .store_actor
+            .get_entry_meta(keystore_index)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_find_entry(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<(KeystoreIndex, LairEntryType)>>
+    {
+        let fut = self.store_actor.find_entry(id);
+        Ok(async move {
+            match fut.await? {
+                Some((keystore_index, entry)) => {
+                    Ok(Some((keystore_index, entry_type(&entry)?)))
+                }
+                None => Ok(None),
             }
         }
         .boxed()
         .into())
     }
 
+    fn handle_lair_update_entry_tag(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: String,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self
+            .store_actor
+            .update_entry_tag(keystore_index, tag)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_set_entry_rate_limit(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        rate_limit: Option<u32>,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(self
+            .store_actor
+            .update_entry_rate_limit(keystore_index, rate_limit)
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_export_entry_public(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<EntryPublic> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move { fut.await?.export_public() }.boxed().into())
+    }
+
+    fn handle_lair_delete_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let keypair_cache = self.keypair_cache.clone();
+        let fut = self.store_actor.delete_entry(keystore_index);
+        Ok(async move {
+            fut.await?;
+            crypto_box_cache.invalidate(keystore_index).await;
+            keypair_cache.invalidate(keystore_index).await;
+            Ok(())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_compact_store(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairCompactInfo> {
+        Ok(self.store_actor.compact_store().boxed().into())
+    }
+
+    fn handle_lair_shutdown(&mut self) -> LairClientApiHandlerResult<()> {
+        let config = self.config.clone();
+        let store_actor = self.store_actor.clone();
+        // only the ipc server has a pidfile to clean up
+        let shutdown_send = self.shutdown_send.take();
+        // a socket passed to us by systemd is not ours to remove
+        let owns_socket = self.owns_socket;
+        tokio::task::spawn(async move {
+            if owns_socket {
+                // removing the socket file stops new clients connecting
+                lair_keystore_api::internal::ipc::unbind_ipc(&config);
+            }
+
+            tokio::time::sleep(SHUTDOWN_GRACE).await;
+
+            if let Err(err) = store_actor.flush_and_close().await {
+                tracing::error!(?err, "error closing store on shutdown");
+            }
+            // the usage flush timer would otherwise keep it alive,
+            // along with the entries of an ephemeral store
+            use ghost_actor::GhostControlSender;
+            let _ = store_actor.ghost_actor_shutdown().await;
+
+            if let Some(shutdown_send) = shutdown_send {
+                if let Err(err) = internal::pid_check::pid_release(&config) {
+                    tracing::error!(?err, "error removing pidfile");
+                }
+                let _ = shutdown_send.send(());
+            }
+        });
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_change_unlock_passphrase(
+        &mut self,
+        old_passphrase: String,
+        new_passphrase: String,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.store_actor.change_unlock_passphrase(
+            old_passphrase.into_bytes().into(),
+            new_passphrase.into_bytes().into(),
+        );
+        Ok(fut.boxed().into())
+    }
+
     fn handle_tls_cert_new_self_signed_from_entropy(
         &mut self,
         options: TlsCertOptions,
     ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        self.new_tls_cert(options, None)
+    }
+
+    fn handle_tls_cert_new_self_signed_tagged(
+        &mut self,
+        options: TlsCertOptions,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        self.new_tls_cert(options, Some(tag))
+    }
+
+    fn handle_tls_cert_renew(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self.store_actor.tls_cert_renew(keystore_index, options);
+        Ok(async move {
+            let (cert_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    cert_index,
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                )),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_ca_new_self_signed(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self.store_actor.tls_ca_new_self_signed(options);
+        Ok(async move {
+            let (ca_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => {
+                    Ok((ca_index, entry.sni.clone(), entry.cert_digest.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_signed_by_ca(
+        &mut self,
+        ca_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        CertSni,
+        CertDigest,
+        Vec<Cert>,
+    )> {
+        let store_actor = self.store_actor.clone();
         let fut = self
             .store_actor
-            .tls_cert_self_signed_new_from_entropy(options);
+            .tls_cert_new_signed_by_ca(ca_index, options);
         Ok(async move {
-            let (keystore_index, entry) = fut.await?;
+            let (cert_index, entry, ca_cert_der) = fut.await?;
+            // signing the cert used the CA private key
+            store_actor.record_entry_use(ca_index).await?;
             match &*entry {
                 LairEntry::TlsCert(entry) => Ok((
-                    keystore_index,
+                    cert_index,
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                    vec![entry.cert_der.clone(), ca_cert_der],
+                )),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_import(
+        &mut self,
+        cert_der: Cert,
+        priv_key_der: CertPrivKey,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut =
+            self.store_actor
+                .tls_cert_import(cert_der, priv_key_der, options);
+        Ok(async move {
+            let (cert_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    cert_index,
                     entry.sni.clone(),
                     entry.cert_digest.clone(),
                 )),
-                _ => Err("invalid entry type".into()),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_find_by_digest_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let fut = self.store_actor.get_entry_by_digest_prefix(prefix);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok((
+                    keystore_index,
+                    entry.sni.clone(),
+                    entry.cert_digest.clone(),
+                )),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => {
+                    Ok((entry.sni.clone(), entry.cert_digest.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
+        Ok(async move {
+            let (_, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+        Ok(async move {
+            let (_, entry) = fut.await?;
+            match &*entry {
+                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            let out = match &*entry {
+                LairEntry::TlsCert(entry) => entry.exportable_priv_key()?,
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            let out = match &*entry {
+                LairEntry::TlsCert(entry) => entry.exportable_priv_key()?,
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            let out = match &*entry {
+                LairEntry::TlsCert(entry) => entry.exportable_priv_key()?,
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_export_pkcs12(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        password: Option<String>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            let (cert_der, priv_key_der, sni) = match &*entry {
+                LairEntry::TlsCert(entry) => (
+                    entry.cert_der.0.clone(),
+                    entry.exportable_priv_key()?.0,
+                    entry.sni.to_string(),
+                ),
+                _ => return Err(LairError::WrongEntryType),
+            };
+            let out =
+                pkcs12::pkcs12_bundle(cert_der, priv_key_der, sni, password)
+                    .await?;
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        scheme: TlsCertSignatureScheme,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let sig = match &*entry {
+                LairEntry::TlsCert(entry) => entry.sign(scheme, message),
+                _ => return Err(LairError::WrongEntryType),
+            }
+            .await?;
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(sig)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        self.new_sign_ed25519(None)
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_tagged(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        self.new_sign_ed25519(Some(tag))
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_requiring_approval(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let fut = self
+            .store_actor
+            .sign_ed25519_keypair_new_requiring_approval();
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_seed(
+        &mut self,
+        seed: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        if seed.len() != 32 {
+            return Err(LairError::SignEd25519SeedLength(seed.len()));
+        }
+        // the new entry keeps its own copy of the seed,
+        // the request buffer is wiped as soon as we're done with it
+        let seed = zeroize::Zeroizing::new(
+            Arc::try_unwrap(seed).unwrap_or_else(|seed| seed.to_vec()),
+        );
+        let fut = self
+            .store_actor
+            .sign_ed25519_keypair_new_from_seed(seed.to_vec(), false);
+        drop(seed);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519PubKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::SignEd25519(entry) => Ok(entry.pub_key.clone()),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let store_actor = self.store_actor.clone();
+        let keypair_cache = self.keypair_cache.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    keypair_cache
+                        .sign_ed25519(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            message,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let store_actor = self.store_actor.clone();
+        let keypair_cache = self.keypair_cache.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    keypair_cache
+                        .sign_ed25519(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            message,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_with_context(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519_with_context(
+                        entry.priv_key.clone(),
+                        context,
+                        message,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key_with_context(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519_with_context(
+                        entry.priv_key.clone(),
+                        context,
+                        message,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_batch_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        messages: Vec<bytes::Bytes>,
+    ) -> LairClientApiHandlerResult<Vec<sign_ed25519::SignEd25519Signature>>
+    {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            rate_limit(&store_actor, keystore_index, messages.len()).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519_batch(
+                        entry.priv_key.clone(),
+                        messages,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_batch_by_pub_key(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        messages: Vec<bytes::Bytes>,
+    ) -> LairClientApiHandlerResult<Vec<sign_ed25519::SignEd25519Signature>>
+    {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            rate_limit(&store_actor, keystore_index, messages.len()).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519_batch(
+                        entry.priv_key.clone(),
+                        messages,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_prehashed(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519_prehashed(
+                        entry.priv_key.clone(),
+                        digest,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_verify(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        message: bytes::Bytes,
+        signature: sign_ed25519::SignEd25519Signature,
+    ) -> LairClientApiHandlerResult<bool> {
+        Ok(
+            sign_ed25519::sign_ed25519_verify(pub_key, message, signature)
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_sign_ed25519_sign_by_tag(
+        &mut self,
+        tag: String,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_tag(tag);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_list_pub_keys(
+        &mut self,
+    ) -> LairClientApiHandlerResult<
+        Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>,
+    > {
+        let fut = self.store_actor.list_entries();
+        Ok(async move {
+            Ok(fut
+                .await?
+                .into_iter()
+                .filter_map(|(idx, entry, _)| match &*entry {
+                    LairEntry::SignEd25519(entry) => {
+                        Some((idx, entry.pub_key.clone()))
+                    }
+                    _ => None,
+                })
+                .collect())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_new_from_entropy(
+        &mut self,
+        exportable: bool,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)> {
+        let fut = self.store_actor.seed_new_from_entropy(exportable);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::Seed(entry) => {
+                    Ok((keystore_index, entry.seed_id.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_export_mnemonic(
+        &mut self,
+        seed_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<String> {
+        let store_actor = self.store_actor.clone();
+        Ok(async move {
+            let entry = store_actor.get_entry_by_index(seed_index).await?;
+            let mnemonic = match &*entry {
+                LairEntry::Seed(entry) if entry.exportable => {
+                    seed::seed_to_mnemonic(&entry.seed)?
+                }
+                LairEntry::Seed(_) => return Err(LairError::NotExportable),
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(seed_index).await?;
+            Ok(mnemonic)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_import_mnemonic(
+        &mut self,
+        mnemonic: String,
+        exportable: bool,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)> {
+        let mnemonic = zeroize::Zeroizing::new(mnemonic);
+        let seed = seed::seed_from_mnemonic(&mnemonic)?;
+        let fut = self.store_actor.seed_import(seed.to_vec(), exportable);
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::Seed(entry) => {
+                    Ok((keystore_index, entry.seed_id.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_derive_from_seed(
+        &mut self,
+        seed_index: KeystoreIndex,
+        derivation_path: Vec<u32>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let store_actor = self.store_actor.clone();
+        Ok(async move {
+            let (keystore_index, entry) = store_actor
+                .sign_ed25519_keypair_derive_from_seed(
+                    seed_index,
+                    derivation_path,
+                )
+                .await?;
+            let out = match &*entry {
+                LairEntry::SignEd25519(entry) => {
+                    (keystore_index, entry.pub_key.clone())
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(seed_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_secp256k1::SignSecp256k1PubKey,
+    )> {
+        let fut = self.store_actor.secp256k1_keypair_new_from_entropy();
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::SignSecp256k1(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1PubKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::SignSecp256k1(entry) => Ok(entry.pub_key.clone()),
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1Signature>
+    {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                LairEntry::SignSecp256k1(entry) => {
+                    sign_secp256k1::sign_secp256k1(
+                        entry.priv_key.clone(),
+                        digest,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_secp256k1::SignSecp256k1PubKey,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1Signature>
+    {
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_pub_id(pub_key.pub_id());
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            rate_limit(&store_actor, keystore_index, 1).await?;
+            let out = match &*entry {
+                // the pub id is a hash, make sure it is the key asked for
+                LairEntry::SignSecp256k1(entry) if entry.pub_key == pub_key => {
+                    sign_secp256k1::sign_secp256k1(
+                        entry.priv_key.clone(),
+                        digest,
+                    )
+                    .await?
+                }
+                LairEntry::SignSecp256k1(_) => {
+                    return Err(LairError::PubKeyNotFound)
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_bls_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_bls12381::SignBls12381PubKey,
+    )> {
+        #[cfg(not(feature = "bls"))]
+        {
+            Err(sign_bls12381::bls_not_enabled())
+        }
+        #[cfg(feature = "bls")]
+        {
+            let fut = self.store_actor.bls12381_keypair_new_from_entropy();
+            Ok(async move {
+                let (keystore_index, entry) = fut.await?;
+                match &*entry {
+                    LairEntry::SignBls12381(entry) => {
+                        Ok((keystore_index, entry.pub_key.clone()))
+                    }
+                    _ => Err(LairError::WrongEntryType),
+                }
+            }
+            .boxed()
+            .into())
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn handle_bls_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_bls12381::SignBls12381PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<sign_bls12381::SignBls12381Signature> {
+        #[cfg(not(feature = "bls"))]
+        {
+            Err(sign_bls12381::bls_not_enabled())
+        }
+        #[cfg(feature = "bls")]
+        {
+            let store_actor = self.store_actor.clone();
+            let fut = self.store_actor.get_entry_by_pub_id(pub_key.pub_id());
+            Ok(async move {
+                let (keystore_index, entry) = fut.await?;
+                rate_limit(&store_actor, keystore_index, 1).await?;
+                let out = match &*entry {
+                    // the pub id is a hash, make sure it is the key asked for
+                    LairEntry::SignBls12381(entry)
+                        if entry.pub_key == pub_key =>
+                    {
+                        sign_bls12381::sign_bls12381(
+                            entry.priv_key.clone(),
+                            message,
+                        )
+                        .await?
+                    }
+                    LairEntry::SignBls12381(_) => {
+                        return Err(LairError::PubKeyNotFound)
+                    }
+                    _ => return Err(LairError::WrongEntryType),
+                };
+                store_actor.record_entry_use(keystore_index).await?;
+                Ok(out)
+            }
+            .boxed()
+            .into())
+        }
+    }
+
+    fn handle_sign_ed25519_to_x25519(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        let store_actor = self.store_actor.clone();
+        let fut = self
+            .store_actor
+            .x25519_keypair_convert_from_sign_ed25519(keystore_index);
+        Ok(async move {
+            let (x25519_index, entry) = fut.await?;
+            let pub_key = match &*entry {
+                LairEntry::X25519(entry) => entry.pub_key.clone(),
+                _ => return Err(LairError::WrongEntryType),
+            };
+            // the signature private key was used for the conversion
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok((x25519_index, pub_key))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        let fut = self.store_actor.x25519_keypair_new_from_entropy();
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_new_from_priv_key(
+        &mut self,
+        priv_key: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        if priv_key.len() != x25519::PRIV_KEY_BYTES {
+            return Err(LairError::X25519PrivKeyImportLength(priv_key.len()));
+        }
+        // the new entry keeps its own (clamped) copy of the key,
+        // the request buffer is wiped once the entry is persisted
+        let priv_key = zeroize::Zeroizing::new(
+            Arc::try_unwrap(priv_key).unwrap_or_else(|k| k.to_vec()),
+        );
+        let fut = self
+            .store_actor
+            .x25519_keypair_new_from_priv_key(priv_key.to_vec());
+        Ok(async move {
+            let res = fut.await;
+            drop(priv_key);
+            let (keystore_index, entry) = res?;
+            match &*entry {
+                LairEntry::X25519(entry) => {
+                    Ok((keystore_index, entry.pub_key.clone()))
+                }
+                _ => Err(LairError::WrongEntryType),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<x25519::X25519PubKey> {
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
+        Ok(async move {
+            let entry = fut.await?;
+            match &*entry {
+                LairEntry::X25519(entry) => Ok(entry.pub_key.clone()),
+                _ => Err(LairError::WrongEntryType),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get(
+    fn handle_x25519_list_pub_keys(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
-        let fut = self.store_actor.get_entry_by_index(keystore_index);
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, x25519::X25519PubKey)>>
+    {
+        let fut = self.store_actor.list_entries();
         Ok(async move {
-            let entry = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => {
-                    Ok((entry.sni.clone(), entry.cert_digest.clone()))
-                }
-                _ => Err("invalid entry type".into()),
-            }
+            Ok(fut
+                .await?
+                .into_iter()
+                .filter_map(|(idx, entry, _)| match &*entry {
+                    LairEntry::X25519(entry) => {
+                        Some((idx, entry.pub_key.clone()))
+                    }
+                    _ => None,
+                })
+                .collect())
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_index(
+    fn handle_kx_session_keys_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<Cert> {
+        remote_pub: x25519::X25519PubKey,
+        is_server: bool,
+    ) -> LairClientApiHandlerResult<(kx::KxSessionKey, kx::KxSessionKey)> {
+        let store_actor = self.store_actor.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    kx::kx_session_keys(
+                        entry.priv_key.clone(),
+                        entry.pub_key.clone(),
+                        remote_pub,
+                        is_server,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_digest(
+    fn handle_crypto_box_by_index(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
+        keystore_index: KeystoreIndex,
+        recipient: x25519::X25519PubKey,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let entry = fut.await?;
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            recipient,
+                            data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_sni(
+    fn handle_crypto_box_by_pub_key(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+        pub_key: x25519::X25519PubKey,
+        recipient: x25519::X25519PubKey,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let fut = self
+            .store_actor
+            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.cert_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let (keystore_index, entry) = fut.await?;
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            recipient,
+                            data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_index(
+    fn handle_crypto_box_open_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        sender: x25519::X25519PubKey,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box_open(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            sender,
+                            encrypted_data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_digest(
+    fn handle_crypto_box_open_by_pub_key(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.store_actor.get_entry_by_pub_id(cert_digest.0);
+        pub_key: x25519::X25519PubKey,
+        sender: x25519::X25519PubKey,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let fut = self
+            .store_actor
+            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let (keystore_index, entry) = fut.await?;
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box_open(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            sender,
+                            encrypted_data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_sni(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_by_index_with_aad(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.store_actor.get_entry_by_sni(cert_sni);
+        keystore_index: KeystoreIndex,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::TlsCert(entry) => Ok(entry.priv_key_der.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let entry = fut.await?;
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box_with_aad(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            recipient,
+                            aad,
+                            data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_new_from_entropy(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_by_pub_key_with_aad(
         &mut self,
-    ) -> LairClientApiHandlerResult<(
-        KeystoreIndex,
-        sign_ed25519::SignEd25519PubKey,
-    )> {
-        let fut = self.store_actor.sign_ed25519_keypair_new_from_entropy();
+        pub_key: x25519::X25519PubKey,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let fut = self
+            .store_actor
+            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
         Ok(async move {
             let (keystore_index, entry) = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => {
-                    Ok((keystore_index, entry.pub_key.clone()))
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box_with_aad(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            recipient,
+                            aad,
+                            data,
+                        )
+                        .await?
                 }
-                _ => Err("invalid entry type".into()),
-            }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_get(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_open_by_index_with_aad(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519PubKey> {
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => Ok(entry.pub_key.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box_open_with_aad(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            sender,
+                            aad,
+                            encrypted_data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_index(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_open_by_pub_key_with_aad(
+        &mut self,
+        pub_key: x25519::X25519PubKey,
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let store_actor = self.store_actor.clone();
+        let crypto_box_cache = self.crypto_box_cache.clone();
+        let fut = self
+            .store_actor
+            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
+        Ok(async move {
+            let (keystore_index, entry) = fut.await?;
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box_cache
+                        .crypto_box_open_with_aad(
+                            keystore_index,
+                            entry.priv_key.clone(),
+                            sender,
+                            aad,
+                            encrypted_data,
+                        )
+                        .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_seal_open_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let store_actor = self.store_actor.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => {
-                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
-                        .await
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box::crypto_box_seal_open(
+                        entry.priv_key.clone(),
+                        entry.pub_key.clone(),
+                        sealed_data,
+                    )
+                    .await?
                 }
-                _ => Err("invalid entry type".into()),
-            }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_pub_key(
+    fn handle_crypto_box_seal_open_by_pub_key(
         &mut self,
-        pub_key: sign_ed25519::SignEd25519PubKey,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
-        let fut = self.store_actor.get_entry_by_pub_id(pub_key.0);
+        pub_key: x25519::X25519PubKey,
+        sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let store_actor = self.store_actor.clone();
+        let fut = self
+            .store_actor
+            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::SignEd25519(entry) => {
-                    sign_ed25519::sign_ed25519(entry.priv_key.clone(), message)
-                        .await
+            let (keystore_index, entry) = fut.await?;
+            let out = match &*entry {
+                LairEntry::X25519(entry) => {
+                    crypto_box::crypto_box_seal_open(
+                        entry.priv_key.clone(),
+                        entry.pub_key.clone(),
+                        sealed_data,
+                    )
+                    .await?
                 }
-                _ => Err("invalid entry type".into()),
-            }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_x25519_new_from_entropy(
+    fn handle_secret_new_from_entropy(
         &mut self,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
-        let fut = self.store_actor.x25519_keypair_new_from_entropy();
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, secret_key::SecretKeyTag)>
+    {
+        let fut = self.store_actor.secret_key_new_from_entropy();
         Ok(async move {
             let (keystore_index, entry) = fut.await?;
             match &*entry {
-                LairEntry::X25519(entry) => {
-                    Ok((keystore_index, entry.pub_key.clone()))
+                LairEntry::SecretKey(entry) => {
+                    Ok((keystore_index, entry.tag.clone()))
                 }
-                _ => Err("invalid entry type".into()),
+                _ => Err(LairError::WrongEntryType),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_x25519_get(
+    fn handle_secret_encrypt_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<x25519::X25519PubKey> {
+        aad: Arc<Vec<u8>>,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(secret_key::SecretNonce, Arc<Vec<u8>>)>
+    {
+        let store_actor = self.store_actor.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
-                LairEntry::X25519(entry) => Ok(entry.pub_key.clone()),
-                _ => Err("invalid entry type".into()),
-            }
+            let out = match &*entry {
+                LairEntry::SecretKey(entry) => {
+                    secret_key::secret_encrypt(
+                        entry.secret_key.clone(),
+                        aad,
+                        data,
+                    )
+                    .await?
+                }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_by_index(
+    fn handle_secret_decrypt_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-        recipient: x25519::X25519PubKey,
-        data: Arc<crypto_box::CryptoBoxData>,
-    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        aad: Arc<Vec<u8>>,
+        nonce: secret_key::SecretNonce,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<Arc<Vec<u8>>>> {
+        let store_actor = self.store_actor.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
-                LairEntry::X25519(entry) => {
-                    crypto_box::crypto_box(
-                        entry.priv_key.clone(),
-                        recipient,
-                        data,
+            let out = match &*entry {
+                LairEntry::SecretKey(entry) => {
+                    secret_key::secret_decrypt(
+                        entry.secret_key.clone(),
+                        aad,
+                        nonce,
+                        ciphertext,
                     )
-                    .await
+                    .await?
                 }
-                _ => Err("invalid entry type".into()),
-            }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_by_pub_key(
+    fn handle_derive_subkey_by_index(
         &mut self,
-        pub_key: x25519::X25519PubKey,
-        recipient: x25519::X25519PubKey,
-        data: Arc<crypto_box::CryptoBoxData>,
-    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
-        let fut = self
-            .store_actor
-            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
+        keystore_index: KeystoreIndex,
+        subkey_len: usize,
+        context: [u8; secret_key::SUBKEY_CONTEXT_BYTES],
+        subkey_id: u64,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        secret_key::check_subkey_len(subkey_len)?;
+        let store_actor = self.store_actor.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
-                LairEntry::X25519(entry) => {
-                    crypto_box::crypto_box(
-                        entry.priv_key.clone(),
-                        recipient,
-                        data,
-                    )
-                    .await
-                }
-                _ => Err("invalid entry type".into()),
-            }
+            let entry = fut.await?;
+            let key = match &*entry {
+                LairEntry::SecretKey(entry) => entry.secret_key.0.clone(),
+                LairEntry::Seed(entry) => entry.seed.0.clone(),
+                _ => return Err(LairError::WrongEntryType),
+            };
+            let out =
+                secret_key::derive_subkey(key, subkey_len, context, subkey_id)
+                    .await?;
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_open_by_index(
+    fn handle_secretstream_init_push_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-        sender: x25519::X25519PubKey,
-        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
-    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        remote_pub: x25519::X25519PubKey,
+    ) -> LairClientApiHandlerResult<(
+        secretstream::SecretstreamSessionId,
+        secretstream::SecretstreamHeader,
+    )> {
+        let store_actor = self.store_actor.clone();
+        let sessions = self.secretstream_sessions.clone();
         let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
             let entry = fut.await?;
-            match &*entry {
+            let out = match &*entry {
                 LairEntry::X25519(entry) => {
-                    crypto_box::crypto_box_open(
-                        entry.priv_key.clone(),
-                        sender,
-                        encrypted_data,
-                    )
-                    .await
+                    sessions
+                        .init_push(entry.priv_key.clone(), remote_pub)
+                        .await?
                 }
-                _ => Err("invalid entry type".into()),
-            }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_open_by_pub_key(
+    fn handle_secretstream_push(
         &mut self,
-        pub_key: x25519::X25519PubKey,
-        sender: x25519::X25519PubKey,
-        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
-    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
-        let fut = self
-            .store_actor
-            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()));
+        session_id: secretstream::SecretstreamSessionId,
+        chunk: Arc<Vec<u8>>,
+        is_final: bool,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let sessions = self.secretstream_sessions.clone();
+        Ok(
+            async move { sessions.push(session_id, chunk, is_final).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_secretstream_init_pull_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        remote_pub: x25519::X25519PubKey,
+        header: secretstream::SecretstreamHeader,
+    ) -> LairClientApiHandlerResult<secretstream::SecretstreamSessionId> {
+        let store_actor = self.store_actor.clone();
+        let sessions = self.secretstream_sessions.clone();
+        let fut = self.store_actor.get_entry_by_index(keystore_index);
         Ok(async move {
-            let (_, entry) = fut.await?;
-            match &*entry {
+            let entry = fut.await?;
+            let out = match &*entry {
                 LairEntry::X25519(entry) => {
-                    crypto_box::crypto_box_open(
-                        entry.priv_key.clone(),
-                        sender,
-                        encrypted_data,
-                    )
-                    .await
+                    sessions
+                        .init_pull(entry.priv_key.clone(), remote_pub, header)
+                        .await?
                 }
-                _ => Err("invalid entry type".into()),
-            }
+                _ => return Err(LairError::WrongEntryType),
+            };
+            store_actor.record_entry_use(keystore_index).await?;
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secretstream_pull(
+        &mut self,
+        session_id: secretstream::SecretstreamSessionId,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<(Arc<Vec<u8>>, bool)>> {
+        let sessions = self.secretstream_sessions.clone();
+        Ok(async move { sessions.pull(session_id, ciphertext).await }
+            .boxed()
+            .into())
+    }
+
+    fn handle_secretstream_close(
+        &mut self,
+        session_id: secretstream::SecretstreamSessionId,
+    ) -> LairClientApiHandlerResult<()> {
+        let sessions = self.secretstream_sessions.clone();
+        Ok(async move {
+            sessions.close(session_id).await;
+            Ok(())
         }
         .boxed()
         .into())
@@ -20,21 +20,412 @@ pub mod store;
 pub mod ipc;
 
 /// Main loop of lair executable.
-pub async fn execute_lair() -> LairResult<()> {
-    let mut config = Config::builder();
+/// The returned receiver resolves once a client requests
+/// a graceful shutdown via `lair_shutdown`.
+pub async fn execute_lair() -> LairResult<ipc::LairShutdownReceiver> {
+    execute_lair_inner(None, None).await
+}
+
+/// Main loop of lair executable, unlocking the store with the
+/// given passphrase up front rather than requesting it from clients.
+pub async fn execute_lair_with_passphrase(
+    passphrase: Vec<u8>,
+) -> LairResult<ipc::LairShutdownReceiver> {
+    execute_lair_inner(Some(passphrase), None).await
+}
+
+/// Main loop of lair executable, sending on `ready` once the store
+/// is loaded and the socket is accepting connections.
+/// Prefer this to scraping `#lair-keystore-ready#` from stdout.
+pub async fn execute_lair_with_ready(
+    ready: futures::channel::oneshot::Sender<()>,
+) -> LairResult<ipc::LairShutdownReceiver> {
+    execute_lair_inner(None, Some(ready)).await
+}
+
+/// `LAIR_PROFILE` may name several profiles, separated by commas,
+/// all served by this process (with the same `passphrase`, if given).
+async fn execute_lair_inner(
+    passphrase: Option<Vec<u8>>,
+    ready: Option<futures::channel::oneshot::Sender<()>>,
+) -> LairResult<ipc::LairShutdownReceiver> {
+    let passphrase = passphrase.map(zeroize::Zeroizing::new);
+    let config_file = std::env::var_os("LAIR_CONFIG");
+    let lair_dir = std::env::var_os("LAIR_DIR");
+    let profiles = std::env::var("LAIR_PROFILE").unwrap_or_default();
+    let mut profiles = profiles
+        .split(',')
+        .filter(|p| !p.is_empty())
+        .map(Some)
+        .collect::<Vec<_>>();
+    if profiles.is_empty() {
+        profiles.push(None);
+    }
+
+    let profiles = profiles
+        .into_iter()
+        .map(|profile| {
+            let config = load_profile_config(
+                config_file.as_ref().map(std::path::Path::new),
+                lair_dir.as_ref().map(std::path::Path::new),
+                profile,
+            )?;
+            Ok((config, passphrase.as_ref().map(|p| p.to_vec())))
+        })
+        .collect::<LairResult<Vec<_>>>()?;
+
+    execute_lair_profiles(profiles, ready).await
+}
+
+/// The lair dir (e.g. `LAIR_DIR`) selecting an ephemeral config,
+/// see `ConfigBuilder::set_ephemeral()`.
+pub const EPHEMERAL_LAIR_DIR: &str = ":memory:";
 
-    if let Some(lair_dir) = std::env::var_os("LAIR_DIR") {
+/// Build the lair config from `config_file`, or from `lair-config.toml`
+/// in the lair dir if it exists, else from defaults.
+/// An explicit `lair_dir` (e.g. `LAIR_DIR`) overrides the file,
+/// `EPHEMERAL_LAIR_DIR` makes it ephemeral.
+pub fn load_config(
+    config_file: Option<&std::path::Path>,
+    lair_dir: Option<&std::path::Path>,
+) -> LairResult<Arc<Config>> {
+    load_profile_config(config_file, lair_dir, None)
+}
+
+/// Like `load_config()`, for the named `profile` if given
+/// (e.g. `LAIR_PROFILE`), overriding any profile in the file.
+pub fn load_profile_config(
+    config_file: Option<&std::path::Path>,
+    lair_dir: Option<&std::path::Path>,
+    profile: Option<&str>,
+) -> LairResult<Arc<Config>> {
+    Ok(load_config_builder(config_file, lair_dir, profile)?.build())
+}
+
+/// Like `load_profile_config()`, leaving the config to be built,
+/// for callers overriding more of it (e.g. command line flags).
+pub fn load_config_builder(
+    config_file: Option<&std::path::Path>,
+    lair_dir: Option<&std::path::Path>,
+    profile: Option<&str>,
+) -> LairResult<ConfigBuilder> {
+    let mut config = match find_config_file(config_file, lair_dir) {
+        Some(config_file) => Config::from_toml_file(config_file)?,
+        None => Config::builder(),
+    };
+
+    if let Some(lair_dir) = lair_dir {
         config = config.set_root_path(lair_dir);
+        if lair_dir == std::path::Path::new(EPHEMERAL_LAIR_DIR) {
+            config = config.set_ephemeral(true);
+        }
+    }
+
+    if let Some(profile) = profile {
+        config = config.set_profile(profile)?;
+    }
+
+    Ok(config)
+}
+
+/// The config file `load_config()` would read, if any.
+pub fn find_config_file(
+    config_file: Option<&std::path::Path>,
+    lair_dir: Option<&std::path::Path>,
+) -> Option<std::path::PathBuf> {
+    match config_file {
+        Some(config_file) => Some(config_file.to_owned()),
+        None => {
+            let config = Config::builder();
+            let config_file = lair_dir
+                .unwrap_or_else(|| config.get_root_path())
+                .join(CONFIG_FILE_NAME);
+            if config_file.exists() {
+                Some(config_file)
+            } else {
+                None
+            }
+        }
     }
+}
+
+/// Main loop of lair executable with an explicit config.
+/// See `execute_lair_with_passphrase` and `execute_lair_with_ready`
+/// for the meaning of `passphrase` and `ready`.
+pub async fn execute_lair_with_config(
+    config: Arc<Config>,
+    passphrase: Option<Vec<u8>>,
+    ready: Option<futures::channel::oneshot::Sender<()>>,
+) -> LairResult<ipc::LairShutdownReceiver> {
+    execute_lair_profiles(vec![(config, passphrase)], ready).await
+}
+
+/// Serve the entries of `store`, an embedder's own
+/// `store::EntryStoreBackend`, rather than the store file of `config`.
+/// Only the socket (or in-process channel) of `config` is used, and no
+/// pidfile is taken: keeping a single server per store is up to the
+/// embedder. A new store is initialized with `passphrase`.
+pub async fn execute_lair_with_store(
+    config: Arc<Config>,
+    store: Box<dyn store::EntryStoreBackend>,
+    passphrase: Option<Vec<u8>>,
+) -> LairResult<ipc::LairShutdownReceiver> {
+    internal::hardening::harden_process(&config);
+    let store_actor =
+        store::spawn_entry_store_actor_with_backend(config.clone(), store)
+            .await?;
+    ipc::spawn_bind_server_ipc_with_store(config, store_actor, passphrase, None)
+        .await
+}
+
+/// Main loop of lair executable, serving several profiles (see
+/// `ConfigBuilder::set_profile()`), each with its own config and
+/// optional unlock passphrase, from this one process.
+/// `ready` is sent once all of them accept connections, the returned
+/// receiver resolves once all of them have been asked to shut down.
+/// If a profile fails to start, the profiles started before it keep
+/// serving until they are shut down (or the process exits).
+pub async fn execute_lair_profiles(
+    profiles: Vec<(Arc<Config>, Option<Vec<u8>>)>,
+    ready: Option<futures::channel::oneshot::Sender<()>>,
+) -> LairResult<ipc::LairShutdownReceiver> {
+    // profiles sharing a file would clobber each other
+    for (i, (config, _)) in profiles.iter().enumerate() {
+        for (other, _) in &profiles[..i] {
+            if config.get_store_path() == other.get_store_path()
+                || config.get_socket_path() == other.get_socket_path()
+                || config.get_pid_path() == other.get_pid_path()
+                || (config.get_tcp_bind().is_some()
+                    && config.get_tcp_bind() == other.get_tcp_bind())
+            {
+                return Err(format!(
+                    "profiles {:?} and {:?} share a store, socket, \
+                    tcp address or pid file",
+                    other.get_profile(),
+                    config.get_profile(),
+                )
+                .into());
+            }
+        }
+    }
+
+    // adopt a socket passed by systemd, rather than binding our own
+    // (it is served by the first profile)
+    #[cfg(unix)]
+    let mut listener = internal::socket_activation::take_listener()?;
+    #[cfg(not(unix))]
+    let mut listener = None;
+
+    let mut shutdowns = Vec::new();
+    for (config, passphrase) in profiles {
+        // before the profile creates any file
+        internal::hardening::harden_process(&config);
+        println!("#lair-keystore-dir:{:?}#", config.get_root_path());
+        if let Some(profile) = config.get_profile() {
+            println!("#lair-keystore-profile:{}#", profile);
+        }
+
+        let res = if config.get_ephemeral() {
+            // no pid file, nothing but this process can reach the store
+            match store::spawn_ephemeral_entry_store_actor(config.clone()).await
+            {
+                Ok(store_actor) => {
+                    ipc::spawn_bind_server_ipc_with_store(
+                        config.clone(),
+                        store_actor,
+                        passphrase,
+                        listener.take(),
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            let internal::pid_check::PidCheckResult { store_file } =
+                internal::pid_check::pid_check(&config)?;
+            ipc::spawn_bind_server_ipc(
+                config.clone(),
+                store_file,
+                passphrase,
+                listener.take(),
+            )
+            .await
+        };
+
+        match res {
+            Ok(shutdown) => shutdowns.push(shutdown),
+            Err(e) => {
+                let _ = internal::pid_check::pid_release(&config);
+                return Err(e);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    if let Err(err) = internal::sd_notify::notify("READY=1") {
+        tracing::warn!(?err, "failed to notify systemd of readiness");
+    }
+    if let Some(ready) = ready {
+        let _ = ready.send(());
+    }
+
+    if shutdowns.len() == 1 {
+        return Ok(shutdowns.remove(0));
+    }
+    let (shutdown_send, shutdown_recv) = futures::channel::oneshot::channel();
+    tokio::task::spawn(async move {
+        let res = futures::future::join_all(shutdowns).await;
+        // a server dropping its sender without shutting down
+        // drops ours too
+        if res.iter().all(|r| r.is_ok()) {
+            let _ = shutdown_send.send(());
+        }
+    });
+    Ok(shutdown_recv)
+}
+
+/// Initialize a new lair store in the configured lair directory,
+/// without starting the ipc server.
+pub async fn init_lair(
+    config: Arc<Config>,
+    passphrase: Vec<u8>,
+    force: bool,
+) -> LairResult<()> {
+    internal::hardening::harden_process(&config);
+    let internal::pid_check::PidCheckResult { store_file } =
+        internal::pid_check::pid_check(&config)?;
+
+    let res =
+        store::init_store_file(&config, store_file, passphrase.into(), force)
+            .await;
+
+    internal::pid_check::pid_release(&config)?;
+
+    res
+}
+
+/// Upgrade the configured lair store to the current on-disk format,
+/// keeping a backup copy of the original.
+/// Fails if a lair server process is already running against this store.
+pub async fn migrate_lair(
+    config: Arc<Config>,
+) -> LairResult<store::MigrateResult> {
+    check_writable(&config)?;
+    let internal::pid_check::PidCheckResult { store_file } =
+        internal::pid_check::pid_check(&config)?;
+    // we rewrite the file by path, don't hold it open
+    drop(store_file);
+
+    let res = store::migrate_store_file(&config).await;
+
+    internal::pid_check::pid_release(&config)?;
+
+    res
+}
+
+/// Copy the configured lair store into a new store of another kind,
+/// see `store::convert_store_file()`.
+/// Fails if a lair server process is already running against this store.
+pub async fn convert_lair(
+    config: Arc<Config>,
+    to: Arc<Config>,
+) -> LairResult<store::ConvertResult> {
+    check_writable(&config)?;
+    let internal::pid_check::PidCheckResult { store_file } =
+        internal::pid_check::pid_check(&config)?;
+
+    let res = store::convert_store_file(&config, store_file, &to).await;
+
+    internal::pid_check::pid_release(&config)?;
+
+    res
+}
+
+/// Move invalid entries out of the configured lair store,
+/// see `store::repair_store_file()`.
+/// Fails if a lair server process is already running against this store.
+pub async fn repair_lair(
+    config: Arc<Config>,
+) -> LairResult<store::RepairResult> {
+    check_writable(&config)?;
+    let internal::pid_check::PidCheckResult { store_file } =
+        internal::pid_check::pid_check(&config)?;
+    // we rewrite the file by path, don't hold it open
+    drop(store_file);
+
+    let res = store::repair_store_file(&config).await;
+
+    internal::pid_check::pid_release(&config)?;
 
-    let config = config.build();
-    
-    println!("#lair-keystore-dir:{:?}#", config.get_root_path());
+    res
+}
+
+/// Read all entries of the configured lair store into a backup,
+/// see `store::StoreBackup::encrypt()`.
+/// Fails if a lair server process is already running against this store.
+pub async fn backup_lair(
+    config: Arc<Config>,
+) -> LairResult<store::StoreBackup> {
+    let internal::pid_check::PidCheckResult { store_file } =
+        internal::pid_check::pid_check(&config)?;
+
+    let res = store::backup_store_file(&config, store_file).await;
+
+    internal::pid_check::pid_release(&config)?;
+
+    res
+}
 
+/// Write the entries of a backup into the configured lair store,
+/// see `store::restore_store_file()` for the `merge` semantics.
+/// Fails if a lair server process is already running against this store.
+pub async fn restore_lair(
+    config: Arc<Config>,
+    backup: store::StoreBackup,
+    merge: bool,
+) -> LairResult<store::RestoreResult> {
+    check_writable(&config)?;
     let internal::pid_check::PidCheckResult { store_file } =
         internal::pid_check::pid_check(&config)?;
 
-    ipc::spawn_bind_server_ipc(config, store_file).await?;
+    let res =
+        store::restore_store_file(&config, store_file, backup, merge).await;
 
+    internal::pid_check::pid_release(&config)?;
+
+    res
+}
+
+/// Commands rewriting the store by path cannot run read-only.
+fn check_writable(config: &Config) -> LairResult<()> {
+    if config.get_read_only() {
+        return Err(LairError::ReadOnly);
+    }
     Ok(())
 }
+
+/// Open the lair store directly, serving the client api locally
+/// without binding the ipc socket.
+/// `unlock_passphrase` is only called if the store entries are sealed.
+/// Fails if a lair server process is already running against this store.
+/// Call `internal::pid_check::pid_release()` when finished.
+pub async fn spawn_local_lair<P>(
+    config: Arc<Config>,
+    unlock_passphrase: P,
+) -> LairResult<ghost_actor::GhostSender<actor::LairClientApi>>
+where
+    P: FnOnce() -> LairResult<Vec<u8>>,
+{
+    let internal::pid_check::PidCheckResult { store_file } =
+        internal::pid_check::pid_check(&config)?;
+
+    match ipc::spawn_local_api(config.clone(), store_file, unlock_passphrase)
+        .await
+    {
+        Ok(api) => Ok(api),
+        Err(e) => {
+            let _ = internal::pid_check::pid_release(&config);
+            Err(e)
+        }
+    }
+}
@@ -3,30 +3,161 @@
 use crate::*;
 use entry::LairEntry;
 use futures::future::FutureExt;
+use lair_keystore_api::internal::tls;
+use lair_keystore_api::internal::util::SecretBytes;
 use lair_keystore_api::{actor::*, internal::*};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+mod version;
+pub use version::*;
+
+mod backup;
+pub use backup::*;
+
+mod check;
+pub use check::*;
+
+mod convert;
+pub use convert::*;
+
+mod backend;
+pub use backend::*;
+
+pub mod conformance;
 
 ghost_actor::ghost_chan! {
     /// persistence manager for entry storage
     pub chan EntryStore<LairError> {
         /// generate a new tls cert entry && save it && return it
+        /// (fails if the tag is already in use)
         fn tls_cert_self_signed_new_from_entropy(
             options: TlsCertOptions,
+            tag: Option<String>,
         ) -> (KeystoreIndex, Arc<LairEntry>);
 
         /// generate a new signature ed25519 keypair entry && save it && return it
-        fn sign_ed25519_keypair_new_from_entropy() ->
-            (KeystoreIndex, Arc<LairEntry>);
+        /// (fails if the tag is already in use)
+        fn sign_ed25519_keypair_new_from_entropy(
+            tag: Option<String>,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new signature ed25519 keypair entry whose
+        /// signatures must be approved (see `EntryMeta::requires_approval`)
+        /// && save it && return it
+        fn sign_ed25519_keypair_new_requiring_approval(
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// derive a signature ed25519 keypair entry from an existing seed
+        /// && save it && return it
+        /// (fails with `LairError::DuplicatePubKey` if the pub key is
+        /// already present, unless allow_duplicate)
+        fn sign_ed25519_keypair_new_from_seed(
+            seed: Vec<u8>,
+            allow_duplicate: bool,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
 
         /// generate a new x25519 keypair entry && save it && return it
         fn x25519_keypair_new_from_entropy() -> (KeystoreIndex, Arc<LairEntry>);
 
+        /// import an x25519 keypair entry from an existing private key
+        /// && save it && return it
+        /// (fails with `LairError::DuplicatePubKey` if the pub key is
+        /// already present)
+        fn x25519_keypair_new_from_priv_key(
+            priv_key: Vec<u8>,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new secp256k1 signature keypair entry
+        /// && save it && return it
+        fn secp256k1_keypair_new_from_entropy(
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new bls12-381 signature keypair entry
+        /// && save it && return it
+        /// (fails without the `bls` feature)
+        fn bls12381_keypair_new_from_entropy(
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new master seed entry && save it && return it
+        fn seed_new_from_entropy(
+            exportable: bool,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// create a master seed entry from existing seed bytes
+        /// && save it && return it
+        /// (fails with `LairError::DuplicatePubKey` if the seed is
+        /// already present)
+        fn seed_import(
+            seed: Vec<u8>,
+            exportable: bool,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new xchacha20-poly1305 secret key entry
+        /// && save it && return it
+        fn secret_key_new_from_entropy() -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// derive the signature ed25519 keypair at `derivation_path`
+        /// from the master seed entry at `seed_index`
+        /// && save it (recording the derivation in its metadata)
+        /// && return it
+        /// (resolves to the existing entry if the keypair is already present)
+        fn sign_ed25519_keypair_derive_from_seed(
+            seed_index: KeystoreIndex,
+            derivation_path: Vec<u32>,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// convert the signature ed25519 keypair entry at `sign_index`
+        /// to an x25519 keypair && save it (recording the conversion
+        /// in its metadata) && return it
+        /// (resolves to the existing entry if the keypair is already present)
+        fn x25519_keypair_convert_from_sign_ed25519(
+            sign_index: KeystoreIndex,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// issue a new self-signed cert for the private key of the tls
+        /// cert entry at `cert_index` && save it (recording the renewal
+        /// in its metadata) && return it
+        /// the new cert takes over the sni lookup
+        fn tls_cert_renew(
+            cert_index: KeystoreIndex,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new self-signed tls CA cert entry
+        /// && save it && return it
+        fn tls_ca_new_self_signed(
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// generate a new tls cert entry signed by the tls CA cert
+        /// entry at `ca_index` && save it && return it
+        /// (along with the CA cert)
+        fn tls_cert_new_signed_by_ca(
+            ca_index: KeystoreIndex,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, Arc<LairEntry>, Cert);
+
+        /// import an externally issued tls cert && its private key
+        /// as a new entry && save it && return it
+        /// (fails if the cert is already in the keystore)
+        fn tls_cert_import(
+            cert_der: Cert,
+            priv_key_der: CertPrivKey,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, Arc<LairEntry>);
+
         /// fetch the highest / most recently added keystore_index
         fn get_last_entry_index() -> KeystoreIndex;
 
         /// fetch an entry from the store by keystore index
+        /// fails with `LairError::EntryDeleted` for deleted entries,
+        /// and `LairError::EntryCorrupt` for corrupt entries
         fn get_entry_by_index(index: KeystoreIndex) -> Arc<LairEntry>;
 
+        /// fetch all entries in the store, with their metadata,
+        /// ordered by keystore index
+        fn list_entries() -> Vec<(KeystoreIndex, Arc<LairEntry>, entry::EntryMeta)>;
+
         /// fetch an entry by its 32 byte public identifier
         /// for kepair, this is the pub key
         /// for tls cert, this is the digest
@@ -34,23 +165,290 @@ ghost_actor::ghost_chan! {
 
         /// get a tls cert entry by sni
         fn get_entry_by_sni(sni: CertSni) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// get the tls cert entry whose digest starts with the prefix
+        /// fails listing the candidates if more than one matches
+        fn get_entry_by_digest_prefix(prefix: Arc<Vec<u8>>) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// find an entry by pub id (see `get_entry_by_pub_id`)
+        /// or, failing that, by tls cert sni
+        /// resolves to None if nothing matches
+        fn find_entry(id: Arc<Vec<u8>>) -> Option<(KeystoreIndex, Arc<LairEntry>)>;
+
+        /// get an entry by tag
+        fn get_entry_by_tag(tag: String) -> (KeystoreIndex, Arc<LairEntry>);
+
+        /// fetch the metadata of an entry by keystore index
+        /// fails with `LairError::EntryDeleted` for deleted entries
+        fn get_entry_meta(index: KeystoreIndex) -> entry::EntryMeta;
+
+        /// overwrite an entry with a tombstone && stop tracking it
+        /// (its index is never reused)
+        fn delete_entry(index: KeystoreIndex) -> ();
+
+        /// rewrite an entry with a new tag && move the tag lookup
+        /// (fails if the tag is already in use)
+        fn update_entry_tag(index: KeystoreIndex, tag: String) -> ();
+
+        /// rewrite an entry with its own signatures per second limit
+        /// (`None` for the server's `entry_rate_limit`)
+        fn update_entry_rate_limit(
+            index: KeystoreIndex,
+            rate_limit: Option<u32>,
+        ) -> ();
+
+        /// reserve `count` signatures with an entry's private key,
+        /// returns how long to wait before signing,
+        /// fails with `LairError::RateLimited` if more would wait than
+        /// `Config::get_rate_limit_queue()` allows
+        fn reserve_signatures(
+            index: KeystoreIndex,
+            count: u32,
+        ) -> std::time::Duration;
+
+        /// count a use of an entry's private key
+        /// (unknown / deleted entries are ignored)
+        /// the new counts are written to disk by the next usage flush
+        fn record_entry_use(index: KeystoreIndex) -> ();
+
+        /// write any usage counts not yet on disk
+        /// (this also happens every `USAGE_FLUSH_INTERVAL`,
+        /// and on `flush_and_close`)
+        fn flush_entry_usage() -> ();
+
+        /// whether the store entries are sealed, and if they are,
+        /// whether the store has been unlocked
+        fn lock_state() -> StoreLockState;
+
+        /// the number of entries that failed their integrity check,
+        /// as the store was loaded or unlocked
+        fn corrupt_entry_count() -> u64;
+
+        /// receive the entries created / deleted from now on
+        /// (subscribers too slow to keep up miss changes, see
+        /// `STORE_CHANGES_CAPACITY`)
+        fn subscribe_changes() -> tokio::sync::broadcast::Receiver<StoreChange>;
+
+        /// rewrite the store without its deleted entries, keeping the
+        /// index of every other entry, writes wait for it to finish
+        fn compact_store() -> LairCompactInfo;
+
+        /// verify the given passphrase against the store unlock entry,
+        /// and unseal the store entries with the key it derives
+        /// (an ephemeral or embedded store without unlock passphrase is
+        /// initialized with it)
+        fn unlock(passphrase: SecretBytes) -> ();
+
+        /// forget the store key and the unsealed entries, so they
+        /// cannot be used until the store is unlocked again
+        /// (a store that does not seal its entries cannot be locked)
+        fn lock() -> ();
+
+        /// the number of unlock attempts with a wrong passphrase
+        fn failed_unlock_count() -> u64;
+
+        /// verify the old passphrase and replace the store unlock entry
+        /// with one derived from the new passphrase
+        fn change_unlock_passphrase(
+            old_passphrase: SecretBytes,
+            new_passphrase: SecretBytes,
+        ) -> ();
+
+        /// write usage counts, wait for pending writes,
+        /// sync and close the store file
+        /// any further writes will fail
+        fn flush_and_close() -> ();
     }
 }
 
 ghost_actor::ghost_chan! {
     chan EntryStoreInternal<LairError> {
+        fn claim_pub_id(id: Arc<Vec<u8>>) -> ();
+
+        fn release_pub_id(id: Arc<Vec<u8>>) -> ();
+
+        fn claim_derived_pub_id(
+            id: Arc<Vec<u8>>,
+        ) -> Option<(KeystoreIndex, Arc<LairEntry>)>;
+
         fn finalize_new_entry(
             entry_index: KeystoreIndex,
             entry: Arc<LairEntry>,
+            meta: entry::EntryMeta,
+        ) -> ();
+
+        fn release_tag(tag: String) -> ();
+
+        fn finalize_update_entry_tag(
+            entry_index: KeystoreIndex,
+            tag: String,
+        ) -> ();
+
+        fn finalize_update_entry_rate_limit(
+            entry_index: KeystoreIndex,
+            rate_limit: Option<u32>,
+        ) -> ();
+
+        fn release_rewrite(entry_index: KeystoreIndex) -> ();
+
+        fn set_unlock(unlock: entry::EntryUnlock) -> ();
+
+        fn finish_unlock(key: Arc<entry::EntryStoreKey>) -> ();
+
+        fn finalize_delete_entry(entry_index: KeystoreIndex) -> ();
+
+        /// track the (sealed) entries reloaded once the key is forgotten
+        fn finish_lock(entries: Vec<(KeystoreIndex, Vec<u8>)>) -> ();
+
+        /// lock the store if it has not been used for `idle`,
+        /// returning how long until it might be
+        fn lock_if_idle(idle: std::time::Duration) -> std::time::Duration;
+
+        fn start_usage_flush() -> ();
+
+        fn finalize_usage_flush(
+            entry_indexes: Vec<KeystoreIndex>,
+            success: bool,
         ) -> ();
     }
 }
 
+/// Whether the entries of a store are sealed at rest,
+/// and if they are, whether the store has been unlocked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreLockState {
+    /// The store was never initialized with an unlock passphrase,
+    /// its entries are not sealed.
+    NoPassphrase,
+
+    /// The store entries are sealed, they cannot be used
+    /// until the store is unlocked with its passphrase.
+    Locked,
+
+    /// The store entries are sealed, and the store is unlocked.
+    Unlocked,
+}
+
+impl From<StoreLockState> for LairLockState {
+    fn from(state: StoreLockState) -> Self {
+        match state {
+            StoreLockState::NoPassphrase => LairLockState::NoPassphrase,
+            StoreLockState::Locked => LairLockState::Locked,
+            StoreLockState::Unlocked => LairLockState::Unlocked,
+        }
+    }
+}
+
+/// A change to the entries of a store, see `subscribe_changes`.
+#[derive(Debug, Clone)]
+pub enum StoreChange {
+    /// A new entry was written to the store.
+    EntryCreated(KeystoreIndex, Arc<LairEntry>),
+
+    /// An entry was deleted from the store.
+    EntryDeleted(KeystoreIndex),
+
+    /// The store was locked, its entries cannot be used until it is
+    /// unlocked again.
+    Locked,
+
+    /// The store was unlocked.
+    Unlocked,
+}
+
+/// How many store changes a subscriber may fall behind by before it
+/// misses some.
+pub const STORE_CHANGES_CAPACITY: usize = 256;
+
+/// How long an unlock attempt with a wrong passphrase is held up before
+/// it fails, doubling with every further wrong passphrase in a row, up
+/// to `UNLOCK_RETRY_DELAY_MAX`. Attempts are made one at a time.
+pub const UNLOCK_RETRY_DELAY: std::time::Duration =
+    std::time::Duration::from_millis(100);
+
+/// see `UNLOCK_RETRY_DELAY`
+pub const UNLOCK_RETRY_DELAY_MAX: std::time::Duration =
+    std::time::Duration::from_secs(10);
+
+/// How often usage counts recorded by `record_entry_use` are written
+/// to disk. At most this much usage is lost if lair is killed.
+pub const USAGE_FLUSH_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(5);
+
 /// Spawn a new entry store actor.
 pub async fn spawn_entry_store_actor(
     config: Arc<Config>,
     store_file: tokio::fs::File,
 ) -> LairResult<ghost_actor::GhostSender<EntryStore>> {
+    let store_file = spawn_store_backend(&config, store_file).await?;
+    spawn_store_actor(config, store_file, false).await
+}
+
+/// Spawn a new entry store actor over an empty in-memory store, for
+/// ephemeral configs (see `ConfigBuilder::set_ephemeral()`).
+/// The store is dropped once it is closed, see `flush_and_close`.
+pub async fn spawn_ephemeral_entry_store_actor(
+    config: Arc<Config>,
+) -> LairResult<ghost_actor::GhostSender<EntryStore>> {
+    spawn_entry_store_actor_with_backend(
+        config,
+        Box::new(MemoryEntryStore::default()),
+    )
+    .await
+}
+
+/// Spawn a new entry store actor keeping its entries in `backend`.
+/// A new store is initialized by the first unlock passphrase,
+/// there is no `init_store_file()` for it.
+pub async fn spawn_entry_store_actor_with_backend(
+    config: Arc<Config>,
+    backend: Box<dyn EntryStoreBackend>,
+) -> LairResult<ghost_actor::GhostSender<EntryStore>> {
+    let store_file = backend::spawn_entry_store_backend_task(backend);
+    spawn_store_actor(config, store_file, true).await
+}
+
+/// The process-wide settings, mlocking secrets and the inline crypto
+/// threshold, as applied by the first store spawned in this process.
+static PROCESS_SETTINGS: once_cell::sync::OnceCell<(bool, usize)> =
+    once_cell::sync::OnceCell::new();
+
+async fn spawn_store_actor(
+    config: Arc<Config>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    init_on_unlock: bool,
+) -> LairResult<ghost_actor::GhostSender<EntryStore>> {
+    // before the store holds any secrets, once: stores spawned later
+    // must not change how the secrets of earlier ones are held
+    let settings = (
+        config.get_mlock_secrets(),
+        config.get_inline_crypto_threshold(),
+    );
+    let applied = *PROCESS_SETTINGS.get_or_init(|| {
+        util::set_mem_lock_enabled(settings.0);
+        set_inline_crypto_threshold(settings.1);
+        settings
+    });
+    if applied != settings {
+        tracing::warn!(
+            mlock_secrets = applied.0,
+            inline_crypto_threshold = applied.1,
+            "process-wide settings already applied, \
+            mlock_secrets and inline_crypto_threshold ignored"
+        );
+    }
+    // the first server of the process sizes the crypto pool, unless
+    // crypto work was done before, building the default pool
+    let threads = config.get_crypto_threads();
+    if !init_once_rayon_thread_pool(|| new_crypto_thread_pool(threads))
+        && crypto_pool_stats().threads != threads
+    {
+        tracing::warn!(
+            threads = crypto_pool_stats().threads,
+            "crypto thread pool already running, crypto_threads ignored"
+        );
+    }
+
     let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
     let sender = builder
@@ -63,83 +461,409 @@ pub async fn spawn_entry_store_actor(
         .create_channel::<EntryStoreInternal>()
         .await?;
 
+    let auto_lock = config.get_auto_lock_after_idle();
     tokio::task::spawn(
-        builder.spawn(EntryStoreImpl::new(i_s, config, store_file).await?),
+        builder.spawn(
+            EntryStoreImpl::new(
+                i_s.clone(),
+                config,
+                store_file,
+                init_on_unlock,
+            )
+            .await?,
+        ),
     );
 
+    if let Some(idle) = auto_lock {
+        tokio::task::spawn(async move {
+            let mut wait = idle;
+            loop {
+                tokio::time::sleep(wait).await;
+                wait = match i_s.lock_if_idle(idle).await {
+                    Ok(wait) => wait,
+                    // the store actor has shut down
+                    Err(LairError::GhostError(_)) => break,
+                    Err(err) => {
+                        tracing::warn!(?err, "failed to lock the idle store");
+                        idle
+                    }
+                };
+            }
+        });
+    }
+
+    let flush_sender = sender.clone();
+    tokio::task::spawn(async move {
+        loop {
+            tokio::time::sleep(USAGE_FLUSH_INTERVAL).await;
+            match flush_sender.flush_entry_usage().await {
+                Ok(()) => (),
+                // the store actor has shut down
+                Err(LairError::GhostError(_)) => break,
+                Err(err) => {
+                    tracing::warn!(?err, "failed to write entry usage")
+                }
+            }
+        }
+    });
+
     Ok(sender)
 }
 
+/// Initialize a store with unlock material derived from `passphrase`.
+/// Refuses to touch an already initialized store unless `force` is set,
+/// in which case any existing entries are discarded.
+pub async fn init_store_file(
+    config: &Config,
+    store_file: tokio::fs::File,
+    passphrase: SecretBytes,
+    force: bool,
+) -> LairResult<()> {
+    let store_file = spawn_store_backend(config, store_file).await?;
+
+    if store_file.init_load_unlock().await?.is_some() && !force {
+        return Err(LairError::StoreAlreadyInitialized);
+    }
+
+    // the store is empty, there is nothing to seal yet
+    let (unlock, _) = entry::EntryUnlock::new_from_passphrase(
+        passphrase,
+        config.get_unlock_kdf(),
+    )
+    .await?;
+
+    store_file.truncate().await?;
+    store_file.write_unlock(unlock.encode()?).await?;
+
+    Ok(())
+}
+
 // -- internal -- //
 
+mod layout;
+
+mod rate_limit;
+use rate_limit::RateLimits;
+
 mod store_file;
 use store_file::EntryStoreFileSender;
 
+mod store_memory;
+pub use store_memory::MemoryEntryStore;
+
+#[cfg(feature = "sqlite")]
+mod store_sqlite;
+
+/// Spawn the task serving reads / writes of the configured kind of store.
+/// `store_file` is the store file as opened by `pid_check()`,
+/// a sqlite store is opened again by path.
+/// Stores of read-only configs are never written.
+async fn spawn_store_backend(
+    config: &Config,
+    store_file: tokio::fs::File,
+) -> LairResult<futures::channel::mpsc::Sender<store_file::EntryStoreFile>> {
+    match config.get_store_kind() {
+        StoreKind::File => {
+            store_file::spawn_entry_store_file_task(
+                store_file,
+                config.get_store_path().to_owned(),
+                config.get_read_only(),
+            )
+            .await
+        }
+        #[cfg(feature = "sqlite")]
+        StoreKind::Sqlite => {
+            drop(store_file);
+            store_sqlite::spawn_entry_store_sqlite_task(
+                config.get_store_path().to_owned(),
+                config.get_read_only(),
+            )
+            .await
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StoreKind::Sqlite => Err(sqlite_not_enabled()),
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_not_enabled() -> LairError {
+    "sqlite stores require the `sqlite` feature".into()
+}
+
+/// Tallies the sealed entries opened while resealing a store.
+/// Entries failing to open are corrupt, and left as they are for the
+/// store to report, unless no sealed entry opens: then the key is wrong.
+#[derive(Default)]
+struct SealedOpenCount {
+    opened: usize,
+    failed: usize,
+}
+
+impl SealedOpenCount {
+    fn open<T>(&mut self, sealed: bool, res: LairResult<T>) -> Option<T> {
+        match res {
+            Ok(r) => {
+                if sealed {
+                    self.opened += 1;
+                }
+                Some(r)
+            }
+            Err(err) => {
+                tracing::warn!(?err, "skipping corrupt entry on reseal");
+                self.failed += 1;
+                None
+            }
+        }
+    }
+
+    fn check(&self) -> LairResult<()> {
+        if self.failed > 0 && self.opened == 0 {
+            return Err("no sealed entry opens with the store key".into());
+        }
+        Ok(())
+    }
+}
+
+/// Unlock attempts, made one at a time, each wrong passphrase holding
+/// up the next attempt (see `UNLOCK_RETRY_DELAY`).
+#[derive(Clone, Default)]
+struct UnlockAttempts {
+    /// wrong passphrases in a row, locked for the attempt in flight
+    in_a_row: Arc<tokio::sync::Mutex<u32>>,
+    failed: Arc<std::sync::atomic::AtomicU64>,
+}
+
+impl UnlockAttempts {
+    async fn attempt<F>(self, unlock: F) -> LairResult<()>
+    where
+        F: std::future::Future<Output = LairResult<()>>,
+    {
+        let mut in_a_row = self.in_a_row.lock().await;
+        let res = unlock.await;
+        match &res {
+            Ok(()) => *in_a_row = 0,
+            Err(LairError::WrongPassphrase) => {
+                self.failed
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let delay = UNLOCK_RETRY_DELAY
+                    .checked_mul(1 << std::cmp::min(*in_a_row, 16))
+                    .map_or(UNLOCK_RETRY_DELAY_MAX, |d| {
+                        std::cmp::min(d, UNLOCK_RETRY_DELAY_MAX)
+                    });
+                *in_a_row += 1;
+                tokio::time::sleep(delay).await;
+            }
+            Err(_) => (),
+        }
+        res
+    }
+
+    fn failed_count(&self) -> u64 {
+        self.failed.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// `path`, or `path` with a numeric suffix if it already exists,
+/// so we don't clobber backups / quarantine files from earlier runs.
+fn unused_path(path: std::path::PathBuf) -> std::path::PathBuf {
+    let mut out = path.clone();
+    let mut i = 1;
+    while out.exists() {
+        let mut name = path.clone().into_os_string();
+        name.push(format!(".{}", i));
+        out = name.into();
+        i += 1;
+    }
+    out
+}
+
+/// Write `data` to a new file at `path` and sync it to disk.
+async fn write_synced(path: &std::path::Path, data: &[u8]) -> LairResult<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(LairError::other)?;
+    file.write_all(data).await.map_err(LairError::other)?;
+    file.sync_all().await.map_err(LairError::other)
+}
+
+/// Resolves once a usage flush has been written (or has failed).
+type UsageFlush = futures::future::Shared<
+    futures::future::BoxFuture<'static, Result<(), String>>,
+>;
+
 struct EntryStoreImpl {
     i_s: ghost_actor::GhostSender<EntryStoreInternal>,
-    #[allow(dead_code)]
     config: Arc<Config>,
     store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    /// a store without unlock passphrase gets one as it is first unlocked
+    init_on_unlock: bool,
+    unlock: Option<entry::EntryUnlock>,
+    /// the key sealing the store entries, once unlocked
+    store_key: Option<Arc<entry::EntryStoreKey>>,
+    /// sealed entries, not yet decoded as the store is locked
+    sealed: HashMap<KeystoreIndex, Vec<u8>>,
     last_entry_index: KeystoreIndex,
     entries_by_index: HashMap<KeystoreIndex, Arc<LairEntry>>,
+    deleted: HashSet<KeystoreIndex>,
+    /// entries failing their integrity check
+    corrupt: HashSet<KeystoreIndex>,
+    /// entries by pub key, cert digest, seed id or secret key tag, as
+    /// probed by clients: keep the randomly keyed hasher of `HashMap`
+    /// (see `util::ct_eq()`)
     #[allow(clippy::rc_buffer)]
     entries_by_pub_id: HashMap<Arc<Vec<u8>>, (KeystoreIndex, Arc<LairEntry>)>,
     entries_by_sni: HashMap<CertSni, (KeystoreIndex, Arc<LairEntry>)>,
+    /// cert digests in order, for finding them by prefix
+    #[allow(clippy::rc_buffer)]
+    certs_by_digest: BTreeMap<Arc<Vec<u8>>, KeystoreIndex>,
+    entries_by_tag: HashMap<String, (KeystoreIndex, Arc<LairEntry>)>,
+    meta_by_index: HashMap<KeystoreIndex, entry::EntryMeta>,
+    /// tags of entries that are still being created
+    pending_tags: HashSet<String>,
+    /// pub ids of entries that are still being imported
+    #[allow(clippy::rc_buffer)]
+    pending_pub_ids: HashSet<Arc<Vec<u8>>>,
+    /// entries with a delete / update in flight
+    pending_rewrites: HashSet<KeystoreIndex>,
+    /// entries with usage counts not yet written to disk
+    usage_dirty: HashSet<KeystoreIndex>,
+    /// the entries being written by the usage flush in flight, if any
+    usage_flush: Option<(HashSet<KeystoreIndex>, UsageFlush)>,
+    /// entries created / deleted, for `subscribe_changes`
+    changes: tokio::sync::broadcast::Sender<StoreChange>,
+    unlock_attempts: UnlockAttempts,
+    rate_limits: RateLimits,
+    /// when the entries were last used, for `lock_if_idle`
+    last_use: std::time::Instant,
 }
 
 impl EntryStoreImpl {
     pub async fn new(
         i_s: ghost_actor::GhostSender<EntryStoreInternal>,
         config: Arc<Config>,
-        store_file: tokio::fs::File,
+        store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+        init_on_unlock: bool,
     ) -> LairResult<Self> {
-        let store_file =
-            store_file::spawn_entry_store_file_task(store_file).await?;
-
-        match store_file.init_load_unlock().await? {
+        let unlock = match store_file.init_load_unlock().await? {
+            None if config.get_read_only() => None,
             None => {
                 // write a STUB unlock entry of all zeroes for now
                 let unlock_entry = vec![0_u8; entry::ENTRY_SIZE];
                 store_file.write_unlock(unlock_entry).await?;
+                None
             }
-            Some(_unlock_entry) => {
-                // someday, do some crypto stuff to read other entries
-            }
-        }
+            // someday, do some crypto stuff to read other entries
+            Some(unlock_entry) => entry::EntryUnlock::decode(&unlock_entry)?,
+        };
 
+        let rate_limits = RateLimits::new(&config);
         let mut out = Self {
             i_s,
             config,
             store_file,
+            init_on_unlock,
+            unlock,
+            store_key: None,
+            sealed: HashMap::new(),
             last_entry_index: 0.into(),
             entries_by_index: HashMap::new(),
+            deleted: HashSet::new(),
+            corrupt: HashSet::new(),
             entries_by_pub_id: HashMap::new(),
             entries_by_sni: HashMap::new(),
+            certs_by_digest: BTreeMap::new(),
+            entries_by_tag: HashMap::new(),
+            meta_by_index: HashMap::new(),
+            pending_tags: HashSet::new(),
+            pending_pub_ids: HashSet::new(),
+            pending_rewrites: HashSet::new(),
+            usage_dirty: HashSet::new(),
+            usage_flush: None,
+            changes: tokio::sync::broadcast::channel(STORE_CHANGES_CAPACITY).0,
+            unlock_attempts: UnlockAttempts::default(),
+            rate_limits,
+            last_use: std::time::Instant::now(),
         };
 
-        // load / decode all entries
-        for (entry_index, entry) in out.store_file.load_all_entries().await? {
-            let entry = Arc::new(entry::LairEntry::decode(&entry)?);
-            out.track_new_entry(entry_index, entry);
-            if entry_index.0 > out.last_entry_index.0 {
-                out.last_entry_index = entry_index;
+        let entries = out.store_file.load_all_entries().await?;
+        out.track_loaded_entries(entries);
+
+        Ok(out)
+    }
+
+    /// Track the entries loaded from the store file, sealed entries
+    /// are decoded once the store is unlocked.
+    /// (corrupt entries are reported, not fatal, so the rest of
+    /// the store stays usable)
+    fn track_loaded_entries(&mut self, entries: Vec<(KeystoreIndex, Vec<u8>)>) {
+        for (entry_index, entry) in entries {
+            if entry_index.0 > self.last_entry_index.0 {
+                self.last_entry_index = entry_index;
+            }
+            if entry::EntryDeleted::is_deleted(&entry) {
+                self.deleted.insert(entry_index);
+                continue;
+            }
+            if entry::LairEntry::is_sealed(&entry) {
+                self.sealed.insert(entry_index, entry);
+                continue;
+            }
+            match entry::LairEntry::decode_with_meta(&entry) {
+                Ok((entry, meta)) => {
+                    self.track_new_entry(entry_index, Arc::new(entry), meta)
+                }
+                Err(err) => self.track_corrupt_entry(entry_index, err),
             }
         }
+    }
 
-        Ok(out)
+    fn track_corrupt_entry(
+        &mut self,
+        entry_index: KeystoreIndex,
+        err: LairError,
+    ) {
+        tracing::error!(%entry_index, ?err, "corrupt keystore entry");
+        self.corrupt.insert(entry_index);
     }
 
     fn track_new_entry(
         &mut self,
         entry_index: KeystoreIndex,
         entry: Arc<LairEntry>,
+        mut meta: entry::EntryMeta,
     ) {
         self.entries_by_index.insert(entry_index, entry.clone());
 
+        if let Some(tag) = meta.tag.take() {
+            self.pending_tags.remove(&tag);
+            match self.entries_by_tag.get(&tag) {
+                // creation checks for this, but the store file
+                // may have been merged / edited behind our back
+                Some((idx, _)) => tracing::warn!(
+                    "ignoring duplicate tag {:?} on entry {}, \
+                    already in use by entry {}",
+                    tag,
+                    entry_index,
+                    idx
+                ),
+                None => {
+                    self.entries_by_tag
+                        .insert(tag.clone(), (entry_index, entry.clone()));
+                    meta.tag = Some(tag);
+                }
+            }
+        }
+        if meta != entry::EntryMeta::default() {
+            self.meta_by_index.insert(entry_index, meta);
+        }
+
         match &*entry {
             LairEntry::TlsCert(e) => {
                 self.entries_by_sni
                     .insert(e.sni.clone(), (entry_index, entry.clone()));
+                self.certs_by_digest
+                    .insert(e.cert_digest.0.clone(), entry_index);
                 self.entries_by_pub_id
                     .insert(e.cert_digest.0.clone(), (entry_index, entry));
             }
@@ -153,6 +877,23 @@ impl EntryStoreImpl {
                     (entry_index, entry),
                 );
             }
+            LairEntry::Seed(e) => {
+                self.entries_by_pub_id
+                    .insert(e.seed_id.0.clone(), (entry_index, entry));
+            }
+            LairEntry::SecretKey(e) => {
+                self.entries_by_pub_id
+                    .insert(e.tag.0.clone(), (entry_index, entry));
+            }
+            LairEntry::SignSecp256k1(e) => {
+                self.entries_by_pub_id
+                    .insert(e.pub_key.pub_id(), (entry_index, entry));
+            }
+            #[cfg(feature = "bls")]
+            LairEntry::SignBls12381(e) => {
+                self.entries_by_pub_id
+                    .insert(e.pub_key.pub_id(), (entry_index, entry));
+            }
             _ => {
                 tracing::warn!(
                     "silently ignoring unhandled entry type {:?}",
@@ -167,179 +908,4568 @@ impl EntryStoreImpl {
     }
 }
 
-impl ghost_actor::GhostControlHandler for EntryStoreImpl {}
+impl EntryStoreImpl {
+    fn untrack_entry(&mut self, entry_index: KeystoreIndex) {
+        let entry = match self.entries_by_index.remove(&entry_index) {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.deleted.insert(entry_index);
+        self.usage_dirty.remove(&entry_index);
 
-impl ghost_actor::GhostHandler<EntryStore> for EntryStoreImpl {}
+        if let Some(meta) = self.meta_by_index.remove(&entry_index) {
+            if let Some(tag) = meta.tag {
+                self.entries_by_tag.remove(&tag);
+            }
+        }
 
-impl EntryStoreHandler for EntryStoreImpl {
-    fn handle_tls_cert_self_signed_new_from_entropy(
-        &mut self,
-        options: TlsCertOptions,
-    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        Ok(
-            new_tls_cert(self.i_s.clone(), self.store_file.clone(), options)
-                .boxed()
-                .into(),
-        )
+        // a duplicate entry may have claimed the lookup since
+        let mut untrack_pub_id = |pub_id: Arc<Vec<u8>>| {
+            if let Some((idx, _)) = self.entries_by_pub_id.get(&pub_id) {
+                if *idx == entry_index {
+                    self.entries_by_pub_id.remove(&pub_id);
+                }
+            }
+        };
+        match &*entry {
+            LairEntry::TlsCert(e) => {
+                untrack_pub_id(e.cert_digest.0.clone());
+                if self.certs_by_digest.get(&e.cert_digest.0)
+                    == Some(&entry_index)
+                {
+                    self.certs_by_digest.remove(&e.cert_digest.0);
+                }
+                if let Some((idx, _)) = self.entries_by_sni.get(&e.sni) {
+                    if *idx == entry_index {
+                        self.entries_by_sni.remove(&e.sni);
+                        // the newest remaining cert with the same sni
+                        // (e.g. the one this cert was renewed from)
+                        // takes the lookup back
+                        let newest = self
+                            .entries_by_index
+                            .iter()
+                            .filter(|(_, entry)| match &***entry {
+                                LairEntry::TlsCert(o) => o.sni == e.sni,
+                                _ => false,
+                            })
+                            .max_by_key(|(idx, _)| idx.0);
+                        if let Some((idx, entry)) = newest {
+                            self.entries_by_sni
+                                .insert(e.sni.clone(), (*idx, entry.clone()));
+                        }
+                    }
+                }
+            }
+            LairEntry::SignEd25519(e) => untrack_pub_id(e.pub_key.0.clone()),
+            LairEntry::X25519(e) => {
+                untrack_pub_id(Arc::new(e.pub_key.to_bytes().to_vec()))
+            }
+            LairEntry::Seed(e) => untrack_pub_id(e.seed_id.0.clone()),
+            LairEntry::SecretKey(e) => untrack_pub_id(e.tag.0.clone()),
+            LairEntry::SignSecp256k1(e) => untrack_pub_id(e.pub_key.pub_id()),
+            #[cfg(feature = "bls")]
+            LairEntry::SignBls12381(e) => untrack_pub_id(e.pub_key.pub_id()),
+            _ => (),
+        }
     }
 
-    fn handle_sign_ed25519_keypair_new_from_entropy(
+    /// Reserve `tag` (if any) for an entry about to be created,
+    /// returning the metadata (stamped with the creation time)
+    /// to store with it.
+    /// Release it with `release_tag()` if creating the entry fails.
+    fn reserve_tag(
         &mut self,
-    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        Ok(
-            new_sign_ed25519_keypair(self.i_s.clone(), self.store_file.clone())
-                .boxed()
-                .into(),
-        )
+        tag: Option<String>,
+    ) -> LairResult<entry::EntryMeta> {
+        if let Some(tag) = &tag {
+            self.claim_tag(tag)?;
+        }
+        Ok(entry::EntryMeta::new_created_now(tag))
     }
 
-    fn handle_x25519_keypair_new_from_entropy(
-        &mut self,
-    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        Ok(
-            new_x25519_keypair(self.i_s.clone(), self.store_file.clone())
-                .boxed()
-                .into(),
-        )
+    fn claim_tag(&mut self, tag: &str) -> LairResult<()> {
+        entry::EntryMeta::check_tag(tag)?;
+        if self.entries_by_tag.contains_key(tag)
+            || self.pending_tags.contains(tag)
+        {
+            return Err(LairError::EntryTagInUse(tag.to_string()));
+        }
+        self.pending_tags.insert(tag.to_string());
+        Ok(())
     }
 
-    fn handle_get_last_entry_index(
-        &mut self,
-    ) -> EntryStoreHandlerResult<KeystoreIndex> {
-        let idx = self.last_entry_index;
-        Ok(async move { Ok(idx) }.boxed().into())
+    /// Claim exclusive access to rewrite an existing entry,
+    /// so a concurrent delete / update can't overwrite the result.
+    /// Release it with `release_rewrite()` if the rewrite fails.
+    fn claim_rewrite(&mut self, index: KeystoreIndex) -> LairResult<()> {
+        if self.deleted.contains(&index) {
+            return Err(LairError::EntryDeleted(index.0));
+        }
+        if !self.entries_by_index.contains_key(&index) {
+            return Err(LairError::EntryNotFound(index.0));
+        }
+        if !self.pending_rewrites.insert(index) {
+            return Err(format!(
+                "entry {} is already being modified, try again",
+                index
+            )
+            .into());
+        }
+        Ok(())
     }
 
-    fn handle_get_entry_by_index(
-        &mut self,
-        index: KeystoreIndex,
-    ) -> EntryStoreHandlerResult<Arc<LairEntry>> {
-        match self.entries_by_index.get(&index) {
-            Some(entry) => {
-                let entry = entry.clone();
-                Ok(async move { Ok(entry) }.boxed().into())
+    fn lock_state(&self) -> StoreLockState {
+        match &self.unlock {
+            _ if self.store_key.is_some() => StoreLockState::Unlocked,
+            Some(_) => StoreLockState::Locked,
+            _ if !self.sealed.is_empty() => StoreLockState::Locked,
+            None => StoreLockState::NoPassphrase,
+        }
+    }
+
+    /// Entries cannot be used (or written) while the store is locked.
+    /// Otherwise, they are about to be, see `lock_if_idle`.
+    fn check_unlocked(&mut self) -> LairResult<()> {
+        match self.lock_state() {
+            StoreLockState::Locked => Err(LairError::StoreLocked),
+            _ => {
+                self.last_use = std::time::Instant::now();
+                Ok(())
             }
-            None => Err(format!("invalid KeystoreIndex: {}", index).into()),
         }
     }
 
+    /// Forget the store key, then reload the entries from the store
+    /// file, still sealed. Writes in flight either made it before the
+    /// key was forgotten, or fail as the store is locked.
+    fn lock(
+        &mut self,
+    ) -> LairResult<futures::future::BoxFuture<'static, LairResult<()>>> {
+        match self.lock_state() {
+            StoreLockState::Unlocked => (),
+            StoreLockState::Locked => {
+                return Ok(async move { Ok(()) }.boxed());
+            }
+            StoreLockState::NoPassphrase => {
+                return Err("this store does not seal its entries, \
+                    it cannot be locked"
+                    .into());
+            }
+        }
+        // usage counts are written while the key is still known
+        let flush = self.handle_flush_entry_usage()?;
+        self.store_key = None;
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        Ok(async move {
+            if let Err(err) = flush.await {
+                tracing::warn!(?err, "failed to write entry usage");
+            }
+            store_file.forget_key().await?;
+            let entries = store_file.load_all_entries().await?;
+            i_s.finish_lock(entries).await
+        }
+        .boxed())
+    }
+
+    /// Entries cannot be created or modified in a read-only store.
+    fn check_writable(&self) -> LairResult<()> {
+        if self.config.get_read_only() {
+            return Err(LairError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// `options` for a new tls cert, exportable as configured
+    /// (see `Config::get_exportable_by_default()`) if left unset.
+    fn new_tls_cert_options(
+        &self,
+        mut options: TlsCertOptions,
+    ) -> TlsCertOptions {
+        options
+            .exportable
+            .get_or_insert(self.config.get_exportable_by_default());
+        options
+    }
+
+    /// The in-flight usage flush, if it is writing `index`.
+    /// Rewrites of `index` must wait for it, lest it overwrite them.
+    fn usage_flush_of(&self, index: KeystoreIndex) -> Option<UsageFlush> {
+        match &self.usage_flush {
+            Some((indexes, flush)) if indexes.contains(&index) => {
+                Some(flush.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ghost_actor::GhostControlHandler for EntryStoreImpl {}
+
+impl ghost_actor::GhostHandler<EntryStore> for EntryStoreImpl {}
+
+impl EntryStoreHandler for EntryStoreImpl {
+    fn handle_tls_cert_self_signed_new_from_entropy(
+        &mut self,
+        options: TlsCertOptions,
+        tag: Option<String>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        let meta = self.reserve_tag(tag)?;
+        let fut = new_tls_cert(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.new_tls_cert_options(options),
+            meta.clone(),
+        );
+        Ok(release_tag_on_err(self.i_s.clone(), meta, fut)
+            .boxed()
+            .into())
+    }
+
+    fn handle_sign_ed25519_keypair_new_from_entropy(
+        &mut self,
+        tag: Option<String>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        let meta = self.reserve_tag(tag)?;
+        let fut = new_sign_ed25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            meta.clone(),
+        );
+        Ok(release_tag_on_err(self.i_s.clone(), meta, fut)
+            .boxed()
+            .into())
+    }
+
+    fn handle_sign_ed25519_keypair_new_requiring_approval(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        let meta = entry::EntryMeta::new_created_now(None)
+            .with_requires_approval(true);
+        Ok(new_sign_ed25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            meta,
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_keypair_new_from_seed(
+        &mut self,
+        seed: Vec<u8>,
+        allow_duplicate: bool,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(import_sign_ed25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            seed,
+            allow_duplicate,
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_keypair_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(
+            new_x25519_keypair(self.i_s.clone(), self.store_file.clone())
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_x25519_keypair_new_from_priv_key(
+        &mut self,
+        priv_key: Vec<u8>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(import_x25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            priv_key,
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_keypair_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(
+            new_secp256k1_keypair(self.i_s.clone(), self.store_file.clone())
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_bls12381_keypair_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        #[cfg(not(feature = "bls"))]
+        {
+            Err(sign_bls12381::bls_not_enabled())
+        }
+        #[cfg(feature = "bls")]
+        {
+            Ok(
+                new_bls12381_keypair(self.i_s.clone(), self.store_file.clone())
+                    .boxed()
+                    .into(),
+            )
+        }
+    }
+
+    fn handle_seed_new_from_entropy(
+        &mut self,
+        exportable: bool,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(
+            new_seed(self.i_s.clone(), self.store_file.clone(), exportable)
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_seed_import(
+        &mut self,
+        seed: Vec<u8>,
+        exportable: bool,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        if seed.len() != seed::SEED_BYTES {
+            return Err(LairError::wrong_length(
+                "seed",
+                seed::SEED_BYTES,
+                seed.len(),
+            ));
+        }
+        Ok(import_seed(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            entry::EntrySeed::new(seed.into(), exportable),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_secret_key_new_from_entropy(
+        &mut self,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(new_secret_key(self.i_s.clone(), self.store_file.clone())
+            .boxed()
+            .into())
+    }
+
+    fn handle_sign_ed25519_keypair_derive_from_seed(
+        &mut self,
+        seed_index: KeystoreIndex,
+        derivation_path: Vec<u32>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        if self.deleted.contains(&seed_index) {
+            return Err(LairError::EntryDeleted(seed_index.0));
+        }
+        let seed = match self.entries_by_index.get(&seed_index).map(|e| &**e) {
+            Some(LairEntry::Seed(e)) => e.seed.clone(),
+            Some(_) => return Err(LairError::WrongEntryType),
+            None => return Err(LairError::EntryNotFound(seed_index.0)),
+        };
+        seed::check_derivation_path(&derivation_path)?;
+        Ok(derive_sign_ed25519_keypair(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            seed,
+            entry::EntryDerivation {
+                seed_index,
+                path: derivation_path,
+            },
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_keypair_convert_from_sign_ed25519(
+        &mut self,
+        sign_index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        if self.deleted.contains(&sign_index) {
+            return Err(LairError::EntryDeleted(sign_index.0));
+        }
+        let priv_key =
+            match self.entries_by_index.get(&sign_index).map(|e| &**e) {
+                Some(LairEntry::SignEd25519(e)) => e.priv_key.clone(),
+                Some(_) => return Err(LairError::WrongEntryType),
+                None => return Err(LairError::EntryNotFound(sign_index.0)),
+            };
+        Ok(convert_sign_ed25519_to_x25519(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            priv_key,
+            sign_index,
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_renew(
+        &mut self,
+        cert_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        if self.deleted.contains(&cert_index) {
+            return Err(LairError::EntryDeleted(cert_index.0));
+        }
+        let cert = match self.entries_by_index.get(&cert_index).map(|e| &**e) {
+            Some(LairEntry::TlsCert(e)) => e.clone(),
+            Some(_) => return Err(LairError::WrongEntryType),
+            None => return Err(LairError::EntryNotFound(cert_index.0)),
+        };
+        Ok(renew_tls_cert(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            cert,
+            cert_index,
+            options,
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_ca_new_self_signed(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(new_tls_ca(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.new_tls_cert_options(options),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_signed_by_ca(
+        &mut self,
+        ca_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>, Cert)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        if self.deleted.contains(&ca_index) {
+            return Err(LairError::EntryDeleted(ca_index.0));
+        }
+        let ca = match self.entries_by_index.get(&ca_index).map(|e| &**e) {
+            Some(LairEntry::TlsCert(e)) => e.clone(),
+            Some(_) => return Err(LairError::WrongEntryType),
+            None => return Err(LairError::EntryNotFound(ca_index.0)),
+        };
+        Ok(new_ca_signed_tls_cert(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            ca,
+            self.new_tls_cert_options(options),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_import(
+        &mut self,
+        cert_der: Cert,
+        priv_key_der: CertPrivKey,
+        options: TlsCertOptions,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        Ok(import_tls_cert(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            cert_der,
+            priv_key_der,
+            self.new_tls_cert_options(options),
+        )
+        .boxed()
+        .into())
+    }
+
+    fn handle_get_last_entry_index(
+        &mut self,
+    ) -> EntryStoreHandlerResult<KeystoreIndex> {
+        let idx = self.last_entry_index;
+        Ok(async move { Ok(idx) }.boxed().into())
+    }
+
+    fn handle_get_entry_by_index(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<Arc<LairEntry>> {
+        self.check_unlocked()?;
+        match self.entries_by_index.get(&index) {
+            Some(entry) => {
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None if self.deleted.contains(&index) => {
+                Err(LairError::EntryDeleted(index.0))
+            }
+            None if self.corrupt.contains(&index) => {
+                Err(LairError::EntryCorrupt(index.0))
+            }
+            None => Err(LairError::EntryNotFound(index.0)),
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn handle_list_entries(
+        &mut self,
+    ) -> EntryStoreHandlerResult<
+        Vec<(KeystoreIndex, Arc<LairEntry>, entry::EntryMeta)>,
+    > {
+        self.check_unlocked()?;
+        let mut out = self
+            .entries_by_index
+            .iter()
+            .map(|(idx, entry)| {
+                let meta =
+                    self.meta_by_index.get(idx).cloned().unwrap_or_default();
+                (*idx, entry.clone(), meta)
+            })
+            .collect::<Vec<_>>();
+        out.sort_by_key(|(idx, _, _)| *idx);
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
     fn handle_get_entry_by_pub_id(
         &mut self,
         id: Arc<Vec<u8>>,
     ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_unlocked()?;
         match self.entries_by_pub_id.get(&id) {
             Some(entry) => {
                 let entry = entry.clone();
                 Ok(async move { Ok(entry) }.boxed().into())
             }
-            None => Err(format!("invalid pub id: {:?}", id).into()),
+            None => Err(LairError::PubKeyNotFound),
+        }
+    }
+
+    fn handle_get_entry_by_sni(
+        &mut self,
+        sni: CertSni,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_unlocked()?;
+        match self.entries_by_sni.get(&sni) {
+            Some(entry) => {
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(format!("invalid sni: {:?}", sni).into()),
+        }
+    }
+
+    fn handle_get_entry_by_digest_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_unlocked()?;
+        // the digests starting with the prefix follow it in order
+        let from = (
+            std::ops::Bound::Included(&*prefix),
+            std::ops::Bound::Unbounded,
+        );
+        let entries_by_index = &self.entries_by_index;
+        let (index, _) = tls::tls_cert_find_by_digest_prefix(
+            &prefix,
+            self.certs_by_digest
+                .range::<Vec<u8>, _>(from)
+                .take_while(|(digest, _)| digest.starts_with(&prefix))
+                .filter_map(|(_, index)| {
+                    match &**entries_by_index.get(index)? {
+                        LairEntry::TlsCert(e) => Some((*index, e)),
+                        _ => None,
+                    }
+                }),
+        )?;
+        let entry = (index, self.entries_by_index[&index].clone());
+        Ok(async move { Ok(entry) }.boxed().into())
+    }
+
+    fn handle_find_entry(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> EntryStoreHandlerResult<Option<(KeystoreIndex, Arc<LairEntry>)>> {
+        self.check_unlocked()?;
+        let entry = match self.entries_by_pub_id.get(&id) {
+            Some(entry) => Some(entry.clone()),
+            None => std::str::from_utf8(&id).ok().and_then(|sni| {
+                self.entries_by_sni.get(&sni.to_string().into()).cloned()
+            }),
+        };
+        Ok(async move { Ok(entry) }.boxed().into())
+    }
+
+    fn handle_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
+        self.check_unlocked()?;
+        match self.entries_by_tag.get(&tag) {
+            Some(entry) => {
+                let entry = entry.clone();
+                Ok(async move { Ok(entry) }.boxed().into())
+            }
+            None => Err(LairError::EntryTagNotFound(tag)),
+        }
+    }
+
+    fn handle_get_entry_meta(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<entry::EntryMeta> {
+        self.check_unlocked()?;
+        if self.entries_by_index.contains_key(&index) {
+            let mut meta =
+                self.meta_by_index.get(&index).cloned().unwrap_or_default();
+            if meta.rate_limit.is_none() {
+                meta.rate_limit = self.config.get_entry_rate_limit();
+            }
+            Ok(async move { Ok(meta) }.boxed().into())
+        } else if self.deleted.contains(&index) {
+            Err(LairError::EntryDeleted(index.0))
+        } else {
+            Err(LairError::EntryNotFound(index.0))
+        }
+    }
+
+    fn handle_delete_entry(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        if self.deleted.contains(&index) {
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+        self.claim_rewrite(index)?;
+        let usage_flush = self.usage_flush_of(index);
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let store_path = self.config.get_store_path().to_owned();
+        Ok(async move {
+            if let Some(usage_flush) = usage_flush {
+                let _ = usage_flush.await;
+            }
+            let res = async {
+                let tombstone = entry::EntryDeleted.encode()?;
+                store_file
+                    .replace_entry(store_path, index, tombstone)
+                    .await?;
+                i_s.finalize_delete_entry(index).await
+            }
+            .await;
+            if res.is_err() {
+                i_s.release_rewrite(index).await?;
+            }
+            res
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_update_entry_tag(
+        &mut self,
+        index: KeystoreIndex,
+        tag: String,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        let mut meta =
+            self.meta_by_index.get(&index).cloned().unwrap_or_default();
+        if meta.tag.as_ref() == Some(&tag) {
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+        self.claim_rewrite(index)?;
+        meta.tag = Some(tag.clone());
+        let data = self.entries_by_index[&index].encode_with_meta(&meta);
+        let data = match data.and_then(|data| {
+            self.claim_tag(&tag)?;
+            Ok(data)
+        }) {
+            Ok(data) => data,
+            Err(err) => {
+                self.pending_rewrites.remove(&index);
+                return Err(err);
+            }
+        };
+        let usage_flush = self.usage_flush_of(index);
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let store_path = self.config.get_store_path().to_owned();
+        Ok(async move {
+            if let Some(usage_flush) = usage_flush {
+                let _ = usage_flush.await;
+            }
+            let res = async {
+                store_file.replace_entry(store_path, index, data).await?;
+                i_s.finalize_update_entry_tag(index, tag.clone()).await
+            }
+            .await;
+            if res.is_err() {
+                i_s.release_rewrite(index).await?;
+                i_s.release_tag(tag).await?;
+            }
+            res
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_update_entry_rate_limit(
+        &mut self,
+        index: KeystoreIndex,
+        rate_limit: Option<u32>,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_writable()?;
+        self.check_unlocked()?;
+        let mut meta =
+            self.meta_by_index.get(&index).cloned().unwrap_or_default();
+        if meta.rate_limit == rate_limit {
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+        self.claim_rewrite(index)?;
+        meta.rate_limit = rate_limit;
+        let data = match self.entries_by_index[&index].encode_with_meta(&meta) {
+            Ok(data) => data,
+            Err(err) => {
+                self.pending_rewrites.remove(&index);
+                return Err(err);
+            }
+        };
+        let usage_flush = self.usage_flush_of(index);
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let store_path = self.config.get_store_path().to_owned();
+        Ok(async move {
+            if let Some(usage_flush) = usage_flush {
+                let _ = usage_flush.await;
+            }
+            let res = async {
+                store_file.replace_entry(store_path, index, data).await?;
+                i_s.finalize_update_entry_rate_limit(index, rate_limit)
+                    .await
+            }
+            .await;
+            if res.is_err() {
+                i_s.release_rewrite(index).await?;
+            }
+            res
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_reserve_signatures(
+        &mut self,
+        index: KeystoreIndex,
+        count: u32,
+    ) -> EntryStoreHandlerResult<std::time::Duration> {
+        self.check_unlocked()?;
+        let entry_limit = self
+            .meta_by_index
+            .get(&index)
+            .and_then(|meta| meta.rate_limit)
+            .or_else(|| self.config.get_entry_rate_limit());
+        let wait = self.rate_limits.reserve(
+            index,
+            entry_limit,
+            count,
+            std::time::Instant::now(),
+        )?;
+        Ok(async move { Ok(wait) }.boxed().into())
+    }
+
+    fn handle_record_entry_use(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> EntryStoreHandlerResult<()> {
+        // a read-only store keeps no usage counts, it could not flush them
+        if self.entries_by_index.contains_key(&index)
+            && !self.config.get_read_only()
+        {
+            self.meta_by_index
+                .entry(index)
+                .or_default()
+                .record_use_now();
+            self.usage_dirty.insert(index);
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_flush_entry_usage(&mut self) -> EntryStoreHandlerResult<()> {
+        // only one flush runs at a time, wait for it before
+        // starting another for any usage recorded since
+        let in_flight = self.usage_flush.as_ref().map(|(_, f)| f.clone());
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            if let Some(in_flight) = in_flight {
+                let _ = in_flight.await;
+            }
+            i_s.start_usage_flush().await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lock_state(&mut self) -> EntryStoreHandlerResult<StoreLockState> {
+        let state = self.lock_state();
+        Ok(async move { Ok(state) }.boxed().into())
+    }
+
+    fn handle_corrupt_entry_count(&mut self) -> EntryStoreHandlerResult<u64> {
+        let count = self.corrupt.len() as u64;
+        Ok(async move { Ok(count) }.boxed().into())
+    }
+
+    fn handle_subscribe_changes(
+        &mut self,
+    ) -> EntryStoreHandlerResult<tokio::sync::broadcast::Receiver<StoreChange>>
+    {
+        let changes = self.changes.subscribe();
+        Ok(async move { Ok(changes) }.boxed().into())
+    }
+
+    fn handle_compact_store(
+        &mut self,
+    ) -> EntryStoreHandlerResult<LairCompactInfo> {
+        self.check_writable()?;
+        let store_file = self.store_file.clone();
+        let store_path = self.config.get_store_path().to_owned();
+        Ok(async move { store_file.compact(store_path).await }
+            .boxed()
+            .into())
+    }
+
+    fn handle_unlock(
+        &mut self,
+        passphrase: SecretBytes,
+    ) -> EntryStoreHandlerResult<()> {
+        if self.unlock.is_none() && self.init_on_unlock {
+            let fut = init_store_on_unlock(
+                self.i_s.clone(),
+                self.store_file.clone(),
+                self.config.get_store_path().to_owned(),
+                passphrase,
+                self.config.get_unlock_kdf(),
+            );
+            return Ok(fut.boxed().into());
         }
+        let fut = unlock_store(
+            self.i_s.clone(),
+            self.store_file.clone(),
+            self.config.get_store_path().to_owned(),
+            self.unlock.clone(),
+            passphrase,
+            self.config.get_unlock_kdf(),
+            self.config.get_read_only(),
+        );
+        Ok(self.unlock_attempts.clone().attempt(fut).boxed().into())
+    }
+
+    fn handle_lock(&mut self) -> EntryStoreHandlerResult<()> {
+        Ok(self.lock()?.into())
+    }
+
+    fn handle_failed_unlock_count(&mut self) -> EntryStoreHandlerResult<u64> {
+        let count = self.unlock_attempts.failed_count();
+        Ok(async move { Ok(count) }.boxed().into())
+    }
+
+    fn handle_change_unlock_passphrase(
+        &mut self,
+        old_passphrase: SecretBytes,
+        new_passphrase: SecretBytes,
+    ) -> EntryStoreHandlerResult<()> {
+        self.check_writable()?;
+        let unlock = self.unlock.clone();
+        let unlock_attempts = self.unlock_attempts.clone();
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let store_path = self.config.get_store_path().to_owned();
+        let kdf = self.config.get_unlock_kdf();
+        Ok(async move {
+            unlock_attempts
+                .attempt(unlock_store(
+                    i_s.clone(),
+                    store_file.clone(),
+                    store_path.clone(),
+                    unlock,
+                    old_passphrase,
+                    kdf,
+                    false,
+                ))
+                .await?;
+            if new_passphrase.is_empty() {
+                return Err("passphrase cannot be empty".into());
+            }
+            let (unlock, key) =
+                entry::EntryUnlock::new_from_passphrase(new_passphrase, kdf)
+                    .await?;
+            // the new unlock entry is written along with the resealed
+            // entries, a crash leaves either the old store or the new
+            let key = Arc::new(key);
+            store_file
+                .reseal_all(store_path, key.clone(), Some(unlock.encode()?))
+                .await?;
+            i_s.set_unlock(unlock).await?;
+            i_s.finish_unlock(key).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_flush_and_close(&mut self) -> EntryStoreHandlerResult<()> {
+        // the store file task processes requests in order,
+        // so this completes after any previously queued writes
+        let flush = self.handle_flush_entry_usage()?;
+        let store_file = self.store_file.clone();
+        Ok(async move {
+            if let Err(err) = flush.await {
+                tracing::warn!(?err, "failed to write entry usage");
+            }
+            store_file.close().await
+        }
+        .boxed()
+        .into())
+    }
+}
+
+impl ghost_actor::GhostHandler<EntryStoreInternal> for EntryStoreImpl {}
+
+impl EntryStoreInternalHandler for EntryStoreImpl {
+    fn handle_claim_pub_id(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        if let Some((idx, _)) = self.entries_by_pub_id.get(&id) {
+            return Err(LairError::DuplicatePubKey {
+                existing_index: idx.0,
+            });
+        }
+        if !self.pending_pub_ids.insert(id) {
+            return Err(
+                "an entry with this pub key is already being imported".into()
+            );
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_release_pub_id(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.pending_pub_ids.remove(&id);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_claim_derived_pub_id(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> EntryStoreInternalHandlerResult<Option<(KeystoreIndex, Arc<LairEntry>)>>
+    {
+        let out = self.entries_by_pub_id.get(&id).cloned();
+        if out.is_none() && !self.pending_pub_ids.insert(id) {
+            return Err(
+                "an entry with this pub key is already being derived".into()
+            );
+        }
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_finalize_new_entry(
+        &mut self,
+        entry_index: KeystoreIndex,
+        entry: Arc<LairEntry>,
+        meta: entry::EntryMeta,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        // unless the store was locked since, and reloaded it sealed
+        if !self.sealed.contains_key(&entry_index) {
+            self.track_new_entry(entry_index, entry.clone(), meta);
+        }
+        // no subscribers is fine
+        let _ = self
+            .changes
+            .send(StoreChange::EntryCreated(entry_index, entry));
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_release_tag(
+        &mut self,
+        tag: String,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.pending_tags.remove(&tag);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_finalize_update_entry_tag(
+        &mut self,
+        entry_index: KeystoreIndex,
+        tag: String,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.pending_rewrites.remove(&entry_index);
+        self.pending_tags.remove(&tag);
+        if let Some(entry) = self.entries_by_index.get(&entry_index).cloned() {
+            // keep any usage recorded while the entry was rewritten
+            let meta = self.meta_by_index.entry(entry_index).or_default();
+            if let Some(old_tag) = meta.tag.replace(tag.clone()) {
+                self.entries_by_tag.remove(&old_tag);
+            }
+            self.entries_by_tag.insert(tag, (entry_index, entry));
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_finalize_update_entry_rate_limit(
+        &mut self,
+        entry_index: KeystoreIndex,
+        rate_limit: Option<u32>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.pending_rewrites.remove(&entry_index);
+        if self.entries_by_index.contains_key(&entry_index) {
+            // keep any usage recorded while the entry was rewritten
+            self.meta_by_index
+                .entry(entry_index)
+                .or_default()
+                .rate_limit = rate_limit;
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_release_rewrite(
+        &mut self,
+        entry_index: KeystoreIndex,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.pending_rewrites.remove(&entry_index);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_set_unlock(
+        &mut self,
+        unlock: entry::EntryUnlock,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.unlock = Some(unlock);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_finish_unlock(
+        &mut self,
+        key: Arc<entry::EntryStoreKey>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        // the key is checked against the unlock entry,
+        // so entries that fail to open are corrupt
+        let mut unsealed = Vec::with_capacity(self.sealed.len());
+        for (index, data) in std::mem::take(&mut self.sealed) {
+            unsealed
+                .push((index, entry::LairEntry::unseal_with_meta(&data, &key)));
+        }
+        unsealed.sort_by_key(|(index, _)| *index);
+        for (index, res) in unsealed {
+            match res {
+                Ok((entry, meta)) => {
+                    self.track_new_entry(index, Arc::new(entry), meta)
+                }
+                Err(err) => self.track_corrupt_entry(index, err),
+            }
+        }
+        if self.store_key.replace(key).is_none() {
+            let _ = self.changes.send(StoreChange::Unlocked);
+        }
+        self.last_use = std::time::Instant::now();
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_finish_lock(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        if self.store_key.is_some() {
+            // unlocked again meanwhile, the entries are still tracked
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+        self.sealed.clear();
+        self.entries_by_index.clear();
+        self.deleted.clear();
+        self.corrupt.clear();
+        self.entries_by_pub_id.clear();
+        self.entries_by_sni.clear();
+        self.certs_by_digest.clear();
+        self.entries_by_tag.clear();
+        self.meta_by_index.clear();
+        self.usage_dirty.clear();
+        self.track_loaded_entries(entries);
+        tracing::info!("store locked");
+        let _ = self.changes.send(StoreChange::Locked);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lock_if_idle(
+        &mut self,
+        idle: std::time::Duration,
+    ) -> EntryStoreInternalHandlerResult<std::time::Duration> {
+        if self.lock_state() != StoreLockState::Unlocked {
+            return Ok(async move { Ok(idle) }.boxed().into());
+        }
+        let unused = self.last_use.elapsed();
+        if unused < idle {
+            let wait = idle - unused;
+            return Ok(async move { Ok(wait) }.boxed().into());
+        }
+        tracing::info!(?idle, "locking the unused store");
+        let lock = self.lock()?;
+        Ok(async move {
+            lock.await?;
+            Ok(idle)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_finalize_delete_entry(
+        &mut self,
+        entry_index: KeystoreIndex,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.pending_rewrites.remove(&entry_index);
+        self.untrack_entry(entry_index);
+        self.rate_limits.remove(entry_index);
+        // the store was locked since
+        if self.sealed.remove(&entry_index).is_some() {
+            self.deleted.insert(entry_index);
+        }
+        let _ = self.changes.send(StoreChange::EntryDeleted(entry_index));
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_start_usage_flush(
+        &mut self,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        if let Some((_, in_flight)) = &self.usage_flush {
+            let in_flight = in_flight.clone();
+            return Ok(async move { Ok(in_flight.await?) }.boxed().into());
+        }
+
+        // entries with a rewrite in flight stay dirty for the next flush,
+        // so we don't overwrite a tombstone / new tag with stale data
+        let indexes = self
+            .usage_dirty
+            .iter()
+            .filter(|idx| !self.pending_rewrites.contains(idx))
+            .copied()
+            .collect::<Vec<_>>();
+        if indexes.is_empty() {
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+
+        let mut entries = Vec::with_capacity(indexes.len());
+        for idx in indexes.iter() {
+            self.usage_dirty.remove(idx);
+            let meta = self.meta_by_index.get(idx).cloned().unwrap_or_default();
+            match self.entries_by_index[idx].encode_with_meta(&meta) {
+                Ok(data) => entries.push((*idx, data)),
+                Err(err) => {
+                    tracing::warn!(?err, "cannot write usage of entry {}", idx)
+                }
+            }
+        }
+
+        let i_s = self.i_s.clone();
+        let store_file = self.store_file.clone();
+        let store_path = self.config.get_store_path().to_owned();
+        let flush_indexes = indexes.clone();
+        let flush: UsageFlush = async move {
+            let res = store_file.replace_entries(store_path, entries).await;
+            let _ = i_s.finalize_usage_flush(flush_indexes, res.is_ok()).await;
+            res.map_err(|err| err.to_string())
+        }
+        .boxed()
+        .shared();
+        self.usage_flush = Some((indexes.into_iter().collect(), flush.clone()));
+
+        Ok(async move { Ok(flush.await?) }.boxed().into())
+    }
+
+    fn handle_finalize_usage_flush(
+        &mut self,
+        entry_indexes: Vec<KeystoreIndex>,
+        success: bool,
+    ) -> EntryStoreInternalHandlerResult<()> {
+        self.usage_flush = None;
+        if !success {
+            // try again next flush
+            for idx in entry_indexes {
+                if self.entries_by_index.contains_key(&idx) {
+                    self.usage_dirty.insert(idx);
+                }
+            }
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+}
+
+/// Verify `passphrase` against `unlock`, and unseal the store entries
+/// with the store key it derives. A store whose unlock entry is weaker
+/// than `kdf` gets a new one derived with it, and its entries resealed
+/// with the new key. A `read_only` store is only unsealed.
+async fn unlock_store(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    store_path: std::path::PathBuf,
+    unlock: Option<entry::EntryUnlock>,
+    passphrase: SecretBytes,
+    kdf: entry::UnlockKdf,
+    read_only: bool,
+) -> LairResult<()> {
+    let unlock = match unlock {
+        None => {
+            return Err("this store has no unlock passphrase, \
+                run `lair-keystore init` first"
+                .into())
+        }
+        Some(unlock) => unlock,
+    };
+    // keep the passphrase to derive the upgraded key
+    let upgrade_passphrase = passphrase.clone();
+    let key = Arc::new(unlock.unlock(passphrase).await?);
+    if read_only {
+        return i_s.finish_unlock(key).await;
+    }
+    // from here on the store file seals the entries written with `key`
+    store_file
+        .reseal_all(store_path.clone(), key.clone(), None)
+        .await?;
+    if let Some(kdf) = unlock.kdf.upgrade_to(kdf) {
+        // the store is unlocked either way, with the old key
+        // should the upgrade fail
+        i_s.finish_unlock(key).await?;
+        return upgrade_unlock_kdf(
+            i_s,
+            store_file,
+            store_path,
+            upgrade_passphrase,
+            kdf,
+        )
+        .await;
+    }
+    i_s.finish_unlock(key).await
+}
+
+/// Replace the unlock entry of an unlocked store with one derived
+/// from `passphrase` with the stronger `kdf`, resealing the entries.
+/// Failing that is logged, the store stays as it was.
+async fn upgrade_unlock_kdf(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    store_path: std::path::PathBuf,
+    passphrase: SecretBytes,
+    kdf: entry::UnlockKdf,
+) -> LairResult<()> {
+    let res = async {
+        let (unlock, key) =
+            entry::EntryUnlock::new_from_passphrase(passphrase, kdf).await?;
+        // written along with the resealed entries, like a new passphrase
+        let key = Arc::new(key);
+        store_file
+            .reseal_all(store_path, key.clone(), Some(unlock.encode()?))
+            .await?;
+        i_s.set_unlock(unlock).await?;
+        i_s.finish_unlock(key).await
+    }
+    .await;
+    match res {
+        Ok(()) => tracing::info!(?kdf, "re-keyed the unlock entry"),
+        Err(err) => tracing::warn!(?err, "failed to re-key the unlock entry"),
+    }
+    Ok(())
+}
+
+/// Stores of ephemeral configs and embedders' backends cannot be
+/// initialized ahead of time (by `init_store_file()`), they are
+/// initialized by the first unlock passphrase, sealing any entries
+/// written before.
+async fn init_store_on_unlock(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    store_path: std::path::PathBuf,
+    passphrase: SecretBytes,
+    kdf: entry::UnlockKdf,
+) -> LairResult<()> {
+    if passphrase.is_empty() {
+        return Err("passphrase cannot be empty".into());
+    }
+    let (unlock, key) =
+        entry::EntryUnlock::new_from_passphrase(passphrase, kdf).await?;
+    let key = Arc::new(key);
+    store_file.write_unlock(unlock.encode()?).await?;
+    store_file.reseal_all(store_path, key.clone(), None).await?;
+    i_s.set_unlock(unlock).await?;
+    i_s.finish_unlock(key).await
+}
+
+/// Await the creation of an entry with `meta`,
+/// releasing its reserved tag if that fails.
+async fn release_tag_on_err<F, R>(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    meta: entry::EntryMeta,
+    fut: F,
+) -> LairResult<R>
+where
+    F: std::future::Future<Output = LairResult<R>>,
+{
+    let res = fut.await;
+    if res.is_err() {
+        if let Some(tag) = meta.tag {
+            i_s.release_tag(tag).await?;
+        }
+    }
+    res
+}
+
+async fn new_tls_cert(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    options: TlsCertOptions,
+    meta: entry::EntryMeta,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let cert = Arc::new(LairEntry::TlsCert(
+        tls::tls_cert_self_signed_new_from_entropy(options).await?,
+    ));
+    let encoded_cert = cert.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_cert).await?;
+    i_s.finalize_new_entry(entry_index, cert.clone(), meta)
+        .await?;
+    Ok((entry_index, cert))
+}
+
+async fn renew_tls_cert(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    cert: entry::EntryTlsCert,
+    cert_index: KeystoreIndex,
+    options: TlsCertOptions,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let cert = Arc::new(LairEntry::TlsCert(
+        tls::tls_cert_self_signed_renew(&cert, options).await?,
+    ));
+    let mut meta = entry::EntryMeta::new_created_now(None);
+    meta.renewed_from = Some(cert_index);
+    let encoded_cert = cert.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_cert).await?;
+    i_s.finalize_new_entry(entry_index, cert.clone(), meta)
+        .await?;
+    Ok((entry_index, cert))
+}
+
+async fn new_tls_ca(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    options: TlsCertOptions,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let ca = Arc::new(LairEntry::TlsCert(
+        tls::tls_ca_new_self_signed(options).await?,
+    ));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_ca = ca.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_ca).await?;
+    i_s.finalize_new_entry(entry_index, ca.clone(), meta)
+        .await?;
+    Ok((entry_index, ca))
+}
+
+async fn new_ca_signed_tls_cert(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    ca: entry::EntryTlsCert,
+    options: TlsCertOptions,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>, Cert)> {
+    let cert = Arc::new(LairEntry::TlsCert(
+        tls::tls_cert_new_signed_by_ca(&ca, options).await?,
+    ));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_cert = cert.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_cert).await?;
+    i_s.finalize_new_entry(entry_index, cert.clone(), meta)
+        .await?;
+    Ok((entry_index, cert, ca.cert_der))
+}
+
+async fn import_tls_cert(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    cert_der: Cert,
+    priv_key_der: CertPrivKey,
+    options: TlsCertOptions,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let cert = tls::tls_cert_import(cert_der, priv_key_der, options).await?;
+
+    // claim the cert digest, so a concurrent import of the same cert
+    // can't slip in between our check and our write
+    let pub_id = cert.cert_digest.0.clone();
+    i_s.claim_pub_id(pub_id.clone()).await?;
+    let res = async {
+        let cert = Arc::new(LairEntry::TlsCert(cert));
+        let meta = entry::EntryMeta::new_created_now(None);
+        let encoded_cert = cert.encode_with_meta(&meta)?;
+        let entry_index = store_file.write_next_entry(encoded_cert).await?;
+        i_s.finalize_new_entry(entry_index, cert.clone(), meta)
+            .await?;
+        Ok((entry_index, cert))
+    }
+    .await;
+    i_s.release_pub_id(pub_id).await?;
+    res
+}
+
+async fn new_sign_ed25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    meta: entry::EntryMeta,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::SignEd25519(
+        sign_ed25519::sign_ed25519_keypair_new_from_entropy().await?,
+    ));
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+async fn import_sign_ed25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    seed: Vec<u8>,
+    allow_duplicate: bool,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = sign_ed25519::sign_ed25519_keypair_from_seed(seed).await?;
+    if allow_duplicate {
+        return write_sign_ed25519_keypair(i_s, store_file, entry).await;
+    }
+
+    // claim the pub key, so a concurrent import of the same seed
+    // can't slip in between our check and our write
+    let pub_id = entry.pub_key.0.clone();
+    i_s.claim_pub_id(pub_id.clone()).await?;
+    let res = write_sign_ed25519_keypair(i_s.clone(), store_file, entry).await;
+    i_s.release_pub_id(pub_id).await?;
+    res
+}
+
+async fn write_sign_ed25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    entry: entry::EntrySignEd25519,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::SignEd25519(entry));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+async fn new_x25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::X25519(
+        x25519::x25519_keypair_new_from_entropy().await?,
+    ));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+async fn import_x25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    priv_key: Vec<u8>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = x25519::x25519_keypair_from_priv_key(priv_key).await?;
+
+    // claim the pub key, so a concurrent import of the same key
+    // can't slip in between our check and our write
+    let pub_id = Arc::new(entry.pub_key.to_bytes().to_vec());
+    i_s.claim_pub_id(pub_id.clone()).await?;
+    let res = async {
+        let entry = Arc::new(LairEntry::X25519(entry));
+        let meta = entry::EntryMeta::new_created_now(None);
+        let encoded_entry = entry.encode_with_meta(&meta)?;
+        let entry_index = store_file.write_next_entry(encoded_entry).await?;
+        i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+            .await?;
+        Ok((entry_index, entry))
+    }
+    .await;
+    i_s.release_pub_id(pub_id).await?;
+    res
+}
+
+async fn new_secp256k1_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::SignSecp256k1(
+        sign_secp256k1::sign_secp256k1_keypair_new_from_entropy().await?,
+    ));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+#[cfg(feature = "bls")]
+async fn new_bls12381_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::SignBls12381(
+        sign_bls12381::sign_bls12381_keypair_new_from_entropy().await?,
+    ));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+async fn new_seed(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    exportable: bool,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = seed::seed_new_from_entropy(exportable).await?;
+    write_seed(i_s, store_file, entry).await
+}
+
+async fn import_seed(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    entry: entry::EntrySeed,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    // claim the seed id, so a concurrent import of the same seed
+    // can't slip in between our check and our write
+    let pub_id = entry.seed_id.0.clone();
+    i_s.claim_pub_id(pub_id.clone()).await?;
+    let res = write_seed(i_s.clone(), store_file, entry).await;
+    i_s.release_pub_id(pub_id).await?;
+    res
+}
+
+async fn write_seed(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    entry: entry::EntrySeed,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::Seed(entry));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+async fn new_secret_key(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = Arc::new(LairEntry::SecretKey(
+        secret_key::secret_key_new_from_entropy().await?,
+    ));
+    let meta = entry::EntryMeta::new_created_now(None);
+    let encoded_entry = entry.encode_with_meta(&meta)?;
+    let entry_index = store_file.write_next_entry(encoded_entry).await?;
+    i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+        .await?;
+    Ok((entry_index, entry))
+}
+
+async fn derive_sign_ed25519_keypair(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    seed: seed::Seed,
+    derivation: entry::EntryDerivation,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry =
+        seed::sign_ed25519_keypair_derive(seed, derivation.path.clone())
+            .await?;
+
+    // derivation is deterministic, the keypair may already be present
+    // otherwise, claim the pub key so a concurrent derivation of the
+    // same path can't slip in between our check and our write
+    let pub_id = entry.pub_key.0.clone();
+    if let Some(existing) = i_s.claim_derived_pub_id(pub_id.clone()).await? {
+        return Ok(existing);
+    }
+
+    let res = async {
+        let entry = Arc::new(LairEntry::SignEd25519(entry));
+        let mut meta = entry::EntryMeta::new_created_now(None);
+        meta.derived_from = Some(derivation);
+        let encoded_entry = entry.encode_with_meta(&meta)?;
+        let entry_index = store_file.write_next_entry(encoded_entry).await?;
+        i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+            .await?;
+        Ok((entry_index, entry))
+    }
+    .await;
+    i_s.release_pub_id(pub_id).await?;
+    res
+}
+
+async fn convert_sign_ed25519_to_x25519(
+    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
+    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
+    priv_key: sign_ed25519::SignEd25519PrivKey,
+    sign_index: KeystoreIndex,
+) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
+    let entry = x25519::x25519_keypair_from_sign_ed25519(priv_key).await?;
+
+    // conversion is deterministic, the keypair may already be present
+    // otherwise, claim the pub key so a concurrent conversion of the
+    // same keypair can't slip in between our check and our write
+    let pub_id = Arc::new(entry.pub_key.to_bytes().to_vec());
+    if let Some(existing) = i_s.claim_derived_pub_id(pub_id.clone()).await? {
+        return Ok(existing);
+    }
+
+    let res = async {
+        let entry = Arc::new(LairEntry::X25519(entry));
+        let mut meta = entry::EntryMeta::new_created_now(None);
+        meta.converted_from = Some(sign_index);
+        let encoded_entry = entry.encode_with_meta(&meta)?;
+        let entry_index = store_file.write_next_entry(encoded_entry).await?;
+        i_s.finalize_new_entry(entry_index, entry.clone(), meta)
+            .await?;
+        Ok((entry_index, entry))
+    }
+    .await;
+    i_s.release_pub_id(pub_id).await?;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! as_cert {
+        ($e:ident) => {
+            let $e = match &*$e {
+                LairEntry::TlsCert(e) => e,
+                _ => panic!("unexpected"),
+            };
+        };
+    }
+
+    macro_rules! as_sign {
+        ($e:ident) => {
+            let $e = match &*$e {
+                LairEntry::SignEd25519(e) => e,
+                _ => panic!("unexpected"),
+            };
+        };
+    }
+
+    macro_rules! as_seed {
+        ($e:ident) => {
+            let $e = match &*$e {
+                LairEntry::Seed(e) => e,
+                _ => panic!("unexpected"),
+            };
+        };
+    }
+
+    macro_rules! as_x25519 {
+        ($e:ident) => {
+            let $e = match &*$e {
+                LairEntry::X25519(e) => e,
+                _ => panic!("unexpected"),
+            };
+        };
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_import_sign_ed25519_seed() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+
+        let (index, entry) = store
+            .sign_ed25519_keypair_new_from_seed(vec![0xdb; 32], false)
+            .await
+            .unwrap();
+        assert_eq!(1, index.0);
+        as_sign!(entry);
+
+        assert!(matches!(
+            store
+                .sign_ed25519_keypair_new_from_seed(vec![0xdb; 32], false)
+                .await,
+            Err(LairError::DuplicatePubKey { existing_index: 1 })
+        ));
+
+        // concurrent imports of the same seed, only one gets in
+        let (r1, r2) = futures::future::join(
+            store.sign_ed25519_keypair_new_from_seed(vec![0xdc; 32], false),
+            store.sign_ed25519_keypair_new_from_seed(vec![0xdc; 32], false),
+        )
+        .await;
+        assert!(r1.is_ok() != r2.is_ok());
+        let (index, _) = r1.or(r2).unwrap();
+        assert_eq!(2, index.0);
+        // a failed import doesn't leave the pub key claimed
+        assert!(matches!(
+            store
+                .sign_ed25519_keypair_new_from_seed(vec![0xdc; 32], false)
+                .await,
+            Err(LairError::DuplicatePubKey { existing_index: 2 })
+        ));
+
+        let (index, dup) = store
+            .sign_ed25519_keypair_new_from_seed(vec![0xdb; 32], true)
+            .await
+            .unwrap();
+        assert_eq!(3, index.0);
+        as_sign!(dup);
+        assert_eq!(entry.pub_key, dup.pub_key);
+
+        assert!(matches!(
+            store
+                .sign_ed25519_keypair_new_from_seed(vec![0xdb; 16], false)
+                .await,
+            Err(LairError::SignEd25519SeedLength(16))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_import_x25519_from_priv_key() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (index, entry) = store
+            .x25519_keypair_new_from_priv_key(vec![0xdb; 32])
+            .await
+            .unwrap();
+        assert_eq!(1, index.0);
+        let pub_key = match &*entry {
+            LairEntry::X25519(e) => e.pub_key.clone(),
+            _ => panic!("unexpected"),
+        };
+
+        // the clamped key is the same key
+        let mut clamped = vec![0xdb; 32];
+        clamped[0] &= 248;
+        clamped[31] &= 127;
+        assert!(matches!(
+            store.x25519_keypair_new_from_priv_key(clamped).await,
+            Err(LairError::DuplicatePubKey { existing_index: 1 })
+        ));
+
+        // concurrent imports of the same key, only one gets in
+        let (r1, r2) = futures::future::join(
+            store.x25519_keypair_new_from_priv_key(vec![0xdc; 32]),
+            store.x25519_keypair_new_from_priv_key(vec![0xdc; 32]),
+        )
+        .await;
+        assert!(r1.is_ok() != r2.is_ok());
+        let (index, _) = r1.or(r2).unwrap();
+        assert_eq!(2, index.0);
+
+        assert!(matches!(
+            store.x25519_keypair_new_from_priv_key(vec![0xdb; 16]).await,
+            Err(LairError::X25519PrivKeyImportLength(16))
+        ));
+
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let (r_index, _) = store
+            .get_entry_by_pub_id(Arc::new(pub_key.to_bytes().to_vec()))
+            .await
+            .unwrap();
+        assert_eq!(1, r_index.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_store_secret_keys() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (index, entry) = store.secret_key_new_from_entropy().await.unwrap();
+        assert_eq!(1, index.0);
+        let (secret_key, tag) = match &*entry {
+            LairEntry::SecretKey(e) => (e.secret_key.clone(), e.tag.clone()),
+            _ => panic!("unexpected"),
+        };
+
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let (r_index, r_entry) =
+            store.get_entry_by_pub_id(tag.0.clone()).await.unwrap();
+        assert_eq!(1, r_index.0);
+        match &*r_entry {
+            LairEntry::SecretKey(e) => assert_eq!(secret_key, e.secret_key),
+            _ => panic!("unexpected"),
+        }
+
+        store.delete_entry(index).await.unwrap();
+        assert!(store.get_entry_by_pub_id(tag.0).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_reports_entries_created_and_deleted() {
+        let config = Config::builder().set_ephemeral(true).build();
+        let store = spawn_ephemeral_entry_store_actor(config).await.unwrap();
+        let mut changes = store.subscribe_changes().await.unwrap();
+
+        let (index, entry) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        match changes.recv().await.unwrap() {
+            StoreChange::EntryCreated(r_index, r_entry) => {
+                assert_eq!(index, r_index);
+                assert!(Arc::ptr_eq(&entry, &r_entry));
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+
+        store.delete_entry(index).await.unwrap();
+        match changes.recv().await.unwrap() {
+            StoreChange::EntryDeleted(r_index) => assert_eq!(index, r_index),
+            o => panic!("unexpected: {:?}", o),
+        }
+
+        // deleting it again changes nothing
+        store.delete_entry(index).await.unwrap();
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_derive_sign_ed25519_from_seed() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (seed_index, seed_entry) =
+            store.seed_new_from_entropy(false).await.unwrap();
+        assert_eq!(1, seed_index.0);
+        let seed_id = match &*seed_entry {
+            LairEntry::Seed(e) => e.seed_id.clone(),
+            _ => panic!("unexpected"),
+        };
+        let (index, _) =
+            store.get_entry_by_pub_id(seed_id.0.clone()).await.unwrap();
+        assert_eq!(seed_index, index);
+
+        let (index, entry) = store
+            .sign_ed25519_keypair_derive_from_seed(seed_index, vec![0, 1])
+            .await
+            .unwrap();
+        assert_eq!(2, index.0);
+        as_sign!(entry);
+
+        // deriving the same path again resolves to the existing entry
+        let (again, _) = store
+            .sign_ed25519_keypair_derive_from_seed(seed_index, vec![0, 1])
+            .await
+            .unwrap();
+        assert_eq!(index, again);
+
+        // racing derivations of a new path never write a duplicate entry
+        let (r1, r2) = futures::future::join(
+            store.sign_ed25519_keypair_derive_from_seed(seed_index, vec![7]),
+            store.sign_ed25519_keypair_derive_from_seed(seed_index, vec![7]),
+        )
+        .await;
+        let raced = r1.as_ref().or(r2.as_ref()).unwrap().0;
+        assert_eq!(3, raced.0);
+        for r in [r1, r2].iter().flatten() {
+            assert_eq!(raced, r.0);
+        }
+        assert_eq!(raced, store.get_last_entry_index().await.unwrap());
+
+        let (other_index, other) = store
+            .sign_ed25519_keypair_derive_from_seed(seed_index, vec![0, 2])
+            .await
+            .unwrap();
+        assert_eq!(4, other_index.0);
+        as_sign!(other);
+        assert_ne!(entry.pub_key, other.pub_key);
+
+        // only seeds can be derived from, along valid paths
+        assert!(store
+            .sign_ed25519_keypair_derive_from_seed(index, vec![0])
+            .await
+            .is_err());
+        assert!(store
+            .sign_ed25519_keypair_derive_from_seed(seed_index, vec![])
+            .await
+            .is_err());
+        assert!(store
+            .sign_ed25519_keypair_derive_from_seed(
+                seed_index,
+                vec![seed::HARDENED_BIT]
+            )
+            .await
+            .is_err());
+
+        let meta = store.get_entry_meta(index).await.unwrap();
+        let expect_derivation = entry::EntryDerivation {
+            seed_index,
+            path: vec![0, 1],
+        };
+        assert_eq!(Some(expect_derivation.clone()), meta.derived_from);
+        assert_eq!(
+            None,
+            store.get_entry_meta(seed_index).await.unwrap().derived_from
+        );
+
+        let pub_key = entry.pub_key.clone();
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        // seeds and derivations survive a restart
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let meta = store.get_entry_meta(index).await.unwrap();
+        assert_eq!(Some(expect_derivation), meta.derived_from);
+        let (again, _) = store
+            .sign_ed25519_keypair_derive_from_seed(seed_index, vec![0, 1])
+            .await
+            .unwrap();
+        assert_eq!(index, again);
+        let entry = store.get_entry_by_index(index).await.unwrap();
+        as_sign!(entry);
+        assert_eq!(pub_key, entry.pub_key);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_convert_sign_ed25519_to_x25519() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (sign_index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+        let (index, entry) = store
+            .x25519_keypair_convert_from_sign_ed25519(sign_index)
+            .await
+            .unwrap();
+        assert_eq!(2, index.0);
+        as_x25519!(entry);
+        assert_eq!(
+            x25519::x25519_pub_key_from_sign_ed25519(&sign.pub_key).unwrap(),
+            entry.pub_key
+        );
+
+        // converting again resolves to the existing entry,
+        // even when racing
+        let (r1, r2) = futures::future::join(
+            store.x25519_keypair_convert_from_sign_ed25519(sign_index),
+            store.x25519_keypair_convert_from_sign_ed25519(sign_index),
+        )
+        .await;
+        for r in [r1, r2].iter().flatten() {
+            assert_eq!(index, r.0);
+        }
+        assert_eq!(index, store.get_last_entry_index().await.unwrap());
+
+        // only ed25519 signature keypairs can be converted
+        assert!(store
+            .x25519_keypair_convert_from_sign_ed25519(index)
+            .await
+            .is_err());
+        assert!(store
+            .x25519_keypair_convert_from_sign_ed25519(42.into())
+            .await
+            .is_err());
+
+        let meta = store.get_entry_meta(index).await.unwrap();
+        assert_eq!(Some(sign_index), meta.converted_from);
+        assert_eq!(
+            None,
+            store
+                .get_entry_meta(sign_index)
+                .await
+                .unwrap()
+                .converted_from
+        );
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        // the conversion source survives a restart
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let meta = store.get_entry_meta(index).await.unwrap();
+        assert_eq!(Some(sign_index), meta.converted_from);
+        let (again, _) = store
+            .x25519_keypair_convert_from_sign_ed25519(sign_index)
+            .await
+            .unwrap();
+        assert_eq!(index, again);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_renew_tls_certs() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (old_index, old) = store
+            .tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        as_cert!(old);
+        let (new_index, new) = store
+            .tls_cert_renew(old_index, TlsCertOptions::default())
+            .await
+            .unwrap();
+        as_cert!(new);
+        assert_eq!(2, new_index.0);
+        assert_eq!(old.sni, new.sni);
+        assert_eq!(old.priv_key_der.0, new.priv_key_der.0);
+        assert_ne!(old.cert_digest, new.cert_digest);
+
+        // digests keep resolving their own entry, the sni the newest
+        let by_digest = |digest: CertDigest| {
+            let store = store.clone();
+            async move { store.get_entry_by_pub_id(digest.0).await.unwrap().0 }
+        };
+        assert_eq!(old_index, by_digest(old.cert_digest.clone()).await);
+        assert_eq!(new_index, by_digest(new.cert_digest.clone()).await);
+        assert_eq!(
+            new_index,
+            store.get_entry_by_sni(old.sni.clone()).await.unwrap().0
+        );
+
+        let meta = store.get_entry_meta(new_index).await.unwrap();
+        assert_eq!(Some(old_index), meta.renewed_from);
+        assert_eq!(
+            None,
+            store.get_entry_meta(old_index).await.unwrap().renewed_from
+        );
+
+        // only tls certs can be renewed
+        let (sign_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert!(store
+            .tls_cert_renew(sign_index, TlsCertOptions::default())
+            .await
+            .is_err());
+        assert!(store
+            .tls_cert_renew(42.into(), TlsCertOptions::default())
+            .await
+            .is_err());
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        // the renewal survives a restart, the newest cert keeps the sni
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let meta = store.get_entry_meta(new_index).await.unwrap();
+        assert_eq!(Some(old_index), meta.renewed_from);
+        assert_eq!(
+            new_index,
+            store.get_entry_by_sni(old.sni.clone()).await.unwrap().0
+        );
+
+        // deleting the renewed cert hands the sni back
+        store.delete_entry(new_index).await.unwrap();
+        assert_eq!(
+            old_index,
+            store.get_entry_by_sni(old.sni.clone()).await.unwrap().0
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_issue_tls_certs_from_a_ca() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+
+        let (ca_index, ca) = store
+            .tls_ca_new_self_signed(TlsCertOptions::default())
+            .await
+            .unwrap();
+        as_cert!(ca);
+        let (cert_index, cert, ca_cert_der) = store
+            .tls_cert_new_signed_by_ca(ca_index, TlsCertOptions::default())
+            .await
+            .unwrap();
+        as_cert!(cert);
+        assert_eq!(ca.cert_der, ca_cert_der);
+        assert_ne!(ca.sni, cert.sni);
+        assert_eq!(
+            cert_index,
+            store.get_entry_by_sni(cert.sni.clone()).await.unwrap().0
+        );
+
+        // issued certs outlive their CA
+        store.delete_entry(ca_index).await.unwrap();
+        assert!(matches!(
+            store
+                .tls_cert_new_signed_by_ca(ca_index, TlsCertOptions::default())
+                .await,
+            Err(LairError::EntryDeleted(_))
+        ));
+        let entry = store.get_entry_by_index(cert_index).await.unwrap();
+        as_cert!(entry);
+        assert_eq!(cert.cert_der, entry.cert_der);
+
+        // only CA certs can issue certs
+        assert!(matches!(
+            store
+                .tls_cert_new_signed_by_ca(
+                    cert_index,
+                    TlsCertOptions::default()
+                )
+                .await,
+            Err(LairError::TlsCertNotCa)
+        ));
+        let (sign_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert!(store
+            .tls_cert_new_signed_by_ca(sign_index, TlsCertOptions::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_find_tls_certs_by_digest_prefix() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let mut options = TlsCertOptions::default();
+        options.digest_alg = TlsCertDigestAlg::Sha256;
+        let (sha_index, sha) = store
+            .tls_cert_self_signed_new_from_entropy(options, None)
+            .await
+            .unwrap();
+        as_cert!(sha);
+        assert_eq!(TlsCertDigestAlg::Sha256, sha.digest_alg);
+        assert_eq!(
+            ring::digest::digest(&ring::digest::SHA256, &sha.cert_der).as_ref(),
+            &sha.cert_digest[..]
+        );
+        let (blake_index, blake) = store
+            .tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        as_cert!(blake);
+        assert_eq!(TlsCertDigestAlg::Blake2b256, blake.digest_alg);
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        // the digest alg survives a restart
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let by_prefix = |digest: &CertDigest| {
+            let store = store.clone();
+            let prefix = Arc::new(digest[..4].to_vec());
+            async move {
+                let (index, entry) =
+                    store.get_entry_by_digest_prefix(prefix).await.unwrap();
+                as_cert!(entry);
+                (index, entry.digest_alg)
+            }
+        };
+        assert_eq!(
+            (sha_index, TlsCertDigestAlg::Sha256),
+            by_prefix(&sha.cert_digest).await
+        );
+        assert_eq!(
+            (blake_index, TlsCertDigestAlg::Blake2b256),
+            by_prefix(&blake.cert_digest).await
+        );
+        match store.get_entry_by_digest_prefix(Arc::new(Vec::new())).await {
+            Err(LairError::TlsCertDigestPrefixAmbiguous(candidates)) => {
+                assert_eq!(2, candidates.len())
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+
+        // deleted certs are found no more
+        store.delete_entry(sha_index).await.unwrap();
+        let prefix = Arc::new(sha.cert_digest[..4].to_vec());
+        assert!(store.get_entry_by_digest_prefix(prefix).await.is_err());
+        assert_eq!(
+            blake_index,
+            store
+                .get_entry_by_digest_prefix(Arc::new(Vec::new()))
+                .await
+                .unwrap()
+                .0
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_import_tls_certs() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let mut options = TlsCertOptions::default();
+        options.sni = Some("lair.example.com".to_string());
+        options.alg = TlsCertAlg::PkcsEcdsaP256Sha256;
+        let external = tls::tls_cert_self_signed_new_from_entropy(options)
+            .await
+            .unwrap();
+        let (index, cert) = store
+            .tls_cert_import(
+                external.cert_der.clone(),
+                external.priv_key_der.clone(),
+                TlsCertOptions::default(),
+            )
+            .await
+            .unwrap();
+        as_cert!(cert);
+        assert_eq!("lair.example.com", cert.sni.as_str());
+        assert_eq!(external.cert_digest, cert.cert_digest);
+        assert!(store
+            .get_entry_meta(index)
+            .await
+            .unwrap()
+            .created_at
+            .is_some());
+
+        // the same cert can't be imported twice
+        assert!(matches!(
+            store
+                .tls_cert_import(
+                    external.cert_der.clone(),
+                    external.priv_key_der.clone(),
+                    TlsCertOptions::default(),
+                )
+                .await,
+            Err(LairError::DuplicatePubKey { existing_index }) if existing_index == index.0
+        ));
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        // the import survives a restart
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        let (sni_index, by_sni) =
+            store.get_entry_by_sni(cert.sni.clone()).await.unwrap();
+        as_cert!(by_sni);
+        assert_eq!(index, sni_index);
+        assert_eq!(external.priv_key_der, by_sni.priv_key_der);
+        assert_eq!(external.cert_der, by_sni.cert_der);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_import_seeds() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (index, entry) =
+            store.seed_import(vec![0xdb; 32], true).await.unwrap();
+        assert_eq!(1, index.0);
+        let seed_id = match &*entry {
+            LairEntry::Seed(e) => {
+                assert!(e.exportable);
+                e.seed_id.clone()
+            }
+            _ => panic!("unexpected"),
+        };
+        assert_eq!(seed::seed_id(&[0xdb; 32]), seed_id);
+
+        // the same seed can't be imported twice, exportable or not
+        assert!(matches!(
+            store.seed_import(vec![0xdb; 32], false).await,
+            Err(LairError::DuplicatePubKey { existing_index: 1 })
+        ));
+        assert!(store.seed_import(vec![0xdb; 16], false).await.is_err());
+
+        let (other_index, _) =
+            store.seed_import(vec![0xdc; 32], false).await.unwrap();
+        assert_eq!(2, other_index.0);
+
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        // the exportable flag survives a restart
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        for (index, exportable) in [(index, true), (other_index, false)] {
+            match &*store.get_entry_by_index(index).await.unwrap() {
+                LairEntry::Seed(e) => assert_eq!(exportable, e.exportable),
+                _ => panic!("unexpected"),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_store_secp256k1_keypairs() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (index, entry) =
+            store.secp256k1_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(1, index.0);
+        let pub_key = match &*entry {
+            LairEntry::SignSecp256k1(e) => e.pub_key.clone(),
+            _ => panic!("unexpected"),
+        };
+
+        // secp256k1 keys are found by the hash of their pub key
+        let (r_index, _) =
+            store.get_entry_by_pub_id(pub_key.pub_id()).await.unwrap();
+        assert_eq!(index, r_index);
+        assert!(store.get_entry_by_pub_id(pub_key.0.clone()).await.is_err());
+
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        match &*store.get_entry_by_index(index).await.unwrap() {
+            LairEntry::SignSecp256k1(e) => assert_eq!(pub_key, e.pub_key),
+            _ => panic!("unexpected"),
+        }
+        let (r_index, _) =
+            store.get_entry_by_pub_id(pub_key.pub_id()).await.unwrap();
+        assert_eq!(index, r_index);
+    }
+
+    #[cfg(feature = "bls")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_store_bls12381_keypairs() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config.clone(), store_file)
+            .await
+            .unwrap();
+
+        let (index, entry) =
+            store.bls12381_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(1, index.0);
+        let pub_key = match &*entry {
+            LairEntry::SignBls12381(e) => e.pub_key.clone(),
+            _ => panic!("unexpected"),
+        };
+
+        store.flush_and_close().await.unwrap();
+        drop(store);
+
+        let store_file = open_store_file(&config).await;
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        match &*store.get_entry_by_index(index).await.unwrap() {
+            LairEntry::SignBls12381(e) => assert_eq!(pub_key, e.pub_key),
+            _ => panic!("unexpected"),
+        }
+        let (r_index, _) =
+            store.get_entry_by_pub_id(pub_key.pub_id()).await.unwrap();
+        assert_eq!(index, r_index);
+    }
+
+    #[cfg(not(feature = "bls"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_cannot_store_bls12381_keypairs_without_the_feature() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_file = tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store = spawn_entry_store_actor(config, store_file).await.unwrap();
+        assert!(store.bls12381_keypair_new_from_entropy().await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_init_store_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        let open = || async {
+            let mut store_file = tokio::fs::OpenOptions::new();
+            store_file.read(true);
+            store_file.append(true);
+            store_file.create(true);
+            store_file.open(config.get_store_path()).await.unwrap()
+        };
+
+        init_store_file(&config, open().await, b"test".to_vec().into(), false)
+            .await
+            .unwrap();
+
+        let store = spawn_entry_store_actor(config.clone(), open().await)
+            .await
+            .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert!(store.unlock(b"nope".to_vec().into()).await.is_err());
+        store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        match init_store_file(
+            &config,
+            open().await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        {
+            Err(LairError::StoreAlreadyInitialized) => (),
+            oth => panic!("unexpected: {:?}", oth),
+        }
+
+        init_store_file(&config, open().await, b"test2".to_vec().into(), true)
+            .await
+            .unwrap();
+
+        let data = tokio::fs::read(config.get_store_path()).await.unwrap();
+        assert_eq!(HEADER_SIZE + entry::ENTRY_SIZE, data.len());
+        assert_eq!(Some(StoreVersion::CURRENT), StoreVersion::detect(&data));
+        let unlock = entry::EntryUnlock::decode(&data[HEADER_SIZE..])
+            .unwrap()
+            .unwrap();
+        assert!(unlock.verify_passphrase(b"test2".to_vec().into()).await);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_change_unlock_passphrase() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert!(store
+            .change_unlock_passphrase(
+                b"test".to_vec().into(),
+                b"test2".to_vec().into()
+            )
+            .await
+            .is_err());
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            true,
+        )
+        .await
+        .unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        assert!(matches!(
+            store.sign_ed25519_keypair_new_from_entropy(None).await,
+            Err(LairError::StoreLocked),
+        ));
+
+        assert!(store
+            .change_unlock_passphrase(
+                b"nope".to_vec().into(),
+                b"test2".to_vec().into()
+            )
+            .await
+            .is_err());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (_, first) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+
+        store
+            .change_unlock_passphrase(
+                b"test".to_vec().into(),
+                b"test2".to_vec().into(),
+            )
+            .await
+            .unwrap();
+        store.unlock(b"test2".to_vec().into()).await.unwrap();
+        assert!(store.unlock(b"test".to_vec().into()).await.is_err());
+
+        // writes go to the swapped in store file
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(2, index.0);
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let mut files = std::fs::read_dir(tmpdir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(vec!["store"], files);
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, store.get_last_entry_index().await.unwrap().0);
+        assert!(matches!(
+            store.get_entry_by_index(1.into()).await,
+            Err(LairError::StoreLocked),
+        ));
+        assert!(store.unlock(b"test".to_vec().into()).await.is_err());
+        store.unlock(b"test2".to_vec().into()).await.unwrap();
+        // the entries were resealed with the key of the new passphrase
+        let entry = store.get_entry_by_index(1.into()).await.unwrap();
+        assert_eq!(
+            first.export_public().unwrap(),
+            entry.export_public().unwrap(),
+        );
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_upgrades_the_unlock_kdf_on_unlock() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let with_kdf = |mem_kib, iterations| {
+            Config::builder()
+                .set_root_path(tmpdir.path())
+                .set_unlock_kdf(
+                    entry::UnlockKdf::argon2id(mem_kib, iterations, 1).unwrap(),
+                )
+                .build()
+        };
+        let stored_kdf = |config: &Config| {
+            let data = std::fs::read(config.get_store_path()).unwrap();
+            let unlock = &data[HEADER_SIZE..HEADER_SIZE + entry::ENTRY_SIZE];
+            entry::EntryUnlock::decode(unlock).unwrap().unwrap().kdf
+        };
+        let weak = with_kdf(64, 1);
+        tokio::fs::File::create(weak.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &weak,
+            open_store_file(&weak).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(weak.get_unlock_kdf(), stored_kdf(&weak));
+
+        let store =
+            spawn_entry_store_actor(weak.clone(), open_store_file(&weak).await)
+                .await
+                .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        store.flush_and_close().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // stronger settings upgrade the store as it is unlocked,
+        // weaker ones leave it as it is
+        for (config, expect) in &[
+            (with_kdf(128, 2), (128, 2)),
+            (with_kdf(64, 1), (128, 2)),
+            (with_kdf(64, 3), (128, 3)),
+        ] {
+            let store = spawn_entry_store_actor(
+                config.clone(),
+                open_store_file(config).await,
+            )
+            .await
+            .unwrap();
+            assert!(store.unlock(b"nope".to_vec().into()).await.is_err());
+            store.unlock(b"test".to_vec().into()).await.unwrap();
+            let entry = store.get_entry_by_index(index).await.unwrap();
+            assert_eq!(
+                sign.export_public().unwrap(),
+                entry.export_public().unwrap(),
+            );
+            store.flush_and_close().await.unwrap();
+            store.ghost_actor_shutdown().await.unwrap();
+            assert_eq!(
+                entry::UnlockKdf::argon2id(expect.0, expect.1, 1).unwrap(),
+                stored_kdf(config)
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn any_normal_form_of_the_passphrase_unlocks_the_store() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_unlock_kdf(entry::UnlockKdf::argon2id(64, 1, 1).unwrap())
+            .build();
+        // "é" as one composed code point, and as "e" and a combining accent
+        let composed = || "caf\u{e9}".to_string().into();
+        let decomposed = || "cafe\u{301}".to_string().into();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            composed(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(decomposed()).await.unwrap();
+        let (index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        store.flush_and_close().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert!(store.unlock(b"cafe".to_vec().into()).await.is_err());
+        store.unlock(composed()).await.unwrap();
+        let entry = store.get_entry_by_index(index).await.unwrap();
+        assert_eq!(
+            sign.export_public().unwrap(),
+            entry.export_public().unwrap(),
+        );
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_seals_entries_at_rest() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        assert!(store.unlock(b"nope".to_vec().into()).await.is_err());
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert_eq!(StoreLockState::Unlocked, store.lock_state().await.unwrap());
+
+        let (_, cert) = store
+            .tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions::default(),
+                Some("cert".to_string()),
+            )
+            .await
+            .unwrap();
+        as_cert!(cert);
+        let (_, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+        let (seed_index, seed) =
+            store.seed_new_from_entropy(true).await.unwrap();
+        as_seed!(seed);
+        store.flush_and_close().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let data = std::fs::read(config.get_store_path()).unwrap();
+        let find =
+            |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        assert!(!find(&cert.priv_key_der));
+        assert!(!find(&sign.priv_key[..]));
+        assert!(!find(&seed.seed[..]));
+        assert!(find(&sign.pub_key[..]));
+
+        // a reopened store is locked until unlocked
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        assert_eq!(3, store.get_last_entry_index().await.unwrap().0);
+        assert!(matches!(
+            store.get_entry_by_tag("cert".to_string()).await,
+            Err(LairError::StoreLocked),
+        ));
+        assert!(matches!(
+            store.x25519_keypair_new_from_entropy().await,
+            Err(LairError::StoreLocked),
+        ));
+        assert!(store.unlock(b"nope".to_vec().into()).await.is_err());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (_, entry) =
+            store.get_entry_by_tag("cert".to_string()).await.unwrap();
+        as_cert!(entry);
+        assert_eq!(cert.priv_key_der, entry.priv_key_der);
+        assert_eq!(cert.cert_digest, entry.cert_digest);
+        let entry = store.get_entry_by_index(seed_index).await.unwrap();
+        as_seed!(entry);
+        assert_eq!(seed.seed, entry.seed);
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_locks_again() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_auto_lock_after_idle(std::time::Duration::from_millis(300))
+            .build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        // locking a locked store changes nothing
+        store.lock().await.unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("sign".to_string()))
+            .await
+            .unwrap();
+        as_sign!(sign);
+        let mut changes = store.subscribe_changes().await.unwrap();
+
+        store.lock().await.unwrap();
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        assert!(matches!(changes.recv().await, Ok(StoreChange::Locked)));
+        assert!(matches!(
+            store.get_entry_by_index(index).await,
+            Err(LairError::StoreLocked),
+        ));
+        assert!(matches!(
+            store.get_entry_by_tag("sign".to_string()).await,
+            Err(LairError::StoreLocked),
+        ));
+        assert!(matches!(
+            store.seed_new_from_entropy(false).await,
+            Err(LairError::StoreLocked),
+        ));
+
+        // wrong passphrases are counted, and held up
+        let start = std::time::Instant::now();
+        assert!(store.unlock(b"nope".to_vec().into()).await.is_err());
+        assert!(store.unlock(b"nope".to_vec().into()).await.is_err());
+        assert!(start.elapsed() >= UNLOCK_RETRY_DELAY * 3);
+        assert_eq!(2, store.failed_unlock_count().await.unwrap());
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert!(matches!(changes.recv().await, Ok(StoreChange::Unlocked)));
+        let (_, entry) =
+            store.get_entry_by_tag("sign".to_string()).await.unwrap();
+        as_sign!(entry);
+        assert_eq!(sign.priv_key, entry.priv_key);
+
+        // and once unused for long enough, locks by itself
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        store.get_entry_by_index(index).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(StoreLockState::Unlocked, store.lock_state().await.unwrap());
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+
+        // entries written before locking are still there, sealed
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        store.delete_entry(index).await.unwrap();
+        store.lock().await.unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert!(matches!(
+            store.get_entry_by_index(index).await,
+            Err(LairError::EntryDeleted(_)),
+        ));
+
+        // a store without sealed entries cannot be locked
+        let config = Config::builder().set_ephemeral(true).build();
+        let ephemeral =
+            spawn_ephemeral_entry_store_actor(config).await.unwrap();
+        assert!(ephemeral.lock().await.is_err());
+
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_keeps_loading_past_corrupt_entries() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+
+        // flip a byte in the middle of the entry on disk
+        let corrupt = |path: &std::path::Path, index: u32| {
+            let mut data = std::fs::read(path).unwrap();
+            let at = HEADER_SIZE + index as usize * entry::ENTRY_SIZE + 500;
+            data[at] ^= 0x01;
+            std::fs::write(path, &data).unwrap();
+        };
+
+        // a store without an unlock passphrase keeps entries unsealed
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        let mut signs = Vec::new();
+        for _ in 0..3 {
+            signs.push(
+                store
+                    .sign_ed25519_keypair_new_from_entropy(None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        corrupt(config.get_store_path(), signs[1].0 .0);
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, store.corrupt_entry_count().await.unwrap());
+        assert_eq!(3, store.get_last_entry_index().await.unwrap().0);
+        assert!(matches!(
+            store.get_entry_by_index(signs[1].0).await,
+            Err(LairError::EntryCorrupt(2)),
+        ));
+        assert_eq!(2, store.list_entries().await.unwrap().len());
+        for (index, sign) in [signs[0].clone(), signs[2].clone()] {
+            as_sign!(sign);
+            let entry = store.get_entry_by_index(index).await.unwrap();
+            as_sign!(entry);
+            assert_eq!(sign.pub_key, entry.pub_key);
+        }
+
+        // the corrupt index is never reused
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(4, index.0);
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // the seal of sealed entries is checked as the store unlocks
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_store_path(tmpdir.path().join("sealed"))
+            .build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (seed_index, _) = store.seed_new_from_entropy(false).await.unwrap();
+        let (sign_index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        corrupt(config.get_store_path(), seed_index.0);
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(0, store.corrupt_entry_count().await.unwrap());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert_eq!(1, store.corrupt_entry_count().await.unwrap());
+        assert!(matches!(
+            store.get_entry_by_index(seed_index).await,
+            Err(LairError::EntryCorrupt(_)),
+        ));
+        let entry = store.get_entry_by_index(sign_index).await.unwrap();
+        as_sign!(entry);
+        assert_eq!(sign.pub_key, entry.pub_key);
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_compacts_deleted_entries_keeping_indices() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        let store_len = || {
+            std::fs::metadata(config.get_store_path()).unwrap().len() as usize
+        };
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        let mut signs = Vec::new();
+        for _ in 0..5 {
+            signs.push(
+                store
+                    .sign_ed25519_keypair_new_from_entropy(None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        store.delete_entry(2.into()).await.unwrap();
+        store.delete_entry(4.into()).await.unwrap();
+        assert_eq!(HEADER_SIZE + 6 * entry::ENTRY_SIZE, store_len());
+
+        let info = store.compact_store().await.unwrap();
+        assert_eq!(2, info.entries_removed);
+        assert_eq!(entry::ENTRY_SIZE as u64, info.bytes_reclaimed);
+        // header, unlock, one compacted block, three entries
+        assert_eq!(HEADER_SIZE + 5 * entry::ENTRY_SIZE, store_len());
+        assert_eq!(
+            LairCompactInfo::default(),
+            store.compact_store().await.unwrap()
+        );
+
+        let check_entries =
+            |store: ghost_actor::GhostSender<EntryStore>,
+             signs: Vec<(KeystoreIndex, Arc<LairEntry>)>| {
+                async move {
+                    for (index, sign) in signs {
+                        as_sign!(sign);
+                        match index.0 {
+                            2 | 4 => assert!(matches!(
+                                store.get_entry_by_index(index).await,
+                                Err(LairError::EntryDeleted(_)),
+                            )),
+                            _ => {
+                                let entry = store
+                                    .get_entry_by_index(index)
+                                    .await
+                                    .unwrap();
+                                as_sign!(entry);
+                                assert_eq!(sign.pub_key, entry.pub_key);
+                            }
+                        }
+                    }
+                }
+            };
+        check_entries(store.clone(), signs.clone()).await;
+
+        // entries after the compacted ones are still rewritten in place,
+        // and deleted indices are never reused
+        store
+            .update_entry_tag(5.into(), "five".to_string())
+            .await
+            .unwrap();
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(6, index.0);
+        store.delete_entry(index).await.unwrap();
+        assert_eq!(1, store.compact_store().await.unwrap().entries_removed);
+        let (index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(7, index.0);
+        signs.push((index, sign));
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+        assert_eq!(
+            vec![1, 3, 5, 7],
+            check.entries.iter().map(|e| e.index.0).collect::<Vec<_>>()
+        );
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(7, store.get_last_entry_index().await.unwrap().0);
+        assert_eq!(4, store.list_entries().await.unwrap().len());
+        assert_eq!(
+            5,
+            store
+                .get_entry_by_tag("five".to_string())
+                .await
+                .unwrap()
+                .0
+                 .0
+        );
+        check_entries(store.clone(), signs).await;
+        assert!(matches!(
+            store.get_entry_by_index(6.into()).await,
+            Err(LairError::EntryDeleted(6)),
+        ));
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_recovers_from_interrupted_entry_writes() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let store_path = config.get_store_path().to_owned();
+        let staging = store_file::staging_path(&store_path);
+        let appending = store_file::appending_path(&store_path);
+        tokio::fs::File::create(&store_path).await.unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        let mut committed = Vec::new();
+        for _ in 0..2 {
+            let (_, sign) = store
+                .sign_ed25519_keypair_new_from_entropy(None)
+                .await
+                .unwrap();
+            as_sign!(sign);
+            committed.push(sign.pub_key.clone());
+        }
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        assert!(!staging.exists());
+        assert!(!appending.exists());
+        let base = std::fs::read(&store_path).unwrap();
+
+        // the entry being written as the process dies
+        let sign = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let new_entry = LairEntry::from(entry::EntrySignEd25519 {
+            priv_key: sign.priv_key,
+            pub_key: sign.pub_key.clone(),
+        })
+        .encode()
+        .unwrap();
+        let journal = store_file::encode_appending(3.into(), &new_entry);
+
+        let load = || async {
+            let store =
+                spawn_store_backend(&config, open_store_file(&config).await)
+                    .await
+                    .unwrap();
+            store.init_load_unlock().await.unwrap();
+            let entries = store.load_all_entries().await.unwrap();
+            store.close().await.unwrap();
+            assert!(!staging.exists());
+            assert!(!appending.exists());
+            entries
+                .into_iter()
+                .map(|(_, data)| match LairEntry::decode(&data).unwrap() {
+                    LairEntry::SignEd25519(e) => e.pub_key,
+                    e => panic!("unexpected entry: {:?}", e),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut with_new = committed.clone();
+        with_new.push(sign.pub_key);
+
+        for cut in 0..=entry::ENTRY_SIZE {
+            // dying while writing the journal leaves the store as it was
+            std::fs::write(&store_path, &base).unwrap();
+            std::fs::write(&staging, &journal[..cut]).unwrap();
+            assert_eq!(committed, load().await, "staging cut at {}", cut);
+
+            // dying while appending the committed entry finishes it
+            let mut data = base.clone();
+            data.extend_from_slice(&new_entry[..cut]);
+            std::fs::write(&store_path, &data).unwrap();
+            std::fs::write(&appending, &journal).unwrap();
+            assert_eq!(with_new, load().await, "appending cut at {}", cut);
+
+            // a partial entry without a journal was never committed
+            if cut < entry::ENTRY_SIZE {
+                std::fs::write(&store_path, &data).unwrap();
+                assert_eq!(committed, load().await, "entry cut at {}", cut);
+            }
+        }
+
+        // the store is usable after, the cut off entry index is reused
+        std::fs::write(&store_path, &base).unwrap();
+        std::fs::write(&appending, &journal[..100]).unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, store.list_entries().await.unwrap().len());
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(3, index.0);
+        store.ghost_actor_shutdown().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_delete_entries() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (cert_index, cert) = store
+            .tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        as_cert!(cert);
+        let (sign_index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+
+        let find = |id: Vec<u8>| {
+            let store = store.clone();
+            async move {
+                store
+                    .find_entry(Arc::new(id))
+                    .await
+                    .unwrap()
+                    .map(|(idx, _)| idx)
+            }
+        };
+        assert_eq!(Some(cert_index), find(cert.cert_digest.to_vec()).await);
+        assert_eq!(Some(cert_index), find(cert.sni.as_bytes().to_vec()).await);
+        assert_eq!(Some(sign_index), find(sign.pub_key.to_vec()).await);
+        assert_eq!(None, find(vec![0xdb; 32]).await);
+
+        store.delete_entry(cert_index).await.unwrap();
+        assert_eq!(None, find(cert.cert_digest.to_vec()).await);
+        assert_eq!(None, find(cert.sni.as_bytes().to_vec()).await);
+        // deleting twice is fine, unknown indices are not
+        store.delete_entry(cert_index).await.unwrap();
+        assert!(store.delete_entry(42.into()).await.is_err());
+
+        match store.get_entry_by_index(cert_index).await {
+            Err(LairError::EntryDeleted(idx)) => assert_eq!(cert_index.0, idx),
+            o => panic!("unexpected: {:?}", o),
+        }
+        assert!(store
+            .get_entry_by_pub_id(cert.cert_digest.0.clone())
+            .await
+            .is_err());
+        assert!(store.get_entry_by_sni(cert.sni.clone()).await.is_err());
+        assert_eq!(1, store.list_entries().await.unwrap().len());
+
+        // indices are not reused
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(3, index.0);
+        store.flush_and_close().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // the key material is gone from disk,
+        // that of the live entry is only there sealed
+        let data = std::fs::read(config.get_store_path()).unwrap();
+        let find =
+            |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        assert!(!find(&cert.priv_key_der));
+        assert!(!find(&cert.cert_der));
+        assert!(!find(&sign.priv_key[..]));
+        assert!(find(&sign.pub_key[..]));
+
+        // tombstones survive a restart
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert_eq!(3, store.get_last_entry_index().await.unwrap().0);
+        assert!(matches!(
+            store.get_entry_by_index(cert_index).await,
+            Err(LairError::EntryDeleted(_))
+        ));
+        let (index, _) = store
+            .get_entry_by_pub_id(sign.pub_key.0.clone())
+            .await
+            .unwrap();
+        assert_eq!(sign_index, index);
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_tag_entries() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (sign_index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("agent".to_string()))
+            .await
+            .unwrap();
+        as_sign!(sign);
+        let (cert_index, _) = store
+            .tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions::default(),
+                Some("cert".to_string()),
+            )
+            .await
+            .unwrap();
+
+        // tags are unique
+        match store
+            .sign_ed25519_keypair_new_from_entropy(Some("cert".to_string()))
+            .await
+        {
+            Err(LairError::EntryTagInUse(tag)) => assert_eq!("cert", tag),
+            o => panic!("unexpected: {:?}", o),
+        }
+        assert!(store
+            .sign_ed25519_keypair_new_from_entropy(Some("".to_string()))
+            .await
+            .is_err());
+        assert_eq!(2, store.get_last_entry_index().await.unwrap().0);
+
+        let (index, _) =
+            store.get_entry_by_tag("agent".to_string()).await.unwrap();
+        assert_eq!(sign_index, index);
+        assert!(matches!(
+            store.get_entry_by_tag("nope".to_string()).await,
+            Err(LairError::EntryTagNotFound(_))
+        ));
+        let sign_meta = store.get_entry_meta(sign_index).await.unwrap();
+        assert_eq!(Some("agent".to_string()), sign_meta.tag);
+        assert!(sign_meta.created_at.unwrap() > 0);
+        store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // tags survive a restart
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let tags = store
+            .list_entries()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, meta)| meta.tag)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![Some("agent".to_string()), Some("cert".to_string()), None],
+            tags
+        );
+        let (index, entry) =
+            store.get_entry_by_tag("agent".to_string()).await.unwrap();
+        assert_eq!(sign_index, index);
+        as_sign!(entry);
+        assert_eq!(sign.pub_key, entry.pub_key);
+        assert_eq!(sign_meta, store.get_entry_meta(sign_index).await.unwrap());
+
+        // deleting an entry frees its tag
+        store.delete_entry(cert_index).await.unwrap();
+        assert!(matches!(
+            store.get_entry_meta(cert_index).await,
+            Err(LairError::EntryDeleted(_))
+        ));
+        assert!(store.get_entry_by_tag("cert".to_string()).await.is_err());
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("cert".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            index,
+            store.get_entry_by_tag("cert".to_string()).await.unwrap().0
+        );
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_record_entry_usage() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        let (a_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        let (b_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("b".into()))
+            .await
+            .unwrap();
+        let (c_index, _) =
+            store.x25519_keypair_new_from_entropy().await.unwrap();
+
+        for _ in 0..3 {
+            store.record_entry_use(a_index).await.unwrap();
+        }
+        store.record_entry_use(b_index).await.unwrap();
+        let meta = store.get_entry_meta(a_index).await.unwrap();
+        assert_eq!(3, meta.use_count);
+        assert!(meta.last_used.unwrap() >= meta.created_at.unwrap());
+        assert_eq!(
+            None,
+            store.get_entry_meta(c_index).await.unwrap().last_used
+        );
+        store.flush_entry_usage().await.unwrap();
+
+        // a rename / delete racing a flush is not overwritten by it
+        store.record_entry_use(a_index).await.unwrap();
+        store.record_entry_use(b_index).await.unwrap();
+        store.record_entry_use(c_index).await.unwrap();
+        let (r1, r2, r3) = futures::future::join3(
+            store.flush_entry_usage(),
+            store.delete_entry(c_index),
+            store.update_entry_tag(b_index, "b2".into()),
+        )
+        .await;
+        r1.unwrap();
+        r2.unwrap();
+        r3.unwrap();
+        // uses of deleted entries are ignored
+        store.record_entry_use(c_index).await.unwrap();
+        assert_eq!(2, store.get_entry_meta(b_index).await.unwrap().use_count);
+
+        // usage not yet flushed is written on close
+        store.record_entry_use(a_index).await.unwrap();
+        store.flush_and_close().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        let meta = store.get_entry_meta(a_index).await.unwrap();
+        assert_eq!(5, meta.use_count);
+        let meta = store.get_entry_meta(b_index).await.unwrap();
+        assert_eq!(2, meta.use_count);
+        assert_eq!(Some("b2".to_string()), meta.tag);
+        assert!(matches!(
+            store.get_entry_meta(c_index).await,
+            Err(LairError::EntryDeleted(_))
+        ));
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_update_entry_tags() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (a_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("test-agent".into()))
+            .await
+            .unwrap();
+        let (b_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        let created_at =
+            store.get_entry_meta(a_index).await.unwrap().created_at;
+
+        store
+            .update_entry_tag(a_index, "prod-agent".into())
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.get_entry_by_tag("test-agent".into()).await,
+            Err(LairError::EntryTagNotFound(_))
+        ));
+        let (index, _) =
+            store.get_entry_by_tag("prod-agent".into()).await.unwrap();
+        assert_eq!(a_index, index);
+        // renaming keeps the rest of the metadata
+        assert_eq!(
+            created_at,
+            store.get_entry_meta(a_index).await.unwrap().created_at
+        );
+        // renaming to the current tag is a no-op
+        store
+            .update_entry_tag(a_index, "prod-agent".into())
+            .await
+            .unwrap();
+
+        // concurrent renames to the same tag, the first one in wins
+        let (r1, r2) = futures::future::join(
+            store.update_entry_tag(b_index, "shared".into()),
+            store.update_entry_tag(a_index, "shared".into()),
+        )
+        .await;
+        r1.unwrap();
+        assert!(matches!(r2, Err(LairError::EntryTagInUse(_))));
+        assert!(matches!(
+            store.update_entry_tag(a_index, "shared".into()).await,
+            Err(LairError::EntryTagInUse(_))
+        ));
+        assert!(store.update_entry_tag(42.into(), "x".into()).await.is_err());
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+        drop(store);
+
+        // renames survive a restart
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let tags = store
+            .list_entries()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, meta)| meta.tag)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![Some("prod-agent".to_string()), Some("shared".to_string())],
+            tags
+        );
+        assert!(store.get_entry_by_tag("test-agent".into()).await.is_err());
+        store.delete_entry(b_index).await.unwrap();
+        assert!(matches!(
+            store.update_entry_tag(b_index, "other".into()).await,
+            Err(LairError::EntryDeleted(_))
+        ));
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+    }
+
+    /// a v1 store: zeroed stub unlock block, then entry blocks, no header
+    async fn write_v1_fixture(config: &Config) -> Vec<entry::EntrySignEd25519> {
+        let mut data = vec![0; entry::ENTRY_SIZE];
+        let mut entries = Vec::new();
+        for _ in 0..2 {
+            let e = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+                .await
+                .unwrap();
+            data.extend_from_slice(&e.encode().unwrap());
+            entries.push(e);
+        }
+        tokio::fs::write(config.get_store_path(), data)
+            .await
+            .unwrap();
+        entries
+    }
+
+    async fn open_store_file(config: &Config) -> tokio::fs::File {
+        let mut store_file = tokio::fs::OpenOptions::new();
+        store_file.read(true);
+        store_file.append(true);
+        store_file.open(config.get_store_path()).await.unwrap()
+    }
+
+    fn config_of_kind(root: &std::path::Path, kind: StoreKind) -> Arc<Config> {
+        Config::builder()
+            .set_root_path(root)
+            .set_store_kind(kind)
+            .build()
+    }
+
+    /// the requests every kind of store backend must serve alike
+    async fn check_store_backend(config: Arc<Config>) {
+        let store_path = config.get_store_path().to_owned();
+        let backend = || async {
+            let store_file = tokio::fs::OpenOptions::new()
+                .read(true)
+                .append(true)
+                .create(true)
+                .open(&store_path)
+                .await
+                .unwrap();
+            spawn_store_backend(&config, store_file).await.unwrap()
+        };
+        async fn encode(tag: &str) -> Vec<u8> {
+            let e = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+                .await
+                .unwrap();
+            let meta = entry::EntryMeta::with_tag(Some(tag.to_string()));
+            LairEntry::from(e).encode_with_meta(&meta).unwrap()
+        }
+        // (encodings differ by their random padding)
+        let decode = |data: &[u8], key: &entry::EntryStoreKey| {
+            let (entry, meta) = LairEntry::unseal_with_meta(data, key).unwrap();
+            match entry {
+                LairEntry::SignEd25519(e) => {
+                    (e.priv_key[..].to_vec(), e.pub_key.to_vec(), meta)
+                }
+                _ => panic!("unexpected"),
+            }
+        };
+
+        let store = backend().await;
+        assert!(store.init_load_unlock().await.unwrap().is_none());
+        assert!(store.load_all_entries().await.unwrap().is_empty());
+
+        let (unlock, key) = entry::EntryUnlock::new_from_passphrase(
+            b"test".to_vec().into(),
+            entry::UnlockKdf::default(),
+        )
+        .await
+        .unwrap();
+        let (unlock, key) = (unlock.encode().unwrap(), Arc::new(key));
+        store.write_unlock(unlock.clone()).await.unwrap();
+        assert_eq!(Some(unlock), store.init_load_unlock().await.unwrap());
+
+        let mut entries = Vec::new();
+        for tag in &["a", "b", "c"] {
+            let data = encode(tag).await;
+            let index = store.write_next_entry(data.clone()).await.unwrap();
+            entries.push((index, data));
+        }
+        assert_eq!(
+            vec![1, 2, 3],
+            entries.iter().map(|(i, _)| i.0).collect::<Vec<_>>()
+        );
+        assert_eq!(entries, store.load_all_entries().await.unwrap());
+
+        let tombstone = entry::EntryDeleted.encode().unwrap();
+        store
+            .replace_entry(store_path.clone(), 2.into(), tombstone.clone())
+            .await
+            .unwrap();
+        entries[1].1 = tombstone;
+
+        // a batch with a missing entry replaces none of them
+        let replaced = encode("d").await;
+        assert!(store
+            .replace_entries(
+                store_path.clone(),
+                vec![(1.into(), replaced.clone()), (9.into(), replaced)],
+            )
+            .await
+            .is_err());
+        assert!(store.write_next_entry(vec![0; 10]).await.is_err());
+        assert_eq!(entries, store.load_all_entries().await.unwrap());
+
+        // sealing keeps the entries, and seals those written after it
+        store
+            .reseal_all(store_path.clone(), key.clone(), None)
+            .await
+            .unwrap();
+        let data = encode("e").await;
+        assert_eq!(4, store.write_next_entry(data.clone()).await.unwrap().0);
+        entries.push((4.into(), data));
+        let loaded = store.load_all_entries().await.unwrap();
+        assert_eq!(entries.len(), loaded.len());
+        for ((index, data), (l_index, l_data)) in entries.iter().zip(&loaded) {
+            assert_eq!(index, l_index);
+            if entry::EntryDeleted::is_deleted(data) {
+                assert_eq!(data, l_data);
+                continue;
+            }
+            assert!(LairEntry::is_sealed(l_data));
+            assert_eq!(decode(data, &key), decode(l_data, &key));
+        }
+        store.close().await.unwrap();
+
+        // rekeying replaces the unlock entry, and survives a reopen
+        let store = backend().await;
+        let (unlock2, key2) = entry::EntryUnlock::new_from_passphrase(
+            b"test2".to_vec().into(),
+            entry::UnlockKdf::default(),
+        )
+        .await
+        .unwrap();
+        let (unlock2, key2) = (unlock2.encode().unwrap(), Arc::new(key2));
+        assert!(store
+            .reseal_all(store_path.clone(), key2.clone(), Some(unlock2.clone()))
+            .await
+            .is_err());
+        store
+            .reseal_all(store_path.clone(), key.clone(), None)
+            .await
+            .unwrap();
+        store
+            .reseal_all(store_path.clone(), key2.clone(), Some(unlock2.clone()))
+            .await
+            .unwrap();
+        store.close().await.unwrap();
+
+        let store = backend().await;
+        assert_eq!(Some(unlock2), store.init_load_unlock().await.unwrap());
+        let reloaded = store.load_all_entries().await.unwrap();
+        for ((_, data), (_, r_data)) in loaded.iter().zip(&reloaded) {
+            if entry::EntryDeleted::is_deleted(data) {
+                continue;
+            }
+            assert_eq!(decode(data, &key), decode(r_data, &key2));
+        }
+
+        // compaction keeps the indices of the entries it keeps, and
+        // loads deleted entries as tombstones (the sqlite store keeps
+        // its last row, whatever it is)
+        let tombstone = entry::EntryDeleted.encode().unwrap();
+        store
+            .replace_entry(store_path.clone(), 4.into(), tombstone.clone())
+            .await
+            .unwrap();
+        assert!(
+            store
+                .compact(store_path.clone())
+                .await
+                .unwrap()
+                .entries_removed
+                > 0
+        );
+        assert_eq!(
+            entries.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            store
+                .load_all_entries()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(i, data)| {
+                    if i.0 == 2 || i.0 == 4 {
+                        assert!(entry::EntryDeleted::is_deleted(&data));
+                    }
+                    i
+                })
+                .collect::<Vec<_>>(),
+        );
+        store
+            .replace_entry(store_path.clone(), 3.into(), reloaded[2].1.clone())
+            .await
+            .unwrap();
+        let data = encode("f").await;
+        assert_eq!(5, store.write_next_entry(data).await.unwrap().0);
+        store.close().await.unwrap();
+
+        let store = backend().await;
+        store.init_load_unlock().await.unwrap();
+        assert_eq!(
+            vec![1, 2, 3, 4, 5],
+            store
+                .load_all_entries()
+                .await
+                .unwrap()
+                .iter()
+                .map(|(i, _)| i.0)
+                .collect::<Vec<_>>()
+        );
+
+        store.truncate().await.unwrap();
+        assert!(store.init_load_unlock().await.unwrap().is_none());
+        assert!(store.load_all_entries().await.unwrap().is_empty());
+        store.close().await.unwrap();
     }
 
-    fn handle_get_entry_by_sni(
-        &mut self,
-        sni: CertSni,
-    ) -> EntryStoreHandlerResult<(KeystoreIndex, Arc<LairEntry>)> {
-        match self.entries_by_sni.get(&sni) {
-            Some(entry) => {
-                let entry = entry.clone();
-                Ok(async move { Ok(entry) }.boxed().into())
+    #[tokio::test(flavor = "multi_thread")]
+    async fn file_store_backend_serves_store_requests() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        check_store_backend(config_of_kind(tmpdir.path(), StoreKind::File))
+            .await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sqlite_store_backend_serves_store_requests() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        check_store_backend(config_of_kind(tmpdir.path(), StoreKind::Sqlite))
+            .await;
+    }
+
+    async fn check_config_entry_store(kind: StoreKind) {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = config_of_kind(tmpdir.path(), kind);
+        let store = ConfigEntryStore::open(&config).await.unwrap();
+        conformance::check_entry_store_backend(Box::new(store)).await;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = config_of_kind(tmpdir.path(), kind);
+        conformance::check_entry_store_backend_durability(|| {
+            let config = config.clone();
+            async move {
+                let store = ConfigEntryStore::open(&config).await?;
+                Ok(Box::new(store) as Box<dyn EntryStoreBackend>)
             }
-            None => Err(format!("invalid sni: {:?}", sni).into()),
-        }
+        })
+        .await;
     }
-}
 
-impl ghost_actor::GhostHandler<EntryStoreInternal> for EntryStoreImpl {}
+    #[tokio::test(flavor = "multi_thread")]
+    async fn file_entry_store_conforms() {
+        check_config_entry_store(StoreKind::File).await;
+    }
 
-impl EntryStoreInternalHandler for EntryStoreImpl {
-    fn handle_finalize_new_entry(
-        &mut self,
-        entry_index: KeystoreIndex,
-        entry: Arc<LairEntry>,
-    ) -> EntryStoreInternalHandlerResult<()> {
-        self.track_new_entry(entry_index, entry);
-        Ok(async move { Ok(()) }.boxed().into())
+    #[cfg(feature = "sqlite")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sqlite_entry_store_conforms() {
+        check_config_entry_store(StoreKind::Sqlite).await;
     }
-}
 
-async fn new_tls_cert(
-    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
-    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
-    options: TlsCertOptions,
-) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
-    let cert = Arc::new(LairEntry::TlsCert(
-        tls::tls_cert_self_signed_new_from_entropy(options).await?,
-    ));
-    let encoded_cert = cert.encode()?;
-    let entry_index = store_file.write_next_entry(encoded_cert).await?;
-    i_s.finalize_new_entry(entry_index, cert.clone()).await?;
-    Ok((entry_index, cert))
-}
+    #[tokio::test(flavor = "multi_thread")]
+    async fn memory_entry_store_conforms() {
+        conformance::check_entry_store_backend(Box::new(
+            MemoryEntryStore::default(),
+        ))
+        .await;
+    }
 
-async fn new_sign_ed25519_keypair(
-    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
-    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
-) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
-    let entry = Arc::new(LairEntry::SignEd25519(
-        sign_ed25519::sign_ed25519_keypair_new_from_entropy().await?,
-    ));
-    let encoded_entry = entry.encode()?;
-    let entry_index = store_file.write_next_entry(encoded_entry).await?;
-    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
-    Ok((entry_index, entry))
-}
+    #[tokio::test(flavor = "multi_thread")]
+    async fn ephemeral_store_writes_nothing() {
+        use ghost_actor::GhostControlSender;
 
-async fn new_x25519_keypair(
-    i_s: ghost_actor::GhostSender<EntryStoreInternal>,
-    store_file: futures::channel::mpsc::Sender<store_file::EntryStoreFile>,
-) -> LairResult<(KeystoreIndex, Arc<LairEntry>)> {
-    let entry = Arc::new(LairEntry::X25519(
-        x25519::x25519_keypair_new_from_entropy().await?,
-    ));
-    let encoded_entry = entry.encode()?;
-    let entry_index = store_file.write_next_entry(encoded_entry).await?;
-    i_s.finalize_new_entry(entry_index, entry.clone()).await?;
-    Ok((entry_index, entry))
-}
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path().join("lair"))
+            .set_ephemeral(true)
+            .build();
+        let store = spawn_ephemeral_entry_store_actor(config.clone())
+            .await
+            .unwrap();
+        assert!(store.unlock(Vec::new().into()).await.is_err());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let (sign_index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("agent".into()))
+            .await
+            .unwrap();
+        let (deleted_index, _) =
+            store.x25519_keypair_new_from_entropy().await.unwrap();
+        store.delete_entry(deleted_index).await.unwrap();
+        assert_eq!(1, store.compact_store().await.unwrap().entries_removed);
+        let (index, _) = store.get_entry_by_tag("agent".into()).await.unwrap();
+        assert_eq!(sign_index, index);
+        let (next_index, _) =
+            store.x25519_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(deleted_index.0 + 1, next_index.0);
 
-    macro_rules! as_cert {
-        ($e:ident) => {
-            let $e = match &*$e {
-                LairEntry::TlsCert(e) => e,
-                _ => panic!("unexpected"),
-            };
+        // the passphrase that created it still guards it
+        assert!(store.unlock(b"other".to_vec().into()).await.is_err());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+
+        store.flush_and_close().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        assert_eq!(0, std::fs::read_dir(tmpdir.path()).unwrap().count());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_convert_between_store_kinds() {
+        use ghost_actor::GhostControlSender;
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = config_of_kind(tmpdir.path(), StoreKind::File);
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (sign_index, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(Some("agent".into()))
+            .await
+            .unwrap();
+        as_sign!(sign);
+        let (deleted_index, _) =
+            store.x25519_keypair_new_from_entropy().await.unwrap();
+        store.delete_entry(deleted_index).await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let sqlite = config_of_kind(tmpdir.path(), StoreKind::Sqlite);
+        let res = convert_store_file(
+            &config,
+            open_store_file(&config).await,
+            &sqlite,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, res.entries);
+        assert_eq!(sqlite.get_store_path(), res.path);
+        assert!(convert_store_file(
+            &config,
+            open_store_file(&config).await,
+            &sqlite
+        )
+        .await
+        .is_err());
+
+        let pub_id_of_tag = |tag: &str| {
+            let con =
+                rusqlite::Connection::open(sqlite.get_store_path()).unwrap();
+            con.query_row(
+                "SELECT pub_id FROM entries WHERE tag = ?1",
+                rusqlite::params![tag],
+                |r| r.get::<_, Vec<u8>>(0),
+            )
+            .ok()
         };
+
+        // the entries were copied sealed, they are indexed once unlocked
+        assert_eq!(None, pub_id_of_tag("agent"));
+        let store = spawn_entry_store_actor(
+            sqlite.clone(),
+            open_store_file(&sqlite).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (r_index, r_sign) =
+            store.get_entry_by_tag("agent".into()).await.unwrap();
+        as_sign!(r_sign);
+        assert_eq!(sign_index, r_index);
+        assert_eq!(sign.pub_key, r_sign.pub_key);
+        assert!(matches!(
+            store.get_entry_by_index(deleted_index).await,
+            Err(LairError::EntryDeleted(_))
+        ));
+        store.ghost_actor_shutdown().await.unwrap();
+        assert_eq!(Some(sign.pub_key.to_vec()), pub_id_of_tag("agent"));
+
+        let check = check_store(&sqlite).await.unwrap();
+        assert_eq!(2, check.entries.len());
+        assert_eq!(0, check.problem_count());
+        assert!(repair_store_file(&sqlite).await.is_err());
+
+        // and back again, with the entries unchanged
+        let file2 = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_store_path("store2")
+            .build();
+        convert_store_file(&sqlite, open_store_file(&sqlite).await, &file2)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(config.get_store_path()).unwrap(),
+            std::fs::read(file2.get_store_path()).unwrap()
+        );
     }
 
-    macro_rules! as_sign {
-        ($e:ident) => {
-            let $e = match &*$e {
-                LairEntry::SignEd25519(e) => e,
-                _ => panic!("unexpected"),
-            };
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_migrate_v1_store_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let entries = write_v1_fixture(&config).await;
+        let v1_data = tokio::fs::read(config.get_store_path()).await.unwrap();
+
+        match spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        {
+            Err(LairError::StoreMigrationRequired(1)) => (),
+            oth => panic!("unexpected: {:?}", oth.map(|_| ())),
+        }
+
+        let res = migrate_store_file(&config).await.unwrap();
+        assert_eq!(StoreVersion::V1, res.from);
+        assert_eq!(StoreVersion::CURRENT, res.to);
+        let backup = res.backup.unwrap();
+        assert_eq!(v1_data, tokio::fs::read(&backup).await.unwrap());
+
+        // already current, nothing to do
+        let res = migrate_store_file(&config).await.unwrap();
+        assert_eq!(StoreVersion::CURRENT, res.from);
+        assert!(res.backup.is_none());
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        for (i, e) in entries.iter().enumerate() {
+            let (index, r_e) = store
+                .get_entry_by_pub_id(e.pub_key.0.clone())
+                .await
+                .unwrap();
+            assert_eq!(i as u32 + 1, index.0);
+            as_sign!(r_e);
+            assert_eq!(e.pub_key, r_e.pub_key);
+        }
+
+        // new entries land after the migrated ones
+        let (index, _) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        assert_eq!(3, index.0);
+
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // a second migration doesn't clobber the first backup
+        write_v1_fixture(&config).await;
+        let res = migrate_store_file(&config).await.unwrap();
+        assert_ne!(Some(backup), res.backup);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_rejects_newer_store_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let mut data =
+            StoreVersion(StoreVersion::CURRENT.0 + 1).encode_header();
+        data.extend_from_slice(&[0; entry::ENTRY_SIZE]);
+        tokio::fs::write(config.get_store_path(), &data)
+            .await
+            .unwrap();
+
+        match spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        {
+            Err(LairError::StoreVersionUnsupported(v)) => {
+                assert_eq!(StoreVersion::CURRENT.0 + 1, v)
+            }
+            oth => panic!("unexpected: {:?}", oth.map(|_| ())),
+        }
+
+        assert!(migrate_store_file(&config).await.is_err());
+        assert_eq!(
+            data,
+            tokio::fs::read(config.get_store_path()).await.unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_backup_and_restore_store_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (_, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+        store.x25519_keypair_new_from_entropy().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let backup = backup_store_file(&config, open_store_file(&config).await)
+            .await
+            .unwrap();
+        assert_eq!(2, backup.entries.len());
+        let kdf = entry::UnlockKdf::argon2id(64, 1, 1).unwrap();
+        let data = backup.encrypt(b"backup".to_vec(), kdf).unwrap();
+
+        assert_eq!(
+            backup,
+            StoreBackup::decrypt(&data, b"backup".to_vec()).unwrap()
+        );
+        assert!(StoreBackup::decrypt(&data, b"nope".to_vec()).is_err());
+        // the kdf parameters are part of the authenticated header
+        assert_eq!(&64_u32.to_le_bytes()[..], &data[28..32]);
+        let mut tampered = data.clone();
+        tampered[32] = 2;
+        assert!(StoreBackup::decrypt(&tampered, b"backup".to_vec()).is_err());
+        for len in &[0, 4, 40, 56, data.len() - 1] {
+            assert!(StoreBackup::decrypt(&data[..*len], b"backup".to_vec())
+                .is_err());
+        }
+
+        // restore into an empty store keeps the unlock passphrase
+        let tmpdir2 = tempfile::tempdir().unwrap();
+        let config2 = Config::builder().set_root_path(tmpdir2.path()).build();
+        tokio::fs::File::create(config2.get_store_path())
+            .await
+            .unwrap();
+        let res = restore_store_file(
+            &config2,
+            open_store_file(&config2).await,
+            backup.clone(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, res.restored);
+        assert_eq!(
+            tokio::fs::read(config.get_store_path()).await.unwrap(),
+            tokio::fs::read(config2.get_store_path()).await.unwrap(),
+        );
+
+        assert!(restore_store_file(
+            &config2,
+            open_store_file(&config2).await,
+            backup.clone(),
+            false,
+        )
+        .await
+        .is_err());
+
+        // the restored entries open with the backed up passphrase
+        let store = spawn_entry_store_actor(
+            config2.clone(),
+            open_store_file(&config2).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(StoreLockState::Locked, store.lock_state().await.unwrap());
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (index, _) = store
+            .get_entry_by_pub_id(sign.pub_key.0.clone())
+            .await
+            .unwrap();
+        assert_eq!(1, index.0);
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // sealed entries are bound to the key of their store
+        assert!(restore_store_file(
+            &config,
+            open_store_file(&config).await,
+            backup.clone(),
+            true,
+        )
+        .await
+        .is_err());
+
+        // merging skips entries we already have
+        // (stores without an unlock passphrase keep their entries plain)
+        let plain_store = |config: Arc<Config>| async move {
+            let store = spawn_entry_store_actor(
+                config.clone(),
+                open_store_file(&config).await,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                StoreLockState::NoPassphrase,
+                store.lock_state().await.unwrap()
+            );
+            store
         };
+        let tmpdir3 = tempfile::tempdir().unwrap();
+        let config3 = Config::builder().set_root_path(tmpdir3.path()).build();
+        tokio::fs::File::create(config3.get_store_path())
+            .await
+            .unwrap();
+        let store = plain_store(config3.clone()).await;
+        let (_, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+        store.x25519_keypair_new_from_entropy().await.unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+        let backup =
+            backup_store_file(&config3, open_store_file(&config3).await)
+                .await
+                .unwrap();
+
+        let tmpdir4 = tempfile::tempdir().unwrap();
+        let config4 = Config::builder().set_root_path(tmpdir4.path()).build();
+        tokio::fs::File::create(config4.get_store_path())
+            .await
+            .unwrap();
+        restore_store_file(
+            &config4,
+            open_store_file(&config4).await,
+            backup.clone(),
+            false,
+        )
+        .await
+        .unwrap();
+        let store = plain_store(config4.clone()).await;
+        store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let res = restore_store_file(
+            &config3,
+            open_store_file(&config3).await,
+            backup,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(0, res.restored);
+        assert_eq!(2, res.skipped);
+
+        let backup4 =
+            backup_store_file(&config4, open_store_file(&config4).await)
+                .await
+                .unwrap();
+        let res = restore_store_file(
+            &config3,
+            open_store_file(&config3).await,
+            backup4,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, res.restored);
+        assert_eq!(2, res.skipped);
+
+        let store = plain_store(config3.clone()).await;
+        assert_eq!(3, store.get_last_entry_index().await.unwrap().0);
+        let (index, _) = store
+            .get_entry_by_pub_id(sign.pub_key.0.clone())
+            .await
+            .unwrap();
+        assert_eq!(1, index.0);
+        store.ghost_actor_shutdown().await.unwrap();
     }
 
-    macro_rules! as_x25519 {
-        ($e:ident) => {
-            let $e = match &*$e {
-                LairEntry::X25519(e) => e,
-                _ => panic!("unexpected"),
-            };
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_check_and_repair_store_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        tokio::fs::File::create(config.get_store_path())
+            .await
+            .unwrap();
+
+        init_store_file(
+            &config,
+            open_store_file(&config).await,
+            b"test".to_vec().into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        let (_, sign) = store
+            .sign_ed25519_keypair_new_from_entropy(None)
+            .await
+            .unwrap();
+        as_sign!(sign);
+        store.x25519_keypair_new_from_entropy().await.unwrap();
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await.unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+        assert_eq!(2, check.entries.len());
+        assert_eq!(
+            Some(LairEntryType::SignEd25519),
+            check.entries[0].entry_type
+        );
+        assert!(check.entries[0].sealed);
+
+        // a garbage entry, a pub key that doesn't match its priv key,
+        // a valid entry, then a partial write
+        let mut data = tokio::fs::read(config.get_store_path()).await.unwrap();
+        let valid = data[data.len() - entry::ENTRY_SIZE..].to_vec();
+        data.extend_from_slice(&[0; entry::ENTRY_SIZE]);
+        let other = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let mismatch = entry::EntrySignEd25519 {
+            priv_key: sign.priv_key.clone(),
+            pub_key: other.pub_key,
         };
+        data.extend_from_slice(&mismatch.encode().unwrap());
+        data.extend_from_slice(&valid);
+        data.extend_from_slice(&[0xdb; 100]);
+        tokio::fs::write(config.get_store_path(), &data)
+            .await
+            .unwrap();
+
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(3, check.problem_count());
+        assert!(check.entries[2].error.is_some());
+        assert_eq!(
+            Some(LairEntryType::SignEd25519),
+            check.entries[3].entry_type
+        );
+        assert!(check.entries[3].error.is_some());
+        assert!(check.entries[4].error.is_none());
+        assert_eq!(100, check.trailing_bytes);
+
+        let res = repair_store_file(&config).await.unwrap();
+        assert_eq!(vec![KeystoreIndex(3), KeystoreIndex(4)], res.dropped);
+        assert_eq!(100, res.trailing_bytes);
+        let quarantine =
+            tokio::fs::read(res.quarantine.unwrap()).await.unwrap();
+        let bad_start = data.len() - 100 - 3 * entry::ENTRY_SIZE;
+        assert_eq!(
+            &data[bad_start..bad_start + 2 * entry::ENTRY_SIZE],
+            &quarantine[..2 * entry::ENTRY_SIZE]
+        );
+        assert_eq!(
+            &data[data.len() - 100..],
+            &quarantine[2 * entry::ENTRY_SIZE..]
+        );
+
+        // the dropped entries are left deleted, the valid one after
+        // them keeps its index
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert_eq!(0, check.problem_count());
+        assert_eq!(5, check.entries.len());
+        assert_eq!(Some(LairEntryType::Deleted), check.entries[2].entry_type);
+        assert_eq!(Some(LairEntryType::Deleted), check.entries[3].entry_type);
+        assert_eq!(KeystoreIndex(5), check.entries[4].index);
+        assert_eq!(Some(LairEntryType::X25519), check.entries[4].entry_type);
+
+        let store = spawn_entry_store_actor(
+            config.clone(),
+            open_store_file(&config).await,
+        )
+        .await
+        .unwrap();
+        store.unlock(b"test".to_vec().into()).await.unwrap();
+        assert_eq!(5, store.get_last_entry_index().await.unwrap().0);
+        assert!(store.get_entry_by_index(KeystoreIndex(3)).await.is_err());
+        let entry = store.get_entry_by_index(KeystoreIndex(5)).await.unwrap();
+        assert!(matches!(*entry, LairEntry::X25519(_)));
+        store.ghost_actor_shutdown().await.unwrap();
+
+        // nothing left to repair
+        let res = repair_store_file(&config).await.unwrap();
+        assert!(res.quarantine.is_none());
+
+        // we can't repair the unlock entry
+        let mut data = tokio::fs::read(config.get_store_path()).await.unwrap();
+        for b in &mut data[HEADER_SIZE..HEADER_SIZE + 128] {
+            *b = 0xdb;
+        }
+        tokio::fs::write(config.get_store_path(), &data)
+            .await
+            .unwrap();
+        let check = check_store_file(config.get_store_path()).await.unwrap();
+        assert!(check.unlock_error.is_some());
+        assert!(repair_store_file(&config).await.is_err());
     }
 
     #[tokio::test(flavor = "multi_thread")]
     async fn it_can_store_and_retrieve_entries_from_disk() {
+        check_store_and_retrieve_entries(StoreKind::File).await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_store_and_retrieve_entries_from_sqlite() {
+        check_store_and_retrieve_entries(StoreKind::Sqlite).await;
+    }
+
+    async fn check_store_and_retrieve_entries(kind: StoreKind) {
         let tmpdir = tempfile::tempdir().unwrap();
 
         let (cert, sign, x25519) = {
-            let config = Config::builder().set_root_path(tmpdir.path()).build();
+            let config = config_of_kind(tmpdir.path(), kind);
 
             let store_file_path = config.get_store_path().to_owned();
 
@@ -349,17 +5479,19 @@ mod tests {
             let store =
                 spawn_entry_store_actor(config, store_file).await.unwrap();
 
-            let (cert_index, cert) =
-                store
-                    .tls_cert_self_signed_new_from_entropy(
-                        TlsCertOptions::default(),
-                    )
-                    .await
-                    .unwrap();
+            let (cert_index, cert) = store
+                .tls_cert_self_signed_new_from_entropy(
+                    TlsCertOptions::default(),
+                    None,
+                )
+                .await
+                .unwrap();
             assert_eq!(1, cert_index.0);
 
-            let (sign_index, sign) =
-                store.sign_ed25519_keypair_new_from_entropy().await.unwrap();
+            let (sign_index, sign) = store
+                .sign_ed25519_keypair_new_from_entropy(None)
+                .await
+                .unwrap();
             assert_eq!(2, sign_index.0);
 
             let (x25519_index, x25519) =
@@ -376,7 +5508,7 @@ mod tests {
         as_sign!(sign);
         as_x25519!(x25519);
 
-        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        let config = config_of_kind(tmpdir.path(), kind);
 
         let store_file_path = config.get_store_path().to_owned();
 
@@ -0,0 +1,345 @@
+//! pluggable persistence of the entries of a store
+
+use super::store_file::{
+    check_sealable, check_sealable_data, seal_entry, EntryStoreFile,
+    EntryStoreFileSender,
+};
+use crate::*;
+use futures::future::{BoxFuture, FutureExt};
+use lair_keystore_api::actor::{KeystoreIndex, LairCompactInfo};
+
+/// Entries of a store along with their indices.
+pub type IndexedEntries = Vec<(KeystoreIndex, Vec<u8>)>;
+
+/// Where a lair store keeps its entries, for embedders that want them in
+/// their own database rather than lair's files
+/// (see `spawn_entry_store_actor_with_backend()`).
+///
+/// Entries are opaque blocks of `entry::ENTRY_SIZE` bytes, to be kept
+/// verbatim. Lair seals them itself before they get here, and makes its
+/// requests one at a time, each one after the last has resolved,
+/// starting with `load_unlock()`.
+///
+/// Indices:
+/// - index zero is the unlock entry, see `write_unlock()`
+/// - `append_entry()` assigns index 1 to the first entry,
+///   then one more than the highest index it ever assigned:
+///   indices of deleted entries are never used again
+///
+/// Durability: once `flush()` resolves, everything written before it
+/// must be loaded by the store as it is opened again.
+///
+/// `conformance::check_entry_store_backend()` checks all of the above,
+/// run it against your implementation.
+pub trait EntryStoreBackend: Send + 'static {
+    /// The unlock entry, `None` if this is a new store.
+    fn load_unlock(&mut self) -> BoxFuture<'_, LairResult<Option<Vec<u8>>>>;
+
+    /// Write the unlock entry, replacing any there is.
+    fn write_unlock(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<()>>;
+
+    /// Every index assigned so far, each once and in ascending order,
+    /// along with its entry. Deleted entries are loaded as the
+    /// tombstone `entry::EntryDeleted` encodes to.
+    fn load_all_entries(&mut self)
+        -> BoxFuture<'_, LairResult<IndexedEntries>>;
+
+    /// Add an entry at the next index, returning that index.
+    fn append_entry(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<KeystoreIndex>>;
+
+    /// Swap in new data for existing entries (index zero being the
+    /// unlock entry): all of them, or none of them if any fails,
+    /// e.g. for having no entry at its index.
+    fn replace_entries(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    ) -> BoxFuture<'_, LairResult<()>>;
+
+    /// Mark an existing entry deleted, by replacing it with a tombstone
+    /// unless the store has a better way to.
+    fn mark_deleted(
+        &mut self,
+        index: KeystoreIndex,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        async move {
+            let tombstone = entry::EntryDeleted.encode()?;
+            self.replace_entries(vec![(index, tombstone)]).await
+        }
+        .boxed()
+    }
+
+    /// Drop the data of deleted entries, if the store can. Their
+    /// indices stay assigned, loading as tombstones.
+    fn compact(&mut self) -> BoxFuture<'_, LairResult<LairCompactInfo>> {
+        async move { Ok(LairCompactInfo::default()) }.boxed()
+    }
+
+    /// Make every write so far durable.
+    fn flush(&mut self) -> BoxFuture<'_, LairResult<()>>;
+}
+
+/// The store of `config`, of its configured `StoreKind`, as an
+/// `EntryStoreBackend`. Every write to it is committed before it
+/// resolves, so there is nothing to flush.
+pub struct ConfigEntryStore {
+    store_path: std::path::PathBuf,
+    store_file: futures::channel::mpsc::Sender<EntryStoreFile>,
+}
+
+impl ConfigEntryStore {
+    /// Open (or create) the store of `config`.
+    /// It is not guarded by a pidfile, see `pid_check()`.
+    pub async fn open(config: &Config) -> LairResult<Self> {
+        let store_file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(config.get_store_path())
+            .await
+            .map_err(LairError::other)?;
+        Ok(Self {
+            store_path: config.get_store_path().to_owned(),
+            store_file: super::spawn_store_backend(config, store_file).await?,
+        })
+    }
+}
+
+impl EntryStoreBackend for ConfigEntryStore {
+    fn load_unlock(&mut self) -> BoxFuture<'_, LairResult<Option<Vec<u8>>>> {
+        self.store_file.init_load_unlock().boxed()
+    }
+
+    fn write_unlock(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        self.store_file.write_unlock(entry_data).boxed()
+    }
+
+    fn load_all_entries(
+        &mut self,
+    ) -> BoxFuture<'_, LairResult<IndexedEntries>> {
+        self.store_file.load_all_entries().boxed()
+    }
+
+    fn append_entry(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<KeystoreIndex>> {
+        self.store_file.write_next_entry(entry_data).boxed()
+    }
+
+    fn replace_entries(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        self.store_file
+            .replace_entries(self.store_path.clone(), entries)
+            .boxed()
+    }
+
+    fn compact(&mut self) -> BoxFuture<'_, LairResult<LairCompactInfo>> {
+        self.store_file.compact(self.store_path.clone()).boxed()
+    }
+
+    fn flush(&mut self) -> BoxFuture<'_, LairResult<()>> {
+        async move { Ok(()) }.boxed()
+    }
+}
+
+/// Serve the requests of a store actor from `backend`, sealing entries
+/// on their way to it.
+pub(crate) fn spawn_entry_store_backend_task(
+    backend: Box<dyn EntryStoreBackend>,
+) -> futures::channel::mpsc::Sender<EntryStoreFile> {
+    let (s, r) = futures::channel::mpsc::channel(10);
+
+    tokio::task::spawn(entry_store_backend_task(backend, r));
+
+    s
+}
+
+async fn entry_store_backend_task(
+    mut backend: Box<dyn EntryStoreBackend>,
+    mut recv: futures::channel::mpsc::Receiver<EntryStoreFile>,
+) -> LairResult<()> {
+    use futures::stream::StreamExt;
+
+    // once set, entries are sealed with this key as they are written
+    let mut key: Option<Arc<entry::EntryStoreKey>> = None;
+    // set once the key is forgotten
+    let mut locked = false;
+
+    while let Some(req) = recv.next().await {
+        match req {
+            EntryStoreFile::InitLoadUnlock { respond, .. } => {
+                let res = backend.load_unlock().await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Truncate { respond, .. } => {
+                let res = Err("an embedded store cannot be truncated".into());
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteUnlock {
+                respond,
+                entry_data,
+                ..
+            } => {
+                let res = match seal_entry(entry_data, None) {
+                    Ok(entry_data) => backend.write_unlock(entry_data).await,
+                    Err(err) => Err(err),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ReplaceEntry {
+                respond,
+                index,
+                entry_data,
+                ..
+            } => {
+                let entries = vec![(index, entry_data)];
+                let res = if index.0 != 0
+                    && entry::EntryDeleted::is_deleted(&entries[0].1)
+                {
+                    backend.mark_deleted(index).await
+                } else {
+                    match check_sealable(locked, &entries) {
+                        Ok(()) => {
+                            replace_entries(
+                                &mut *backend,
+                                entries,
+                                key.as_deref(),
+                            )
+                            .await
+                        }
+                        Err(err) => Err(err),
+                    }
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ReplaceEntries {
+                respond, entries, ..
+            } => {
+                let res = match check_sealable(locked, &entries) {
+                    Ok(()) => {
+                        replace_entries(&mut *backend, entries, key.as_deref())
+                            .await
+                    }
+                    Err(err) => Err(err),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadAllEntries { respond, .. } => {
+                let res = backend.load_all_entries().await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteNextEntry {
+                respond,
+                entry_data,
+                ..
+            } => {
+                let res = match check_sealable_data(locked, &entry_data)
+                    .and_then(|()| seal_entry(entry_data, key.as_deref()))
+                {
+                    Ok(entry_data) => backend.append_entry(entry_data).await,
+                    Err(err) => Err(err),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ResealAll {
+                respond,
+                key: new_key,
+                unlock_entry,
+                ..
+            } => {
+                let res = reseal_all(
+                    &mut *backend,
+                    key.clone(),
+                    new_key.clone(),
+                    unlock_entry,
+                )
+                .await;
+                if res.is_ok() {
+                    key = Some(new_key);
+                    locked = false;
+                }
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ForgetKey { respond, .. } => {
+                locked = key.take().is_some() || locked;
+                respond.r(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            EntryStoreFile::Compact { respond, .. } => {
+                let res = backend.compact().await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Close { respond, .. } => {
+                let res = backend.flush().await;
+                respond.r(Ok(async move { res }.boxed().into()));
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn replace_entries(
+    backend: &mut dyn EntryStoreBackend,
+    entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    key: Option<&entry::EntryStoreKey>,
+) -> LairResult<()> {
+    let mut sealed = Vec::with_capacity(entries.len());
+    for (index, entry_data) in entries {
+        // the unlock entry is never sealed
+        let key = if index.0 == 0 { None } else { key };
+        sealed.push((index, seal_entry(entry_data, key)?));
+    }
+    backend.replace_entries(sealed).await
+}
+
+/// see `EntryStoreFile::reseal_all`, the resealed entries are
+/// swapped in along with the unlock entry in a single replace
+async fn reseal_all(
+    backend: &mut dyn EntryStoreBackend,
+    key: Option<Arc<entry::EntryStoreKey>>,
+    new_key: Arc<entry::EntryStoreKey>,
+    unlock_entry: Option<Vec<u8>>,
+) -> LairResult<()> {
+    let rekey = unlock_entry.is_some();
+
+    let open_key = key.unwrap_or_else(|| new_key.clone());
+    let mut opened = super::SealedOpenCount::default();
+    let mut resealed = Vec::new();
+    for (index, data) in backend.load_all_entries().await? {
+        if entry::EntryDeleted::is_deleted(&data) {
+            continue;
+        }
+        let sealed = entry::LairEntry::is_sealed(&data);
+        let (entry, meta) = match opened
+            .open(sealed, entry::LairEntry::unseal_with_meta(&data, &open_key))
+        {
+            Some(r) => r,
+            None => continue,
+        };
+        if sealed && !rekey {
+            continue;
+        }
+        resealed.push((index, entry.seal_with_meta(&meta, &new_key)?));
+    }
+    opened.check()?;
+
+    if let Some(unlock_entry) = unlock_entry {
+        resealed.push((0.into(), seal_entry(unlock_entry, None)?));
+    }
+    if resealed.is_empty() {
+        return Ok(());
+    }
+    backend.replace_entries(resealed).await
+}
@@ -0,0 +1,355 @@
+//! encrypted whole-keystore backup file format
+
+use super::store_file::EntryStoreFileSender;
+use super::version::*;
+use crate::*;
+use entry::LairEntry;
+
+/// Magic bytes at the start of a lair backup file.
+pub const BACKUP_MAGIC: &[u8; 8] = b"lairback";
+
+/// Backup file format version written by this build.
+const BACKUP_VERSION: u32 = 2;
+
+const SALT_BYTES: usize = 16;
+// argon2id memory cost, iterations and parallelism
+const KDF_BYTES: usize = 3 * 4;
+const NONCE_BYTES: usize = 12;
+const KEY_BYTES: usize = 32;
+
+// magic + version + salt + kdf + nonce, authenticated as aad
+const HEADER_BYTES: usize = 8 + 4 + SALT_BYTES + KDF_BYTES + NONCE_BYTES;
+
+/// The decrypted content of a backup file,
+/// the raw blocks of a store file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreBackup {
+    /// The store format the blocks were read from.
+    pub store_version: StoreVersion,
+
+    /// The encoded unlock entry (may be an all-zero stub).
+    pub unlock_entry: Vec<u8>,
+
+    /// The encoded keystore entries, in index order.
+    pub entries: Vec<Vec<u8>>,
+}
+
+impl StoreBackup {
+    /// Encrypt this backup with a key derived from `passphrase` with
+    /// `kdf` (usually `Config::get_unlock_kdf()`), whose parameters are
+    /// written in the backup header.
+    /// The passphrase buffer is zeroized once hashed.
+    pub fn encrypt(
+        &self,
+        passphrase: Vec<u8>,
+        kdf: entry::UnlockKdf,
+    ) -> LairResult<Vec<u8>> {
+        let mut plain = zeroize::Zeroizing::new(Vec::with_capacity(
+            8 + entry::ENTRY_SIZE * (1 + self.entries.len()),
+        ));
+        plain.extend_from_slice(&self.store_version.0.to_le_bytes());
+        plain.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for block in Some(&self.unlock_entry).into_iter().chain(&self.entries) {
+            if block.len() != entry::ENTRY_SIZE {
+                return Err(format!(
+                    "bad entry size, expected {}, got {}",
+                    entry::ENTRY_SIZE,
+                    block.len(),
+                )
+                .into());
+            }
+            plain.extend_from_slice(block);
+        }
+
+        let sys_rand = ring::rand::SystemRandom::new();
+        let mut salt = [0; SALT_BYTES];
+        ring::rand::SecureRandom::fill(&sys_rand, &mut salt)
+            .map_err(|e| format!("{:?}", e))?;
+        let mut nonce = [0; NONCE_BYTES];
+        ring::rand::SecureRandom::fill(&sys_rand, &mut nonce)
+            .map_err(|e| format!("{:?}", e))?;
+
+        let mut out = Vec::with_capacity(
+            HEADER_BYTES + plain.len() + ring::aead::MAX_TAG_LEN,
+        );
+        out.extend_from_slice(BACKUP_MAGIC);
+        out.extend_from_slice(&BACKUP_VERSION.to_le_bytes());
+        out.extend_from_slice(&salt);
+        let entry::UnlockKdf::Argon2id {
+            mem_kib,
+            iterations,
+            parallelism,
+        } = kdf;
+        for param in &[mem_kib, iterations, parallelism] {
+            out.extend_from_slice(&param.to_le_bytes());
+        }
+        out.extend_from_slice(&nonce);
+
+        let mut cipher = plain.to_vec();
+        backup_key(&salt, kdf, passphrase)?
+            .seal_in_place_append_tag(
+                ring::aead::Nonce::assume_unique_for_key(nonce),
+                ring::aead::Aad::from(&out[..]),
+                &mut cipher,
+            )
+            .map_err(|_| "failed to encrypt backup")?;
+        out.extend_from_slice(&cipher);
+
+        Ok(out)
+    }
+
+    /// Decrypt a backup file with a key derived from `passphrase`.
+    /// The passphrase buffer is zeroized once hashed.
+    pub fn decrypt(data: &[u8], passphrase: Vec<u8>) -> LairResult<Self> {
+        if data.len() < 8 || &data[..8] != BACKUP_MAGIC {
+            return Err("not a lair backup file".into());
+        }
+        if data.len() < HEADER_BYTES + ring::aead::MAX_TAG_LEN {
+            return Err("backup file is truncated".into());
+        }
+
+        let (header, cipher) = data.split_at(HEADER_BYTES);
+
+        let version = read_u32(&header[8..]);
+        if version != BACKUP_VERSION {
+            return Err(format!(
+                "unsupported backup format version {}",
+                version
+            )
+            .into());
+        }
+
+        let mut salt = [0; SALT_BYTES];
+        salt.copy_from_slice(&header[12..12 + SALT_BYTES]);
+        let kdf_params = &header[12 + SALT_BYTES..];
+        let kdf = entry::UnlockKdf::argon2id(
+            read_u32(kdf_params),
+            read_u32(&kdf_params[4..]),
+            read_u32(&kdf_params[8..]),
+        )?;
+        let mut nonce = [0; NONCE_BYTES];
+        nonce.copy_from_slice(&header[12 + SALT_BYTES + KDF_BYTES..]);
+
+        // a truncated or tampered body fails authentication
+        // just the same as a wrong passphrase, we can't tell them apart
+        let mut plain = zeroize::Zeroizing::new(cipher.to_vec());
+        let plain_len = backup_key(&salt, kdf, passphrase)?
+            .open_in_place(
+                ring::aead::Nonce::assume_unique_for_key(nonce),
+                ring::aead::Aad::from(header),
+                &mut plain,
+            )
+            .map_err(|_| "invalid backup passphrase, or the backup is corrupt")?
+            .len();
+        let plain = &plain[..plain_len];
+
+        if plain.len() < 8 {
+            return Err("backup content is truncated".into());
+        }
+        let store_version = StoreVersion(read_u32(plain));
+        let entry_count = read_u32(&plain[4..]) as usize;
+        let blocks = &plain[8..];
+        if blocks.len() != entry::ENTRY_SIZE * (1 + entry_count) {
+            return Err("backup content is truncated".into());
+        }
+
+        let mut blocks = blocks.chunks(entry::ENTRY_SIZE).map(|b| b.to_vec());
+        let unlock_entry = blocks.next().expect("checked length");
+        let entries = blocks.collect();
+
+        Ok(Self {
+            store_version,
+            unlock_entry,
+            entries,
+        })
+    }
+}
+
+/// The outcome of a `restore_store_file()` call.
+#[derive(Debug)]
+pub struct RestoreResult {
+    /// The number of entries written to the store.
+    pub restored: usize,
+
+    /// The number of entries skipped because their pub key
+    /// was already present in the store (`merge` only).
+    pub skipped: usize,
+}
+
+/// Read the unlock entry and all entries out of a store.
+pub async fn backup_store_file(
+    config: &Config,
+    store_file: tokio::fs::File,
+) -> LairResult<StoreBackup> {
+    let store_file = super::spawn_store_backend(config, store_file).await?;
+
+    let unlock_entry = store_file
+        .init_load_unlock()
+        .await?
+        .unwrap_or_else(|| vec![0; entry::ENTRY_SIZE]);
+
+    let entries = store_file
+        .load_all_entries()
+        .await?
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect();
+
+    store_file.close().await?;
+
+    Ok(StoreBackup {
+        store_version: StoreVersion::CURRENT,
+        unlock_entry,
+        entries,
+    })
+}
+
+/// Write the entries of a backup into a store.
+/// An empty store takes the backup's unlock entry and entry indices.
+/// A non-empty store is refused unless `merge` is set, in which case
+/// it keeps its own unlock passphrase, and backup entries are appended
+/// unless an entry with the same pub key already exists.
+/// Neither store may have sealed entries to be merged.
+pub async fn restore_store_file(
+    config: &Config,
+    store_file: tokio::fs::File,
+    backup: StoreBackup,
+    merge: bool,
+) -> LairResult<RestoreResult> {
+    if backup.store_version > StoreVersion::CURRENT {
+        return Err(LairError::StoreVersionUnsupported(backup.store_version.0));
+    }
+
+    let store_file = super::spawn_store_backend(config, store_file).await?;
+
+    let unlock = store_file.init_load_unlock().await?;
+    let has_unlock = match &unlock {
+        Some(unlock) => entry::EntryUnlock::decode(unlock)?.is_some(),
+        None => false,
+    };
+    let existing = store_file.load_all_entries().await?;
+
+    let mut out = RestoreResult {
+        restored: 0,
+        skipped: 0,
+    };
+
+    if !has_unlock && existing.is_empty() {
+        store_file.truncate().await?;
+        store_file.write_unlock(backup.unlock_entry).await?;
+        for entry in backup.entries {
+            store_file.write_next_entry(entry).await?;
+            out.restored += 1;
+        }
+    } else if merge {
+        // sealed entries only open with the key of the store they were
+        // sealed in, and their public material is not decoded without it
+        if existing
+            .iter()
+            .map(|(_, entry)| entry)
+            .chain(backup.entries.iter())
+            .any(|entry| LairEntry::is_sealed(entry))
+        {
+            return Err("cannot merge stores with sealed entries, \
+                restore the backup into an empty store instead"
+                .into());
+        }
+        let mut pub_ids = std::collections::HashSet::new();
+        let mut tags = std::collections::HashSet::new();
+        for (_, entry) in existing {
+            if entry::EntryDeleted::is_deleted(&entry) {
+                continue;
+            }
+            let (entry, meta) = LairEntry::decode_with_meta(&entry)?;
+            pub_ids.extend(entry_pub_id(&entry));
+            tags.extend(meta.tag);
+        }
+        for mut data in backup.entries {
+            // deleted entries only matter for keeping indices stable
+            if entry::EntryDeleted::is_deleted(&data) {
+                continue;
+            }
+            let (entry, mut meta) = LairEntry::decode_with_meta(&data)?;
+            if let Some(pub_id) = entry_pub_id(&entry) {
+                if !pub_ids.insert(pub_id) {
+                    out.skipped += 1;
+                    continue;
+                }
+            }
+            if let Some(tag) = meta.tag.take() {
+                if tags.insert(tag.clone()) {
+                    meta.tag = Some(tag);
+                } else {
+                    // tags must stay unique, keep the entry untagged
+                    tracing::warn!(
+                        "dropping tag {:?} from restored entry, \
+                        already in use",
+                        tag
+                    );
+                    data = entry.encode_with_meta(&meta)?;
+                }
+            }
+            store_file.write_next_entry(data).await?;
+            out.restored += 1;
+        }
+    } else {
+        return Err(
+            "store is not empty, restore with `--merge` to add to it".into()
+        );
+    }
+
+    store_file.close().await?;
+
+    Ok(out)
+}
+
+fn entry_pub_id(entry: &LairEntry) -> Option<Vec<u8>> {
+    match entry {
+        LairEntry::TlsCert(e) => Some(e.cert_digest.to_vec()),
+        LairEntry::SignEd25519(e) => Some(e.pub_key.to_vec()),
+        LairEntry::X25519(e) => Some(e.pub_key.to_bytes().to_vec()),
+        LairEntry::Seed(e) => Some(e.seed_id.to_vec()),
+        LairEntry::SecretKey(e) => Some(e.tag.to_vec()),
+        _ => None,
+    }
+}
+
+/// Derive the backup key from `passphrase` with argon2id,
+/// like the store key is derived from the unlock passphrase.
+fn backup_key(
+    salt: &[u8; SALT_BYTES],
+    kdf: entry::UnlockKdf,
+    passphrase: Vec<u8>,
+) -> LairResult<ring::aead::LessSafeKey> {
+    let passphrase = zeroize::Zeroizing::new(passphrase);
+    let entry::UnlockKdf::Argon2id {
+        mem_kib,
+        iterations,
+        parallelism,
+    } = kdf;
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        version: argon2::Version::Version13,
+        mem_cost: mem_kib,
+        time_cost: iterations,
+        lanes: parallelism,
+        thread_mode: argon2::ThreadMode::from_threads(parallelism),
+        secret: &[],
+        ad: b"lair-backup-key",
+        hash_length: KEY_BYTES as u32,
+    };
+    let hash = zeroize::Zeroizing::new(
+        argon2::hash_raw(&passphrase, salt, &config)
+            .map_err(LairError::other)?,
+    );
+    let key =
+        ring::aead::UnboundKey::new(&ring::aead::CHACHA20_POLY1305, &hash)
+            .map_err(|_| "failed to derive backup key")?;
+    Ok(ring::aead::LessSafeKey::new(key))
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    let mut out = [0; 4];
+    out.copy_from_slice(&data[..4]);
+    u32::from_le_bytes(out)
+}
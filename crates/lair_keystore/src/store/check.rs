@@ -0,0 +1,266 @@
+//! store integrity checking and repair
+
+use super::layout::Layout;
+use super::version::*;
+use crate::*;
+use entry::LairEntry;
+use lair_keystore_api::actor::*;
+
+/// The check result for a single store entry.
+#[derive(Debug, Clone)]
+pub struct EntryCheck {
+    /// The index of the entry.
+    pub index: KeystoreIndex,
+
+    /// The entry type, if the entry could be decoded.
+    pub entry_type: Option<LairEntryType>,
+
+    /// Why the entry is invalid, if it is.
+    pub error: Option<String>,
+
+    /// The entry is sealed, its private material (and whether it
+    /// matches its public material) cannot be checked without the
+    /// store key.
+    pub sealed: bool,
+}
+
+/// The check result for a whole store file.
+#[derive(Debug, Clone)]
+pub struct StoreCheck {
+    /// The on-disk format of the store.
+    pub version: StoreVersion,
+
+    /// Why the unlock entry is invalid, if it is.
+    pub unlock_error: Option<String>,
+
+    /// The check result for every entry in the store.
+    pub entries: Vec<EntryCheck>,
+
+    /// Bytes after the last complete entry,
+    /// e.g. from a write interrupted by a full disk.
+    pub trailing_bytes: usize,
+}
+
+impl StoreCheck {
+    /// The number of problems found.
+    pub fn problem_count(&self) -> usize {
+        self.unlock_error.iter().count()
+            + self.entries.iter().filter(|e| e.error.is_some()).count()
+            + if self.trailing_bytes > 0 { 1 } else { 0 }
+    }
+}
+
+/// Check the raw content of a store file: that every entry decodes,
+/// and that its pub key / cert digest matches its private material.
+/// Sealed entries are only checked for their entry type.
+pub fn check_store_data(data: &[u8]) -> LairResult<StoreCheck> {
+    let version = StoreVersion::detect(data).unwrap_or(StoreVersion::CURRENT);
+    let mut blocks = &data[entries_offset(version, data)?..];
+
+    let mut out = StoreCheck {
+        version,
+        unlock_error: None,
+        entries: Vec::new(),
+        trailing_bytes: blocks.len() % entry::ENTRY_SIZE,
+    };
+
+    if blocks.is_empty() {
+        return Ok(out);
+    }
+    if blocks.len() < entry::ENTRY_SIZE {
+        out.unlock_error = Some("unlock entry is truncated".to_string());
+        out.trailing_bytes = 0;
+        return Ok(out);
+    }
+
+    if let Err(e) = entry::EntryUnlock::decode(&blocks[..entry::ENTRY_SIZE]) {
+        out.unlock_error = Some(e.to_string());
+    }
+    blocks = &blocks[entry::ENTRY_SIZE..];
+
+    let layout = store_layout(version, data)?;
+    blocks = &blocks[std::cmp::min(
+        layout.compacted_blocks as usize * entry::ENTRY_SIZE,
+        blocks.len(),
+    )..];
+
+    for (i, block) in blocks.chunks_exact(entry::ENTRY_SIZE).enumerate() {
+        let mut check = EntryCheck {
+            index: layout.index_of(i as u64),
+            entry_type: None,
+            error: None,
+            sealed: false,
+        };
+        if entry::EntryDeleted::is_deleted(block) {
+            check.entry_type = Some(LairEntryType::Deleted);
+            out.entries.push(check);
+            continue;
+        }
+        if let Some(entry_type) = LairEntry::sealed_entry_type(block) {
+            check.entry_type = Some(entry_type);
+            check.sealed = true;
+            out.entries.push(check);
+            continue;
+        }
+        match LairEntry::decode(block) {
+            Ok(entry) => {
+                check.entry_type = Some(match entry {
+                    LairEntry::TlsCert(_) => LairEntryType::TlsCert,
+                    LairEntry::SignEd25519(_) => LairEntryType::SignEd25519,
+                    LairEntry::X25519(_) => LairEntryType::X25519,
+                    LairEntry::Seed(_) => LairEntryType::Seed,
+                    LairEntry::SecretKey(_) => LairEntryType::SecretKey,
+                    _ => LairEntryType::Invalid,
+                });
+                check.error = entry.verify().err().map(|e| e.to_string());
+            }
+            Err(e) => check.error = Some(e.to_string()),
+        }
+        out.entries.push(check);
+    }
+
+    Ok(out)
+}
+
+/// Read and check a store file, without modifying it.
+pub async fn check_store_file(
+    path: &std::path::Path,
+) -> LairResult<StoreCheck> {
+    let data = tokio::fs::read(path).await.map_err(LairError::Io)?;
+    check_store_data(&data)
+}
+
+/// Read and check the configured store, without modifying it.
+/// Sqlite stores are checked as the store file they would convert to.
+pub async fn check_store(config: &Config) -> LairResult<StoreCheck> {
+    match config.get_store_kind() {
+        StoreKind::File => check_store_file(config.get_store_path()).await,
+        #[cfg(feature = "sqlite")]
+        StoreKind::Sqlite => {
+            let data = super::store_sqlite::read_store_image(
+                config.get_store_path().to_owned(),
+            )
+            .await?;
+            check_store_data(&data)
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StoreKind::Sqlite => Err(super::sqlite_not_enabled()),
+    }
+}
+
+/// The outcome of a `repair_store_file()` call.
+#[derive(Debug)]
+pub struct RepairResult {
+    /// The indices of the entries moved to the quarantine file.
+    /// They are left deleted, every other entry keeps its index.
+    pub dropped: Vec<KeystoreIndex>,
+
+    /// The number of trailing bytes moved to the quarantine file.
+    pub trailing_bytes: usize,
+
+    /// Where the dropped data was written, if anything was dropped.
+    pub quarantine: Option<std::path::PathBuf>,
+}
+
+/// Move invalid entries (and any trailing partial entry) out of the
+/// configured store file into a quarantine file next to it, leaving a
+/// deleted entry tombstone in place of each invalid entry.
+/// A corrupt unlock entry cannot be repaired.
+/// Sqlite stores are written in transactions, and cannot be repaired.
+/// The caller must ensure no lair process is using the store.
+pub async fn repair_store_file(config: &Config) -> LairResult<RepairResult> {
+    if config.get_store_kind() != StoreKind::File {
+        return Err(format!(
+            "cannot repair {} stores, only file stores",
+            config.get_store_kind()
+        )
+        .into());
+    }
+
+    let store_path = config.get_store_path();
+    let data = tokio::fs::read(store_path).await.map_err(LairError::Io)?;
+    let check = check_store_data(&data)?;
+
+    if let Some(e) = check.unlock_error {
+        return Err(format!(
+            "the unlock entry is corrupt ({}), restore from a backup",
+            e
+        )
+        .into());
+    }
+
+    let mut out = RepairResult {
+        dropped: Vec::new(),
+        trailing_bytes: check.trailing_bytes,
+        quarantine: None,
+    };
+
+    let offset = entries_offset(check.version, &data)?;
+    let mut keep = data[..offset].to_vec();
+    let mut quarantine = Vec::new();
+
+    // the unlock entry, and the indices of compacted deleted entries
+    let layout = store_layout(check.version, &data)?;
+    let mut blocks = data[offset..].chunks(entry::ENTRY_SIZE);
+    for _ in 0..1 + layout.compacted_blocks {
+        keep.extend_from_slice(blocks.next().unwrap_or(&[]));
+    }
+    for (block, check) in blocks.zip(
+        check
+            .entries
+            .iter()
+            .map(Some)
+            .chain(std::iter::repeat(None)),
+    ) {
+        match check {
+            Some(check) if check.error.is_none() => {
+                keep.extend_from_slice(block)
+            }
+            Some(check) => {
+                out.dropped.push(check.index);
+                quarantine.extend_from_slice(block);
+                keep.extend_from_slice(&entry::EntryDeleted.encode()?);
+            }
+            // the trailing partial entry
+            None => quarantine.extend_from_slice(block),
+        }
+    }
+
+    if quarantine.is_empty() {
+        return Ok(out);
+    }
+
+    let quarantine_path =
+        super::unused_path(store_path.with_extension("quarantine"));
+    super::write_synced(&quarantine_path, &quarantine).await?;
+
+    // write the repaired store beside the old one, then swap it in
+    let tmp = store_path.with_extension("repairing");
+    super::write_synced(&tmp, &keep).await?;
+    tokio::fs::rename(&tmp, store_path)
+        .await
+        .map_err(LairError::Io)?;
+    // make the rename itself durable
+    super::store_file::sync_parent_dir(store_path).await?;
+
+    out.quarantine = Some(quarantine_path);
+    Ok(out)
+}
+
+/// The layout of the entries of a store of this version,
+/// only current stores can be compacted.
+fn store_layout(version: StoreVersion, data: &[u8]) -> LairResult<Layout> {
+    match version {
+        StoreVersion::V1 => Ok(Layout::default()),
+        _ => Layout::parse(data),
+    }
+}
+
+/// Where the unlock entry starts for a store of this version.
+fn entries_offset(version: StoreVersion, data: &[u8]) -> LairResult<usize> {
+    match version {
+        StoreVersion::V1 => Ok(0),
+        StoreVersion::V2 => Ok(std::cmp::min(HEADER_SIZE, data.len())),
+        oth => Err(LairError::StoreVersionUnsupported(oth.0)),
+    }
+}
@@ -0,0 +1,186 @@
+//! Checks an `EntryStoreBackend` keeps the contract lair relies on, for
+//! implementations to run from their own tests, e.g.
+//! `check_entry_store_backend(Box::new(MyStore::new_empty())).await`.
+//! The checks panic on the first rule the store breaks, naming it.
+
+use super::EntryStoreBackend;
+use crate::*;
+use lair_keystore_api::actor::KeystoreIndex;
+use lair_keystore_api::internal::sign_ed25519;
+
+/// Check ordering, index assignment and replace / delete semantics
+/// against a new, empty `store`.
+pub async fn check_entry_store_backend(mut store: Box<dyn EntryStoreBackend>) {
+    assert!(
+        store.load_unlock().await.unwrap().is_none(),
+        "a new store has no unlock entry"
+    );
+    assert!(
+        store.load_all_entries().await.unwrap().is_empty(),
+        "a new store has no entries"
+    );
+
+    let unlock = new_unlock_entry().await;
+    store.write_unlock(unlock.clone()).await.unwrap();
+    assert_eq!(
+        Some(unlock),
+        store.load_unlock().await.unwrap(),
+        "the unlock entry is loaded as written"
+    );
+
+    let mut entries = Vec::new();
+    for _ in 0..3 {
+        let data = new_entry().await;
+        entries.push((store.append_entry(data.clone()).await.unwrap(), data));
+    }
+    assert_eq!(
+        vec![1, 2, 3],
+        indices(&entries),
+        "indices are assigned from 1, in order"
+    );
+    assert_eq!(
+        entries,
+        store.load_all_entries().await.unwrap(),
+        "entries are loaded in index order, as written"
+    );
+
+    store.mark_deleted(2.into()).await.unwrap();
+    entries[1].1 = entry::EntryDeleted.encode().unwrap();
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+
+    let replaced = new_entry().await;
+    assert!(
+        store
+            .replace_entries(vec![
+                (1.into(), replaced.clone()),
+                (9.into(), replaced.clone()),
+            ])
+            .await
+            .is_err(),
+        "replacing an entry that does not exist fails"
+    );
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+
+    let unlock = new_unlock_entry().await;
+    store
+        .replace_entries(vec![
+            (1.into(), replaced.clone()),
+            (0.into(), unlock.clone()),
+        ])
+        .await
+        .unwrap();
+    entries[0].1 = replaced;
+    assert_eq!(
+        Some(unlock),
+        store.load_unlock().await.unwrap(),
+        "index zero replaces the unlock entry"
+    );
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+
+    // the highest index is deleted, it still may not be used again
+    store.mark_deleted(3.into()).await.unwrap();
+    entries[2].1 = entry::EntryDeleted.encode().unwrap();
+    store.compact().await.unwrap();
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+    let data = new_entry().await;
+    entries.push((store.append_entry(data.clone()).await.unwrap(), data));
+    assert_eq!(
+        vec![1, 2, 3, 4],
+        indices(&entries),
+        "indices of deleted entries are not used again"
+    );
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+
+    store.flush().await.unwrap();
+}
+
+/// Check writes survive reopening the store once they are flushed.
+/// `open` must open the same store every time, which is new and empty
+/// the first time.
+pub async fn check_entry_store_backend_durability<F, Fut>(mut open: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = LairResult<Box<dyn EntryStoreBackend>>>,
+{
+    let mut store = open().await.unwrap();
+    assert!(
+        store.load_unlock().await.unwrap().is_none(),
+        "a new store has no unlock entry"
+    );
+    let unlock = new_unlock_entry().await;
+    store.write_unlock(unlock.clone()).await.unwrap();
+    let mut entries = Vec::new();
+    for _ in 0..2 {
+        let data = new_entry().await;
+        entries.push((store.append_entry(data.clone()).await.unwrap(), data));
+    }
+    store.mark_deleted(2.into()).await.unwrap();
+    entries[1].1 = entry::EntryDeleted.encode().unwrap();
+    store.flush().await.unwrap();
+    drop(store);
+
+    let mut store = open().await.unwrap();
+    assert_eq!(
+        Some(unlock),
+        store.load_unlock().await.unwrap(),
+        "the unlock entry survives reopening"
+    );
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+    let data = new_entry().await;
+    entries.push((store.append_entry(data.clone()).await.unwrap(), data));
+    assert_eq!(
+        vec![1, 2, 3],
+        indices(&entries),
+        "indices are not used again after reopening"
+    );
+    store.flush().await.unwrap();
+    drop(store);
+
+    let mut store = open().await.unwrap();
+    store.load_unlock().await.unwrap();
+    check_loaded(&entries, store.load_all_entries().await.unwrap());
+}
+
+async fn new_unlock_entry() -> Vec<u8> {
+    let (unlock, _) = entry::EntryUnlock::new_from_passphrase(
+        b"conformance".to_vec().into(),
+        entry::UnlockKdf::default(),
+    )
+    .await
+    .unwrap();
+    unlock.encode().unwrap()
+}
+
+async fn new_entry() -> Vec<u8> {
+    let e = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+        .await
+        .unwrap();
+    entry::LairEntry::from(e).encode().unwrap()
+}
+
+fn indices(entries: &[(KeystoreIndex, Vec<u8>)]) -> Vec<u32> {
+    entries.iter().map(|(index, _)| index.0).collect()
+}
+
+/// (deleted entries may load as any tombstone)
+fn check_loaded(
+    expected: &[(KeystoreIndex, Vec<u8>)],
+    loaded: Vec<(KeystoreIndex, Vec<u8>)>,
+) {
+    assert_eq!(
+        indices(expected),
+        indices(&loaded),
+        "every index assigned is loaded, once and in order"
+    );
+    for ((index, data), (_, l_data)) in expected.iter().zip(&loaded) {
+        if entry::EntryDeleted::is_deleted(data) {
+            assert!(
+                entry::EntryDeleted::is_deleted(l_data),
+                "deleted entry {} loads as a tombstone",
+                index
+            );
+        } else {
+            assert_eq!(data, l_data, "entry {} loads as written", index);
+        }
+    }
+}
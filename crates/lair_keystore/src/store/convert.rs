@@ -0,0 +1,83 @@
+//! converting stores between store kinds
+
+use super::store_file::EntryStoreFileSender;
+use crate::*;
+
+/// The outcome of a `convert_store_file()` call.
+#[derive(Debug)]
+pub struct ConvertResult {
+    /// The kind of store converted to.
+    pub to: StoreKind,
+
+    /// Where the converted store was written.
+    pub path: std::path::PathBuf,
+
+    /// The number of entries copied, deleted entries included.
+    pub entries: usize,
+}
+
+/// Copy the unlock entry and all entries of the configured store into a
+/// new store of the kind and at the path configured in `to`, keeping
+/// their indices. Sealed entries are copied as they are, still sealed.
+/// The original store is left in place, point the config at the new
+/// store to use it. Refuses to write over an existing store.
+/// The caller must ensure no lair process is using either store.
+pub async fn convert_store_file(
+    config: &Config,
+    store_file: tokio::fs::File,
+    to: &Config,
+) -> LairResult<ConvertResult> {
+    let path = to.get_store_path().to_owned();
+    if path == config.get_store_path() {
+        return Err("cannot convert a store into itself".into());
+    }
+    if tokio::fs::metadata(&path).await.is_ok() {
+        return Err(format!("{} already exists", path.display()).into());
+    }
+
+    let from = super::spawn_store_backend(config, store_file).await?;
+    let unlock_entry = from
+        .init_load_unlock()
+        .await?
+        .unwrap_or_else(|| vec![0; entry::ENTRY_SIZE]);
+    let entries = from.load_all_entries().await?;
+    from.close().await?;
+
+    // write the new store beside its path, then move it in place
+    let tmp = path.with_extension("converting");
+    let _ = tokio::fs::remove_file(&tmp).await;
+    let tmp_file = tokio::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(&tmp)
+        .await
+        .map_err(LairError::other)?;
+    let tmp_to = Config::builder()
+        .set_root_path(to.get_root_path())
+        .set_store_kind(to.get_store_kind())
+        .set_store_path(&tmp)
+        .build();
+    let to_store = super::spawn_store_backend(&tmp_to, tmp_file).await?;
+
+    to_store.init_load_unlock().await?;
+    to_store.write_unlock(unlock_entry).await?;
+    let mut out = ConvertResult {
+        to: to.get_store_kind(),
+        path,
+        entries: 0,
+    };
+    for (index, entry) in entries {
+        if to_store.write_next_entry(entry).await? != index {
+            return Err("store entry indices are not contiguous".into());
+        }
+        out.entries += 1;
+    }
+    to_store.close().await?;
+
+    tokio::fs::rename(&tmp, &out.path)
+        .await
+        .map_err(LairError::other)?;
+
+    Ok(out)
+}
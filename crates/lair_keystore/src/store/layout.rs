@@ -0,0 +1,160 @@
+//! where the entries of a store file are
+
+use super::version::*;
+use super::KeystoreIndex;
+use crate::*;
+
+/// Byte offset in the header of the number of `EntryCompacted` blocks
+/// following the unlock entry, zero unless the store was compacted.
+const COMPACTED_BLOCKS_AT: usize = 12;
+
+/// The layout of a (current version) store file: the header, the unlock
+/// entry, then for compacted stores the `EntryCompacted` blocks listing
+/// the deleted indices compaction dropped. The entry blocks after take
+/// the remaining indices, in order.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Layout {
+    /// the number of `EntryCompacted` blocks
+    pub compacted_blocks: u32,
+
+    /// the indices dropped by compaction, ascending
+    pub dropped: Vec<u32>,
+}
+
+impl Layout {
+    /// Parse the layout from the start of a store file, at least the
+    /// header block, the unlock entry, and the compacted blocks.
+    pub fn parse(data: &[u8]) -> LairResult<Self> {
+        let mut out = Self {
+            compacted_blocks: compacted_blocks(data),
+            dropped: Vec::new(),
+        };
+        if out.compacted_blocks == 0 {
+            return Ok(out);
+        }
+
+        let start = HEADER_SIZE + entry::ENTRY_SIZE;
+        let end = out.entries_offset() as usize;
+        if data.len() < end {
+            return Err(LairError::StoreCorrupt(
+                "compacted entries are truncated".to_string(),
+            ));
+        }
+        for block in data[start..end].chunks_exact(entry::ENTRY_SIZE) {
+            // a corrupt block here would shift the index of every entry
+            // after it, refuse to guess
+            let compacted =
+                entry::EntryCompacted::decode(block).map_err(|e| {
+                    LairError::StoreCorrupt(format!("compacted entry: {}", e))
+                })?;
+            out.dropped.extend(compacted.deleted);
+        }
+        if out.dropped.windows(2).any(|w| w[0] >= w[1])
+            || out.dropped.first() == Some(&0)
+        {
+            return Err(LairError::StoreCorrupt(
+                "compacted entries are invalid".to_string(),
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Read the layout of a store file.
+    pub async fn read(store_file: &mut tokio::fs::File) -> LairResult<Self> {
+        use tokio::io::AsyncReadExt;
+        use tokio::io::AsyncSeekExt;
+
+        store_file
+            .seek(std::io::SeekFrom::Start(0))
+            .await
+            .map_err(LairError::Io)?;
+        let mut header = vec![0; HEADER_SIZE];
+        match store_file.read_exact(&mut header).await {
+            Ok(_) => (),
+            // a new or truncated store has no entries to lay out
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(LairError::Io(e)),
+        }
+        let count = compacted_blocks(&header);
+        if count == 0 {
+            return Ok(Self::default());
+        }
+
+        let mut data = header;
+        data.resize(HEADER_SIZE + (1 + count as usize) * entry::ENTRY_SIZE, 0);
+        store_file
+            .read_exact(&mut data[HEADER_SIZE..])
+            .await
+            .map_err(LairError::Io)?;
+        Self::parse(&data)
+    }
+
+    /// Encode the blocks following the unlock entry in a store file
+    /// dropping the deleted indices `dropped` (ascending), recording
+    /// their number in `header`.
+    pub fn encode_compacted(
+        header: &mut [u8],
+        dropped: &[u32],
+    ) -> LairResult<Vec<u8>> {
+        let mut out = Vec::new();
+        for deleted in dropped.chunks(entry::EntryCompacted::MAX_INDICES) {
+            out.extend_from_slice(
+                &entry::EntryCompacted {
+                    deleted: deleted.to_vec(),
+                }
+                .encode()?,
+            );
+        }
+        let count = (out.len() / entry::ENTRY_SIZE) as u32;
+        header[COMPACTED_BLOCKS_AT..COMPACTED_BLOCKS_AT + 4]
+            .copy_from_slice(&count.to_le_bytes());
+        Ok(out)
+    }
+
+    /// Where the first entry block (after the unlock entry and the
+    /// compacted blocks) starts.
+    pub fn entries_offset(&self) -> u64 {
+        (HEADER_SIZE + entry::ENTRY_SIZE) as u64
+            + self.compacted_blocks as u64 * entry::ENTRY_SIZE as u64
+    }
+
+    /// The index of the entry block at `position`,
+    /// counting from the first entry block.
+    pub fn index_of(&self, position: u64) -> KeystoreIndex {
+        let mut index = position as u32 + 1;
+        for dropped in self.dropped.iter() {
+            if *dropped > index {
+                break;
+            }
+            index += 1;
+        }
+        index.into()
+    }
+
+    /// Where the block of the entry at `index` starts, index zero being
+    /// the unlock entry. None for indices dropped by compaction.
+    pub fn offset_of(&self, index: KeystoreIndex) -> Option<u64> {
+        if index.0 == 0 {
+            return Some(HEADER_SIZE as u64);
+        }
+        let before = match self.dropped.binary_search(&index.0) {
+            Ok(_) => return None,
+            Err(before) => before,
+        };
+        let position = (index.0 - 1) as u64 - before as u64;
+        Some(self.entries_offset() + position * entry::ENTRY_SIZE as u64)
+    }
+}
+
+/// The number of `EntryCompacted` blocks recorded in a store header.
+fn compacted_blocks(header: &[u8]) -> u32 {
+    if header.len() < HEADER_SIZE {
+        return 0;
+    }
+    let mut count = [0; 4];
+    count
+        .copy_from_slice(&header[COMPACTED_BLOCKS_AT..COMPACTED_BLOCKS_AT + 4]);
+    u32::from_le_bytes(count)
+}
@@ -0,0 +1,192 @@
+//! token bucket rate limits on signing, see `Config::get_rate_limit()`
+
+use crate::*;
+use lair_keystore_api::actor::KeystoreIndex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Signatures at `rate` per second, holding a second's worth for bursts.
+/// Tokens go negative for the requests waiting their turn.
+struct TokenBucket {
+    rate: u32,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, now: Instant) -> Self {
+        Self {
+            rate,
+            tokens: rate as f64,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = f64::min(
+            self.rate as f64,
+            self.tokens + elapsed * self.rate as f64,
+        );
+        self.last = now;
+    }
+
+    /// How long until `count` signatures may be made, after those waiting
+    /// already, `Err` with the time until they could wait, if more than
+    /// `queue` signatures would be waiting.
+    fn check(&self, count: u32, queue: usize) -> Result<Duration, Duration> {
+        // batches larger than a burst wait for a full bucket
+        let needed = f64::min(count as f64, self.rate as f64);
+        let rate = self.rate as f64;
+        let short = needed - self.tokens;
+        if short <= 0.0 {
+            Ok(Duration::ZERO)
+        } else if short <= queue as f64 {
+            Ok(Duration::from_secs_f64(short / rate))
+        } else {
+            // whole milliseconds, as errors cross ipc
+            let retry_after = (short - queue as f64) / rate;
+            Err(Duration::from_millis((retry_after * 1000.0).ceil() as u64))
+        }
+    }
+}
+
+/// The rate limits of a store: of all signatures together, and of the
+/// signatures with each key.
+pub(super) struct RateLimits {
+    all: Option<TokenBucket>,
+    by_index: HashMap<KeystoreIndex, TokenBucket>,
+    queue: usize,
+}
+
+impl RateLimits {
+    pub fn new(config: &Config) -> Self {
+        let now = Instant::now();
+        Self {
+            all: config.get_rate_limit().map(|r| TokenBucket::new(r, now)),
+            by_index: HashMap::new(),
+            queue: config.get_rate_limit_queue(),
+        }
+    }
+
+    /// Reserve `count` signatures with the key of `index`, limited to
+    /// `entry_limit` per second. Returns how long to wait before making
+    /// them, or `LairError::RateLimited` if too many are waiting already.
+    pub fn reserve(
+        &mut self,
+        index: KeystoreIndex,
+        entry_limit: Option<u32>,
+        count: u32,
+        now: Instant,
+    ) -> LairResult<Duration> {
+        let entry = match entry_limit {
+            Some(rate) => {
+                let bucket = self
+                    .by_index
+                    .entry(index)
+                    .or_insert_with(|| TokenBucket::new(rate, now));
+                if bucket.rate != rate {
+                    bucket.rate = rate;
+                    bucket.tokens = f64::min(bucket.tokens, rate as f64);
+                }
+                Some(bucket)
+            }
+            None => {
+                self.by_index.remove(&index);
+                None
+            }
+        };
+        let mut buckets: Vec<&mut TokenBucket> =
+            self.all.iter_mut().chain(entry).collect();
+
+        let mut wait = Duration::ZERO;
+        let mut retry_after = None;
+        for bucket in buckets.iter_mut() {
+            bucket.refill(now);
+            match bucket.check(count, self.queue) {
+                Ok(w) => wait = std::cmp::max(wait, w),
+                Err(r) => {
+                    retry_after = std::cmp::max(retry_after, Some(r));
+                }
+            }
+        }
+        if let Some(retry_after) = retry_after {
+            return Err(LairError::RateLimited { retry_after });
+        }
+        for bucket in buckets {
+            bucket.tokens -= count as f64;
+        }
+        Ok(wait)
+    }
+
+    /// Forget the bucket of an entry that is gone.
+    pub fn remove(&mut self, index: KeystoreIndex) {
+        self.by_index.remove(&index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(all: u32, queue: usize) -> RateLimits {
+        let config = Config::builder()
+            .set_ephemeral(true)
+            .set_rate_limit(all)
+            .set_rate_limit_queue(queue)
+            .build();
+        RateLimits::new(&config)
+    }
+
+    #[test]
+    fn entries_get_a_burst_then_their_rate() {
+        let mut limits = limits(0, 0);
+        let now = Instant::now();
+        let index = 1.into();
+        for _ in 0..10 {
+            assert_eq!(
+                Duration::ZERO,
+                limits.reserve(index, Some(10), 1, now).unwrap()
+            );
+        }
+        match limits.reserve(index, Some(10), 1, now) {
+            Err(LairError::RateLimited { retry_after }) => {
+                assert_eq!(Duration::from_millis(100), retry_after)
+            }
+            r => panic!("unexpected: {:?}", r),
+        }
+        // other entries have buckets of their own, or none
+        assert!(limits.reserve(2.into(), Some(10), 1, now).is_ok());
+        assert!(limits.reserve(3.into(), None, 1000, now).is_ok());
+
+        let later = now + Duration::from_millis(250);
+        assert!(limits.reserve(index, Some(10), 2, later).is_ok());
+        assert!(limits.reserve(index, Some(10), 1, later).is_err());
+    }
+
+    #[test]
+    fn the_global_limit_spans_entries_and_queues() {
+        let mut limits = limits(4, 2);
+        let now = Instant::now();
+        assert!(limits.reserve(1.into(), None, 2, now).is_ok());
+        assert!(limits.reserve(2.into(), None, 2, now).is_ok());
+        // the next two wait their turn
+        assert_eq!(
+            Duration::from_millis(250),
+            limits.reserve(3.into(), None, 1, now).unwrap()
+        );
+        assert_eq!(
+            Duration::from_millis(500),
+            limits.reserve(4.into(), None, 1, now).unwrap()
+        );
+        assert!(limits.reserve(5.into(), None, 1, now).is_err());
+
+        // a batch larger than a burst waits for a full bucket
+        let later = now + Duration::from_secs(2);
+        assert_eq!(
+            Duration::ZERO,
+            limits.reserve(1.into(), None, 10, later).unwrap()
+        );
+        assert!(limits.reserve(1.into(), None, 1, later).is_err());
+    }
+}
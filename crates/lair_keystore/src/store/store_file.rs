@@ -1,5 +1,7 @@
 //! internal ghost actor file wrapper
 
+use super::layout::Layout;
+use super::version::*;
 use crate::*;
 
 ghost_actor::ghost_chan! {
@@ -8,23 +10,78 @@ ghost_actor::ghost_chan! {
         /// init and load up the "unlock" entry if it exists
         fn init_load_unlock() -> Option<Vec<u8>>;
 
+        /// discard all content of the store file
+        fn truncate() -> ();
+
         /// write the unlock entry to the file
         fn write_unlock(entry_data: Vec<u8>) -> ();
 
+        /// atomically swap in new data for an existing entry
+        /// (index zero being the unlock entry) by writing a copy of the
+        /// store beside it and renaming it over the store file
+        fn replace_entry(
+            store_path: std::path::PathBuf,
+            index: super::KeystoreIndex,
+            entry_data: Vec<u8>,
+        ) -> ();
+
+        /// like `replace_entry`, but swapping in several entries
+        /// with a single copy of the store
+        fn replace_entries(
+            store_path: std::path::PathBuf,
+            entries: Vec<(super::KeystoreIndex, Vec<u8>)>,
+        ) -> ();
+
         /// loading all entries from the file
         fn load_all_entries() -> Vec<(super::KeystoreIndex, Vec<u8>)>;
 
         /// write a new entry to the store file
+        /// (it is committed to a journal beside the store file before
+        /// it is appended, see `recover_appending()`)
         fn write_next_entry(entry_data: Vec<u8>) -> super::KeystoreIndex;
+
+        /// seal all entries with `key`, which from then on seals every
+        /// entry written. Entries already sealed are opened with the
+        /// current key (or `key` if there is none yet), and only
+        /// resealed if `unlock_entry` is given, replacing the unlock
+        /// entry along with them in a single copy of the store.
+        fn reseal_all(
+            store_path: std::path::PathBuf,
+            key: Arc<entry::EntryStoreKey>,
+            unlock_entry: Option<Vec<u8>>,
+        ) -> ();
+
+        /// forget the key set by `reseal_all`, as the store is locked.
+        /// Until it is set again, writing entries that would have been
+        /// sealed fails with `LairError::StoreLocked`.
+        fn forget_key() -> ();
+
+        /// rewrite the store without its deleted entries, recording
+        /// their indices so every other entry keeps its index,
+        /// and swap it in
+        fn compact(
+            store_path: std::path::PathBuf,
+        ) -> lair_keystore_api::actor::LairCompactInfo;
+
+        /// sync the store file to disk and stop processing requests
+        fn close() -> ();
     }
 }
 
+/// A `read_only` store file is never written, requests that would
+/// write it fail with `LairError::ReadOnly`.
 pub(crate) async fn spawn_entry_store_file_task(
     store_file: tokio::fs::File,
+    store_path: std::path::PathBuf,
+    read_only: bool,
 ) -> LairResult<futures::channel::mpsc::Sender<EntryStoreFile>> {
     let (s, r) = futures::channel::mpsc::channel(10);
 
-    tokio::task::spawn(entry_store_file_task(store_file, r));
+    if read_only {
+        tokio::task::spawn(read_only_store_file_task(store_file, r));
+    } else {
+        tokio::task::spawn(entry_store_file_task(store_file, store_path, r));
+    }
 
     Ok(s)
 }
@@ -33,14 +90,33 @@ pub(crate) async fn spawn_entry_store_file_task(
 /// we actually need to process requests in series.
 async fn entry_store_file_task(
     mut store_file: tokio::fs::File,
+    store_path: std::path::PathBuf,
     mut recv: futures::channel::mpsc::Receiver<EntryStoreFile>,
 ) -> LairResult<()> {
     use futures::{future::FutureExt, stream::StreamExt};
 
+    // once set, entries are sealed with this key as they are written
+    let mut key: Option<Arc<entry::EntryStoreKey>> = None;
+    // set once the key is forgotten
+    let mut locked = false;
+
+    // where the entries are in the store file, read as it is opened
+    let mut layout = Layout::default();
+
     while let Some(req) = recv.next().await {
         match req {
             EntryStoreFile::InitLoadUnlock { respond, .. } => {
-                let res = init_load_unlock(&mut store_file).await;
+                let res = init_load_unlock(
+                    &mut store_file,
+                    Some(&store_path),
+                    &mut layout,
+                )
+                .await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Truncate { respond, .. } => {
+                layout = Layout::default();
+                let res = truncate(&mut store_file).await;
                 respond.r(Ok(async move { res }.boxed().into()));
             }
             EntryStoreFile::WriteUnlock {
@@ -51,8 +127,53 @@ async fn entry_store_file_task(
                 let res = write_unlock(&mut store_file, entry_data).await;
                 respond.r(Ok(async move { res }.boxed().into()));
             }
+            EntryStoreFile::ReplaceEntry {
+                respond,
+                store_path,
+                index,
+                entry_data,
+                ..
+            } => {
+                let entries = vec![(index, entry_data)];
+                let res = match check_sealable(locked, &entries) {
+                    Ok(()) => {
+                        replace_entries(
+                            &mut store_file,
+                            store_path,
+                            &layout,
+                            entries,
+                            key.as_deref(),
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ReplaceEntries {
+                respond,
+                store_path,
+                entries,
+                ..
+            } => {
+                let res = match check_sealable(locked, &entries) {
+                    Ok(()) => {
+                        replace_entries(
+                            &mut store_file,
+                            store_path,
+                            &layout,
+                            entries,
+                            key.as_deref(),
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
             EntryStoreFile::LoadAllEntries { respond, .. } => {
-                let res = load_all_entries(&mut store_file).await;
+                let res =
+                    load_all_entries(&mut store_file, &layout, false).await;
                 respond.r(Ok(async move { res }.boxed().into()));
             }
             EntryStoreFile::WriteNextEntry {
@@ -60,34 +181,177 @@ async fn entry_store_file_task(
                 entry_data,
                 ..
             } => {
-                let res = write_next_entry(&mut store_file, entry_data).await;
+                let res = match check_sealable_data(locked, &entry_data) {
+                    Ok(()) => {
+                        write_next_entry(
+                            &mut store_file,
+                            &store_path,
+                            &layout,
+                            entry_data,
+                            key.as_deref(),
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
                 respond.r(Ok(async move { res }.boxed().into()));
             }
+            EntryStoreFile::ResealAll {
+                respond,
+                store_path,
+                key: new_key,
+                unlock_entry,
+                ..
+            } => {
+                let res = reseal_all(
+                    &mut store_file,
+                    store_path,
+                    &layout,
+                    key.as_deref(),
+                    &new_key,
+                    unlock_entry,
+                )
+                .await;
+                if res.is_ok() {
+                    key = Some(new_key);
+                    locked = false;
+                }
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ForgetKey { respond, .. } => {
+                locked = key.take().is_some() || locked;
+                respond.r(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            EntryStoreFile::Compact {
+                respond,
+                store_path,
+                ..
+            } => {
+                let res =
+                    compact(&mut store_file, store_path, &mut layout).await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Close { respond, .. } => {
+                let res = store_file.sync_all().await.map_err(LairError::Io);
+                respond.r(Ok(async move { res }.boxed().into()));
+                break;
+            }
         }
     }
 
     Ok(())
 }
 
+/// Serves the requests reading a store file, failing the rest.
+/// Unlike `entry_store_file_task` the file is taken as it is, writes
+/// interrupted by a crash are neither finished nor cut off.
+async fn read_only_store_file_task(
+    mut store_file: tokio::fs::File,
+    mut recv: futures::channel::mpsc::Receiver<EntryStoreFile>,
+) -> LairResult<()> {
+    use futures::{future::FutureExt, stream::StreamExt};
+
+    let mut layout = Layout::default();
+
+    while let Some(req) = recv.next().await {
+        match req {
+            EntryStoreFile::InitLoadUnlock { respond, .. } => {
+                let res =
+                    init_load_unlock(&mut store_file, None, &mut layout).await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadAllEntries { respond, .. } => {
+                let res =
+                    load_all_entries(&mut store_file, &layout, true).await;
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Truncate { respond, .. }
+            | EntryStoreFile::WriteUnlock { respond, .. }
+            | EntryStoreFile::ReplaceEntry { respond, .. }
+            | EntryStoreFile::ReplaceEntries { respond, .. }
+            | EntryStoreFile::ResealAll { respond, .. } => {
+                respond.r(Ok(async move { Err(LairError::ReadOnly) }
+                    .boxed()
+                    .into()));
+            }
+            EntryStoreFile::WriteNextEntry { respond, .. } => {
+                respond.r(Ok(async move { Err(LairError::ReadOnly) }
+                    .boxed()
+                    .into()));
+            }
+            EntryStoreFile::Compact { respond, .. } => {
+                respond.r(Ok(async move { Err(LairError::ReadOnly) }
+                    .boxed()
+                    .into()));
+            }
+            // there is no key, nothing is written
+            EntryStoreFile::ForgetKey { respond, .. } => {
+                respond.r(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            EntryStoreFile::Close { respond, .. } => {
+                respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the layout and the unlock entry of the store file. Without a
+/// `store_path` (read-only) nothing is written: a new store is left
+/// without a header, and interrupted writes are left as they are.
 async fn init_load_unlock(
     store_file: &mut tokio::fs::File,
+    store_path: Option<&std::path::Path>,
+    layout: &mut Layout,
 ) -> LairResult<Option<Vec<u8>>> {
     use tokio::io::AsyncReadExt;
     use tokio::io::AsyncSeekExt;
 
-    let meta = store_file.metadata().await.map_err(LairError::other)?;
+    let meta = store_file.metadata().await.map_err(LairError::Io)?;
     let total_size = meta.len();
-    if total_size >= entry::ENTRY_SIZE as u64 {
+
+    if total_size == 0 {
+        *layout = Layout::default();
+        if store_path.is_some() {
+            write_header(store_file).await?;
+        }
+        return Ok(None);
+    }
+
+    store_file
+        .seek(std::io::SeekFrom::Start(0))
+        .await
+        .map_err(LairError::Io)?;
+
+    let mut header = vec![0; std::cmp::min(total_size as usize, HEADER_SIZE)];
+    store_file
+        .read_exact(&mut header)
+        .await
+        .map_err(LairError::Io)?;
+    if let Some(version) = StoreVersion::detect(&header) {
+        version.check()?;
+    }
+
+    *layout = Layout::read(store_file).await?;
+    let total_size = match store_path {
+        Some(store_path) => {
+            recover_appending(store_file, store_path, layout).await?
+        }
+        None => total_size,
+    };
+
+    if total_size >= (HEADER_SIZE + entry::ENTRY_SIZE) as u64 {
         store_file
-            .seek(std::io::SeekFrom::Start(0))
+            .seek(std::io::SeekFrom::Start(HEADER_SIZE as u64))
             .await
-            .map_err(LairError::other)?;
-
+            .map_err(LairError::Io)?;
         let mut buf = vec![0; entry::ENTRY_SIZE];
         store_file
             .read_exact(&mut buf)
             .await
-            .map_err(LairError::other)?;
+            .map_err(LairError::Io)?;
 
         Ok(Some(buf))
     } else {
@@ -95,6 +359,185 @@ async fn init_load_unlock(
     }
 }
 
+/// Byte length of the checksum of an `.appending` journal record.
+const APPENDING_CHECKSUM_BYTES: usize = 16;
+
+/// Where a new entry is committed before it is appended to the store.
+pub(super) fn appending_path(
+    store_path: &std::path::Path,
+) -> std::path::PathBuf {
+    path_with_suffix(store_path, ".appending")
+}
+
+/// Where the `.appending` journal is written before it is renamed
+/// into place, committing the entry.
+pub(super) fn staging_path(store_path: &std::path::Path) -> std::path::PathBuf {
+    path_with_suffix(store_path, ".staging")
+}
+
+/// `path` with `suffix` appended, rather than replacing its extension,
+/// so stores at `store` and e.g. `store.converting` don't share journals.
+fn path_with_suffix(
+    path: &std::path::Path,
+    suffix: &str,
+) -> std::path::PathBuf {
+    let mut out = path.to_owned().into_os_string();
+    out.push(suffix);
+    out.into()
+}
+
+/// Encode the `.appending` journal record of a new entry:
+/// its index, the entry, then a checksum of both.
+pub(super) fn encode_appending(
+    index: super::KeystoreIndex,
+    entry_data: &[u8],
+) -> Vec<u8> {
+    let mut out =
+        Vec::with_capacity(4 + entry::ENTRY_SIZE + APPENDING_CHECKSUM_BYTES);
+    out.extend_from_slice(&index.0.to_le_bytes());
+    out.extend_from_slice(entry_data);
+    let checksum = blake2b_simd::Params::new()
+        .hash_length(APPENDING_CHECKSUM_BYTES)
+        .hash(&out);
+    out.extend_from_slice(checksum.as_bytes());
+    out
+}
+
+fn decode_appending(data: &[u8]) -> Option<(super::KeystoreIndex, &[u8])> {
+    if data.len() != 4 + entry::ENTRY_SIZE + APPENDING_CHECKSUM_BYTES {
+        return None;
+    }
+    let (record, checksum) =
+        data.split_at(data.len() - APPENDING_CHECKSUM_BYTES);
+    let expect = blake2b_simd::Params::new()
+        .hash_length(APPENDING_CHECKSUM_BYTES)
+        .hash(record);
+    if expect.as_bytes() != checksum {
+        return None;
+    }
+    let mut index = [0; 4];
+    index.copy_from_slice(&record[..4]);
+    Some((u32::from_le_bytes(index).into(), &record[4..]))
+}
+
+/// Sync the directory holding `path`, making renames / removals in it
+/// durable.
+pub(super) async fn sync_parent_dir(path: &std::path::Path) -> LairResult<()> {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        tokio::fs::File::open(dir)
+            .await
+            .map_err(LairError::Io)?
+            .sync_all()
+            .await
+            .map_err(LairError::Io)?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Finish or roll back an entry write interrupted by a crash, returning
+/// the size of the store file after.
+/// An entry committed to the `.appending` journal is written (again) at
+/// its index, anything else past the last whole entry was never
+/// committed, and is cut off, as is an unfinished `.staging` journal.
+async fn recover_appending(
+    store_file: &mut tokio::fs::File,
+    store_path: &std::path::Path,
+    layout: &Layout,
+) -> LairResult<u64> {
+    use tokio::io::AsyncWriteExt;
+
+    let _ = tokio::fs::remove_file(staging_path(store_path)).await;
+
+    let mut total_size =
+        store_file.metadata().await.map_err(LairError::Io)?.len();
+    let whole_size = HEADER_SIZE as u64
+        + (total_size.saturating_sub(HEADER_SIZE as u64)
+            / entry::ENTRY_SIZE as u64)
+            * entry::ENTRY_SIZE as u64;
+
+    let appending = appending_path(store_path);
+    let journal = match tokio::fs::read(&appending).await {
+        Ok(data) => Some(data),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(LairError::Io(err)),
+    };
+    let replay = journal.as_deref().and_then(decode_appending).and_then(
+        |(index, entry_data)| {
+            // the journal is of the last entry written, either cut
+            // off or whole (if the journal outlived the write)
+            let at = layout.offset_of(index)?;
+            if index.0 > 0
+                && at <= whole_size
+                && at + entry::ENTRY_SIZE as u64 >= whole_size
+            {
+                Some((index, entry_data, at))
+            } else {
+                None
+            }
+        },
+    );
+
+    if let Some((index, entry_data, at)) = replay {
+        tracing::warn!(%index, "finishing an interrupted entry write");
+        // the store file is opened for appending,
+        // so the entry is written where it was cut off
+        store_file.set_len(at).await.map_err(LairError::Io)?;
+        store_file
+            .write_all(entry_data)
+            .await
+            .map_err(LairError::Io)?;
+        store_file.sync_all().await.map_err(LairError::Io)?;
+        total_size = at + entry::ENTRY_SIZE as u64;
+    } else if total_size != whole_size {
+        tracing::warn!(
+            bytes = total_size - whole_size,
+            "cutting off a partially written entry"
+        );
+        store_file
+            .set_len(whole_size)
+            .await
+            .map_err(LairError::Io)?;
+        store_file.sync_all().await.map_err(LairError::Io)?;
+        total_size = whole_size;
+    }
+
+    if journal.is_some() {
+        tokio::fs::remove_file(&appending)
+            .await
+            .map_err(LairError::Io)?;
+        sync_parent_dir(&appending).await?;
+    }
+
+    Ok(total_size)
+}
+
+async fn write_header(store_file: &mut tokio::fs::File) -> LairResult<()> {
+    use tokio::io::AsyncSeekExt;
+    use tokio::io::AsyncWriteExt;
+
+    store_file
+        .seek(std::io::SeekFrom::Start(0))
+        .await
+        .map_err(LairError::Io)?;
+
+    store_file
+        .write_all(&StoreVersion::CURRENT.encode_header())
+        .await
+        .map_err(LairError::Io)?;
+
+    store_file.sync_all().await.map_err(LairError::Io)?;
+
+    Ok(())
+}
+
+async fn truncate(store_file: &mut tokio::fs::File) -> LairResult<()> {
+    store_file.set_len(0).await.map_err(LairError::Io)?;
+    write_header(store_file).await
+}
+
 async fn write_unlock(
     store_file: &mut tokio::fs::File,
     entry_data: Vec<u8>,
@@ -103,102 +546,395 @@ async fn write_unlock(
     use tokio::io::AsyncWriteExt;
 
     store_file
-        .seek(std::io::SeekFrom::Start(0))
+        .seek(std::io::SeekFrom::Start(HEADER_SIZE as u64))
         .await
-        .map_err(LairError::other)?;
+        .map_err(LairError::Io)?;
 
     store_file
         .write_all(&entry_data)
         .await
-        .map_err(LairError::other)?;
+        .map_err(LairError::Io)?;
+
+    store_file.sync_all().await.map_err(LairError::Io)?;
+
+    Ok(())
+}
+
+/// Seal `entry_data` with `key`, if there is a key and it is an
+/// entry that is not yet sealed (tombstones have nothing to seal).
+/// Once the key is forgotten (`locked`), only entries that would not be
+/// sealed anyway may be written: the unlock entry and tombstones.
+pub(super) fn check_sealable(
+    locked: bool,
+    entries: &[(super::KeystoreIndex, Vec<u8>)],
+) -> LairResult<()> {
+    entries
+        .iter()
+        .filter(|(index, _)| index.0 != 0)
+        .try_for_each(|(_, entry_data)| check_sealable_data(locked, entry_data))
+}
+
+/// see `check_sealable`, for a new entry
+pub(super) fn check_sealable_data(
+    locked: bool,
+    entry_data: &[u8],
+) -> LairResult<()> {
+    if locked
+        && !entry::EntryDeleted::is_deleted(entry_data)
+        && !entry::LairEntry::is_sealed(entry_data)
+    {
+        return Err(LairError::StoreLocked);
+    }
+    Ok(())
+}
+
+pub(super) fn seal_entry(
+    entry_data: Vec<u8>,
+    key: Option<&entry::EntryStoreKey>,
+) -> LairResult<Vec<u8>> {
+    if entry_data.len() != entry::ENTRY_SIZE {
+        return Err(format!(
+            "bad entry size, expected {}, got {}",
+            entry::ENTRY_SIZE,
+            entry_data.len(),
+        )
+        .into());
+    }
+    match key {
+        Some(key)
+            if !entry::EntryDeleted::is_deleted(&entry_data)
+                && !entry::LairEntry::is_sealed(&entry_data) =>
+        {
+            let (entry, meta) =
+                entry::LairEntry::decode_with_meta(&entry_data)?;
+            entry.seal_with_meta(&meta, key)
+        }
+        _ => Ok(entry_data),
+    }
+}
+
+async fn read_all(store_file: &mut tokio::fs::File) -> LairResult<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncSeekExt;
+
+    store_file
+        .seek(std::io::SeekFrom::Start(0))
+        .await
+        .map_err(LairError::Io)?;
+    let mut data = Vec::new();
+    store_file
+        .read_to_end(&mut data)
+        .await
+        .map_err(LairError::Io)?;
+    Ok(data)
+}
 
-    store_file.sync_all().await.map_err(LairError::other)?;
+async fn replace_entries(
+    store_file: &mut tokio::fs::File,
+    store_path: std::path::PathBuf,
+    layout: &Layout,
+    entries: Vec<(super::KeystoreIndex, Vec<u8>)>,
+    key: Option<&entry::EntryStoreKey>,
+) -> LairResult<()> {
+    let entries = entries
+        .into_iter()
+        .map(|(index, entry_data)| {
+            // the unlock entry is never sealed
+            if index.0 == 0 {
+                return seal_entry(entry_data, None).map(|d| (index, d));
+            }
+            seal_entry(entry_data, key).map(|d| (index, d))
+        })
+        .collect::<LairResult<Vec<_>>>()?;
+
+    let mut data = read_all(store_file).await?;
+    for (index, entry_data) in entries {
+        let start = match layout.offset_of(index) {
+            Some(start) => start as usize,
+            None => {
+                return Err(format!(
+                    "store entry {} was deleted and compacted",
+                    index
+                )
+                .into())
+            }
+        };
+        let end = start + entry::ENTRY_SIZE;
+        if data.len() < end {
+            return Err(format!(
+                "store file has no entry {} to replace",
+                index
+            )
+            .into());
+        }
+        data[start..end].copy_from_slice(&entry_data);
+    }
+
+    swap_in(store_file, store_path, data).await
+}
+
+/// Write `data` beside the store file, and rename it over it.
+async fn swap_in(
+    store_file: &mut tokio::fs::File,
+    store_path: std::path::PathBuf,
+    data: Vec<u8>,
+) -> LairResult<()> {
+    use tokio::io::AsyncWriteExt;
+
+    // until the rename, the old store file is untouched,
+    // after it, the new one is complete on disk
+    let tmp = store_path.with_extension("rewriting");
+    let mut tmp_file =
+        tokio::fs::File::create(&tmp).await.map_err(LairError::Io)?;
+    tmp_file.write_all(&data).await.map_err(LairError::Io)?;
+    tmp_file.sync_all().await.map_err(LairError::Io)?;
+    drop(tmp_file);
+    tokio::fs::rename(&tmp, &store_path)
+        .await
+        .map_err(LairError::Io)?;
+    // make the rename itself durable
+    sync_parent_dir(&store_path).await?;
+    // the new store file has every entry written so far,
+    // an entry write journal outliving its write is moot now
+    let _ = tokio::fs::remove_file(appending_path(&store_path)).await;
+
+    // our handle still refers to the replaced file
+    *store_file = tokio::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .open(&store_path)
+        .await
+        .map_err(LairError::Io)?;
 
     Ok(())
 }
 
+async fn reseal_all(
+    store_file: &mut tokio::fs::File,
+    store_path: std::path::PathBuf,
+    layout: &Layout,
+    key: Option<&entry::EntryStoreKey>,
+    new_key: &entry::EntryStoreKey,
+    unlock_entry: Option<Vec<u8>>,
+) -> LairResult<()> {
+    let mut data = read_all(store_file).await?;
+    let unlock_end = HEADER_SIZE + entry::ENTRY_SIZE;
+    if data.len() < unlock_end {
+        return Err("store file has no unlock entry".into());
+    }
+    let entries_start = layout.entries_offset() as usize;
+    let rekey = unlock_entry.is_some();
+    let mut changed = false;
+
+    if let Some(unlock_entry) = unlock_entry {
+        if unlock_entry.len() != entry::ENTRY_SIZE {
+            return Err("bad unlock entry size".into());
+        }
+        data[HEADER_SIZE..unlock_end].copy_from_slice(&unlock_entry);
+        changed = true;
+    }
+
+    let open_key = key.unwrap_or(new_key);
+    let mut opened = super::SealedOpenCount::default();
+    for block in data[entries_start..].chunks_exact_mut(entry::ENTRY_SIZE) {
+        if entry::EntryDeleted::is_deleted(block) {
+            continue;
+        }
+        let sealed = entry::LairEntry::is_sealed(block);
+        let (entry, meta) = match opened
+            .open(sealed, entry::LairEntry::unseal_with_meta(block, open_key))
+        {
+            Some(r) => r,
+            None => continue,
+        };
+        if sealed && !rekey {
+            continue;
+        }
+        block.copy_from_slice(&entry.seal_with_meta(&meta, new_key)?);
+        changed = true;
+    }
+    opened.check()?;
+
+    if !changed {
+        return Ok(());
+    }
+
+    swap_in(store_file, store_path, data).await
+}
+
 async fn query_entry_count(
     store_file: &mut tokio::fs::File,
 ) -> LairResult<u64> {
-    let meta = store_file.metadata().await.map_err(LairError::other)?;
-    let total_size = meta.len();
+    let meta = store_file.metadata().await.map_err(LairError::Io)?;
+    let total_size = meta.len().saturating_sub(HEADER_SIZE as u64);
     let entry_count = total_size / entry::ENTRY_SIZE as u64;
 
     if entry_count * entry::ENTRY_SIZE as u64 != total_size {
-        // @todo - panic for now... eventually cover over invalid entry
-        panic!(
+        // partial entries are cut off as the store is opened,
+        // see `recover_appending()`
+        return Err(format!(
             "BAD entry size {} count * {} size != {} file size",
             entry_count,
             entry::ENTRY_SIZE,
             total_size
-        );
+        )
+        .into());
     }
 
     Ok(entry_count)
 }
 
+/// Load the entries of the store file, skipping a partially written
+/// entry at its end if `partial_ok` (for read-only stores, which are
+/// not recovered as they are opened) rather than failing.
 async fn load_all_entries(
     store_file: &mut tokio::fs::File,
+    layout: &Layout,
+    partial_ok: bool,
 ) -> LairResult<Vec<(super::KeystoreIndex, Vec<u8>)>> {
     use tokio::io::AsyncReadExt;
     use tokio::io::AsyncSeekExt;
 
-    let entry_count = query_entry_count(store_file).await?;
+    let entry_count = if partial_ok {
+        let total_size =
+            store_file.metadata().await.map_err(LairError::Io)?.len();
+        total_size.saturating_sub(HEADER_SIZE as u64) / entry::ENTRY_SIZE as u64
+    } else {
+        query_entry_count(store_file).await?
+    };
+    let block_count =
+        entry_count.saturating_sub(1 + layout.compacted_blocks as u64);
 
-    if entry_count <= 1 {
-        return Ok(Vec::with_capacity(0));
-    }
+    let mut out = Vec::new();
 
-    store_file
-        .seek(std::io::SeekFrom::Start(entry::ENTRY_SIZE as u64))
-        .await
-        .map_err(LairError::other)?;
+    // deleted entries dropped by compaction are still deleted entries,
+    // and their indices are never reused
+    for index in layout.dropped.iter() {
+        out.push(((*index).into(), entry::EntryDeleted.encode()?));
+    }
 
-    let mut out = Vec::new();
+    if block_count > 0 {
+        store_file
+            .seek(std::io::SeekFrom::Start(layout.entries_offset()))
+            .await
+            .map_err(LairError::Io)?;
+    }
 
-    for i in 1..(entry_count as u32) {
+    for position in 0..block_count {
         let mut buf = vec![0; entry::ENTRY_SIZE];
         store_file
             .read_exact(&mut buf)
             .await
-            .map_err(LairError::other)?;
-        out.push((i.into(), buf));
+            .map_err(LairError::Io)?;
+        out.push((layout.index_of(position), buf));
     }
 
+    out.sort_by_key(|(index, _)| *index);
+
     Ok(out)
 }
 
+/// Write a new entry: commit it to the `.appending` journal (written as
+/// `.staging`, then renamed into place), append it to the store file,
+/// then drop the journal. A crash before the rename leaves the store
+/// as it was, one after it has the entry finished on the next open.
 async fn write_next_entry(
     store_file: &mut tokio::fs::File,
+    store_path: &std::path::Path,
+    layout: &Layout,
     entry_data: Vec<u8>,
+    key: Option<&entry::EntryStoreKey>,
 ) -> LairResult<super::KeystoreIndex> {
     use tokio::io::AsyncSeekExt;
     use tokio::io::AsyncWriteExt;
 
-    if entry_data.len() != entry::ENTRY_SIZE {
-        return Err(format!(
-            "bad entry size, expected {}, got {}",
-            entry::ENTRY_SIZE,
-            entry_data.len(),
-        )
-        .into());
-    }
+    let entry_data = seal_entry(entry_data, key)?;
 
     let entry_count = query_entry_count(store_file).await?;
+    let index =
+        match entry_count.checked_sub(1 + layout.compacted_blocks as u64) {
+            Some(position) => layout.index_of(position),
+            None => (entry_count as u32).into(),
+        };
+
+    let staging = staging_path(store_path);
+    let appending = appending_path(store_path);
+    super::write_synced(&staging, &encode_appending(index, &entry_data))
+        .await?;
+    tokio::fs::rename(&staging, &appending)
+        .await
+        .map_err(LairError::Io)?;
+    sync_parent_dir(&appending).await?;
 
-    let start_loc = entry_count * entry::ENTRY_SIZE as u64;
+    let start_loc = HEADER_SIZE as u64 + entry_count * entry::ENTRY_SIZE as u64;
 
     store_file
         .seek(std::io::SeekFrom::Start(start_loc))
         .await
-        .map_err(LairError::other)?;
+        .map_err(LairError::Io)?;
 
     store_file
         .write_all(&entry_data)
         .await
-        .map_err(LairError::other)?;
+        .map_err(LairError::Io)?;
+
+    store_file.sync_all().await.map_err(LairError::Io)?;
 
-    store_file.sync_all().await.map_err(LairError::other)?;
+    // the entry is in the store now, a journal that outlives this
+    // (e.g. the process dies first) is dropped as the store is opened
+    if let Err(err) = tokio::fs::remove_file(&appending).await {
+        tracing::warn!(?err, "failed to remove the entry write journal");
+    }
 
-    Ok((entry_count as u32).into())
+    Ok(index)
+}
+
+/// Rewrite the store without its deleted entries. Their indices are
+/// recorded in `EntryCompacted` blocks after the unlock entry, which
+/// `Layout` uses to give every entry after them the same index as before.
+async fn compact(
+    store_file: &mut tokio::fs::File,
+    store_path: std::path::PathBuf,
+    layout: &mut Layout,
+) -> LairResult<lair_keystore_api::actor::LairCompactInfo> {
+    let mut out = lair_keystore_api::actor::LairCompactInfo::default();
+
+    let data = read_all(store_file).await?;
+    let unlock_end = HEADER_SIZE + entry::ENTRY_SIZE;
+    if data.len() < unlock_end {
+        // no unlock entry, no entries to compact
+        return Ok(out);
+    }
+
+    let mut dropped = layout.dropped.clone();
+    let mut kept = Vec::new();
+    let blocks = &data[layout.entries_offset() as usize..];
+    for (position, block) in blocks.chunks_exact(entry::ENTRY_SIZE).enumerate()
+    {
+        if entry::EntryDeleted::is_deleted(block) {
+            dropped.push(layout.index_of(position as u64).0);
+        } else {
+            kept.extend_from_slice(block);
+        }
+    }
+
+    if dropped.len() == layout.dropped.len() {
+        return Ok(out);
+    }
+    dropped.sort_unstable();
+
+    let mut new_data = data[..unlock_end].to_vec();
+    let compacted =
+        Layout::encode_compacted(&mut new_data[..HEADER_SIZE], &dropped)?;
+    new_data.extend_from_slice(&compacted);
+    new_data.extend_from_slice(&kept);
+
+    out.entries_removed = (dropped.len() - layout.dropped.len()) as u64;
+    out.bytes_reclaimed =
+        (data.len() as u64).saturating_sub(new_data.len() as u64);
+
+    swap_in(store_file, store_path, new_data.clone()).await?;
+    *layout = Layout::parse(&new_data)?;
+
+    Ok(out)
 }
@@ -0,0 +1,132 @@
+//! in-memory store of ephemeral configs
+
+use super::{EntryStoreBackend, IndexedEntries};
+use crate::*;
+use futures::future::{BoxFuture, FutureExt};
+use lair_keystore_api::actor::{KeystoreIndex, LairCompactInfo};
+use std::collections::BTreeMap;
+
+/// An `EntryStoreBackend` keeping its entries in memory: nothing is
+/// written anywhere, and the entries are gone once it is dropped
+/// (the store of ephemeral configs, see `ConfigBuilder::set_ephemeral()`).
+#[derive(Default)]
+pub struct MemoryEntryStore {
+    unlock: Option<Vec<u8>>,
+    /// entries by index, without the deleted entries dropped by compaction
+    entries: BTreeMap<u32, Vec<u8>>,
+    /// the highest index assigned, indices are never used again
+    last_index: u32,
+}
+
+impl MemoryEntryStore {
+    fn check_size(entry_data: &[u8]) -> LairResult<()> {
+        if entry_data.len() != entry::ENTRY_SIZE {
+            return Err(format!(
+                "bad entry size, expected {}, got {}",
+                entry::ENTRY_SIZE,
+                entry_data.len(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn replace(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    ) -> LairResult<()> {
+        for (index, entry_data) in &entries {
+            Self::check_size(entry_data)?;
+            let exists = match index.0 {
+                0 => self.unlock.is_some(),
+                index => self.entries.contains_key(&index),
+            };
+            if !exists {
+                return Err(
+                    format!("store has no entry {} to replace", index).into()
+                );
+            }
+        }
+        for (index, entry_data) in entries {
+            match index.0 {
+                0 => self.unlock = Some(entry_data),
+                index => {
+                    self.entries.insert(index, entry_data);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deleted entries dropped by compaction are still deleted entries.
+    fn load_all(&self) -> LairResult<IndexedEntries> {
+        let mut out = Vec::with_capacity(self.last_index as usize);
+        for index in 1..=self.last_index {
+            let entry_data = match self.entries.get(&index) {
+                Some(entry_data) => entry_data.clone(),
+                None => entry::EntryDeleted.encode()?,
+            };
+            out.push((index.into(), entry_data));
+        }
+        Ok(out)
+    }
+}
+
+impl EntryStoreBackend for MemoryEntryStore {
+    fn load_unlock(&mut self) -> BoxFuture<'_, LairResult<Option<Vec<u8>>>> {
+        let res = Ok(self.unlock.clone());
+        async move { res }.boxed()
+    }
+
+    fn write_unlock(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        let res = Self::check_size(&entry_data).map(|()| {
+            self.unlock = Some(entry_data);
+        });
+        async move { res }.boxed()
+    }
+
+    fn load_all_entries(
+        &mut self,
+    ) -> BoxFuture<'_, LairResult<IndexedEntries>> {
+        let res = self.load_all();
+        async move { res }.boxed()
+    }
+
+    fn append_entry(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<KeystoreIndex>> {
+        let res = Self::check_size(&entry_data).map(|()| {
+            self.last_index += 1;
+            self.entries.insert(self.last_index, entry_data);
+            self.last_index.into()
+        });
+        async move { res }.boxed()
+    }
+
+    fn replace_entries(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        let res = self.replace(entries);
+        async move { res }.boxed()
+    }
+
+    #[allow(clippy::field_reassign_with_default)]
+    fn compact(&mut self) -> BoxFuture<'_, LairResult<LairCompactInfo>> {
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, data| !entry::EntryDeleted::is_deleted(data));
+        let mut out = LairCompactInfo::default();
+        out.entries_removed = (before - self.entries.len()) as u64;
+        out.bytes_reclaimed = out.entries_removed * entry::ENTRY_SIZE as u64;
+        async move { Ok(out) }.boxed()
+    }
+
+    fn flush(&mut self) -> BoxFuture<'_, LairResult<()>> {
+        async move { Ok(()) }.boxed()
+    }
+}
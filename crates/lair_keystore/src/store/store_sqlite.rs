@@ -0,0 +1,583 @@
+//! internal sqlite store, serving the same requests as the store file
+
+use super::store_file::{
+    check_sealable, check_sealable_data, seal_entry, EntryStoreFile,
+};
+use super::KeystoreIndex;
+use crate::*;
+use lair_keystore_api::actor::LairEntryType;
+use rusqlite::{params, OptionalExtension};
+
+/// Version of the sqlite schema, kept in `PRAGMA user_version`.
+const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE entries (
+    idx INTEGER PRIMARY KEY NOT NULL,
+    entry_type INTEGER,
+    pub_id BLOB,
+    tag TEXT,
+    data BLOB NOT NULL
+);
+CREATE INDEX entries_by_type ON entries (entry_type);
+CREATE INDEX entries_by_pub_id ON entries (pub_id);
+CREATE INDEX entries_by_tag ON entries (tag);
+";
+
+/// A `read_only` store is opened read-only, requests that would
+/// write it fail with `LairError::ReadOnly`.
+pub(crate) async fn spawn_entry_store_sqlite_task(
+    store_path: std::path::PathBuf,
+    read_only: bool,
+) -> LairResult<futures::channel::mpsc::Sender<EntryStoreFile>> {
+    let con = tokio::task::spawn_blocking(move || {
+        if read_only {
+            open_read_only(&store_path)
+        } else {
+            open(&store_path)
+        }
+    })
+    .await
+    .map_err(LairError::other)??;
+
+    let (s, r) = futures::channel::mpsc::channel(10);
+
+    // sqlite calls block, keep them off the async runtime
+    std::thread::Builder::new()
+        .name("lair-sqlite-store".to_string())
+        .spawn(move || {
+            if read_only {
+                read_only_sqlite_task(con, r)
+            } else {
+                entry_store_sqlite_task(con, r)
+            }
+        })
+        .map_err(LairError::other)?;
+
+    Ok(s)
+}
+
+/// Read the unlock entry and all entries out of a sqlite store,
+/// laid out as a store file of the current format, for checking.
+/// Indices with no entry (deleted entries removed by compaction) are
+/// filled in with tombstones, a missing unlock entry is left zeroed.
+pub(crate) async fn read_store_image(
+    store_path: std::path::PathBuf,
+) -> LairResult<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let con = rusqlite::Connection::open_with_flags(
+            &store_path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .map_err(LairError::other)?;
+        if check_schema_version(&con)? == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut out = super::StoreVersion::CURRENT.encode_header();
+        let tombstone = entry::EntryDeleted.encode()?;
+        let mut stmt = con
+            .prepare("SELECT idx, data FROM entries ORDER BY idx")
+            .map_err(LairError::other)?;
+        let rows = stmt
+            .query_map(params![], |r| {
+                Ok((r.get::<_, u32>(0)?, r.get::<_, Vec<u8>>(1)?))
+            })
+            .map_err(LairError::other)?;
+        for row in rows {
+            let (index, data) = row.map_err(LairError::other)?;
+            let start = super::HEADER_SIZE + index as usize * entry::ENTRY_SIZE;
+            if out.len() == super::HEADER_SIZE && index > 0 {
+                out.resize(super::HEADER_SIZE + entry::ENTRY_SIZE, 0);
+            }
+            while out.len() < start {
+                out.extend_from_slice(&tombstone);
+            }
+            out.extend_from_slice(&data);
+        }
+        Ok(out)
+    })
+    .await
+    .map_err(LairError::other)?
+}
+
+fn open(store_path: &std::path::Path) -> LairResult<rusqlite::Connection> {
+    let mut con =
+        rusqlite::Connection::open(store_path).map_err(LairError::other)?;
+
+    // every committed write is on disk before we respond to it
+    con.pragma_update(None, "synchronous", &"FULL")
+        .map_err(LairError::other)?;
+
+    if check_schema_version(&con)? == 0 {
+        let tx = con.transaction().map_err(LairError::other)?;
+        tx.execute_batch(SCHEMA).map_err(LairError::other)?;
+        tx.pragma_update(None, "user_version", &SCHEMA_VERSION)
+            .map_err(LairError::other)?;
+        tx.commit().map_err(LairError::other)?;
+    }
+
+    Ok(con)
+}
+
+fn open_read_only(
+    store_path: &std::path::Path,
+) -> LairResult<rusqlite::Connection> {
+    let con = rusqlite::Connection::open_with_flags(
+        store_path,
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )
+    .map_err(LairError::other)?;
+    if check_schema_version(&con)? == 0 {
+        return Err("sqlite store is not initialized".into());
+    }
+    Ok(con)
+}
+
+/// The schema version of the database, 0 if it is new.
+fn check_schema_version(con: &rusqlite::Connection) -> LairResult<u32> {
+    let version: u32 = con
+        .pragma_query_value(None, "user_version", |r| r.get(0))
+        .map_err(LairError::other)?;
+    if version > SCHEMA_VERSION {
+        return Err(LairError::StoreVersionUnsupported(version));
+    }
+    Ok(version)
+}
+
+/// this is not an actor, sqlite access is blocking,
+/// so requests are processed in series on a thread of their own.
+fn entry_store_sqlite_task(
+    mut con: rusqlite::Connection,
+    mut recv: futures::channel::mpsc::Receiver<EntryStoreFile>,
+) {
+    use futures::{future::FutureExt, stream::StreamExt};
+
+    // once set, entries are sealed with this key as they are written
+    let mut key: Option<Arc<entry::EntryStoreKey>> = None;
+    // set once the key is forgotten
+    let mut locked = false;
+
+    while let Some(req) = futures::executor::block_on(recv.next()) {
+        match req {
+            EntryStoreFile::InitLoadUnlock { respond, .. } => {
+                let res = load_unlock(&con);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Truncate { respond, .. } => {
+                let res = con
+                    .execute("DELETE FROM entries", params![])
+                    .map(|_| ())
+                    .map_err(LairError::other);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteUnlock {
+                respond,
+                entry_data,
+                ..
+            } => {
+                let res = con
+                    .execute(
+                        "INSERT OR REPLACE INTO entries (idx, data)
+                        VALUES (0, ?1)",
+                        params![entry_data],
+                    )
+                    .map(|_| ())
+                    .map_err(LairError::other);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ReplaceEntry {
+                respond,
+                index,
+                entry_data,
+                ..
+            } => {
+                let entries = vec![(index, entry_data)];
+                let res = check_sealable(locked, &entries).and_then(|()| {
+                    replace_entries(&mut con, entries, key.as_deref())
+                });
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ReplaceEntries {
+                respond, entries, ..
+            } => {
+                let res = check_sealable(locked, &entries).and_then(|()| {
+                    replace_entries(&mut con, entries, key.as_deref())
+                });
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadAllEntries { respond, .. } => {
+                let res = load_all_entries(&con);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::WriteNextEntry {
+                respond,
+                entry_data,
+                ..
+            } => {
+                let res =
+                    check_sealable_data(locked, &entry_data).and_then(|()| {
+                        write_next_entry(&mut con, entry_data, key.as_deref())
+                    });
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ResealAll {
+                respond,
+                key: new_key,
+                unlock_entry,
+                ..
+            } => {
+                let res = reseal_all(
+                    &mut con,
+                    key.as_deref(),
+                    &new_key,
+                    unlock_entry,
+                );
+                if res.is_ok() {
+                    key = Some(new_key);
+                    locked = false;
+                }
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::ForgetKey { respond, .. } => {
+                locked = key.take().is_some() || locked;
+                respond.r(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            EntryStoreFile::Compact { respond, .. } => {
+                let res = compact(&con);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Close { respond, .. } => {
+                let res = con.close().map_err(|(_, e)| LairError::other(e));
+                respond.r(Ok(async move { res }.boxed().into()));
+                return;
+            }
+        }
+    }
+}
+
+/// Serves the requests reading a sqlite store, failing the rest.
+fn read_only_sqlite_task(
+    con: rusqlite::Connection,
+    mut recv: futures::channel::mpsc::Receiver<EntryStoreFile>,
+) {
+    use futures::{future::FutureExt, stream::StreamExt};
+
+    while let Some(req) = futures::executor::block_on(recv.next()) {
+        match req {
+            EntryStoreFile::InitLoadUnlock { respond, .. } => {
+                let res = load_unlock(&con);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::LoadAllEntries { respond, .. } => {
+                let res = load_all_entries(&con);
+                respond.r(Ok(async move { res }.boxed().into()));
+            }
+            EntryStoreFile::Truncate { respond, .. }
+            | EntryStoreFile::WriteUnlock { respond, .. }
+            | EntryStoreFile::ReplaceEntry { respond, .. }
+            | EntryStoreFile::ReplaceEntries { respond, .. }
+            | EntryStoreFile::ResealAll { respond, .. } => {
+                respond.r(Ok(async move { Err(LairError::ReadOnly) }
+                    .boxed()
+                    .into()));
+            }
+            EntryStoreFile::WriteNextEntry { respond, .. } => {
+                respond.r(Ok(async move { Err(LairError::ReadOnly) }
+                    .boxed()
+                    .into()));
+            }
+            EntryStoreFile::Compact { respond, .. } => {
+                respond.r(Ok(async move { Err(LairError::ReadOnly) }
+                    .boxed()
+                    .into()));
+            }
+            // there is no key, nothing is written
+            EntryStoreFile::ForgetKey { respond, .. } => {
+                respond.r(Ok(async move { Ok(()) }.boxed().into()));
+            }
+            EntryStoreFile::Close { respond, .. } => {
+                let res = con.close().map_err(|(_, e)| LairError::other(e));
+                respond.r(Ok(async move { res }.boxed().into()));
+                return;
+            }
+        }
+    }
+}
+
+/// The indexed columns of an entry row.
+#[derive(Default)]
+struct EntryColumns {
+    entry_type: Option<u32>,
+    pub_id: Option<Vec<u8>>,
+    tag: Option<String>,
+}
+
+impl EntryColumns {
+    fn of_entry(
+        index: KeystoreIndex,
+        entry: &entry::LairEntry,
+        meta: &entry::EntryMeta,
+    ) -> Self {
+        let item = entry.list_item(index, meta);
+        Self {
+            entry_type: Some(item.entry_type as u32),
+            pub_id: Some(item.pub_id.to_vec()),
+            tag: item.tag,
+        }
+    }
+
+    /// Sealed entries only have their type indexed until the key is known,
+    /// entries that do not decode are indexed as invalid.
+    fn of_data(
+        index: KeystoreIndex,
+        data: &[u8],
+        key: Option<&entry::EntryStoreKey>,
+    ) -> Self {
+        if index.0 == 0 {
+            return Self::default();
+        }
+        if entry::EntryDeleted::is_deleted(data) {
+            return Self {
+                entry_type: Some(LairEntryType::Deleted as u32),
+                ..Default::default()
+            };
+        }
+        let decoded = match key {
+            Some(key) => entry::LairEntry::unseal_with_meta(data, key),
+            None => entry::LairEntry::decode_with_meta(data),
+        };
+        match decoded {
+            Ok((entry, meta)) => Self::of_entry(index, &entry, &meta),
+            Err(_) => Self {
+                entry_type: Some(
+                    entry::LairEntry::sealed_entry_type(data)
+                        .unwrap_or(LairEntryType::Invalid)
+                        as u32,
+                ),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+fn load_unlock(con: &rusqlite::Connection) -> LairResult<Option<Vec<u8>>> {
+    con.query_row("SELECT data FROM entries WHERE idx = 0", params![], |r| {
+        r.get(0)
+    })
+    .optional()
+    .map_err(LairError::other)
+}
+
+fn load_all_entries(
+    con: &rusqlite::Connection,
+) -> LairResult<Vec<(KeystoreIndex, Vec<u8>)>> {
+    let mut stmt = con
+        .prepare("SELECT idx, data FROM entries WHERE idx > 0 ORDER BY idx")
+        .map_err(LairError::other)?;
+    let rows = stmt
+        .query_map(params![], |r| Ok((r.get::<_, u32>(0)?, r.get(1)?)))
+        .map_err(LairError::other)?;
+
+    // deleted entries removed by compaction are still deleted entries
+    let mut out = Vec::new();
+    let mut next = 1;
+    for row in rows {
+        let (index, data) = row.map_err(LairError::other)?;
+        while next < index {
+            out.push((next.into(), entry::EntryDeleted.encode()?));
+            next += 1;
+        }
+        out.push((index.into(), data));
+        next = index + 1;
+    }
+    Ok(out)
+}
+
+fn update_entry(
+    tx: &rusqlite::Transaction<'_>,
+    index: KeystoreIndex,
+    columns: EntryColumns,
+    entry_data: &[u8],
+) -> LairResult<()> {
+    let updated = tx
+        .execute(
+            "UPDATE entries
+            SET entry_type = ?2, pub_id = ?3, tag = ?4, data = ?5
+            WHERE idx = ?1",
+            params![
+                index.0,
+                columns.entry_type,
+                columns.pub_id,
+                columns.tag,
+                entry_data,
+            ],
+        )
+        .map_err(LairError::other)?;
+    if updated == 0 {
+        return Err(format!("store has no entry {} to replace", index).into());
+    }
+    Ok(())
+}
+
+fn replace_entries(
+    con: &mut rusqlite::Connection,
+    entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    key: Option<&entry::EntryStoreKey>,
+) -> LairResult<()> {
+    let tx = con.transaction().map_err(LairError::other)?;
+    for (index, entry_data) in entries {
+        // the unlock entry is never sealed
+        let key = if index.0 == 0 { None } else { key };
+        let entry_data = seal_entry(entry_data, key)?;
+        let columns = EntryColumns::of_data(index, &entry_data, key);
+        update_entry(&tx, index, columns, &entry_data)?;
+    }
+    tx.commit().map_err(LairError::other)
+}
+
+fn write_next_entry(
+    con: &mut rusqlite::Connection,
+    entry_data: Vec<u8>,
+    key: Option<&entry::EntryStoreKey>,
+) -> LairResult<KeystoreIndex> {
+    let entry_data = seal_entry(entry_data, key)?;
+
+    let tx = con.transaction().map_err(LairError::other)?;
+    let index: u32 = tx
+        .query_row(
+            "SELECT IFNULL(MAX(idx) + 1, 0) FROM entries",
+            params![],
+            |r| r.get(0),
+        )
+        .map_err(LairError::other)?;
+    let columns = EntryColumns::of_data(index.into(), &entry_data, key);
+    tx.execute(
+        "INSERT INTO entries (idx, entry_type, pub_id, tag, data)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            index,
+            columns.entry_type,
+            columns.pub_id,
+            columns.tag,
+            entry_data,
+        ],
+    )
+    .map_err(LairError::other)?;
+    tx.commit().map_err(LairError::other)?;
+
+    Ok(index.into())
+}
+
+fn reseal_all(
+    con: &mut rusqlite::Connection,
+    key: Option<&entry::EntryStoreKey>,
+    new_key: &entry::EntryStoreKey,
+    unlock_entry: Option<Vec<u8>>,
+) -> LairResult<()> {
+    let tx = con.transaction().map_err(LairError::other)?;
+    let rekey = unlock_entry.is_some();
+
+    match unlock_entry {
+        Some(unlock_entry) => {
+            if unlock_entry.len() != entry::ENTRY_SIZE {
+                return Err("bad unlock entry size".into());
+            }
+            update_entry(&tx, 0.into(), EntryColumns::default(), &unlock_entry)
+                .map_err(|_| "store has no unlock entry")?;
+        }
+        None => {
+            if load_unlock(&tx)?.is_none() {
+                return Err("store has no unlock entry".into());
+            }
+        }
+    }
+
+    let rows = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT idx, data, pub_id IS NULL FROM entries
+                WHERE idx > 0 ORDER BY idx",
+            )
+            .map_err(LairError::other)?;
+        let rows = stmt
+            .query_map(params![], |r| {
+                Ok((
+                    KeystoreIndex::from(r.get::<_, u32>(0)?),
+                    r.get::<_, Vec<u8>>(1)?,
+                    r.get::<_, bool>(2)?,
+                ))
+            })
+            .map_err(LairError::other)?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(LairError::other)?
+    };
+
+    let open_key = key.unwrap_or(new_key);
+    let mut opened = super::SealedOpenCount::default();
+    for (index, data, unindexed) in rows {
+        if entry::EntryDeleted::is_deleted(&data) {
+            continue;
+        }
+        let sealed = entry::LairEntry::is_sealed(&data);
+        let (entry, meta) = match opened
+            .open(sealed, entry::LairEntry::unseal_with_meta(&data, open_key))
+        {
+            Some(r) => r,
+            None => continue,
+        };
+        let columns = EntryColumns::of_entry(index, &entry, &meta);
+        if sealed && !rekey {
+            // entries written sealed before the key was known
+            // (e.g. converted from a store file) are indexed now
+            if unindexed {
+                update_entry(&tx, index, columns, &data)?;
+            }
+            continue;
+        }
+        let data = entry.seal_with_meta(&meta, new_key)?;
+        update_entry(&tx, index, columns, &data)?;
+    }
+    opened.check()?;
+
+    tx.commit().map_err(LairError::other)
+}
+
+/// The size of the database file, in bytes.
+fn database_size(con: &rusqlite::Connection) -> LairResult<u64> {
+    let pages: u64 = con
+        .pragma_query_value(None, "page_count", |r| r.get(0))
+        .map_err(LairError::other)?;
+    let page_size: u64 = con
+        .pragma_query_value(None, "page_size", |r| r.get(0))
+        .map_err(LairError::other)?;
+    Ok(pages * page_size)
+}
+
+/// Delete the rows of deleted entries, and vacuum the database. Indices
+/// without a row read as deleted entries, the row with the highest index
+/// is kept even if it is deleted, so no index is ever used again.
+fn compact(
+    con: &rusqlite::Connection,
+) -> LairResult<lair_keystore_api::actor::LairCompactInfo> {
+    let mut out = lair_keystore_api::actor::LairCompactInfo::default();
+
+    let before = database_size(con)?;
+    out.entries_removed = con
+        .execute(
+            "DELETE FROM entries
+            WHERE idx > 0 AND entry_type = ?1
+            AND idx < (SELECT MAX(idx) FROM entries)",
+            params![LairEntryType::Deleted as u32],
+        )
+        .map_err(LairError::other)? as u64;
+    if out.entries_removed == 0 {
+        return Ok(out);
+    }
+
+    // sqlite swaps in the vacuumed database atomically, by its journal
+    con.execute_batch("VACUUM").map_err(LairError::other)?;
+    out.bytes_reclaimed = before.saturating_sub(database_size(con)?);
+
+    Ok(out)
+}
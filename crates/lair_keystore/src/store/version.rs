@@ -0,0 +1,151 @@
+//! on-disk store format versioning
+
+use crate::*;
+
+/// Magic bytes at the start of a versioned (v2+) store file.
+pub const STORE_MAGIC: &[u8; 8] = b"lairstor";
+
+/// Byte length of the header block preceding the unlock entry.
+/// Kept at the entry size so entries stay block aligned.
+pub const HEADER_SIZE: usize = entry::ENTRY_SIZE;
+
+/// On-disk store format version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StoreVersion(pub u32);
+
+impl std::fmt::Display for StoreVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl StoreVersion {
+    /// Original format: no header, the unlock entry is the first block.
+    pub const V1: Self = Self(1);
+
+    /// Adds a header block with magic bytes and version.
+    pub const V2: Self = Self(2);
+
+    /// The format written by this build.
+    pub const CURRENT: Self = Self::V2;
+
+    /// Detect the format version from the start of a store file.
+    /// Returns `None` for an empty (new) store.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.is_empty() {
+            return None;
+        }
+        if data.len() < 12 || &data[..8] != STORE_MAGIC {
+            return Some(Self::V1);
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&data[8..12]);
+        Some(Self(u32::from_le_bytes(version)))
+    }
+
+    /// Error unless a store of this version can be loaded
+    /// by this build without migrating it first.
+    pub fn check(self) -> LairResult<()> {
+        match self.cmp(&Self::CURRENT) {
+            std::cmp::Ordering::Equal => Ok(()),
+            std::cmp::Ordering::Less => {
+                Err(LairError::StoreMigrationRequired(self.0))
+            }
+            std::cmp::Ordering::Greater => {
+                Err(LairError::StoreVersionUnsupported(self.0))
+            }
+        }
+    }
+
+    /// Encode the header block for this version.
+    pub fn encode_header(self) -> Vec<u8> {
+        let mut out = vec![0; HEADER_SIZE];
+        out[..8].copy_from_slice(STORE_MAGIC);
+        out[8..12].copy_from_slice(&self.0.to_le_bytes());
+        out
+    }
+}
+
+/// The outcome of a `migrate_store_file()` call.
+#[derive(Debug)]
+pub struct MigrateResult {
+    /// The format the store was in.
+    pub from: StoreVersion,
+
+    /// The format the store is in now.
+    pub to: StoreVersion,
+
+    /// Where the original store was copied to,
+    /// if anything needed migrating.
+    pub backup: Option<std::path::PathBuf>,
+}
+
+/// Upgrade raw store file content to the current format.
+pub fn migrate_store_data(
+    from: StoreVersion,
+    data: &[u8],
+) -> LairResult<Vec<u8>> {
+    match from {
+        // v2 is v1 with a header block prepended
+        StoreVersion::V1 => {
+            let mut out = StoreVersion::V2.encode_header();
+            out.extend_from_slice(data);
+            Ok(out)
+        }
+        StoreVersion::CURRENT => Ok(data.to_vec()),
+        oth => Err(LairError::StoreVersionUnsupported(oth.0)),
+    }
+}
+
+/// Upgrade the configured store file to the current format in place,
+/// writing a backup copy of the original first.
+/// Sqlite stores keep a schema version of their own,
+/// and are left as they are.
+/// The caller must ensure no lair process is using the store.
+pub async fn migrate_store_file(config: &Config) -> LairResult<MigrateResult> {
+    if config.get_store_kind() != StoreKind::File {
+        return Ok(MigrateResult {
+            from: StoreVersion::CURRENT,
+            to: StoreVersion::CURRENT,
+            backup: None,
+        });
+    }
+
+    let store_path = config.get_store_path();
+    let data = tokio::fs::read(store_path)
+        .await
+        .map_err(LairError::other)?;
+
+    let from = match StoreVersion::detect(&data) {
+        None => StoreVersion::CURRENT,
+        Some(from) => from,
+    };
+
+    let mut out = MigrateResult {
+        from,
+        to: from,
+        backup: None,
+    };
+
+    if from == StoreVersion::CURRENT {
+        return Ok(out);
+    }
+
+    let migrated = migrate_store_data(from, &data)?;
+
+    // don't clobber the backup from any earlier migration
+    let backup =
+        super::unused_path(store_path.with_extension(format!("{}.bak", from)));
+    super::write_synced(&backup, &data).await?;
+
+    // write the new store beside the old one, then swap it in
+    let tmp = store_path.with_extension("migrating");
+    super::write_synced(&tmp, &migrated).await?;
+    tokio::fs::rename(&tmp, store_path)
+        .await
+        .map_err(LairError::other)?;
+
+    out.to = StoreVersion::CURRENT;
+    out.backup = Some(backup);
+    Ok(out)
+}
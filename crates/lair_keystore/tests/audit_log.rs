@@ -0,0 +1,61 @@
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::internal::audit::{
+    self, AuditEntry, AuditOp, AuditOutcome,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn secret_key_operations_are_audited() -> lair_keystore_api::LairResult<()>
+{
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_audit_log(true)
+        .set_client_name("audited")?
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    let message = bytes::Bytes::from_static(b"top secret message");
+    api.sign_ed25519_sign_by_index(index, message.clone())
+        .await?;
+    api.sign_ed25519_sign_by_pub_key(pub_key.clone(), message.clone())
+        .await?;
+    let (seed_index, _) = api.seed_new_from_entropy(false).await?;
+    assert!(api.seed_export_mnemonic(seed_index).await.is_err());
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // creating entries is not recorded, using them is
+    let path = config.get_audit_log_path();
+    let records = audit::tail(path, 10)?;
+    assert_eq!(3, records.len());
+    assert!(records.iter().all(|r| r.client == "audited"));
+    assert_eq!(AuditOp::Sign, records[0].op);
+    assert_eq!(AuditEntry::Index(index.0), records[0].entry);
+    assert_eq!(AuditOutcome::Ok, records[0].outcome);
+    assert_eq!(
+        AuditEntry::PubKey(base64::encode(&**pub_key)),
+        records[1].entry
+    );
+    assert_eq!(AuditOp::Export, records[2].op);
+    assert_eq!(AuditEntry::Index(seed_index.0), records[2].entry);
+    assert_eq!(AuditOutcome::Error, records[2].outcome);
+    assert_eq!(Some(2), audit::verify(path)?.last_seq);
+
+    // and never what was signed
+    let data = std::fs::read_to_string(path).unwrap();
+    assert!(!data.contains("top secret"));
+    assert!(!data.contains(&base64::encode(&*message)));
+
+    Ok(())
+}
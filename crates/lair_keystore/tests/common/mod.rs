@@ -0,0 +1,191 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use futures::{future::FutureExt, stream::StreamExt};
+use lair_keystore_api::actor::{
+    KeystoreIndex, LairClientEvent, LairClientEventReceiver, LairEntryType,
+};
+use lair_keystore_api::LairResult;
+use std::time::Duration;
+
+/// A lair-keystore server process, killed when dropped.
+pub struct Server(pub std::process::Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Spawn a lair-keystore server process on `lair_dir`.
+pub fn spawn_server(lair_dir: &std::path::Path) -> Server {
+    Server(
+        std::process::Command::new(env!("CARGO_BIN_EXE_lair-keystore"))
+            .arg("--lair-dir")
+            .arg(lair_dir)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap(),
+    )
+}
+
+/// Retry `f` every 100ms until it succeeds, for up to 20s.
+pub async fn retry<F, Fut, T>(mut f: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = LairResult<T>>,
+{
+    for _ in 0..200 {
+        if let Ok(res) = f().await {
+            return res;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("gave up retrying");
+}
+
+/// What a client heard from the server, as passed on by
+/// `respond_to_events`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Heard {
+    PassphraseRequest {
+        last_passphrase_failed: bool,
+        attempt: u32,
+        max_attempts: u32,
+    },
+    SigningRequest {
+        entry_index: KeystoreIndex,
+        client_id: String,
+        message_digest: [u8; 32],
+    },
+    Reconnected,
+    Created(KeystoreIndex, LairEntryType),
+    Deleted(KeystoreIndex),
+    Locked,
+    Unlocked,
+}
+
+/// Answer the events of a client: asked for the unlock passphrase at
+/// `attempt`, with what `passphrase` gives (refusing on `None`), asked
+/// for signing approval, with what `approve` resolves to, and
+/// acknowledge everything else. Every event is passed on to the
+/// receiver returned.
+pub fn respond_to_events<P, A, Fut>(
+    mut evt_recv: LairClientEventReceiver,
+    passphrase: P,
+    approve: A,
+) -> tokio::sync::mpsc::UnboundedReceiver<Heard>
+where
+    P: Fn(u32) -> Option<&'static str> + Send + 'static,
+    A: Fn() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = bool> + Send + 'static,
+{
+    let (heard_send, heard_recv) = tokio::sync::mpsc::unbounded_channel();
+    tokio::task::spawn(async move {
+        while let Some(msg) = evt_recv.next().await {
+            let (respond, heard) = match msg {
+                LairClientEvent::RequestUnlockPassphrase {
+                    respond,
+                    last_passphrase_failed,
+                    attempt,
+                    max_attempts,
+                    ..
+                } => {
+                    let _ = heard_send.send(Heard::PassphraseRequest {
+                        last_passphrase_failed,
+                        attempt,
+                        max_attempts,
+                    });
+                    let passphrase = passphrase(attempt);
+                    respond.respond(Ok(async move {
+                        match passphrase {
+                            Some(passphrase) => {
+                                Ok(passphrase.to_string().into())
+                            }
+                            None => Err("not a passphrase provider".into()),
+                        }
+                    }
+                    .boxed()
+                    .into()));
+                    continue;
+                }
+                LairClientEvent::RequestSigningApproval {
+                    respond,
+                    entry_index,
+                    client_id,
+                    message_digest,
+                    ..
+                } => {
+                    let _ = heard_send.send(Heard::SigningRequest {
+                        entry_index,
+                        client_id,
+                        message_digest,
+                    });
+                    let approved = approve();
+                    respond.respond(Ok(async move { Ok(approved.await) }
+                        .boxed()
+                        .into()));
+                    continue;
+                }
+                LairClientEvent::Reconnected { respond, .. } => {
+                    (respond, Heard::Reconnected)
+                }
+                LairClientEvent::EntryCreated {
+                    respond,
+                    index,
+                    entry_type,
+                    ..
+                } => (respond, Heard::Created(index, entry_type)),
+                LairClientEvent::EntryDeleted { respond, index, .. } => {
+                    (respond, Heard::Deleted(index))
+                }
+                LairClientEvent::Locked { respond, .. } => {
+                    (respond, Heard::Locked)
+                }
+                LairClientEvent::Unlocked { respond, .. } => {
+                    (respond, Heard::Unlocked)
+                }
+            };
+            let _ = heard_send.send(heard);
+            respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+        }
+    });
+    heard_recv
+}
+
+/// The next of the events passed on by `respond_to_events` that
+/// `f` picks, waiting up to 5s for it.
+pub async fn next_heard<F, T>(
+    recv: &mut tokio::sync::mpsc::UnboundedReceiver<Heard>,
+    mut f: F,
+) -> T
+where
+    F: FnMut(Heard) -> Option<T>,
+{
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(res) = f(recv.recv().await.unwrap()) {
+                return res;
+            }
+        }
+    })
+    .await
+    .expect("an event in time")
+}
+
+/// Read one frame of the lair wire protocol off a raw connection.
+#[cfg(unix)]
+pub async fn read_frame(
+    con: &mut tokio::net::UnixStream,
+) -> lair_keystore_api::internal::wire::LairWire {
+    use lair_keystore_api::internal::wire::LairWire;
+    use tokio::io::AsyncReadExt;
+
+    let mut data = vec![0; 4];
+    con.read_exact(&mut data).await.unwrap();
+    data.resize(LairWire::peek_size(&data).unwrap(), 0);
+    con.read_exact(&mut data[4..]).await.unwrap();
+    LairWire::decode(&data).unwrap()
+}
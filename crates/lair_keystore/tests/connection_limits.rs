@@ -0,0 +1,98 @@
+use futures::future::FutureExt;
+use ghost_actor::GhostControlSender;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::{Config, LairError};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn servers_turn_away_connections_past_the_limit(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_max_connections(2)
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (other, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let info = api.lair_get_server_info().await?;
+    assert_eq!(2, info.connection_count);
+    // just this one
+    assert_eq!(1, info.requests_in_flight);
+
+    match lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await {
+        Err(LairError::Busy) => (),
+        Err(err) => panic!("unexpected: {:?}", err),
+        Ok(_) => panic!("connected past the limit"),
+    }
+
+    // a connection closing makes room
+    other.ghost_actor_shutdown().await?;
+    let mut third = None;
+    for _ in 0..50 {
+        match lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await {
+            Ok((third_api, _)) => {
+                third = Some(third_api);
+                break;
+            }
+            Err(LairError::Busy) => {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await
+            }
+            Err(err) => panic!("unexpected: {:?}", err),
+        }
+    }
+    let third = third.expect("room for another connection");
+    assert_eq!(2, third.lair_get_server_info().await?.connection_count);
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_past_the_in_flight_cap_wait_their_turn(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_max_in_flight_requests(2)
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+
+    let message = bytes::Bytes::from_static(b"message");
+    let signatures = futures::future::try_join_all((0..50).map(|_| {
+        api.sign_ed25519_sign_by_index(index, message.clone())
+            .boxed()
+    }))
+    .await?;
+    assert_eq!(50, signatures.len());
+    for signature in signatures {
+        assert!(pub_key.verify(message.clone(), signature).await?);
+    }
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
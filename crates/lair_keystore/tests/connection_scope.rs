@@ -0,0 +1,193 @@
+use lair_keystore_api::actor::{LairClientApiSender, TlsCertOptions};
+use lair_keystore_api::internal::crypto_box::CryptoBoxData;
+use lair_keystore_api::{Config, ConnectionScope};
+use std::sync::Arc;
+
+/// Assert the request is denied for the scope of the connection.
+macro_rules! denied {
+    ($req:expr) => {{
+        let err = $req.await.expect_err(stringify!($req));
+        assert!(
+            err.to_string().starts_with("Permission denied"),
+            "{}: {}",
+            stringify!($req),
+            err
+        );
+    }};
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restricted_connections_are_denied_requests_outside_their_scope(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .add_scoped_token("reader", ConnectionScope::ReadOnly)
+        .add_scoped_token("signer", ConnectionScope::SignOnly)
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    // the token file grants full access
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (ed_index, ed_pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    let (x_index, x_pub_key) = api.x25519_new_from_entropy().await?;
+    let (cert_index, _, _) = api
+        .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+        .await?;
+    let cert = api.tls_cert_get_cert_by_index(cert_index).await?;
+    let (seed_index, _) = api.seed_new_from_entropy(true).await?;
+    let (secret_index, _) = api.secret_new_from_entropy().await?;
+    let message = bytes::Bytes::from_static(b"message");
+    let box_data = Arc::new(CryptoBoxData::from(message.clone()));
+
+    let scoped = |token: &str| {
+        Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_token(token)
+            .build()
+    };
+
+    // read-only connections read entries and public material
+    let (reader, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(scoped("reader")).await?;
+    assert_eq!(secret_index, reader.lair_get_last_entry_index().await?);
+    assert_eq!(5, reader.lair_list_entries().await?.len());
+    assert_eq!(ed_pub_key, reader.sign_ed25519_get(ed_index).await?);
+    assert_eq!(x_pub_key, reader.x25519_get(x_index).await?);
+    assert_eq!(cert, reader.tls_cert_get_cert_by_index(cert_index).await?);
+
+    // but sign, create, export or delete nothing
+    denied!(reader.sign_ed25519_sign_by_index(ed_index, message.clone()));
+    denied!(reader
+        .sign_ed25519_sign_by_pub_key(ed_pub_key.clone(), message.clone()));
+    denied!(reader.crypto_box_by_index(
+        x_index,
+        x_pub_key.clone(),
+        box_data.clone()
+    ));
+    denied!(reader.secret_encrypt_by_index(
+        secret_index,
+        Arc::new(vec![]),
+        Arc::new(message.to_vec())
+    ));
+    denied!(reader.sign_ed25519_new_from_entropy());
+    denied!(reader.x25519_new_from_entropy());
+    denied!(reader.secp256k1_new_from_entropy());
+    denied!(reader.seed_new_from_entropy(true));
+    denied!(reader.secret_new_from_entropy());
+    denied!(
+        reader.tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+    );
+    denied!(reader.tls_cert_get_priv_key_by_index(cert_index));
+    denied!(reader.seed_export_mnemonic(seed_index));
+    denied!(reader.lair_delete_entry(ed_index));
+    denied!(reader.lair_shutdown());
+
+    // sign-only connections also use existing keys
+    let (signer, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(scoped("signer")).await?;
+    let signature = signer
+        .sign_ed25519_sign_by_index(ed_index, message.clone())
+        .await?;
+    assert!(
+        signer
+            .sign_ed25519_verify(ed_pub_key.clone(), message.clone(), signature)
+            .await?
+    );
+    signer
+        .crypto_box_by_index(x_index, x_pub_key.clone(), box_data.clone())
+        .await?;
+    signer
+        .secret_encrypt_by_index(
+            secret_index,
+            Arc::new(vec![]),
+            Arc::new(message.to_vec()),
+        )
+        .await?;
+
+    // but still create, export or delete nothing
+    denied!(signer.sign_ed25519_new_from_entropy());
+    denied!(signer.x25519_new_from_entropy());
+    denied!(signer.secp256k1_new_from_entropy());
+    denied!(signer.seed_new_from_entropy(true));
+    denied!(signer.secret_new_from_entropy());
+    denied!(
+        signer.tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+    );
+    denied!(signer.tls_cert_get_priv_key_by_index(cert_index));
+    denied!(signer.seed_export_mnemonic(seed_index));
+    denied!(signer.lair_delete_entry(ed_index));
+    denied!(signer.lair_shutdown());
+
+    // clients can ask for less than their token allows, never more
+    let (limited, _) = lair_keystore_api::ipc::spawn_client_ipc(
+        Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_connection_scope(ConnectionScope::ReadOnly)
+            .build(),
+    )
+    .await?;
+    assert_eq!(ed_pub_key, limited.sign_ed25519_get(ed_index).await?);
+    denied!(limited.sign_ed25519_sign_by_index(ed_index, Vec::new().into()));
+    let (reader, _) = lair_keystore_api::ipc::spawn_client_ipc(
+        Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_token("reader")
+            .set_connection_scope(ConnectionScope::Full)
+            .build(),
+    )
+    .await?;
+    denied!(reader.sign_ed25519_new_from_entropy());
+
+    // unknown tokens are refused outright
+    assert!(lair_keystore_api::ipc::spawn_client_ipc(scoped("writer"))
+        .await
+        .is_err());
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn socket_connections_are_scoped_by_config(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_connection_scope(ConnectionScope::ReadOnly)
+        .build();
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    // no token needed, but asking for full access grants read-only
+    let (api, _) = lair_keystore_api::ipc::spawn_client_ipc(
+        Config::builder().set_root_path(tmpdir.path()).build(),
+    )
+    .await?;
+    assert_eq!(0, api.lair_list_entries().await?.len());
+    denied!(api.sign_ed25519_new_from_entropy());
+    denied!(api.lair_shutdown());
+
+    // no client may shut it down, it goes with the test runtime
+    drop(api);
+    drop(shutdown);
+
+    Ok(())
+}
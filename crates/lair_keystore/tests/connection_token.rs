@@ -0,0 +1,67 @@
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::internal::auth_token::AUTH_TIMEOUT;
+use lair_keystore_api::internal::ipc::{
+    spawn_ipc_connection, IpcWireApiSender,
+};
+use lair_keystore_api::internal::util::next_msg_id;
+use lair_keystore_api::internal::wire::LairWire;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn connections_must_present_the_token(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_require_token(true)
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let token = std::fs::read_to_string(config.get_token_path()).unwrap();
+
+    // clients pick the token up from the lair dir
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+
+    // a wrong token is refused
+    let wrong = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_token(format!("{}x", token))
+        .build();
+    let err = match lair_keystore_api::ipc::spawn_client_ipc(wrong).await {
+        Ok(_) => panic!("connected with a wrong token"),
+        Err(err) => err,
+    };
+    assert!(
+        err.to_string().contains("invalid connection token"),
+        "{}",
+        err
+    );
+
+    // as are requests of a connection that presents none,
+    // which is dropped after a while
+    let (con_kill, con_send, _con_recv, _) =
+        spawn_ipc_connection(config.clone()).await?;
+    let err = con_send
+        .request(LairWire::ToLairLairGetLastEntryIndex {
+            msg_id: next_msg_id(),
+        })
+        .await
+        .expect_err("request without a token");
+    assert!(err.to_string().contains("requires a connection token"));
+    tokio::time::sleep(AUTH_TIMEOUT + std::time::Duration::from_secs(1)).await;
+    assert!(!con_kill.cont());
+
+    // while the authenticated connection carries on
+    assert_eq!(pub_key, api.sign_ed25519_get(index).await?);
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
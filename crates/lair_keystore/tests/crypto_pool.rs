@@ -0,0 +1,47 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::{Config, LairResult, CONFIG_FILE_NAME};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_server_works_on_its_own_crypto_pool() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        tmpdir.path().join(CONFIG_FILE_NAME),
+        "crypto_threads = 2\ninline_crypto_threshold = 0\n",
+    )
+    .unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+    let server = spawn_server(tmpdir.path());
+    let (api, _evt_recv) =
+        retry(|| lair_keystore_api::ipc::spawn_client_ipc(config.clone()))
+            .await;
+
+    let (sign_idx, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    let sig = api
+        .sign_ed25519_sign_by_index(sign_idx, b"message".to_vec().into())
+        .await?;
+    assert!(pub_key.verify(b"message".to_vec().into(), sig).await?);
+
+    let info = api.lair_get_server_info().await?;
+    assert_eq!(2, info.crypto_threads);
+    assert_eq!(0, info.crypto_queue_depth);
+    assert_eq!(0, info.crypto_busy_threads);
+
+    let mut names = std::fs::read_dir(format!("/proc/{}/task", server.0.id()))
+        .unwrap()
+        .map(|task| {
+            let comm = task.unwrap().path().join("comm");
+            std::fs::read_to_string(comm).unwrap().trim().to_string()
+        })
+        .filter(|name| name.starts_with("lair-crypto-"))
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(vec!["lair-crypto-0", "lair-crypto-1"], names);
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use futures::future::{BoxFuture, FutureExt};
+use lair_keystore::store::{
+    EntryStoreBackend, IndexedEntries, MemoryEntryStore,
+};
+use lair_keystore_api::actor::{KeystoreIndex, LairClientApiSender};
+use lair_keystore_api::LairResult;
+use std::sync::{Arc, Mutex};
+
+/// An embedder's store, keeping its entries where the test can see them.
+#[derive(Clone, Default)]
+struct SharedStore(Arc<Mutex<MemoryEntryStore>>);
+
+impl SharedStore {
+    fn with<R: Send + 'static>(
+        &mut self,
+        f: impl FnOnce(&mut MemoryEntryStore) -> BoxFuture<'_, LairResult<R>>,
+    ) -> BoxFuture<'static, LairResult<R>> {
+        // the memory store resolves its futures at once
+        let res = f(&mut self.0.lock().unwrap()).now_or_never().unwrap();
+        async move { res }.boxed()
+    }
+
+    fn entries(&mut self) -> IndexedEntries {
+        self.load_all_entries().now_or_never().unwrap().unwrap()
+    }
+}
+
+impl EntryStoreBackend for SharedStore {
+    fn load_unlock(&mut self) -> BoxFuture<'_, LairResult<Option<Vec<u8>>>> {
+        self.with(|s| s.load_unlock())
+    }
+
+    fn write_unlock(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        self.with(|s| s.write_unlock(entry_data))
+    }
+
+    fn load_all_entries(
+        &mut self,
+    ) -> BoxFuture<'_, LairResult<IndexedEntries>> {
+        self.with(|s| s.load_all_entries())
+    }
+
+    fn append_entry(
+        &mut self,
+        entry_data: Vec<u8>,
+    ) -> BoxFuture<'_, LairResult<KeystoreIndex>> {
+        self.with(|s| s.append_entry(entry_data))
+    }
+
+    fn replace_entries(
+        &mut self,
+        entries: Vec<(KeystoreIndex, Vec<u8>)>,
+    ) -> BoxFuture<'_, LairResult<()>> {
+        self.with(|s| s.replace_entries(entries))
+    }
+
+    fn flush(&mut self) -> BoxFuture<'_, LairResult<()>> {
+        async move { Ok(()) }.boxed()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn embedded_store_conforms() {
+    lair_keystore::store::conformance::check_entry_store_backend(Box::new(
+        SharedStore::default(),
+    ))
+    .await;
+    let store = SharedStore::default();
+    lair_keystore::store::conformance::check_entry_store_backend_durability(
+        || {
+            let store = store.clone();
+            async move { Ok(Box::new(store) as Box<dyn EntryStoreBackend>) }
+        },
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lair_serves_an_embedded_store() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    let mut store = SharedStore::default();
+
+    let shutdown = lair_keystore::execute_lair_with_store(
+        config.clone(),
+        Box::new(store.clone()),
+        Some(b"passphrase".to_vec()),
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    let (deleted_index, _) = api.x25519_new_from_entropy().await?;
+    api.lair_delete_entry(deleted_index).await?;
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // the entries went to our store, sealed, not to a store file
+    assert!(!config.get_store_path().exists());
+    let entries = store.entries();
+    assert_eq!(
+        vec![index, deleted_index],
+        entries.iter().map(|e| e.0).collect::<Vec<_>>()
+    );
+    assert!(lair_keystore_api::entry::LairEntry::is_sealed(
+        &entries[0].1
+    ));
+    assert!(lair_keystore_api::entry::EntryDeleted::is_deleted(
+        &entries[1].1
+    ));
+
+    // and are served from it again, with the passphrase that created it
+    assert!(lair_keystore::execute_lair_with_store(
+        config.clone(),
+        Box::new(store.clone()),
+        Some(b"wrong".to_vec()),
+    )
+    .await
+    .is_err());
+    let shutdown = lair_keystore::execute_lair_with_store(
+        config.clone(),
+        Box::new(store.clone()),
+        Some(b"passphrase".to_vec()),
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    assert_eq!(pub_key, api.sign_ed25519_get(index).await?);
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
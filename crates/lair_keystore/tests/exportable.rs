@@ -0,0 +1,68 @@
+use lair_keystore_api::actor::{LairClientApiSender, TlsCertOptions};
+use lair_keystore_api::LairError;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_server_decides_what_is_exportable_by_default(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let server_config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_exportable_by_default(false)
+        .build();
+    // the client's own config has keys exportable by default
+    let client_config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    assert!(client_config.get_exportable_by_default());
+
+    lair_keystore::init_lair(
+        server_config.clone(),
+        b"passphrase".to_vec(),
+        false,
+    )
+    .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        server_config,
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(client_config).await?;
+
+    // left unset, the server keeps the key
+    let (kept, _, _) = api
+        .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+        .await?;
+    assert!(matches!(
+        api.tls_cert_get_priv_key_by_index(kept).await,
+        Err(LairError::NotExportable),
+    ));
+    let (ca, _, _) = api
+        .tls_ca_new_self_signed(TlsCertOptions::default())
+        .await?;
+    let (signed, _, _, _) = api
+        .tls_cert_new_signed_by_ca(ca, TlsCertOptions::default())
+        .await?;
+    assert!(matches!(
+        api.tls_cert_get_priv_key_by_index(signed).await,
+        Err(LairError::NotExportable),
+    ));
+
+    // clients still choose for themselves
+    let mut options = TlsCertOptions::default();
+    options.exportable = Some(true);
+    let (exported, _, _) =
+        api.tls_cert_new_self_signed_from_entropy(options).await?;
+    api.tls_cert_get_priv_key_by_index(exported).await?;
+
+    // renewed certs keep their flag
+    let (renewed, _, _) = api
+        .tls_cert_renew(exported, TlsCertOptions::default())
+        .await?;
+    api.tls_cert_get_priv_key_by_index(renewed).await?;
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+    Ok(())
+}
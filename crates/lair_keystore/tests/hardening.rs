@@ -0,0 +1,55 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::{Config, LairResult};
+use std::os::unix::fs::PermissionsExt;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_server_hardens_its_process() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+    // the server inherits a permissive umask
+    unsafe { libc::umask(0o002) };
+    let server = spawn_server(tmpdir.path());
+    let (api, _evt_recv) =
+        retry(|| lair_keystore_api::ipc::spawn_client_ipc(config.clone()))
+            .await;
+
+    let info = api.lair_get_server_info().await?;
+    assert!(info.core_dumps_disabled);
+    assert!(info.umask_restricted);
+    assert_eq!(cfg!(target_os = "linux"), info.ptrace_disabled);
+
+    for path in &[
+        config.get_store_path(),
+        config.get_pid_path(),
+        config.get_socket_path(),
+    ] {
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(0, mode & 0o077, "{:?} has mode {:o}", path, mode);
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let limits =
+            std::fs::read_to_string(format!("/proc/{}/limits", server.0.id()))
+                .unwrap();
+        let core = limits
+            .lines()
+            .find(|l| l.starts_with("Max core file size"))
+            .unwrap()
+            .split_whitespace()
+            .skip(4)
+            .take(2)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["0", "0"], core, "{}", limits);
+    }
+
+    drop(server);
+
+    Ok(())
+}
@@ -1,7 +1,15 @@
-use futures::{future::FutureExt, stream::StreamExt};
+mod common;
+
+use common::*;
 use ghost_actor::dependencies::tracing;
-use lair_keystore_api::actor::LairClientApiSender;
-use lair_keystore_api::internal::crypto_box;
+use lair_keystore_api::actor::{
+    EntryPublic, LairClientApiSender, LairEntryType, TlsCertAlg,
+    TlsCertDigestAlg, TlsCertSignatureScheme,
+};
+use lair_keystore_api::internal::{
+    crypto_box, kx, secret_key, secretstream, seed, sign_bls12381,
+    sign_ed25519, sign_secp256k1, tls, x25519,
+};
 
 fn init_tracing() {
     let _ = tracing::subscriber::set_global_default(
@@ -12,6 +20,72 @@ fn init_tracing() {
     );
 }
 
+/// Complete an in-memory rustls handshake, with a client trusting only
+/// `root_der`, against a server presenting `cert_chain`.
+fn tls_handshake(
+    sni: &str,
+    root_der: &[u8],
+    cert_chain: Vec<Vec<u8>>,
+    priv_key_der: Vec<u8>,
+) {
+    let server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            cert_chain.into_iter().map(rustls::Certificate).collect(),
+            rustls::PrivateKey(priv_key_der),
+        )
+        .unwrap();
+    tls_handshake_with_config(sni, root_der, server_config);
+}
+
+/// Complete an in-memory rustls handshake, with a client trusting only
+/// `root_der`, against a server using `server_config`.
+fn tls_handshake_with_config(
+    sni: &str,
+    root_der: &[u8],
+    server_config: rustls::ServerConfig,
+) {
+    use std::convert::TryFrom;
+    use std::io::{Read, Write};
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(&rustls::Certificate(root_der.to_vec())).unwrap();
+    let client_config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let mut server = rustls::Connection::from(
+        rustls::ServerConnection::new(std::sync::Arc::new(server_config))
+            .unwrap(),
+    );
+    let mut client = rustls::Connection::from(
+        rustls::ClientConnection::new(
+            std::sync::Arc::new(client_config),
+            rustls::ServerName::try_from(sni).unwrap(),
+        )
+        .unwrap(),
+    );
+    let transfer = |from: &mut rustls::Connection,
+                    to: &mut rustls::Connection| {
+        let mut buf = Vec::new();
+        from.write_tls(&mut buf).unwrap();
+        to.read_tls(&mut &buf[..]).unwrap();
+        to.process_new_packets().unwrap();
+    };
+
+    client.writer().write_all(b"hello lair").unwrap();
+    while client.is_handshaking() || server.is_handshaking() {
+        transfer(&mut client, &mut server);
+        transfer(&mut server, &mut client);
+    }
+    transfer(&mut client, &mut server);
+    let mut data = [0; 10];
+    server.reader().read_exact(&mut data).unwrap();
+    assert_eq!(b"hello lair", &data);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
     init_tracing();
@@ -19,12 +93,16 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
     let tmpdir = tempfile::tempdir().unwrap();
     std::env::set_var("LAIR_DIR", tmpdir.path());
 
-    lair_keystore::execute_lair().await?;
+    let (ready_send, ready_recv) = futures::channel::oneshot::channel();
+    let shutdown = lair_keystore::execute_lair_with_ready(ready_send).await?;
+    ready_recv.await.unwrap();
 
     let config = lair_keystore_api::Config::builder()
         .set_root_path(tmpdir.path())
         .build();
 
+    // (named pipes are not files, opening one would connect to it)
+    #[cfg(not(windows))]
     if let Err(e) = std::fs::metadata(config.get_socket_path()) {
         panic!(
             "could not read socket file!!: {:?} {:?}",
@@ -33,26 +111,68 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
         );
     }
 
+    check_lair_api(config.clone(), shutdown).await?;
+
+    #[cfg(not(windows))]
+    assert!(std::fs::metadata(config.get_socket_path()).is_err());
+    assert!(std::fs::metadata(config.get_pid_path()).is_err());
+
+    drop(tmpdir);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lair_ephemeral_integration_test() -> lair_keystore_api::LairResult<()>
+{
+    init_tracing();
+
+    let config = lair_keystore::load_config(
+        None,
+        Some(std::path::Path::new(lair_keystore::EPHEMERAL_LAIR_DIR)),
+    )?;
+    assert!(config.get_ephemeral());
+    assert!(config.get_in_process_ipc());
+
+    let (ready_send, ready_recv) = futures::channel::oneshot::channel();
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        None,
+        Some(ready_send),
+    )
+    .await?;
+    ready_recv.await.unwrap();
+
+    // a second server cannot take over the channel
+    assert!(lair_keystore::execute_lair_with_config(
+        config.clone(),
+        None,
+        None
+    )
+    .await
+    .is_err());
+
+    check_lair_api(config.clone(), shutdown).await?;
+
+    // nothing was written, and nothing is left to connect to
+    assert!(!config.get_root_path().exists());
+    assert!(lair_keystore_api::ipc::spawn_client_ipc(config)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+/// Exercise the client api of a lair server with a new (uninitialized)
+/// store, listening on the socket of `config`, then shut it down.
+async fn check_lair_api(
+    config: std::sync::Arc<lair_keystore_api::Config>,
+    shutdown: lair_keystore::ipc::LairShutdownReceiver,
+) -> lair_keystore_api::LairResult<()> {
     let spawn = || async {
-        let (api_send, mut evt_recv) =
+        let (api_send, evt_recv) =
             lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
-
-        tokio::task::spawn(async move {
-            while let Some(msg) = evt_recv.next().await {
-                match msg {
-                    lair_keystore_api::actor::LairClientEvent::RequestUnlockPassphrase {
-                        respond,
-                        ..
-                    } => {
-                        respond.respond(Ok(
-                            async move { Ok("passphrase".to_string()) }
-                                .boxed()
-                                .into(),
-                        ));
-                    }
-                }
-            }
-        });
+        respond_to_events(evt_recv, |_| Some("passphrase"), || async { false });
 
         lair_keystore_api::LairResult::<_>::Ok(api_send)
     };
@@ -74,6 +194,7 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
         lair_keystore_api::actor::LairEntryType::Invalid,
         api_send.lair_get_entry_type(0.into()).await?,
     );
+    assert!(api_send.lair_list_entries().await?.is_empty());
 
     let (cert_index, cert_sni, cert_digest) = api_send
         .tls_cert_new_self_signed_from_entropy(
@@ -122,7 +243,7 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
 
     assert_eq!(sign_pub_key, sign_pub_key2);
 
-    let data = std::sync::Arc::new(b"test-data".to_vec());
+    let data = bytes::Bytes::from_static(b"test-data");
 
     let sign1 = api_send
         .sign_ed25519_sign_by_index(sign_index, data.clone())
@@ -143,6 +264,165 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
     assert_eq!(sign2, sign3);
     assert_eq!(sign3, sign4);
 
+    // batches come back in message order, each batch is a single use
+    let other_data = bytes::Bytes::from_static(b"other-test-data");
+    let batch = vec![data.clone(), other_data.clone(), data.clone()];
+    let sigs = api_send
+        .sign_ed25519_sign_batch_by_index(sign_index, batch.clone())
+        .await?;
+    assert_eq!(3, sigs.len());
+    assert_eq!(sign1, sigs[0]);
+    assert_ne!(sign1, sigs[1]);
+    assert_eq!(sign1, sigs[2]);
+    assert_eq!(
+        sigs,
+        api_send2
+            .sign_ed25519_sign_batch_by_pub_key(sign_pub_key2.clone(), batch)
+            .await?,
+    );
+    assert!(sign_pub_key2.verify(other_data, sigs[1].clone()).await?);
+
+    // prehashed signing of a payload that never leaves the client
+    let payload = vec![0xdb; 1024 * 1024];
+    let mut prehasher = sign_ed25519::SignEd25519Prehasher::new();
+    for chunk in payload.chunks(64 * 1024) {
+        prehasher.update(chunk);
+    }
+    let digest = prehasher.finalize();
+    let prehashed_sig = api_send
+        .sign_ed25519_sign_prehashed(sign_index, digest)
+        .await?;
+    assert!(
+        sign_pub_key2
+            .verify_prehashed(digest, prehashed_sig.clone())
+            .await?
+    );
+    assert!(!sign_pub_key2.verify(payload.into(), prehashed_sig).await?);
+    let mut forged = sign_ed25519::SIGN_ED25519_PREHASH_DOMAIN.to_vec();
+    forged.extend_from_slice(&digest);
+    assert!(api_send
+        .sign_ed25519_sign_by_index(sign_index, forged.into())
+        .await
+        .is_err());
+
+    // domain separated signing
+    let context = std::sync::Arc::new(b"test-context".to_vec());
+    let ctx_sig = api_send
+        .sign_ed25519_sign_by_index_with_context(
+            sign_index,
+            context.clone(),
+            data.clone(),
+        )
+        .await?;
+    assert_eq!(
+        ctx_sig,
+        api_send2
+            .sign_ed25519_sign_by_pub_key_with_context(
+                sign_pub_key2.clone(),
+                context.clone(),
+                data.clone(),
+            )
+            .await?
+    );
+    assert_ne!(sign1, ctx_sig);
+    assert!(
+        sign_pub_key2
+            .verify_with_context(context.clone(), data.clone(), ctx_sig.clone())
+            .await?
+    );
+    assert!(!sign_pub_key2.verify(data.clone(), ctx_sig.clone()).await?);
+    assert!(
+        !sign_pub_key2
+            .verify_with_context(
+                std::sync::Arc::new(b"other-context".to_vec()),
+                data.clone(),
+                ctx_sig,
+            )
+            .await?
+    );
+    assert!(
+        !sign_pub_key2
+            .verify_with_context(context.clone(), data.clone(), sign1.clone())
+            .await?
+    );
+    let mut forged = sign_ed25519::SIGN_ED25519_CONTEXT_DOMAIN.to_vec();
+    forged.push(context.len() as u8);
+    forged.extend_from_slice(&context);
+    forged.extend_from_slice(&data);
+    assert!(api_send
+        .sign_ed25519_sign_by_index(sign_index, forged.into())
+        .await
+        .is_err());
+    assert!(api_send
+        .sign_ed25519_sign_by_index_with_context(
+            sign_index,
+            std::sync::Arc::new(vec![0; 256]),
+            data.clone(),
+        )
+        .await
+        .is_err());
+
+    // server-side verification
+    assert!(
+        api_send2
+            .sign_ed25519_verify(
+                sign_pub_key2.clone(),
+                data.clone(),
+                sign1.clone()
+            )
+            .await?
+    );
+    let mut bad_sig = sign1.to_vec();
+    bad_sig[0] ^= 1;
+    assert!(
+        !api_send2
+            .sign_ed25519_verify(
+                sign_pub_key2.clone(),
+                data.clone(),
+                bad_sig.into()
+            )
+            .await?
+    );
+    // keys don't need to be in the store
+    let foreign =
+        sign_ed25519::sign_ed25519_keypair_from_seed(vec![0xf0; 32]).await?;
+    let foreign_sig =
+        sign_ed25519::sign_ed25519(foreign.priv_key, data.clone()).await?;
+    assert!(
+        api_send2
+            .sign_ed25519_verify(foreign.pub_key, data.clone(), foreign_sig)
+            .await?
+    );
+    // malformed keys / signatures are just invalid
+    assert!(
+        !api_send2
+            .sign_ed25519_verify(
+                vec![0xff; 32].into(),
+                data.clone(),
+                sign1.clone()
+            )
+            .await?
+    );
+    assert!(
+        !api_send2
+            .sign_ed25519_verify(
+                vec![0; 12].into(),
+                data.clone(),
+                sign1.clone()
+            )
+            .await?
+    );
+    assert!(
+        !api_send2
+            .sign_ed25519_verify(
+                sign_pub_key2.clone(),
+                data.clone(),
+                vec![0; 3].into()
+            )
+            .await?
+    );
+    assert_eq!(sign_pub_key2, api_send2.sign_ed25519_get(sign_index).await?);
+
     let (x25519_alice_index, x25519_alice_pub_key) =
         api_send.x25519_new_from_entropy().await?;
 
@@ -161,43 +441,35 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
         api_send.x25519_new_from_entropy().await?;
     assert_eq!(4, x25519_bob_index.0);
 
-    let data = std::sync::Arc::new(b"test-data".to_vec().into());
+    let data = bytes::Bytes::from_static(b"test-data");
 
     // Encrypt a few times in a few ways.
     let crypto_box1 = api_send
         .crypto_box_by_index(
             x25519_alice_index,
             x25519_bob_pub_key.clone(),
-            std::sync::Arc::new(crypto_box::CryptoBoxData {
-                data: std::sync::Arc::clone(&data),
-            }),
+            std::sync::Arc::new(data.clone().into()),
         )
         .await?;
     let crypto_box2 = api_send
         .crypto_box_by_pub_key(
             x25519_alice_pub_key.clone(),
             x25519_bob_pub_key.clone(),
-            std::sync::Arc::new(crypto_box::CryptoBoxData {
-                data: std::sync::Arc::clone(&data),
-            }),
+            std::sync::Arc::new(data.clone().into()),
         )
         .await?;
     let crypto_box3 = api_send2
         .crypto_box_by_index(
             x25519_alice_index,
             x25519_bob_pub_key.clone(),
-            std::sync::Arc::new(crypto_box::CryptoBoxData {
-                data: std::sync::Arc::clone(&data),
-            }),
+            std::sync::Arc::new(data.clone().into()),
         )
         .await?;
     let crypto_box4 = api_send2
         .crypto_box_by_pub_key(
             x25519_alice_pub_key.clone(),
             x25519_bob_pub_key.clone(),
-            std::sync::Arc::new(crypto_box::CryptoBoxData {
-                data: std::sync::Arc::clone(&data),
-            }),
+            std::sync::Arc::new(data.clone().into()),
         )
         .await?;
 
@@ -249,13 +521,140 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
     // Show that decryption can fail.
     let crypto_box_open_carol = api_send2
         .crypto_box_open_by_pub_key(
-            x25519_carol_pub_key,
+            x25519_carol_pub_key.clone(),
             x25519_alice_pub_key.clone(),
             std::sync::Arc::new(crypto_box4.clone()),
         )
         .await?;
     assert!(crypto_box_open_carol.is_none());
 
+    // Sealed boxes, the sender stays anonymous.
+    let sealed = crypto_box::crypto_box_seal(
+        x25519_carol_pub_key.clone(),
+        std::sync::Arc::new(data.clone().into()),
+    )
+    .await?;
+    assert_eq!(
+        crypto_box::SEALED_BOX_OVERHEAD_BYTES + data.len(),
+        sealed.len()
+    );
+    let sealed = std::sync::Arc::new(sealed);
+    let sealed_open = api_send
+        .crypto_box_seal_open_by_index(x25519_carol_index, sealed.clone())
+        .await?;
+    assert_eq!(&data, &sealed_open.unwrap().data);
+    let sealed_open = api_send2
+        .crypto_box_seal_open_by_pub_key(
+            x25519_carol_pub_key.clone(),
+            sealed.clone(),
+        )
+        .await?;
+    assert_eq!(&data, &sealed_open.unwrap().data);
+    // only the recipient can open it
+    assert!(api_send
+        .crypto_box_seal_open_by_pub_key(
+            x25519_alice_pub_key.clone(),
+            sealed.clone(),
+        )
+        .await?
+        .is_none());
+    assert!(api_send
+        .crypto_box_seal_open_by_index(sign_index, sealed)
+        .await
+        .is_err());
+
+    // List all entries in one go.
+    let entries = api_send2.lair_list_entries().await?;
+    assert_eq!(
+        vec![1, 2, 3, 4, 5],
+        entries
+            .iter()
+            .map(|e| e.keystore_index.0)
+            .collect::<Vec<_>>(),
+    );
+    {
+        use lair_keystore_api::actor::LairEntryType::*;
+        assert_eq!(
+            vec![TlsCert, SignEd25519, X25519, X25519, X25519],
+            entries.iter().map(|e| e.entry_type).collect::<Vec<_>>(),
+        );
+    }
+    let (cert_sni, cert_digest) = api_send.tls_cert_get(cert_index).await?;
+    assert_eq!(Some(cert_sni), entries[0].cert_sni);
+    assert_eq!(cert_digest.0, entries[0].pub_id);
+    let sign_pub_key = api_send.sign_ed25519_get(sign_index).await?;
+    assert_eq!(sign_pub_key.0, entries[1].pub_id);
+    assert_eq!(None, entries[1].cert_sni);
+    assert_eq!(&x25519_alice_pub_key.to_bytes()[..], &entries[2].pub_id[..]);
+    assert_eq!(&x25519_bob_pub_key.to_bytes()[..], &entries[3].pub_id[..]);
+
+    // Resolve pub keys, digests and snis to their entries.
+    {
+        use lair_keystore_api::actor::LairEntryType::*;
+        let find = |id: &[u8]| {
+            api_send2.lair_find_entry(std::sync::Arc::new(id.to_vec()))
+        };
+        assert_eq!(Some((cert_index, TlsCert)), find(&cert_digest).await?);
+        assert_eq!(
+            Some((cert_index, TlsCert)),
+            find(entries[0].cert_sni.as_ref().unwrap().as_bytes()).await?
+        );
+        assert_eq!(Some((sign_index, SignEd25519)), find(&sign_pub_key).await?);
+        assert_eq!(
+            Some((x25519_bob_index, X25519)),
+            find(&x25519_bob_pub_key.to_bytes()).await?
+        );
+        assert_eq!(None, find(&[0xdb; 32]).await?);
+    }
+
+    // Delete an entry, its index is not reused.
+    api_send.lair_delete_entry(x25519_carol_index).await?;
+    assert_eq!(
+        lair_keystore_api::actor::LairEntryType::Deleted,
+        api_send2.lair_get_entry_type(x25519_carol_index).await?,
+    );
+    assert_eq!(4, api_send2.lair_list_entries().await?.len());
+    let err = api_send2.x25519_get(x25519_carol_index).await.unwrap_err();
+    assert!(err.to_string().contains("has been deleted"), "{}", err);
+
+    // Errors arrive as the variant the server produced.
+    assert!(matches!(
+        api_send2.x25519_get(x25519_carol_index).await,
+        Err(lair_keystore_api::LairError::EntryDeleted(index))
+            if index == x25519_carol_index.0
+    ));
+    assert!(matches!(
+        api_send2.x25519_get(u32::MAX.into()).await,
+        Err(lair_keystore_api::LairError::EntryNotFound(u32::MAX))
+    ));
+    assert!(matches!(
+        api_send2.x25519_get(sign_index).await,
+        Err(lair_keystore_api::LairError::WrongEntryType)
+    ));
+    assert!(api_send2
+        .crypto_box_by_pub_key(
+            x25519_carol_pub_key,
+            x25519_alice_pub_key.clone(),
+            std::sync::Arc::new(data.clone().into()),
+        )
+        .await
+        .is_err());
+    let (x25519_dave_index, _) = api_send.x25519_new_from_entropy().await?;
+    assert_eq!(6, x25519_dave_index.0);
+
+    // Compacting the store drops the deleted entry, keeping indices.
+    let compact = api_send.lair_compact_store().await?;
+    assert_eq!(1, compact.entries_removed);
+    assert_eq!(
+        lair_keystore_api::actor::LairEntryType::Deleted,
+        api_send2.lair_get_entry_type(x25519_carol_index).await?,
+    );
+    assert_eq!(
+        lair_keystore_api::actor::LairEntryType::X25519,
+        api_send2.lair_get_entry_type(x25519_dave_index).await?,
+    );
+    api_send2.x25519_get(x25519_dave_index).await?;
+
     // Ensure we didn't accidentally hang the ipc with an invalid decryption.
     let crypto_box_open5 = api_send2
         .crypto_box_open_by_pub_key(
@@ -266,7 +665,1190 @@ async fn lair_integration_test() -> lair_keystore_api::LairResult<()> {
         .await?;
     assert_eq!(&data, &crypto_box_open5.unwrap().data);
 
-    drop(tmpdir);
+    // Tagged entries can be looked up and used by tag.
+    let (agent_index, agent_pub_key) = api_send
+        .sign_ed25519_new_from_entropy_tagged("my-agent".to_string())
+        .await?;
+    assert_eq!(
+        (
+            agent_index,
+            lair_keystore_api::actor::LairEntryType::SignEd25519
+        ),
+        api_send2
+            .lair_get_entry_by_tag("my-agent".to_string())
+            .await?,
+    );
+    let sig = api_send2
+        .sign_ed25519_sign_by_tag("my-agent".to_string(), data.clone())
+        .await?;
+    assert!(agent_pub_key.verify(data.clone(), sig).await?);
+    let (tagged_cert_index, _, _) = api_send
+        .tls_cert_new_self_signed_tagged(
+            lair_keystore_api::actor::TlsCertOptions::default(),
+            "my-cert".to_string(),
+        )
+        .await?;
+    let err = api_send
+        .sign_ed25519_new_from_entropy_tagged("my-cert".to_string())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already in use"), "{}", err);
+    // Two clients racing to rename entries to the same tag, one wins.
+    let (r1, r2) = futures::future::join(
+        api_send.lair_update_entry_tag(agent_index, "renamed".to_string()),
+        api_send2
+            .lair_update_entry_tag(tagged_cert_index, "renamed".to_string()),
+    )
+    .await;
+    let winner = match (r1, r2) {
+        (Ok(()), Err(_)) => agent_index,
+        (Err(_), Ok(())) => tagged_cert_index,
+        oth => panic!("expected exactly one rename to win: {:?}", oth),
+    };
+    assert_eq!(
+        winner,
+        api_send2
+            .lair_get_entry_by_tag("renamed".to_string())
+            .await?
+            .0,
+    );
+    let loser_tag = if winner == agent_index {
+        "my-cert"
+    } else {
+        "my-agent"
+    };
+    assert!(api_send2
+        .lair_get_entry_by_tag(loser_tag.to_string())
+        .await
+        .is_ok());
+    // put things back how the rest of this test expects them
+    api_send
+        .lair_update_entry_tag(
+            winner,
+            if winner == agent_index {
+                "my-agent"
+            } else {
+                "my-cert"
+            }
+            .to_string(),
+        )
+        .await?;
+
+    // Creation times come from the server clock.
+    let agent_meta = api_send2.lair_get_entry_meta(agent_index).await?;
+    let cert_meta = api_send2.lair_get_entry_meta(tagged_cert_index).await?;
+    assert_eq!(Some("my-agent".to_string()), agent_meta.tag);
+    assert!(agent_meta.created_at.unwrap() > 0);
+    assert!(cert_meta.created_at.unwrap() >= agent_meta.created_at.unwrap());
+    let entries = api_send2.lair_list_entries().await?;
+    assert!(entries.iter().all(|e| e.created_at.is_some()));
+    let tagged = entries
+        .iter()
+        .filter_map(|e| Some((e.keystore_index, e.tag.clone()?)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (agent_index, "my-agent".to_string()),
+            (tagged_cert_index, "my-cert".to_string()),
+        ],
+        tagged,
+    );
+
+    // Exporting public material, as serializable records.
+    let (cert_sni, cert_digest) = api_send.tls_cert_get(cert_index).await?;
+    let cert_public = api_send2.lair_export_entry_public(cert_index).await?;
+    match &cert_public {
+        EntryPublic::TlsCert {
+            sni,
+            cert_digest: digest,
+            digest_alg,
+            cert_der,
+            not_after,
+            alg,
+        } => {
+            assert_eq!(cert_sni.as_str(), sni);
+            assert_eq!(cert_digest.as_slice(), digest.as_slice());
+            assert_eq!(&cert1[..], cert_der.as_slice());
+            assert!(*not_after > cert_meta.created_at.unwrap());
+            assert_eq!(TlsCertAlg::PkcsEd25519, *alg);
+            assert_eq!(TlsCertDigestAlg::Blake2b256, *digest_alg);
+        }
+        o => panic!("unexpected: {:?}", o),
+    }
+    let json = serde_json::to_string(&cert_public).unwrap();
+    assert!(json.contains(r#""entry_type":"tls_cert""#));
+    assert_eq!(cert_public, serde_json::from_str(&json).unwrap());
+    assert_eq!(
+        EntryPublic::SignEd25519 {
+            pub_key: sign_pub_key2.to_vec()
+        },
+        api_send2.lair_export_entry_public(sign_index).await?,
+    );
+    assert_eq!(
+        EntryPublic::X25519 {
+            pub_key: x25519_alice_pub_key.to_bytes().to_vec()
+        },
+        api_send2
+            .lair_export_entry_public(x25519_alice_index)
+            .await?,
+    );
+
+    // Using private keys counts, reading public material doesn't.
+    let cert_meta = api_send2.lair_get_entry_meta(cert_index).await?;
+    assert_eq!(3, cert_meta.use_count);
+    assert!(cert_meta.last_used.unwrap() >= cert_meta.created_at.unwrap());
+    assert_eq!(
+        9,
+        api_send2.lair_get_entry_meta(sign_index).await?.use_count
+    );
+    assert_eq!(1, agent_meta.use_count);
+    let unused_meta = api_send2.lair_get_entry_meta(tagged_cert_index).await?;
+    assert_eq!(0, unused_meta.use_count);
+    assert_eq!(None, unused_meta.last_used);
+
+    // Bulk pub key listing, deleted entries are skipped.
+    let agent_pub_key = api_send.sign_ed25519_get(agent_index).await?;
+    assert_eq!(
+        vec![(sign_index, sign_pub_key2), (agent_index, agent_pub_key)],
+        api_send2.sign_ed25519_list_pub_keys().await?,
+    );
+    let x25519_pub_keys = api_send2.x25519_list_pub_keys().await?;
+    assert_eq!(
+        vec![x25519_alice_index, x25519_bob_index, x25519_dave_index],
+        x25519_pub_keys
+            .iter()
+            .map(|(idx, _)| *idx)
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(x25519_alice_pub_key, x25519_pub_keys[0].1);
+
+    // Importing a keypair from an externally derived seed.
+    let seed = std::sync::Arc::new(vec![0xd5; 32]);
+    let expected = sign_ed25519::sign_ed25519_keypair_from_seed(seed.to_vec())
+        .await?
+        .pub_key;
+    let (seed_index, seed_pub_key) =
+        api_send.sign_ed25519_new_from_seed(seed.clone()).await?;
+    assert_eq!(expected, seed_pub_key);
+    assert_eq!(seed_pub_key, api_send2.sign_ed25519_get(seed_index).await?);
+    let sig = api_send
+        .sign_ed25519_sign_by_pub_key(seed_pub_key.clone(), data.clone())
+        .await?;
+    assert!(seed_pub_key.verify(data.clone(), sig).await?);
+    assert!(api_send.sign_ed25519_new_from_seed(seed).await.is_err());
+    assert!(matches!(
+        api_send
+            .sign_ed25519_new_from_seed(std::sync::Arc::new(vec![0xd5; 31]))
+            .await,
+        Err(lair_keystore_api::LairError::SignEd25519SeedLength(31))
+    ));
+
+    // Importing an x25519 keypair from an existing private key
+    // (RFC 7748 section 6.1, Alice).
+    let priv_key = std::sync::Arc::new(vec![
+        0x77, 0x07, 0x6d, 0x0a, 0x73, 0x18, 0xa5, 0x7d, 0x3c, 0x16, 0xc1, 0x72,
+        0x51, 0xb2, 0x66, 0x45, 0xdf, 0x4c, 0x2f, 0x87, 0xeb, 0xc0, 0x99, 0x2a,
+        0xb1, 0x77, 0xfb, 0xa5, 0x1d, 0xb9, 0x2c, 0x2a,
+    ]);
+    let (imported_index, imported_pub_key) =
+        api_send.x25519_new_from_priv_key(priv_key.clone()).await?;
+    assert_eq!(
+        [
+            0x85, 0x20, 0xf0, 0x09, 0x89, 0x30, 0xa7, 0x54, 0x74, 0x8b, 0x7d,
+            0xdc, 0xb4, 0x3e, 0xf7, 0x5a, 0x0d, 0xbf, 0x3a, 0x0d, 0x26, 0x38,
+            0x1a, 0xf4, 0xeb, 0xa4, 0xa9, 0x8e, 0xaa, 0x9b, 0x4e, 0x6a,
+        ],
+        imported_pub_key.to_bytes()
+    );
+    assert_eq!(
+        imported_pub_key,
+        api_send2.x25519_get(imported_index).await?
+    );
+    let imported_box = api_send
+        .crypto_box_by_pub_key(
+            imported_pub_key.clone(),
+            x25519_alice_pub_key.clone(),
+            std::sync::Arc::new(data.clone().into()),
+        )
+        .await?;
+    let imported_open = api_send2
+        .crypto_box_open_by_pub_key(
+            x25519_alice_pub_key.clone(),
+            imported_pub_key.clone(),
+            std::sync::Arc::new(imported_box),
+        )
+        .await?;
+    assert_eq!(&data, &imported_open.unwrap().data);
+    assert!(api_send.x25519_new_from_priv_key(priv_key).await.is_err());
+    assert!(matches!(
+        api_send
+            .x25519_new_from_priv_key(std::sync::Arc::new(vec![0x77; 33]))
+            .await,
+        Err(lair_keystore_api::LairError::X25519PrivKeyImportLength(33))
+    ));
+
+    // Deriving keypairs from a master seed.
+    let (master_index, seed_id) = api_send.seed_new_from_entropy(false).await?;
+    assert_eq!(
+        LairEntryType::Seed,
+        api_send2.lair_get_entry_type(master_index).await?
+    );
+    assert_eq!(
+        EntryPublic::Seed {
+            seed_id: seed_id.to_vec()
+        },
+        api_send2.lair_export_entry_public(master_index).await?
+    );
+    assert_eq!(
+        Some((master_index, LairEntryType::Seed)),
+        api_send2.lair_find_entry(seed_id.0.clone()).await?
+    );
+    let (derived_index, derived_pub_key) = api_send
+        .sign_ed25519_derive_from_seed(master_index, vec![0, 1])
+        .await?;
+    assert_eq!(
+        (derived_index, derived_pub_key.clone()),
+        api_send2
+            .sign_ed25519_derive_from_seed(master_index, vec![0, 1])
+            .await?
+    );
+    let (other_index, other_pub_key) = api_send
+        .sign_ed25519_derive_from_seed(master_index, vec![1, 0])
+        .await?;
+    assert_ne!(derived_index, other_index);
+    assert_ne!(derived_pub_key, other_pub_key);
+    let derived_meta = api_send2.lair_get_entry_meta(derived_index).await?;
+    let derivation = derived_meta.derived_from.unwrap();
+    assert_eq!(master_index, derivation.seed_index);
+    assert_eq!(vec![0, 1], derivation.path);
+    let sig = api_send
+        .sign_ed25519_sign_by_index(derived_index, data.clone())
+        .await?;
+    assert!(derived_pub_key.verify(data.clone(), sig).await?);
+    // the seed counts a use per derivation request
+    assert_eq!(
+        3,
+        api_send2.lair_get_entry_meta(master_index).await?.use_count
+    );
+    assert!(api_send
+        .sign_ed25519_derive_from_seed(derived_index, vec![0])
+        .await
+        .is_err());
+    assert!(api_send
+        .sign_ed25519_derive_from_seed(master_index, vec![0x8000_0000])
+        .await
+        .is_err());
+
+    // Mnemonic export / import of master seeds.
+    assert!(api_send.seed_export_mnemonic(master_index).await.is_err());
+    assert!(api_send.seed_export_mnemonic(derived_index).await.is_err());
+    let (export_index, export_seed_id) =
+        api_send.seed_new_from_entropy(true).await?;
+    let mnemonic = api_send.seed_export_mnemonic(export_index).await?;
+    assert_eq!(24, mnemonic.split(' ').count());
+    assert_eq!(
+        export_seed_id,
+        seed::seed_id(&seed::seed_from_mnemonic(&mnemonic)?)
+    );
+    assert!(api_send
+        .seed_import_mnemonic(mnemonic.to_uppercase(), false)
+        .await
+        .is_err());
+    let mnemonic = seed::seed_to_mnemonic(&vec![0xd6; 32].into())?;
+    let (import_index, import_seed_id) = api_send
+        .seed_import_mnemonic(mnemonic.to_uppercase(), true)
+        .await?;
+    assert_eq!(seed::seed_id(&[0xd6; 32]), import_seed_id);
+    assert_eq!(
+        mnemonic,
+        api_send2.seed_export_mnemonic(import_index).await?
+    );
+    let (_, import_pub_key) = api_send
+        .sign_ed25519_derive_from_seed(import_index, vec![0, 1])
+        .await?;
+    let expected =
+        seed::sign_ed25519_keypair_derive(vec![0xd6; 32].into(), vec![0, 1])
+            .await?
+            .pub_key;
+    assert_eq!(expected, import_pub_key);
+    assert!(api_send
+        .seed_import_mnemonic("abandon ".repeat(24), true)
+        .await
+        .is_err());
+
+    // Converting an ed25519 identity for encryption.
+    let (ed_index, ed_pub_key) =
+        api_send.sign_ed25519_new_from_entropy().await?;
+    let (converted_index, converted_pub_key) =
+        api_send.sign_ed25519_to_x25519(ed_index).await?;
+    assert_eq!(
+        x25519::x25519_pub_key_from_sign_ed25519(&ed_pub_key)?,
+        converted_pub_key
+    );
+    assert_eq!(
+        (converted_index, converted_pub_key.clone()),
+        api_send2.sign_ed25519_to_x25519(ed_index).await?
+    );
+    assert_eq!(
+        converted_pub_key,
+        api_send2.x25519_get(converted_index).await?
+    );
+    assert_eq!(
+        Some(ed_index),
+        api_send2
+            .lair_get_entry_meta(converted_index)
+            .await?
+            .converted_from
+    );
+    assert_eq!(2, api_send2.lair_get_entry_meta(ed_index).await?.use_count);
+    assert!(api_send
+        .sign_ed25519_to_x25519(converted_index)
+        .await
+        .is_err());
+
+    // secp256k1 signature keypairs.
+    let (secp_index, secp_pub_key) =
+        api_send.secp256k1_new_from_entropy().await?;
+    assert_eq!(sign_secp256k1::PUB_KEY_BYTES, secp_pub_key.len());
+    assert_eq!(secp_pub_key, api_send2.secp256k1_get(secp_index).await?);
+    assert_eq!(
+        LairEntryType::SignSecp256k1,
+        api_send2.lair_get_entry_type(secp_index).await?
+    );
+    assert_eq!(
+        EntryPublic::SignSecp256k1 {
+            pub_key: secp_pub_key.to_vec()
+        },
+        api_send2.lair_export_entry_public(secp_index).await?
+    );
+    assert_eq!(
+        Some((secp_index, LairEntryType::SignSecp256k1)),
+        api_send2.lair_find_entry(secp_pub_key.pub_id()).await?
+    );
+    let digest = [0x42; 32];
+    let sig = api_send.secp256k1_sign_by_index(secp_index, digest).await?;
+    assert_eq!(sign_secp256k1::SIGNATURE_BYTES, sig.len());
+    assert!(secp_pub_key.verify(digest, sig.clone()).await?);
+    assert_eq!(
+        secp_pub_key,
+        sign_secp256k1::sign_secp256k1_recover(digest, sig.clone()).await?
+    );
+    // signatures are deterministic
+    assert_eq!(
+        sig,
+        api_send2
+            .secp256k1_sign_by_pub_key(secp_pub_key.clone(), digest)
+            .await?
+    );
+    assert!(!secp_pub_key.verify([0x43; 32], sig).await?);
+    assert_eq!(
+        2,
+        api_send2.lair_get_entry_meta(secp_index).await?.use_count
+    );
+    assert!(api_send.secp256k1_get(master_index).await.is_err());
+    assert!(api_send
+        .secp256k1_sign_by_index(master_index, digest)
+        .await
+        .is_err());
+    let (_, other_secp_pub_key) = api_send.secp256k1_new_from_entropy().await?;
+    assert_ne!(secp_pub_key, other_secp_pub_key);
+    let mut unknown = secp_pub_key.to_vec();
+    unknown[0] ^= 1;
+    assert!(api_send
+        .secp256k1_sign_by_pub_key(unknown.into(), digest)
+        .await
+        .is_err());
+    assert!(api_send
+        .secp256k1_sign_by_pub_key(vec![0x02; 32].into(), digest)
+        .await
+        .is_err());
+
+    // bls12-381 signature keypairs, only with the `bls` feature.
+    #[cfg(feature = "bls")]
+    {
+        let (bls_index, bls_pub_key) = api_send.bls_new_from_entropy().await?;
+        assert_eq!(sign_bls12381::PUB_KEY_BYTES, bls_pub_key.len());
+        assert_eq!(
+            LairEntryType::SignBls12381,
+            api_send2.lair_get_entry_type(bls_index).await?
+        );
+        assert_eq!(
+            EntryPublic::SignBls12381 {
+                pub_key: bls_pub_key.to_vec()
+            },
+            api_send2.lair_export_entry_public(bls_index).await?
+        );
+        assert_eq!(
+            Some((bls_index, LairEntryType::SignBls12381)),
+            api_send2.lair_find_entry(bls_pub_key.pub_id()).await?
+        );
+        let (_, other_bls_pub_key) = api_send.bls_new_from_entropy().await?;
+        let message: std::sync::Arc<Vec<u8>> = b"hello".to_vec().into();
+        let sig = api_send
+            .bls_sign_by_pub_key(bls_pub_key.clone(), message.clone())
+            .await?;
+        assert!(bls_pub_key.verify(message.clone(), sig.clone()).await?);
+        assert_eq!(
+            1,
+            api_send2.lair_get_entry_meta(bls_index).await?.use_count
+        );
+        let other_message: std::sync::Arc<Vec<u8>> = b"world".to_vec().into();
+        let other_sig = api_send2
+            .bls_sign_by_pub_key(
+                other_bls_pub_key.clone(),
+                other_message.clone(),
+            )
+            .await?;
+        let agg = sign_bls12381::sign_bls12381_aggregate(&[sig, other_sig])?;
+        assert!(
+            sign_bls12381::sign_bls12381_aggregate_verify(
+                vec![bls_pub_key.clone(), other_bls_pub_key],
+                vec![message.clone(), other_message],
+                agg,
+            )
+            .await?
+        );
+        let mut unknown = bls_pub_key.to_vec();
+        unknown[1] ^= 1;
+        assert!(api_send
+            .bls_sign_by_pub_key(unknown.into(), message.clone())
+            .await
+            .is_err());
+        // malformed pub keys are rejected
+        assert!(api_send
+            .bls_sign_by_pub_key(vec![0x42; 32].into(), message)
+            .await
+            .is_err());
+    }
+    #[cfg(not(feature = "bls"))]
+    assert!(api_send.bls_new_from_entropy().await.is_err());
+    #[cfg(not(feature = "bls"))]
+    assert!(api_send
+        .bls_sign_by_pub_key(
+            vec![0x42; sign_bls12381::PUB_KEY_BYTES].into(),
+            b"hello".to_vec().into(),
+        )
+        .await
+        .is_err());
+
+    // Symmetric encryption, the secret key never leaves lair.
+    let (secret_index, secret_tag) = api_send.secret_new_from_entropy().await?;
+    assert_eq!(
+        LairEntryType::SecretKey,
+        api_send2.lair_get_entry_type(secret_index).await?
+    );
+    assert_eq!(
+        EntryPublic::SecretKey {
+            tag: secret_tag.to_vec(),
+        },
+        api_send2.lair_export_entry_public(secret_index).await?
+    );
+    let data = std::sync::Arc::new(data.to_vec());
+    let aad: std::sync::Arc<Vec<u8>> = b"conductor-state-v1".to_vec().into();
+    let (nonce, ciphertext) = api_send
+        .secret_encrypt_by_index(secret_index, aad.clone(), data.clone())
+        .await?;
+    assert_eq!(data.len() + secret_key::MAC_BYTES, ciphertext.len());
+    let (nonce2, ciphertext2) = api_send
+        .secret_encrypt_by_index(secret_index, aad.clone(), data.clone())
+        .await?;
+    assert_ne!(nonce, nonce2);
+    assert_ne!(ciphertext, ciphertext2);
+    assert_eq!(
+        Some(data.clone()),
+        api_send2
+            .secret_decrypt_by_index(
+                secret_index,
+                aad.clone(),
+                nonce,
+                ciphertext.clone(),
+            )
+            .await?
+    );
+    // a different aad or nonce, or tampering, fails to decrypt
+    assert!(api_send2
+        .secret_decrypt_by_index(
+            secret_index,
+            b"conductor-state-v2".to_vec().into(),
+            nonce,
+            ciphertext.clone(),
+        )
+        .await?
+        .is_none());
+    assert!(api_send2
+        .secret_decrypt_by_index(
+            secret_index,
+            aad.clone(),
+            nonce2,
+            ciphertext.clone(),
+        )
+        .await?
+        .is_none());
+    let mut tampered = ciphertext.to_vec();
+    tampered[0] ^= 1;
+    assert!(api_send2
+        .secret_decrypt_by_index(
+            secret_index,
+            aad.clone(),
+            nonce,
+            tampered.into(),
+        )
+        .await?
+        .is_none());
+    // every encrypt / decrypt attempt counts as a use
+    assert_eq!(
+        6,
+        api_send2.lair_get_entry_meta(secret_index).await?.use_count
+    );
+    // other key types can't be used
+    assert!(api_send
+        .secret_encrypt_by_index(sign_index, aad.clone(), data.clone())
+        .await
+        .is_err());
+    assert!(api_send
+        .secret_decrypt_by_index(sign_index, aad, nonce, ciphertext)
+        .await
+        .is_err());
+
+    // Streaming encryption, alice pushes a stream that bob pulls
+    // (over another connection).
+    let (stream_alice_index, stream_alice_pub_key) =
+        api_send.x25519_new_from_entropy().await?;
+    let (stream_bob_index, stream_bob_pub_key) =
+        api_send2.x25519_new_from_entropy().await?;
+    let (push_id, header) = api_send
+        .secretstream_init_push_by_index(
+            stream_alice_index,
+            stream_bob_pub_key.clone(),
+        )
+        .await?;
+    let pull_id = api_send2
+        .secretstream_init_pull_by_index(
+            stream_bob_index,
+            stream_alice_pub_key.clone(),
+            header,
+        )
+        .await?;
+    // sessions can only be used by the connection that opened them
+    assert!(api_send2
+        .secretstream_push(push_id, data.clone(), false)
+        .await
+        .is_err());
+    assert!(api_send2.secretstream_close(push_id).await.is_err());
+    let chunks = [
+        std::sync::Arc::new(vec![0x42; secretstream::CHUNK_MAX_BYTES]),
+        data.clone(),
+    ];
+    let mut ciphertexts = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        ciphertexts.push(
+            api_send
+                .secretstream_push(push_id, chunk.clone(), i == 1)
+                .await?,
+        );
+    }
+    // the final chunk ended the push session
+    assert!(api_send
+        .secretstream_push(push_id, data.clone(), false)
+        .await
+        .is_err());
+    assert!(api_send
+        .secretstream_pull(pull_id, ciphertexts[0].clone())
+        .await
+        .is_err());
+    let mut tampered = ciphertexts[0].to_vec();
+    tampered[1] ^= 1;
+    assert!(api_send2
+        .secretstream_pull(pull_id, tampered.into())
+        .await?
+        .is_none());
+    for (i, ciphertext) in ciphertexts.into_iter().enumerate() {
+        assert_eq!(
+            Some((chunks[i].clone(), i == 1)),
+            api_send2.secretstream_pull(pull_id, ciphertext).await?,
+        );
+    }
+    // over 64 KiB has to be split into more chunks
+    let (push_id, _) = api_send
+        .secretstream_init_push_by_index(stream_alice_index, stream_bob_pub_key)
+        .await?;
+    assert!(api_send
+        .secretstream_push(
+            push_id,
+            vec![0; secretstream::CHUNK_MAX_BYTES + 1].into(),
+            false,
+        )
+        .await
+        .is_err());
+    api_send.secretstream_close(push_id).await?;
+    assert!(api_send
+        .secretstream_push(push_id, data.clone(), true)
+        .await
+        .is_err());
+    // only x25519 keypairs key streams
+    assert!(api_send
+        .secretstream_init_push_by_index(sign_index, stream_alice_pub_key)
+        .await
+        .is_err());
+    assert_eq!(
+        2,
+        api_send2
+            .lair_get_entry_meta(stream_alice_index)
+            .await?
+            .use_count
+    );
+
+    // subkeys are derived deterministically from secret keys and seeds
+    let subkey = api_send
+        .derive_subkey_by_index(secret_index, 32, *b"app-keys", 7)
+        .await?;
+    assert_eq!(32, subkey.len());
+    assert_eq!(
+        subkey,
+        api_send2
+            .derive_subkey_by_index(secret_index, 32, *b"app-keys", 7)
+            .await?
+    );
+    assert_ne!(
+        subkey,
+        api_send
+            .derive_subkey_by_index(secret_index, 32, *b"app-keys", 8)
+            .await?
+    );
+    assert_ne!(
+        subkey,
+        api_send
+            .derive_subkey_by_index(secret_index, 32, *b"app-keyz", 7)
+            .await?
+    );
+    assert_eq!(
+        secret_key::SUBKEY_MAX_BYTES,
+        api_send
+            .derive_subkey_by_index(master_index, 64, *b"app-keys", 7)
+            .await?
+            .len()
+    );
+    assert!(matches!(
+        api_send
+            .derive_subkey_by_index(secret_index, 65, *b"app-keys", 7)
+            .await,
+        Err(lair_keystore_api::LairError::SubkeyLength(65))
+    ));
+    // only secret keys and seeds derive subkeys
+    assert!(api_send
+        .derive_subkey_by_index(sign_index, 32, *b"app-keys", 7)
+        .await
+        .is_err());
+
+    // crypto_box with aad only opens with the same aad
+    let (aad_alice_index, aad_alice_pub_key) =
+        api_send.x25519_new_from_entropy().await?;
+    let (aad_bob_index, aad_bob_pub_key) =
+        api_send2.x25519_new_from_entropy().await?;
+    let aad = std::sync::Arc::new(b"message type".to_vec());
+    let aad_data: std::sync::Arc<crypto_box::CryptoBoxData> =
+        std::sync::Arc::new(b"bound to its context".to_vec().into());
+    let boxed = std::sync::Arc::new(
+        api_send
+            .crypto_box_by_index_with_aad(
+                aad_alice_index,
+                aad_bob_pub_key.clone(),
+                aad.clone(),
+                aad_data.clone(),
+            )
+            .await?,
+    );
+    assert_eq!(
+        Some((*aad_data).clone()),
+        api_send2
+            .crypto_box_open_by_pub_key_with_aad(
+                aad_bob_pub_key.clone(),
+                aad_alice_pub_key.clone(),
+                aad.clone(),
+                boxed.clone(),
+            )
+            .await?
+    );
+    assert_eq!(
+        None,
+        api_send2
+            .crypto_box_open_by_index_with_aad(
+                aad_bob_index,
+                aad_alice_pub_key.clone(),
+                std::sync::Arc::new(b"other type".to_vec()),
+                boxed.clone(),
+            )
+            .await?
+    );
+    // boxes with and without aad don't open as each other
+    assert_eq!(
+        None,
+        api_send2
+            .crypto_box_open_by_index(
+                aad_bob_index,
+                aad_alice_pub_key.clone(),
+                boxed,
+            )
+            .await?
+    );
+    let boxed = std::sync::Arc::new(
+        api_send2
+            .crypto_box_by_pub_key_with_aad(
+                aad_bob_pub_key.clone(),
+                aad_alice_pub_key.clone(),
+                aad.clone(),
+                aad_data.clone(),
+            )
+            .await?,
+    );
+    assert_eq!(
+        Some((*aad_data).clone()),
+        api_send
+            .crypto_box_open_by_index_with_aad(
+                aad_alice_index,
+                aad_bob_pub_key.clone(),
+                aad,
+                boxed,
+            )
+            .await?
+    );
+
+    // tls certs with a custom sni and subject alt names
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.sni = Some("proxy.example.com".to_string());
+    tls_options.alt_names =
+        vec!["*.proxy.example.com".to_string(), "10.0.0.1".to_string()];
+    let (named_cert_index, named_cert_sni, named_cert_digest) = api_send
+        .tls_cert_new_self_signed_from_entropy(tls_options)
+        .await?;
+    assert_eq!("proxy.example.com", named_cert_sni.as_str());
+    assert_eq!(
+        api_send
+            .tls_cert_get_cert_by_index(named_cert_index)
+            .await?,
+        api_send2
+            .tls_cert_get_cert_by_sni("proxy.example.com".to_string().into())
+            .await?,
+    );
+    assert_eq!(
+        (named_cert_sni, named_cert_digest.clone()),
+        api_send2.tls_cert_get(named_cert_index).await?,
+    );
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.sni = Some("not a hostname".to_string());
+    assert!(matches!(
+        api_send
+            .tls_cert_new_self_signed_from_entropy(tls_options)
+            .await,
+        Err(lair_keystore_api::LairError::TlsCertName(_))
+    ));
+    assert_eq!(
+        named_cert_index,
+        api_send.lair_get_last_entry_index().await?,
+    );
+
+    // renewing a tls cert keeps its private key
+    let (renewed_cert_index, renewed_cert_sni, renewed_cert_digest) = api_send
+        .tls_cert_renew(
+            named_cert_index,
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await?;
+    assert_eq!("proxy.example.com", renewed_cert_sni.as_str());
+    assert_ne!(named_cert_digest, renewed_cert_digest);
+    assert_eq!(
+        api_send
+            .tls_cert_get_priv_key_by_index(named_cert_index)
+            .await?,
+        api_send2
+            .tls_cert_get_priv_key_by_index(renewed_cert_index)
+            .await?,
+    );
+    assert_eq!(
+        api_send
+            .tls_cert_get_cert_by_index(renewed_cert_index)
+            .await?,
+        api_send2.tls_cert_get_cert_by_sni(renewed_cert_sni).await?,
+    );
+    assert_eq!(
+        api_send
+            .tls_cert_get_cert_by_index(named_cert_index)
+            .await?,
+        api_send2
+            .tls_cert_get_cert_by_digest(named_cert_digest)
+            .await?,
+    );
+    assert_eq!(
+        Some(named_cert_index),
+        api_send
+            .lair_get_entry_meta(renewed_cert_index)
+            .await?
+            .renewed_from,
+    );
+
+    // externally issued tls cert import
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.sni = Some("import.example.com".to_string());
+    let external = tls::tls_cert_self_signed_new_from_entropy(tls_options)
+        .await
+        .unwrap();
+    let (import_index, import_sni, import_digest) = api_send
+        .tls_cert_import(
+            external.cert_der.clone(),
+            external.priv_key_der.clone(),
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await?;
+    assert_eq!(external.sni, import_sni);
+    assert_eq!(external.cert_digest, import_digest);
+    assert_eq!(
+        (import_sni.clone(), import_digest.clone()),
+        api_send2.tls_cert_get(import_index).await?,
+    );
+    for cert in [
+        api_send2.tls_cert_get_cert_by_index(import_index).await?,
+        api_send2
+            .tls_cert_get_cert_by_digest(import_digest.clone())
+            .await?,
+        api_send2
+            .tls_cert_get_cert_by_sni(import_sni.clone())
+            .await?,
+    ] {
+        assert_eq!(external.cert_der, cert);
+    }
+    for priv_key in [
+        api_send2
+            .tls_cert_get_priv_key_by_index(import_index)
+            .await?,
+        api_send2
+            .tls_cert_get_priv_key_by_digest(import_digest)
+            .await?,
+        api_send2.tls_cert_get_priv_key_by_sni(import_sni).await?,
+    ] {
+        assert_eq!(external.priv_key_der, priv_key);
+    }
+    // typed errors reach the client as their message
+    let err = api_send
+        .tls_cert_import(
+            external.cert_der.clone(),
+            api_send
+                .tls_cert_get_priv_key_by_index(renewed_cert_index)
+                .await?,
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("does not match"), "{}", err);
+    let err = api_send
+        .tls_cert_import(
+            vec![0x30, 0x00].into(),
+            external.priv_key_der.clone(),
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("Invalid tls cert import"),
+        "{}",
+        err
+    );
+
+    // generated certs of each alg are usable by rustls
+    for (alg, sni) in [
+        (TlsCertAlg::PkcsEd25519, "ed25519.example.com"),
+        (TlsCertAlg::PkcsEcdsaP256Sha256, "p256.example.com"),
+        (TlsCertAlg::PkcsEcdsaP384Sha384, "p384.example.com"),
+    ] {
+        let mut tls_options =
+            lair_keystore_api::actor::TlsCertOptions::default();
+        tls_options.alg = alg;
+        tls_options.sni = Some(sni.to_string());
+        let (alg_index, alg_sni, _) = api_send
+            .tls_cert_new_self_signed_from_entropy(tls_options)
+            .await?;
+        match api_send2.lair_export_entry_public(alg_index).await? {
+            EntryPublic::TlsCert { alg: entry_alg, .. } => {
+                assert_eq!(alg, entry_alg)
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+        let cert = api_send2.tls_cert_get_cert_by_sni(alg_sni.clone()).await?;
+        let priv_key = api_send2.tls_cert_get_priv_key_by_sni(alg_sni).await?;
+        tls_handshake(
+            sni,
+            &tls::WK_CA_CERT_DER,
+            vec![cert.to_vec()],
+            priv_key.to_vec(),
+        );
+    }
+
+    // certs issued by a stored CA
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.sni = Some("ca.example.com".to_string());
+    let (ca_index, _, _) = api_send.tls_ca_new_self_signed(tls_options).await?;
+    let ca_cert = api_send.tls_cert_get_cert_by_index(ca_index).await?;
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.sni = Some("leaf.example.com".to_string());
+    let (leaf_index, leaf_sni, _, cert_chain) = api_send
+        .tls_cert_new_signed_by_ca(ca_index, tls_options)
+        .await?;
+    assert_eq!(2, cert_chain.len());
+    assert_eq!(ca_cert, cert_chain[1]);
+    let leaf_priv_key =
+        api_send.tls_cert_get_priv_key_by_index(leaf_index).await?;
+    tls_handshake(
+        "leaf.example.com",
+        &ca_cert,
+        cert_chain.iter().map(|c| c.to_vec()).collect(),
+        leaf_priv_key.to_vec(),
+    );
+
+    // issued certs outlive their CA
+    api_send.lair_delete_entry(ca_index).await?;
+    assert_eq!(
+        cert_chain[0],
+        api_send.tls_cert_get_cert_by_sni(leaf_sni).await?
+    );
+    assert_eq!(
+        leaf_priv_key,
+        api_send.tls_cert_get_priv_key_by_index(leaf_index).await?
+    );
+    let err = api_send
+        .tls_cert_new_signed_by_ca(
+            ca_index,
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("has been deleted"), "{}", err);
+
+    // only CA certs can issue certs
+    let err = api_send
+        .tls_cert_new_signed_by_ca(
+            leaf_index,
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not a CA cert"), "{}", err);
+
+    // sha256 cert digests, found by a fingerprint prefix
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.digest_alg = TlsCertDigestAlg::Sha256;
+    let (sha_index, sha_sni, sha_digest) = api_send
+        .tls_cert_new_self_signed_from_entropy(tls_options)
+        .await?;
+    let sha_cert = api_send.tls_cert_get_cert_by_index(sha_index).await?;
+    assert_eq!(
+        ring::digest::digest(&ring::digest::SHA256, &sha_cert).as_ref(),
+        &sha_digest[..]
+    );
+    assert_eq!(
+        (sha_index, sha_sni.clone(), sha_digest.clone()),
+        api_send
+            .tls_cert_find_by_digest_prefix(std::sync::Arc::new(
+                sha_digest[..8].to_vec()
+            ))
+            .await?
+    );
+    let sha_item = api_send
+        .lair_list_entries()
+        .await?
+        .into_iter()
+        .find(|e| e.keystore_index == sha_index)
+        .unwrap();
+    assert_eq!(Some(TlsCertDigestAlg::Sha256), sha_item.cert_digest_alg);
+    let err = api_send
+        .tls_cert_find_by_digest_prefix(std::sync::Arc::new(vec![0; 32]))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("no tls cert digest"), "{}", err);
+
+    // rustls servers using only lair-held material
+    let resolver = std::sync::Arc::new(
+        lair_keystore_api::tls::LairServerCertResolver::new(api_send.clone()),
+    );
+    for sni in [
+        "ed25519.example.com",
+        "p256.example.com",
+        "ed25519.example.com",
+    ] {
+        let server_config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone());
+        tls_handshake_with_config(sni, &tls::WK_CA_CERT_DER, server_config);
+    }
+    let certified_key =
+        lair_keystore_api::tls::certified_key_from_lair(&api_send, sha_index)
+            .await?;
+    let mut by_sni = rustls::server::ResolvesServerCertUsingSni::new();
+    by_sni.add(&sha_sni, certified_key).unwrap();
+    let server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(std::sync::Arc::new(by_sni));
+    tls_handshake_with_config(&sha_sni, &tls::WK_CA_CERT_DER, server_config);
+
+    // keys that never leave the keystore, signing handshakes remotely
+    let mut tls_options = lair_keystore_api::actor::TlsCertOptions::default();
+    tls_options.alg = TlsCertAlg::PkcsEcdsaP256Sha256;
+    tls_options.sni = Some("remote.example.com".to_string());
+    tls_options.exportable = Some(false);
+    let (remote_index, remote_sni, remote_digest) = api_send
+        .tls_cert_new_self_signed_from_entropy(tls_options)
+        .await?;
+    for err in [
+        api_send
+            .tls_cert_get_priv_key_by_index(remote_index)
+            .await
+            .unwrap_err(),
+        api_send
+            .tls_cert_get_priv_key_by_digest(remote_digest)
+            .await
+            .unwrap_err(),
+        api_send
+            .tls_cert_get_priv_key_by_sni(remote_sni.clone())
+            .await
+            .unwrap_err(),
+        api_send
+            .tls_cert_export_pkcs12(remote_index, None)
+            .await
+            .unwrap_err(),
+        lair_keystore_api::tls::certified_key_from_lair(
+            &api_send,
+            remote_index,
+        )
+        .await
+        .err()
+        .unwrap(),
+    ] {
+        assert!(err.to_string().contains("not exportable"), "{}", err);
+    }
+
+    let err = api_send
+        .tls_cert_sign_by_index(
+            remote_index,
+            TlsCertSignatureScheme::Ed25519,
+            std::sync::Arc::new(b"message".to_vec()),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("signature scheme"), "{}", err);
+    let server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver.clone());
+    tls_handshake_with_config(
+        "remote.example.com",
+        &tls::WK_CA_CERT_DER,
+        server_config,
+    );
+    let certified_key = lair_keystore_api::tls::remote_certified_key_from_lair(
+        &api_send,
+        remote_index,
+    )
+    .await?;
+    let mut by_sni = rustls::server::ResolvesServerCertUsingSni::new();
+    by_sni.add(&remote_sni, certified_key).unwrap();
+    let server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(std::sync::Arc::new(by_sni));
+    tls_handshake_with_config(&remote_sni, &tls::WK_CA_CERT_DER, server_config);
+
+    // a renewed key stays in the keystore
+    let (renewed_index, _, _) = api_send
+        .tls_cert_renew(
+            remote_index,
+            lair_keystore_api::actor::TlsCertOptions::default(),
+        )
+        .await?;
+    let err = api_send
+        .tls_cert_get_priv_key_by_index(renewed_index)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not exportable"), "{}", err);
+
+    // pkcs12 export
+    let renewed_use_count = api_send
+        .lair_get_entry_meta(renewed_cert_index)
+        .await?
+        .use_count;
+    let p12 = api_send
+        .tls_cert_export_pkcs12(renewed_cert_index, Some("pw".to_string()))
+        .await?;
+    let pfx = p12::PFX::parse(&p12).unwrap();
+    assert!(pfx.verify_mac("pw"));
+    assert_eq!(
+        vec![api_send
+            .tls_cert_get_cert_by_index(renewed_cert_index)
+            .await?
+            .to_vec()],
+        pfx.cert_x509_bags("pw").unwrap(),
+    );
+    assert_eq!(
+        vec![api_send2
+            .tls_cert_get_priv_key_by_index(renewed_cert_index)
+            .await?
+            .to_vec()],
+        pfx.key_bags("pw").unwrap(),
+    );
+    assert_eq!(
+        renewed_use_count + 2,
+        api_send2
+            .lair_get_entry_meta(renewed_cert_index)
+            .await?
+            .use_count,
+    );
+    assert!(api_send
+        .tls_cert_export_pkcs12(sign_index, None)
+        .await
+        .is_err());
+
+    // kx session keys: each side receives what the other transmits
+    let (kx_client_index, kx_client_pub_key) =
+        api_send.x25519_new_from_entropy().await?;
+    let (kx_server_index, kx_server_pub_key) =
+        api_send2.x25519_new_from_entropy().await?;
+    let (client_rx, client_tx) = api_send
+        .kx_session_keys_by_index(
+            kx_client_index,
+            kx_server_pub_key.clone(),
+            false,
+        )
+        .await?;
+    let (server_rx, server_tx) = api_send2
+        .kx_session_keys_by_index(kx_server_index, kx_client_pub_key, true)
+        .await?;
+    assert_eq!(kx::SESSION_KEY_BYTES, client_rx.len());
+    assert_ne!(client_rx, client_tx);
+    assert_eq!(client_rx, server_tx);
+    assert_eq!(client_tx, server_rx);
+    // only x25519 keypairs derive session keys
+    assert!(api_send
+        .kx_session_keys_by_index(sign_index, kx_server_pub_key, false)
+        .await
+        .is_err());
+    assert_eq!(
+        1,
+        api_send
+            .lair_get_entry_meta(kx_client_index)
+            .await?
+            .use_count
+    );
+
+    // this store was never initialized with an unlock passphrase
+    assert!(api_send
+        .lair_change_unlock_passphrase(
+            "passphrase".to_string(),
+            "new passphrase".to_string(),
+        )
+        .await
+        .is_err());
+
+    api_send.lair_shutdown().await?;
+    shutdown.await.unwrap();
 
     Ok(())
 }
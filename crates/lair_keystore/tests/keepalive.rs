@@ -0,0 +1,104 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::internal::ipc::{
+    MIN_PROTOCOL_VERSION, PROTOCOL_FEATURES, PROTOCOL_VERSION,
+};
+use lair_keystore_api::internal::wire::LairWire;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn servers_echo_pings() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_ping_interval(Duration::from_millis(50))
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    assert_eq!(42, api.lair_ping(42).await?);
+
+    // idle well past two ping intervals, the connection is kept
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(7, api.lair_ping(7).await?);
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unanswered_pings_break_the_connection() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_ping_interval(Duration::from_millis(100))
+        .build();
+    let listener =
+        tokio::net::UnixListener::bind(config.get_socket_path()).unwrap();
+
+    // a server that handshakes, then never answers
+    let (pings_send, mut pings_recv) = tokio::sync::mpsc::unbounded_channel();
+    tokio::task::spawn(async move {
+        loop {
+            let (mut con, _) = listener.accept().await.unwrap();
+            let pings_send = pings_send.clone();
+            tokio::task::spawn(async move {
+                read_frame(&mut con).await;
+                let handshake = LairWire::Handshake {
+                    msg_id: 0,
+                    version: PROTOCOL_VERSION,
+                    min_version: MIN_PROTOCOL_VERSION,
+                    features: PROTOCOL_FEATURES,
+                };
+                con.write_all(&handshake.encode().unwrap()).await.unwrap();
+                loop {
+                    if let LairWire::ToLairLairPing { .. } =
+                        read_frame(&mut con).await
+                    {
+                        if pings_send.send(()).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let (api, _) = lair_keystore_api::ipc::spawn_client_ipc(config)
+        .await
+        .unwrap();
+
+    // two unanswered pings, then the client gives up on the connection
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(5), pings_recv.recv())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    match api.lair_get_last_entry_index().await {
+        Err(lair_keystore_api::LairError::Disconnected) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+
+    // and reconnects, pinging the new connection in turn
+    tokio::time::timeout(Duration::from_secs(5), pings_recv.recv())
+        .await
+        .unwrap()
+        .unwrap();
+}
@@ -0,0 +1,193 @@
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::{LairClientApiSender, LairLockState};
+use lair_keystore_api::internal::crypto_box::CryptoBoxData;
+use lair_keystore_api::{Config, LairError};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_lock_and_unlock_the_store() -> lair_keystore_api::LairResult<()>
+{
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    let message = bytes::Bytes::from_static(b"message");
+
+    // box both ways, so the keyed boxes are cached
+    let (alice, alice_pub_key) = api.x25519_new_from_entropy().await?;
+    let (bob, bob_pub_key) = api.x25519_new_from_entropy().await?;
+    let data = Arc::new(CryptoBoxData::from(b"data".to_vec()));
+    let encrypted_data = Arc::new(
+        api.crypto_box_by_index(alice, bob_pub_key.clone(), data.clone())
+            .await?,
+    );
+    assert_eq!(
+        Some(&*data),
+        api.crypto_box_open_by_index(
+            bob,
+            alice_pub_key.clone(),
+            encrypted_data.clone(),
+        )
+        .await?
+        .as_ref(),
+    );
+
+    api.lair_lock().await?;
+    match api.sign_ed25519_sign_by_index(index, message.clone()).await {
+        Err(LairError::StoreLocked) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+    match api
+        .crypto_box_by_index(alice, bob_pub_key.clone(), data.clone())
+        .await
+    {
+        Err(LairError::StoreLocked) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+    match api
+        .crypto_box_open_by_index(
+            bob,
+            alice_pub_key.clone(),
+            encrypted_data.clone(),
+        )
+        .await
+    {
+        Err(LairError::StoreLocked) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+    match api.sign_ed25519_new_from_entropy().await {
+        Err(LairError::StoreLocked) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+
+    assert!(api.lair_unlock("nope".to_string().into()).await.is_err());
+    assert_eq!(1, api.lair_get_server_info().await?.failed_unlock_count);
+
+    api.lair_unlock("passphrase".to_string().into()).await?;
+    let signature = api
+        .sign_ed25519_sign_by_index(index, message.clone())
+        .await?;
+    assert!(pub_key.verify(message, signature).await?);
+    assert_eq!(
+        Some((*data).clone()),
+        api.crypto_box_open_by_index(bob, alice_pub_key, encrypted_data)
+            .await?,
+    );
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unused_stores_lock_by_themselves() -> lair_keystore_api::LairResult<()>
+{
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_auto_lock_after_idle(Duration::from_millis(200))
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, _) = api.sign_ed25519_new_from_entropy().await?;
+    let message = bytes::Bytes::from_static(b"message");
+
+    // in use, it stays unlocked
+    for _ in 0..5 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        api.sign_ed25519_sign_by_index(index, message.clone())
+            .await?;
+    }
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    match api.sign_ed25519_sign_by_index(index, message.clone()).await {
+        Err(LairError::StoreLocked) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+
+    api.lair_unlock("passphrase".to_string().into()).await?;
+    api.sign_ed25519_sign_by_index(index, message).await?;
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+async fn next_state(
+    recv: &mut tokio::sync::mpsc::UnboundedReceiver<Heard>,
+) -> LairLockState {
+    next_heard(recv, |heard| match heard {
+        Heard::Locked => Some(LairLockState::Locked),
+        Heard::Unlocked => Some(LairLockState::Unlocked),
+        _ => None,
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_client_hears_of_lock_state_changes(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (watcher, evt_recv) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let mut heard = respond_to_events(evt_recv, |_| None, || async { false });
+
+    assert_eq!(
+        LairLockState::Unlocked,
+        watcher.lair_get_lock_state().await?
+    );
+
+    api.lair_lock().await?;
+    assert_eq!(LairLockState::Locked, next_state(&mut heard).await);
+    assert_eq!(LairLockState::Locked, watcher.lair_get_lock_state().await?);
+
+    // locking a locked store is no change
+    api.lair_lock().await?;
+    api.lair_unlock("passphrase".to_string().into()).await?;
+    assert_eq!(LairLockState::Unlocked, next_state(&mut heard).await);
+    assert_eq!(
+        LairLockState::Unlocked,
+        watcher.lair_get_lock_state().await?
+    );
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
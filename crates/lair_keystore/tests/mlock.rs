@@ -0,0 +1,64 @@
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::internal::util;
+use lair_keystore_api::{Config, CONFIG_FILE_NAME};
+
+// mlocking is process-wide, so this is the only test of this file
+// starting servers in this process
+#[tokio::test(flavor = "multi_thread")]
+async fn servers_report_whether_secrets_are_mlocked(
+) -> lair_keystore_api::LairResult<()> {
+    // the first server of the process decides, later ones are ignored
+    for &mlock_secrets in &[true, false] {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_mlock_secrets(mlock_secrets)
+            .build();
+        lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+            .await?;
+        let shutdown = lair_keystore::execute_lair_with_config(
+            config.clone(),
+            Some(b"passphrase".to_vec()),
+            None,
+        )
+        .await?;
+
+        let (api, _) =
+            lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+        let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+        let message = bytes::Bytes::from_static(b"message");
+        let signature = api
+            .sign_ed25519_sign_by_index(index, message.clone())
+            .await?;
+        assert!(pub_key.verify(message, signature).await?);
+
+        // a memlock limit too low is no error, just reported
+        assert!(util::mem_lock_enabled());
+        let info = api.lair_get_server_info().await?;
+        assert_eq!(util::mem_lock_active(), info.secrets_mlocked);
+
+        api.lair_shutdown().await?;
+        shutdown.await.unwrap();
+    }
+
+    // a process of its own can keep its secrets unlocked
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        tmpdir.path().join(CONFIG_FILE_NAME),
+        "mlock_secrets = false\n",
+    )
+    .unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let _server = spawn_server(tmpdir.path());
+    let (api, _evt_recv) =
+        retry(|| lair_keystore_api::ipc::spawn_client_ipc(config.clone()))
+            .await;
+    assert!(!api.lair_get_server_info().await?.secrets_mlocked);
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use futures::future::FutureExt;
+use lair_keystore_api::actor::LairClientApiSender;
+
+fn profile_config(
+    root: &std::path::Path,
+    profile: &str,
+) -> std::sync::Arc<lair_keystore_api::Config> {
+    lair_keystore_api::Config::builder()
+        .set_root_path(root)
+        .set_profile(profile)
+        .unwrap()
+        .build()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lair_serves_several_profiles_from_one_process(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let alice = profile_config(tmpdir.path(), "alice");
+    let bob = profile_config(tmpdir.path(), "bob");
+
+    lair_keystore::init_lair(alice.clone(), b"alice-pass".to_vec(), false)
+        .await?;
+    lair_keystore::init_lair(bob.clone(), b"bob-pass".to_vec(), false).await?;
+
+    // profiles must not share files
+    assert!(lair_keystore::execute_lair_profiles(
+        vec![(alice.clone(), None), (alice.clone(), None)],
+        None,
+    )
+    .await
+    .is_err());
+
+    let (ready_send, ready_recv) = futures::channel::oneshot::channel();
+    let shutdown = lair_keystore::execute_lair_profiles(
+        vec![
+            (alice.clone(), Some(b"alice-pass".to_vec())),
+            (bob.clone(), Some(b"bob-pass".to_vec())),
+        ],
+        Some(ready_send),
+    )
+    .await?;
+    ready_recv.await.unwrap();
+
+    for name in &["socket-alice", "socket-bob", "store-alice", "store-bob"] {
+        assert!(tmpdir.path().join(name).exists(), "{}", name);
+    }
+
+    // clients pick the profile by the config they connect with,
+    // and each profile has its own entry indices
+    let (alice_api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(alice.clone()).await?;
+    let (bob_api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(bob.clone()).await?;
+    let (alice_index, alice_pub_key) =
+        alice_api.sign_ed25519_new_from_entropy().await?;
+    let (alice_index2, _) = alice_api.sign_ed25519_new_from_entropy().await?;
+    let (bob_index, bob_pub_key) =
+        bob_api.sign_ed25519_new_from_entropy().await?;
+    assert_eq!(alice_index, bob_index);
+    assert_eq!(alice_index.0 + 1, alice_index2.0);
+    assert_ne!(alice_pub_key, bob_pub_key);
+    assert_eq!(
+        alice_pub_key,
+        alice_api.sign_ed25519_get(alice_index).await?
+    );
+    assert_eq!(bob_pub_key, bob_api.sign_ed25519_get(bob_index).await?);
+    assert!(bob_api.sign_ed25519_get(alice_index2).await.is_err());
+
+    // the process serves until every profile has shut down
+    let mut shutdown = shutdown.fuse();
+    alice_api.lair_shutdown().await?;
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert!((&mut shutdown).now_or_never().is_none());
+    assert!(bob_api.sign_ed25519_get(bob_index).await.is_ok());
+    bob_api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // each profile has its own unlock passphrase
+    assert!(lair_keystore::execute_lair_with_config(
+        bob.clone(),
+        Some(b"alice-pass".to_vec()),
+        None,
+    )
+    .await
+    .is_err());
+
+    Ok(())
+}
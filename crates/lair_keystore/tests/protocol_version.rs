@@ -0,0 +1,94 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::internal::ipc::PROTOCOL_VERSION;
+use lair_keystore_api::internal::wire::LairWire;
+use lair_keystore_api::LairError;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// The handshake of a peer from before there were any versions.
+fn version_0() -> Vec<u8> {
+    LairWire::Handshake {
+        msg_id: 0,
+        version: 0,
+        min_version: 0,
+        features: 0,
+    }
+    .encode()
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn servers_drop_clients_of_an_unsupported_version(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let mut con = tokio::net::UnixStream::connect(config.get_socket_path())
+        .await
+        .unwrap();
+    con.write_all(&version_0()).await.unwrap();
+    match read_frame(&mut con).await {
+        LairWire::Handshake { version, .. } => {
+            assert_eq!(PROTOCOL_VERSION, version)
+        }
+        o => panic!("unexpected: {:?}", o),
+    }
+    // and hangs up
+    let mut rest = Vec::new();
+    con.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+
+    // while current clients are served
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    api.lair_get_last_entry_index().await?;
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_fail_against_servers_of_an_unsupported_version() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    let listener =
+        tokio::net::UnixListener::bind(config.get_socket_path()).unwrap();
+    let server = tokio::task::spawn(async move {
+        let (mut con, _) = listener.accept().await.unwrap();
+        match read_frame(&mut con).await {
+            LairWire::Handshake { version, .. } => {
+                assert_eq!(PROTOCOL_VERSION, version)
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+        con.write_all(&version_0()).await.unwrap();
+        con
+    });
+
+    match lair_keystore_api::ipc::spawn_client_ipc(config).await {
+        Err(LairError::ProtocolVersionMismatch { ours, theirs }) => {
+            assert_eq!(PROTOCOL_VERSION, ours);
+            assert_eq!(0, theirs);
+        }
+        Err(err) => panic!("unexpected: {:?}", err),
+        Ok(_) => panic!("connected to a version 0 server"),
+    }
+    server.await.unwrap();
+}
@@ -0,0 +1,113 @@
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::LairError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+async fn start(
+    config: Arc<lair_keystore_api::Config>,
+) -> lair_keystore_api::LairResult<(
+    ghost_actor::GhostSender<lair_keystore_api::actor::LairClientApi>,
+    futures::future::BoxFuture<'static, ()>,
+)> {
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) = lair_keystore_api::ipc::spawn_client_ipc(config).await?;
+    Ok((api, Box::pin(async move { shutdown.await.unwrap() })))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signing_is_held_to_the_entry_rate_limit(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    let (api, shutdown) = start(config).await?;
+
+    const RATE: u32 = 20;
+    let (index, _) = api.sign_ed25519_new_from_entropy().await?;
+    let (other, _) = api.sign_ed25519_new_from_entropy().await?;
+    assert_eq!(None, api.lair_get_entry_meta(index).await?.rate_limit);
+    api.lair_set_entry_rate_limit(index, Some(RATE)).await?;
+    assert_eq!(Some(RATE), api.lair_get_entry_meta(index).await?.rate_limit);
+
+    let message = bytes::Bytes::from_static(b"hello");
+    let start = Instant::now();
+    let mut signed = 0;
+    let mut limited = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        let sigs =
+            futures::future::join_all((0..10).map(|_| {
+                api.sign_ed25519_sign_by_index(index, message.clone())
+            }))
+            .await;
+        for sig in sigs {
+            match sig {
+                Ok(_) => signed += 1,
+                Err(LairError::RateLimited { retry_after }) => {
+                    assert!(retry_after > Duration::ZERO);
+                    limited += 1;
+                }
+                Err(err) => panic!("unexpected: {:?}", err),
+            }
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    // a burst of a second's worth, then the rate
+    let ceiling = RATE as f64 * (1.0 + elapsed) + 1.0;
+    assert!(signed >= RATE, "signed {}", signed);
+    assert!(
+        signed as f64 <= ceiling,
+        "signed {} in {}s",
+        signed,
+        elapsed
+    );
+    assert!(limited > 0);
+
+    // other entries are not held back
+    for _ in 0..RATE * 2 {
+        api.sign_ed25519_sign_by_index(other, message.clone())
+            .await?;
+    }
+
+    api.lair_shutdown().await?;
+    shutdown.await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signing_waits_for_the_server_rate_limit(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_rate_limit(10)
+        .set_rate_limit_queue(100)
+        .build();
+    let (api, shutdown) = start(config).await?;
+
+    let (a, _) = api.sign_ed25519_new_from_entropy().await?;
+    let (b, _) = api.sign_ed25519_new_from_entropy().await?;
+
+    // the server limit spans entries, the requests past the burst queue
+    let message = bytes::Bytes::from_static(b"hello");
+    let start = Instant::now();
+    let sigs = futures::future::join_all((0..15).map(|i| {
+        let index = if i % 2 == 0 { a } else { b };
+        api.sign_ed25519_sign_by_index(index, message.clone())
+    }))
+    .await;
+    assert!(sigs.iter().all(|sig| sig.is_ok()));
+    assert!(start.elapsed() >= Duration::from_millis(450));
+
+    api.lair_shutdown().await?;
+    shutdown.await;
+    Ok(())
+}
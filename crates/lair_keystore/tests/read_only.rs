@@ -0,0 +1,119 @@
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::internal::crypto_box;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The name, contents and modification time of every file in `dir`,
+/// along with the modification time of `dir` itself.
+fn snapshot(
+    dir: &std::path::Path,
+) -> (
+    std::time::SystemTime,
+    BTreeMap<std::ffi::OsString, (Vec<u8>, std::time::SystemTime)>,
+) {
+    let mut files = BTreeMap::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        let modified = entry.metadata().unwrap().modified().unwrap();
+        let data = std::fs::read(entry.path()).unwrap();
+        files.insert(entry.file_name(), (data, modified));
+    }
+    let modified = std::fs::metadata(dir).unwrap().modified().unwrap();
+    (modified, files)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_only_lair_never_changes_the_store_dir(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (sign_index, sign_pub_key) =
+        api.sign_ed25519_new_from_entropy().await?;
+    let (alice_index, alice_pub_key) = api.x25519_new_from_entropy().await?;
+    let (bob_index, bob_pub_key) = api.x25519_new_from_entropy().await?;
+    let (deleted_index, _) = api.sign_ed25519_new_from_entropy().await?;
+    api.lair_delete_entry(deleted_index).await?;
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // the socket goes elsewhere, binding it would change the lair dir
+    let sockdir = tempfile::tempdir().unwrap();
+    let read_only = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_socket_path(sockdir.path().join("socket"))
+        .set_read_only(true)
+        .build();
+
+    // let mtimes be told apart from the writes above
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let before = snapshot(tmpdir.path());
+
+    let shutdown = lair_keystore::execute_lair_with_config(
+        read_only.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(read_only.clone()).await?;
+
+    // entries are served
+    assert_eq!(sign_pub_key, api.sign_ed25519_get(sign_index).await?);
+    let data = bytes::Bytes::from_static(b"read-only");
+    let sig = api
+        .sign_ed25519_sign_by_index(sign_index, data.clone())
+        .await?;
+    assert!(sign_pub_key.verify(data.clone(), sig.clone()).await?);
+    assert!(
+        api.sign_ed25519_verify(sign_pub_key.clone(), data.clone(), sig)
+            .await?
+    );
+    let boxed = api
+        .crypto_box_by_index(
+            alice_index,
+            bob_pub_key.clone(),
+            Arc::new(crypto_box::CryptoBoxData::from(data.clone())),
+        )
+        .await?;
+    let opened = api
+        .crypto_box_open_by_index(bob_index, alice_pub_key, Arc::new(boxed))
+        .await?;
+    assert_eq!(data, opened.unwrap().data);
+    assert!(api.sign_ed25519_get(deleted_index).await.is_err());
+
+    // nothing is written
+    let err = api.sign_ed25519_new_from_entropy().await.unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{}", err);
+    let err = api.x25519_new_from_entropy().await.unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{}", err);
+    let err = api.lair_delete_entry(sign_index).await.unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{}", err);
+    let err = api.lair_compact_store().await.unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{}", err);
+
+    // a usage flush would have run by now
+    tokio::time::sleep(
+        lair_keystore::store::USAGE_FLUSH_INTERVAL
+            + std::time::Duration::from_millis(500),
+    )
+    .await;
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    assert_eq!(before, snapshot(tmpdir.path()));
+
+    Ok(())
+}
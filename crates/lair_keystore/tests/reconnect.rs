@@ -0,0 +1,60 @@
+mod common;
+
+use common::*;
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::{LairError, LairResult};
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_reconnect_when_the_server_restarts() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+
+    let server = spawn_server(tmpdir.path());
+    let (api, evt_recv) =
+        retry(|| lair_keystore_api::ipc::spawn_client_ipc(config.clone()))
+            .await;
+
+    // unlock every server we connect to, and note reconnections
+    let mut heard =
+        respond_to_events(evt_recv, |_| Some("passphrase"), || async { false });
+
+    api.lair_register_passphrase_provider().await?;
+
+    let (index, pub_key) = retry(|| api.sign_ed25519_new_from_entropy()).await;
+    let message = bytes::Bytes::from_static(b"message");
+    let signature = api
+        .sign_ed25519_sign_by_index(index, message.clone())
+        .await?;
+    assert!(pub_key.verify(message.clone(), signature).await?);
+
+    drop(server);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // requests fail while there is no server
+    match api.sign_ed25519_sign_by_index(index, message.clone()).await {
+        Err(LairError::Disconnected) => (),
+        o => panic!("unexpected: {:?}", o),
+    }
+
+    let server = spawn_server(tmpdir.path());
+    tokio::time::timeout(Duration::from_secs(20), async {
+        while heard.recv().await.unwrap() != Heard::Reconnected {}
+    })
+    .await
+    .expect("reconnected");
+
+    // and the same client carries on signing
+    let signature =
+        retry(|| api.sign_ed25519_sign_by_index(index, message.clone())).await;
+    assert!(pub_key.verify(message, signature).await?);
+    assert_eq!(pub_key, api.sign_ed25519_get(index).await?);
+
+    drop(server);
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use common::*;
+use futures::future::FutureExt;
+use lair_keystore_api::actor::{LairClientApiSender, LairLockState};
+
+/// Connect to the lair server as a passphrase provider, answering
+/// requests for the unlock passphrase with `passphrase`.
+async fn spawn_client(
+    config: std::sync::Arc<lair_keystore_api::Config>,
+    passphrase: &'static str,
+) -> lair_keystore_api::LairResult<
+    ghost_actor::GhostSender<lair_keystore_api::actor::LairClientApi>,
+> {
+    let (api_send, evt_recv) =
+        lair_keystore_api::ipc::spawn_client_ipc(config).await?;
+    respond_to_events(evt_recv, move |_| Some(passphrase), || async { false });
+    api_send.lair_register_passphrase_provider().await?;
+
+    Ok(api_send)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lair_unlocks_sealed_store_with_client_passphrase(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    // so the client with the wrong passphrase gives up soon
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_unlock_passphrase_attempts(2)
+        .build();
+
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+
+    let (ready_send, ready_recv) = futures::channel::oneshot::channel();
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        None,
+        Some(ready_send),
+    )
+    .await?;
+    ready_recv.await.unwrap();
+
+    // a client with the wrong passphrase leaves the store locked
+    let api_send = spawn_client(config.clone(), "nope").await?;
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert!(api_send.sign_ed25519_new_from_entropy().await.is_err());
+
+    // clients that are not passphrase providers are never asked
+    let (other, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    assert_eq!(LairLockState::Locked, other.lair_get_lock_state().await?);
+
+    // the passphrase arrives just after the provider registers
+    let api_send = spawn_client(config.clone(), "passphrase").await?;
+    let mut created = None;
+    for _ in 0..100 {
+        if let Ok(res) = api_send.sign_ed25519_new_from_entropy().await {
+            created = Some(res);
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let (sign_index, sign_pub_key) = created.expect("store was never unlocked");
+    assert_eq!(LairLockState::Unlocked, other.lair_get_lock_state().await?);
+
+    api_send.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // the key material is sealed on disk
+    let priv_key = {
+        let store_file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(config.get_store_path())
+            .await
+            .unwrap();
+        let store = lair_keystore::store::spawn_entry_store_actor(
+            config.clone(),
+            store_file,
+        )
+        .await?;
+        use lair_keystore::store::EntryStoreSender;
+        store.unlock(b"passphrase".to_vec().into()).await?;
+        let entry = store.get_entry_by_index(sign_index).await?;
+        use ghost_actor::GhostControlSender;
+        store.ghost_actor_shutdown().await?;
+        match &*entry {
+            lair_keystore_api::entry::LairEntry::SignEd25519(e) => {
+                e.priv_key.clone()
+            }
+            _ => panic!("unexpected entry type"),
+        }
+    };
+    let data = std::fs::read(config.get_store_path()).unwrap();
+    assert!(!data.windows(32).any(|w| w == &priv_key[..]));
+
+    // an unlock passphrase given at startup must be the right one
+    assert!(lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"nope".to_vec()),
+        None,
+    )
+    .await
+    .is_err());
+
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let api_send = spawn_client(config.clone(), "nope").await?;
+    assert_eq!(sign_pub_key, api_send.sign_ed25519_get(sign_index).await?);
+    api_send.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn providers_are_asked_again_after_a_wrong_passphrase(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_unlock_passphrase_attempts(3)
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown =
+        lair_keystore::execute_lair_with_config(config.clone(), None, None)
+            .await?;
+
+    let (api_send, evt_recv) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+
+    // wrong the first time, right the second
+    let mut heard = respond_to_events(
+        evt_recv,
+        |attempt| Some(if attempt == 1 { "nope" } else { "passphrase" }),
+        || async { false },
+    );
+    api_send.lair_register_passphrase_provider().await?;
+
+    let asked = |heard| match heard {
+        Heard::PassphraseRequest {
+            last_passphrase_failed,
+            attempt,
+            max_attempts,
+        } => Some((last_passphrase_failed, attempt, max_attempts)),
+        _ => None,
+    };
+    assert_eq!((false, 1, 3), next_heard(&mut heard, asked).await);
+    assert_eq!((true, 2, 3), next_heard(&mut heard, asked).await);
+
+    let mut unlocked = false;
+    for _ in 0..100 {
+        if api_send.lair_get_lock_state().await? == LairLockState::Unlocked {
+            unlocked = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(unlocked, "store was never unlocked");
+    assert_eq!(
+        1,
+        api_send.lair_get_server_info().await?.failed_unlock_count
+    );
+    while let Some(Some(heard)) = heard.recv().now_or_never() {
+        assert_eq!(None, asked(heard));
+    }
+
+    api_send.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
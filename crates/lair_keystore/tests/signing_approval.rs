@@ -0,0 +1,243 @@
+mod common;
+
+use common::*;
+use futures::future::FutureExt;
+use lair_keystore_api::actor::{
+    signing_approval_digest, KeystoreIndex, LairClientApiSender,
+    TlsCertOptions, TlsCertSignatureScheme,
+};
+use lair_keystore_api::LairError;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How a test approver answers requests for signing approval.
+#[derive(Clone, Copy)]
+enum Answer {
+    Approve,
+    Deny,
+    /// answer long after the server gave up waiting
+    Stall,
+}
+
+type Request = (KeystoreIndex, String, [u8; 32]);
+
+fn config(
+    root: &std::path::Path,
+    client_name: &str,
+) -> Arc<lair_keystore_api::Config> {
+    lair_keystore_api::Config::builder()
+        .set_root_path(root)
+        .set_signing_approval_timeout(Duration::from_millis(500))
+        .set_client_name(client_name)
+        .unwrap()
+        .build()
+}
+
+async fn start(
+    config: Arc<lair_keystore_api::Config>,
+) -> lair_keystore_api::LairResult<(
+    ghost_actor::GhostSender<lair_keystore_api::actor::LairClientApi>,
+    futures::future::BoxFuture<'static, ()>,
+)> {
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) = lair_keystore_api::ipc::spawn_client_ipc(config).await?;
+    Ok((api, Box::pin(async move { shutdown.await.unwrap() })))
+}
+
+/// Connect to the lair server as a signing approver, answering every
+/// request with `answer` and passing it on to the receiver returned.
+async fn spawn_approver(
+    config: Arc<lair_keystore_api::Config>,
+    answer: Answer,
+) -> lair_keystore_api::LairResult<(
+    ghost_actor::GhostSender<lair_keystore_api::actor::LairClientApi>,
+    tokio::sync::mpsc::UnboundedReceiver<Heard>,
+)> {
+    let (api_send, evt_recv) =
+        lair_keystore_api::ipc::spawn_client_ipc(config).await?;
+    let heard = respond_to_events(
+        evt_recv,
+        |_| None,
+        move || async move {
+            match answer {
+                Answer::Approve => true,
+                Answer::Deny => false,
+                Answer::Stall => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    true
+                }
+            }
+        },
+    );
+    api_send.lair_register_signing_approver().await?;
+
+    Ok((api_send, heard))
+}
+
+async fn next_request(
+    recv: &mut tokio::sync::mpsc::UnboundedReceiver<Heard>,
+) -> Request {
+    next_heard(recv, |heard| match heard {
+        Heard::SigningRequest {
+            entry_index,
+            client_id,
+            message_digest,
+        } => Some((entry_index, client_id, message_digest)),
+        _ => None,
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signing_fails_closed_without_an_approver(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let (api, shutdown) = start(config(tmpdir.path(), "agent")).await?;
+
+    let (admin, _) = api
+        .sign_ed25519_new_from_entropy_requiring_approval()
+        .await?;
+    let (agent, _) = api.sign_ed25519_new_from_entropy().await?;
+    assert!(api.lair_get_entry_meta(admin).await?.requires_approval);
+    assert!(!api.lair_get_entry_meta(agent).await?.requires_approval);
+
+    let message = bytes::Bytes::from_static(b"hello");
+    assert!(matches!(
+        api.sign_ed25519_sign_by_index(admin, message.clone()).await,
+        Err(LairError::NoSigningApprover),
+    ));
+    // other keys keep working unattended
+    api.sign_ed25519_sign_by_index(agent, message).await?;
+
+    api.lair_shutdown().await?;
+    shutdown.await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn approved_signatures_are_made() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let (api, shutdown) = start(config(tmpdir.path(), "agent")).await?;
+    let (_approver, mut requests) =
+        spawn_approver(config(tmpdir.path(), "approver"), Answer::Approve)
+            .await?;
+
+    let (admin, pub_key) = api
+        .sign_ed25519_new_from_entropy_requiring_approval()
+        .await?;
+    let (agent, _) = api.sign_ed25519_new_from_entropy().await?;
+
+    let message = bytes::Bytes::from_static(b"hello");
+    let signature = api
+        .sign_ed25519_sign_by_pub_key(pub_key.clone(), message.clone())
+        .await?;
+    assert!(pub_key.verify(message.clone(), signature).await?);
+    assert_eq!(
+        (
+            admin,
+            "agent".to_string(),
+            signing_approval_digest(&[&message[..]])
+        ),
+        next_request(&mut requests).await,
+    );
+
+    // a batch is approved as one
+    let messages = vec![message.clone(), bytes::Bytes::from_static(b"world")];
+    api.sign_ed25519_sign_batch_by_index(admin, messages)
+        .await?;
+    assert_eq!(
+        signing_approval_digest(&[b"hello".as_ref(), b"world".as_ref()]),
+        next_request(&mut requests).await.2,
+    );
+
+    // other keys are signed with without asking, whatever their type
+    api.sign_ed25519_sign_by_index(agent, message).await?;
+    let (secp256k1, _) = api.secp256k1_new_from_entropy().await?;
+    api.secp256k1_sign_by_index(secp256k1, [0xdb; 32]).await?;
+    let (cert, _, _) = api
+        .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
+        .await?;
+    api.tls_cert_sign_by_index(
+        cert,
+        TlsCertSignatureScheme::Ed25519,
+        b"hello".to_vec().into(),
+    )
+    .await?;
+    assert!(requests.recv().now_or_never().is_none());
+
+    api.lair_shutdown().await?;
+    shutdown.await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn denied_signatures_fail() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let (api, shutdown) = start(config(tmpdir.path(), "agent")).await?;
+    let (_approver, mut requests) =
+        spawn_approver(config(tmpdir.path(), "approver"), Answer::Deny).await?;
+
+    let (admin, _) = api
+        .sign_ed25519_new_from_entropy_requiring_approval()
+        .await?;
+    let message = bytes::Bytes::from_static(b"hello");
+    assert!(matches!(
+        api.sign_ed25519_sign_by_index(admin, message).await,
+        Err(LairError::SigningDenied),
+    ));
+    assert_eq!(admin, next_request(&mut requests).await.0);
+    // signing of any other type is gated the same, before the entry
+    // is even looked at
+    assert!(matches!(
+        api.secp256k1_sign_by_index(admin, [0xdb; 32]).await,
+        Err(LairError::SigningDenied),
+    ));
+    assert_eq!(admin, next_request(&mut requests).await.0);
+    assert!(matches!(
+        api.tls_cert_sign_by_index(
+            admin,
+            TlsCertSignatureScheme::Ed25519,
+            b"hello".to_vec().into(),
+        )
+        .await,
+        Err(LairError::SigningDenied),
+    ));
+    assert_eq!(admin, next_request(&mut requests).await.0);
+    // the key was not used
+    assert_eq!(0, api.lair_get_entry_meta(admin).await?.use_count);
+
+    api.lair_shutdown().await?;
+    shutdown.await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unanswered_approvals_time_out() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let (api, shutdown) = start(config(tmpdir.path(), "agent")).await?;
+    let (_approver, _requests) =
+        spawn_approver(config(tmpdir.path(), "approver"), Answer::Stall)
+            .await?;
+
+    let (admin, _) = api
+        .sign_ed25519_new_from_entropy_requiring_approval()
+        .await?;
+    let message = bytes::Bytes::from_static(b"hello");
+    let start = std::time::Instant::now();
+    assert!(matches!(
+        api.sign_ed25519_sign_by_index(admin, message).await,
+        Err(LairError::SigningApprovalTimeout),
+    ));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    api.lair_shutdown().await?;
+    shutdown.await;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+#![cfg(unix)]
+
+use futures::stream::StreamExt;
+use lair_keystore_api::actor::LairClientApiSender;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lair_adopts_systemd_socket() -> lair_keystore_api::LairResult<()> {
+    // the lair data dir
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::env::set_var("LAIR_DIR", tmpdir.path());
+
+    // where "systemd" binds the socket
+    let sock_dir = tempfile::tempdir().unwrap();
+    let sock_config = lair_keystore_api::Config::builder()
+        .set_root_path(sock_dir.path())
+        .build();
+    let listener =
+        std::os::unix::net::UnixListener::bind(sock_config.get_socket_path())
+            .unwrap();
+
+    // we can't move the fd to 3 without clobbering something else in
+    // the test process, so pad the fd list and select ours by name
+    use std::os::unix::io::IntoRawFd;
+    let fd = listener.into_raw_fd();
+    let fd_count = fd - 2;
+    let mut names = vec!["other"; fd_count as usize - 1];
+    names.push("lair-keystore");
+    std::env::set_var("LISTEN_PID", std::process::id().to_string());
+    std::env::set_var("LISTEN_FDS", fd_count.to_string());
+    std::env::set_var("LISTEN_FDNAMES", names.join(":"));
+
+    let shutdown = lair_keystore::execute_lair().await?;
+
+    assert!(std::env::var_os("LISTEN_FDS").is_none());
+
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    assert!(std::fs::metadata(config.get_socket_path()).is_err());
+
+    let (api_send, mut evt_recv) =
+        lair_keystore_api::ipc::spawn_client_ipc(sock_config.clone()).await?;
+    tokio::task::spawn(async move { while evt_recv.next().await.is_some() {} });
+
+    let info = api_send.lair_get_server_info().await?;
+    assert_eq!("lair-keystore", &info.name);
+
+    let (index, _) = api_send.sign_ed25519_new_from_entropy().await?;
+    assert_eq!(1, index.0);
+
+    // the activated socket is not ours to clean up
+    api_send.lair_shutdown().await?;
+    shutdown.await.unwrap();
+    assert!(std::fs::metadata(sock_config.get_socket_path()).is_ok());
+
+    Ok(())
+}
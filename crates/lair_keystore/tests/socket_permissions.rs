@@ -0,0 +1,77 @@
+#![cfg(unix)]
+
+use lair_keystore_api::actor::LairClientApiSender;
+use lair_keystore_api::{Config, LairResult};
+use std::os::unix::fs::MetadataExt;
+use std::sync::Arc;
+
+/// Serve a store of `config`, check one request gets through,
+/// then return the metadata of its socket file.
+async fn serve(config: Arc<Config>) -> LairResult<std::fs::Metadata> {
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    api.sign_ed25519_new_from_entropy().await?;
+    let meta = std::fs::metadata(config.get_socket_path()).unwrap();
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+    Ok(meta)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sockets_are_owner_only_by_default() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = Config::builder().set_root_path(tmpdir.path()).build();
+    let meta = serve(config).await?;
+    assert_eq!(0o600, meta.mode() & 0o777);
+    assert_eq!(unsafe { libc::geteuid() }, meta.uid());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sockets_get_the_configured_dir_mode_and_group() -> LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let run_dir = tempfile::tempdir().unwrap();
+    // our own group, the only one we can be sure to give files to
+    let gid = unsafe { libc::getegid() };
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_socket_dir(run_dir.path().join("lair"))
+        .set_socket_mode(0o660)?
+        .set_socket_group(gid.to_string())
+        .build();
+    assert_eq!(
+        run_dir.path().join("lair").join("socket"),
+        config.get_socket_path()
+    );
+
+    let meta = serve(config.clone()).await?;
+    assert_eq!(0o660, meta.mode() & 0o777);
+    assert_eq!(unsafe { libc::geteuid() }, meta.uid());
+    assert_eq!(gid, meta.gid());
+    // the store stays in the data dir
+    assert!(config.get_store_path().starts_with(config.get_root_path()));
+    assert!(config.get_store_path().exists());
+
+    // a group that doesn't exist fails the server
+    let config = Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_socket_group("no-such-lair-group")
+        .build();
+    assert!(lair_keystore::execute_lair_with_config(
+        config,
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await
+    .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::*;
+use futures::future::FutureExt;
+use lair_keystore_api::actor::{LairClientApiSender, LairEntryType};
+
+/// Connect to the lair server, passing on the events it sends.
+async fn spawn_client(
+    config: std::sync::Arc<lair_keystore_api::Config>,
+) -> lair_keystore_api::LairResult<(
+    ghost_actor::GhostSender<lair_keystore_api::actor::LairClientApi>,
+    tokio::sync::mpsc::UnboundedReceiver<Heard>,
+)> {
+    let (api_send, evt_recv) =
+        lair_keystore_api::ipc::spawn_client_ipc(config).await?;
+    let heard = respond_to_events(evt_recv, |_| None, || async { false });
+    Ok((api_send, heard))
+}
+
+async fn next_event(
+    recv: &mut tokio::sync::mpsc::UnboundedReceiver<Heard>,
+) -> Heard {
+    next_heard(recv, |heard| match heard {
+        Heard::Created(..) | Heard::Deleted(..) => Some(heard),
+        _ => None,
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribed_clients_see_other_clients_store_changes(
+) -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    let (api, mut api_events) = spawn_client(config.clone()).await?;
+    let (watcher, mut watcher_events) = spawn_client(config.clone()).await?;
+    watcher.lair_subscribe_store_events().await?;
+
+    let (index, _) = api.sign_ed25519_new_from_entropy().await?;
+    assert_eq!(
+        Heard::Created(index, LairEntryType::SignEd25519),
+        next_event(&mut watcher_events).await,
+    );
+
+    api.lair_delete_entry(index).await?;
+    assert_eq!(Heard::Deleted(index), next_event(&mut watcher_events).await);
+
+    // the client that did not subscribe got neither
+    assert!(api_events.recv().now_or_never().is_none());
+
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use lair_keystore_api::actor::LairClientApiSender;
+
+/// how long a start refused by the lock may take, at most
+const FAIL_FAST: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_one_lair_runs_per_store() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+
+    // a crashed lair leaves its pidfile behind, unlocked - even naming
+    // a live process (as a reused pid would) it is taken over
+    std::fs::write(config.get_pid_path(), format!("{}", std::process::id()))
+        .unwrap();
+
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+
+    // the second server fails before touching the socket
+    let socket_meta = std::fs::metadata(config.get_socket_path()).unwrap();
+    let second = tokio::time::timeout(
+        FAIL_FAST,
+        lair_keystore::execute_lair_with_config(
+            config.clone(),
+            Some(b"passphrase".to_vec()),
+            None,
+        ),
+    )
+    .await
+    .expect("second lair did not fail fast");
+    let err = second.expect_err("second lair started");
+    assert_eq!(
+        format!(
+            "another lair-keystore is running (pid {})",
+            std::process::id()
+        ),
+        err.to_string(),
+    );
+    assert_eq!(
+        socket_meta.modified().unwrap(),
+        std::fs::metadata(config.get_socket_path())
+            .unwrap()
+            .modified()
+            .unwrap(),
+    );
+
+    // so do one-off commands that need the store to themselves
+    assert!(matches!(
+        lair_keystore::repair_lair(config.clone()).await,
+        Err(lair_keystore_api::LairError::ProcessAlreadyExists(Some(_))),
+    ));
+
+    // the first one is unharmed
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // a clean shutdown releases the lock
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    assert_eq!(pub_key, api.sign_ed25519_get(index).await?);
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use lair_keystore_api::actor::LairClientApiSender;
+
+/// a port nothing is listening on, as far as we know
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lair_serves_clients_over_tcp() -> lair_keystore_api::LairResult<()> {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let port = free_port();
+    let config = lair_keystore_api::Config::builder()
+        .set_root_path(tmpdir.path())
+        .set_tcp_bind(&port.to_string())?
+        .set_tcp_only(true)
+        .build();
+    lair_keystore::init_lair(config.clone(), b"passphrase".to_vec(), false)
+        .await?;
+
+    let shutdown = lair_keystore::execute_lair_with_config(
+        config.clone(),
+        Some(b"passphrase".to_vec()),
+        None,
+    )
+    .await?;
+    assert!(!config.get_socket_path().exists());
+
+    let (api, _) =
+        lair_keystore_api::ipc::spawn_client_ipc(config.clone()).await?;
+    let (index, pub_key) = api.sign_ed25519_new_from_entropy().await?;
+    assert_eq!(pub_key, api.sign_ed25519_get(index).await?);
+    api.lair_shutdown().await?;
+    shutdown.await.unwrap();
+
+    // the listener is closed along with the server
+    assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_err());
+    assert!(lair_keystore_api::ipc::spawn_client_ipc(config.clone())
+        .await
+        .is_err());
+
+    Ok(())
+}
@@ -3,7 +3,14 @@
 use crate::*;
 use derive_more::*;
 use internal::crypto_box;
+use internal::kx;
+use internal::secret_key;
+use internal::secretstream;
+use internal::seed;
+use internal::sign_bls12381;
 use internal::sign_ed25519;
+use internal::sign_secp256k1;
+use internal::util::SecretBytes;
 use internal::x25519;
 
 ghost_actor::ghost_chan! {
@@ -11,7 +18,81 @@ ghost_actor::ghost_chan! {
     pub chan LairClientEvent<LairError> {
         /// The keystore is currently locked - the user
         /// must supply a passphrase in order to unlock.
-        fn request_unlock_passphrase() -> String;
+        /// Only sent to clients that registered with
+        /// `LairClientApi::lair_register_passphrase_provider()`.
+        /// While the passphrase given is wrong, the client is asked again
+        /// with `last_passphrase_failed`, up to `max_attempts` times
+        /// (`attempt` counts from 1), e.g. to show "2 of 5 attempts".
+        /// The passphrase is zeroized once sent, the server normalizes
+        /// it (NFKD), so any keyboard's form of it unlocks the store.
+        fn request_unlock_passphrase(
+            last_passphrase_failed: bool,
+            attempt: u32,
+            max_attempts: u32,
+        ) -> SecretBytes;
+
+        /// The client lost its connection to the server and connected
+        /// again: requests in flight at the time failed with
+        /// `LairError::Disconnected`, the server may have restarted.
+        fn reconnected() -> ();
+
+        /// An entry was created in the store, by any client. Only sent
+        /// to clients that subscribed with
+        /// `LairClientApi::lair_subscribe_store_events()`.
+        fn entry_created(
+            index: KeystoreIndex,
+            entry_type: LairEntryType,
+        ) -> ();
+
+        /// An entry was deleted from the store, by any client. Only sent
+        /// to clients that subscribed with
+        /// `LairClientApi::lair_subscribe_store_events()`.
+        fn entry_deleted(index: KeystoreIndex) -> ();
+
+        /// The store was locked, by a client or as it went unused.
+        /// Sent to every client, see `LairClientApi::lair_get_lock_state()`
+        /// for the state at the time of connecting.
+        fn locked() -> ();
+
+        /// The store was unlocked. Sent to every client.
+        fn unlocked() -> ();
+
+        /// A client asks to sign with the key of an entry that requires
+        /// approval (see `EntryMeta::requires_approval`), respond `true`
+        /// to let it. Only sent to clients that registered with
+        /// `LairClientApi::lair_register_signing_approver()`.
+        /// `client_id` is the name the client identified itself by (see
+        /// `ConfigBuilder::set_client_name()`), `message_digest` the
+        /// `signing_approval_digest()` of what it asks to sign.
+        fn request_signing_approval(
+            entry_index: KeystoreIndex,
+            client_id: String,
+            message_digest: [u8; 32],
+        ) -> bool;
+    }
+}
+
+/// The blake2b-256 hash of a message to sign, as given to signing
+/// approvers: of the message itself, or for a batch, of the hashes of its
+/// messages one after another.
+pub fn signing_approval_digest(messages: &[&[u8]]) -> [u8; 32] {
+    let hash = |data: &[u8]| {
+        let mut out = [0; 32];
+        out.copy_from_slice(
+            blake2b_simd::Params::new()
+                .hash_length(32)
+                .hash(data)
+                .as_bytes(),
+        );
+        out
+    };
+    match messages {
+        [message] => hash(message),
+        messages => {
+            let hashes: Vec<u8> =
+                messages.iter().flat_map(|m| hash(m)).collect();
+            hash(&hashes)
+        }
     }
 }
 
@@ -24,22 +105,40 @@ pub type LairClientEventReceiver =
     futures::channel::mpsc::Receiver<LairClientEvent>;
 
 /// Tls keypair algorithm to use.
+/// The private key of every algorithm is a DER encoded pkcs #8
+/// PrivateKeyInfo, as returned by `tls_cert_get_priv_key_by_*`,
+/// the variant docs describe what it wraps.
 #[non_exhaustive]
 #[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
 pub enum TlsCertAlg {
     /// Ed25519 Curve.
+    /// The key is the RFC 8410 `id-Ed25519` 32 byte seed,
+    /// (a v2 OneAsymmetricKey including the public key if generated).
+    #[default]
     PkcsEd25519 = 0x00000200,
     /// Ecdsa Curve 256.
+    /// The key is an RFC 5915 SEC1 `ECPrivateKey`,
+    /// with the `prime256v1` named curve parameter.
     PkcsEcdsaP256Sha256 = 0x00000201,
     /// Ecdsa Curve 384.
+    /// The key is an RFC 5915 SEC1 `ECPrivateKey`,
+    /// with the `secp384r1` named curve parameter.
     PkcsEcdsaP384Sha384 = 0x00000202,
-}
-
-impl Default for TlsCertAlg {
-    fn default() -> Self {
-        Self::PkcsEd25519
-    }
+    /// Rsa, signing with pkcs #1 v1.5 Sha256.
+    /// The key is an RFC 8017 `RSAPrivateKey`.
+    /// Only imported certs use this, it cannot be generated.
+    PkcsRsaSha256 = 0x00000203,
 }
 
 impl TlsCertAlg {
@@ -50,22 +149,133 @@ impl TlsCertAlg {
             x if x == PkcsEd25519 as u32 => PkcsEd25519,
             x if x == PkcsEcdsaP256Sha256 as u32 => PkcsEcdsaP256Sha256,
             x if x == PkcsEcdsaP384Sha384 as u32 => PkcsEcdsaP384Sha384,
+            x if x == PkcsRsaSha256 as u32 => PkcsRsaSha256,
             _ => return Err("invalid tls cert alg".into()),
         })
     }
 }
 
+/// Digest algorithm producing the `CertDigest` of a tls cert,
+/// always computed over the DER encoded certificate.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsCertDigestAlg {
+    /// 32 byte blake2b.
+    #[default]
+    Blake2b256 = 0x00000300,
+    /// Sha256, the fingerprint printed by
+    /// `openssl x509 -noout -fingerprint -sha256`.
+    Sha256 = 0x00000301,
+}
+
+impl TlsCertDigestAlg {
+    /// parse a u32 into a TlsCertDigestAlg enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use TlsCertDigestAlg::*;
+        Ok(match d {
+            x if x == Blake2b256 as u32 => Blake2b256,
+            x if x == Sha256 as u32 => Sha256,
+            _ => return Err("invalid tls cert digest alg".into()),
+        })
+    }
+}
+
+/// Tls handshake signature scheme for `tls_cert_sign_by_index`,
+/// numbered as the TLS `SignatureScheme` registry.
+/// Each scheme signs with the keypair of one `TlsCertAlg`.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsCertSignatureScheme {
+    /// Ed25519, for `TlsCertAlg::PkcsEd25519`.
+    Ed25519 = 0x00000807,
+    /// Ecdsa P-256 Sha256 (ASN.1 DER signature),
+    /// for `TlsCertAlg::PkcsEcdsaP256Sha256`.
+    EcdsaSecp256r1Sha256 = 0x00000403,
+    /// Ecdsa P-384 Sha384 (ASN.1 DER signature),
+    /// for `TlsCertAlg::PkcsEcdsaP384Sha384`.
+    EcdsaSecp384r1Sha384 = 0x00000503,
+    /// Rsa pkcs #1 v1.5 Sha256, for `TlsCertAlg::PkcsRsaSha256`.
+    RsaPkcs1Sha256 = 0x00000401,
+    /// Rsa PSS Sha256, for `TlsCertAlg::PkcsRsaSha256`.
+    /// TLS 1.3 requires PSS for rsa keys.
+    RsaPssRsaeSha256 = 0x00000804,
+}
+
+impl TlsCertSignatureScheme {
+    /// parse a u32 into a TlsCertSignatureScheme enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use TlsCertSignatureScheme::*;
+        Ok(match d {
+            x if x == Ed25519 as u32 => Ed25519,
+            x if x == EcdsaSecp256r1Sha256 as u32 => EcdsaSecp256r1Sha256,
+            x if x == EcdsaSecp384r1Sha384 as u32 => EcdsaSecp384r1Sha384,
+            x if x == RsaPkcs1Sha256 as u32 => RsaPkcs1Sha256,
+            x if x == RsaPssRsaeSha256 as u32 => RsaPssRsaeSha256,
+            _ => return Err("invalid tls cert signature scheme".into()),
+        })
+    }
+
+    /// The keypair algorithm this scheme signs with.
+    pub fn alg(&self) -> TlsCertAlg {
+        use TlsCertSignatureScheme::*;
+        match self {
+            Ed25519 => TlsCertAlg::PkcsEd25519,
+            EcdsaSecp256r1Sha256 => TlsCertAlg::PkcsEcdsaP256Sha256,
+            EcdsaSecp384r1Sha384 => TlsCertAlg::PkcsEcdsaP384Sha384,
+            RsaPkcs1Sha256 | RsaPssRsaeSha256 => TlsCertAlg::PkcsRsaSha256,
+        }
+    }
+}
+
 /// Configuration for Tls Certificate Generation.
 #[non_exhaustive]
 pub struct TlsCertOptions {
     /// Tls keypair algorithm to use.
     pub alg: TlsCertAlg,
+
+    /// The sni (a DNS hostname) to build into the certificate,
+    /// and find it by. A random sni is generated if `None`.
+    pub sni: Option<String>,
+
+    /// Additional subject alt names, DNS hostnames or IP addresses.
+    pub alt_names: Vec<String>,
+
+    /// Digest algorithm producing the cert digest.
+    pub digest_alg: TlsCertDigestAlg,
+
+    /// Whether the private key can leave the keystore.
+    /// Keys of certs created with `false` are refused by the
+    /// `tls_cert_get_priv_key_by_*` getters and pkcs12 export,
+    /// they can only sign via `tls_cert_sign_by_index`.
+    /// `None` leaves it to the server's `exportable_by_default`
+    /// (see `Config::get_exportable_by_default()`), exportable outside
+    /// of a server, and keeps it as it was for renewed certs.
+    pub exportable: Option<bool>,
 }
 
 impl Default for TlsCertOptions {
     fn default() -> Self {
         Self {
             alg: TlsCertAlg::PkcsEd25519,
+            sni: None,
+            alt_names: Vec::new(),
+            digest_alg: TlsCertDigestAlg::Blake2b256,
+            exportable: None,
         }
     }
 }
@@ -75,6 +285,7 @@ impl Default for TlsCertOptions {
     Clone,
     Copy,
     Debug,
+    Default,
     Display,
     PartialEq,
     Eq,
@@ -88,28 +299,34 @@ impl Default for TlsCertOptions {
 pub struct KeystoreIndex(pub u32);
 
 /// Der encoded Tls Certificate bytes.
+/// Received over ipc, this is a slice of the received frame.
 #[derive(
     Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
 )]
-#[allow(clippy::rc_buffer)]
-pub struct Cert(pub Arc<Vec<u8>>);
+pub struct Cert(pub bytes::Bytes);
 
 impl From<Vec<u8>> for Cert {
     fn from(d: Vec<u8>) -> Self {
-        Self(Arc::new(d))
+        Self(d.into())
+    }
+}
+
+impl From<Arc<Vec<u8>>> for Cert {
+    #[allow(clippy::rc_buffer)]
+    fn from(d: Arc<Vec<u8>>) -> Self {
+        // no copy unless the arc is shared
+        Self(Arc::try_unwrap(d).unwrap_or_else(|d| (*d).clone()).into())
     }
 }
 
 /// Der encoded pkcs #8 Tls Certificate private key bytes.
-#[derive(
-    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
-)]
-#[allow(clippy::rc_buffer)]
-pub struct CertPrivKey(pub Arc<Vec<u8>>);
+/// See `TlsCertAlg` for the key each algorithm wraps.
+#[derive(Clone, Debug, PartialEq, Eq, Deref, From, Into)]
+pub struct CertPrivKey(pub internal::util::SecretBytes);
 
 impl From<Vec<u8>> for CertPrivKey {
     fn from(d: Vec<u8>) -> Self {
-        Self(Arc::new(d))
+        Self(d.into())
     }
 }
 
@@ -126,13 +343,14 @@ impl From<String> for CertSni {
     }
 }
 
-/// The 32 byte blake2b digest of given Tls Certificate.
-#[derive(
-    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
-)]
+/// The 32 byte digest of given Tls Certificate,
+/// see `TlsCertDigestAlg`.
+#[derive(Clone, Debug, PartialOrd, Ord, Deref, From, Into)]
 #[allow(clippy::rc_buffer)]
 pub struct CertDigest(pub Arc<Vec<u8>>);
 
+internal::util::impl_ct_eq!(CertDigest);
+
 impl From<Vec<u8>> for CertDigest {
     fn from(d: Vec<u8>) -> Self {
         Self(Arc::new(d))
@@ -142,11 +360,15 @@ impl From<Vec<u8>> for CertDigest {
 /// The entry type for a given entry.
 #[non_exhaustive]
 #[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub enum LairEntryType {
-    /// This entry index was deleted or corrupted.
+    /// This entry index does not exist or is corrupted.
+    #[default]
     Invalid = 0x00000000,
 
+    /// This entry index was explicitly deleted, see `lair_delete_entry`.
+    Deleted = 0x00000001,
+
     /// Tls Certificate & private key.
     TlsCert = 0x00000100,
 
@@ -155,12 +377,19 @@ pub enum LairEntryType {
 
     /// X25519 ECDH keypair.
     X25519 = 0x00000300,
-}
 
-impl Default for LairEntryType {
-    fn default() -> Self {
-        Self::Invalid
-    }
+    /// Master seed for deterministic keypair derivation.
+    Seed = 0x00000400,
+
+    /// Secp256k1 ECDSA signature keypair.
+    SignSecp256k1 = 0x00000500,
+
+    /// Bls12-381 aggregatable signature keypair.
+    #[cfg(feature = "bls")]
+    SignBls12381 = 0x00000600,
+
+    /// XChaCha20-Poly1305 symmetric secret key.
+    SecretKey = 0x00000700,
 }
 
 impl LairEntryType {
@@ -169,14 +398,50 @@ impl LairEntryType {
         use LairEntryType::*;
         Ok(match d {
             x if x == Invalid as u32 => Invalid,
+            x if x == Deleted as u32 => Deleted,
             x if x == TlsCert as u32 => TlsCert,
             x if x == SignEd25519 as u32 => SignEd25519,
             x if x == X25519 as u32 => X25519,
+            x if x == Seed as u32 => Seed,
+            x if x == SignSecp256k1 as u32 => SignSecp256k1,
+            #[cfg(feature = "bls")]
+            x if x == SignBls12381 as u32 => SignBls12381,
+            x if x == SecretKey as u32 => SecretKey,
             _ => return Err("invalide lair entry type".into()),
         })
     }
 }
 
+/// Whether the entries of the keystore are sealed at rest,
+/// and if they are, whether the keystore is unlocked.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LairLockState {
+    /// The keystore has no unlock passphrase, its entries are not sealed.
+    #[default]
+    NoPassphrase = 0x00000000,
+
+    /// Entries cannot be used until the keystore is unlocked.
+    Locked = 0x00000002,
+
+    /// The keystore entries are sealed, and the keystore is unlocked.
+    Unlocked = 0x00000003,
+}
+
+impl LairLockState {
+    /// parse a u32 into a LairLockState enum variant.
+    pub fn parse(d: u32) -> LairResult<Self> {
+        use LairLockState::*;
+        Ok(match d {
+            x if x == NoPassphrase as u32 => NoPassphrase,
+            x if x == Locked as u32 => Locked,
+            x if x == Unlocked as u32 => Unlocked,
+            _ => return Err("invalid lair lock state".into()),
+        })
+    }
+}
+
 /// Get information about the server we are connected to.
 #[non_exhaustive]
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -186,6 +451,181 @@ pub struct LairServerInfo {
 
     /// Server version.
     pub version: String,
+
+    /// The number of keystore entries found corrupt, failing their
+    /// integrity check as the store was loaded or unlocked.
+    pub corrupt_entry_count: u64,
+
+    /// The number of connections open to the server.
+    pub connection_count: u64,
+
+    /// The number of requests the server is handling, over all
+    /// connections.
+    pub requests_in_flight: u64,
+
+    /// The number of unlock attempts with a wrong passphrase.
+    pub failed_unlock_count: u64,
+
+    /// Whether the memory holding unlocked secrets is mlocked, kept off
+    /// swap. False if disabled by config, or once mlocking failed,
+    /// e.g. for a memlock limit too low.
+    pub secrets_mlocked: bool,
+
+    /// Whether the server process has disabled its core dumps.
+    pub core_dumps_disabled: bool,
+
+    /// Whether the server process is marked not dumpable, so not
+    /// ptraceable by non-root processes (linux only).
+    pub ptrace_disabled: bool,
+
+    /// Whether the server process has set a restrictive umask.
+    pub umask_restricted: bool,
+
+    /// The threads of the pool the server does its crypto work on
+    /// (see `ConfigBuilder::set_crypto_threads()`).
+    pub crypto_threads: u64,
+
+    /// The crypto tasks waiting for a thread of the pool.
+    pub crypto_queue_depth: u64,
+
+    /// The threads of the crypto pool working on a task.
+    pub crypto_busy_threads: u64,
+}
+
+/// The outcome of a `lair_compact_store` call.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LairCompactInfo {
+    /// The number of deleted entries dropped from the store.
+    pub entries_removed: u64,
+
+    /// The number of bytes the store shrank by.
+    pub bytes_reclaimed: u64,
+}
+
+/// Summary of a single keystore entry, as returned by `lair_list_entries`.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EntryListItem {
+    /// Keystore index of this entry.
+    pub keystore_index: KeystoreIndex,
+
+    /// The entry type.
+    pub entry_type: LairEntryType,
+
+    /// The 32 byte public identifier of this entry:
+    /// the pub key for keypairs, the cert digest for tls certs.
+    #[allow(clippy::rc_buffer)]
+    pub pub_id: Arc<Vec<u8>>,
+
+    /// Sni of tls cert entries, None for keypairs.
+    pub cert_sni: Option<CertSni>,
+
+    /// Digest algorithm of tls cert entries, None for keypairs.
+    pub cert_digest_alg: Option<TlsCertDigestAlg>,
+
+    /// The entry tag, if it was created with one.
+    pub tag: Option<String>,
+
+    /// Creation time in unix epoch milliseconds,
+    /// None for entries created by older lair versions.
+    pub created_at: Option<u64>,
+}
+
+/// The public material of a keystore entry,
+/// as returned by `lair_export_entry_public`.
+/// No variant has a field for private key material, so these records
+/// are safe to write out, log, or hand to other tools.
+/// Byte fields serialize as standard base64 strings.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "entry_type", rename_all = "snake_case")]
+pub enum EntryPublic {
+    /// An ed25519 signature keypair.
+    SignEd25519 {
+        /// The 32 byte signature pub key.
+        #[serde(with = "serde_base64")]
+        pub_key: Vec<u8>,
+    },
+
+    /// An x25519 keypair.
+    X25519 {
+        /// The 32 byte x25519 pub key.
+        #[serde(with = "serde_base64")]
+        pub_key: Vec<u8>,
+    },
+
+    /// A secp256k1 signature keypair.
+    SignSecp256k1 {
+        /// The 33 byte SEC1 compressed pub key.
+        #[serde(with = "serde_base64")]
+        pub_key: Vec<u8>,
+    },
+
+    /// A bls12-381 signature keypair.
+    #[cfg(feature = "bls")]
+    SignBls12381 {
+        /// The 48 byte compressed pub key.
+        #[serde(with = "serde_base64")]
+        pub_key: Vec<u8>,
+    },
+
+    /// A master seed. Only its public identifier is exported.
+    Seed {
+        /// The 32 byte seed id.
+        #[serde(with = "serde_base64")]
+        seed_id: Vec<u8>,
+    },
+
+    /// A symmetric secret key. Only its public tag is exported.
+    SecretKey {
+        /// The 32 byte secret key tag.
+        #[serde(with = "serde_base64")]
+        tag: Vec<u8>,
+    },
+
+    /// A tls certificate.
+    TlsCert {
+        /// The sni built into the certificate.
+        sni: String,
+
+        /// The 32 byte certificate digest.
+        #[serde(with = "serde_base64")]
+        cert_digest: Vec<u8>,
+
+        /// The algorithm of the certificate digest.
+        digest_alg: TlsCertDigestAlg,
+
+        /// The DER encoded certificate.
+        #[serde(with = "serde_base64")]
+        cert_der: Vec<u8>,
+
+        /// End of the certificate validity period,
+        /// in unix epoch milliseconds.
+        not_after: u64,
+
+        /// The algorithm of the certificate keypair.
+        alg: TlsCertAlg,
+    },
+}
+
+mod serde_base64 {
+    use serde::Deserialize;
+
+    pub fn serialize<S>(b: &[u8], s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        s.serialize_str(&base64::encode(b))
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        base64::decode(String::deserialize(d)?)
+            .map_err(serde::de::Error::custom)
+    }
 }
 
 ghost_actor::ghost_chan! {
@@ -194,6 +634,33 @@ ghost_actor::ghost_chan! {
         /// Get lair server info.
         fn lair_get_server_info() -> LairServerInfo;
 
+        /// Have the server echo `nonce` back, checking the connection
+        /// is alive. Answered without touching the store.
+        fn lair_ping(nonce: u64) -> u64;
+
+        /// Receive `LairClientEvent::EntryCreated` and `EntryDeleted`
+        /// as any client creates or deletes entries from now on, rather
+        /// than polling. Clients subscribe again as they reconnect.
+        fn lair_subscribe_store_events() -> ();
+
+        /// Get whether the keystore is locked. Clients connecting
+        /// learn of later changes from `LairClientEvent::Locked` and
+        /// `LairClientEvent::Unlocked`.
+        fn lair_get_lock_state() -> LairLockState;
+
+        /// Be sent `LairClientEvent::RequestUnlockPassphrase`: right away
+        /// if the keystore is locked, the passphrase given is used to
+        /// unlock it. Clients that did not register are never asked.
+        /// Clients register again as they reconnect.
+        fn lair_register_passphrase_provider() -> ();
+
+        /// Be sent `LairClientEvent::RequestSigningApproval` for every
+        /// signature with a key that requires approval. While no approver
+        /// is connected such signatures fail with
+        /// `LairError::NoSigningApprover`. Clients register again as they
+        /// reconnect.
+        fn lair_register_signing_approver() -> ();
+
         /// Get the highest entry index.
         /// Note, some entries my be stubs / erased values.
         fn lair_get_last_entry_index() -> KeystoreIndex;
@@ -203,16 +670,160 @@ ghost_actor::ghost_chan! {
             keystore_index: KeystoreIndex,
         ) -> LairEntryType;
 
+        /// List all valid entries in the keystore, in index order.
+        fn lair_list_entries() -> Vec<EntryListItem>;
+
+        /// Look up the index and type of the entry with the given tag.
+        fn lair_get_entry_by_tag(
+            tag: String,
+        ) -> (KeystoreIndex, LairEntryType);
+
+        /// Get the metadata (tag, creation time, usage) of an entry.
+        /// Signing, crypto_box / crypto_box_open, and fetching a tls
+        /// private key count as uses, reads of public material do not.
+        /// Usage is written to disk lazily, in batches: after a crash,
+        /// the last few seconds of usage may be lost.
+        fn lair_get_entry_meta(
+            keystore_index: KeystoreIndex,
+        ) -> entry::EntryMeta;
+
+        /// Find the entry matching `id`, which may be an ed25519 or x25519
+        /// pub key, a secp256k1 pub id (see `SignSecp256k1PubKey::pub_id`),
+        /// a seed id, a tls cert digest, or a tls cert sni.
+        /// Resolves to None (rather than an error) if nothing matches.
+        #[allow(clippy::rc_buffer)]
+        fn lair_find_entry(
+            id: Arc<Vec<u8>>,
+        ) -> Option<(KeystoreIndex, LairEntryType)>;
+
+        /// Tag an existing entry, replacing any previous tag,
+        /// which stops resolving immediately.
+        /// Fails if the new tag is already in use by another entry.
+        fn lair_update_entry_tag(
+            keystore_index: KeystoreIndex,
+            tag: String,
+        ) -> ();
+
+        /// Limit the signatures per second made with the key of an entry,
+        /// `None` for the server's default (see
+        /// `ConfigBuilder::set_entry_rate_limit()`). Requests over the
+        /// limit wait or fail with `LairError::RateLimited`.
+        fn lair_set_entry_rate_limit(
+            keystore_index: KeystoreIndex,
+            rate_limit: Option<u32>,
+        ) -> ();
+
+        /// Export the public material of an entry as a serializable record.
+        /// Does not count as a use of the entry.
+        fn lair_export_entry_public(
+            keystore_index: KeystoreIndex,
+        ) -> EntryPublic;
+
+        /// Delete an entry, erasing its key material.
+        /// The index is never reused: afterwards `lair_get_entry_type`
+        /// reports it as `Deleted`, and it can no longer be used or
+        /// looked up by pub key / cert digest / sni.
+        fn lair_delete_entry(keystore_index: KeystoreIndex) -> ();
+
+        /// Rewrite the store without its deleted entries, reclaiming
+        /// their space. The index of every remaining entry is unchanged,
+        /// and deleted indices are still never reused. The rewritten
+        /// store is swapped in atomically, writes to the store wait
+        /// until it is done.
+        fn lair_compact_store() -> LairCompactInfo;
+
+        /// Gracefully shut down the lair server.
+        /// The server stops accepting connections, flushes the store,
+        /// removes its socket file, and exits after acknowledging.
+        fn lair_shutdown() -> ();
+
+        /// Lock the store, forgetting its key, like `auto_lock_after_idle`
+        /// does once it goes unused. Until it is unlocked again, requests
+        /// using entries fail with `LairError::StoreLocked`.
+        /// Locking a locked store succeeds, a store that does not seal
+        /// its entries cannot be locked.
+        fn lair_lock() -> ();
+
+        /// Unlock the store with its passphrase. Attempts are made one
+        /// at a time, wrong passphrases are counted (see
+        /// `LairServerInfo::failed_unlock_count`) and fail after a delay,
+        /// longer with every wrong passphrase in a row.
+        fn lair_unlock(passphrase: SecretBytes) -> ();
+
+        /// Replace the store unlock passphrase.
+        /// Fails (leaving the passphrase unchanged)
+        /// if `old_passphrase` is incorrect.
+        fn lair_change_unlock_passphrase(
+            old_passphrase: String,
+            new_passphrase: String,
+        ) -> ();
+
         /// Create a new self-signed tls certificate.
         fn tls_cert_new_self_signed_from_entropy(
             options: TlsCertOptions,
         ) -> (KeystoreIndex, CertSni, CertDigest);
 
+        /// Create a new self-signed tls certificate,
+        /// tagged for later lookup by `lair_get_entry_by_tag`.
+        /// Fails if the tag is already in use.
+        fn tls_cert_new_self_signed_tagged(
+            options: TlsCertOptions,
+            tag: String,
+        ) -> (KeystoreIndex, CertSni, CertDigest);
+
+        /// Issue a new self-signed certificate for the private key of
+        /// the tls cert at `keystore_index`, valid from now, as a new
+        /// entry recording the old index as its `renewed_from`.
+        /// The sni is kept unless `options.sni` is set, and the key
+        /// algorithm is kept regardless of `options.alg`.
+        /// The old entry stays resolvable by index and digest,
+        /// lookup by sni resolves the newest cert.
+        fn tls_cert_renew(
+            keystore_index: KeystoreIndex,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, CertSni, CertDigest);
+
+        /// Import an externally issued certificate and its pkcs8
+        /// private key, both DER encoded, as a tls cert entry.
+        /// The sni is the first (non-wildcard) dns name of the cert
+        /// unless `options.sni` is set, which must be covered by one of
+        /// the cert's names. `options.alt_names` must be empty.
+        /// The cert and key must fit in a keystore entry.
+        fn tls_cert_import(
+            cert_der: Cert,
+            priv_key_der: CertPrivKey,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, CertSni, CertDigest);
+
+        /// Create a new self-signed CA certificate, as a tls cert entry
+        /// for issuing certs with `tls_cert_new_signed_by_ca`.
+        /// The CA cert itself is retrieved like any other tls cert.
+        fn tls_ca_new_self_signed(
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, CertSni, CertDigest);
+
+        /// Create a new tls certificate, for a new keypair, signed by the
+        /// CA cert at `ca_index`. Also returns the chain to present:
+        /// the new cert followed by the CA cert.
+        /// Issued certs are independent entries, they stay retrievable
+        /// after the CA entry is deleted.
+        fn tls_cert_new_signed_by_ca(
+            ca_index: KeystoreIndex,
+            options: TlsCertOptions,
+        ) -> (KeystoreIndex, CertSni, CertDigest, Vec<Cert>);
+
         /// Get tls cert info by keystore index.
         fn tls_cert_get(
             keystore_index: KeystoreIndex,
         ) -> (CertSni, CertDigest);
 
+        /// Find the tls cert whose digest starts with the given bytes,
+        /// e.g. a fingerprint copied from other tools, cut short.
+        /// Fails listing the candidates if more than one cert matches.
+        fn tls_cert_find_by_digest_prefix(
+            prefix: Arc<Vec<u8>>,
+        ) -> (KeystoreIndex, CertSni, CertDigest);
+
         /// Fetch the certificate by entry index.
         fn tls_cert_get_cert_by_index(
             keystore_index: KeystoreIndex,
@@ -243,10 +854,51 @@ ghost_actor::ghost_chan! {
             cert_sni: CertSni,
         ) -> CertPrivKey;
 
+        /// Sign a tls handshake `message` with the private key of the
+        /// tls cert at `keystore_index`, which never leaves the keystore.
+        /// The `scheme` must be one for the cert keypair algorithm.
+        fn tls_cert_sign_by_index(
+            keystore_index: KeystoreIndex,
+            scheme: TlsCertSignatureScheme,
+            message: Arc<Vec<u8>>,
+        ) -> Arc<Vec<u8>>;
+
+        /// Export the certificate and private key by entry index as a
+        /// DER encoded PKCS#12 (.p12) bundle, the key encrypted with
+        /// `password`. Without a password nothing is encrypted.
+        fn tls_cert_export_pkcs12(
+            keystore_index: KeystoreIndex,
+            password: Option<String>,
+        ) -> Arc<Vec<u8>>;
+
         /// Create a new signature ed25519 keypair from entropy.
         fn sign_ed25519_new_from_entropy(
         ) -> (KeystoreIndex, sign_ed25519::SignEd25519PubKey);
 
+        /// Create a new signature ed25519 keypair from entropy,
+        /// tagged for later lookup by `lair_get_entry_by_tag`.
+        /// Fails if the tag is already in use.
+        fn sign_ed25519_new_from_entropy_tagged(
+            tag: String,
+        ) -> (KeystoreIndex, sign_ed25519::SignEd25519PubKey);
+
+        /// Create a new signature ed25519 keypair from entropy, every
+        /// signature with which must be approved by a signing approver
+        /// (see `lair_register_signing_approver`), e.g. for admin keys.
+        /// Signatures that are denied or not approved in time fail with
+        /// `LairError::SigningDenied` / `SigningApprovalTimeout`.
+        fn sign_ed25519_new_from_entropy_requiring_approval(
+        ) -> (KeystoreIndex, sign_ed25519::SignEd25519PubKey);
+
+        /// Import a signature ed25519 keypair derived from an existing
+        /// 32 byte seed. Fails with `LairError::SignEd25519SeedLength`
+        /// for seeds of any other length, and with
+        /// `LairError::DuplicatePubKey` if the keypair is already present.
+        #[allow(clippy::rc_buffer)]
+        fn sign_ed25519_new_from_seed(
+            seed: Arc<Vec<u8>>,
+        ) -> (KeystoreIndex, sign_ed25519::SignEd25519PubKey);
+
         /// Get ed25519 keypair info by keystore index.
         fn sign_ed25519_get(
             keystore_index: KeystoreIndex,
@@ -255,23 +907,210 @@ ghost_actor::ghost_chan! {
         /// Generate a signature for message by keystore index.
         fn sign_ed25519_sign_by_index(
             keystore_index: KeystoreIndex,
-            message: Arc<Vec<u8>>,
+            message: bytes::Bytes,
+        ) -> sign_ed25519::SignEd25519Signature;
+
+        /// Generate signatures for a batch of messages by keystore index,
+        /// returned in message order. Much cheaper than one request per
+        /// message. The whole batch counts as a single use of the entry.
+        fn sign_ed25519_sign_batch_by_index(
+            keystore_index: KeystoreIndex,
+            messages: Vec<bytes::Bytes>,
+        ) -> Vec<sign_ed25519::SignEd25519Signature>;
+
+        /// Generate signatures for a batch of messages by signature
+        /// pub key, see `sign_ed25519_sign_batch_by_index`.
+        fn sign_ed25519_sign_batch_by_pub_key(
+            pub_key: sign_ed25519::SignEd25519PubKey,
+            messages: Vec<bytes::Bytes>,
+        ) -> Vec<sign_ed25519::SignEd25519Signature>;
+
+        /// Sign a large payload without shipping it to lair: the client
+        /// computes the 32 byte blake2b prehash digest locally
+        /// (see `sign_ed25519::SignEd25519Prehasher`), lair signs it
+        /// under a dedicated domain prefix. Verify the result with
+        /// `SignEd25519PubKey::verify_prehashed`, it is never a valid
+        /// direct signature.
+        fn sign_ed25519_sign_prehashed(
+            keystore_index: KeystoreIndex,
+            digest: [u8; 32],
         ) -> sign_ed25519::SignEd25519Signature;
 
+        /// Verify an ed25519 signature on the server, so clients don't
+        /// need a crypto library of their own. The pub key does not need
+        /// to be in the keystore. Malformed pub keys or signatures
+        /// resolve to false.
+        fn sign_ed25519_verify(
+            pub_key: sign_ed25519::SignEd25519PubKey,
+            message: bytes::Bytes,
+            signature: sign_ed25519::SignEd25519Signature,
+        ) -> bool;
+
         /// Generate a signature for message by signature pub key.
         fn sign_ed25519_sign_by_pub_key(
             pub_key: sign_ed25519::SignEd25519PubKey,
-            message: Arc<Vec<u8>>,
+            message: bytes::Bytes,
+        ) -> sign_ed25519::SignEd25519Signature;
+
+        /// Generate a signature for message by keystore index, domain
+        /// separated by `context` (at most 255 bytes), so signatures of
+        /// one kind of structure can't be replayed as another. Verify
+        /// with `SignEd25519PubKey::verify_with_context`, it is never
+        /// valid without the context, or under a different one.
+        #[allow(clippy::rc_buffer)]
+        fn sign_ed25519_sign_by_index_with_context(
+            keystore_index: KeystoreIndex,
+            context: Arc<Vec<u8>>,
+            message: bytes::Bytes,
         ) -> sign_ed25519::SignEd25519Signature;
 
+        /// Generate a signature for message by signature pub key,
+        /// see `sign_ed25519_sign_by_index_with_context`.
+        #[allow(clippy::rc_buffer)]
+        fn sign_ed25519_sign_by_pub_key_with_context(
+            pub_key: sign_ed25519::SignEd25519PubKey,
+            context: Arc<Vec<u8>>,
+            message: bytes::Bytes,
+        ) -> sign_ed25519::SignEd25519Signature;
+
+        /// Generate a signature for message by entry tag.
+        fn sign_ed25519_sign_by_tag(
+            tag: String,
+            message: bytes::Bytes,
+        ) -> sign_ed25519::SignEd25519Signature;
+
+        /// List the pub keys of all signature keypairs, in index order.
+        /// One round trip, rather than one `sign_ed25519_get` per entry.
+        fn sign_ed25519_list_pub_keys(
+        ) -> Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>;
+
+        /// Create a new secp256k1 ECDSA signature keypair from entropy,
+        /// e.g. for signing ethereum transactions.
+        fn secp256k1_new_from_entropy(
+        ) -> (KeystoreIndex, sign_secp256k1::SignSecp256k1PubKey);
+
+        /// Get secp256k1 keypair info by keystore index.
+        fn secp256k1_get(
+            keystore_index: KeystoreIndex,
+        ) -> sign_secp256k1::SignSecp256k1PubKey;
+
+        /// Generate a recoverable signature over a caller provided 32 byte
+        /// digest (e.g. keccak256 for ethereum) by keystore index.
+        /// See `sign_secp256k1::SignSecp256k1Signature` for the layout.
+        fn secp256k1_sign_by_index(
+            keystore_index: KeystoreIndex,
+            digest: [u8; 32],
+        ) -> sign_secp256k1::SignSecp256k1Signature;
+
+        /// Generate a recoverable signature over a 32 byte digest
+        /// by secp256k1 pub key, see `secp256k1_sign_by_index`.
+        fn secp256k1_sign_by_pub_key(
+            pub_key: sign_secp256k1::SignSecp256k1PubKey,
+            digest: [u8; 32],
+        ) -> sign_secp256k1::SignSecp256k1Signature;
+
+        /// Create a new bls12-381 signature keypair from entropy, for
+        /// signatures that can be aggregated, see
+        /// `sign_bls12381::sign_bls12381_aggregate`.
+        /// Needs a lair server built with the `bls` feature.
+        fn bls_new_from_entropy(
+        ) -> (KeystoreIndex, sign_bls12381::SignBls12381PubKey);
+
+        /// Generate a bls12-381 signature over a message by pub key.
+        /// Needs a lair server built with the `bls` feature.
+        fn bls_sign_by_pub_key(
+            pub_key: sign_bls12381::SignBls12381PubKey,
+            message: Arc<Vec<u8>>,
+        ) -> sign_bls12381::SignBls12381Signature;
+
+        /// Create a new random master seed, from which signature keypairs
+        /// can be derived with `sign_ed25519_derive_from_seed`.
+        /// The seed is identified by its public seed id. It never leaves
+        /// lair, unless created `exportable`, see `seed_export_mnemonic`.
+        fn seed_new_from_entropy(
+            exportable: bool,
+        ) -> (KeystoreIndex, seed::SeedId);
+
+        /// Export an exportable master seed as a 24 word BIP39 mnemonic
+        /// (lowercase, space separated), for offline backup. Fails for
+        /// seeds not created exportable. Counts as a use of the seed.
+        fn seed_export_mnemonic(seed_index: KeystoreIndex) -> String;
+
+        /// Import a master seed from a 24 word BIP39 mnemonic
+        /// (case insensitive), as exported by `seed_export_mnemonic`.
+        /// The imported seed derives the same keypairs as the original.
+        /// Fails with `LairError::DuplicatePubKey` if the seed is
+        /// already present.
+        fn seed_import_mnemonic(
+            mnemonic: String,
+            exportable: bool,
+        ) -> (KeystoreIndex, seed::SeedId);
+
+        /// Deterministically derive the signature ed25519 keypair at
+        /// `derivation_path` from the master seed at `seed_index`,
+        /// per SLIP-0010 with every level hardened: path indexes must be
+        /// below `seed::HARDENED_BIT`, and there can be at most
+        /// `seed::MAX_DERIVATION_DEPTH` of them. The keypair is stored as
+        /// a regular signature entry, with the seed and path recorded in
+        /// its metadata. Deriving an existing path resolves to the
+        /// existing entry.
+        fn sign_ed25519_derive_from_seed(
+            seed_index: KeystoreIndex,
+            derivation_path: Vec<u32>,
+        ) -> (KeystoreIndex, sign_ed25519::SignEd25519PubKey);
+
+        /// Convert the ed25519 signature keypair at `keystore_index` to
+        /// the x25519 keypair of the same identity (as libsodium's
+        /// `crypto_sign_ed25519_sk_to_curve25519`), and store it,
+        /// recording its source in its metadata. Idempotent, converting
+        /// the same keypair again resolves to the existing x25519 entry.
+        /// Peers can convert the ed25519 pub key themselves with
+        /// `x25519::x25519_pub_key_from_sign_ed25519`.
+        fn sign_ed25519_to_x25519(
+            keystore_index: KeystoreIndex,
+        ) -> (KeystoreIndex, x25519::X25519PubKey);
+
         /// Generate new x25519 keypair from entropy.
         fn x25519_new_from_entropy() -> (KeystoreIndex, x25519::X25519PubKey);
 
+        /// Import an x25519 keypair from an existing 32 byte private key,
+        /// e.g. when migrating encryption keys from a libsodium based store.
+        /// The private key is clamped per RFC 7748 before it is stored.
+        /// Fails with `LairError::X25519PrivKeyImportLength` for keys of any
+        /// other length, and with `LairError::DuplicatePubKey` if the
+        /// keypair is already present.
+        #[allow(clippy::rc_buffer)]
+        fn x25519_new_from_priv_key(
+            priv_key: Arc<Vec<u8>>,
+        ) -> (KeystoreIndex, x25519::X25519PubKey);
+
         /// Get x25519 keypair by keystore index.
         fn x25519_get(
             keystore_index: KeystoreIndex,
         ) -> x25519::X25519PubKey;
 
+        /// List the pub keys of all x25519 keypairs, in index order.
+        /// One round trip, rather than one `x25519_get` per entry.
+        fn x25519_list_pub_keys(
+        ) -> Vec<(KeystoreIndex, x25519::X25519PubKey)>;
+
+        /// Derive `(rx, tx)` session keys between the x25519 keypair at
+        /// keystore index and the holder of `remote_pub`, exactly as
+        /// libsodium's `crypto_kx_client_session_keys` (or
+        /// `crypto_kx_server_session_keys` if `is_server`).
+        /// Each side receives with its rx key what the other side
+        /// transmits with its tx key.
+        ///
+        /// Unlike every other key lair holds, the session keys are
+        /// returned to the caller: they are ephemeral, derived per
+        /// session, and reveal nothing about the private key. Callers are
+        /// responsible for zeroizing them once the session ends.
+        fn kx_session_keys_by_index(
+            keystore_index: KeystoreIndex,
+            remote_pub: x25519::X25519PubKey,
+            is_server: bool,
+        ) -> (kx::KxSessionKey, kx::KxSessionKey);
+
         /// Generate encrypted crypto box data by sender keystore index for recipient pubkey.
         fn crypto_box_by_index(
             keystore_index: KeystoreIndex,
@@ -299,6 +1138,145 @@ ghost_actor::ghost_chan! {
             sender: x25519::X25519PubKey,
             encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>
         ) -> Option<crypto_box::CryptoBoxData>;
+
+        /// As `crypto_box_by_index`, additionally authenticating `aad` so
+        /// the box only opens given the same aad (see
+        /// `crypto_box::crypto_box_with_aad`). Not interchangeable with
+        /// boxes from `crypto_box_by_index`.
+        #[allow(clippy::rc_buffer)]
+        fn crypto_box_by_index_with_aad(
+            keystore_index: KeystoreIndex,
+            recipient: x25519::X25519PubKey,
+            aad: Arc<Vec<u8>>,
+            data: Arc<crypto_box::CryptoBoxData>,
+        ) -> crypto_box::CryptoBoxEncryptedData;
+
+        /// As `crypto_box_by_pub_key`, additionally authenticating `aad`.
+        #[allow(clippy::rc_buffer)]
+        fn crypto_box_by_pub_key_with_aad(
+            pub_key: x25519::X25519PubKey,
+            recipient: x25519::X25519PubKey,
+            aad: Arc<Vec<u8>>,
+            data: Arc<crypto_box::CryptoBoxData>,
+        ) -> crypto_box::CryptoBoxEncryptedData;
+
+        /// Open a box made by `crypto_box_by_index_with_aad` (or
+        /// `crypto_box_by_pub_key_with_aad`). `None` if the aad differs
+        /// from the one it was boxed with.
+        #[allow(clippy::rc_buffer)]
+        fn crypto_box_open_by_index_with_aad(
+            keystore_index: KeystoreIndex,
+            sender: x25519::X25519PubKey,
+            aad: Arc<Vec<u8>>,
+            encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+        ) -> Option<crypto_box::CryptoBoxData>;
+
+        /// As `crypto_box_open_by_index_with_aad`, by recipient pubkey.
+        #[allow(clippy::rc_buffer)]
+        fn crypto_box_open_by_pub_key_with_aad(
+            pub_key: x25519::X25519PubKey,
+            sender: x25519::X25519PubKey,
+            aad: Arc<Vec<u8>>,
+            encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+        ) -> Option<crypto_box::CryptoBoxData>;
+
+        /// Open a sealed box (see `crypto_box::crypto_box_seal`, libsodium's
+        /// `crypto_box_seal`) sealed to the keypair at recipient keystore
+        /// index. `None` if it was not sealed to this keypair.
+        fn crypto_box_seal_open_by_index(
+            keystore_index: KeystoreIndex,
+            sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+        ) -> Option<crypto_box::CryptoBoxData>;
+
+        /// Open a sealed box sealed to the recipient pubkey,
+        /// see `crypto_box_seal_open_by_index`.
+        fn crypto_box_seal_open_by_pub_key(
+            pub_key: x25519::X25519PubKey,
+            sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+        ) -> Option<crypto_box::CryptoBoxData>;
+
+        /// Generate a new random xchacha20-poly1305 secret key, for
+        /// symmetric encryption where the key never leaves lair.
+        fn secret_new_from_entropy(
+        ) -> (KeystoreIndex, secret_key::SecretKeyTag);
+
+        /// Encrypt data with the secret key at keystore index, under a
+        /// fresh random nonce. The `aad` is authenticated, not encrypted:
+        /// the same aad must be given to decrypt.
+        #[allow(clippy::rc_buffer)]
+        fn secret_encrypt_by_index(
+            keystore_index: KeystoreIndex,
+            aad: Arc<Vec<u8>>,
+            data: Arc<Vec<u8>>,
+        ) -> (secret_key::SecretNonce, Arc<Vec<u8>>);
+
+        /// Decrypt data encrypted by `secret_encrypt_by_index`.
+        /// `None` if the key, aad or nonce differ, or the ciphertext
+        /// has been tampered with.
+        #[allow(clippy::rc_buffer)]
+        fn secret_decrypt_by_index(
+            keystore_index: KeystoreIndex,
+            aad: Arc<Vec<u8>>,
+            nonce: secret_key::SecretNonce,
+            ciphertext: Arc<Vec<u8>>,
+        ) -> Option<Arc<Vec<u8>>>;
+
+        /// Derive the `subkey_id`th `subkey_len` byte subkey, in
+        /// `context`, of the secret key (or seed) at keystore index,
+        /// exactly as libsodium's `crypto_kdf_derive_from_key`. The same
+        /// inputs always derive the same subkey, so subkeys never need
+        /// storing. Unlike the key they are derived from, subkeys are
+        /// returned to the caller.
+        /// Fails with `LairError::SubkeyLength` unless `subkey_len` is
+        /// 16 to 64 bytes.
+        fn derive_subkey_by_index(
+            keystore_index: KeystoreIndex,
+            subkey_len: usize,
+            context: [u8; secret_key::SUBKEY_CONTEXT_BYTES],
+            subkey_id: u64,
+        ) -> Arc<Vec<u8>>;
+
+        /// Start a secretstream to push data to the holder of `remote_pub`
+        /// in chunks, keyed by the x25519 keypair at keystore index.
+        /// The header must reach the remote to pull the stream.
+        fn secretstream_init_push_by_index(
+            keystore_index: KeystoreIndex,
+            remote_pub: x25519::X25519PubKey,
+        ) -> (
+            secretstream::SecretstreamSessionId,
+            secretstream::SecretstreamHeader,
+        );
+
+        /// Encrypt the next chunk (at most 64 KiB) of a secretstream.
+        /// Pushing the final chunk closes the session.
+        #[allow(clippy::rc_buffer)]
+        fn secretstream_push(
+            session_id: secretstream::SecretstreamSessionId,
+            chunk: Arc<Vec<u8>>,
+            is_final: bool,
+        ) -> Arc<Vec<u8>>;
+
+        /// Start pulling a secretstream pushed by the holder of
+        /// `remote_pub`, keyed by the x25519 keypair at keystore index.
+        fn secretstream_init_pull_by_index(
+            keystore_index: KeystoreIndex,
+            remote_pub: x25519::X25519PubKey,
+            header: secretstream::SecretstreamHeader,
+        ) -> secretstream::SecretstreamSessionId;
+
+        /// Decrypt the next chunk of a secretstream, and whether it was
+        /// the final chunk, which closes the session.
+        /// `None` if the chunk is out of order or has been tampered with.
+        #[allow(clippy::rc_buffer)]
+        fn secretstream_pull(
+            session_id: secretstream::SecretstreamSessionId,
+            ciphertext: Arc<Vec<u8>>,
+        ) -> Option<(Arc<Vec<u8>>, bool)>;
+
+        /// Close a secretstream session before its final chunk.
+        fn secretstream_close(
+            session_id: secretstream::SecretstreamSessionId,
+        ) -> ();
     }
 }
 
@@ -1,36 +1,464 @@
-use std::{
-    path::{Path, PathBuf},
-    sync::Arc,
-};
+use crate::*;
+use std::path::{Path, PathBuf};
+
+/// How long clients wait for a response by default
+/// (see `ConfigBuilder::set_request_timeout()`).
+pub const DEFAULT_REQUEST_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(30);
+
+/// How often clients ping idle tcp connections by default
+/// (see `ConfigBuilder::set_ping_interval()`).
+pub const DEFAULT_PING_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(30);
+
+/// How many connections a server accepts at once by default
+/// (see `ConfigBuilder::set_max_connections()`).
+pub const DEFAULT_MAX_CONNECTIONS: usize = 128;
+
+/// How many requests of one connection are handled at once by default
+/// (see `ConfigBuilder::set_max_in_flight_requests()`).
+pub const DEFAULT_MAX_IN_FLIGHT_REQUESTS: usize = 64;
+
+/// How many times a passphrase provider is asked for the unlock passphrase
+/// by default (see `ConfigBuilder::set_unlock_passphrase_attempts()`).
+pub const DEFAULT_UNLOCK_PASSPHRASE_ATTEMPTS: u32 = 5;
+
+/// How long the server waits for a signing approver to answer by
+/// default (see `ConfigBuilder::set_signing_approval_timeout()`), less
+/// than the `DEFAULT_REQUEST_TIMEOUT` of the client asking to sign.
+pub const DEFAULT_SIGNING_APPROVAL_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(20);
+
+/// Size past which the audit log is rotated by default
+/// (see `ConfigBuilder::set_audit_log_max_bytes()`).
+pub const DEFAULT_AUDIT_LOG_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Mode the unix socket file is given by default
+/// (see `ConfigBuilder::set_socket_mode()`).
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
+
+/// Longest client name, in bytes (see `ConfigBuilder::set_client_name()`).
+pub const CLIENT_NAME_MAX_BYTES: usize = 64;
+
+/// Name of the optional config file read from the lair data directory.
+pub const CONFIG_FILE_NAME: &str = "lair-config.toml";
+
+/// Commented template of all config file options, with their defaults.
+pub const DEFAULT_CONFIG_TOML: &str = r#"# lair-keystore configuration
+#
+# Read from `lair-config.toml` in the lair data directory,
+# or from the file given by `--config` / `LAIR_CONFIG`.
+# Command line flags and environment variables override these values.
+# Relative paths are resolved against the lair data directory
+# (or, for `root_path`, against the directory of this file).
+
+# The lair data directory (overridden by `--lair-dir` / `LAIR_DIR`).
+# Defaults to a platform specific data dir.
+#root_path = "/path/to/lair"
+
+# A named profile (overridden by `--profile` / `LAIR_PROFILE`), with a
+# store, socket and pid file of its own in the lair data directory:
+# "store-<profile>", "socket-<profile>" and "pid-<profile>".
+# Paths set explicitly below are used as they are.
+#profile = "name"
+
+# Path to the ipc socket clients connect to.
+# Defaults to "socket", or "socket-read-only" for read-only servers.
+# On windows this is a named pipe, "\\.\pipe\<name>", by default
+# named after the lair data directory.
+#socket_path = "socket"
+
+# Unix only: the directory the socket is placed in, instead of the lair
+# data directory, e.g. "/run/lair" for a store on an encrypted volume.
+# Created by the server if missing. A relative `socket_path` is
+# resolved against it.
+#socket_dir = "/run/lair"
+
+# Unix only: the mode of the socket file, and the group (a name or a
+# gid) it is given. Members of the group may connect if its mode lets
+# them, and their gid is allowed (see `allowed_peer_gids`).
+#socket_mode = 0o600
+#socket_group = "lair"
+
+# Unix only: users (by uid) besides the server's own user that may
+# connect to the socket, and groups (by gid) whose members may. The
+# credentials of each peer are checked as it connects, others are
+# logged and disconnected.
+#allowed_peer_uids = []
+#allowed_peer_gids = []
+
+# Serve the store without ever writing to it (overridden by
+# `--read-only`): entries cannot be created, imported or deleted,
+# and usage counters are not recorded. No pid file is taken, so a
+# read-only server can run beside the one writing the store.
+#read_only = false
+
+# Keep the store in memory, writing no files at all (also selected by
+# `LAIR_DIR=:memory:`): everything is gone once the server shuts down.
+# Clients in the same process connect through an in-process channel,
+# unless `socket_path` is set, which is then bound as usual.
+#ephemeral = false
+
+# Also listen on this TCP address, "<ip>:<port>" or just a port (on
+# 127.0.0.1). Clients with the same setting connect over TCP instead of
+# the socket. TCP connections carry no filesystem permissions, so they
+# must present the connection token (see `require_token`), and anyone
+# able to read it can use the keystore: keep it on loopback unless the
+# network is trusted.
+#tcp_bind = "127.0.0.1:9876"
+
+# Listen on `tcp_bind` only, binding no socket.
+#tcp_only = false
+
+# Require clients to present the connection token, read from the
+# "token" file in the lair data directory (created by the server,
+# readable by its user only). Always required with `tcp_bind`.
+#require_token = false
+
+# What clients may do: "full" access, "sign-only" (use existing keys,
+# but create, export or delete nothing) or "read-only" (list entries
+# and get pub keys and certs). On a server, the most that connections
+# presenting the token file (or no token) are granted. On a client, the
+# scope it asks for, a server never grants more than it allows.
+#connection_scope = "full"
+
+# Further connection tokens, each granting a scope of its own.
+# Requires clients to present a token.
+#[scoped_tokens]
+#"<token>" = "sign-only"
+
+# Whether the server creates new tls certs with exportable private keys
+# when the client leaves it unset (`TlsCertOptions::exportable` of
+# `None`, as `lair-keystore generate tls-cert` does without flags). Set
+# false so that keys stay in the keystore unless created exportable.
+# The flag is fixed as the entry is created, see
+# `tls_cert_get_priv_key_by_*`.
+#exportable_by_default = true
+
+# Seconds clients wait for the response to a request before failing it
+# with a timeout, 0 waits forever.
+#request_timeout = 30
+
+# Seconds between the pings clients send over idle connections, 0 never
+# pings. Two unanswered pings mark the connection broken, and the client
+# reconnects. Defaults to 30 over tcp, and 0 over unix sockets and
+# named pipes.
+#ping_interval = 30
+
+# How many connections a server accepts at once, further clients are
+# turned away as busy. 0 accepts any number.
+#max_connections = 128
+
+# How many requests of one connection are handled at once. Past that,
+# the connection is not read from until one is done.
+#max_in_flight_requests = 64
+
+# Seconds the unlocked store may go unused (no entry looked up, used or
+# written) before it locks again, until a client unlocks it with the
+# passphrase. 0 never locks it.
+#auto_lock_after_idle = 0
+
+# How many times a passphrase provider is asked for the unlock
+# passphrase while the ones it gives are wrong, before the server gives
+# up on it. Wrong passphrases are held up longer with every one in a row.
+#unlock_passphrase_attempts = 5
+
+# Signatures per second the server makes, with all keys together,
+# 0 for no limit. Limits are token buckets, holding a second's worth of
+# signatures for bursts.
+#rate_limit = 0
+
+# Signatures per second the server makes with any one key, 0 for no
+# limit. Entries may have a limit of their own instead, see
+# `lair_set_entry_rate_limit`.
+#entry_rate_limit = 0
+
+# How many signing requests over a rate limit wait their turn, further
+# ones fail as rate limited, telling the client when to retry. 0 fails
+# every request over the limit.
+#rate_limit_queue = 0
+
+# Seconds the server waits for a signing approver to approve a signature
+# with a key that requires approval, before failing the request.
+#signing_approval_timeout = 20
+
+# Keep an audit log of every signature, decryption and private key
+# export: "audit.log" in the lair data directory, one json record per
+# line naming the client, the operation, the entry and the outcome,
+# each chained to the one before by its hash. Check it with
+# `lair-keystore audit verify`. Ephemeral servers keep none.
+#audit_log = false
+
+# Size in bytes past which the audit log is rotated, to "audit.log.1"
+# and so on, keeping 4 rotated files.
+#audit_log_max_bytes = 16777216
+
+# mlock the memory holding unlocked secrets (entry private keys and the
+# store key), keeping it off swap. If the memlock limit is too low
+# (`ulimit -l`), a warning is logged and secrets are kept unlocked,
+# see `secrets_mlocked` in `lair-keystore status`.
+#mlock_secrets = true
+
+# Process hardening, applied as the server starts, before it creates
+# any file. Each is reported in `lair-keystore status`.
+# Set RLIMIT_CORE to 0, so the server dumps no core holding secrets.
+#disable_core_dumps = true
+# Mark the server not dumpable (linux only), which also keeps other
+# non-root processes from ptracing it.
+#disable_ptrace = true
+# Set a 077 umask, so the files the server creates are its own only.
+#restrict_umask = true
+
+# Log level / tracing filter directives (overridden by `RUST_LOG`).
+#log_level = "info"
+
+# How many precomputed crypto_box shared keys (by local key and remote
+# pub key) to keep, 0 disables the cache.
+#crypto_box_cache_size = 1024
+
+# Payloads (messages to sign or verify, data to box or open) smaller than
+# this many bytes are worked on inline, rather than handed to the crypto
+# thread pool and back, which costs more than signing them. 0 sends all
+# crypto work to the pool.
+#inline_crypto_threshold = 4096
+
+# Threads of the pool crypto work is done on (named "lair-crypto-N"),
+# the number of cpus by default, at most 4. See the crypto pool queue
+# depth and busy threads in `lair-keystore status` to size it.
+#crypto_threads = 4
+
+[unlock_kdf]
+# The argon2id parameters the unlock passphrase is hashed with, stored
+# with the salt in the store. Stores hashed with less memory or fewer
+# iterations are upgraded as they are next unlocked (which reseals
+# their entries), costs are never lowered. See `cargo bench --bench
+# unlock` for the unlock latency of these on your hardware.
+# Memory cost, in KiB.
+#mem_kib = 19456
+# Number of passes over the memory.
+#iterations = 2
+# Degree of parallelism, at most one thread per lane.
+#parallelism = 1
+
+[store]
+# The store implementation, "file" (the default), or "sqlite" for large
+# stores. Use `lair-keystore migrate --to` to convert an existing store.
+# `store = "sqlite"` at the top level is a shorthand for this key.
+#kind = "file"
+
+# Path to the store file.
+# Defaults to "store", or "store.sqlite3" for sqlite stores.
+#path = "store"
+"#;
+
+/// The store implementation backing the lair keystore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StoreKind {
+    /// A single file of fixed size entries, appended to.
+    #[default]
+    File,
+
+    /// A sqlite database, with its entries indexed by index,
+    /// pub key, tag and type. Suited to stores of many keys.
+    Sqlite,
+}
+
+impl std::fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::File => "file",
+            Self::Sqlite => "sqlite",
+        })
+    }
+}
+
+impl std::str::FromStr for StoreKind {
+    type Err = LairError;
+
+    fn from_str(s: &str) -> LairResult<Self> {
+        match s {
+            "file" => Ok(Self::File),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => Err(format!(
+                "invalid store kind {:?}, expected \"file\" or \"sqlite\"",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// What a connection may do. Scopes are ordered, each allowing all the
+/// requests of the scopes before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ConnectionScope {
+    /// List and look up entries, and get pub keys and certs.
+    ReadOnly = 1,
+
+    /// Also sign, encrypt and decrypt with existing keys. Creating,
+    /// importing, exporting or deleting entries is still denied.
+    SignOnly = 2,
+
+    /// Every request.
+    #[default]
+    Full = 3,
+}
+
+impl ConnectionScope {
+    /// The scope of its wire representation, if valid.
+    pub fn from_u32(scope: u32) -> Option<Self> {
+        match scope {
+            1 => Some(Self::ReadOnly),
+            2 => Some(Self::SignOnly),
+            3 => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectionScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ReadOnly => "read-only",
+            Self::SignOnly => "sign-only",
+            Self::Full => "full",
+        })
+    }
+}
+
+impl std::str::FromStr for ConnectionScope {
+    type Err = LairError;
+
+    fn from_str(s: &str) -> LairResult<Self> {
+        match s {
+            "read-only" => Ok(Self::ReadOnly),
+            "sign-only" => Ok(Self::SignOnly),
+            "full" => Ok(Self::Full),
+            _ => Err(format!(
+                "invalid connection scope {:?}, expected \"full\", \
+                \"sign-only\" or \"read-only\"",
+                s
+            )
+            .into()),
+        }
+    }
+}
 
 /// Lair configuration struct.
 pub struct Config {
     root_path: PathBuf,
+    profile: Option<String>,
+    store_kind: StoreKind,
     store_path: PathBuf,
     pid_path: PathBuf,
     socket_path: PathBuf,
+    socket_dir: PathBuf,
+    socket_mode: u32,
+    socket_group: Option<String>,
     stdout_path: PathBuf,
     stderr_path: PathBuf,
+    log_level: Option<String>,
+    crypto_box_cache_size: usize,
+    inline_crypto_threshold: usize,
+    crypto_threads: usize,
+    read_only: bool,
+    ephemeral: bool,
+    in_process_ipc: bool,
+    tcp_bind: Option<std::net::SocketAddr>,
+    tcp_only: bool,
+    token_path: PathBuf,
+    require_token: bool,
+    token: Option<String>,
+    connection_scope: ConnectionScope,
+    scoped_tokens: std::collections::BTreeMap<String, ConnectionScope>,
+    request_timeout: Option<std::time::Duration>,
+    /// `None` for the default of the transport
+    ping_interval: Option<std::time::Duration>,
+    max_connections: usize,
+    max_in_flight_requests: usize,
+    auto_lock_after_idle: Option<std::time::Duration>,
+    unlock_passphrase_attempts: u32,
+    client_name: Option<String>,
+    audit_log: bool,
+    audit_log_path: PathBuf,
+    audit_log_max_bytes: u64,
+    rate_limit: Option<u32>,
+    entry_rate_limit: Option<u32>,
+    rate_limit_queue: usize,
+    signing_approval_timeout: std::time::Duration,
+    mlock_secrets: bool,
+    disable_core_dumps: bool,
+    disable_ptrace: bool,
+    restrict_umask: bool,
+    allowed_peer_uids: Vec<u32>,
+    allowed_peer_gids: Vec<u32>,
+    unlock_kdf: entry::UnlockKdf,
+    exportable_by_default: bool,
 }
 
 impl Config {
     pub(crate) fn finalize(mut self) -> Arc<Config> {
-        std::fs::create_dir_all(self.root_path.as_path())
-            .expect("can cannonicalize root path");
-        self.root_path = self
-            .root_path
-            .canonicalize()
-            .expect("can cannonicalize root path");
-        self.store_path = self.root_path.clone();
-        self.store_path.push("store");
+        // the paths of ephemeral configs only name them, nothing is
+        // created there (see `ConfigBuilder::set_ephemeral()`)
+        if !self.ephemeral {
+            std::fs::create_dir_all(self.root_path.as_path())
+                .expect("can cannonicalize root path");
+            self.root_path = self
+                .root_path
+                .canonicalize()
+                .expect("can cannonicalize root path");
+        }
+        self.in_process_ipc = self.ephemeral
+            && self.socket_path.as_os_str().is_empty()
+            && self.tcp_bind.is_none();
+        // profiles get their own files, e.g. "socket-<profile>"
+        let suffix = match &self.profile {
+            Some(profile) => format!("-{}", profile),
+            None => String::new(),
+        };
+        // (joining an absolute path replaces the root)
+        if self.store_path.as_os_str().is_empty() {
+            self.store_path.push(match self.store_kind {
+                StoreKind::File => format!("store{}", suffix),
+                StoreKind::Sqlite => format!("store{}.sqlite3", suffix),
+            });
+        }
+        self.store_path = self.root_path.join(&self.store_path);
         self.pid_path = self.root_path.clone();
-        self.pid_path.push("pid");
-        self.socket_path = self.root_path.clone();
-        self.socket_path.push("socket");
+        self.pid_path.push(format!("pid{}", suffix));
+        self.token_path = self.root_path.clone();
+        self.token_path.push(format!("token{}", suffix));
+        // like the socket, a read-only server keeps a log of its own
+        self.audit_log_path = self.root_path.clone();
+        self.audit_log_path.push(if self.read_only {
+            format!("audit{}-read-only.log", suffix)
+        } else {
+            format!("audit{}.log", suffix)
+        });
+        // binding the socket replaces any other, a read-only server
+        // must not take over the socket of the server beside it
+        if self.socket_path.as_os_str().is_empty() {
+            let socket_name = if self.read_only {
+                format!("socket{}-read-only", suffix)
+            } else {
+                format!("socket{}", suffix)
+            };
+            // named pipes live in a namespace of their own
+            #[cfg(windows)]
+            let socket_path = pipe_name(&self.root_path, &socket_name);
+            #[cfg(not(windows))]
+            let socket_path = PathBuf::from(socket_name);
+            self.socket_path = socket_path;
+        }
+        // (an absolute socket path replaces the socket dir)
+        if !self.socket_dir.as_os_str().is_empty() {
+            self.socket_path = self.socket_dir.join(&self.socket_path);
+        }
+        self.socket_path = self.root_path.join(&self.socket_path);
         self.stdout_path = self.root_path.clone();
-        self.stdout_path.push("stdout");
+        self.stdout_path.push(format!("stdout{}", suffix));
         self.stderr_path = self.root_path.clone();
-        self.stderr_path.push("stderr");
+        self.stderr_path.push(format!("stderr{}", suffix));
         Arc::new(self)
     }
 
@@ -39,26 +467,64 @@ impl Config {
         ConfigBuilder::default()
     }
 
+    /// Obtain a config builder initialized from a toml config file.
+    /// See `DEFAULT_CONFIG_TOML` for the available options.
+    /// Unknown keys are logged as warnings, not treated as errors.
+    pub fn from_toml_file<P: AsRef<Path>>(
+        path: P,
+    ) -> LairResult<ConfigBuilder> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(|e| {
+            LairError::other(format!("{}: {}", path.display(), e))
+        })?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        ConfigBuilder::from_toml_str(&data, base_dir)
+            .map_err(|e| LairError::other(format!("{}: {}", path.display(), e)))
+    }
+
     /// Get the root data directory as specified by this config.
     pub fn get_root_path(&self) -> &Path {
         self.root_path.as_path()
     }
 
+    /// Get the profile this config is for, if any.
+    pub fn get_profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Get the kind of store backing the lair keystore.
+    pub fn get_store_kind(&self) -> StoreKind {
+        self.store_kind
+    }
+
     /// Get the path to the lair store.
     pub fn get_store_path(&self) -> &Path {
         self.store_path.as_path()
     }
 
     /// Get the path to the lair pidfile.
+    /// The lair process with the store open holds a lock on it.
     pub fn get_pid_path(&self) -> &Path {
         self.pid_path.as_path()
     }
 
     /// Get the path to the lair ipc socket.
+    /// On windows this is the name of a named pipe,
+    /// `\\.\pipe\lair-keystore-<hash of the data dir and socket name>`.
     pub fn get_socket_path(&self) -> &Path {
         self.socket_path.as_path()
     }
 
+    /// Get the mode the unix socket file is given.
+    pub fn get_socket_mode(&self) -> u32 {
+        self.socket_mode
+    }
+
+    /// Get the group (a name or a gid) the unix socket file is given.
+    pub fn get_socket_group(&self) -> Option<&str> {
+        self.socket_group.as_deref()
+    }
+
     /// Get the path to the lair stdout file.
     pub fn get_stdout_path(&self) -> &Path {
         self.stdout_path.as_path()
@@ -68,6 +534,220 @@ impl Config {
     pub fn get_stderr_path(&self) -> &Path {
         self.stderr_path.as_path()
     }
+
+    /// Get the configured log level / tracing filter, if any.
+    pub fn get_log_level(&self) -> Option<&str> {
+        self.log_level.as_deref()
+    }
+
+    /// Get how many precomputed crypto_box shared keys to cache.
+    pub fn get_crypto_box_cache_size(&self) -> usize {
+        self.crypto_box_cache_size
+    }
+
+    /// Get the payload size below which crypto work is done inline,
+    /// rather than on the crypto thread pool.
+    pub fn get_inline_crypto_threshold(&self) -> usize {
+        self.inline_crypto_threshold
+    }
+
+    /// Get how many threads the crypto thread pool of the server has.
+    pub fn get_crypto_threads(&self) -> usize {
+        self.crypto_threads
+    }
+
+    /// Get whether the store is served read-only.
+    pub fn get_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Get whether the store is kept in memory, writing no files.
+    pub fn get_ephemeral(&self) -> bool {
+        self.ephemeral
+    }
+
+    /// Get whether clients connect through an in-process channel
+    /// (named by the socket path) instead of the socket, as they do
+    /// for ephemeral configs without an explicit socket path.
+    pub fn get_in_process_ipc(&self) -> bool {
+        self.in_process_ipc
+    }
+
+    /// Get the TCP address the server listens on, and clients connect
+    /// to, if any.
+    pub fn get_tcp_bind(&self) -> Option<std::net::SocketAddr> {
+        self.tcp_bind
+    }
+
+    /// Get whether the server listens on the TCP address only,
+    /// binding no socket.
+    pub fn get_tcp_only(&self) -> bool {
+        self.tcp_only && self.tcp_bind.is_some()
+    }
+
+    /// Get the path to the connection token file.
+    pub fn get_token_path(&self) -> &Path {
+        self.token_path.as_path()
+    }
+
+    /// Get whether clients must present the connection token,
+    /// as they must over tcp, or if a token is configured.
+    pub fn get_require_token(&self) -> bool {
+        self.require_token
+            || self.token.is_some()
+            || self.tcp_bind.is_some()
+            || !self.scoped_tokens.is_empty()
+    }
+
+    /// Get the configured connection token, if any
+    /// (otherwise it is read from the token file).
+    pub fn get_token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Get the connection scope: on a server, the most granted to
+    /// connections presenting the connection token (or none, if not
+    /// required), on a client, the scope it asks for.
+    pub fn get_connection_scope(&self) -> ConnectionScope {
+        self.connection_scope
+    }
+
+    /// Get how long clients wait for the response to a request,
+    /// `None` if forever.
+    pub fn get_request_timeout(&self) -> Option<std::time::Duration> {
+        self.request_timeout
+    }
+
+    /// Get how often clients ping idle connections, `None` if never.
+    pub fn get_ping_interval(&self) -> Option<std::time::Duration> {
+        match self.ping_interval {
+            Some(interval) if interval.as_nanos() == 0 => None,
+            Some(interval) => Some(interval),
+            None if self.tcp_bind.is_some() => Some(DEFAULT_PING_INTERVAL),
+            None => None,
+        }
+    }
+
+    /// Get how many connections a server accepts at once, 0 if any
+    /// number.
+    pub fn get_max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    /// Get how many requests of one connection are handled at once.
+    pub fn get_max_in_flight_requests(&self) -> usize {
+        self.max_in_flight_requests
+    }
+
+    /// Get how long the unlocked store may go unused before it locks,
+    /// `None` if it never locks by itself.
+    pub fn get_auto_lock_after_idle(&self) -> Option<std::time::Duration> {
+        self.auto_lock_after_idle
+    }
+
+    /// Get how many times a passphrase provider is asked for the unlock
+    /// passphrase while the ones it gives are wrong.
+    pub fn get_unlock_passphrase_attempts(&self) -> u32 {
+        self.unlock_passphrase_attempts
+    }
+
+    /// Get how many signatures per second the server makes with all keys
+    /// together, `None` if any number.
+    pub fn get_rate_limit(&self) -> Option<u32> {
+        self.rate_limit
+    }
+
+    /// Get how many signatures per second the server makes with an entry
+    /// without a rate limit of its own, `None` if any number.
+    pub fn get_entry_rate_limit(&self) -> Option<u32> {
+        self.entry_rate_limit
+    }
+
+    /// Get how many signing requests over a rate limit wait their turn.
+    pub fn get_rate_limit_queue(&self) -> usize {
+        self.rate_limit_queue
+    }
+
+    /// Get how long the server waits for a signing approver to answer.
+    pub fn get_signing_approval_timeout(&self) -> std::time::Duration {
+        self.signing_approval_timeout
+    }
+
+    /// Get whether the server keeps an audit log of secret key
+    /// operations, never for ephemeral configs.
+    pub fn get_audit_log(&self) -> bool {
+        self.audit_log && !self.ephemeral
+    }
+
+    /// Get the path to the audit log.
+    pub fn get_audit_log_path(&self) -> &Path {
+        self.audit_log_path.as_path()
+    }
+
+    /// Get the size in bytes past which the audit log is rotated.
+    pub fn get_audit_log_max_bytes(&self) -> u64 {
+        self.audit_log_max_bytes
+    }
+
+    /// Get whether the server mlocks the memory holding unlocked secrets.
+    pub fn get_mlock_secrets(&self) -> bool {
+        self.mlock_secrets
+    }
+
+    /// Get whether the server disables its core dumps on startup.
+    pub fn get_disable_core_dumps(&self) -> bool {
+        self.disable_core_dumps
+    }
+
+    /// Get whether the server marks itself not dumpable on startup.
+    pub fn get_disable_ptrace(&self) -> bool {
+        self.disable_ptrace
+    }
+
+    /// Get whether the server sets a restrictive umask on startup.
+    pub fn get_restrict_umask(&self) -> bool {
+        self.restrict_umask
+    }
+
+    /// Get the uids, besides the server's own, allowed to connect to
+    /// the socket.
+    pub fn get_allowed_peer_uids(&self) -> &[u32] {
+        &self.allowed_peer_uids
+    }
+
+    /// Get the gids whose members are allowed to connect to the socket.
+    pub fn get_allowed_peer_gids(&self) -> &[u32] {
+        &self.allowed_peer_gids
+    }
+
+    /// Get the kdf new unlock entries are derived with, and stores
+    /// with weaker ones are upgraded to as they are unlocked.
+    pub fn get_unlock_kdf(&self) -> entry::UnlockKdf {
+        self.unlock_kdf
+    }
+
+    /// Get whether new entries are exportable unless asked otherwise.
+    pub fn get_exportable_by_default(&self) -> bool {
+        self.exportable_by_default
+    }
+
+    /// Get the name clients using this config identify themselves by.
+    pub fn get_client_name(&self) -> Option<&str> {
+        self.client_name.as_deref()
+    }
+
+    /// Get the scope a server grants connections presenting one of the
+    /// further tokens of `ConfigBuilder::add_scoped_token()`.
+    pub fn get_token_scope(&self, token: &str) -> Option<ConnectionScope> {
+        // compare with every token, which one matched is not to be timed
+        self.scoped_tokens.iter().fold(None, |found, (t, scope)| {
+            if internal::auth_token::token_eq(t, token) {
+                Some(*scope)
+            } else {
+                found
+            }
+        })
+    }
 }
 
 /// Lair configuration builder.
@@ -79,11 +759,52 @@ impl Default for ConfigBuilder {
             .expect("can determine project dir");
         Self(Config {
             root_path: pdir.data_local_dir().to_path_buf(),
+            profile: None,
+            store_kind: StoreKind::default(),
             store_path: PathBuf::new(),
             pid_path: PathBuf::new(),
             socket_path: PathBuf::new(),
+            socket_dir: PathBuf::new(),
+            socket_mode: DEFAULT_SOCKET_MODE,
+            socket_group: None,
             stdout_path: PathBuf::new(),
             stderr_path: PathBuf::new(),
+            log_level: None,
+            crypto_box_cache_size: internal::crypto_box::DEFAULT_CACHE_SIZE,
+            inline_crypto_threshold: DEFAULT_INLINE_CRYPTO_THRESHOLD,
+            crypto_threads: default_crypto_threads(),
+            read_only: false,
+            ephemeral: false,
+            in_process_ipc: false,
+            tcp_bind: None,
+            tcp_only: false,
+            token_path: PathBuf::new(),
+            require_token: false,
+            token: None,
+            connection_scope: ConnectionScope::default(),
+            scoped_tokens: Default::default(),
+            request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+            ping_interval: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_in_flight_requests: DEFAULT_MAX_IN_FLIGHT_REQUESTS,
+            auto_lock_after_idle: None,
+            unlock_passphrase_attempts: DEFAULT_UNLOCK_PASSPHRASE_ATTEMPTS,
+            client_name: None,
+            audit_log: false,
+            audit_log_path: PathBuf::new(),
+            audit_log_max_bytes: DEFAULT_AUDIT_LOG_MAX_BYTES,
+            rate_limit: None,
+            entry_rate_limit: None,
+            rate_limit_queue: 0,
+            signing_approval_timeout: DEFAULT_SIGNING_APPROVAL_TIMEOUT,
+            mlock_secrets: true,
+            disable_core_dumps: true,
+            disable_ptrace: true,
+            restrict_umask: true,
+            allowed_peer_uids: Vec::new(),
+            allowed_peer_gids: Vec::new(),
+            unlock_kdf: entry::UnlockKdf::default(),
+            exportable_by_default: true,
         })
     }
 }
@@ -107,4 +828,1287 @@ impl ConfigBuilder {
         self.0.root_path = p.into();
         self
     }
+
+    /// Get the data directory this builder is currently set to.
+    pub fn get_root_path(&self) -> &Path {
+        self.0.root_path.as_path()
+    }
+
+    /// Select a named profile: an independent store within the data
+    /// directory, with its own unlock passphrase, entry indices, socket
+    /// and pid file ("store-<profile>", "socket-<profile>", "pid-<profile>",
+    /// unless the store / socket paths are set explicitly).
+    /// Profile names are ascii letters, digits, `-` and `_`.
+    pub fn set_profile<S>(mut self, profile: S) -> LairResult<Self>
+    where
+        S: Into<String>,
+    {
+        let profile = profile.into();
+        check_profile_name(&profile)?;
+        self.0.profile = Some(profile);
+        Ok(self)
+    }
+
+    /// Override the ipc socket path.
+    /// Relative paths are resolved against the data directory.
+    /// On windows, set a named pipe, `\\.\pipe\<name>`.
+    pub fn set_socket_path<P>(mut self, p: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.0.socket_path = p.into();
+        self
+    }
+
+    /// Place the unix socket in `dir` instead of the data directory,
+    /// e.g. in "/run" while the store is on an encrypted volume.
+    /// A relative socket path is resolved against it, and a relative
+    /// `dir` against the data directory. The server creates it if
+    /// missing. Has no effect on windows named pipes.
+    pub fn set_socket_dir<P>(mut self, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.0.socket_dir = dir.into();
+        self
+    }
+
+    /// Give the unix socket file `mode` (permission bits only) once
+    /// bound. Defaults to `DEFAULT_SOCKET_MODE`, owner only.
+    pub fn set_socket_mode(mut self, mode: u32) -> LairResult<Self> {
+        if mode & !0o777 != 0 {
+            return Err(LairError::other(format!(
+                "invalid socket mode {:o}",
+                mode
+            )));
+        }
+        self.0.socket_mode = mode;
+        Ok(self)
+    }
+
+    /// Give the unix socket file to `group`, a group name or a gid,
+    /// once bound. The server must be a member of it (or root).
+    /// Its members may connect only if the socket mode lets them, and
+    /// their gid is allowed with `add_allowed_peer_gid()`.
+    pub fn set_socket_group<S>(mut self, group: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.0.socket_group = Some(group.into());
+        self
+    }
+
+    /// Set the kind of store backing the lair keystore.
+    pub fn set_store_kind(mut self, kind: StoreKind) -> Self {
+        self.0.store_kind = kind;
+        self
+    }
+
+    /// Override the store file path.
+    /// Relative paths are resolved against the data directory.
+    pub fn set_store_path<P>(mut self, p: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.0.store_path = p.into();
+        self
+    }
+
+    /// Set the log level / tracing filter directives.
+    pub fn set_log_level<S>(mut self, s: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.0.log_level = Some(s.into());
+        self
+    }
+
+    /// Set how many precomputed crypto_box shared keys to cache,
+    /// 0 disables the cache.
+    pub fn set_crypto_box_cache_size(mut self, size: usize) -> Self {
+        self.0.crypto_box_cache_size = size;
+        self
+    }
+
+    /// Set the payload size (in bytes) below which signing, verifying,
+    /// boxing and opening are done inline on the requesting task, rather
+    /// than on the crypto thread pool. 0 sends all crypto work to the
+    /// pool. See `set_inline_crypto_threshold()`. This is process-wide,
+    /// only the first server started in a process applies it.
+    pub fn set_inline_crypto_threshold(mut self, bytes: usize) -> Self {
+        self.0.inline_crypto_threshold = bytes;
+        self
+    }
+
+    /// Have the server do its crypto work on a pool of `threads` threads
+    /// (at least one) of its own, named `lair-crypto-N`, rather than
+    /// competing for those of the embedding application. Defaults to
+    /// `default_crypto_threads()`. Only the first server of a process
+    /// sizes the pool, unless crypto work was done before it started.
+    pub fn set_crypto_threads(mut self, threads: usize) -> Self {
+        self.0.crypto_threads = std::cmp::max(1, threads);
+        self
+    }
+
+    /// Serve the store without writing to it: requests creating or
+    /// modifying entries fail with `LairError::ReadOnly`, usage counters
+    /// are not recorded, and no pid file is taken, so a read-only server
+    /// can run beside the one writing the store (on a socket of its own,
+    /// "socket-read-only" unless the socket path is set explicitly).
+    pub fn set_read_only(mut self, read_only: bool) -> Self {
+        self.0.read_only = read_only;
+        self
+    }
+
+    /// Keep the store in memory, for tests and throwaway environments:
+    /// no files are written (the data directory is not even created),
+    /// there is no pid file, and the store is gone once the server shuts
+    /// down. The first unlock passphrase initializes the store.
+    /// Unless a socket path is set, the server listens on an in-process
+    /// channel, for clients in this process using an equal config.
+    pub fn set_ephemeral(mut self, ephemeral: bool) -> Self {
+        self.0.ephemeral = ephemeral;
+        self
+    }
+
+    /// Also listen on TCP, at `addr`: "<ip>:<port>", or just a port,
+    /// on 127.0.0.1. Clients using this config connect over TCP rather
+    /// than the socket. Unlike the socket, TCP connections are not
+    /// guarded by filesystem permissions, so they must present the
+    /// connection token (see `set_require_token()`). Binding anything but
+    /// a loopback address logs a warning.
+    pub fn set_tcp_bind(mut self, addr: &str) -> LairResult<Self> {
+        self.0.tcp_bind = Some(parse_tcp_bind(addr)?);
+        Ok(self)
+    }
+
+    /// Listen on the TCP address only, binding no socket
+    /// (see `set_tcp_bind()`).
+    pub fn set_tcp_only(mut self, tcp_only: bool) -> Self {
+        self.0.tcp_only = tcp_only;
+        self
+    }
+
+    /// Require clients to present the connection token before making
+    /// any request (see `set_token()`), connections that don't are
+    /// dropped. Unless a token is configured, the server generates
+    /// one, writing it to the token file in the data directory, where
+    /// clients of this user pick it up.
+    pub fn set_require_token(mut self, require_token: bool) -> Self {
+        self.0.require_token = require_token;
+        self
+    }
+
+    /// Use this connection token rather than the token file, requiring
+    /// it. A server still writes it to the token file, for clients
+    /// that aren't configured with it, unless the config is ephemeral.
+    pub fn set_token<S>(mut self, token: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.0.token = Some(token.into());
+        self
+    }
+
+    /// Limit what connections may do (see `ConnectionScope`). A server
+    /// grants connections no more than this, a client asks for it.
+    pub fn set_connection_scope(mut self, scope: ConnectionScope) -> Self {
+        self.0.connection_scope = scope;
+        self
+    }
+
+    /// Also accept `token` as a connection token, granting connections
+    /// presenting it no more than `scope`. Requires clients to present
+    /// a token. Clients are given such a token with `set_token()`.
+    pub fn add_scoped_token<S>(
+        mut self,
+        token: S,
+        scope: ConnectionScope,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        self.0.scoped_tokens.insert(token.into(), scope);
+        self
+    }
+
+    /// Fail requests of clients using this config with
+    /// `LairError::Timeout` if the server hasn't responded within
+    /// `timeout`, a zero duration waits forever. Defaults to
+    /// `DEFAULT_REQUEST_TIMEOUT`. Slow requests can be given longer
+    /// with `ipc::with_timeout()`.
+    pub fn set_request_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.0.request_timeout = if timeout.as_nanos() == 0 {
+            None
+        } else {
+            Some(timeout)
+        };
+        self
+    }
+
+    /// Have clients using this config ping the server every `interval`
+    /// the connection is idle, and reconnect once two pings go unanswered,
+    /// a zero duration never pings. Defaults to `DEFAULT_PING_INTERVAL`
+    /// over tcp, where idle connections may be dropped silently on the
+    /// way, and to never over unix sockets and named pipes.
+    pub fn set_ping_interval(mut self, interval: std::time::Duration) -> Self {
+        self.0.ping_interval = Some(interval);
+        self
+    }
+
+    /// Turn clients away with `LairError::Busy` while a server already
+    /// has `max` connections, 0 accepts any number. Defaults to
+    /// `DEFAULT_MAX_CONNECTIONS`.
+    pub fn set_max_connections(mut self, max: usize) -> Self {
+        self.0.max_connections = max;
+        self
+    }
+
+    /// Handle at most `max` requests of one connection at once (at least
+    /// one), reading no more from it until one is done, so clients
+    /// sending faster than their requests are handled are held up rather
+    /// than buffered. Defaults to `DEFAULT_MAX_IN_FLIGHT_REQUESTS`.
+    pub fn set_max_in_flight_requests(mut self, max: usize) -> Self {
+        self.0.max_in_flight_requests = std::cmp::max(1, max);
+        self
+    }
+
+    /// Lock the store again once it goes unused (no entry looked up,
+    /// used or written) for `idle`, like `LairClientApi::lair_lock()`,
+    /// a zero duration never locks it. Defaults to never.
+    pub fn set_auto_lock_after_idle(
+        mut self,
+        idle: std::time::Duration,
+    ) -> Self {
+        self.0.auto_lock_after_idle = if idle.as_nanos() == 0 {
+            None
+        } else {
+            Some(idle)
+        };
+        self
+    }
+
+    /// Ask a passphrase provider for the unlock passphrase up to
+    /// `attempts` times (at least once) while the ones it gives are wrong,
+    /// telling it the last one failed and which attempt of how many it
+    /// is on. Defaults to `DEFAULT_UNLOCK_PASSPHRASE_ATTEMPTS`.
+    pub fn set_unlock_passphrase_attempts(mut self, attempts: u32) -> Self {
+        self.0.unlock_passphrase_attempts = std::cmp::max(1, attempts);
+        self
+    }
+
+    /// Make at most `per_second` signatures per second, with all keys
+    /// together, 0 for no limit. Requests over the limit wait or fail,
+    /// see `set_rate_limit_queue()`. Defaults to no limit.
+    pub fn set_rate_limit(mut self, per_second: u32) -> Self {
+        self.0.rate_limit = Some(per_second).filter(|n| *n != 0);
+        self
+    }
+
+    /// Make at most `per_second` signatures per second with any one key,
+    /// 0 for no limit, unless the entry has a limit of its own (see
+    /// `LairClientApi::lair_set_entry_rate_limit()`). Defaults to no limit.
+    pub fn set_entry_rate_limit(mut self, per_second: u32) -> Self {
+        self.0.entry_rate_limit = Some(per_second).filter(|n| *n != 0);
+        self
+    }
+
+    /// Have up to `depth` signing requests over a rate limit wait until
+    /// they are allowed, further ones fail with `LairError::RateLimited`.
+    /// Defaults to 0, failing every request over the limit.
+    pub fn set_rate_limit_queue(mut self, depth: usize) -> Self {
+        self.0.rate_limit_queue = depth;
+        self
+    }
+
+    /// Fail signatures with keys that require approval with
+    /// `LairError::SigningApprovalTimeout` if no signing approver has
+    /// answered within `timeout`. Defaults to
+    /// `DEFAULT_SIGNING_APPROVAL_TIMEOUT`.
+    pub fn set_signing_approval_timeout(
+        mut self,
+        timeout: std::time::Duration,
+    ) -> Self {
+        self.0.signing_approval_timeout = timeout;
+        self
+    }
+
+    /// Append a record of every signature, decryption and private key
+    /// export the server makes to the audit log, "audit.log" in the data
+    /// directory (see `internal::audit`). Responses are withheld if their
+    /// record can't be written. Ephemeral configs keep no audit log.
+    /// Defaults to false.
+    pub fn set_audit_log(mut self, audit_log: bool) -> Self {
+        self.0.audit_log = audit_log;
+        self
+    }
+
+    /// Rotate the audit log once a record would take it past `max_bytes`.
+    /// Defaults to `DEFAULT_AUDIT_LOG_MAX_BYTES`.
+    pub fn set_audit_log_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.0.audit_log_max_bytes = max_bytes;
+        self
+    }
+
+    /// Have the server mlock the memory holding unlocked secrets, entry
+    /// private keys and the store key, keeping it off swap. Should the
+    /// memlock limit be too low, a warning is logged and secrets are
+    /// kept unlocked, as reported by `LairServerInfo::secrets_mlocked`.
+    /// This is process-wide, only the first server started in a process
+    /// applies it. Defaults to true.
+    pub fn set_mlock_secrets(mut self, mlock_secrets: bool) -> Self {
+        self.0.mlock_secrets = mlock_secrets;
+        self
+    }
+
+    /// Have the server set its `RLIMIT_CORE` to 0 on startup, so it
+    /// dumps no core holding secrets. Disable for debugging workflows
+    /// needing cores. Once disabled, core dumps stay disabled for the
+    /// rest of the process. Defaults to true.
+    pub fn set_disable_core_dumps(mut self, disable: bool) -> Self {
+        self.0.disable_core_dumps = disable;
+        self
+    }
+
+    /// Have the server mark itself not dumpable (`PR_SET_DUMPABLE`,
+    /// linux only) on startup, which also keeps other non-root
+    /// processes from ptracing it. Defaults to true.
+    pub fn set_disable_ptrace(mut self, disable: bool) -> Self {
+        self.0.disable_ptrace = disable;
+        self
+    }
+
+    /// Have the server set a `077` umask on startup, before it creates
+    /// its store files or socket, so they are its own only.
+    /// Defaults to true.
+    pub fn set_restrict_umask(mut self, restrict: bool) -> Self {
+        self.0.restrict_umask = restrict;
+        self
+    }
+
+    /// Also allow peers running as `uid` to connect to the unix socket.
+    /// By default only peers running as the server's own (effective)
+    /// uid may, the credentials of others are logged and they are
+    /// disconnected before any protocol traffic.
+    pub fn add_allowed_peer_uid(mut self, uid: u32) -> Self {
+        self.0.allowed_peer_uids.push(uid);
+        self
+    }
+
+    /// Also allow peers whose (primary) group is `gid` to connect to
+    /// the unix socket (see `add_allowed_peer_uid()`).
+    pub fn add_allowed_peer_gid(mut self, gid: u32) -> Self {
+        self.0.allowed_peer_gids.push(gid);
+        self
+    }
+
+    /// Hash unlock passphrases with `kdf` (see
+    /// `entry::UnlockKdf::argon2id()`). The parameters are stored in
+    /// the store. A store whose unlock entry has less memory or fewer
+    /// iterations is upgraded the next time it is unlocked, resealing
+    /// its entries. Defaults to `entry::UnlockKdf::default()`.
+    pub fn set_unlock_kdf(mut self, kdf: entry::UnlockKdf) -> Self {
+        self.0.unlock_kdf = kdf;
+        self
+    }
+
+    /// Whether the server creates new tls certs exportable unless asked
+    /// otherwise, see `TlsCertOptions::exportable`. Whether an entry
+    /// is exportable is fixed as it is created. Defaults to true.
+    pub fn set_exportable_by_default(mut self, exportable: bool) -> Self {
+        self.0.exportable_by_default = exportable;
+        self
+    }
+
+    /// Have clients using this config identify themselves to the server
+    /// by `name` as they connect, e.g. "holochain-conductor". The server
+    /// logs their requests under it, unnamed clients are `anon-<id>`.
+    /// Names are up to `CLIENT_NAME_MAX_BYTES`, without control characters.
+    pub fn set_client_name<S>(mut self, name: S) -> LairResult<Self>
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        check_client_name(&name)?;
+        self.0.client_name = Some(name);
+        Ok(self)
+    }
+
+    fn from_toml_str(data: &str, base_dir: &Path) -> LairResult<Self> {
+        let root: toml::Value =
+            toml::from_str(data).map_err(LairError::other)?;
+        let root = root.as_table().ok_or("expected a toml table")?;
+
+        let mut out = Self::default();
+
+        for (key, value) in root {
+            match (key.as_str(), value) {
+                ("root_path", toml::Value::String(p)) => {
+                    out = out.set_root_path(base_dir.join(p));
+                }
+                ("profile", toml::Value::String(profile)) => {
+                    out = out.set_profile(profile.as_str())?;
+                }
+                ("socket_path", toml::Value::String(p)) => {
+                    out = out.set_socket_path(p);
+                }
+                ("socket_dir", toml::Value::String(dir)) => {
+                    out = out.set_socket_dir(dir);
+                }
+                ("socket_mode", toml::Value::Integer(mode))
+                    if *mode >= 0 && *mode <= 0o777 =>
+                {
+                    out = out.set_socket_mode(*mode as u32)?;
+                }
+                ("socket_group", toml::Value::String(group)) => {
+                    out = out.set_socket_group(group.as_str());
+                }
+                ("socket_group", toml::Value::Integer(gid))
+                    if *gid >= 0 && *gid <= u32::MAX as i64 =>
+                {
+                    out = out.set_socket_group(gid.to_string());
+                }
+                ("log_level", toml::Value::String(l)) => {
+                    out = out.set_log_level(l.as_str());
+                }
+                ("crypto_box_cache_size", toml::Value::Integer(n))
+                    if *n >= 0 =>
+                {
+                    out = out.set_crypto_box_cache_size(*n as usize);
+                }
+                ("inline_crypto_threshold", toml::Value::Integer(n))
+                    if *n >= 0 =>
+                {
+                    out = out.set_inline_crypto_threshold(*n as usize);
+                }
+                ("crypto_threads", toml::Value::Integer(n)) if *n >= 1 => {
+                    out = out.set_crypto_threads(*n as usize);
+                }
+                ("read_only", toml::Value::Boolean(b)) => {
+                    out = out.set_read_only(*b);
+                }
+                ("ephemeral", toml::Value::Boolean(b)) => {
+                    out = out.set_ephemeral(*b);
+                }
+                ("tcp_bind", toml::Value::String(addr)) => {
+                    out = out.set_tcp_bind(addr)?;
+                }
+                ("tcp_only", toml::Value::Boolean(b)) => {
+                    out = out.set_tcp_only(*b);
+                }
+                ("require_token", toml::Value::Boolean(b)) => {
+                    out = out.set_require_token(*b);
+                }
+                ("request_timeout", toml::Value::Integer(secs))
+                    if *secs >= 0 =>
+                {
+                    out = out.set_request_timeout(
+                        std::time::Duration::from_secs(*secs as u64),
+                    );
+                }
+                ("ping_interval", toml::Value::Integer(secs)) if *secs >= 0 => {
+                    out = out.set_ping_interval(
+                        std::time::Duration::from_secs(*secs as u64),
+                    );
+                }
+                ("max_connections", toml::Value::Integer(n)) if *n >= 0 => {
+                    out = out.set_max_connections(*n as usize);
+                }
+                ("max_in_flight_requests", toml::Value::Integer(n))
+                    if *n >= 1 =>
+                {
+                    out = out.set_max_in_flight_requests(*n as usize);
+                }
+                ("auto_lock_after_idle", toml::Value::Integer(secs))
+                    if *secs >= 0 =>
+                {
+                    out = out.set_auto_lock_after_idle(
+                        std::time::Duration::from_secs(*secs as u64),
+                    );
+                }
+                ("unlock_passphrase_attempts", toml::Value::Integer(n))
+                    if *n >= 1 && *n <= u32::MAX as i64 =>
+                {
+                    out = out.set_unlock_passphrase_attempts(*n as u32);
+                }
+                ("rate_limit", toml::Value::Integer(n))
+                    if *n >= 0 && *n <= u32::MAX as i64 =>
+                {
+                    out = out.set_rate_limit(*n as u32);
+                }
+                ("entry_rate_limit", toml::Value::Integer(n))
+                    if *n >= 0 && *n <= u32::MAX as i64 =>
+                {
+                    out = out.set_entry_rate_limit(*n as u32);
+                }
+                ("rate_limit_queue", toml::Value::Integer(n)) if *n >= 0 => {
+                    out = out.set_rate_limit_queue(*n as usize);
+                }
+                ("signing_approval_timeout", toml::Value::Integer(secs))
+                    if *secs >= 1 =>
+                {
+                    out = out.set_signing_approval_timeout(
+                        std::time::Duration::from_secs(*secs as u64),
+                    );
+                }
+                ("audit_log", toml::Value::Boolean(b)) => {
+                    out = out.set_audit_log(*b);
+                }
+                ("audit_log_max_bytes", toml::Value::Integer(n)) if *n >= 1 => {
+                    out = out.set_audit_log_max_bytes(*n as u64);
+                }
+                ("mlock_secrets", toml::Value::Boolean(b)) => {
+                    out = out.set_mlock_secrets(*b);
+                }
+                ("disable_core_dumps", toml::Value::Boolean(b)) => {
+                    out = out.set_disable_core_dumps(*b);
+                }
+                ("disable_ptrace", toml::Value::Boolean(b)) => {
+                    out = out.set_disable_ptrace(*b);
+                }
+                ("restrict_umask", toml::Value::Boolean(b)) => {
+                    out = out.set_restrict_umask(*b);
+                }
+                ("allowed_peer_uids", toml::Value::Array(uids)) => {
+                    for uid in uids {
+                        out = out.add_allowed_peer_uid(id_from_toml(
+                            "allowed_peer_uids",
+                            uid,
+                        )?);
+                    }
+                }
+                ("allowed_peer_gids", toml::Value::Array(gids)) => {
+                    for gid in gids {
+                        out = out.add_allowed_peer_gid(id_from_toml(
+                            "allowed_peer_gids",
+                            gid,
+                        )?);
+                    }
+                }
+                ("exportable_by_default", toml::Value::Boolean(b)) => {
+                    out = out.set_exportable_by_default(*b);
+                }
+                ("connection_scope", toml::Value::String(scope)) => {
+                    out = out.set_connection_scope(scope.parse()?);
+                }
+                ("scoped_tokens", toml::Value::Table(tokens)) => {
+                    for (token, scope) in tokens {
+                        let scope = scope.as_str().ok_or_else(|| {
+                            format!("scoped_tokens.{} must be a string", token)
+                        })?;
+                        out = out.add_scoped_token(token, scope.parse()?);
+                    }
+                }
+                ("unlock_kdf", toml::Value::Table(kdf)) => {
+                    let entry::UnlockKdf::Argon2id {
+                        mut mem_kib,
+                        mut iterations,
+                        mut parallelism,
+                    } = out.0.unlock_kdf;
+                    for (key, value) in kdf {
+                        let field = match key.as_str() {
+                            "mem_kib" => &mut mem_kib,
+                            "iterations" => &mut iterations,
+                            "parallelism" => &mut parallelism,
+                            _ => {
+                                warn!(
+                                    "ignoring unknown config key unlock_kdf.{}",
+                                    key
+                                );
+                                continue;
+                            }
+                        };
+                        *field = match value {
+                            toml::Value::Integer(n)
+                                if *n >= 0 && *n <= u32::MAX as i64 =>
+                            {
+                                *n as u32
+                            }
+                            _ => {
+                                return Err(format!(
+                                    "unlock_kdf.{} must be a number",
+                                    key
+                                )
+                                .into())
+                            }
+                        };
+                    }
+                    out = out.set_unlock_kdf(entry::UnlockKdf::argon2id(
+                        mem_kib,
+                        iterations,
+                        parallelism,
+                    )?);
+                }
+                ("store", toml::Value::String(kind)) => {
+                    out = out.set_store_kind(kind.parse()?);
+                }
+                ("store", toml::Value::Table(store)) => {
+                    for (key, value) in store {
+                        match (key.as_str(), value) {
+                            ("kind", toml::Value::String(kind)) => {
+                                out = out.set_store_kind(kind.parse()?);
+                            }
+                            ("kind", _) => {
+                                return Err("store.kind must be a string".into())
+                            }
+                            ("path", toml::Value::String(p)) => {
+                                out = out.set_store_path(p);
+                            }
+                            ("path", _) => {
+                                return Err("store.path must be a string".into())
+                            }
+                            _ => warn!(
+                                "ignoring unknown config key store.{}",
+                                key
+                            ),
+                        }
+                    }
+                }
+                ("root_path", _)
+                | ("profile", _)
+                | ("socket_path", _)
+                | ("socket_dir", _)
+                | ("socket_mode", _)
+                | ("socket_group", _)
+                | ("log_level", _)
+                | ("crypto_box_cache_size", _)
+                | ("inline_crypto_threshold", _)
+                | ("crypto_threads", _)
+                | ("read_only", _)
+                | ("ephemeral", _)
+                | ("tcp_bind", _)
+                | ("tcp_only", _)
+                | ("require_token", _)
+                | ("request_timeout", _)
+                | ("ping_interval", _)
+                | ("max_connections", _)
+                | ("max_in_flight_requests", _)
+                | ("auto_lock_after_idle", _)
+                | ("unlock_passphrase_attempts", _)
+                | ("rate_limit", _)
+                | ("entry_rate_limit", _)
+                | ("rate_limit_queue", _)
+                | ("signing_approval_timeout", _)
+                | ("audit_log", _)
+                | ("audit_log_max_bytes", _)
+                | ("mlock_secrets", _)
+                | ("disable_core_dumps", _)
+                | ("disable_ptrace", _)
+                | ("restrict_umask", _)
+                | ("allowed_peer_uids", _)
+                | ("allowed_peer_gids", _)
+                | ("exportable_by_default", _)
+                | ("connection_scope", _)
+                | ("scoped_tokens", _)
+                | ("unlock_kdf", _)
+                | ("store", _) => {
+                    return Err(format!(
+                        "invalid value type for config key {}",
+                        key
+                    )
+                    .into())
+                }
+                _ => warn!("ignoring unknown config key {}", key),
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// A uid or gid of the `key` array of a config file.
+fn id_from_toml(key: &str, value: &toml::Value) -> LairResult<u32> {
+    match value {
+        toml::Value::Integer(n) if *n >= 0 && *n <= u32::MAX as i64 => {
+            Ok(*n as u32)
+        }
+        _ => Err(format!("{} must be an array of ids", key).into()),
+    }
+}
+
+/// The named pipe of a socket in `root_path`: every data directory
+/// needs a pipe of its own, while the name has to be the same for
+/// every process using it.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn pipe_name(root_path: &Path, socket_name: &str) -> PathBuf {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(16)
+        .to_state()
+        .update(root_path.to_string_lossy().as_bytes())
+        .update(&[0])
+        .update(socket_name.as_bytes())
+        .finalize();
+    let hash: String = hash
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    PathBuf::from(format!(r"\\.\pipe\lair-keystore-{}", hash))
+}
+
+/// Parse a TCP address to bind: "<ip>:<port>", or a port on 127.0.0.1.
+fn parse_tcp_bind(addr: &str) -> LairResult<std::net::SocketAddr> {
+    if let Ok(port) = addr.parse::<u16>() {
+        return Ok((std::net::Ipv4Addr::LOCALHOST, port).into());
+    }
+    addr.parse().map_err(|_| {
+        format!(
+            "invalid tcp address {:?}, expected \"<ip>:<port>\" or a port",
+            addr
+        )
+        .into()
+    })
+}
+
+/// Error unless `profile` is a valid profile name: non-empty ascii
+/// letters, digits, `-` and `_`, as it becomes part of file names.
+pub fn check_profile_name(profile: &str) -> LairResult<()> {
+    if profile.is_empty()
+        || profile.len() > 64
+        || !profile
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    {
+        return Err(format!(
+            "invalid profile name {:?}, expected up to 64 ascii letters, \
+            digits, '-' or '_'",
+            profile
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Error unless `name` is a valid client name: non-empty, up to
+/// `CLIENT_NAME_MAX_BYTES`, without control characters, as it is logged.
+pub fn check_client_name(name: &str) -> LairResult<()> {
+    if name.is_empty()
+        || name.len() > CLIENT_NAME_MAX_BYTES
+        || name.chars().any(char::is_control)
+    {
+        return Err(format!(
+            "invalid client name {:?}, expected up to {} bytes \
+            without control characters",
+            name, CLIENT_NAME_MAX_BYTES
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_toml_parses_to_defaults() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let builder =
+            ConfigBuilder::from_toml_str(DEFAULT_CONFIG_TOML, tmpdir.path())
+                .unwrap();
+        let config = builder.set_root_path(tmpdir.path()).build();
+        assert_eq!(
+            config.get_root_path().join("socket"),
+            config.get_socket_path()
+        );
+        assert_eq!(
+            config.get_root_path().join("store"),
+            config.get_store_path()
+        );
+        assert_eq!(StoreKind::File, config.get_store_kind());
+        assert_eq!(None, config.get_profile());
+        assert_eq!(None, config.get_log_level());
+        assert_eq!(
+            internal::crypto_box::DEFAULT_CACHE_SIZE,
+            config.get_crypto_box_cache_size()
+        );
+        assert_eq!(
+            DEFAULT_INLINE_CRYPTO_THRESHOLD,
+            config.get_inline_crypto_threshold()
+        );
+        assert_eq!(default_crypto_threads(), config.get_crypto_threads());
+        assert!(!config.get_read_only());
+        assert!(!config.get_ephemeral());
+        assert!(!config.get_in_process_ipc());
+        assert_eq!(None, config.get_tcp_bind());
+        assert!(!config.get_tcp_only());
+        assert_eq!(
+            config.get_root_path().join("token"),
+            config.get_token_path()
+        );
+        assert!(!config.get_require_token());
+        assert_eq!(None, config.get_token());
+        assert_eq!(ConnectionScope::Full, config.get_connection_scope());
+        assert_eq!(Some(DEFAULT_REQUEST_TIMEOUT), config.get_request_timeout());
+        assert_eq!(None, config.get_ping_interval());
+        assert_eq!(DEFAULT_MAX_CONNECTIONS, config.get_max_connections());
+        assert_eq!(
+            DEFAULT_MAX_IN_FLIGHT_REQUESTS,
+            config.get_max_in_flight_requests()
+        );
+        assert_eq!(
+            DEFAULT_UNLOCK_PASSPHRASE_ATTEMPTS,
+            config.get_unlock_passphrase_attempts()
+        );
+        assert_eq!(None, config.get_rate_limit());
+        assert_eq!(None, config.get_entry_rate_limit());
+        assert_eq!(0, config.get_rate_limit_queue());
+        assert_eq!(
+            DEFAULT_SIGNING_APPROVAL_TIMEOUT,
+            config.get_signing_approval_timeout()
+        );
+        assert!(!config.get_audit_log());
+        assert_eq!(
+            config.get_root_path().join("audit.log"),
+            config.get_audit_log_path()
+        );
+        assert_eq!(
+            DEFAULT_AUDIT_LOG_MAX_BYTES,
+            config.get_audit_log_max_bytes()
+        );
+        assert!(config.get_mlock_secrets());
+        assert!(config.get_disable_core_dumps());
+        assert!(config.get_disable_ptrace());
+        assert!(config.get_restrict_umask());
+        assert!(config.get_allowed_peer_uids().is_empty());
+        assert!(config.get_allowed_peer_gids().is_empty());
+        assert_eq!(DEFAULT_SOCKET_MODE, config.get_socket_mode());
+        assert_eq!(None, config.get_socket_group());
+        assert_eq!(entry::UnlockKdf::default(), config.get_unlock_kdf());
+        assert!(config.get_exportable_by_default());
+    }
+
+    #[test]
+    fn config_from_toml_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            r#"
+root_path = "data"
+socket_path = "/tmp/lair-test.sock"
+log_level = "debug"
+crypto_box_cache_size = 16
+inline_crypto_threshold = 0
+crypto_threads = 2
+request_timeout = 0
+ping_interval = 5
+max_connections = 0
+max_in_flight_requests = 8
+auto_lock_after_idle = 600
+unlock_passphrase_attempts = 3
+rate_limit = 1000
+entry_rate_limit = 0
+rate_limit_queue = 16
+signing_approval_timeout = 120
+audit_log = true
+audit_log_max_bytes = 4096
+mlock_secrets = false
+disable_core_dumps = false
+restrict_umask = false
+allowed_peer_uids = [1001, 1002]
+allowed_peer_gids = [100]
+socket_mode = 0o660
+socket_group = 100
+exportable_by_default = false
+not_a_key = 42
+
+[unlock_kdf]
+mem_kib = 65536
+iterations = 3
+
+[store]
+kind = "sqlite"
+path = "my-store"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_toml_file(&path).unwrap().build();
+        let root = tmpdir.path().join("data").canonicalize().unwrap();
+        assert_eq!(root, config.get_root_path());
+        assert_eq!(Path::new("/tmp/lair-test.sock"), config.get_socket_path());
+        assert_eq!(StoreKind::Sqlite, config.get_store_kind());
+        assert_eq!(root.join("my-store"), config.get_store_path());
+        assert_eq!(Some("debug"), config.get_log_level());
+        assert_eq!(16, config.get_crypto_box_cache_size());
+        assert_eq!(0, config.get_inline_crypto_threshold());
+        assert_eq!(2, config.get_crypto_threads());
+        assert_eq!(None, config.get_request_timeout());
+        assert_eq!(
+            Some(std::time::Duration::from_secs(5)),
+            config.get_ping_interval()
+        );
+        assert_eq!(0, config.get_max_connections());
+        assert_eq!(8, config.get_max_in_flight_requests());
+        assert_eq!(
+            Some(std::time::Duration::from_secs(600)),
+            config.get_auto_lock_after_idle()
+        );
+        assert_eq!(3, config.get_unlock_passphrase_attempts());
+        assert_eq!(Some(1000), config.get_rate_limit());
+        assert_eq!(None, config.get_entry_rate_limit());
+        assert_eq!(16, config.get_rate_limit_queue());
+        assert_eq!(
+            std::time::Duration::from_secs(120),
+            config.get_signing_approval_timeout()
+        );
+        assert!(config.get_audit_log());
+        assert_eq!(4096, config.get_audit_log_max_bytes());
+        assert!(!config.get_mlock_secrets());
+        assert!(!config.get_disable_core_dumps());
+        assert!(config.get_disable_ptrace());
+        assert!(!config.get_restrict_umask());
+        assert_eq!(&[1001, 1002], config.get_allowed_peer_uids());
+        assert_eq!(&[100], config.get_allowed_peer_gids());
+        assert_eq!(0o660, config.get_socket_mode());
+        assert_eq!(Some("100"), config.get_socket_group());
+        assert!(!config.get_exportable_by_default());
+        assert_eq!(
+            entry::UnlockKdf::argon2id(
+                65536,
+                3,
+                entry::UNLOCK_ARGON2ID_PARALLELISM
+            )
+            .unwrap(),
+            config.get_unlock_kdf()
+        );
+
+        // explicit overrides win
+        let other = tempfile::tempdir().unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(other.path())
+            .build();
+        assert_eq!(
+            other.path().canonicalize().unwrap(),
+            config.get_root_path()
+        );
+
+        std::fs::write(&path, "socket_path = 42\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "request_timeout = -1\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "ping_interval = \"often\"\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "auto_lock_after_idle = -5\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "max_in_flight_requests = 0\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "unlock_passphrase_attempts = 0\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "rate_limit = -1\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "entry_rate_limit = 5000000000\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "signing_approval_timeout = 0\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "audit_log_max_bytes = 0\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "audit_log = \"yes\"\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "crypto_box_cache_size = -1\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "inline_crypto_threshold = -1\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "crypto_threads = 0\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "store = \"nope\"\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "[unlock_kdf]\niterations = 0\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "[unlock_kdf]\nmem_kib = \"lots\"\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "socket_mode = 0o4755\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "socket_group = -1\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "allowed_peer_uids = 1001\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        std::fs::write(&path, "allowed_peer_gids = [-1]\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+
+        // the shorthand kind, with the default path for that kind
+        std::fs::write(&path, "store = \"sqlite\"\n").unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(other.path())
+            .build();
+        assert_eq!(StoreKind::Sqlite, config.get_store_kind());
+        assert_eq!(
+            config.get_root_path().join("store.sqlite3"),
+            config.get_store_path()
+        );
+    }
+
+    #[test]
+    fn profiles_get_their_own_files() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_profile("alice")
+            .unwrap()
+            .build();
+        let root = config.get_root_path();
+        assert_eq!(Some("alice"), config.get_profile());
+        assert_eq!(root.join("socket-alice"), config.get_socket_path());
+        assert_eq!(root.join("store-alice"), config.get_store_path());
+        assert_eq!(root.join("pid-alice"), config.get_pid_path());
+        assert_eq!(root.join("token-alice"), config.get_token_path());
+        assert_eq!(root.join("audit-alice.log"), config.get_audit_log_path());
+
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_store_kind(StoreKind::Sqlite)
+            .set_profile("bob_2")
+            .unwrap()
+            .set_socket_path("/tmp/lair-bob.sock")
+            .build();
+        assert_eq!(
+            config.get_root_path().join("store-bob_2.sqlite3"),
+            config.get_store_path()
+        );
+        assert_eq!(Path::new("/tmp/lair-bob.sock"), config.get_socket_path());
+
+        // the socket dir takes the place of the data dir
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_profile("carol")
+            .unwrap()
+            .set_socket_dir("/run/lair")
+            .build();
+        assert_eq!(
+            Path::new("/run/lair/socket-carol"),
+            config.get_socket_path()
+        );
+        assert_eq!(
+            config.get_root_path().join("store-carol"),
+            config.get_store_path()
+        );
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_socket_dir("run")
+            .set_socket_path("lair.sock")
+            .build();
+        assert_eq!(
+            config.get_root_path().join("run").join("lair.sock"),
+            config.get_socket_path()
+        );
+
+        for bad in &["", "../up", "a b", "a/b", "ü"] {
+            assert!(Config::builder().set_profile(*bad).is_err(), "{}", bad);
+        }
+
+        let path = tmpdir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "profile = \"carol\"\n").unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(tmpdir.path())
+            .build();
+        assert_eq!(Some("carol"), config.get_profile());
+        std::fs::write(&path, "profile = \"c/d\"\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+    }
+
+    #[test]
+    fn client_names_are_checked() {
+        let config = Config::builder()
+            .set_ephemeral(true)
+            .set_client_name("holochain-conductor")
+            .unwrap()
+            .build();
+        assert_eq!(Some("holochain-conductor"), config.get_client_name());
+        let config = Config::builder().set_ephemeral(true).build();
+        assert_eq!(None, config.get_client_name());
+
+        let too_long = "a".repeat(CLIENT_NAME_MAX_BYTES + 1);
+        for bad in &["", "a\nb", "\u{1b}[31m", too_long.as_str()] {
+            assert!(
+                Config::builder().set_client_name(*bad).is_err(),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn read_only_servers_get_their_own_socket() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_read_only(true)
+            .build();
+        let root = config.get_root_path();
+        assert!(config.get_read_only());
+        assert_eq!(root.join("socket-read-only"), config.get_socket_path());
+        assert_eq!(root.join("store"), config.get_store_path());
+
+        let path = tmpdir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "profile = \"alice\"\nread_only = true\n")
+            .unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(tmpdir.path())
+            .build();
+        assert!(config.get_read_only());
+        assert_eq!(
+            config.get_root_path().join("socket-alice-read-only"),
+            config.get_socket_path()
+        );
+        std::fs::write(&path, "read_only = \"yes\"\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+    }
+
+    #[test]
+    fn ephemeral_configs_create_nothing() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("lair");
+        let config = Config::builder()
+            .set_root_path(&root)
+            .set_ephemeral(true)
+            .build();
+        assert!(config.get_ephemeral());
+        assert!(config.get_in_process_ipc());
+        assert_eq!(root.join("socket"), config.get_socket_path());
+        assert!(!root.exists());
+
+        let config = Config::builder()
+            .set_root_path(&root)
+            .set_ephemeral(true)
+            .set_socket_path(tmpdir.path().join("socket"))
+            .build();
+        assert!(!config.get_in_process_ipc());
+
+        let path = tmpdir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "ephemeral = true\n").unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(&root)
+            .build();
+        assert!(config.get_ephemeral());
+        assert!(!root.exists());
+    }
+    #[test]
+    fn tcp_bind_addresses() {
+        let tcp_bind = |addr| {
+            Config::builder()
+                .set_ephemeral(true)
+                .set_tcp_bind(addr)
+                .map(|b| b.build().get_tcp_bind().unwrap().to_string())
+        };
+        assert_eq!("127.0.0.1:9876", tcp_bind("9876").unwrap());
+        assert_eq!("127.0.0.1:9876", tcp_bind("127.0.0.1:9876").unwrap());
+        assert_eq!("[::1]:9876", tcp_bind("[::1]:9876").unwrap());
+        assert_eq!("0.0.0.0:1", tcp_bind("0.0.0.0:1").unwrap());
+        for bad in &["", "localhost:9876", "127.0.0.1", "99999"] {
+            assert!(tcp_bind(bad).is_err(), "{}", bad);
+        }
+
+        // ephemeral configs listen on tcp rather than in-process
+        let config = Config::builder()
+            .set_ephemeral(true)
+            .set_tcp_bind("9876")
+            .unwrap()
+            .set_tcp_only(true)
+            .build();
+        assert!(!config.get_in_process_ipc());
+        assert!(config.get_tcp_only());
+        // tcp connections carry no filesystem permissions
+        assert!(config.get_require_token());
+        // and may be dropped silently when idle
+        assert_eq!(Some(DEFAULT_PING_INTERVAL), config.get_ping_interval());
+        let config = Config::builder()
+            .set_ephemeral(true)
+            .set_tcp_bind("9876")
+            .unwrap()
+            .set_ping_interval(std::time::Duration::from_secs(0))
+            .build();
+        assert_eq!(None, config.get_ping_interval());
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "tcp_bind = \"9876\"\ntcp_only = true\n")
+            .unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(tmpdir.path())
+            .build();
+        assert_eq!(Some(([127, 0, 0, 1], 9876).into()), config.get_tcp_bind());
+        assert!(config.get_tcp_only());
+        std::fs::write(&path, "tcp_bind = 9876\n").unwrap();
+        assert!(Config::from_toml_file(&path).is_err());
+        // tcp_only does nothing without an address
+        std::fs::write(&path, "tcp_only = true\n").unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(tmpdir.path())
+            .build();
+        assert!(!config.get_tcp_only());
+    }
+
+    #[test]
+    fn pipe_names_are_per_socket() {
+        let name = pipe_name(Path::new(r"C:\lair"), "socket");
+        let name = name.to_str().unwrap();
+        assert!(name.starts_with(r"\\.\pipe\lair-keystore-"), "{}", name);
+        assert_eq!(
+            name,
+            pipe_name(Path::new(r"C:\lair"), "socket").to_str().unwrap()
+        );
+        for other in &[
+            pipe_name(Path::new(r"C:\other"), "socket"),
+            pipe_name(Path::new(r"C:\lair"), "socket-alice"),
+            pipe_name(Path::new(r"C:\lair"), "socket-read-only"),
+        ] {
+            assert_ne!(Path::new(name), other);
+        }
+    }
+
+    #[test]
+    fn connection_scopes() {
+        assert!(ConnectionScope::ReadOnly < ConnectionScope::SignOnly);
+        assert!(ConnectionScope::SignOnly < ConnectionScope::Full);
+        for scope in &[
+            ConnectionScope::ReadOnly,
+            ConnectionScope::SignOnly,
+            ConnectionScope::Full,
+        ] {
+            assert_eq!(*scope, scope.to_string().parse().unwrap());
+            assert_eq!(Some(*scope), ConnectionScope::from_u32(*scope as u32));
+        }
+        assert!("sign".parse::<ConnectionScope>().is_err());
+        assert_eq!(None, ConnectionScope::from_u32(0));
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            "connection_scope = \"read-only\"\n\
+            [scoped_tokens]\n\
+            signer = \"sign-only\"\n",
+        )
+        .unwrap();
+        let config = Config::from_toml_file(&path)
+            .unwrap()
+            .set_root_path(tmpdir.path())
+            .build();
+        assert_eq!(ConnectionScope::ReadOnly, config.get_connection_scope());
+        assert_eq!(
+            Some(ConnectionScope::SignOnly),
+            config.get_token_scope("signer")
+        );
+        assert_eq!(None, config.get_token_scope("signe"));
+        // scoped tokens must be presented
+        assert!(config.get_require_token());
+
+        for bad in &[
+            "connection_scope = \"all\"\n",
+            "connection_scope = 3\n",
+            "scoped_tokens = \"signer\"\n",
+            "[scoped_tokens]\nsigner = 2\n",
+        ] {
+            std::fs::write(&path, bad).unwrap();
+            assert!(Config::from_toml_file(&path).is_err(), "{}", bad);
+        }
+    }
 }
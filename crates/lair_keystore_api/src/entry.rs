@@ -4,12 +4,279 @@ use crate::*;
 
 use actor::*;
 use internal::codec;
+use internal::secret_key;
+use internal::seed;
+#[cfg(feature = "bls")]
+use internal::sign_bls12381;
 use internal::sign_ed25519;
+use internal::sign_secp256k1;
+use internal::util::{MemLock, SecretBytes};
 use internal::x25519;
 
 /// Fixed serialized entry byte count.
 pub const ENTRY_SIZE: usize = 1024;
 
+/// Maximum byte length of an entry tag.
+/// (Tls cert entries leave little room after the key material.)
+pub const MAX_TAG_BYTES: usize = 64;
+
+/// Marks the start of the optional metadata section,
+/// following an entry's key material.
+const ENTRY_META_MAGIC: &[u8] = b"lairmeta";
+
+/// Current metadata section format version.
+/// - v1: tag
+/// - v2: tag, creation timestamp
+/// - v3: tag, creation timestamp, use count, last use timestamp
+/// - v4: tag, creation timestamp, use count, last use timestamp, derivation
+/// - v5: tag, creation timestamp, use count, last use timestamp, derivation,
+///   conversion source
+/// - v6: tag, creation timestamp, use count, last use timestamp, derivation,
+///   conversion source, renewal source
+/// - v7: tag, creation timestamp, use count, last use timestamp, derivation,
+///   conversion source, renewal source, rate limit
+/// - v8: tag, creation timestamp, use count, last use timestamp, derivation,
+///   conversion source, renewal source, rate limit, signing approval
+const ENTRY_META_VERSION: u32 = 8;
+
+/// Where a derived keypair entry came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryDerivation {
+    /// Keystore index of the master seed entry.
+    pub seed_index: KeystoreIndex,
+
+    /// The derivation path, every level hardened.
+    pub path: Vec<u32>,
+}
+
+/// Optional metadata stored alongside an entry's key material.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntryMeta {
+    /// Human-readable tag, unique per store.
+    pub tag: Option<String>,
+
+    /// Creation time in unix epoch milliseconds, by the server clock.
+    /// None for entries written before timestamps were recorded.
+    pub created_at: Option<u64>,
+
+    /// How many times the entry's private key has been used
+    /// to sign, encrypt / decrypt, or been handed out (tls certs).
+    pub use_count: u64,
+
+    /// Time of the most recent use in unix epoch milliseconds,
+    /// by the server clock. None if never used.
+    pub last_used: Option<u64>,
+
+    /// The master seed and path this keypair was derived from.
+    /// None for keypairs that were not derived.
+    pub derived_from: Option<EntryDerivation>,
+
+    /// The ed25519 signature keypair entry this x25519 keypair
+    /// was converted from. None for keypairs that were not converted.
+    pub converted_from: Option<KeystoreIndex>,
+
+    /// The tls cert entry this cert was renewed from, sharing its
+    /// private key. None for certs that were not renewed.
+    pub renewed_from: Option<KeystoreIndex>,
+
+    /// How many signatures per second may be made with the entry's key.
+    /// None for the server's default (see
+    /// `ConfigBuilder::set_entry_rate_limit()`), which the server reports
+    /// here for entries without a limit of their own.
+    pub rate_limit: Option<u32>,
+
+    /// Whether every signature with the entry's key must be approved by
+    /// a signing approver (see
+    /// `LairClientApi::lair_register_signing_approver()`). Only set as
+    /// the entry is created, it cannot be cleared.
+    pub requires_approval: bool,
+}
+
+impl EntryMeta {
+    /// Metadata with the given tag.
+    pub fn with_tag(tag: Option<String>) -> Self {
+        Self {
+            tag,
+            ..Default::default()
+        }
+    }
+
+    /// Metadata for an entry being created right now, with the given tag.
+    pub fn new_created_now(tag: Option<String>) -> Self {
+        Self {
+            tag,
+            created_at: Some(now_millis()),
+            ..Default::default()
+        }
+    }
+
+    /// Require (or stop requiring) the approval of a signing approver
+    /// for every signature with the entry's key.
+    pub fn with_requires_approval(mut self, requires_approval: bool) -> Self {
+        self.requires_approval = requires_approval;
+        self
+    }
+
+    /// Count a use of the entry happening right now.
+    pub fn record_use_now(&mut self) {
+        self.use_count += 1;
+        self.last_used = Some(now_millis());
+    }
+
+    /// Check `tag` is usable as an entry tag.
+    pub fn check_tag(tag: &str) -> LairResult<()> {
+        if tag.is_empty() {
+            return Err("entry tag cannot be empty".into());
+        }
+        if tag.len() > MAX_TAG_BYTES {
+            return Err(format!(
+                "entry tag exceeds {} byte maximum",
+                MAX_TAG_BYTES
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn encode(&self, writer: &mut codec::CodecWriter) -> LairResult<()> {
+        writer.write_bytes(ENTRY_META_MAGIC)?;
+        writer.write_u32(ENTRY_META_VERSION)?;
+
+        // write tag (empty for no tag)
+        let tag = self.tag.as_deref().unwrap_or("").as_bytes();
+        writer.write_u64(tag.len() as u64)?;
+        writer.write_bytes(tag)?;
+
+        // write creation timestamp (zero for unknown)
+        writer.write_u64(self.created_at.unwrap_or(0))?;
+
+        // write usage (zero for never used)
+        writer.write_u64(self.use_count)?;
+        writer.write_u64(self.last_used.unwrap_or(0))?;
+
+        // write derivation (empty path for not derived)
+        match &self.derived_from {
+            Some(d) => {
+                writer.write_u32(d.seed_index.0)?;
+                writer.write_u64(d.path.len() as u64)?;
+                for index in d.path.iter() {
+                    writer.write_u32(*index)?;
+                }
+            }
+            None => {
+                writer.write_u32(0)?;
+                writer.write_u64(0)?;
+            }
+        }
+
+        // write conversion source (zero for not converted)
+        writer.write_u32(self.converted_from.map(|i| i.0).unwrap_or(0))?;
+
+        // write renewal source (zero for not renewed)
+        writer.write_u32(self.renewed_from.map(|i| i.0).unwrap_or(0))?;
+
+        // write rate limit (zero for the server default)
+        writer.write_u32(self.rate_limit.unwrap_or(0))?;
+
+        // write signing approval flag
+        writer.write_bytes(&[self.requires_approval as u8])?;
+
+        Ok(())
+    }
+
+    fn decode(reader: &mut codec::CodecReader<'_>) -> LairResult<Self> {
+        // entries without metadata have random fill here
+        match reader.read_bytes(ENTRY_META_MAGIC.len() as u64) {
+            Ok(magic) if magic == ENTRY_META_MAGIC => (),
+            _ => return Ok(Self::default()),
+        }
+        let version = reader.read_u32()?;
+        if version < 1 {
+            return Err("invalid entry metadata version".into());
+        }
+
+        let tag_len = reader.read_u64()?;
+        let tag =
+            String::from_utf8_lossy(reader.read_bytes(tag_len)?).to_string();
+
+        let created_at = if version >= 2 {
+            Some(reader.read_u64()?).filter(|t| *t != 0)
+        } else {
+            None
+        };
+
+        let (use_count, last_used) = if version >= 3 {
+            let use_count = reader.read_u64()?;
+            (use_count, Some(reader.read_u64()?).filter(|t| *t != 0))
+        } else {
+            (0, None)
+        };
+
+        let derived_from = if version >= 4 {
+            let seed_index = reader.read_u32()?.into();
+            let path_len = reader.read_u64()?;
+            if path_len > seed::MAX_DERIVATION_DEPTH as u64 {
+                return Err("invalid entry metadata derivation path".into());
+            }
+            let path = (0..path_len)
+                .map(|_| reader.read_u32())
+                .collect::<LairResult<Vec<_>>>()?;
+            if path.is_empty() {
+                None
+            } else {
+                Some(EntryDerivation { seed_index, path })
+            }
+        } else {
+            None
+        };
+
+        let converted_from = if version >= 5 {
+            Some(reader.read_u32()?).filter(|i| *i != 0).map(Into::into)
+        } else {
+            None
+        };
+
+        let renewed_from = if version >= 6 {
+            Some(reader.read_u32()?).filter(|i| *i != 0).map(Into::into)
+        } else {
+            None
+        };
+
+        let rate_limit = if version >= 7 {
+            Some(reader.read_u32()?).filter(|n| *n != 0)
+        } else {
+            None
+        };
+
+        let requires_approval = if version >= 8 {
+            reader.read_bytes(1)?[0] == 1
+        } else {
+            false
+        };
+
+        Ok(Self {
+            tag: if tag.is_empty() { None } else { Some(tag) },
+            created_at,
+            use_count,
+            last_used,
+            derived_from,
+            converted_from,
+            renewed_from,
+            rate_limit,
+            requires_approval,
+        })
+    }
+}
+
+/// The server clock in unix epoch milliseconds.
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 /// Enum of lair entry types for decoding.
 #[non_exhaustive]
 #[derive(Debug, Clone)]
@@ -22,6 +289,19 @@ pub enum LairEntry {
 
     /// X25519 Keypair
     X25519(EntryX25519),
+
+    /// Master Seed
+    Seed(EntrySeed),
+
+    /// Sign Secp256k1 Keypair
+    SignSecp256k1(EntrySignSecp256k1),
+
+    /// Sign Bls12381 Keypair
+    #[cfg(feature = "bls")]
+    SignBls12381(EntrySignBls12381),
+
+    /// Symmetric Secret Key
+    SecretKey(EntrySecretKey),
 }
 
 impl From<EntryTlsCert> for LairEntry {
@@ -42,91 +322,927 @@ impl From<EntryX25519> for LairEntry {
     }
 }
 
+impl From<EntrySeed> for LairEntry {
+    fn from(o: EntrySeed) -> Self {
+        Self::Seed(o)
+    }
+}
+
+impl From<EntrySignSecp256k1> for LairEntry {
+    fn from(o: EntrySignSecp256k1) -> Self {
+        Self::SignSecp256k1(o)
+    }
+}
+
+#[cfg(feature = "bls")]
+impl From<EntrySignBls12381> for LairEntry {
+    fn from(o: EntrySignBls12381) -> Self {
+        Self::SignBls12381(o)
+    }
+}
+
+impl From<EntrySecretKey> for LairEntry {
+    fn from(o: EntrySecretKey) -> Self {
+        Self::SecretKey(o)
+    }
+}
+
 impl LairEntry {
     /// Decode a disk entry.
-    /// @todo - once we're integrated with sodoken, this should decrypt too
-    ///         otherwise we would first have to load priv keys into unprotected
-    ///         memory.
+    /// Fails with `StoreLocked` for sealed entries, see `unseal_with_meta()`.
     pub fn decode(data: &[u8]) -> LairResult<LairEntry> {
+        Ok(Self::decode_with_meta(data)?.0)
+    }
+
+    /// Decode a disk entry, along with its metadata.
+    /// Fails with `StoreLocked` for sealed entries, see `unseal_with_meta()`.
+    pub fn decode_with_meta(data: &[u8]) -> LairResult<(LairEntry, EntryMeta)> {
+        Self::decode_reader(codec::CodecReader::new(data))
+    }
+
+    /// Decode a disk entry sealed with `key` (or not sealed at all),
+    /// along with its metadata.
+    pub fn unseal_with_meta(
+        data: &[u8],
+        key: &EntryStoreKey,
+    ) -> LairResult<(LairEntry, EntryMeta)> {
+        Self::decode_reader(codec::CodecReader::new_with_seal_key(data, &key.0))
+    }
+
+    /// Returns true if this disk entry is sealed,
+    /// its secret field encrypted with the store key.
+    pub fn is_sealed(data: &[u8]) -> bool {
         let mut reader = codec::CodecReader::new(data);
+        reader.read_pre_padding().is_ok()
+            && reader.read_entry_type().is_ok()
+            && reader.is_sealed()
+    }
 
+    /// The type of a sealed disk entry, readable without the store key.
+    /// Returns `None` if this is not a sealed entry.
+    pub fn sealed_entry_type(data: &[u8]) -> Option<LairEntryType> {
+        let mut reader = codec::CodecReader::new(data);
+        reader.read_pre_padding().ok()?;
+        let entry_type = reader.read_entry_type().ok()?;
+        if !reader.is_sealed() {
+            return None;
+        }
+        Some(match entry_type {
+            codec::EntryType::TlsCert | codec::EntryType::TlsCertV2 => {
+                LairEntryType::TlsCert
+            }
+            codec::EntryType::SignEd25519 => LairEntryType::SignEd25519,
+            codec::EntryType::X25519 => LairEntryType::X25519,
+            codec::EntryType::Seed => LairEntryType::Seed,
+            codec::EntryType::SignSecp256k1 => LairEntryType::SignSecp256k1,
+            #[cfg(feature = "bls")]
+            codec::EntryType::SignBls12381 => LairEntryType::SignBls12381,
+            codec::EntryType::SecretKey => LairEntryType::SecretKey,
+            _ => LairEntryType::Invalid,
+        })
+    }
+
+    fn decode_reader(
+        mut reader: codec::CodecReader<'_>,
+    ) -> LairResult<(LairEntry, EntryMeta)> {
         reader.read_pre_padding()?;
 
         let entry_type = reader.read_entry_type()?;
 
-        Ok(match entry_type {
+        let entry = match entry_type {
             codec::EntryType::TlsCert => {
-                LairEntry::TlsCert(entry_decode_tls_cert(reader)?)
+                LairEntry::TlsCert(entry_decode_tls_cert_v1(&mut reader)?)
+            }
+            codec::EntryType::TlsCertV2 => {
+                LairEntry::TlsCert(entry_decode_tls_cert(&mut reader)?)
             }
             codec::EntryType::SignEd25519 => {
-                LairEntry::SignEd25519(entry_decode_sign_ed25519(reader)?)
+                LairEntry::SignEd25519(entry_decode_sign_ed25519(&mut reader)?)
             }
             codec::EntryType::X25519 => {
-                LairEntry::X25519(entry_decode_x25519(reader)?)
+                LairEntry::X25519(entry_decode_x25519(&mut reader)?)
+            }
+            codec::EntryType::Seed => {
+                LairEntry::Seed(entry_decode_seed(&mut reader)?)
+            }
+            codec::EntryType::SignSecp256k1 => LairEntry::SignSecp256k1(
+                entry_decode_sign_secp256k1(&mut reader)?,
+            ),
+            #[cfg(feature = "bls")]
+            codec::EntryType::SignBls12381 => LairEntry::SignBls12381(
+                entry_decode_sign_bls12381(&mut reader)?,
+            ),
+            #[cfg(not(feature = "bls"))]
+            codec::EntryType::SignBls12381 => {
+                return Err(internal::sign_bls12381::bls_not_enabled());
+            }
+            codec::EntryType::SecretKey => {
+                LairEntry::SecretKey(entry_decode_secret_key(&mut reader)?)
+            }
+            codec::EntryType::Unlock => {
+                return Err("unlock entry is not a keystore entry".into());
+            }
+            codec::EntryType::Deleted => {
+                return Err("entry has been deleted".into());
+            }
+            codec::EntryType::Compacted => {
+                return Err("compacted entry is not a keystore entry".into());
+            }
+        };
+
+        let meta = EntryMeta::decode(&mut reader)?;
+
+        Ok((entry, meta))
+    }
+
+    /// Check the internal consistency of this entry:
+    /// that the pub key derives from the priv key,
+    /// the cert digest matches the cert bytes
+    /// (and the cert key is of the recorded algorithm),
+    /// or the seed id / secret key tag matches the seed / key bytes.
+    pub fn verify(&self) -> LairResult<()> {
+        match self {
+            LairEntry::TlsCert(e) => {
+                let digest =
+                    internal::tls::tls_cert_digest(e.digest_alg, &e.cert_der);
+                if digest[..] != e.cert_digest[..] {
+                    return Err("cert digest does not match cert".into());
+                }
+                if internal::tls::tls_cert_alg_of_key(&e.priv_key_der)? != e.alg
+                {
+                    return Err("cert key is not of the cert alg".into());
+                }
+            }
+            LairEntry::SignEd25519(e) => {
+                let keypair =
+                    ring::signature::Ed25519KeyPair::from_seed_and_public_key(
+                        &e.priv_key.0,
+                        &e.pub_key.0,
+                    );
+                if keypair.is_err() {
+                    return Err(
+                        "ed25519 pub key does not match priv key".into()
+                    );
+                }
+            }
+            LairEntry::X25519(e) => {
+                let pub_key =
+                    AsRef::<crypto_box::SecretKey>::as_ref(&e.priv_key)
+                        .public_key();
+                if pub_key.as_bytes() != AsRef::<[u8]>::as_ref(&e.pub_key) {
+                    return Err("x25519 pub key does not match priv key".into());
+                }
+            }
+            LairEntry::Seed(e) => {
+                if seed::seed_id(&e.seed) != e.seed_id {
+                    return Err("seed id does not match seed".into());
+                }
+            }
+            LairEntry::SignSecp256k1(e) => {
+                let keypair =
+                    sign_secp256k1::keypair_from_priv_key(e.priv_key.to_vec())?;
+                if keypair.pub_key != e.pub_key {
+                    return Err(
+                        "secp256k1 pub key does not match priv key".into()
+                    );
+                }
             }
+            #[cfg(feature = "bls")]
+            LairEntry::SignBls12381(e) => {
+                let keypair =
+                    sign_bls12381::keypair_from_priv_key(e.priv_key.to_vec())?;
+                if keypair.pub_key != e.pub_key {
+                    return Err(
+                        "bls12-381 pub key does not match priv key".into()
+                    );
+                }
+            }
+            LairEntry::SecretKey(e) => {
+                if secret_key::secret_key_tag(&e.secret_key) != e.tag {
+                    return Err("secret key tag does not match key".into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Summarize this entry's public material for `lair_list_entries`.
+    pub fn list_item(
+        &self,
+        keystore_index: KeystoreIndex,
+        meta: &EntryMeta,
+    ) -> EntryListItem {
+        let (entry_type, pub_id, cert_sni) = match self {
+            LairEntry::TlsCert(e) => (
+                LairEntryType::TlsCert,
+                e.cert_digest.0.clone(),
+                Some(e.sni.clone()),
+            ),
+            LairEntry::SignEd25519(e) => {
+                (LairEntryType::SignEd25519, e.pub_key.0.clone(), None)
+            }
+            LairEntry::X25519(e) => (
+                LairEntryType::X25519,
+                Arc::new(e.pub_key.to_bytes().to_vec()),
+                None,
+            ),
+            LairEntry::Seed(e) => {
+                (LairEntryType::Seed, e.seed_id.0.clone(), None)
+            }
+            LairEntry::SignSecp256k1(e) => {
+                (LairEntryType::SignSecp256k1, e.pub_key.pub_id(), None)
+            }
+            #[cfg(feature = "bls")]
+            LairEntry::SignBls12381(e) => {
+                (LairEntryType::SignBls12381, e.pub_key.pub_id(), None)
+            }
+            LairEntry::SecretKey(e) => {
+                (LairEntryType::SecretKey, e.tag.0.clone(), None)
+            }
+        };
+        EntryListItem {
+            keystore_index,
+            entry_type,
+            pub_id,
+            cert_digest_alg: match self {
+                LairEntry::TlsCert(e) => Some(e.digest_alg),
+                _ => None,
+            },
+            cert_sni,
+            tag: meta.tag.clone(),
+            created_at: meta.created_at,
+        }
+    }
+
+    /// Extract this entry's public material for `lair_export_entry_public`.
+    pub fn export_public(&self) -> LairResult<EntryPublic> {
+        Ok(match self {
+            LairEntry::TlsCert(e) => EntryPublic::TlsCert {
+                sni: e.sni.as_str().to_string(),
+                cert_digest: e.cert_digest.to_vec(),
+                digest_alg: e.digest_alg,
+                cert_der: e.cert_der.to_vec(),
+                not_after: internal::tls::tls_cert_not_after(&e.cert_der)?,
+                alg: e.alg,
+            },
+            LairEntry::SignEd25519(e) => EntryPublic::SignEd25519 {
+                pub_key: e.pub_key.to_vec(),
+            },
+            LairEntry::X25519(e) => EntryPublic::X25519 {
+                pub_key: e.pub_key.to_bytes().to_vec(),
+            },
+            LairEntry::Seed(e) => EntryPublic::Seed {
+                seed_id: e.seed_id.to_vec(),
+            },
+            LairEntry::SignSecp256k1(e) => EntryPublic::SignSecp256k1 {
+                pub_key: e.pub_key.to_vec(),
+            },
+            #[cfg(feature = "bls")]
+            LairEntry::SignBls12381(e) => EntryPublic::SignBls12381 {
+                pub_key: e.pub_key.to_vec(),
+            },
+            LairEntry::SecretKey(e) => EntryPublic::SecretKey {
+                tag: e.tag.to_vec(),
+            },
         })
     }
 
     /// Encode this entry for writing to disk.
-    /// @todo - once we're integrated with sodoken, this should encrypt too
-    ///         otherwise we're writing our priv key to unprotected memory.
+    /// The secret field is left in the clear, see `seal_with_meta()`.
     pub fn encode(&self) -> LairResult<Vec<u8>> {
-        match self {
-            LairEntry::TlsCert(e) => e.encode(),
-            LairEntry::SignEd25519(e) => e.encode(),
-            LairEntry::X25519(e) => e.encode(),
+        self.encode_with_meta(&EntryMeta::default())
+    }
+
+    /// Encode this entry for writing to disk, along with its metadata.
+    /// The secret field is left in the clear, see `seal_with_meta()`.
+    pub fn encode_with_meta(&self, meta: &EntryMeta) -> LairResult<Vec<u8>> {
+        self.encode_writer(meta, None)
+    }
+
+    /// Encode this entry for writing to disk, along with its metadata,
+    /// sealing its secret field with `key`.
+    pub fn seal_with_meta(
+        &self,
+        meta: &EntryMeta,
+        key: &EntryStoreKey,
+    ) -> LairResult<Vec<u8>> {
+        self.encode_writer(meta, Some(key))
+    }
+
+    fn encode_writer(
+        &self,
+        meta: &EntryMeta,
+        key: Option<&EntryStoreKey>,
+    ) -> LairResult<Vec<u8>> {
+        let mut writer = match self {
+            LairEntry::TlsCert(e) => e.encode_writer(key.is_some())?,
+            LairEntry::SignEd25519(e) => e.encode_writer()?,
+            LairEntry::X25519(e) => e.encode_writer()?,
+            LairEntry::Seed(e) => e.encode_writer()?,
+            LairEntry::SignSecp256k1(e) => e.encode_writer()?,
+            #[cfg(feature = "bls")]
+            LairEntry::SignBls12381(e) => e.encode_writer()?,
+            LairEntry::SecretKey(e) => e.encode_writer()?,
+        };
+
+        // entries without metadata keep the original format
+        if *meta != EntryMeta::default() {
+            meta.encode(&mut writer)?;
+        }
+
+        if let Some(key) = key {
+            writer.seal(&key.0)?;
+        }
+
+        let out = writer.finish()?;
+        if out.len() > ENTRY_SIZE {
+            return Err("entry metadata does not fit in the entry".into());
         }
+        Ok(out)
     }
 }
 
+/// Tls cert entry flag: the private key is not exportable.
+const TLS_CERT_FLAG_NOT_EXPORTABLE: u16 = 0x1;
+
 fn entry_decode_tls_cert(
-    mut reader: codec::CodecReader<'_>,
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<EntryTlsCert> {
+    let alg = TlsCertAlg::parse(reader.read_u16()? as u32)?;
+    let digest_alg = TlsCertDigestAlg::parse(reader.read_u16()? as u32)?;
+    let flags = reader.read_u16()?;
+
+    let sni_len = reader.read_u16()? as u64;
+    let sni = String::from_utf8_lossy(reader.read_bytes(sni_len)?).to_string();
+
+    let priv_key_der_len = reader.read_u16()? as u64;
+    let priv_key_der = SecretBytes::from(reader.read_secret(priv_key_der_len)?);
+
+    let cert_der_len = reader.read_u16()? as u64;
+    let cert_der = reader.read_bytes(cert_der_len)?.to_vec();
+
+    let cert_digest = internal::tls::tls_cert_digest(digest_alg, &cert_der);
+
+    Ok(EntryTlsCert {
+        sni: sni.into(),
+        priv_key_der: priv_key_der.into(),
+        cert_der: cert_der.into(),
+        cert_digest: cert_digest.into(),
+        digest_alg,
+        alg,
+        exportable: flags & TLS_CERT_FLAG_NOT_EXPORTABLE == 0,
+    })
+}
+
+/// Decode a tls cert entry written before its algorithms and flags
+/// were recorded: an exportable key, with a blake2b-256 cert digest.
+fn entry_decode_tls_cert_v1(
+    reader: &mut codec::CodecReader<'_>,
 ) -> LairResult<EntryTlsCert> {
     let sni_len = reader.read_u64()?;
     let sni = String::from_utf8_lossy(reader.read_bytes(sni_len)?).to_string();
 
     let priv_key_der_len = reader.read_u64()?;
-    let priv_key_der = reader.read_bytes(priv_key_der_len)?.to_vec();
+    let priv_key_der = SecretBytes::from(reader.read_secret(priv_key_der_len)?);
 
     let cert_der_len = reader.read_u64()?;
     let cert_der = reader.read_bytes(cert_der_len)?.to_vec();
 
-    let cert_digest = reader.read_bytes(32)?.to_vec();
+    let alg = internal::tls::tls_cert_alg_of_key(&priv_key_der)?;
+    let digest_alg = TlsCertDigestAlg::Blake2b256;
+
+    // sealed and checked entries give the room of the digest to the
+    // seal or checksum, deriving it from the cert
+    let cert_digest = if reader.is_sealed() || reader.is_checked() {
+        internal::tls::tls_cert_digest(digest_alg, &cert_der)
+    } else {
+        reader.read_bytes(32)?.to_vec()
+    };
 
     Ok(EntryTlsCert {
         sni: sni.into(),
         priv_key_der: priv_key_der.into(),
         cert_der: cert_der.into(),
         cert_digest: cert_digest.into(),
+        digest_alg,
+        alg,
+        exportable: true,
     })
 }
 
 fn entry_decode_sign_ed25519(
-    mut reader: codec::CodecReader<'_>,
+    reader: &mut codec::CodecReader<'_>,
 ) -> LairResult<EntrySignEd25519> {
-    let priv_key = reader.read_bytes(32)?.to_vec().into();
+    let priv_key = SecretBytes::from(reader.read_secret(32)?).into();
     let pub_key = reader.read_bytes(32)?.to_vec().into();
 
     Ok(EntrySignEd25519 { priv_key, pub_key })
 }
 
 fn entry_decode_x25519(
-    mut reader: codec::CodecReader<'_>,
+    reader: &mut codec::CodecReader<'_>,
 ) -> LairResult<EntryX25519> {
-    let priv_key_data = reader.read_bytes(x25519::PRIV_KEY_BYTES as _)?;
-    let mut priv_key = [0_u8; x25519::PRIV_KEY_BYTES];
+    let priv_key_data = reader.read_secret(x25519::PRIV_KEY_BYTES as _)?;
+    let mut priv_key = zeroize::Zeroizing::new([0_u8; x25519::PRIV_KEY_BYTES]);
     priv_key.copy_from_slice(&priv_key_data);
 
     let pub_key_data = reader.read_bytes(x25519::PUB_KEY_BYTES as _)?;
     let mut pub_key = [0_u8; x25519::PUB_KEY_BYTES];
-    pub_key.copy_from_slice(&pub_key_data);
+    pub_key.copy_from_slice(pub_key_data);
 
     Ok(EntryX25519 {
-        priv_key: priv_key.into(),
+        priv_key: (*priv_key).into(),
         pub_key: pub_key.into(),
     })
 }
 
+fn entry_decode_seed(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<EntrySeed> {
+    let seed = SecretBytes::from(reader.read_secret(seed::SEED_BYTES as _)?);
+
+    let flags = reader.read_u32()?;
+    let exportable = flags & SEED_FLAG_EXPORTABLE != 0;
+
+    Ok(EntrySeed::new(seed.into(), exportable))
+}
+
+fn entry_decode_sign_secp256k1(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<EntrySignSecp256k1> {
+    let priv_key = SecretBytes::from(
+        reader.read_secret(sign_secp256k1::PRIV_KEY_BYTES as _)?,
+    )
+    .into();
+    let pub_key = reader
+        .read_bytes(sign_secp256k1::PUB_KEY_BYTES as _)?
+        .to_vec()
+        .into();
+
+    Ok(EntrySignSecp256k1 { priv_key, pub_key })
+}
+
+#[cfg(feature = "bls")]
+fn entry_decode_sign_bls12381(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<EntrySignBls12381> {
+    let priv_key = SecretBytes::from(
+        reader.read_secret(sign_bls12381::PRIV_KEY_BYTES as _)?,
+    )
+    .into();
+    let pub_key = reader
+        .read_bytes(sign_bls12381::PUB_KEY_BYTES as _)?
+        .to_vec()
+        .into();
+
+    Ok(EntrySignBls12381 { priv_key, pub_key })
+}
+
+fn entry_decode_secret_key(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<EntrySecretKey> {
+    let secret_key = SecretBytes::from(
+        reader.read_secret(secret_key::SECRET_KEY_BYTES as _)?,
+    );
+
+    Ok(EntrySecretKey::new(secret_key.into()))
+}
+
+/// Seed entry flag: the seed may be exported as a mnemonic.
+const SEED_FLAG_EXPORTABLE: u32 = 0x01;
+
+/// Byte length of the random salt used to derive unlock material.
+pub const UNLOCK_SALT_BYTES: usize = 16;
+
+/// Byte length of the passphrase hash stored in the unlock entry.
+pub const UNLOCK_HASH_BYTES: usize = 32;
+
+/// Marks the kdf section following the passphrase hash
+/// of unlock entries that derive a store key.
+const UNLOCK_KDF_MAGIC: &[u8] = b"lairkdf\0";
+
+/// Unlock kdf section identifier for argon2id.
+const UNLOCK_KDF_ARGON2ID: u32 = 1;
+
+/// Argon2id memory cost, in KiB, of new unlock entries by default
+/// (see `ConfigBuilder::set_unlock_kdf()`). `cargo bench --bench unlock`
+/// measures the unlock latency of the defaults.
+pub const UNLOCK_ARGON2ID_MEM_KIB: u32 = 19 * 1024;
+
+/// Argon2id iteration count of new unlock entries by default.
+pub const UNLOCK_ARGON2ID_ITERATIONS: u32 = 2;
+
+/// Argon2id parallelism of new unlock entries by default.
+pub const UNLOCK_ARGON2ID_PARALLELISM: u32 = 1;
+
+/// How an unlock entry derives its passphrase hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnlockKdf {
+    /// Argon2id, deriving both the passphrase hash and the store key.
+    Argon2id {
+        /// memory cost in KiB
+        mem_kib: u32,
+
+        /// number of iterations
+        iterations: u32,
+
+        /// degree of parallelism
+        parallelism: u32,
+    },
+}
+
+impl Default for UnlockKdf {
+    fn default() -> Self {
+        UnlockKdf::Argon2id {
+            mem_kib: UNLOCK_ARGON2ID_MEM_KIB,
+            iterations: UNLOCK_ARGON2ID_ITERATIONS,
+            parallelism: UNLOCK_ARGON2ID_PARALLELISM,
+        }
+    }
+}
+
+impl UnlockKdf {
+    /// Argon2id with the given costs, which argon2 must accept:
+    /// at least one iteration and lane, and 8 KiB of memory per lane.
+    pub fn argon2id(
+        mem_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> LairResult<Self> {
+        if iterations < 1
+            || parallelism < 1
+            || (mem_kib as u64) < 8 * parallelism as u64
+        {
+            return Err(LairError::other(format!(
+                "invalid argon2id parameters: {} KiB, {} iterations, \
+                parallelism {}",
+                mem_kib, iterations, parallelism
+            )));
+        }
+        Ok(UnlockKdf::Argon2id {
+            mem_kib,
+            iterations,
+            parallelism,
+        })
+    }
+
+    /// The kdf an unlock entry of this kdf is upgraded to, if `target`
+    /// asks for more memory or iterations. Costs are only ever raised,
+    /// the parallelism is that of `target`.
+    pub fn upgrade_to(&self, target: UnlockKdf) -> Option<UnlockKdf> {
+        let UnlockKdf::Argon2id {
+            mem_kib,
+            iterations,
+            parallelism,
+        } = target;
+        match *self {
+            UnlockKdf::Argon2id {
+                mem_kib: cur_mem_kib,
+                iterations: cur_iterations,
+                ..
+            } if mem_kib > cur_mem_kib || iterations > cur_iterations => {
+                Some(UnlockKdf::Argon2id {
+                    mem_kib: mem_kib.max(cur_mem_kib),
+                    iterations: iterations.max(cur_iterations),
+                    parallelism,
+                })
+            }
+            UnlockKdf::Argon2id { .. } => None,
+        }
+    }
+}
+
+/// The key sealing the secret fields of store entries at rest,
+/// derived from the unlock passphrase.
+pub struct EntryStoreKey(
+    Box<zeroize::Zeroizing<[u8; codec::SEAL_KEY_BYTES]>>,
+    // held for its Drop only, after the key is zeroized
+    #[allow(dead_code)]
+    MemLock,
+);
+
+impl EntryStoreKey {
+    /// A zeroed key, locked in memory before it is filled in.
+    fn new_zeroed() -> Self {
+        let key = Box::new(zeroize::Zeroizing::new([0; codec::SEAL_KEY_BYTES]));
+        let lock = MemLock::new(&key[..]);
+        Self(key, lock)
+    }
+
+    /// A random key, for checking what fits in a sealed entry.
+    pub(crate) fn new_random() -> LairResult<Self> {
+        let mut key = Self::new_zeroed();
+        let sys_rand = ring::rand::SystemRandom::new();
+        ring::rand::SecureRandom::fill(&sys_rand, &mut key.0[..])
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(key)
+    }
+}
+
+impl std::fmt::Debug for EntryStoreKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EntryStoreKey(<secret>)")
+    }
+}
+
+/// File format entry representing the store unlock material.
+/// This is always the first entry (index zero) in the store file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryUnlock {
+    /// Random salt used when deriving the passphrase hash.
+    pub salt: [u8; UNLOCK_SALT_BYTES],
+
+    /// Hash derived from the unlock passphrase and salt.
+    pub passphrase_hash: [u8; UNLOCK_HASH_BYTES],
+
+    /// How the passphrase hash (and store key) are derived.
+    pub kdf: UnlockKdf,
+}
+
+impl EntryUnlock {
+    /// Derive new unlock material and store key from a passphrase
+    /// with a random salt, using `kdf`
+    /// (usually `Config::get_unlock_kdf()`).
+    pub async fn new_from_passphrase(
+        passphrase: SecretBytes,
+        kdf: UnlockKdf,
+    ) -> LairResult<(Self, EntryStoreKey)> {
+        let mut salt = [0; UNLOCK_SALT_BYTES];
+        let sys_rand = ring::rand::SystemRandom::new();
+        ring::rand::SecureRandom::fill(&sys_rand, &mut salt)
+            .map_err(|e| format!("{:?}", e))?;
+        let passphrase = normalize_passphrase(passphrase);
+        let (passphrase_hash, key) =
+            rayon_exec(move || unlock_derive(kdf, &salt, passphrase)).await?;
+        Ok((
+            Self {
+                salt,
+                passphrase_hash,
+                kdf,
+            },
+            key,
+        ))
+    }
+
+    /// Verify the given passphrase against this unlock entry,
+    /// returning the store key derived along with its hash.
+    pub async fn unlock(
+        &self,
+        passphrase: SecretBytes,
+    ) -> LairResult<EntryStoreKey> {
+        let passphrase = normalize_passphrase(passphrase);
+        let salt = self.salt;
+        let kdf = self.kdf;
+        let (hash, key) =
+            rayon_exec(move || unlock_derive(kdf, &salt, passphrase)).await?;
+        use subtle::ConstantTimeEq;
+        if !bool::from(hash.ct_eq(&self.passphrase_hash)) {
+            return Err(LairError::WrongPassphrase);
+        }
+        Ok(key)
+    }
+
+    /// Returns true if the given passphrase matches this unlock entry.
+    pub async fn verify_passphrase(&self, passphrase: SecretBytes) -> bool {
+        self.unlock(passphrase).await.is_ok()
+    }
+
+    /// Decode the unlock entry from disk.
+    /// Returns `None` if this is a legacy all-zero stub unlock entry.
+    pub fn decode(data: &[u8]) -> LairResult<Option<Self>> {
+        if data.iter().all(|b| *b == 0) {
+            return Ok(None);
+        }
+
+        let mut reader = codec::CodecReader::new(data);
+
+        reader.read_pre_padding()?;
+
+        if reader.read_entry_type()? != codec::EntryType::Unlock {
+            return Err("invalid unlock entry type".into());
+        }
+
+        let mut salt = [0; UNLOCK_SALT_BYTES];
+        salt.copy_from_slice(reader.read_bytes(UNLOCK_SALT_BYTES as u64)?);
+
+        let mut passphrase_hash = [0; UNLOCK_HASH_BYTES];
+        passphrase_hash
+            .copy_from_slice(reader.read_bytes(UNLOCK_HASH_BYTES as u64)?);
+
+        if reader.read_bytes(UNLOCK_KDF_MAGIC.len() as u64)? != UNLOCK_KDF_MAGIC
+        {
+            return Err("invalid unlock entry kdf".into());
+        }
+        let kdf = match reader.read_u32()? {
+            UNLOCK_KDF_ARGON2ID => UnlockKdf::Argon2id {
+                mem_kib: reader.read_u32()?,
+                iterations: reader.read_u32()?,
+                parallelism: reader.read_u32()?,
+            },
+            _ => return Err("invalid unlock entry kdf".into()),
+        };
+
+        Ok(Some(Self {
+            salt,
+            passphrase_hash,
+            kdf,
+        }))
+    }
+
+    /// Encode this unlock entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // unlock entry type
+        writer.write_entry_type(codec::EntryType::Unlock)?;
+
+        // write salt (always 16 bytes)
+        writer.write_bytes(&self.salt)?;
+
+        // write passphrase hash (always 32 bytes)
+        writer.write_bytes(&self.passphrase_hash)?;
+
+        // write kdf
+        let UnlockKdf::Argon2id {
+            mem_kib,
+            iterations,
+            parallelism,
+        } = self.kdf;
+        writer.write_bytes(UNLOCK_KDF_MAGIC)?;
+        writer.write_u32(UNLOCK_KDF_ARGON2ID)?;
+        writer.write_u32(mem_kib)?;
+        writer.write_u32(iterations)?;
+        writer.write_u32(parallelism)?;
+
+        writer.finish()
+    }
+}
+
+/// Normalize a passphrase given as UTF-8 to its NFKD form, so that
+/// e.g. "é" typed as one composed code point or as "e" followed by a
+/// combining accent unlocks the same store. Passphrases that aren't
+/// UTF-8 are used verbatim.
+pub fn normalize_passphrase(passphrase: SecretBytes) -> SecretBytes {
+    use unicode_normalization::UnicodeNormalization;
+    let s = match std::str::from_utf8(&passphrase) {
+        Ok(s) => s,
+        Err(_) => return passphrase,
+    };
+    // sized up front, so no partial copy is left behind by a realloc
+    let len = s.nfkd().map(char::len_utf8).sum();
+    let mut out = zeroize::Zeroizing::new(Vec::with_capacity(len));
+    let mut buf = [0; 4];
+    for c in s.nfkd() {
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    zeroize::Zeroize::zeroize(&mut buf);
+    out.into()
+}
+
+/// Derive the passphrase hash and the store key.
+fn unlock_derive(
+    kdf: UnlockKdf,
+    salt: &[u8; UNLOCK_SALT_BYTES],
+    passphrase: SecretBytes,
+) -> LairResult<([u8; UNLOCK_HASH_BYTES], EntryStoreKey)> {
+    let UnlockKdf::Argon2id {
+        mem_kib,
+        iterations,
+        parallelism,
+    } = kdf;
+
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        version: argon2::Version::Version13,
+        mem_cost: mem_kib,
+        time_cost: iterations,
+        lanes: parallelism,
+        thread_mode: argon2::ThreadMode::from_threads(parallelism),
+        secret: &[],
+        ad: &[],
+        hash_length: 32,
+    };
+    let master = zeroize::Zeroizing::new(
+        argon2::hash_raw(&passphrase, salt, &config)
+            .map_err(LairError::other)?,
+    );
+
+    // the stored hash and the store key are independent subkeys,
+    // the hash reveals nothing about the key
+    let subkey = |personal: &[u8; 16]| {
+        let mut out = zeroize::Zeroizing::new([0; 32]);
+        out.copy_from_slice(
+            blake2b_simd::Params::new()
+                .hash_length(32)
+                .key(&master)
+                .personal(personal)
+                .hash(&[])
+                .as_bytes(),
+        );
+        out
+    };
+    let mut key = EntryStoreKey::new_zeroed();
+    key.0.copy_from_slice(&*subkey(b"lair-store-key\0\0"));
+    Ok((*subkey(b"lair-unlock-pass"), key))
+}
+
+/// File format entry marking a deleted keystore index.
+/// It overwrites the deleted entry in the store file, erasing its
+/// key material while keeping its index from ever being reused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntryDeleted;
+
+impl EntryDeleted {
+    /// Returns true if this disk entry is a deleted entry tombstone.
+    pub fn is_deleted(data: &[u8]) -> bool {
+        let mut reader = codec::CodecReader::new(data);
+        reader.read_pre_padding().is_ok()
+            && matches!(reader.read_entry_type(), Ok(codec::EntryType::Deleted))
+    }
+
+    /// Encode a tombstone for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // deleted entry type, the rest is random fill
+        writer.write_entry_type(codec::EntryType::Deleted)?;
+
+        writer.finish()
+    }
+}
+
+/// File format entry listing deleted keystore indices that have no
+/// tombstone, dropped from the store file by compaction. These blocks
+/// lead the entries of a compacted store file, and the entries after
+/// them take the indices not listed, in order, so compaction leaves
+/// the index of every remaining entry as it was.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntryCompacted {
+    /// The deleted indices, in ascending order.
+    pub deleted: Vec<u32>,
+}
+
+impl EntryCompacted {
+    /// The most indices a single compacted entry lists.
+    pub const MAX_INDICES: usize = (ENTRY_SIZE - 64 - 8 - 4) / 4;
+
+    /// Returns true if this disk entry is a compacted entry.
+    pub fn is_compacted(data: &[u8]) -> bool {
+        let mut reader = codec::CodecReader::new(data);
+        reader.read_pre_padding().is_ok()
+            && matches!(
+                reader.read_entry_type(),
+                Ok(codec::EntryType::Compacted)
+            )
+    }
+
+    /// Decode a compacted entry from disk.
+    pub fn decode(data: &[u8]) -> LairResult<Self> {
+        let mut reader = codec::CodecReader::new(data);
+        reader.read_pre_padding()?;
+        if reader.read_entry_type()? != codec::EntryType::Compacted {
+            return Err("not a compacted entry".into());
+        }
+        let count = reader.read_u32()? as usize;
+        if count > Self::MAX_INDICES {
+            return Err("invalid compacted entry index count".into());
+        }
+        let mut deleted = Vec::with_capacity(count);
+        for _ in 0..count {
+            deleted.push(reader.read_u32()?);
+        }
+        Ok(Self { deleted })
+    }
+
+    /// Encode this compacted entry for writing to disk.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        if self.deleted.len() > Self::MAX_INDICES {
+            return Err("too many indices for one compacted entry".into());
+        }
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // compacted entry type
+        writer.write_entry_type(codec::EntryType::Compacted)?;
+
+        // write the deleted indices
+        writer.write_u32(self.deleted.len() as u32)?;
+        for index in self.deleted.iter() {
+            writer.write_u32(*index)?;
+        }
+
+        writer.finish()
+    }
+}
+
 /// File format entry representing Tls Certificate data.
 #[derive(Debug, Clone)]
 pub struct EntryTlsCert {
@@ -134,46 +1250,101 @@ pub struct EntryTlsCert {
     pub sni: CertSni,
 
     /// Private key bytes.
-    /// @todo - once we're integrated with sodoken, make this a priv buffer.
     pub priv_key_der: CertPrivKey,
 
     /// Certificate bytes.
     pub cert_der: Cert,
 
-    /// 32 byte blake2b certificate digest.
-    pub cert_digest: CertDigest,
-}
+    /// 32 byte certificate digest.
+    pub cert_digest: CertDigest,
+
+    /// The algorithm of `cert_digest`.
+    pub digest_alg: TlsCertDigestAlg,
+
+    /// The algorithm of the keypair, telling how to read `priv_key_der`.
+    pub alg: TlsCertAlg,
+
+    /// Whether `priv_key_der` may leave the keystore,
+    /// see `exportable_priv_key`.
+    pub exportable: bool,
+}
+
+impl EntryTlsCert {
+    /// Encode this entry for writing to disk.
+    /// The secret is left in the clear, see `LairEntry::seal_with_meta()`.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer(false)?.finish()
+    }
+
+    /// The private key, unless the cert was created not exportable.
+    pub fn exportable_priv_key(&self) -> LairResult<CertPrivKey> {
+        if !self.exportable {
+            return Err(LairError::NotExportable);
+        }
+        Ok(self.priv_key_der.clone())
+    }
+
+    /// Sign a tls handshake message with this entry's private key,
+    /// whether or not it is exportable.
+    #[allow(clippy::rc_buffer)]
+    pub fn sign(
+        &self,
+        scheme: TlsCertSignatureScheme,
+        message: Arc<Vec<u8>>,
+    ) -> impl std::future::Future<Output = LairResult<Arc<Vec<u8>>>> + 'static
+    {
+        internal::tls::tls_cert_sign(
+            self.alg,
+            self.priv_key_der.clone(),
+            scheme,
+            message,
+        )
+    }
 
-impl EntryTlsCert {
-    /// Encode this entry for writing to disk.
-    /// @todo - once we're integrated with sodoken, this should encrypt too
-    ///         otherwise we're writing our priv key to unprotected memory.
-    pub fn encode(&self) -> LairResult<Vec<u8>> {
+    fn encode_writer(&self, sealed: bool) -> LairResult<codec::CodecWriter> {
         let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
 
         // pre padding
-        writer.write_pre_padding(16)?;
+        // (sealed entries keep the nonce and tag of the seal here,
+        // the others their checksum)
+        writer.write_pre_padding(if sealed {
+            codec::SEALED_PRE_PADDING
+        } else {
+            codec::CHECKED_PRE_PADDING
+        })?;
 
         // tls cert entry type
-        writer.write_entry_type(codec::EntryType::TlsCert)?;
+        writer.write_entry_type(codec::EntryType::TlsCertV2)?;
+
+        // write keypair and digest algorithms, and flags
+        // (entries have little room to spare, these and the lengths
+        // that follow are 16 bit)
+        writer.write_u16(self.alg as u16)?;
+        writer.write_u16(self.digest_alg as u16)?;
+        writer.write_u16(if self.exportable {
+            0
+        } else {
+            TLS_CERT_FLAG_NOT_EXPORTABLE
+        })?;
 
         // write sni
         let sni_bytes = self.sni.as_bytes();
-        writer.write_u64(sni_bytes.len() as u64)?;
+        writer.write_u16(sni_bytes.len() as u16)?;
         writer.write_bytes(sni_bytes)?;
 
         // write priv key
-        writer.write_u64(self.priv_key_der.len() as u64)?;
-        writer.write_bytes(&self.priv_key_der)?;
+        writer.write_u16(self.priv_key_der.len() as u16)?;
+        writer.write_secret(&self.priv_key_der)?;
 
         // write cert
-        writer.write_u64(self.cert_der.len() as u64)?;
+        writer.write_u16(self.cert_der.len() as u16)?;
         writer.write_bytes(&self.cert_der)?;
 
-        // write digest (always 32 bytes)
-        writer.write_bytes(&self.cert_digest[0..32])?;
+        // the digest is derived from the cert, entries written before
+        // they were checked stored it after the cert instead
+        // (see `entry_decode_tls_cert_v1()`)
 
-        Ok(writer.into_vec())
+        Ok(writer)
     }
 }
 
@@ -191,6 +1362,10 @@ pub struct EntryX25519 {
 impl EntryX25519 {
     /// Encode an X25519 keypair for storage.
     pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer()?.finish()
+    }
+
+    fn encode_writer(&self) -> LairResult<codec::CodecWriter> {
         let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
 
         // pre padding
@@ -200,13 +1375,13 @@ impl EntryX25519 {
         writer.write_entry_type(codec::EntryType::X25519)?;
 
         // write priv_key (always 32 bytes)
-        // @todo cloning a private key probably not great
-        writer.write_bytes(&self.priv_key.to_bytes())?;
+        let priv_key = zeroize::Zeroizing::new(self.priv_key.to_bytes());
+        writer.write_secret(&*priv_key)?;
 
         // write pub_key (always 32 bytes)
         writer.write_bytes(&AsRef::<[u8]>::as_ref(&self.pub_key)[0..32])?;
 
-        Ok(writer.into_vec())
+        Ok(writer)
     }
 }
 
@@ -214,7 +1389,6 @@ impl EntryX25519 {
 #[derive(Debug, Clone)]
 pub struct EntrySignEd25519 {
     /// Private key bytes.
-    /// @todo - once we're integrated with sodoken, make this a priv buffer.
     pub priv_key: sign_ed25519::SignEd25519PrivKey,
 
     /// Public key bytes.
@@ -223,9 +1397,12 @@ pub struct EntrySignEd25519 {
 
 impl EntrySignEd25519 {
     /// Encode this entry for writing to disk.
-    /// @todo - once we're integrated with sodoken, this should encrypt too
-    ///         otherwise we're writing our priv key to unprotected memory.
+    /// The secret is left in the clear, see `LairEntry::seal_with_meta()`.
     pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer()?.finish()
+    }
+
+    fn encode_writer(&self) -> LairResult<codec::CodecWriter> {
         let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
 
         // pre padding
@@ -235,19 +1412,18 @@ impl EntrySignEd25519 {
         writer.write_entry_type(codec::EntryType::SignEd25519)?;
 
         // write priv_key (always 32 bytes)
-        writer.write_bytes(&self.priv_key[0..32])?;
+        writer.write_secret(&self.priv_key[0..32])?;
 
         // write pub_key (always 32 bytes)
         writer.write_bytes(&self.pub_key[0..32])?;
 
-        Ok(writer.into_vec())
+        Ok(writer)
     }
 
     /// Create a signature for given message with this entry's priv_key.
-    #[allow(clippy::rc_buffer)]
     pub fn sign(
         &self,
-        message: Arc<Vec<u8>>,
+        message: bytes::Bytes,
     ) -> impl std::future::Future<
         Output = LairResult<sign_ed25519::SignEd25519Signature>,
     > + 'static {
@@ -256,9 +1432,655 @@ impl EntrySignEd25519 {
     }
 }
 
+/// File format entry representing Sign Secp256k1 Keypair data.
+#[derive(Debug, Clone)]
+pub struct EntrySignSecp256k1 {
+    /// Private key bytes.
+    pub priv_key: sign_secp256k1::SignSecp256k1PrivKey,
+
+    /// Public key bytes (SEC1 compressed).
+    pub pub_key: sign_secp256k1::SignSecp256k1PubKey,
+}
+
+impl EntrySignSecp256k1 {
+    /// Encode this entry for writing to disk.
+    /// The secret is left in the clear, see `LairEntry::seal_with_meta()`.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer()?.finish()
+    }
+
+    fn encode_writer(&self) -> LairResult<codec::CodecWriter> {
+        if self.priv_key.len() != sign_secp256k1::PRIV_KEY_BYTES {
+            return Err(LairError::wrong_length(
+                "secp256k1 priv key",
+                sign_secp256k1::PRIV_KEY_BYTES,
+                self.priv_key.len(),
+            ));
+        }
+        if self.pub_key.len() != sign_secp256k1::PUB_KEY_BYTES {
+            return Err(LairError::wrong_length(
+                "secp256k1 pub key",
+                sign_secp256k1::PUB_KEY_BYTES,
+                self.pub_key.len(),
+            ));
+        }
+
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign secp256k1 entry type
+        writer.write_entry_type(codec::EntryType::SignSecp256k1)?;
+
+        // write priv_key (always 32 bytes)
+        writer.write_secret(&self.priv_key)?;
+
+        // write pub_key (always 33 bytes)
+        writer.write_bytes(&self.pub_key)?;
+
+        Ok(writer)
+    }
+}
+
+/// File format entry representing Sign Bls12381 Keypair data.
+#[cfg(feature = "bls")]
+#[derive(Debug, Clone)]
+pub struct EntrySignBls12381 {
+    /// Private key bytes.
+    pub priv_key: sign_bls12381::SignBls12381PrivKey,
+
+    /// Public key bytes (compressed G1 point).
+    pub pub_key: sign_bls12381::SignBls12381PubKey,
+}
+
+#[cfg(feature = "bls")]
+impl EntrySignBls12381 {
+    /// Encode this entry for writing to disk.
+    /// The secret is left in the clear, see `LairEntry::seal_with_meta()`.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer()?.finish()
+    }
+
+    fn encode_writer(&self) -> LairResult<codec::CodecWriter> {
+        if self.priv_key.len() != sign_bls12381::PRIV_KEY_BYTES {
+            return Err(LairError::wrong_length(
+                "bls12-381 priv key",
+                sign_bls12381::PRIV_KEY_BYTES,
+                self.priv_key.len(),
+            ));
+        }
+        if self.pub_key.len() != sign_bls12381::PUB_KEY_BYTES {
+            return Err(LairError::wrong_length(
+                "bls12-381 pub key",
+                sign_bls12381::PUB_KEY_BYTES,
+                self.pub_key.len(),
+            ));
+        }
+
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // sign bls12381 entry type
+        writer.write_entry_type(codec::EntryType::SignBls12381)?;
+
+        // write priv_key (always 32 bytes)
+        writer.write_secret(&self.priv_key)?;
+
+        // write pub_key (always 48 bytes)
+        writer.write_bytes(&self.pub_key)?;
+
+        Ok(writer)
+    }
+}
+
+/// File format entry representing a master seed,
+/// from which ed25519 signature keypairs can be derived.
+#[derive(Debug, Clone)]
+pub struct EntrySeed {
+    /// Seed bytes.
+    pub seed: seed::Seed,
+
+    /// Public identifier of the seed (not stored, derived from the seed).
+    pub seed_id: seed::SeedId,
+
+    /// Whether the seed may ever leave the store as a mnemonic.
+    /// Fixed when the seed is created.
+    pub exportable: bool,
+}
+
+impl EntrySeed {
+    /// Construct a seed entry, computing its seed id.
+    pub fn new(seed: seed::Seed, exportable: bool) -> Self {
+        let seed_id = seed::seed_id(&seed);
+        Self {
+            seed,
+            seed_id,
+            exportable,
+        }
+    }
+
+    /// Encode this entry for writing to disk.
+    /// The secret is left in the clear, see `LairEntry::seal_with_meta()`.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer()?.finish()
+    }
+
+    fn encode_writer(&self) -> LairResult<codec::CodecWriter> {
+        if self.seed.len() != seed::SEED_BYTES {
+            return Err(LairError::wrong_length(
+                "seed",
+                seed::SEED_BYTES,
+                self.seed.len(),
+            ));
+        }
+
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // seed entry type
+        writer.write_entry_type(codec::EntryType::Seed)?;
+
+        // write seed (always 32 bytes)
+        writer.write_secret(&self.seed)?;
+
+        // write flags
+        let mut flags = 0;
+        if self.exportable {
+            flags |= SEED_FLAG_EXPORTABLE;
+        }
+        writer.write_u32(flags)?;
+
+        Ok(writer)
+    }
+}
+
+/// File format entry representing a symmetric secret key,
+/// used for xchacha20-poly1305 encryption.
+#[derive(Debug, Clone)]
+pub struct EntrySecretKey {
+    /// Secret key bytes.
+    pub secret_key: secret_key::SecretKey,
+
+    /// Public identifier of the key (not stored, derived from the key).
+    pub tag: secret_key::SecretKeyTag,
+}
+
+impl EntrySecretKey {
+    /// Construct a secret key entry, computing its tag.
+    pub fn new(secret_key: secret_key::SecretKey) -> Self {
+        let tag = secret_key::secret_key_tag(&secret_key);
+        Self { secret_key, tag }
+    }
+
+    /// Encode this entry for writing to disk.
+    /// The secret is left in the clear, see `LairEntry::seal_with_meta()`.
+    pub fn encode(&self) -> LairResult<Vec<u8>> {
+        self.encode_writer()?.finish()
+    }
+
+    fn encode_writer(&self) -> LairResult<codec::CodecWriter> {
+        if self.secret_key.len() != secret_key::SECRET_KEY_BYTES {
+            return Err(LairError::wrong_length(
+                "secret key",
+                secret_key::SECRET_KEY_BYTES,
+                self.secret_key.len(),
+            ));
+        }
+
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE)?;
+
+        // pre padding
+        writer.write_pre_padding(64)?;
+
+        // secret key entry type
+        writer.write_entry_type(codec::EntryType::SecretKey)?;
+
+        // write secret key (always 32 bytes)
+        writer.write_secret(&self.secret_key)?;
+
+        Ok(writer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use internal::tls;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_encode_and_decode_unlock_entry() {
+        assert_eq!(None, EntryUnlock::decode(&[0; ENTRY_SIZE]).unwrap());
+
+        let (e, key) = EntryUnlock::new_from_passphrase(
+            b"test".to_vec().into(),
+            UnlockKdf::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(UnlockKdf::default(), e.kdf);
+        let d = e.encode().unwrap();
+        let e2 = EntryUnlock::decode(&d).unwrap().unwrap();
+        assert_eq!(e, e2);
+        assert!(e2.verify_passphrase(b"test".to_vec().into()).await);
+        assert!(!e2.verify_passphrase(b"nope".to_vec().into()).await);
+        assert!(LairEntry::decode(&d).is_err());
+
+        // the same passphrase derives the same store key
+        let key2 = e2.unlock(b"test".to_vec().into()).await.unwrap();
+        assert_eq!(*key.0, *key2.0);
+        assert_ne!(&key.0[..], &e.passphrase_hash[..]);
+        assert!(e2.unlock(b"nope".to_vec().into()).await.is_err());
+        assert_eq!("EntryStoreKey(<secret>)", format!("{:?}", key));
+
+        // every unlock entry names its kdf
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE).unwrap();
+        writer.write_pre_padding(64).unwrap();
+        writer.write_entry_type(codec::EntryType::Unlock).unwrap();
+        writer.write_bytes(&e.salt).unwrap();
+        writer.write_bytes(&e.passphrase_hash).unwrap();
+        let no_kdf = writer.finish().unwrap();
+        assert!(EntryUnlock::decode(&no_kdf).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn unlock_kdf_params_are_stored_and_only_raised() {
+        let weak = UnlockKdf::argon2id(64, 1, 1).unwrap();
+        let (e, _) =
+            EntryUnlock::new_from_passphrase(b"test".to_vec().into(), weak)
+                .await
+                .unwrap();
+        let e2 = EntryUnlock::decode(&e.encode().unwrap()).unwrap().unwrap();
+        assert_eq!(weak, e2.kdf);
+        assert!(e2.verify_passphrase(b"test".to_vec().into()).await);
+
+        assert!(UnlockKdf::argon2id(64, 0, 1).is_err());
+        assert!(UnlockKdf::argon2id(64, 1, 0).is_err());
+        assert!(UnlockKdf::argon2id(15, 1, 2).is_err());
+
+        let strong = UnlockKdf::default();
+        assert_eq!(None, weak.upgrade_to(weak));
+        assert_eq!(None, strong.upgrade_to(weak));
+        assert_eq!(Some(strong), weak.upgrade_to(strong));
+        // each cost is raised on its own
+        let more_mem = UnlockKdf::argon2id(128, 1, 1).unwrap();
+        let more_iter = UnlockKdf::argon2id(64, 3, 1).unwrap();
+        assert_eq!(
+            Some(UnlockKdf::argon2id(128, 3, 1).unwrap()),
+            more_iter.upgrade_to(more_mem)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn passphrases_unlock_in_any_normal_form() {
+        let composed = || "caf\u{e9}".to_string().into();
+        let decomposed = || "cafe\u{301}".to_string().into();
+        assert_eq!(
+            normalize_passphrase(composed()),
+            normalize_passphrase(decomposed())
+        );
+        // not utf8, kept as is
+        assert_eq!(
+            &[0xff, 0xfe][..],
+            &*normalize_passphrase(vec![0xff, 0xfe].into())
+        );
+
+        let kdf = UnlockKdf::argon2id(64, 1, 1).unwrap();
+        let (e, key) = EntryUnlock::new_from_passphrase(composed(), kdf)
+            .await
+            .unwrap();
+        let key2 = e.unlock(decomposed()).await.unwrap();
+        assert_eq!(&key.0[..], &key2.0[..]);
+        assert!(e.verify_passphrase(composed()).await);
+        assert!(!e.verify_passphrase("cafe".to_string().into()).await);
+    }
+
+    fn secret_of(e: &LairEntry) -> Vec<u8> {
+        match e {
+            LairEntry::TlsCert(e) => e.priv_key_der.to_vec(),
+            LairEntry::SignEd25519(e) => e.priv_key.to_vec(),
+            LairEntry::X25519(e) => e.priv_key.to_bytes().to_vec(),
+            LairEntry::Seed(e) => e.seed.to_vec(),
+            LairEntry::SecretKey(e) => e.secret_key.to_vec(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_seal_and_unseal_entries() {
+        let key = EntryStoreKey::new_random().unwrap();
+        let other_key = EntryStoreKey::new_random().unwrap();
+
+        let cert = internal::tls::tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions::default(),
+        )
+        .await
+        .unwrap();
+        let entries: Vec<LairEntry> = vec![
+            cert.into(),
+            EntrySignEd25519 {
+                priv_key: vec![0xdb; 32].into(),
+                pub_key: vec![0x42; 32].into(),
+            }
+            .into(),
+            EntryX25519 {
+                priv_key: [0xdb; 32].into(),
+                pub_key: [0x42; 32].into(),
+            }
+            .into(),
+            EntrySeed::new(vec![0xdb; seed::SEED_BYTES].into(), false).into(),
+            EntrySecretKey::new(
+                vec![0xdb; secret_key::SECRET_KEY_BYTES].into(),
+            )
+            .into(),
+        ];
+
+        for e in entries {
+            let secret = secret_of(&e);
+            let meta = EntryMeta::new_created_now(Some("tag".to_string()));
+            let d = e.seal_with_meta(&meta, &key).unwrap();
+            assert_eq!(ENTRY_SIZE, d.len());
+            assert!(LairEntry::is_sealed(&d));
+            assert_ne!(
+                Some(LairEntryType::Invalid),
+                LairEntry::sealed_entry_type(&d)
+            );
+            assert!(!d.windows(secret.len()).any(|w| w == &secret[..]));
+
+            let (e2, meta2) = LairEntry::unseal_with_meta(&d, &key).unwrap();
+            assert_eq!(secret, secret_of(&e2));
+            assert_eq!(meta, meta2);
+            assert_eq!(
+                format!("{:?}", e.export_public().unwrap()),
+                format!("{:?}", e2.export_public().unwrap()),
+            );
+
+            // the secret is only readable with the key
+            assert!(matches!(
+                LairEntry::decode_with_meta(&d),
+                Err(LairError::StoreLocked)
+            ));
+            assert!(LairEntry::unseal_with_meta(&d, &other_key).is_err());
+
+            // a flipped bit in the nonce fails to open
+            let mut tampered = d.clone();
+            tampered[8] ^= 0x01;
+            assert!(LairEntry::unseal_with_meta(&tampered, &key).is_err());
+
+            // so does one in the public part of the entry
+            let mut tampered = d.clone();
+            tampered[ENTRY_SIZE - 1] ^= 0x01;
+            assert!(LairEntry::unseal_with_meta(&tampered, &key).is_err());
+
+            // entries that are not sealed still unseal
+            let plain = e.encode_with_meta(&meta).unwrap();
+            assert!(!LairEntry::is_sealed(&plain));
+            assert_eq!(None, LairEntry::sealed_entry_type(&plain));
+            let (_, meta3) = LairEntry::unseal_with_meta(&plain, &key).unwrap();
+            assert_eq!(meta, meta3);
+        }
+    }
+
+    #[test]
+    fn it_never_debugs_secrets() {
+        let entries: Vec<LairEntry> = vec![
+            EntryTlsCert {
+                sni: "test".to_string().into(),
+                priv_key_der: vec![0xdb; 32].into(),
+                cert_der: vec![0x42; 32].into(),
+                cert_digest: vec![0x42; 32].into(),
+                digest_alg: TlsCertDigestAlg::Blake2b256,
+                alg: TlsCertAlg::PkcsEd25519,
+                exportable: true,
+            }
+            .into(),
+            EntrySignEd25519 {
+                priv_key: vec![0xdb; 32].into(),
+                pub_key: vec![0x42; 32].into(),
+            }
+            .into(),
+            EntryX25519 {
+                priv_key: [0xdb; 32].into(),
+                pub_key: [0x42; 32].into(),
+            }
+            .into(),
+            EntrySignSecp256k1 {
+                priv_key: vec![0xdb; 32].into(),
+                pub_key: vec![0x42; 33].into(),
+            }
+            .into(),
+            EntrySeed::new(vec![0xdb; seed::SEED_BYTES].into(), false).into(),
+            EntrySecretKey::new(
+                vec![0xdb; secret_key::SECRET_KEY_BYTES].into(),
+            )
+            .into(),
+        ];
+
+        for e in entries {
+            let debug = format!("{:?}", e);
+            assert!(debug.contains("<secret>"), "{}", debug);
+            // 0xdb is 219
+            assert!(!debug.contains("219, 219"), "{}", debug);
+        }
+    }
+
+    #[test]
+    fn it_detects_corrupt_entries() {
+        let e = LairEntry::from(EntrySignEd25519 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: vec![0x42; 32].into(),
+        });
+        let meta = EntryMeta::new_created_now(Some("tag".to_string()));
+        let d = e.encode_with_meta(&meta).unwrap();
+        LairEntry::decode_with_meta(&d).unwrap();
+
+        // whichever byte is flipped, even in the random fill
+        for at in 0..ENTRY_SIZE {
+            let mut corrupt = d.clone();
+            corrupt[at] ^= 0x80;
+            assert!(LairEntry::decode_with_meta(&corrupt).is_err(), "{}", at);
+        }
+        let mut corrupt = d.clone();
+        corrupt[100] ^= 0x01;
+        let err = LairEntry::decode(&corrupt).unwrap_err();
+        assert!(err.to_string().contains("checksum"), "{}", err);
+
+        let d = EntryDeleted.encode().unwrap();
+        let mut corrupt = d.clone();
+        corrupt[ENTRY_SIZE - 1] ^= 0x01;
+        assert!(EntryDeleted::is_deleted(&d));
+        assert!(!EntryDeleted::is_deleted(&corrupt));
+
+        // entries written before they were checked still decode
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE).unwrap();
+        writer.write_pre_padding(64).unwrap();
+        writer
+            .write_entry_type(codec::EntryType::SignEd25519)
+            .unwrap();
+        writer.write_secret(&[0xdb; 32]).unwrap();
+        writer.write_bytes(&[0x42; 32]).unwrap();
+        let mut legacy = writer.into_vec();
+        legacy[ENTRY_SIZE - 1] ^= 0x01;
+        assert!(LairEntry::decode(&legacy).is_ok());
+    }
+
+    #[test]
+    fn it_can_encode_compacted_entry() {
+        let e = EntryCompacted {
+            deleted: (1..=EntryCompacted::MAX_INDICES as u32).collect(),
+        };
+        let d = e.encode().unwrap();
+        assert_eq!(ENTRY_SIZE, d.len());
+        assert!(EntryCompacted::is_compacted(&d));
+        assert!(!EntryDeleted::is_deleted(&d));
+        assert!(LairEntry::decode(&d).is_err());
+        assert_eq!(e, EntryCompacted::decode(&d).unwrap());
+
+        let mut corrupt = d.clone();
+        corrupt[100] ^= 0x01;
+        assert!(EntryCompacted::decode(&corrupt).is_err());
+
+        let too_many = EntryCompacted {
+            deleted: vec![1; EntryCompacted::MAX_INDICES + 1],
+        };
+        assert!(too_many.encode().is_err());
+        let d = EntryDeleted.encode().unwrap();
+        assert!(!EntryCompacted::is_compacted(&d));
+    }
+
+    #[test]
+    fn it_can_encode_deleted_entry() {
+        let d = EntryDeleted.encode().unwrap();
+        assert_eq!(ENTRY_SIZE, d.len());
+        assert!(EntryDeleted::is_deleted(&d));
+        assert!(LairEntry::decode(&d).is_err());
+
+        let e = EntrySignEd25519 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: vec![0x42; 32].into(),
+        };
+        let d = LairEntry::from(e).encode().unwrap();
+        assert!(!EntryDeleted::is_deleted(&d));
+        assert!(!EntryDeleted::is_deleted(&[0; ENTRY_SIZE]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_encode_and_decode_entry_meta() {
+        let e = LairEntry::from(EntrySignEd25519 {
+            priv_key: vec![0xdb; 32].into(),
+            pub_key: vec![0x42; 32].into(),
+        });
+        let (_, meta) =
+            LairEntry::decode_with_meta(&e.encode().unwrap()).unwrap();
+        assert_eq!(EntryMeta::default(), meta);
+
+        let meta = EntryMeta::with_tag(Some("my-agent".to_string()));
+        let d = e.encode_with_meta(&meta).unwrap();
+        assert_eq!(ENTRY_SIZE, d.len());
+        let (e2, meta2) = LairEntry::decode_with_meta(&d).unwrap();
+        assert_eq!(meta, meta2);
+        match e2 {
+            LairEntry::SignEd25519(e2) => {
+                assert_eq!(vec![0x42; 32], *e2.pub_key.0)
+            }
+            e2 => panic!("unexpected type: {:?}", e2),
+        }
+
+        // v1 metadata has no creation timestamp
+        let mut writer = match &e {
+            LairEntry::SignEd25519(e) => e.encode_writer().unwrap(),
+            _ => unreachable!(),
+        };
+        writer.write_bytes(ENTRY_META_MAGIC).unwrap();
+        writer.write_u32(1).unwrap();
+        writer.write_u64(3).unwrap();
+        writer.write_bytes(b"old").unwrap();
+        let (_, meta) =
+            LairEntry::decode_with_meta(&writer.into_vec()).unwrap();
+        assert_eq!(EntryMeta::with_tag(Some("old".to_string())), meta);
+        assert_eq!(None, meta.created_at);
+
+        let mut meta = EntryMeta::new_created_now(None);
+        meta.record_use_now();
+        meta.record_use_now();
+        assert_eq!(2, meta.use_count);
+        assert!(meta.last_used.unwrap() >= meta.created_at.unwrap());
+        let d = e.encode_with_meta(&meta).unwrap();
+        let (_, meta2) = LairEntry::decode_with_meta(&d).unwrap();
+        assert_eq!(meta, meta2);
+
+        // the largest tag still fits beside the largest tls cert, sealed
+        // or not, custom names that would not fit are rejected at generation
+        let label = "a".repeat(31);
+        let long_name = format!("{0}.{0}.{0}.{0}", label);
+        for (sni, alt_names, fits) in [
+            (None, Vec::new(), true),
+            (
+                None,
+                vec!["localhost".into(), "127.0.0.1".into(), "::1".into()],
+                true,
+            ),
+            (Some("a".to_string()), vec![long_name.clone()], true),
+            (Some(long_name), Vec::new(), false),
+        ] {
+            let options = TlsCertOptions {
+                alg: TlsCertAlg::PkcsEcdsaP384Sha384,
+                sni,
+                alt_names,
+                ..Default::default()
+            };
+            tls::check_tls_cert_options(&options).unwrap();
+            let cert =
+                tls::tls_cert_self_signed_new_from_entropy(options).await;
+            if !fits {
+                assert!(matches!(cert, Err(LairError::TlsCertName(_))));
+                continue;
+            }
+            let tag = "t".repeat(MAX_TAG_BYTES);
+            EntryMeta::check_tag(&tag).unwrap();
+            let mut meta = EntryMeta::new_created_now(Some(tag));
+            meta.record_use_now();
+            assert!(meta.created_at.unwrap() > 0);
+            let cert = LairEntry::from(cert.unwrap());
+            let d = cert.encode_with_meta(&meta).unwrap();
+            assert_eq!(ENTRY_SIZE, d.len());
+            assert_eq!(meta, LairEntry::decode_with_meta(&d).unwrap().1);
+            let key = EntryStoreKey::new_random().unwrap();
+            let d = cert.seal_with_meta(&meta, &key).unwrap();
+            assert_eq!(ENTRY_SIZE, d.len());
+            assert_eq!(meta, LairEntry::unseal_with_meta(&d, &key).unwrap().1);
+        }
+
+        // derived keypairs record their seed and path
+        let meta = EntryMeta {
+            derived_from: Some(EntryDerivation {
+                seed_index: 3.into(),
+                path: vec![seed::HARDENED_BIT - 1; seed::MAX_DERIVATION_DEPTH],
+            }),
+            ..EntryMeta::new_created_now(Some("t".repeat(MAX_TAG_BYTES)))
+        };
+        let d = e.encode_with_meta(&meta).unwrap();
+        assert_eq!(meta, LairEntry::decode_with_meta(&d).unwrap().1);
+
+        // converted keypairs record their source
+        let meta = EntryMeta {
+            converted_from: Some(7.into()),
+            ..EntryMeta::new_created_now(None)
+        };
+        let d = e.encode_with_meta(&meta).unwrap();
+        assert_eq!(meta, LairEntry::decode_with_meta(&d).unwrap().1);
+
+        // renewed tls certs record their source
+        let meta = EntryMeta {
+            renewed_from: Some(9.into()),
+            ..EntryMeta::new_created_now(None)
+        };
+        let d = e.encode_with_meta(&meta).unwrap();
+        assert_eq!(meta, LairEntry::decode_with_meta(&d).unwrap().1);
+
+        // and entries their own rate limit
+        let meta = EntryMeta {
+            rate_limit: Some(100),
+            ..EntryMeta::new_created_now(None)
+        };
+        let d = e.encode_with_meta(&meta).unwrap();
+        assert_eq!(meta, LairEntry::decode_with_meta(&d).unwrap().1);
+
+        // or signatures that must be approved
+        let meta = EntryMeta {
+            requires_approval: true,
+            ..EntryMeta::new_created_now(None)
+        };
+        let d = e.encode_with_meta(&meta).unwrap();
+        assert_eq!(meta, LairEntry::decode_with_meta(&d).unwrap().1);
+
+        assert!(EntryMeta::check_tag("").is_err());
+        assert!(EntryMeta::check_tag(&"t".repeat(MAX_TAG_BYTES + 1)).is_err());
+    }
 
     #[test]
     fn it_can_encode_and_decode_x25519_entry() {
@@ -290,22 +2112,249 @@ mod tests {
         assert_eq!(e.pub_key, e2.pub_key);
     }
 
+    #[test]
+    fn it_can_encode_and_decode_seed_entry() {
+        for exportable in [false, true] {
+            let e =
+                EntrySeed::new(vec![0xdb; seed::SEED_BYTES].into(), exportable);
+            let d = LairEntry::from(e.clone()).encode().unwrap();
+            let e2 = match LairEntry::decode(&d).unwrap() {
+                LairEntry::Seed(e2) => e2,
+                e2 => panic!("unexpected type: {:?}", e2),
+            };
+            assert_eq!(e.seed, e2.seed);
+            assert_eq!(e.seed_id, e2.seed_id);
+            assert_eq!(exportable, e2.exportable);
+            LairEntry::from(e2).verify().unwrap();
+        }
+
+        let bad = EntrySeed {
+            seed: vec![0xdb; seed::SEED_BYTES].into(),
+            seed_id: vec![0x42; 32].into(),
+            exportable: false,
+        };
+        assert!(LairEntry::from(bad).verify().is_err());
+        assert!(EntrySeed::new(vec![0xdb; 16].into(), false)
+            .encode()
+            .is_err());
+    }
+
+    #[test]
+    fn it_can_encode_and_decode_secret_key_entry() {
+        let e = EntrySecretKey::new(
+            vec![0xdb; secret_key::SECRET_KEY_BYTES].into(),
+        );
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SecretKey(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.secret_key, e2.secret_key);
+        assert_eq!(e.tag, e2.tag);
+        LairEntry::from(e2).verify().unwrap();
+
+        let bad = EntrySecretKey {
+            secret_key: vec![0xdb; secret_key::SECRET_KEY_BYTES].into(),
+            tag: vec![0x42; 32].into(),
+        };
+        assert!(LairEntry::from(bad).verify().is_err());
+        assert!(EntrySecretKey::new(vec![0xdb; 16].into()).encode().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_encode_and_decode_sign_secp256k1_entry() {
+        let e = sign_secp256k1::sign_secp256k1_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignSecp256k1(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+        LairEntry::from(e2).verify().unwrap();
+
+        let bad = EntrySignSecp256k1 {
+            priv_key: e.priv_key.clone(),
+            pub_key: vec![0x02; sign_secp256k1::PUB_KEY_BYTES].into(),
+        };
+        assert!(LairEntry::from(bad).verify().is_err());
+        let bad = EntrySignSecp256k1 {
+            priv_key: e.priv_key,
+            pub_key: vec![0x02; 32].into(),
+        };
+        assert!(bad.encode().is_err());
+    }
+
+    #[cfg(feature = "bls")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_encode_and_decode_sign_bls12381_entry() {
+        let e = sign_bls12381::sign_bls12381_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let d = LairEntry::from(e.clone()).encode().unwrap();
+        let e2 = match LairEntry::decode(&d).unwrap() {
+            LairEntry::SignBls12381(e2) => e2,
+            e2 => panic!("unexpected type: {:?}", e2),
+        };
+        assert_eq!(e.priv_key, e2.priv_key);
+        assert_eq!(e.pub_key, e2.pub_key);
+        LairEntry::from(e2).verify().unwrap();
+
+        let other = sign_bls12381::sign_bls12381_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let bad = EntrySignBls12381 {
+            priv_key: e.priv_key.clone(),
+            pub_key: other.pub_key,
+        };
+        assert!(LairEntry::from(bad).verify().is_err());
+        let bad = EntrySignBls12381 {
+            priv_key: e.priv_key,
+            pub_key: vec![0x02; 33].into(),
+        };
+        assert!(bad.encode().is_err());
+    }
+
+    #[cfg(not(feature = "bls"))]
+    #[test]
+    fn it_cannot_decode_sign_bls12381_entry_without_the_feature() {
+        let mut writer = codec::CodecWriter::new(ENTRY_SIZE).unwrap();
+        writer.write_pre_padding(64).unwrap();
+        writer
+            .write_entry_type(codec::EntryType::SignBls12381)
+            .unwrap();
+        writer.write_bytes(&[0x42; 32 + 48]).unwrap();
+        let err = LairEntry::decode(&writer.into_vec()).unwrap_err();
+        assert!(err.to_string().contains("bls"), "{}", err);
+    }
+
     #[test]
     fn it_can_encode_and_decode_tls_cert_entry() {
         let e = EntryTlsCert {
             sni: "test".to_string().into(),
             priv_key_der: vec![1, 2].into(),
             cert_der: vec![3, 4].into(),
-            cert_digest: vec![0x42; 32].into(),
+            cert_digest: internal::tls::tls_cert_digest(
+                TlsCertDigestAlg::Sha256,
+                &[3, 4],
+            )
+            .into(),
+            digest_alg: TlsCertDigestAlg::Sha256,
+            alg: TlsCertAlg::PkcsEcdsaP384Sha384,
+            exportable: false,
         };
         let d = LairEntry::from(e.clone()).encode().unwrap();
         let e2 = match LairEntry::decode(&d).unwrap() {
             LairEntry::TlsCert(e2) => e2,
             e2 => panic!("unexpected type: {:?}", e2),
         };
+
+        // the algorithms and flags are fields of their own
+        let mut reader = codec::CodecReader::new(&d);
+        reader.read_pre_padding().unwrap();
+        assert_eq!(
+            codec::EntryType::TlsCertV2,
+            reader.read_entry_type().unwrap()
+        );
+        assert_eq!(e.alg as u16, reader.read_u16().unwrap());
+        assert_eq!(e.digest_alg as u16, reader.read_u16().unwrap());
+        assert_eq!(TLS_CERT_FLAG_NOT_EXPORTABLE, reader.read_u16().unwrap());
+        assert_eq!(4, reader.read_u16().unwrap());
+
         assert_eq!(e.sni, e2.sni);
         assert_eq!(e.priv_key_der, e2.priv_key_der);
         assert_eq!(e.cert_der, e2.cert_der);
         assert_eq!(e.cert_digest, e2.cert_digest);
+        assert_eq!(e.digest_alg, e2.digest_alg);
+        assert_eq!(e.alg, e2.alg);
+        assert!(!e2.exportable);
+        assert!(matches!(
+            e2.exportable_priv_key(),
+            Err(LairError::NotExportable)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_reads_the_alg_of_tls_cert_entries_written_without_it() {
+        let cert = internal::tls::tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions {
+                alg: TlsCertAlg::PkcsEcdsaP256Sha256,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // the encoding before the alg was recorded (with a plain sni
+        // length), followed by random fill or metadata
+        let meta = EntryMeta::new_created_now(Some("tag".to_string()));
+        for with_meta in [false, true] {
+            let mut writer = codec::CodecWriter::new(ENTRY_SIZE).unwrap();
+            writer.write_pre_padding(16).unwrap();
+            writer.write_entry_type(codec::EntryType::TlsCert).unwrap();
+            for bytes in [
+                cert.sni.as_bytes(),
+                &cert.priv_key_der[..],
+                &cert.cert_der[..],
+            ] {
+                writer.write_u64(bytes.len() as u64).unwrap();
+                writer.write_bytes(bytes).unwrap();
+            }
+            writer.write_bytes(&cert.cert_digest).unwrap();
+            if with_meta {
+                meta.encode(&mut writer).unwrap();
+            }
+
+            let (e, m) =
+                LairEntry::decode_with_meta(&writer.into_vec()).unwrap();
+            match e {
+                LairEntry::TlsCert(e) => {
+                    assert_eq!(TlsCertAlg::PkcsEcdsaP256Sha256, e.alg);
+                    assert_eq!(TlsCertDigestAlg::Blake2b256, e.digest_alg);
+                    assert!(e.exportable);
+                }
+                e => panic!("unexpected type: {:?}", e),
+            }
+            assert_eq!(with_meta, m.tag.is_some());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_verify_entries() {
+        let sign = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        LairEntry::from(sign.clone()).verify().unwrap();
+        let bad = EntrySignEd25519 {
+            priv_key: sign.priv_key,
+            pub_key: vec![0x42; 32].into(),
+        };
+        assert!(LairEntry::from(bad).verify().is_err());
+
+        let x = x25519::x25519_keypair_new_from_entropy().await.unwrap();
+        LairEntry::from(x.clone()).verify().unwrap();
+        let bad = EntryX25519 {
+            priv_key: x.priv_key,
+            pub_key: [0x42; 32].into(),
+        };
+        assert!(LairEntry::from(bad).verify().is_err());
+
+        let cert = internal::tls::tls_cert_self_signed_new_from_entropy(
+            TlsCertOptions::default(),
+        )
+        .await
+        .unwrap();
+        LairEntry::from(cert.clone()).verify().unwrap();
+        let mut bad = cert.clone();
+        bad.cert_digest = vec![0; 32].into();
+        assert!(LairEntry::from(bad).verify().is_err());
+        let mut bad = cert.clone();
+        bad.digest_alg = TlsCertDigestAlg::Sha256;
+        assert!(LairEntry::from(bad).verify().is_err());
+        let mut bad = cert;
+        bad.alg = TlsCertAlg::PkcsEcdsaP256Sha256;
+        assert!(LairEntry::from(bad).verify().is_err());
     }
 }
@@ -7,18 +7,208 @@ pub enum LairError {
     #[error("LairError: {0}")]
     GhostError(#[from] ghost_actor::GhostError),
 
-    /// Trying to start up Lair process, but a pidfile/process already exists
-    #[error("Lair pidfile/process already exists")]
-    ProcessAlreadyExists,
+    /// Trying to start up Lair process, but another lair process holds
+    /// the lock on its pidfile (with its pid, if it has written it yet)
+    #[error("another lair-keystore is running (pid {})", pid_or_unknown(.0))]
+    ProcessAlreadyExists(Option<u32>),
+
+    /// Trying to initialize a Lair store, but one already exists
+    #[error("Lair store is already initialized")]
+    StoreAlreadyInitialized,
+
+    /// The two sides of a connection speak no common protocol version
+    #[error("Protocol version mismatch: we speak {ours}, they speak {theirs}")]
+    ProtocolVersionMismatch {
+        /// the protocol version we speak
+        ours: u32,
+        /// the protocol version they speak
+        theirs: u32,
+    },
+
+    /// The connection to the server broke before it responded,
+    /// the client reconnects
+    #[error("Disconnected from lair-keystore")]
+    Disconnected,
+
+    /// The server already has as many connections as it accepts
+    #[error("lair-keystore is busy: too many connections")]
+    Busy,
+
+    /// The server didn't respond to a request in time
+    #[error("Request timed out after {0:?}")]
+    Timeout(std::time::Duration),
 
     /// Failure to establish client connection to Lair IPC.
     #[error("IpcClientConnectError: {0} {1}")]
     IpcClientConnectError(String, Box<dyn std::error::Error + Send + Sync>),
 
+    /// The lair store file was written by a newer lair-keystore
+    #[error(
+        "Lair store format v{0} is newer than this lair-keystore supports"
+    )]
+    StoreVersionUnsupported(u32),
+
+    /// The lair store file is in an older format, run `lair-keystore migrate`
+    #[error(
+        "Lair store format v{0} must be upgraded, run `lair-keystore migrate`"
+    )]
+    StoreMigrationRequired(u32),
+
+    /// The store entries are encrypted, and the store has not yet been
+    /// unlocked with its passphrase
+    #[error("Lair store is locked, unlock it with its passphrase")]
+    StoreLocked,
+
+    /// A passphrase was given to unlock the store that is not its
+    /// unlock passphrase
+    #[error("invalid unlock passphrase")]
+    WrongPassphrase,
+
+    /// The store is served read-only, and the request would modify it
+    #[error("Lair store is read-only")]
+    ReadOnly,
+
+    /// No keystore entry exists at this index
+    #[error("Keystore entry {0} not found")]
+    EntryNotFound(u32),
+
+    /// The keystore entry at this index has been deleted
+    #[error("Keystore entry {0} has been deleted")]
+    EntryDeleted(u32),
+
+    /// The keystore entry at this index failed its integrity check
+    #[error("Keystore entry {0} is corrupt")]
+    EntryCorrupt(u32),
+
+    /// The keystore entry exists, but is not of the type the request
+    /// needs, e.g. a tls cert entry was asked to sign
+    #[error("invalid entry type")]
+    WrongEntryType,
+
+    /// An entry was created with a tag already used by another entry
+    #[error("Entry tag {0:?} is already in use")]
+    EntryTagInUse(String),
+
+    /// An entry tag was provided that cannot be found in the keystore
+    #[error("Entry tag {0:?} not found")]
+    EntryTagNotFound(String),
+
+    /// A key was imported whose public key is already in the keystore
+    #[error(
+        "An entry with this public key already exists at index {existing_index}"
+    )]
+    DuplicatePubKey {
+        /// keystore index of the existing entry
+        existing_index: u32,
+    },
+
     /// A public key was provided (e.g. for signing) that cannot be found in the keystore
     #[error("Public key not found")]
     PubKeyNotFound,
 
+    /// Key material (or a signature) was provided that is not of the
+    /// length required
+    #[error("{what} must be {expected} bytes, got {got}")]
+    WrongLength {
+        /// what was provided, e.g. "secp256k1 priv key"
+        what: String,
+        /// the length required
+        expected: usize,
+        /// the length provided
+        got: usize,
+    },
+
+    /// An ed25519 seed was provided that is not 32 bytes long
+    #[error("Ed25519 seed must be 32 bytes, got {0}")]
+    SignEd25519SeedLength(usize),
+
+    /// An x25519 private key was imported that is not 32 bytes long
+    #[error("X25519 private key must be 32 bytes, got {0}")]
+    X25519PrivKeyImportLength(usize),
+
+    /// A tls cert sni or subject alt name is not a valid hostname
+    /// (or ip address), or the names are too long
+    #[error("Invalid tls cert name: {0}")]
+    TlsCertName(String),
+
+    /// An imported tls cert or private key is not valid DER
+    /// (or the pair is too large to fit in a keystore entry)
+    #[error("Invalid tls cert import: {0}")]
+    TlsCertDer(String),
+
+    /// An imported tls private key is not the key of the imported cert
+    #[error("Tls cert private key does not match the cert public key")]
+    TlsCertKeyMismatch,
+
+    /// A tls cert was used to issue certs, but is not a CA cert
+    #[error("Tls cert is not a CA cert")]
+    TlsCertNotCa,
+
+    /// The key material of an entry created not exportable was
+    /// requested, e.g. a tls cert private key or a seed mnemonic
+    #[error("Entry key material is not exportable")]
+    NotExportable,
+
+    /// The request is not allowed in the scope of this connection
+    #[error(
+        "Permission denied: {request} needs more than a {scope} connection"
+    )]
+    PermissionDenied {
+        /// the request denied
+        request: String,
+        /// the scope of the connection
+        scope: crate::ConnectionScope,
+    },
+
+    /// Signing with this key (or with any key) is over its rate limit,
+    /// and the server queues no more requests
+    #[error("Rate limited, retry after {}ms", .retry_after.as_millis())]
+    RateLimited {
+        /// how long until the request would be allowed
+        retry_after: std::time::Duration,
+    },
+
+    /// The signing approver denied a signature with a key that requires
+    /// approval
+    #[error("Signing denied by the approver")]
+    SigningDenied,
+
+    /// No signing approver answered in time for a signature with a key
+    /// that requires approval
+    #[error("Signing approval timed out")]
+    SigningApprovalTimeout,
+
+    /// A key that requires approval was used to sign, but no signing
+    /// approver is connected, so the signature is refused
+    #[error("No signing approver connected")]
+    NoSigningApprover,
+
+    /// More than one tls cert digest starts with the given prefix,
+    /// candidates are listed as `index:hex digest`
+    #[error(
+        "Tls cert digest prefix is ambiguous, candidates: {}",
+        .0.join(", ")
+    )]
+    TlsCertDigestPrefixAmbiguous(Vec<String>),
+
+    /// A subkey was requested whose length is outside 16..=64 bytes
+    #[error("Subkey must be 16 to 64 bytes, got {0}")]
+    SubkeyLength(usize),
+
+    /// The lair store file is damaged beyond what lair can read,
+    /// restore it from a backup
+    #[error("Lair store is corrupt: {0}")]
+    StoreCorrupt(String),
+
+    /// Reading or writing a file (e.g. the store file) failed
+    #[error("Io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The peer sent a message that is not valid lair wire protocol,
+    /// or not the response expected
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+
     /// Error during aead encryption, likely bad data.
     #[error("Aead error: {0}")]
     Aead(String),
@@ -73,8 +263,94 @@ impl LairError {
     ) -> Self {
         LairError::Other(e.into())
     }
+
+    /// Build a "Protocol" type LairError, for a message received
+    /// that is not the one expected.
+    pub fn unexpected(msg: impl std::fmt::Debug) -> Self {
+        LairError::Protocol(format!("unexpected: {:?}", msg))
+    }
+
+    /// Build a "WrongLength" type LairError.
+    pub fn wrong_length(what: &str, expected: usize, got: usize) -> Self {
+        LairError::WrongLength {
+            what: what.to_string(),
+            expected,
+            got,
+        }
+    }
+
+    /// Stable, machine readable code for this error, the same on both
+    /// sides of an ipc connection.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LairError::GhostError(_) => "ghost_error",
+            LairError::ProcessAlreadyExists(_) => "process_already_exists",
+            LairError::StoreAlreadyInitialized => "store_already_initialized",
+            LairError::StoreVersionUnsupported(_) => {
+                "store_version_unsupported"
+            }
+            LairError::StoreMigrationRequired(_) => "store_migration_required",
+            LairError::StoreLocked => "store_locked",
+            LairError::WrongPassphrase => "wrong_passphrase",
+            LairError::ReadOnly => "read_only",
+            LairError::ProtocolVersionMismatch { .. } => {
+                "protocol_version_mismatch"
+            }
+            LairError::Disconnected => "disconnected",
+            LairError::Busy => "busy",
+            LairError::Timeout(_) => "timeout",
+            LairError::IpcClientConnectError(..) => "ipc_connect_error",
+            LairError::EntryNotFound(_) => "entry_not_found",
+            LairError::EntryDeleted(_) => "entry_deleted",
+            LairError::EntryCorrupt(_) => "entry_corrupt",
+            LairError::WrongEntryType => "wrong_entry_type",
+            LairError::EntryTagInUse(_) => "entry_tag_in_use",
+            LairError::EntryTagNotFound(_) => "entry_tag_not_found",
+            LairError::DuplicatePubKey { .. } => "duplicate_pub_key",
+            LairError::PubKeyNotFound => "pub_key_not_found",
+            LairError::WrongLength { .. } => "wrong_length",
+            LairError::SignEd25519SeedLength(_) => "sign_ed25519_seed_length",
+            LairError::X25519PrivKeyImportLength(_) => {
+                "x25519_priv_key_import_length"
+            }
+            LairError::TlsCertName(_) => "tls_cert_name",
+            LairError::TlsCertDer(_) => "tls_cert_der",
+            LairError::TlsCertKeyMismatch => "tls_cert_key_mismatch",
+            LairError::TlsCertNotCa => "tls_cert_not_ca",
+            LairError::NotExportable => "not_exportable",
+            LairError::PermissionDenied { .. } => "permission_denied",
+            LairError::RateLimited { .. } => "rate_limited",
+            LairError::SigningDenied => "signing_denied",
+            LairError::SigningApprovalTimeout => "signing_approval_timeout",
+            LairError::NoSigningApprover => "no_signing_approver",
+            LairError::TlsCertDigestPrefixAmbiguous(_) => {
+                "tls_cert_digest_prefix_ambiguous"
+            }
+            LairError::SubkeyLength(_) => "subkey_length",
+            LairError::StoreCorrupt(_) => "store_corrupt",
+            LairError::Io(_) => "io",
+            LairError::Protocol(_) => "protocol",
+            LairError::Aead(_) => "aead",
+            LairError::BlockPad(_) => "block_pad",
+            LairError::BlockUnpad(_) => "block_unpad",
+            LairError::CryptoBoxNonceLength => "crypto_box_nonce_length",
+            LairError::X25519PubKeyLength => "x25519_pub_key_length",
+            LairError::X25519PrivKeyLength => "x25519_priv_key_length",
+            LairError::Other(_) => "other",
+        }
+    }
+}
+
+fn pid_or_unknown(pid: &Option<u32>) -> String {
+    match pid {
+        Some(pid) => pid.to_string(),
+        None => "unknown".to_string(),
+    }
 }
 
+/// Builds an "Other" type LairError. Kept for a deprecation period,
+/// so downstream code keeps compiling: new code constructs the
+/// specific variant instead.
 impl From<String> for LairError {
     fn from(s: String) -> Self {
         #[derive(Debug, thiserror::Error)]
@@ -1,6 +1,10 @@
 //! Internal utility functions - note, the api for anything in this module
 //! is unstable and may change even for patch versions of this library.
 
+pub mod audit;
+pub mod auth_token;
+pub mod bip39;
+
 /// utilities for lair build.rs files
 pub mod build;
 
@@ -9,8 +13,15 @@ pub mod codec;
 /// Currently the crypto_box crate, future likely to be libsodium.
 pub mod crypto_box;
 pub mod ipc;
+pub mod kx;
+pub mod pkcs12;
 pub(crate) mod rayon;
+pub mod secret_key;
+pub mod secretstream;
+pub mod seed;
+pub mod sign_bls12381;
 pub mod sign_ed25519;
+pub mod sign_secp256k1;
 pub mod tls;
 pub mod util;
 pub mod wire;
@@ -0,0 +1,478 @@
+//! Append-only audit log of secret key operations: every signature,
+//! decryption and private key export a server makes, as json lines
+//! (see `ConfigBuilder::set_audit_log()`).
+//!
+//! Records name the client, the operation and the entry it used, and
+//! whether it succeeded, never message contents or key material. Each
+//! record carries the hash of the one before it, so `verify()` finds
+//! records that were changed, removed or inserted after they were
+//! written. The log is rotated by size, the chain carrying on into the
+//! new file.
+
+use crate::*;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// How many rotated files (`audit.log.1` being the newest) are kept
+/// beside the current one, older records are dropped.
+pub const AUDIT_LOG_ROTATED_FILES: usize = 4;
+
+/// The `prev` hash of the very first record.
+const GENESIS_PREV: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What a client did with a secret key.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOp {
+    /// Signed a message (or several, or a digest).
+    Sign,
+
+    /// Decrypted or opened a message, or a secretstream chunk.
+    Decrypt,
+
+    /// Got private key material, or secrets derived from it.
+    Export,
+}
+
+/// The entry an operation used, as the client named it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntry {
+    /// By keystore index.
+    Index(u32),
+
+    /// By pub key, base64 encoded.
+    PubKey(String),
+
+    /// By tag.
+    Tag(String),
+
+    /// By tls cert digest, base64 encoded.
+    CertDigest(String),
+
+    /// By tls cert sni.
+    CertSni(String),
+
+    /// By secretstream session id, base64 encoded.
+    Session(String),
+}
+
+/// Whether an operation succeeded.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The client got the result.
+    Ok,
+
+    /// The request failed, or was denied.
+    Error,
+}
+
+/// A single audit log record, one json line.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, counting from 0.
+    pub seq: u64,
+
+    /// When the request was answered, rfc3339.
+    pub time: String,
+
+    /// The name of the client (see `ConfigBuilder::set_client_name()`).
+    pub client: String,
+
+    /// What the client did.
+    pub op: AuditOp,
+
+    /// The request that did it, e.g. "ToLairSignEd25519SignByIndex".
+    pub request: String,
+
+    /// The entry it used.
+    pub entry: AuditEntry,
+
+    /// Whether it succeeded.
+    pub outcome: AuditOutcome,
+
+    /// Why it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Hex blake2b-256 hash of the previous record line.
+    pub prev: String,
+}
+
+/// What `verify()` found in an intact log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct AuditSummary {
+    /// How many records were checked.
+    pub records: u64,
+
+    /// The seq of the oldest record kept, if any.
+    pub first_seq: Option<u64>,
+
+    /// The seq of the newest record, if any.
+    pub last_seq: Option<u64>,
+}
+
+/// An open audit log, records are appended by one server at a time.
+pub struct AuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    inner: std::sync::Mutex<AuditLogInner>,
+}
+
+struct AuditLogInner {
+    file: std::fs::File,
+    size: u64,
+    next_seq: u64,
+    prev: String,
+}
+
+impl AuditLog {
+    /// Open the audit log at `path` to append to, continuing the chain
+    /// of the records already in it. Rotates the file once appending a
+    /// record would take it past `max_bytes`.
+    pub fn open(path: &Path, max_bytes: u64) -> LairResult<Self> {
+        let (next_seq, prev) = match last_line(path)? {
+            Some(line) => {
+                let record = parse_record(&line).map_err(|e| {
+                    LairError::other(format!(
+                        "{}: last record: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                (record.seq + 1, hash_line(&line))
+            }
+            None => (0, GENESIS_PREV.to_string()),
+        };
+        let file = open_append(path)?;
+        let size = file.metadata().map_err(|e| io_err(path, e))?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            inner: std::sync::Mutex::new(AuditLogInner {
+                file,
+                size,
+                next_seq,
+                prev,
+            }),
+        })
+    }
+
+    /// Get the path of the current log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the record of `client` having made a `request` doing `op`
+    /// with `entry`, and the `error` it failed with, if any.
+    /// Returns once the record is on disk.
+    pub fn append(
+        &self,
+        client: &str,
+        op: AuditOp,
+        request: &str,
+        entry: AuditEntry,
+        error: Option<String>,
+    ) -> LairResult<()> {
+        let mut inner = self.inner.lock().expect("audit log poisoned");
+        let outcome = match error {
+            None => AuditOutcome::Ok,
+            Some(_) => AuditOutcome::Error,
+        };
+        let record = AuditRecord {
+            seq: inner.next_seq,
+            time: chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            client: client.to_string(),
+            op,
+            request: request.to_string(),
+            entry,
+            outcome,
+            error,
+            prev: inner.prev.clone(),
+        };
+        let line = serde_json::to_string(&record).map_err(LairError::other)?;
+
+        if inner.size > 0 && inner.size + line.len() as u64 + 1 > self.max_bytes
+        {
+            rotate(&self.path)?;
+            inner.file = open_append(&self.path)?;
+            inner.size = 0;
+        }
+
+        let mut buf = line.clone().into_bytes();
+        buf.push(b'\n');
+        inner
+            .file
+            .write_all(&buf)
+            .and_then(|_| inner.file.sync_data())
+            .map_err(|e| io_err(&self.path, e))?;
+        inner.size += buf.len() as u64;
+        inner.next_seq += 1;
+        inner.prev = hash_line(&line);
+        Ok(())
+    }
+}
+
+/// The last `n` records of the audit log at `path`, oldest first,
+/// reaching back into rotated files as needed.
+pub fn tail(path: &Path, n: usize) -> LairResult<Vec<AuditRecord>> {
+    let mut out = Vec::new();
+    for file in log_files(path).into_iter().rev() {
+        if out.len() >= n {
+            break;
+        }
+        let lines = read_lines(&file)?;
+        for (i, line) in lines.iter().enumerate().rev() {
+            if out.len() >= n {
+                break;
+            }
+            out.push(parse_record(line).map_err(|e| {
+                LairError::other(format!(
+                    "{}: line {}: {}",
+                    file.display(),
+                    i + 1,
+                    e
+                ))
+            })?);
+        }
+    }
+    out.reverse();
+    Ok(out)
+}
+
+/// Check the chain of the audit log at `path` and its rotated files:
+/// every record hashes the one before it, and seqs follow one another.
+/// Fails on the first record that doesn't. The oldest record kept is
+/// trusted, unless it is the first ever written.
+pub fn verify(path: &Path) -> LairResult<AuditSummary> {
+    let mut summary = AuditSummary {
+        records: 0,
+        first_seq: None,
+        last_seq: None,
+    };
+    let mut prev: Option<(u64, String)> = None;
+    for file in log_files(path) {
+        for (i, line) in read_lines(&file)?.into_iter().enumerate() {
+            let err = |e: String| {
+                LairError::other(format!(
+                    "{}: line {}: {}",
+                    file.display(),
+                    i + 1,
+                    e
+                ))
+            };
+            let record = parse_record(&line).map_err(err)?;
+            match &prev {
+                Some((seq, hash)) => {
+                    if record.seq != seq + 1 {
+                        return Err(err(format!(
+                            "record {} follows record {}",
+                            record.seq, seq
+                        )));
+                    }
+                    if &record.prev != hash {
+                        return Err(err(format!(
+                            "record {} does not chain to record {}",
+                            record.seq, seq
+                        )));
+                    }
+                }
+                None if record.seq == 0 && record.prev != GENESIS_PREV => {
+                    return Err(err("record 0 does not start the chain".into()))
+                }
+                None => summary.first_seq = Some(record.seq),
+            }
+            summary.records += 1;
+            summary.last_seq = Some(record.seq);
+            prev = Some((record.seq, hash_line(&line)));
+        }
+    }
+    Ok(summary)
+}
+
+/// The rotated files of the log at `path`, and `path` itself,
+/// oldest first, as far as they exist.
+fn log_files(path: &Path) -> Vec<PathBuf> {
+    let mut out: Vec<PathBuf> = (1..=AUDIT_LOG_ROTATED_FILES)
+        .rev()
+        .map(|n| rotated_path(path, n))
+        .collect();
+    out.push(path.to_path_buf());
+    out.retain(|p| p.exists());
+    out
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Shift `path` to `path.1`, `path.1` to `path.2` and so on, dropping
+/// the oldest.
+fn rotate(path: &Path) -> LairResult<()> {
+    for n in (1..AUDIT_LOG_ROTATED_FILES).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            let to = rotated_path(path, n + 1);
+            std::fs::rename(&from, &to).map_err(|e| io_err(&from, e))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1)).map_err(|e| io_err(path, e))
+}
+
+/// The last record line of the log, if it has any records,
+/// looking into the newest rotated file if the current one is empty.
+fn last_line(path: &Path) -> LairResult<Option<String>> {
+    for file in log_files(path).into_iter().rev() {
+        if let Some(line) = read_lines(&file)?.pop() {
+            return Ok(Some(line));
+        }
+    }
+    Ok(None)
+}
+
+fn read_lines(path: &Path) -> LairResult<Vec<String>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(io_err(path, e)),
+    };
+    let mut out = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| io_err(path, e))?;
+        if !line.is_empty() {
+            out.push(line);
+        }
+    }
+    Ok(out)
+}
+
+fn parse_record(line: &str) -> Result<AuditRecord, String> {
+    serde_json::from_str(line).map_err(|e| format!("not a record: {}", e))
+}
+
+fn hash_line(line: &str) -> String {
+    blake2b_simd::Params::new()
+        .hash_length(32)
+        .hash(line.as_bytes())
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn open_append(path: &Path) -> LairResult<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path).map_err(|e| io_err(path, e))
+}
+
+fn io_err(path: &Path, e: std::io::Error) -> LairError {
+    LairError::other(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(log: &AuditLog, index: u32) {
+        log.append(
+            "test-client",
+            AuditOp::Sign,
+            "ToLairSignEd25519SignByIndex",
+            AuditEntry::Index(index),
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn audit_log_chains_records_and_finds_tampering() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("audit.log");
+        let log = AuditLog::open(&path, 1 << 20).unwrap();
+        sign(&log, 1);
+        log.append(
+            "test-client",
+            AuditOp::Decrypt,
+            "ToLairCryptoBoxOpenByIndex",
+            AuditEntry::Index(2),
+            Some("nope".into()),
+        )
+        .unwrap();
+        drop(log);
+
+        // reopening carries on the chain
+        let log = AuditLog::open(&path, 1 << 20).unwrap();
+        sign(&log, 3);
+        drop(log);
+
+        let summary = verify(&path).unwrap();
+        assert_eq!(3, summary.records);
+        assert_eq!(Some(0), summary.first_seq);
+        assert_eq!(Some(2), summary.last_seq);
+
+        let records = tail(&path, 2).unwrap();
+        assert_eq!(2, records.len());
+        assert_eq!(1, records[0].seq);
+        assert_eq!(AuditOp::Decrypt, records[0].op);
+        assert_eq!(AuditOutcome::Error, records[0].outcome);
+        assert_eq!(Some("nope".to_string()), records[0].error);
+        assert_eq!(AuditEntry::Index(3), records[1].entry);
+        assert_eq!("test-client", records[1].client);
+
+        // changing a record breaks the chain after it
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replacen("\"index\":2", "\"index\":9", 1))
+            .unwrap();
+        assert!(verify(&path).is_err());
+
+        // and so does dropping one
+        let lines: Vec<&str> = data.lines().collect();
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(verify(&path).is_err());
+
+        std::fs::write(&path, data).unwrap();
+        assert!(verify(&path).is_ok());
+    }
+
+    #[test]
+    fn audit_log_rotates_by_size() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("audit.log");
+        // room for a single record per file
+        let log = AuditLog::open(&path, 100).unwrap();
+        for index in 0..(AUDIT_LOG_ROTATED_FILES as u32 + 3) {
+            sign(&log, index);
+        }
+        drop(log);
+
+        assert!(rotated_path(&path, AUDIT_LOG_ROTATED_FILES).exists());
+        assert!(!rotated_path(&path, AUDIT_LOG_ROTATED_FILES + 1).exists());
+
+        // the oldest records are gone, the rest still chain
+        let summary = verify(&path).unwrap();
+        assert_eq!(AUDIT_LOG_ROTATED_FILES as u64 + 1, summary.records);
+        assert_eq!(Some(2), summary.first_seq);
+        assert_eq!(Some(AUDIT_LOG_ROTATED_FILES as u64 + 2), summary.last_seq);
+
+        let records = tail(&path, 100).unwrap();
+        assert_eq!(AUDIT_LOG_ROTATED_FILES + 1, records.len());
+        assert_eq!(2, records[0].seq);
+    }
+}
@@ -0,0 +1,178 @@
+//! Connection tokens: a shared secret clients present when they connect,
+//! for servers reachable by more than the filesystem permissions of the
+//! socket allow (see `ConfigBuilder::set_require_token()`).
+
+use crate::*;
+
+/// How long a connection may take to present the token,
+/// it is dropped if it hasn't by then.
+pub const AUTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Random bytes in a generated token.
+const TOKEN_BYTES: usize = 32;
+
+/// Maximum byte length of a token on the wire.
+pub const TOKEN_MAX_BYTES: usize = 128;
+
+/// The token a server requires: the configured one, written to the token
+/// file for clients to read, or the one in the token file, generating it
+/// if there is none yet.
+pub fn load_or_create_token(config: &Config) -> LairResult<String> {
+    let token = match config.get_token() {
+        Some(token) => token.to_string(),
+        None => match read_token_file(config)? {
+            Some(token) => return Ok(token),
+            None => new_token(),
+        },
+    };
+    check_token(&token)?;
+    if config.get_ephemeral() {
+        // nothing is written for ephemeral configs, nor read
+        if config.get_token().is_none() {
+            return Err(
+                "ephemeral configs requiring a token need one configured"
+                    .into(),
+            );
+        }
+        return Ok(token);
+    }
+    write_token_file(config, &token)?;
+    Ok(token)
+}
+
+/// The token a client presents, if there is one:
+/// the configured one, or the one in the token file.
+pub fn read_token(config: &Config) -> LairResult<Option<String>> {
+    if let Some(token) = config.get_token() {
+        return Ok(Some(token.to_string()));
+    }
+    if config.get_ephemeral() {
+        return Ok(None);
+    }
+    read_token_file(config)
+}
+
+/// Compare tokens in constant time.
+pub fn token_eq(a: &str, b: &str) -> bool {
+    internal::util::ct_eq(a.as_bytes(), b.as_bytes())
+}
+
+fn new_token() -> String {
+    use rand::Rng;
+    let mut bytes = [0; TOKEN_BYTES];
+    rand::thread_rng().fill(&mut bytes[..]);
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn check_token(token: &str) -> LairResult<()> {
+    if token.is_empty() || token.len() > TOKEN_MAX_BYTES {
+        return Err(format!(
+            "connection tokens are 1 to {} bytes",
+            TOKEN_MAX_BYTES
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn read_token_file(config: &Config) -> LairResult<Option<String>> {
+    let path = config.get_token_path();
+    match std::fs::read_to_string(path) {
+        Ok(token) if token.trim().is_empty() => Ok(None),
+        Ok(token) => Ok(Some(token.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(LairError::other(format!("{}: {}", path.display(), e))),
+    }
+}
+
+fn write_token_file(config: &Config, token: &str) -> LairResult<()> {
+    use std::io::Write;
+    let path = config.get_token_path();
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let err = |e: std::io::Error| {
+        LairError::other(format!("{}: {}", path.display(), e))
+    };
+    let mut file = options.open(path).map_err(err)?;
+    // the mode only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(err)?;
+    }
+    file.write_all(token.as_bytes()).map_err(err)?;
+    file.sync_all().map_err(err)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_generated_once_and_read_by_clients() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_require_token(true)
+            .build();
+        assert_eq!(None, read_token(&config).unwrap());
+
+        let token = load_or_create_token(&config).unwrap();
+        assert_eq!(43, token.len());
+        assert_eq!(token, load_or_create_token(&config).unwrap());
+        assert_eq!(Some(token.clone()), read_token(&config).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let meta = std::fs::metadata(config.get_token_path()).unwrap();
+            assert_eq!(0o600, meta.permissions().mode() & 0o777);
+        }
+
+        // a configured token replaces the file
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_token("configured")
+            .build();
+        assert!(config.get_require_token());
+        assert_eq!("configured", load_or_create_token(&config).unwrap());
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        assert_eq!(Some("configured".into()), read_token(&config).unwrap());
+
+        assert!(token_eq("configured", "configured"));
+        assert!(!token_eq("configured", "configurex"));
+        assert!(!token_eq("configured", "config"));
+
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_token("")
+            .build();
+        assert!(load_or_create_token(&config).is_err());
+    }
+
+    #[test]
+    fn ephemeral_tokens_write_nothing() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("lair");
+        let config = Config::builder()
+            .set_root_path(&root)
+            .set_ephemeral(true)
+            .set_require_token(true)
+            .build();
+        assert!(load_or_create_token(&config).is_err());
+        let config = Config::builder()
+            .set_root_path(&root)
+            .set_ephemeral(true)
+            .set_token("configured")
+            .build();
+        assert_eq!("configured", load_or_create_token(&config).unwrap());
+        assert_eq!(Some("configured".into()), read_token(&config).unwrap());
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,263 @@
+//! BIP39 mnemonic encoding of entropy, english wordlist only.
+//! Lair encodes its 32 byte seeds directly as BIP39 entropy: the phrase
+//! is not stretched into a 64 byte BIP39 seed, there is no passphrase.
+
+use crate::*;
+
+/// The official BIP39 english wordlist, one word per line,
+/// sorted, in index order.
+const WORDLIST: &str = include_str!("bip39_english.txt");
+
+static WORDS: once_cell::sync::Lazy<Vec<&'static str>> =
+    once_cell::sync::Lazy::new(|| WORDLIST.lines().collect());
+
+/// Number of mnemonic words encoding a 32 byte lair seed.
+pub const MNEMONIC_WORD_COUNT: usize = 24;
+
+/// Encode entropy (16, 20, 24, 28 or 32 bytes) as a BIP39 mnemonic:
+/// lowercase words separated by single spaces.
+pub fn entropy_to_mnemonic(entropy: &[u8]) -> LairResult<String> {
+    if entropy.len() < 16
+        || entropy.len() > 32
+        || !entropy.len().is_multiple_of(4)
+    {
+        return Err(format!(
+            "invalid mnemonic entropy length {}",
+            entropy.len()
+        )
+        .into());
+    }
+
+    // entropy bits, then the first len / 32 bits of its sha256
+    let checksum = ring::digest::digest(&ring::digest::SHA256, entropy);
+    let mut data = zeroize::Zeroizing::new(entropy.to_vec());
+    data.push(checksum.as_ref()[0]);
+
+    let word_count = entropy.len() * 8 * 33 / 32 / 11;
+    let words = (0..word_count)
+        .map(|w| WORDS[read_bits_11(&data, w * 11)])
+        .collect::<Vec<_>>();
+    Ok(words.join(" "))
+}
+
+/// Decode a BIP39 mnemonic back into its entropy,
+/// validating each word and the checksum.
+/// Words are case insensitive, and may be separated by any whitespace.
+pub fn mnemonic_to_entropy(mnemonic: &str) -> LairResult<Vec<u8>> {
+    let words = mnemonic.split_whitespace().collect::<Vec<_>>();
+    if ![12, 15, 18, 21, 24].contains(&words.len()) {
+        return Err(format!(
+            "invalid mnemonic word count {}, expected 12, 15, 18, 21 or 24",
+            words.len()
+        )
+        .into());
+    }
+
+    let mut data = zeroize::Zeroizing::new(vec![0_u8; 33]);
+    for (w, word) in words.iter().enumerate() {
+        // don't echo the words back, they are secret material
+        let word = zeroize::Zeroizing::new(word.to_lowercase());
+        let index = match WORDS.binary_search(&word.as_str()) {
+            Ok(index) => index,
+            Err(_) => {
+                return Err(format!(
+                    "mnemonic word {} is not in the wordlist",
+                    w + 1
+                )
+                .into())
+            }
+        };
+        write_bits_11(&mut data, w * 11, index);
+    }
+
+    let entropy_len = words.len() * 11 * 32 / 33 / 8;
+    let checksum_bits = entropy_len / 4;
+    let entropy = data[..entropy_len].to_vec();
+    let checksum = ring::digest::digest(&ring::digest::SHA256, &entropy);
+    let mask = 0xff_u8 << (8 - checksum_bits);
+    if (checksum.as_ref()[0] ^ data[entropy_len]) & mask != 0 {
+        return Err("mnemonic checksum mismatch".into());
+    }
+
+    Ok(entropy)
+}
+
+/// Read the 11 bit big-endian word index at `offset` bits into `data`.
+fn read_bits_11(data: &[u8], offset: usize) -> usize {
+    (0..11).fold(0, |acc, i| {
+        let bit = offset + i;
+        let set = data[bit / 8] >> (7 - bit % 8) & 1;
+        (acc << 1) | set as usize
+    })
+}
+
+/// Write an 11 bit big-endian word index at `offset` bits into `data`.
+fn write_bits_11(data: &mut [u8], offset: usize, index: usize) {
+    for i in 0..11 {
+        if index >> (10 - i) & 1 == 1 {
+            let bit = offset + i;
+            data[bit / 8] |= 1 << (7 - bit % 8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_has_the_bip39_english_wordlist() {
+        assert_eq!(2048, WORDS.len());
+        assert_eq!("abandon", WORDS[0]);
+        assert_eq!("zoo", WORDS[2047]);
+        // binary search relies on this
+        assert!(WORDS.windows(2).all(|w| w[0] < w[1]));
+        // the first four letters identify a word
+        let mut prefixes = WORDS
+            .iter()
+            .map(|w| w.get(..4).unwrap_or(w))
+            .collect::<Vec<_>>();
+        prefixes.dedup();
+        assert_eq!(2048, prefixes.len());
+    }
+
+    /// Entropy / mnemonic pairs from the BIP39 reference test vectors.
+    const VECTORS: &[(&str, &str)] = &[
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon \
+            abandon abandon abandon about",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner \
+            thank yellow",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter \
+            advice cage above",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder \
+            eight picnic",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon \
+            abandon abandon abandon abandon abandon abandon abandon abandon \
+            abandon abandon abandon abandon abandon abandon abandon art",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner \
+            thank year wave sausage worth useful legal winner thank year wave \
+            sausage worth title",
+        ),
+        (
+            "8080808080808080808080808080808080808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter \
+            advice cage absurd amount doctor acoustic avoid letter advice cage \
+            absurd amount doctor acoustic bless",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo \
+            zoo zoo zoo zoo zoo zoo zoo vote",
+        ),
+        (
+            "68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c",
+            "hamster diagram private dutch cause delay private meat slide \
+            toddler razor book happy fancy gospel tennis maple dilemma loan \
+            word shrug inflict delay length",
+        ),
+        (
+            "f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+            "void come effort suffer camp survey warrior heavy shoot primary \
+            clutch crush open amazing screen patrol group space point ten \
+            exist slush involve unfold",
+        ),
+    ];
+
+    #[test]
+    fn it_matches_bip39_test_vectors() {
+        for (entropy, mnemonic) in VECTORS {
+            let entropy = unhex(entropy);
+            assert_eq!(*mnemonic, entropy_to_mnemonic(&entropy).unwrap());
+            assert_eq!(entropy, mnemonic_to_entropy(mnemonic).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_round_trips_seeds() {
+        for fill in 0..=255_u8 {
+            let seed =
+                (0..32).map(|i| fill.wrapping_mul(i)).collect::<Vec<_>>();
+            let mnemonic = entropy_to_mnemonic(&seed).unwrap();
+            assert_eq!(MNEMONIC_WORD_COUNT, mnemonic.split(' ').count());
+            assert_eq!(seed, mnemonic_to_entropy(&mnemonic).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_accepts_mixed_case_and_whitespace() {
+        let mnemonic = "  Legal WINNER thank year wave sausage worth useful\n\
+            legal winner thank year wave sausage worth useful legal winner \
+            thank\tyear wave sausage worth TiTlE ";
+        assert_eq!(vec![0x7f; 32], mnemonic_to_entropy(mnemonic).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_bad_checksums() {
+        // every word is valid, but the last one carries the checksum
+        let mut words = vec!["abandon"; 24];
+        for last in &["abandon", "able", "zoo", "arrow"] {
+            words[23] = last;
+            let err = mnemonic_to_entropy(&words.join(" ")).unwrap_err();
+            assert!(err.to_string().contains("checksum"), "{}", err);
+        }
+        words[23] = "art";
+        assert_eq!(vec![0; 32], mnemonic_to_entropy(&words.join(" ")).unwrap());
+
+        // swapping two words breaks the checksum too
+        let swapped = "winner legal thank year wave sausage worth useful \
+            legal winner thank yellow";
+        assert!(mnemonic_to_entropy(swapped).is_err());
+    }
+
+    #[test]
+    fn it_rejects_bad_mnemonics() {
+        // unknown word, not echoed back
+        let mut words = vec!["abandon"; 24];
+        words[5] = "lair";
+        let err = mnemonic_to_entropy(&words.join(" ")).unwrap_err();
+        assert!(err.to_string().contains("word 6"), "{}", err);
+        assert!(!err.to_string().contains("lair"), "{}", err);
+
+        // prefixes are not expanded
+        words[5] = "aban";
+        assert!(mnemonic_to_entropy(&words.join(" ")).is_err());
+
+        // wrong word counts
+        assert!(mnemonic_to_entropy("").is_err());
+        assert!(mnemonic_to_entropy(&["abandon"; 23].join(" ")).is_err());
+        assert!(mnemonic_to_entropy(&["abandon"; 25].join(" ")).is_err());
+
+        // wrong entropy lengths
+        assert!(entropy_to_mnemonic(&[0; 15]).is_err());
+        assert!(entropy_to_mnemonic(&[0; 18]).is_err());
+        assert!(entropy_to_mnemonic(&[0; 36]).is_err());
+    }
+}
@@ -2,92 +2,327 @@
 
 use crate::*;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chacha20poly1305::aead::{AeadInPlace, NewAead};
 use std::io::{Seek, SeekFrom, Write};
 
+/// Unlock Entry Type Identifier.
+pub const UNLOCK_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x01];
+
+/// Deleted Entry (tombstone) Type Identifier.
+pub const DELETED_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x02];
+
+/// Compacted (deleted index list) Entry Type Identifier.
+pub const COMPACTED_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x03];
+
 /// Tls Cert Entry Type Identifier.
 pub const TLS_CERT_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x10];
 
+/// Tls Cert (recording its algorithms and flags) Entry Type Identifier.
+pub const TLS_CERT_V2_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x11];
+
 /// Sign Ed25519 Entry Type Identifier.
 pub const SIGN_ED25519_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x20];
 
 /// X25519 Entry Type Identifier.
 pub const X25519_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x30];
 
+/// Master Seed Entry Type Identifier.
+pub const SEED_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x40];
+
+/// Sign Secp256k1 Entry Type Identifier.
+pub const SIGN_SECP256K1_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x50];
+
+/// Sign Bls12381 Entry Type Identifier.
+pub const SIGN_BLS12381_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x60];
+
+/// Secret Key Entry Type Identifier.
+pub const SECRET_KEY_ENTRY: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0x70];
+
+/// Set in the entry type identifier of sealed entries,
+/// whose secret field is encrypted with the store key.
+const SEALED_FLAG_BYTE: usize = 6;
+
+/// Set in the entry type identifier of checked entries: unsealed
+/// entries carry a checksum of the entry, the seal of sealed entries
+/// authenticates the whole entry rather than the entry type only.
+const CHECKED_FLAG_BYTE: usize = 5;
+
+/// Byte length of the checksum of an unsealed checked entry.
+pub const CHECKSUM_BYTES: usize = 16;
+
+/// The smallest pre-padding with room for the checksum of an entry,
+/// which is kept after the pre-padding header.
+pub const CHECKED_PRE_PADDING: u32 = (8 + CHECKSUM_BYTES) as u32;
+
+/// Byte length of the random nonce of a sealed entry.
+pub const SEAL_NONCE_BYTES: usize = 24;
+
+/// Byte length of the authentication tag of a sealed entry.
+pub const SEAL_TAG_BYTES: usize = 16;
+
+/// Byte length of the key sealing entry secret fields.
+pub const SEAL_KEY_BYTES: usize = 32;
+
+/// The smallest pre-padding with room for the nonce and tag of a seal,
+/// which are kept after the pre-padding header.
+pub const SEALED_PRE_PADDING: u32 =
+    (8 + SEAL_NONCE_BYTES + SEAL_TAG_BYTES) as u32;
+
 /// Entry Type Enum
 #[derive(Debug, PartialEq, Eq)]
 pub enum EntryType {
+    /// Unlock Entry Type
+    Unlock,
+
+    /// Deleted Entry (tombstone) Type
+    Deleted,
+
+    /// Compacted Entry (deleted index list) Type
+    Compacted,
+
     /// Tls Cert Entry Type
+    /// (as written before the algorithms and flags were recorded)
     TlsCert,
 
+    /// Tls Cert Entry Type, recording its algorithms and flags
+    TlsCertV2,
+
     /// Sign Ed25519 Entry Type
     SignEd25519,
 
     /// X25519 Entry Type
     X25519,
+
+    /// Master Seed Entry Type
+    Seed,
+
+    /// Sign Secp256k1 Entry Type
+    SignSecp256k1,
+
+    /// Sign Bls12381 Entry Type
+    /// (only decodable with the `bls` feature)
+    SignBls12381,
+
+    /// Secret Key Entry Type
+    SecretKey,
 }
 
 /// Read from bytes.
-pub struct CodecReader<'lt>(std::io::Cursor<&'lt [u8]>);
+pub struct CodecReader<'lt> {
+    cur: std::io::Cursor<&'lt [u8]>,
+    /// where the bytes after the header of the first pre-padding are
+    pre_padding: Option<std::ops::Range<usize>>,
+    /// the unsealed identifier of the first entry type
+    entry_type_id: Option<[u8; 8]>,
+    sealed: bool,
+    checked: bool,
+    seal_key: Option<&'lt [u8; SEAL_KEY_BYTES]>,
+}
 
 impl<'lt> CodecReader<'lt> {
     /// Create a new codec Reader.
     pub fn new(data: &'lt [u8]) -> Self {
-        Self(std::io::Cursor::new(data))
+        Self {
+            cur: std::io::Cursor::new(data),
+            pre_padding: None,
+            entry_type_id: None,
+            sealed: false,
+            checked: false,
+            seal_key: None,
+        }
+    }
+
+    /// Create a new codec Reader, able to open the secret field
+    /// of an entry sealed with `seal_key`.
+    pub fn new_with_seal_key(
+        data: &'lt [u8],
+        seal_key: &'lt [u8; SEAL_KEY_BYTES],
+    ) -> Self {
+        let mut out = Self::new(data);
+        out.seal_key = Some(seal_key);
+        out
     }
 
     /// Advance cursor beyond a pre-padding element
     pub fn read_pre_padding(&mut self) -> LairResult<()> {
         // pre-padding starts with 4 random bytes
-        seek_cur(&mut self.0, 4)?;
+        seek_cur(&mut self.cur, 4)?;
 
         // get the TOTAL pre-padding len
         // then subtract out 8 bytes for header for remaining-len
-        let rem_len = read_u32(&mut self.0)? - 8;
+        let rem_len = read_u32(&mut self.cur)?
+            .checked_sub(8)
+            .ok_or("invalid pre-padding length")?;
 
         // seek past the remaining len
-        seek_cur(&mut self.0, rem_len as i64)?;
+        let start = self.cur.position() as usize;
+        self.read_bytes(rem_len as u64)?;
+        if self.pre_padding.is_none() {
+            self.pre_padding = Some(start..self.cur.position() as usize);
+        }
 
         Ok(())
     }
 
     /// Read an entry type element.
+    /// Entry types of sealed entries are read as their unsealed type,
+    /// see `is_sealed()`.
+    /// The checksum of unsealed checked entries is verified here,
+    /// that of sealed entries as their secret field is opened.
     pub fn read_entry_type(&mut self) -> LairResult<EntryType> {
-        match self.read_bytes(8)? {
-            TLS_CERT_ENTRY => Ok(EntryType::TlsCert),
-            SIGN_ED25519_ENTRY => Ok(EntryType::SignEd25519),
-            X25519_ENTRY => Ok(EntryType::X25519),
-            _ => Err("invalid entry type bytes".into()),
+        let mut id = [0; 8];
+        id.copy_from_slice(self.read_bytes(8)?);
+        if id[SEALED_FLAG_BYTE] > 1 || id[CHECKED_FLAG_BYTE] > 1 {
+            return Err("invalid entry type bytes".into());
         }
+        let sealed = id[SEALED_FLAG_BYTE] == 1;
+        let checked = id[CHECKED_FLAG_BYTE] == 1;
+        id[SEALED_FLAG_BYTE] = 0;
+        id[CHECKED_FLAG_BYTE] = 0;
+        if self.entry_type_id.is_none() {
+            self.entry_type_id = Some(id);
+            self.sealed = sealed;
+            self.checked = checked;
+            if checked && !sealed {
+                self.verify_checksum()?;
+            }
+        } else if sealed || checked {
+            return Err("invalid entry type bytes".into());
+        }
+        let entry_type = match &id[..] {
+            UNLOCK_ENTRY => EntryType::Unlock,
+            DELETED_ENTRY => EntryType::Deleted,
+            COMPACTED_ENTRY => EntryType::Compacted,
+            TLS_CERT_ENTRY => EntryType::TlsCert,
+            TLS_CERT_V2_ENTRY => EntryType::TlsCertV2,
+            SIGN_ED25519_ENTRY => EntryType::SignEd25519,
+            X25519_ENTRY => EntryType::X25519,
+            SEED_ENTRY => EntryType::Seed,
+            SIGN_SECP256K1_ENTRY => EntryType::SignSecp256k1,
+            SIGN_BLS12381_ENTRY => EntryType::SignBls12381,
+            SECRET_KEY_ENTRY => EntryType::SecretKey,
+            _ => return Err("invalid entry type bytes".into()),
+        };
+        if sealed
+            && matches!(
+                entry_type,
+                EntryType::Unlock | EntryType::Deleted | EntryType::Compacted
+            )
+        {
+            return Err("invalid entry type bytes".into());
+        }
+        Ok(entry_type)
+    }
+
+    /// True if the entry type read was of a sealed entry.
+    pub fn is_sealed(&self) -> bool {
+        self.sealed
+    }
+
+    /// True if the entry type read was of a checked entry.
+    pub fn is_checked(&self) -> bool {
+        self.checked
+    }
+
+    fn verify_checksum(&self) -> LairResult<()> {
+        let data: &'lt [u8] = self.cur.get_ref();
+        let at = self
+            .pre_padding
+            .clone()
+            .filter(|p| p.len() >= CHECKSUM_BYTES)
+            .ok_or("invalid checked entry")?;
+        let at = at.start..at.start + CHECKSUM_BYTES;
+        if entry_checksum(data, at.clone())[..] != data[at] {
+            return Err(CHECKSUM_MISMATCH.into());
+        }
+        Ok(())
+    }
+
+    /// Read a u16 element.
+    pub fn read_u16(&mut self) -> LairResult<u16> {
+        read_u16(&mut self.cur)
     }
 
     /// Read a u32 element.
     pub fn read_u32(&mut self) -> LairResult<u32> {
-        read_u32(&mut self.0)
+        read_u32(&mut self.cur)
     }
 
     /// Read a u64 element.
     pub fn read_u64(&mut self) -> LairResult<u64> {
-        read_u64(&mut self.0)
+        read_u64(&mut self.cur)
     }
 
     /// Read bytes element.
-    pub fn read_bytes(&mut self, size: u64) -> LairResult<&[u8]> {
-        let start = self.0.position() as usize;
-        let end = (self.0.position() + size) as usize;
-        let slice = &self.0.get_ref()[start..end];
-        seek_cur(&mut self.0, size as i64)?;
-        Ok(slice)
+    pub fn read_bytes(&mut self, size: u64) -> LairResult<&'lt [u8]> {
+        let data: &'lt [u8] = self.cur.get_ref();
+        let start = self.cur.position() as usize;
+        let end = self
+            .cur
+            .position()
+            .checked_add(size)
+            .filter(|end| *end <= data.len() as u64)
+            .ok_or("read beyond end of entry")? as usize;
+        seek_cur(&mut self.cur, size as i64)?;
+        Ok(&data[start..end])
+    }
+
+    /// Read the secret field of an entry, opening it with the seal key
+    /// if the entry is sealed.
+    /// Fails with `StoreLocked` for sealed entries if there is no key.
+    pub fn read_secret(
+        &mut self,
+        size: u64,
+    ) -> LairResult<zeroize::Zeroizing<Vec<u8>>> {
+        let mut secret =
+            zeroize::Zeroizing::new(self.read_bytes(size)?.to_vec());
+        if !self.sealed {
+            return Ok(secret);
+        }
+        let key = self.seal_key.ok_or(LairError::StoreLocked)?;
+        let data: &'lt [u8] = self.cur.get_ref();
+        let type_id = self.entry_type_id.ok_or("invalid sealed entry")?;
+        let seal = self
+            .pre_padding
+            .clone()
+            .filter(|p| p.len() >= SEAL_NONCE_BYTES + SEAL_TAG_BYTES)
+            .ok_or("invalid sealed entry")?;
+        let seal = seal.start..seal.start + SEAL_NONCE_BYTES + SEAL_TAG_BYTES;
+        let secret_at = self.cur.position() as usize - secret.len();
+        let aad = if self.checked {
+            seal_aad(data, seal.clone(), secret_at..secret_at + secret.len())
+        } else {
+            type_id.to_vec()
+        };
+        let (nonce, tag) = data[seal].split_at(SEAL_NONCE_BYTES);
+        seal_cipher(key)
+            .decrypt_in_place_detached(
+                chacha20poly1305::XNonce::from_slice(nonce),
+                &aad,
+                &mut secret,
+                chacha20poly1305::Tag::from_slice(tag),
+            )
+            .map_err(|_| "sealed entry failed to open, corrupt or wrong key")?;
+        Ok(secret)
     }
 }
 
 /// Write to bytes.
-pub struct CodecWriter(std::io::Cursor<Vec<u8>>);
+pub struct CodecWriter {
+    cur: std::io::Cursor<Vec<u8>>,
+    /// where the bytes after the header of the first pre-padding are
+    pre_padding: Option<std::ops::Range<usize>>,
+    /// where the identifier of the first entry type is
+    entry_type_at: Option<usize>,
+    /// where the secret field is
+    secret: Option<std::ops::Range<usize>>,
+}
 
 impl CodecWriter {
     /// Create a new codec Writer.
     pub fn new_zeroed(size: usize) -> LairResult<Self> {
         let data = vec![0; size];
-        Ok(Self(std::io::Cursor::new(data)))
+        Ok(Self::with_data(data))
     }
 
     /// Create a new codec Writer.
@@ -96,29 +331,108 @@ impl CodecWriter {
         let sys_rand = ring::rand::SystemRandom::new();
         ring::rand::SecureRandom::fill(&sys_rand, &mut data)
             .map_err(|e| format!("{:?}", e))?;
-        Ok(Self(std::io::Cursor::new(data)))
+        Ok(Self::with_data(data))
+    }
+
+    fn with_data(data: Vec<u8>) -> Self {
+        Self {
+            cur: std::io::Cursor::new(data),
+            pre_padding: None,
+            entry_type_at: None,
+            secret: None,
+        }
     }
 
     /// Convert this codec writer into the underlying Vec<u8>
     pub fn into_vec(self) -> Vec<u8> {
-        self.0.into_inner()
+        self.cur.into_inner()
+    }
+
+    /// Convert the written entry into the underlying Vec<u8>, checking
+    /// it: unless sealed, which already authenticates the entry, its
+    /// checksum is kept in its pre-padding, which must be at least
+    /// `CHECKED_PRE_PADDING` long.
+    pub fn finish(mut self) -> LairResult<Vec<u8>> {
+        let pre_padding = self.pre_padding.clone().ok_or("no pre-padding")?;
+        let entry_type_at = self.entry_type_at.ok_or("no entry type")?;
+        let data = self.cur.get_mut();
+        if data[entry_type_at + SEALED_FLAG_BYTE] == 1 {
+            return Ok(self.into_vec());
+        }
+        if pre_padding.len() < CHECKSUM_BYTES {
+            return Err("pre-padding has no room for a checksum".into());
+        }
+
+        data[entry_type_at + CHECKED_FLAG_BYTE] = 1;
+        let at = pre_padding.start..pre_padding.start + CHECKSUM_BYTES;
+        let checksum = entry_checksum(data, at.clone());
+        data[at].copy_from_slice(&checksum);
+        Ok(self.into_vec())
+    }
+
+    /// Seal the written entry: encrypt its secret field in place with
+    /// `seal_key`, keeping the nonce and tag in its pre-padding,
+    /// which must be at least `SEALED_PRE_PADDING` long.
+    /// The seal authenticates the rest of the entry as well.
+    /// Write the whole entry before sealing it.
+    pub fn seal(&mut self, seal_key: &[u8; SEAL_KEY_BYTES]) -> LairResult<()> {
+        let pre_padding = self.pre_padding.clone().ok_or("no pre-padding")?;
+        let entry_type_at = self.entry_type_at.ok_or("no entry type")?;
+        let secret = self.secret.clone().ok_or("no secret field")?;
+        if pre_padding.len() < SEAL_NONCE_BYTES + SEAL_TAG_BYTES {
+            return Err("pre-padding has no room for a seal".into());
+        }
+
+        let data = self.cur.get_mut();
+        let mut type_id = [0; 8];
+        type_id.copy_from_slice(&data[entry_type_at..entry_type_at + 8]);
+        if type_id[SEALED_FLAG_BYTE] != 0 || type_id[CHECKED_FLAG_BYTE] != 0 {
+            return Err("entry is already sealed".into());
+        }
+
+        let seal = pre_padding.start
+            ..pre_padding.start + SEAL_NONCE_BYTES + SEAL_TAG_BYTES;
+        data[entry_type_at + SEALED_FLAG_BYTE] = 1;
+        data[entry_type_at + CHECKED_FLAG_BYTE] = 1;
+        let aad = seal_aad(data, seal.clone(), secret.clone());
+
+        let mut nonce = [0; SEAL_NONCE_BYTES];
+        let sys_rand = ring::rand::SystemRandom::new();
+        ring::rand::SecureRandom::fill(&sys_rand, &mut nonce)
+            .map_err(|e| format!("{:?}", e))?;
+        let tag = seal_cipher(seal_key)
+            .encrypt_in_place_detached(
+                chacha20poly1305::XNonce::from_slice(&nonce),
+                &aad,
+                &mut data[secret],
+            )
+            .map_err(|_| "failed to seal entry")?;
+
+        let (nonce_at, tag_at) = data[seal].split_at_mut(SEAL_NONCE_BYTES);
+        nonce_at.copy_from_slice(&nonce);
+        tag_at.copy_from_slice(&tag);
+        Ok(())
     }
 
     /// Write pre-padding element.
     pub fn write_pre_padding(&mut self, size: u32) -> LairResult<()> {
-        if self.0.position() + size as u64 > self.0.get_ref().len() as u64 {
+        if self.cur.position() + size as u64 > self.cur.get_ref().len() as u64 {
             panic!("pre padding would write beyond end of buffer");
         }
 
         // pre-padding starts with 4 random bytes
-        seek_cur(&mut self.0, 4)?;
+        seek_cur(&mut self.cur, 4)?;
 
         // write the TOTAL length of the pre_padding
         // (should be multiple of 4)
-        write_u32(&mut self.0, size)?;
+        write_u32(&mut self.cur, size)?;
 
         // seek past the total length (minus rand header + sizeof u32)
-        seek_cur(&mut self.0, size as i64 - 8)?;
+        let start = self.cur.position() as usize;
+        seek_cur(&mut self.cur, size as i64 - 8)?;
+        if self.pre_padding.is_none() {
+            self.pre_padding = Some(start..self.cur.position() as usize);
+        }
 
         Ok(())
     }
@@ -128,32 +442,105 @@ impl CodecWriter {
         &mut self,
         entry_type: EntryType,
     ) -> LairResult<()> {
+        if self.entry_type_at.is_none() {
+            self.entry_type_at = Some(self.cur.position() as usize);
+        }
         match entry_type {
-            EntryType::TlsCert => self.0.write_all(TLS_CERT_ENTRY),
-            EntryType::SignEd25519 => self.0.write_all(SIGN_ED25519_ENTRY),
-            EntryType::X25519 => self.0.write_all(X25519_ENTRY),
+            EntryType::Unlock => self.cur.write_all(UNLOCK_ENTRY),
+            EntryType::Deleted => self.cur.write_all(DELETED_ENTRY),
+            EntryType::Compacted => self.cur.write_all(COMPACTED_ENTRY),
+            EntryType::TlsCert => self.cur.write_all(TLS_CERT_ENTRY),
+            EntryType::TlsCertV2 => self.cur.write_all(TLS_CERT_V2_ENTRY),
+            EntryType::SignEd25519 => self.cur.write_all(SIGN_ED25519_ENTRY),
+            EntryType::X25519 => self.cur.write_all(X25519_ENTRY),
+            EntryType::Seed => self.cur.write_all(SEED_ENTRY),
+            EntryType::SignSecp256k1 => {
+                self.cur.write_all(SIGN_SECP256K1_ENTRY)
+            }
+            EntryType::SignBls12381 => self.cur.write_all(SIGN_BLS12381_ENTRY),
+            EntryType::SecretKey => self.cur.write_all(SECRET_KEY_ENTRY),
         }
         .map_err(LairError::other)?;
         Ok(())
     }
 
+    /// Write a u16 element.
+    pub fn write_u16(&mut self, val: u16) -> LairResult<()> {
+        write_u16(&mut self.cur, val)?;
+        Ok(())
+    }
+
     /// Write a u32 element.
     pub fn write_u32(&mut self, val: u32) -> LairResult<()> {
-        write_u32(&mut self.0, val)?;
+        write_u32(&mut self.cur, val)?;
         Ok(())
     }
 
     /// Write a u64 element.
     pub fn write_u64(&mut self, val: u64) -> LairResult<()> {
-        write_u64(&mut self.0, val)?;
+        write_u64(&mut self.cur, val)?;
         Ok(())
     }
 
     /// Write bytes element.
     pub fn write_bytes(&mut self, val: &[u8]) -> LairResult<()> {
-        self.0.write_all(val).map_err(LairError::other)?;
+        self.cur.write_all(val).map_err(LairError::other)?;
         Ok(())
     }
+
+    /// Write the secret field of an entry, the bytes encrypted by
+    /// `seal()`. An entry has at most one secret field.
+    pub fn write_secret(&mut self, val: &[u8]) -> LairResult<()> {
+        if self.secret.is_some() {
+            return Err("entry already has a secret field".into());
+        }
+        let start = self.cur.position() as usize;
+        self.write_bytes(val)?;
+        self.secret = Some(start..self.cur.position() as usize);
+        Ok(())
+    }
+}
+
+fn seal_cipher(
+    seal_key: &[u8; SEAL_KEY_BYTES],
+) -> chacha20poly1305::XChaCha20Poly1305 {
+    chacha20poly1305::XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(
+        seal_key,
+    ))
+}
+
+/// The error of an unsealed entry failing its checksum.
+const CHECKSUM_MISMATCH: &str = "entry checksum mismatch, the entry is corrupt";
+
+/// The checksum of an entry, `data` but the checksum bytes `at`.
+fn entry_checksum(
+    data: &[u8],
+    at: std::ops::Range<usize>,
+) -> [u8; CHECKSUM_BYTES] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(CHECKSUM_BYTES)
+        .to_state()
+        .update(&data[..at.start])
+        .update(&data[at.end..])
+        .finalize();
+    let mut out = [0; CHECKSUM_BYTES];
+    out.copy_from_slice(hash.as_bytes());
+    out
+}
+
+/// The bytes authenticated by the seal of a checked entry: all of
+/// `data` but the nonce and tag of the `seal` and the `secret` field,
+/// which the seal covers as ciphertext.
+fn seal_aad(
+    data: &[u8],
+    seal: std::ops::Range<usize>,
+    secret: std::ops::Range<usize>,
+) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(data.len());
+    aad.extend_from_slice(&data[..seal.start]);
+    aad.extend_from_slice(&data[seal.end..secret.start]);
+    aad.extend_from_slice(&data[secret.end..]);
+    aad
 }
 
 // -- local helpers -- //
@@ -167,6 +554,23 @@ where
     Ok(())
 }
 
+fn write_u16<W>(mut writer: W, val: u16) -> LairResult<()>
+where
+    W: std::io::Write,
+{
+    writer
+        .write_u16::<LittleEndian>(val)
+        .map_err(LairError::other)?;
+    Ok(())
+}
+
+fn read_u16<R>(mut reader: R) -> LairResult<u16>
+where
+    R: std::io::Read,
+{
+    reader.read_u16::<LittleEndian>().map_err(LairError::other)
+}
+
 fn write_u32<W>(mut writer: W, val: u32) -> LairResult<()>
 where
     W: std::io::Write,
@@ -235,4 +639,18 @@ mod tests {
         reader.read_pre_padding().unwrap();
         assert_eq!(&[44, 44, 44, 44], reader.read_bytes(4).unwrap());
     }
+
+    #[test]
+    fn it_codec_rejects_out_of_range_reads() {
+        let raw = [0_u8; 16];
+
+        // total pre-padding len 0 is less than its own header
+        let mut reader = CodecReader::new(&raw);
+        assert!(reader.read_pre_padding().is_err());
+
+        let mut reader = CodecReader::new(&raw);
+        assert!(reader.read_bytes(17).is_err());
+        assert!(reader.read_bytes(u64::MAX).is_err());
+        assert_eq!(&[0; 16], reader.read_bytes(16).unwrap());
+    }
 }
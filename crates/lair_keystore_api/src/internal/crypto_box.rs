@@ -1,7 +1,12 @@
-use crate::internal::rayon::rayon_exec;
+use crate::actor::KeystoreIndex;
+use crate::internal::rayon::{rayon_exec, rayon_exec_sized};
+use crate::internal::secretstream::hchacha20;
 use crate::internal::x25519;
 use block_padding::Padding;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
 use crypto_box as lib_crypto_box;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 /// Length of the crypto box aead nonce.
@@ -17,13 +22,17 @@ pub const BLOCK_PADDING_SIZE: usize = 32;
 /// The delimiter for padding as per ISO 7816-4.
 pub const BLOCK_PADDING_DELIMITER: u8 = 0x80;
 
+/// Bytes a sealed box adds to the sealed data:
+/// the ephemeral sender pub key, then the crypto_box mac.
+pub const SEALED_BOX_OVERHEAD_BYTES: usize = 32 + 16;
+
 /// Newtype for the nonce for safety.
 #[derive(Debug, PartialEq, Clone)]
 pub struct CryptoBoxNonce([u8; NONCE_BYTES]);
 
 impl CryptoBoxNonce {
     async fn new_random() -> Self {
-        rayon_exec(move || {
+        rayon_exec_sized(0, move || {
             let mut rng = rand::thread_rng();
             let mut bytes = [0; NONCE_BYTES];
             // We rely on the lib_crypto_box nonce length being the same as what we expect.
@@ -80,12 +89,9 @@ impl CryptoBoxNonce {
     }
 }
 
-/// "Additional associated data" as per the aead rust crate Payload.
-/// May be empty. Must be valid if present.
-pub struct CryptoBoxAad(Vec<u8>);
-
 /// The nonce and encrypted data together.
-/// @todo include additional associated data?
+/// The additional authenticated data of `crypto_box_with_aad` is not
+/// included, the recipient must already know it to open the box.
 #[derive(Debug, PartialEq, Clone)]
 pub struct CryptoBoxEncryptedData {
     /// The nonce generated during encryption.
@@ -101,8 +107,8 @@ pub struct CryptoBoxEncryptedData {
 #[derive(Debug, PartialEq, Clone)]
 pub struct CryptoBoxData {
     /// Data to be encrypted.
-    #[allow(clippy::rc_buffer)]
-    pub data: Arc<Vec<u8>>,
+    /// Received over ipc, this is a slice of the received frame.
+    pub data: bytes::Bytes,
 }
 
 impl AsRef<[u8]> for CryptoBoxData {
@@ -125,7 +131,58 @@ impl CryptoBoxData {
 
 impl From<Vec<u8>> for CryptoBoxData {
     fn from(v: Vec<u8>) -> Self {
-        Self { data: Arc::new(v) }
+        Self { data: v.into() }
+    }
+}
+
+impl From<Arc<Vec<u8>>> for CryptoBoxData {
+    #[allow(clippy::rc_buffer)]
+    fn from(v: Arc<Vec<u8>>) -> Self {
+        // no copy unless the arc is shared
+        let data = Arc::try_unwrap(v).unwrap_or_else(|v| (*v).clone());
+        Self { data: data.into() }
+    }
+}
+
+impl From<bytes::Bytes> for CryptoBoxData {
+    fn from(data: bytes::Bytes) -> Self {
+        Self { data }
+    }
+}
+
+/// Data sealed to a recipient with `crypto_box_seal`.
+/// Byte for byte the output of libsodium's `crypto_box_seal`: the
+/// ephemeral sender pub key, then the crypto_box of the (unpadded) data.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CryptoBoxSealedData {
+    /// The ephemeral pub key and the encrypted data.
+    #[allow(clippy::rc_buffer)]
+    pub sealed_data: Arc<Vec<u8>>,
+}
+
+impl AsRef<[u8]> for CryptoBoxSealedData {
+    fn as_ref(&self) -> &[u8] {
+        self.sealed_data.as_ref()
+    }
+}
+
+impl CryptoBoxSealedData {
+    /// Length of newtype is length of inner.
+    pub fn len(&self) -> usize {
+        AsRef::<[u8]>::as_ref(self).len()
+    }
+
+    /// For clippy.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<Vec<u8>> for CryptoBoxSealedData {
+    fn from(v: Vec<u8>) -> Self {
+        Self {
+            sealed_data: Arc::new(v),
+        }
     }
 }
 
@@ -138,7 +195,8 @@ impl From<Vec<u8>> for CryptoBoxData {
 /// Wrapper around crypto_box from whatever lib we use.
 /// No BYO nonces. Nonces always random and returned as part of `CryptoBoxEncryptedData`.
 /// No BYO algorithms (cipher agility). Algorithm always X25519XSalsa20Poly1305.
-/// Currently no additional associated data but DNA space may be included in the future.
+/// No additional associated data, see `crypto_box_with_aad` to bind the
+/// ciphertext to its context (e.g. message type or DHT space).
 /// The sender's private key encrypts _for_ the recipient's pubkey.
 ///
 /// FYI allowing nonces could be dangerous as it's exposed as a general purpose authenticated
@@ -172,38 +230,62 @@ pub async fn crypto_box(
     data: Arc<CryptoBoxData>,
 ) -> crate::error::LairResult<CryptoBoxEncryptedData> {
     let nonce = CryptoBoxNonce::new_random().await;
-    rayon_exec(move || {
-        use lib_crypto_box::aead::Aead;
+    rayon_exec_sized(data.data.len(), move || {
         let sender_box =
             lib_crypto_box::SalsaBox::new(recipient.as_ref(), sender.as_ref());
-
-        // It's actually easier and clearer to directly pad the vector than use the block_padding
-        // crate, as that is optimised for blocks.
-        let mut to_encrypt = data.data.to_vec();
-        let padding_delimiter = vec![BLOCK_PADDING_DELIMITER];
-        let padding = vec![
-            0x0;
-            BLOCK_PADDING_SIZE
-                - (data.data.len() + 1) % BLOCK_PADDING_SIZE
-        ];
-        to_encrypt.extend(padding_delimiter);
-        to_encrypt.extend(padding);
-
-        let encrypted_data = Arc::new(sender_box.encrypt(
-            AsRef::<[u8; NONCE_BYTES]>::as_ref(&nonce).into(),
-            to_encrypt.as_slice(),
-        )?);
-
-        // @todo do we want associated data to enforce the originating DHT space?
-        // https://eprint.iacr.org/2019/519.pdf for 'context separable interfaces'
-        Ok(CryptoBoxEncryptedData {
-            encrypted_data,
-            nonce,
-        })
+        box_data(&sender_box, nonce, &data)
     })
     .await
 }
 
+/// Pad and encrypt `data` with an already keyed box.
+fn box_data(
+    sender_box: &lib_crypto_box::SalsaBox,
+    nonce: CryptoBoxNonce,
+    data: &CryptoBoxData,
+) -> crate::error::LairResult<CryptoBoxEncryptedData> {
+    use lib_crypto_box::aead::Aead;
+
+    let to_encrypt = pad(data);
+    let encrypted_data = Arc::new(sender_box.encrypt(
+        AsRef::<[u8; NONCE_BYTES]>::as_ref(&nonce).into(),
+        to_encrypt.as_slice(),
+    )?);
+
+    Ok(CryptoBoxEncryptedData {
+        encrypted_data,
+        nonce,
+    })
+}
+
+/// Iso7816 pad `data` to a whole number of `BLOCK_PADDING_SIZE` blocks.
+/// The plaintext copy is zeroized once encrypted.
+fn pad(data: &CryptoBoxData) -> zeroize::Zeroizing<Vec<u8>> {
+    // It's actually easier and clearer to directly pad the vector than use the block_padding
+    // crate, as that is optimised for blocks.
+    let mut padded = zeroize::Zeroizing::new(Vec::with_capacity(
+        data.data.len() + BLOCK_PADDING_SIZE,
+    ));
+    padded.extend_from_slice(&data.data);
+    let padding_delimiter = vec![BLOCK_PADDING_DELIMITER];
+    let padding = vec![
+        0x0;
+        BLOCK_PADDING_SIZE
+            - (data.data.len() + 1) % BLOCK_PADDING_SIZE
+    ];
+    padded.extend(padding_delimiter);
+    padded.extend(padding);
+    padded
+}
+
+/// Inverse of `pad`, `None` if the padding is invalid.
+fn unpad(padded: &[u8]) -> Option<CryptoBoxData> {
+    match block_padding::Iso7816::unpad(padded) {
+        Ok(unpadded) => Some(unpadded.to_vec().into()),
+        Err(_) => None,
+    }
+}
+
 /// Wrapper around crypto_box_open from whatever lib we use.
 /// Exact inverse of `crypto_box_open` so nonce must be provided in `CryptoBoxEncryptedData`.
 /// The recipient's private key encrypts _from_ the sender's pubkey.
@@ -212,29 +294,459 @@ pub async fn crypto_box_open(
     sender: x25519::X25519PubKey,
     encrypted_data: Arc<CryptoBoxEncryptedData>,
 ) -> crate::error::LairResult<Option<CryptoBoxData>> {
-    rayon_exec(move || {
-        use lib_crypto_box::aead::Aead;
+    rayon_exec_sized(encrypted_data.encrypted_data.len(), move || {
         let recipient_box =
             lib_crypto_box::SalsaBox::new(sender.as_ref(), recipient.as_ref());
-        match recipient_box.decrypt(
-            AsRef::<[u8; NONCE_BYTES]>::as_ref(&encrypted_data.nonce).into(),
-            encrypted_data.encrypted_data.as_slice(),
-        ) {
-            Ok(decrypted_data) => {
-                match block_padding::Iso7816::unpad(&decrypted_data) {
-                    // @todo do we want associated data to enforce the originating DHT space?
-                    Ok(unpadded) => Ok(Some(CryptoBoxData {
-                        data: Arc::new(unpadded.to_vec()),
-                    })),
-                    Err(_) => Ok(None),
-                }
+        Ok(open_data(&recipient_box, &encrypted_data))
+    })
+    .await
+}
+
+/// Decrypt and unpad `encrypted_data` with an already keyed box.
+fn open_data(
+    recipient_box: &lib_crypto_box::SalsaBox,
+    encrypted_data: &CryptoBoxEncryptedData,
+) -> Option<CryptoBoxData> {
+    use lib_crypto_box::aead::Aead;
+    match recipient_box.decrypt(
+        AsRef::<[u8; NONCE_BYTES]>::as_ref(&encrypted_data.nonce).into(),
+        encrypted_data.encrypted_data.as_slice(),
+    ) {
+        Ok(decrypted_data) => unpad(&zeroize::Zeroizing::new(decrypted_data)),
+        Err(_) => None,
+    }
+}
+
+/// As `crypto_box`, additionally authenticating (but not encrypting)
+/// `aad`, so the box only opens with `crypto_box_open_with_aad` given the
+/// same aad: binding it to its context (message type, DHT basis hash...)
+/// stops it being replayed into a different one.
+///
+/// The data is padded as for `crypto_box`, then encrypted under
+/// XChaCha20-Poly1305 (libsodium's `crypto_aead_xchacha20poly1305_ietf`)
+/// keyed by libsodium's `crypto_box_curve25519xchacha20poly1305_beforenm`.
+/// The output is NOT interchangeable with that of `crypto_box`.
+/// Fails for low order recipient pub keys.
+#[allow(clippy::rc_buffer)]
+pub async fn crypto_box_with_aad(
+    sender: x25519::X25519PrivKey,
+    recipient: x25519::X25519PubKey,
+    aad: Arc<Vec<u8>>,
+    data: Arc<CryptoBoxData>,
+) -> crate::error::LairResult<CryptoBoxEncryptedData> {
+    let nonce = CryptoBoxNonce::new_random().await;
+    rayon_exec_sized(aad.len() + data.data.len(), move || {
+        let sender_aead = aead_box(&sender, &recipient)?;
+        box_data_with_aad(&sender_aead, nonce, &aad, &data)
+    })
+    .await
+}
+
+/// The XChaCha20-Poly1305 cipher keyed by the x25519 shared secret.
+fn aead_box(
+    priv_key: &x25519::X25519PrivKey,
+    pub_key: &x25519::X25519PubKey,
+) -> crate::error::LairResult<XChaCha20Poly1305> {
+    let shared = x25519::x25519_shared_secret(priv_key, pub_key)?;
+    let key = zeroize::Zeroizing::new(hchacha20(&shared, &[0; 16]));
+    Ok(XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(
+        &*key,
+    )))
+}
+
+/// Pad and encrypt `data`, authenticating `aad`, with an already keyed
+/// aead box.
+fn box_data_with_aad(
+    sender_aead: &XChaCha20Poly1305,
+    nonce: CryptoBoxNonce,
+    aad: &[u8],
+    data: &CryptoBoxData,
+) -> crate::error::LairResult<CryptoBoxEncryptedData> {
+    let to_encrypt = pad(data);
+    let encrypted_data = Arc::new(
+        sender_aead
+            .encrypt(
+                AsRef::<[u8; NONCE_BYTES]>::as_ref(&nonce).into(),
+                Payload {
+                    msg: &to_encrypt,
+                    aad,
+                },
+            )
+            .map_err(|_| {
+                crate::error::LairError::from("crypto_box encryption failed")
+            })?,
+    );
+    Ok(CryptoBoxEncryptedData {
+        encrypted_data,
+        nonce,
+    })
+}
+
+/// Inverse of `crypto_box_with_aad`. Resolves to `None` if the box was
+/// not made between these keys with this aad, or has been tampered with.
+#[allow(clippy::rc_buffer)]
+pub async fn crypto_box_open_with_aad(
+    recipient: x25519::X25519PrivKey,
+    sender: x25519::X25519PubKey,
+    aad: Arc<Vec<u8>>,
+    encrypted_data: Arc<CryptoBoxEncryptedData>,
+) -> crate::error::LairResult<Option<CryptoBoxData>> {
+    let len = aad.len() + encrypted_data.encrypted_data.len();
+    rayon_exec_sized(len, move || {
+        let recipient_aead = aead_box(&recipient, &sender)?;
+        Ok(open_data_with_aad(&recipient_aead, &aad, &encrypted_data))
+    })
+    .await
+}
+
+/// Decrypt and unpad `encrypted_data`, checking `aad`, with an already
+/// keyed aead box.
+fn open_data_with_aad(
+    recipient_aead: &XChaCha20Poly1305,
+    aad: &[u8],
+    encrypted_data: &CryptoBoxEncryptedData,
+) -> Option<CryptoBoxData> {
+    match recipient_aead.decrypt(
+        AsRef::<[u8; NONCE_BYTES]>::as_ref(&encrypted_data.nonce).into(),
+        Payload {
+            msg: &encrypted_data.encrypted_data,
+            aad,
+        },
+    ) {
+        Ok(decrypted_data) => unpad(&zeroize::Zeroizing::new(decrypted_data)),
+        Err(_) => None,
+    }
+}
+
+/// Default number of precomputed keys a `CryptoBoxCache` holds.
+pub const DEFAULT_CACHE_SIZE: usize = 1024;
+
+type CacheKey = (KeystoreIndex, [u8; 32]);
+
+/// Least recently used cache of keyed boxes (the `crypto_box_beforenm`
+/// shared key) by local keystore index and remote pub key, so repeated
+/// boxes between the same pair of keys skip the x25519 scalar mult.
+/// Boxes with and without aad are keyed differently, and cached apart.
+/// Entries must be invalidated when their keystore index is deleted,
+/// and all of them cleared when the store is locked.
+#[derive(Clone)]
+pub struct CryptoBoxCache(Arc<tokio::sync::Mutex<CacheInner>>);
+
+struct CacheInner {
+    salsa_boxes: Lru<Arc<lib_crypto_box::SalsaBox>>,
+    aead_boxes: Lru<Arc<XChaCha20Poly1305>>,
+    // bumped by every invalidation, so boxes keyed meanwhile
+    // are not cached after them
+    generation: u64,
+}
+
+impl CryptoBoxCache {
+    /// A cache holding up to `capacity` keyed boxes of each kind
+    /// (0 disables it).
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(tokio::sync::Mutex::new(CacheInner {
+            salsa_boxes: Lru::new(capacity),
+            aead_boxes: Lru::new(capacity),
+            generation: 0,
+        })))
+    }
+
+    /// As `crypto_box`, for the private key at `keystore_index`.
+    pub async fn crypto_box(
+        &self,
+        keystore_index: KeystoreIndex,
+        sender: x25519::X25519PrivKey,
+        recipient: x25519::X25519PubKey,
+        data: Arc<CryptoBoxData>,
+    ) -> crate::error::LairResult<CryptoBoxEncryptedData> {
+        let nonce = CryptoBoxNonce::new_random().await;
+        let sender_box =
+            self.get_salsa_box(keystore_index, sender, recipient).await;
+        rayon_exec_sized(data.data.len(), move || {
+            box_data(&sender_box, nonce, &data)
+        })
+        .await
+    }
+
+    /// As `crypto_box_open`, for the private key at `keystore_index`.
+    pub async fn crypto_box_open(
+        &self,
+        keystore_index: KeystoreIndex,
+        recipient: x25519::X25519PrivKey,
+        sender: x25519::X25519PubKey,
+        encrypted_data: Arc<CryptoBoxEncryptedData>,
+    ) -> crate::error::LairResult<Option<CryptoBoxData>> {
+        let recipient_box =
+            self.get_salsa_box(keystore_index, recipient, sender).await;
+        let len = encrypted_data.encrypted_data.len();
+        Ok(rayon_exec_sized(len, move || {
+            open_data(&recipient_box, &encrypted_data)
+        })
+        .await)
+    }
+
+    /// As `crypto_box_with_aad`, for the private key at `keystore_index`.
+    #[allow(clippy::rc_buffer)]
+    pub async fn crypto_box_with_aad(
+        &self,
+        keystore_index: KeystoreIndex,
+        sender: x25519::X25519PrivKey,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<CryptoBoxData>,
+    ) -> crate::error::LairResult<CryptoBoxEncryptedData> {
+        let nonce = CryptoBoxNonce::new_random().await;
+        let sender_aead =
+            self.get_aead_box(keystore_index, sender, recipient).await?;
+        rayon_exec_sized(aad.len() + data.data.len(), move || {
+            box_data_with_aad(&sender_aead, nonce, &aad, &data)
+        })
+        .await
+    }
+
+    /// As `crypto_box_open_with_aad`, for the private key at
+    /// `keystore_index`.
+    #[allow(clippy::rc_buffer)]
+    pub async fn crypto_box_open_with_aad(
+        &self,
+        keystore_index: KeystoreIndex,
+        recipient: x25519::X25519PrivKey,
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<CryptoBoxEncryptedData>,
+    ) -> crate::error::LairResult<Option<CryptoBoxData>> {
+        let recipient_aead =
+            self.get_aead_box(keystore_index, recipient, sender).await?;
+        let len = aad.len() + encrypted_data.encrypted_data.len();
+        Ok(rayon_exec_sized(len, move || {
+            open_data_with_aad(&recipient_aead, &aad, &encrypted_data)
+        })
+        .await)
+    }
+
+    /// Drop every keyed box of the private key at `keystore_index`.
+    pub async fn invalidate(&self, keystore_index: KeystoreIndex) {
+        let mut inner = self.0.lock().await;
+        inner.generation += 1;
+        inner.salsa_boxes.invalidate(keystore_index);
+        inner.aead_boxes.invalidate(keystore_index);
+    }
+
+    /// Drop every keyed box.
+    pub async fn clear(&self) {
+        let mut inner = self.0.lock().await;
+        inner.generation += 1;
+        inner.salsa_boxes.clear();
+        inner.aead_boxes.clear();
+    }
+
+    async fn get_salsa_box(
+        &self,
+        keystore_index: KeystoreIndex,
+        priv_key: x25519::X25519PrivKey,
+        pub_key: x25519::X25519PubKey,
+    ) -> Arc<lib_crypto_box::SalsaBox> {
+        let key = (keystore_index, pub_key.to_bytes());
+        let generation = {
+            let mut inner = self.0.lock().await;
+            if let Some(salsa_box) = inner.salsa_boxes.touch(&key) {
+                return salsa_box;
+            }
+            inner.generation
+        };
+        // the scalar mult happens outside the lock,
+        // so a miss costs the same as without the cache
+        let salsa_box = rayon_exec_sized(0, move || {
+            Arc::new(lib_crypto_box::SalsaBox::new(
+                pub_key.as_ref(),
+                priv_key.as_ref(),
+            ))
+        })
+        .await;
+        let mut inner = self.0.lock().await;
+        if inner.generation == generation {
+            inner.salsa_boxes.insert(key, salsa_box.clone());
+        }
+        salsa_box
+    }
+
+    async fn get_aead_box(
+        &self,
+        keystore_index: KeystoreIndex,
+        priv_key: x25519::X25519PrivKey,
+        pub_key: x25519::X25519PubKey,
+    ) -> crate::error::LairResult<Arc<XChaCha20Poly1305>> {
+        let key = (keystore_index, pub_key.to_bytes());
+        let generation = {
+            let mut inner = self.0.lock().await;
+            if let Some(aead) = inner.aead_boxes.touch(&key) {
+                return Ok(aead);
+            }
+            inner.generation
+        };
+        let aead = rayon_exec_sized(0, move || {
+            aead_box(&priv_key, &pub_key).map(Arc::new)
+        })
+        .await?;
+        let mut inner = self.0.lock().await;
+        if inner.generation == generation {
+            inner.aead_boxes.insert(key, aead.clone());
+        }
+        Ok(aead)
+    }
+}
+
+struct Lru<V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, (u64, V)>,
+    by_tick: BTreeMap<u64, CacheKey>,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            by_tick: BTreeMap::new(),
+        }
+    }
+
+    fn touch(&mut self, key: &CacheKey) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (old_tick, value) = self.entries.get_mut(key)?;
+        self.by_tick.remove(old_tick);
+        self.by_tick.insert(tick, *key);
+        *old_tick = tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((old_tick, _)) =
+            self.entries.insert(key, (self.tick, value))
+        {
+            self.by_tick.remove(&old_tick);
+        }
+        self.by_tick.insert(self.tick, key);
+        while self.entries.len() > self.capacity {
+            let oldest = *self.by_tick.keys().next().expect("ticks of entries");
+            if let Some(key) = self.by_tick.remove(&oldest) {
+                self.entries.remove(&key);
             }
+        }
+    }
+
+    fn invalidate(&mut self, keystore_index: KeystoreIndex) {
+        let Self {
+            entries, by_tick, ..
+        } = self;
+        entries.retain(|key, (tick, _)| {
+            if key.0 == keystore_index {
+                by_tick.remove(tick);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.by_tick.clear();
+    }
+}
+
+/// Anonymously encrypt data to a recipient, exactly as libsodium's
+/// `crypto_box_seal`, so sodium peers can open it and vice versa.
+/// A fresh ephemeral keypair boxes the data, its private key is dropped
+/// straight away: not even the sender can open the result.
+/// Needs no secrets, so this never has to go through lair.
+/// Unlike `crypto_box`, the data is NOT padded (sodium doesn't pad it).
+pub async fn crypto_box_seal(
+    recipient: x25519::X25519PubKey,
+    data: Arc<CryptoBoxData>,
+) -> crate::error::LairResult<CryptoBoxSealedData> {
+    rayon_exec(move || {
+        let ephemeral =
+            lib_crypto_box::SecretKey::generate(&mut rand::thread_rng());
+        seal(&ephemeral, &recipient, &data.data)
+    })
+    .await
+}
+
+/// Open data sealed to `recipient_pub_key`, as libsodium's
+/// `crypto_box_seal_open`. Resolves to `None` if the data was not sealed
+/// to this keypair, or has been tampered with.
+pub async fn crypto_box_seal_open(
+    recipient: x25519::X25519PrivKey,
+    recipient_pub_key: x25519::X25519PubKey,
+    sealed_data: Arc<CryptoBoxSealedData>,
+) -> crate::error::LairResult<Option<CryptoBoxData>> {
+    rayon_exec(move || {
+        use lib_crypto_box::aead::Aead;
+        let sealed = &sealed_data.sealed_data;
+        if sealed.len() < SEALED_BOX_OVERHEAD_BYTES {
+            return Ok(None);
+        }
+        let mut ephemeral_pub_key = [0; 32];
+        ephemeral_pub_key.copy_from_slice(&sealed[..32]);
+        let ephemeral_pub_key =
+            lib_crypto_box::PublicKey::from(ephemeral_pub_key);
+        let nonce = seal_nonce(&ephemeral_pub_key, &recipient_pub_key);
+        let recipient_box = lib_crypto_box::SalsaBox::new(
+            &ephemeral_pub_key,
+            recipient.as_ref(),
+        );
+        match recipient_box.decrypt((&nonce).into(), &sealed[32..]) {
+            Ok(data) => Ok(Some(data.into())),
             Err(_) => Ok(None),
         }
     })
     .await
 }
 
+/// Seal `data` with the given ephemeral private key.
+fn seal(
+    ephemeral: &lib_crypto_box::SecretKey,
+    recipient: &x25519::X25519PubKey,
+    data: &[u8],
+) -> crate::error::LairResult<CryptoBoxSealedData> {
+    use lib_crypto_box::aead::Aead;
+    let ephemeral_pub_key = ephemeral.public_key();
+    let nonce = seal_nonce(&ephemeral_pub_key, recipient);
+    let sender_box =
+        lib_crypto_box::SalsaBox::new(recipient.as_ref(), ephemeral);
+    let encrypted_data = sender_box.encrypt((&nonce).into(), data)?;
+    let mut sealed_data =
+        Vec::with_capacity(SEALED_BOX_OVERHEAD_BYTES + data.len());
+    sealed_data.extend_from_slice(ephemeral_pub_key.as_bytes());
+    sealed_data.extend_from_slice(&encrypted_data);
+    Ok(sealed_data.into())
+}
+
+/// The sealed box nonce is derived, rather than random:
+/// blake2b-192 of the ephemeral pub key then the recipient pub key.
+fn seal_nonce(
+    ephemeral_pub_key: &lib_crypto_box::PublicKey,
+    recipient: &x25519::X25519PubKey,
+) -> [u8; NONCE_BYTES] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(NONCE_BYTES)
+        .to_state()
+        .update(ephemeral_pub_key.as_bytes())
+        .update(AsRef::<[u8]>::as_ref(recipient))
+        .finalize();
+    let mut nonce = [0; NONCE_BYTES];
+    nonce.copy_from_slice(hash.as_bytes());
+    nonce
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,9 +786,7 @@ mod tests {
                     .await
                     .unwrap();
 
-            let data = CryptoBoxData {
-                data: Arc::new(input.to_vec()),
-            };
+            let data = CryptoBoxData::from(input.to_vec());
 
             // from alice to bob.
             let encrypted_data = super::crypto_box(
@@ -303,4 +813,453 @@ mod tests {
             assert_eq!(&decrypted_data, &Some(data));
         }
     }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// The libsodium test keypair, sealed boxes below came from
+    /// libsodium 1.0.18.
+    fn sodium_recipient() -> (x25519::X25519PrivKey, x25519::X25519PubKey) {
+        let mut priv_key = [0; 32];
+        priv_key.copy_from_slice(&unhex(
+            "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20",
+        ));
+        let priv_key = x25519::X25519PrivKey::from(priv_key);
+        let pub_key = priv_key.public_key().into();
+        (priv_key, pub_key)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_libsodium_sealed_boxes() {
+        let (priv_key, pub_key) = sodium_recipient();
+        assert_eq!(
+            unhex(
+                "07a37cbc142093c8b755dc1b10e86cb426374ad16aa853ed0bdfc0b2b86d1c7c"
+            ),
+            AsRef::<[u8]>::as_ref(&pub_key)
+        );
+
+        // sealed with a fixed ephemeral key, lair seals byte for byte
+        // the same (and sodium's crypto_box_seal_open opens it)
+        let mut ephemeral = [0; 32];
+        ephemeral.copy_from_slice(&unhex(
+            "4142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f60",
+        ));
+        let ephemeral = lib_crypto_box::SecretKey::from(ephemeral);
+        let message = b"lair sealed box test vector".to_vec();
+        let sealed = seal(&ephemeral, &pub_key, &message).unwrap();
+        assert_eq!(
+            unhex(
+                "64b101b1d0be5a8704bd078f9895001fc03e8e9f9522f188dd128d9846d48466\
+                e601649f90b399abe36e5fe87a49104a93466125374e4a5d00068691c238293e\
+                59c744ff2720379b70f824"
+            ),
+            *sealed.sealed_data
+        );
+
+        // sealed by sodium's crypto_box_seal, random ephemeral keys
+        for (sealed, expect) in [
+            (
+                "83bb2833a32499d10c7f269e04fa5f4d19ce084d94f0f766d299ea467a30cd68\
+                df87006eff53c38aa707e641732540ae9b52b1cfaf206f695a0ed439252339f5\
+                c0f327b4bdb2767f22cd64",
+                message.clone(),
+            ),
+            (
+                "cf84a4e90f5c9a3285c9f0cde7b2e9a8985e7b0da376046b939c0d7081afaa1d\
+                f52a4f83a7465ce04928f0db3cec2e7a",
+                vec![],
+            ),
+        ] {
+            let opened = crypto_box_seal_open(
+                priv_key.clone(),
+                pub_key.clone(),
+                Arc::new(unhex(sealed).into()),
+            )
+            .await
+            .unwrap();
+            assert_eq!(Some(expect.into()), opened);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_seal_and_open() {
+        let bob = crate::internal::x25519::x25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let carol = crate::internal::x25519::x25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+
+        for input in [vec![], vec![0], vec![0x80; BLOCK_PADDING_SIZE + 1]] {
+            let data = CryptoBoxData::from(input.clone());
+            let sealed =
+                crypto_box_seal(bob.pub_key.clone(), Arc::new(data.clone()))
+                    .await
+                    .unwrap();
+            assert_eq!(SEALED_BOX_OVERHEAD_BYTES + input.len(), sealed.len());
+
+            // every seal uses a fresh ephemeral key
+            let sealed2 =
+                crypto_box_seal(bob.pub_key.clone(), Arc::new(data.clone()))
+                    .await
+                    .unwrap();
+            assert_ne!(sealed, sealed2);
+
+            let sealed = Arc::new(sealed);
+            assert_eq!(
+                Some(data),
+                crypto_box_seal_open(
+                    bob.priv_key.clone(),
+                    bob.pub_key.clone(),
+                    sealed.clone(),
+                )
+                .await
+                .unwrap()
+            );
+
+            // only the recipient can open it
+            assert_eq!(
+                None,
+                crypto_box_seal_open(
+                    carol.priv_key.clone(),
+                    carol.pub_key.clone(),
+                    sealed.clone(),
+                )
+                .await
+                .unwrap()
+            );
+
+            // tampering is detected
+            let mut tampered = sealed.sealed_data.to_vec();
+            *tampered.last_mut().unwrap() ^= 1;
+            assert_eq!(
+                None,
+                crypto_box_seal_open(
+                    bob.priv_key.clone(),
+                    bob.pub_key.clone(),
+                    Arc::new(tampered.into()),
+                )
+                .await
+                .unwrap()
+            );
+        }
+
+        // too short to be a sealed box
+        assert_eq!(
+            None,
+            crypto_box_seal_open(
+                bob.priv_key,
+                bob.pub_key,
+                Arc::new(vec![0; SEALED_BOX_OVERHEAD_BYTES - 1].into()),
+            )
+            .await
+            .unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_caches_keyed_boxes() {
+        let alice = crate::internal::x25519::x25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let bob = crate::internal::x25519::x25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let carol = crate::internal::x25519::x25519_keypair_new_from_entropy()
+            .await
+            .unwrap();
+        let data = Arc::new(CryptoBoxData::from(vec![0x42; 42]));
+        let cache = CryptoBoxCache::new(2);
+
+        // cached boxes are the same as uncached boxes
+        for _ in 0..2 {
+            let encrypted_data = cache
+                .crypto_box(
+                    1.into(),
+                    alice.priv_key.clone(),
+                    bob.pub_key.clone(),
+                    data.clone(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                Some((*data).clone()),
+                crypto_box_open(
+                    bob.priv_key.clone(),
+                    alice.pub_key.clone(),
+                    Arc::new(encrypted_data),
+                )
+                .await
+                .unwrap()
+            );
+            let encrypted_data = crypto_box(
+                bob.priv_key.clone(),
+                alice.pub_key.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                Some((*data).clone()),
+                cache
+                    .crypto_box_open(
+                        1.into(),
+                        alice.priv_key.clone(),
+                        bob.pub_key.clone(),
+                        Arc::new(encrypted_data),
+                    )
+                    .await
+                    .unwrap()
+            );
+        }
+
+        let cached = |keystore_index: u32, pub_key: &x25519::X25519PubKey| {
+            let key = (keystore_index.into(), pub_key.to_bytes());
+            let cache = cache.clone();
+            async move {
+                cache.0.lock().await.salsa_boxes.entries.contains_key(&key)
+            }
+        };
+        assert!(cached(1, &bob.pub_key).await);
+
+        // the least recently used box is evicted
+        cache
+            .crypto_box(
+                1.into(),
+                alice.priv_key.clone(),
+                carol.pub_key.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap();
+        cache
+            .crypto_box(
+                1.into(),
+                alice.priv_key.clone(),
+                bob.pub_key.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap();
+        cache
+            .crypto_box(
+                2.into(),
+                carol.priv_key.clone(),
+                bob.pub_key.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(cached(1, &bob.pub_key).await);
+        assert!(!cached(1, &carol.pub_key).await);
+        assert!(cached(2, &bob.pub_key).await);
+
+        // boxes with aad are cached apart, and invalidated alongside
+        let aad = Arc::new(b"aad".to_vec());
+        let encrypted_data = cache
+            .crypto_box_with_aad(
+                1.into(),
+                alice.priv_key.clone(),
+                bob.pub_key.clone(),
+                aad.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            Some((*data).clone()),
+            crypto_box_open_with_aad(
+                bob.priv_key.clone(),
+                alice.pub_key.clone(),
+                aad,
+                Arc::new(encrypted_data),
+            )
+            .await
+            .unwrap()
+        );
+        assert_eq!(1, cache.0.lock().await.aead_boxes.entries.len());
+
+        cache.invalidate(1.into()).await;
+        assert!(cache.0.lock().await.aead_boxes.entries.is_empty());
+        assert!(!cached(1, &bob.pub_key).await);
+        assert!(cached(2, &bob.pub_key).await);
+        assert_eq!(1, cache.0.lock().await.salsa_boxes.by_tick.len());
+
+        // clearing drops every box
+        cache.clear().await;
+        assert!(!cached(2, &bob.pub_key).await);
+        assert!(cache.0.lock().await.salsa_boxes.by_tick.is_empty());
+
+        // a zero size cache caches nothing, but still boxes
+        let cache = CryptoBoxCache::new(0);
+        let encrypted_data = cache
+            .crypto_box(
+                1.into(),
+                alice.priv_key.clone(),
+                bob.pub_key.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            Some((*data).clone()),
+            crypto_box_open(
+                bob.priv_key,
+                alice.pub_key,
+                Arc::new(encrypted_data)
+            )
+            .await
+            .unwrap()
+        );
+        assert!(cache.0.lock().await.salsa_boxes.entries.is_empty());
+    }
+
+    /// Boxes made with libsodium (`crypto_box_easy`, and
+    /// `crypto_aead_xchacha20poly1305_ietf_encrypt` keyed by
+    /// `crypto_box_curve25519xchacha20poly1305_beforenm`) of the padded
+    /// data. Boxes made by earlier versions of lair must keep opening.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_crypto_box_fixtures() {
+        let alice_priv: x25519::X25519PrivKey = [0x11; 32].into();
+        let alice_pub: x25519::X25519PubKey = [
+            0x7b, 0x4e, 0x90, 0x9b, 0xbe, 0x7f, 0xfe, 0x44, 0xc4, 0x65, 0xa2,
+            0x20, 0x03, 0x7d, 0x60, 0x8e, 0xe3, 0x58, 0x97, 0xd3, 0x1e, 0xf9,
+            0x72, 0xf0, 0x7f, 0x74, 0x89, 0x2c, 0xb0, 0xf7, 0x3f, 0x13,
+        ]
+        .into();
+        let bob_priv: x25519::X25519PrivKey = [0x22; 32].into();
+        let bob_pub: x25519::X25519PubKey = [
+            0x0f, 0xaa, 0x68, 0x4e, 0xd2, 0x88, 0x67, 0xb9, 0x7f, 0x4a, 0x6a,
+            0x2d, 0xee, 0x5d, 0xf8, 0xce, 0x97, 0x4e, 0x76, 0xb7, 0x01, 0x8e,
+            0x3f, 0x22, 0xa1, 0xc4, 0xcf, 0x26, 0x78, 0x57, 0x0f, 0x20,
+        ]
+        .into();
+        let nonce: CryptoBoxNonce = [0x33; NONCE_BYTES].into();
+        let data: CryptoBoxData = b"lair crypto_box fixture".to_vec().into();
+        let aad = b"lair aad fixture".to_vec();
+
+        let legacy = CryptoBoxEncryptedData {
+            nonce: nonce.clone(),
+            encrypted_data: Arc::new(unhex(
+                "4feedae1e44a9c353c120a4c9b31defa5a7da7eb9e8a9f25d0c6747cfb9bbe65\
+                 9b7e49ae8474b42568c18ed79f13fc57",
+            )),
+        };
+        let with_aad = CryptoBoxEncryptedData {
+            nonce: nonce.clone(),
+            encrypted_data: Arc::new(unhex(
+                "23a521d23e1207599eef31fbd3805a862e50735e8cf5d5c0d29707b8e56ac8cd\
+                 781454107a646e217ba3098d3bfec3a5",
+            )),
+        };
+
+        let sender_box = lib_crypto_box::SalsaBox::new(
+            bob_pub.as_ref(),
+            alice_priv.as_ref(),
+        );
+        assert_eq!(
+            legacy,
+            box_data(&sender_box, nonce.clone(), &data).unwrap()
+        );
+        let sender_aead = aead_box(&alice_priv, &bob_pub).unwrap();
+        assert_eq!(
+            with_aad,
+            box_data_with_aad(&sender_aead, nonce, &aad, &data).unwrap()
+        );
+
+        assert_eq!(
+            Some(data.clone()),
+            crypto_box_open(
+                bob_priv.clone(),
+                alice_pub.clone(),
+                Arc::new(legacy.clone())
+            )
+            .await
+            .unwrap()
+        );
+        assert_eq!(
+            Some(data),
+            crypto_box_open_with_aad(
+                bob_priv.clone(),
+                alice_pub.clone(),
+                Arc::new(aad),
+                Arc::new(with_aad.clone()),
+            )
+            .await
+            .unwrap()
+        );
+
+        // the formats don't open as each other
+        assert_eq!(
+            None,
+            crypto_box_open(
+                bob_priv.clone(),
+                alice_pub.clone(),
+                Arc::new(with_aad)
+            )
+            .await
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            crypto_box_open_with_aad(
+                bob_priv,
+                alice_pub,
+                Arc::new(vec![]),
+                Arc::new(legacy),
+            )
+            .await
+            .unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_only_opens_with_the_same_aad() {
+        let alice = x25519::x25519_keypair_new_from_entropy().await.unwrap();
+        let bob = x25519::x25519_keypair_new_from_entropy().await.unwrap();
+        let data: Arc<CryptoBoxData> = Arc::new(vec![42; 40].into());
+        let aad = Arc::new(b"message type".to_vec());
+
+        let encrypted_data = Arc::new(
+            crypto_box_with_aad(
+                alice.priv_key.clone(),
+                bob.pub_key.clone(),
+                aad.clone(),
+                data.clone(),
+            )
+            .await
+            .unwrap(),
+        );
+        assert_eq!(
+            BLOCK_PADDING_SIZE * 2 + 16,
+            encrypted_data.encrypted_data.len()
+        );
+
+        let open = |aad: Arc<Vec<u8>>| {
+            crypto_box_open_with_aad(
+                bob.priv_key.clone(),
+                alice.pub_key.clone(),
+                aad,
+                encrypted_data.clone(),
+            )
+        };
+        assert_eq!(Some((*data).clone()), open(aad).await.unwrap());
+        assert_eq!(None, open(Arc::new(b"other type".to_vec())).await.unwrap());
+        assert_eq!(None, open(Arc::new(vec![])).await.unwrap());
+
+        // low order pub keys have no shared secret to key the box
+        assert!(crypto_box_with_aad(
+            alice.priv_key,
+            [0; 32].into(),
+            Arc::new(vec![]),
+            data,
+        )
+        .await
+        .is_err());
+    }
 }
@@ -1,4 +1,4 @@
-//! Abstraction over unix domain sockets / windows named pipes
+//! Abstraction over unix domain sockets / windows named pipes / tcp
 
 use crate::internal::util::*;
 use crate::internal::wire::*;
@@ -11,13 +11,24 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 #[cfg(not(windows))]
 mod unix_ipc;
 #[cfg(not(windows))]
+pub use unix_ipc::IpcListener;
+#[cfg(not(windows))]
 use unix_ipc::*;
 
 #[cfg(windows)]
 mod win_ipc;
 #[cfg(windows)]
+pub use win_ipc::IpcListener;
+#[cfg(windows)]
 use win_ipc::*;
 
+mod mem_ipc;
+
+mod tcp_ipc;
+
+mod handshake;
+pub use handshake::*;
+
 mod low_level;
 pub(crate) use low_level::*;
 
@@ -32,6 +43,7 @@ pub type IncomingIpcSender = futures::channel::mpsc::Sender<(
     KillSwitch,
     ghost_actor::GhostSender<IpcWireApi>,
     IpcReceiver,
+    IpcProtocol,
 )>;
 
 /// IncomingIpcReceiver
@@ -39,8 +51,27 @@ pub type IncomingIpcReceiver = futures::channel::mpsc::Receiver<(
     KillSwitch,
     ghost_actor::GhostSender<IpcWireApi>,
     IpcReceiver,
+    IpcProtocol,
 )>;
 
+tokio::task_local! {
+    static REQUEST_TIMEOUT: Option<std::time::Duration>;
+}
+
+/// Run `f`, giving the requests it makes over client connections
+/// `timeout` to be responded to, rather than the request timeout of
+/// their config (see `ConfigBuilder::set_request_timeout()`), e.g. for
+/// large batches. `None` waits forever.
+pub async fn with_timeout<F>(
+    timeout: Option<std::time::Duration>,
+    f: F,
+) -> F::Output
+where
+    F: std::future::Future,
+{
+    REQUEST_TIMEOUT.scope(timeout, f).await
+}
+
 ghost_actor::ghost_chan! {
     /// Ipc wire api for both incoming api requests and outgoing event requests.
     pub chan IpcWireApi<LairError> {
@@ -49,68 +80,272 @@ ghost_actor::ghost_chan! {
     }
 }
 
+/// The live counts of the connections a server accepted.
+#[derive(Default)]
+pub(crate) struct ConnectionCounts {
+    /// open connections
+    pub(crate) connections: std::sync::atomic::AtomicU64,
+    /// requests being handled, over all connections
+    pub(crate) requests_in_flight: std::sync::atomic::AtomicU64,
+}
+
+/// Counts a connection as open, until dropped.
+struct CountedConnection(Arc<ConnectionCounts>);
+
+impl CountedConnection {
+    /// Count a new connection, returning the connections now open.
+    fn new(counts: Arc<ConnectionCounts>) -> (Self, u64) {
+        let open = counts
+            .connections
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+            + 1;
+        (Self(counts), open)
+    }
+}
+
+impl Drop for CountedConnection {
+    fn drop(&mut self) {
+        self.0
+            .connections
+            .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
 /// Spawn/bind a new ipc listener connection awaiting incoming clients.
+/// If `listener` is supplied, serve on it instead of binding
+/// the configured socket path. Configs with a tcp address
+/// are served on it as well (or only, see `Config::get_tcp_only()`).
+/// Clients beyond `Config::get_max_connections()` are turned away busy.
 pub async fn spawn_bind_ipc(
     config: Arc<Config>,
+    listener: Option<IpcListener>,
+) -> LairResult<(KillSwitch, IncomingIpcReceiver)> {
+    spawn_bind_ipc_counted(config, listener, Default::default()).await
+}
+
+/// `spawn_bind_ipc()`, keeping `counts` of its connections.
+pub(crate) async fn spawn_bind_ipc_counted(
+    config: Arc<Config>,
+    listener: Option<IpcListener>,
+    counts: Arc<ConnectionCounts>,
 ) -> LairResult<(KillSwitch, IncomingIpcReceiver)> {
     let kill_switch = KillSwitch::new();
     let (in_send, in_recv) = futures::channel::mpsc::channel(10);
 
-    let srv = IpcServer::bind(config)?;
+    let mut servers = Vec::new();
+    if config.get_tcp_bind().is_some() {
+        servers.push(Listening::Tcp(tcp_ipc::TcpServer::bind(config.clone())?));
+    }
+    match listener {
+        Some(listener) => servers.push(Listening::Socket(
+            IpcServer::from_listener(config.clone(), listener)?,
+        )),
+        None if config.get_tcp_only() => (),
+        None if config.get_in_process_ipc() => servers.push(
+            Listening::InProcess(mem_ipc::MemServer::bind(config.clone())?),
+        ),
+        None => {
+            servers.push(Listening::Socket(IpcServer::bind(config.clone())?))
+        }
+    }
 
-    err_spawn(
-        "srv-bind",
-        srv_main_bind_task(kill_switch.clone(), srv, in_send),
-    );
+    for srv in servers {
+        err_spawn(
+            "srv-bind",
+            srv_main_bind_task(
+                kill_switch.clone(),
+                srv,
+                Incoming {
+                    in_send: in_send.clone(),
+                    config: config.clone(),
+                    counts: counts.clone(),
+                },
+            ),
+        );
+    }
 
     Ok((kill_switch, in_recv))
 }
 
+/// Stop new clients connecting: remove the socket file (close the named
+/// pipe, on windows), or close the in-process channel of an ephemeral config, and close any tcp listener.
+pub fn unbind_ipc(config: &Config) {
+    tcp_ipc::tcp_unbind(config);
+    if config.get_in_process_ipc() {
+        mem_ipc::mem_unbind(config);
+    } else if !config.get_tcp_only() {
+        #[cfg(not(windows))]
+        let _ = std::fs::remove_file(config.get_socket_path());
+        #[cfg(windows)]
+        pipe_unbind(config);
+    }
+}
+
+/// Where a server accepts connections.
+enum Listening {
+    Socket(IpcServer),
+    InProcess(mem_ipc::MemServer),
+    Tcp(tcp_ipc::TcpServer),
+}
+
+/// Where accepted connections go.
+struct Incoming {
+    in_send: IncomingIpcSender,
+    config: Arc<Config>,
+    counts: Arc<ConnectionCounts>,
+}
+
 async fn srv_main_bind_task(
     kill_switch: KillSwitch,
-    mut srv: IpcServer,
-    mut in_send: IncomingIpcSender,
+    mut srv: Listening,
+    incoming: Incoming,
 ) -> LairResult<()> {
-    while let Ok((read_half, write_half)) = kill_switch.mix(srv.accept()).await
-    {
-        let (con_kill_switch, send, recv) = kill_switch
-            .mix(async { spawn_connection_pair(read_half, write_half).await })
-            .await?;
+    loop {
+        match &mut srv {
+            Listening::Socket(srv) => {
+                let (read_half, write_half) =
+                    match kill_switch.mix(srv.accept()).await {
+                        Ok(con) => con,
+                        Err(_) => break,
+                    };
+                spawn_incoming(&kill_switch, read_half, write_half, &incoming);
+            }
+            Listening::InProcess(srv) => {
+                let (read_half, write_half) =
+                    match kill_switch.mix(srv.accept()).await {
+                        Ok(con) => con,
+                        Err(_) => break,
+                    };
+                spawn_incoming(&kill_switch, read_half, write_half, &incoming);
+            }
+            Listening::Tcp(srv) => {
+                let (read_half, write_half) =
+                    match kill_switch.mix(srv.accept()).await {
+                        Ok(con) => con,
+                        Err(_) => break,
+                    };
+                spawn_incoming(&kill_switch, read_half, write_half, &incoming);
+            }
+        }
+    }
+    Ok(())
+}
 
+/// Handshake with a new connection, off the accept loop so slow peers
+/// hold up no one else, then pass it on to the server. Turn it away busy
+/// instead if there are too many connections already.
+fn spawn_incoming<R, W>(
+    kill_switch: &KillSwitch,
+    mut read_half: R,
+    mut write_half: W,
+    incoming: &Incoming,
+) where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let kill_switch = kill_switch.weak();
+    let mut in_send = incoming.in_send.clone();
+    let counts = incoming.counts.clone();
+    let max_connections = incoming.config.get_max_connections() as u64;
+    let max_in_flight = incoming.config.get_max_in_flight_requests();
+    let (counted, open) = CountedConnection::new(counts.clone());
+    err_spawn("srv-incoming", async move {
+        if max_connections > 0 && open > max_connections {
+            warn!(max_connections, "turning away a connection as busy");
+            return turn_away_busy(&mut read_half, &mut write_half).await;
+        }
+        let con = kill_switch
+            .mix(spawn_connection_pair(
+                read_half,
+                write_half,
+                None,
+                max_in_flight,
+                Some(counts),
+            ))
+            .await?;
+        // counted until the connection is closed
+        con.0
+            .register_kill_callback(Box::new(move || {
+                Box::pin(async move { drop(counted) })
+            }))
+            .await;
         kill_switch
             .mix(async {
                 trace!("notify new connection");
-                in_send
-                    .send((con_kill_switch, send, recv))
-                    .await
-                    .map_err(LairError::other)
+                in_send.send(con).await.map_err(LairError::other)
             })
-            .await?;
-    }
-    Ok(())
+            .await
+    });
 }
 
-/// Establish an outgoing client ipc connection to a lair server.
+/// Establish an outgoing client ipc connection to a lair server,
+/// over tcp if the config has a tcp address. Fails with
+/// `LairError::ProtocolVersionMismatch` if the server speaks no
+/// protocol version in common. Requests fail with `LairError::Timeout`
+/// unless responded to within the request timeout of the config.
 pub async fn spawn_ipc_connection(
     config: Arc<Config>,
 ) -> LairResult<(
     KillSwitch,
     ghost_actor::GhostSender<IpcWireApi>,
     IpcReceiver,
+    IpcProtocol,
 )> {
+    let timeout = config.get_request_timeout();
+    let max_in_flight = config.get_max_in_flight_requests();
+
+    if config.get_in_process_ipc() {
+        let (read_half, write_half) = mem_ipc::mem_connect(config).await?;
+        return spawn_connection_pair(
+            read_half,
+            write_half,
+            timeout,
+            max_in_flight,
+            None,
+        )
+        .await;
+    }
+
+    if config.get_tcp_bind().is_some() {
+        let (read_half, write_half) = tcp_ipc::tcp_connect(config).await?;
+        return spawn_connection_pair(
+            read_half,
+            write_half,
+            timeout,
+            max_in_flight,
+            None,
+        )
+        .await;
+    }
+
     let (read_half, write_half) = ipc_connect(config).await?;
 
-    spawn_connection_pair(read_half, write_half).await
+    spawn_connection_pair(read_half, write_half, timeout, max_in_flight, None)
+        .await
 }
 
-async fn spawn_connection_pair(
-    read_half: IpcRead,
-    write_half: IpcWrite,
+/// `request_timeout` bounds the wait for responses to our requests,
+/// `max_in_flight` the messages of the peer handled at once, which are
+/// added to the `counts` of a server.
+async fn spawn_connection_pair<R, W>(
+    mut read_half: R,
+    mut write_half: W,
+    request_timeout: Option<std::time::Duration>,
+    max_in_flight: usize,
+    counts: Option<Arc<ConnectionCounts>>,
 ) -> LairResult<(
     KillSwitch,
     ghost_actor::GhostSender<IpcWireApi>,
     IpcReceiver,
-)> {
+    IpcProtocol,
+)>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let protocol = handshake(&mut read_half, &mut write_half).await?;
+    trace!(?protocol, "negotiated protocol");
+
     let kill_switch = KillSwitch::new();
 
     let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
@@ -134,7 +369,12 @@ async fn spawn_connection_pair(
         }))
         .await;
 
-    let reader = spawn_low_level_read_half(kill_switch.clone(), read_half)?;
+    let reader = spawn_low_level_read_half(
+        kill_switch.clone(),
+        read_half,
+        max_in_flight,
+        counts,
+    )?;
     builder.channel_factory().attach_receiver(reader).await?;
 
     let writer = spawn_low_level_write_half(kill_switch.clone(), write_half)?;
@@ -144,9 +384,11 @@ async fn spawn_connection_pair(
         pending: HashMap::new(),
         writer,
         evt_send,
+        request_timeout,
+        typed_errors: protocol.has_feature(feature::TYPED_ERRORS),
     }));
 
-    Ok((kill_switch, sender, evt_recv))
+    Ok((kill_switch, sender, evt_recv, protocol))
 }
 
 struct Internal {
@@ -154,6 +396,9 @@ struct Internal {
     pending: HashMap<u64, tokio::sync::oneshot::Sender<LairWire>>,
     writer: futures::channel::mpsc::Sender<LowLevelWireApi>,
     evt_send: futures::channel::mpsc::Sender<IpcWireApi>,
+    request_timeout: Option<std::time::Duration>,
+    /// respond errors as `TypedErrorResponse`
+    typed_errors: bool,
 }
 
 impl ghost_actor::GhostControlHandler for Internal {}
@@ -167,24 +412,38 @@ impl LowLevelWireApiHandler for Internal {
     ) -> LowLevelWireApiHandlerResult<()> {
         trace!(?msg, "RECV MSG");
         if msg.is_req() {
+            let msg_id = msg.get_msg_id();
             let fut = self.kill_switch.mix_static(self.evt_send.request(msg));
             let writer_clone = self.writer.clone();
             let weak_kill_switch = self.kill_switch.weak();
+            let typed_errors = self.typed_errors;
             Ok(async move {
-                if let Ok(res) = fut.await {
-                    let _ = weak_kill_switch
-                        .mix(writer_clone.low_level_send(res))
-                        .await;
-                }
-                // TODO - send errors back so we don't have dangling reqs!!
+                // send errors back so we don't have dangling reqs
+                let res = match fut.await {
+                    Ok(res) => res,
+                    Err(err) if typed_errors => {
+                        typed_error_response(msg_id, &err)
+                    }
+                    Err(err) => LairWire::ErrorResponse {
+                        msg_id,
+                        message: error_message(&err),
+                    },
+                };
+                let _ = weak_kill_switch
+                    .mix(writer_clone.low_level_send(res))
+                    .await;
                 Ok(())
             }
             .boxed()
             .into())
         } else {
-            if let Some(send) = self.pending.remove(&msg.get_msg_id()) {
-                trace!("outgoing response received");
-                let _ = send.send(msg);
+            match self.pending.remove(&msg.get_msg_id()) {
+                Some(send) => {
+                    trace!("outgoing response received");
+                    let _ = send.send(msg);
+                }
+                // the request timed out
+                None => trace!("discarding response to no pending request"),
             }
             Ok(async move { Ok(()) }.boxed().into())
         }
@@ -198,19 +457,47 @@ impl IpcWireApiHandler for Internal {
         &mut self,
         msg: LairWire,
     ) -> IpcWireApiHandlerResult<LairWire> {
+        // forget the requests that timed out
+        self.pending.retain(|_, send| !send.is_closed());
         let (send, recv) = tokio::sync::oneshot::channel();
         self.pending.insert(msg.get_msg_id(), send);
         trace!("con write {:?}", msg);
         let fut = self.kill_switch.mix_static(self.writer.low_level_send(msg));
         let weak_kill_switch = self.kill_switch.weak();
+        let request_timeout = self.request_timeout;
         Ok(async move {
+            // the future is awaited by the caller, in its task
+            let request_timeout =
+                REQUEST_TIMEOUT.try_with(|t| *t).unwrap_or(request_timeout);
             fut.await?;
             weak_kill_switch
                 .mix(async move {
                     trace!("await incoming request...");
-                    let res = recv.await.map_err(LairError::other);
+                    let res = match request_timeout {
+                        Some(timeout) => {
+                            match tokio::time::timeout(timeout, recv).await {
+                                Ok(res) => res.map_err(LairError::other),
+                                Err(_) => Err(LairError::Timeout(timeout)),
+                            }
+                        }
+                        None => recv.await.map_err(LairError::other),
+                    };
                     trace!(?res, "respond to incoming request");
-                    res
+                    match res? {
+                        LairWire::ErrorResponse { message, .. } => {
+                            Err(error_from_message(message))
+                        }
+                        LairWire::TypedErrorResponse {
+                            message,
+                            code,
+                            value,
+                            detail,
+                            ..
+                        } => {
+                            Err(error_from_typed(message, &code, value, detail))
+                        }
+                        res => Ok(res),
+                    }
                 })
                 .await
         }
@@ -219,6 +506,192 @@ impl IpcWireApiHandler for Internal {
     }
 }
 
+/// `s` cut down to at most `max` bytes.
+fn truncate(mut s: String, max: usize) -> String {
+    if s.len() > max {
+        let mut end = max;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+    s
+}
+
+/// The message for an ErrorResponse, which is limited to 128 bytes.
+fn error_message(err: &LairError) -> String {
+    truncate(err.to_string(), 128)
+}
+
+/// The TypedErrorResponse for an error: besides its message and code,
+/// the value and detail its variant is rebuilt from (see
+/// `error_from_typed()`), whose meaning depends on the code.
+fn typed_error_response(msg_id: u64, err: &LairError) -> LairWire {
+    let (value, detail) = match err {
+        LairError::ProcessAlreadyExists(pid) => {
+            (pid.map(u64::from).unwrap_or(u64::MAX), String::new())
+        }
+        LairError::ProtocolVersionMismatch { ours, theirs } => {
+            ((u64::from(*ours) << 32) | u64::from(*theirs), String::new())
+        }
+        LairError::Timeout(timeout) => {
+            (timeout.as_millis() as u64, String::new())
+        }
+        LairError::RateLimited { retry_after } => {
+            (retry_after.as_millis() as u64, String::new())
+        }
+        LairError::StoreVersionUnsupported(n)
+        | LairError::StoreMigrationRequired(n)
+        | LairError::EntryNotFound(n)
+        | LairError::EntryDeleted(n)
+        | LairError::EntryCorrupt(n)
+        | LairError::DuplicatePubKey { existing_index: n } => {
+            (u64::from(*n), String::new())
+        }
+        LairError::SignEd25519SeedLength(len)
+        | LairError::X25519PrivKeyImportLength(len)
+        | LairError::SubkeyLength(len) => (*len as u64, String::new()),
+        LairError::WrongLength {
+            what,
+            expected,
+            got,
+        } => (((*expected as u64) << 32) | *got as u64, what.clone()),
+        LairError::PermissionDenied { request, scope } => {
+            (*scope as u64, request.clone())
+        }
+        LairError::EntryTagInUse(s)
+        | LairError::EntryTagNotFound(s)
+        | LairError::TlsCertName(s)
+        | LairError::TlsCertDer(s)
+        | LairError::StoreCorrupt(s)
+        | LairError::Protocol(s)
+        | LairError::Aead(s)
+        | LairError::BlockPad(s)
+        | LairError::BlockUnpad(s) => (0, s.clone()),
+        LairError::TlsCertDigestPrefixAmbiguous(candidates) => {
+            (0, candidates.join("\n"))
+        }
+        LairError::Io(err) => (0, err.to_string()),
+        _ => (0, String::new()),
+    };
+    LairWire::TypedErrorResponse {
+        msg_id,
+        message: error_message(err),
+        code: err.code().to_string(),
+        value,
+        detail: truncate(detail, ERROR_DETAIL_MAX_BYTES),
+    }
+}
+
+/// The error of a TypedErrorResponse, the variant the peer produced.
+/// Errors that only make sense on their side (and codes we don't know)
+/// are rebuilt as "Other" errors with their message.
+fn error_from_typed(
+    message: String,
+    code: &str,
+    value: u64,
+    detail: String,
+) -> LairError {
+    use std::convert::TryFrom;
+    let n = value as u32;
+    let len = value as usize;
+    let ms = std::time::Duration::from_millis(value);
+    match code {
+        "process_already_exists" => {
+            LairError::ProcessAlreadyExists(u32::try_from(value).ok())
+        }
+        "store_already_initialized" => LairError::StoreAlreadyInitialized,
+        "protocol_version_mismatch" => LairError::ProtocolVersionMismatch {
+            ours: (value >> 32) as u32,
+            theirs: n,
+        },
+        "disconnected" => LairError::Disconnected,
+        "busy" => LairError::Busy,
+        "timeout" => LairError::Timeout(ms),
+        "store_version_unsupported" => LairError::StoreVersionUnsupported(n),
+        "store_migration_required" => LairError::StoreMigrationRequired(n),
+        "store_locked" => LairError::StoreLocked,
+        "wrong_passphrase" => LairError::WrongPassphrase,
+        "read_only" => LairError::ReadOnly,
+        "entry_not_found" => LairError::EntryNotFound(n),
+        "entry_deleted" => LairError::EntryDeleted(n),
+        "entry_corrupt" => LairError::EntryCorrupt(n),
+        "wrong_entry_type" => LairError::WrongEntryType,
+        "entry_tag_in_use" => LairError::EntryTagInUse(detail),
+        "entry_tag_not_found" => LairError::EntryTagNotFound(detail),
+        "duplicate_pub_key" => LairError::DuplicatePubKey { existing_index: n },
+        "pub_key_not_found" => LairError::PubKeyNotFound,
+        "wrong_length" => LairError::WrongLength {
+            what: detail,
+            expected: (value >> 32) as usize,
+            got: n as usize,
+        },
+        "sign_ed25519_seed_length" => LairError::SignEd25519SeedLength(len),
+        "x25519_priv_key_import_length" => {
+            LairError::X25519PrivKeyImportLength(len)
+        }
+        "tls_cert_name" => LairError::TlsCertName(detail),
+        "tls_cert_der" => LairError::TlsCertDer(detail),
+        "tls_cert_key_mismatch" => LairError::TlsCertKeyMismatch,
+        "tls_cert_not_ca" => LairError::TlsCertNotCa,
+        "not_exportable" => LairError::NotExportable,
+        "permission_denied" => match ConnectionScope::from_u32(n) {
+            Some(scope) => LairError::PermissionDenied {
+                request: detail,
+                scope,
+            },
+            None => message.into(),
+        },
+        "rate_limited" => LairError::RateLimited { retry_after: ms },
+        "signing_denied" => LairError::SigningDenied,
+        "signing_approval_timeout" => LairError::SigningApprovalTimeout,
+        "no_signing_approver" => LairError::NoSigningApprover,
+        "tls_cert_digest_prefix_ambiguous" => {
+            LairError::TlsCertDigestPrefixAmbiguous(
+                detail.split('\n').map(String::from).collect(),
+            )
+        }
+        "subkey_length" => LairError::SubkeyLength(len),
+        "store_corrupt" => LairError::StoreCorrupt(detail),
+        "io" => LairError::Io(std::io::Error::other(detail)),
+        "protocol" => LairError::Protocol(detail),
+        "aead" => LairError::Aead(detail),
+        "block_pad" => LairError::BlockPad(detail),
+        "block_unpad" => LairError::BlockUnpad(detail),
+        "crypto_box_nonce_length" => LairError::CryptoBoxNonceLength,
+        "x25519_pub_key_length" => LairError::X25519PubKeyLength,
+        "x25519_priv_key_length" => LairError::X25519PrivKeyLength,
+        // "ghost_error", "ipc_connect_error", "other"
+        _ => message.into(),
+    }
+}
+
+/// The error of an ErrorResponse message, from peers without
+/// `feature::TYPED_ERRORS`, as typed as it can be told.
+fn error_from_message(message: String) -> LairError {
+    for err in [
+        LairError::StoreLocked,
+        LairError::WrongPassphrase,
+        LairError::SigningDenied,
+        LairError::SigningApprovalTimeout,
+        LairError::NoSigningApprover,
+    ] {
+        if message == err.to_string() {
+            return err;
+        }
+    }
+    let retry_after_ms = message
+        .strip_prefix("Rate limited, retry after ")
+        .and_then(|s| s.strip_suffix("ms"))
+        .and_then(|ms| ms.parse().ok());
+    if let Some(ms) = retry_after_ms {
+        return LairError::RateLimited {
+            retry_after: std::time::Duration::from_millis(ms),
+        };
+    }
+    message.into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,11 +715,12 @@ mod tests {
 
         let config = Config::builder().set_root_path(tmpdir.path()).build();
 
-        let (srv_kill, mut srv_recv) = spawn_bind_ipc(config.clone()).await?;
+        let (srv_kill, mut srv_recv) =
+            spawn_bind_ipc(config.clone(), None).await?;
 
         let srv_task_kill = srv_kill.clone();
         err_spawn("test-outer", async move {
-            while let Some((con_kill, con_send, mut con_recv)) =
+            while let Some((con_kill, con_send, mut con_recv, _)) =
                 srv_recv.next().await
             {
                 err_spawn("test-inner", async move {
@@ -254,6 +728,9 @@ mod tests {
                     let r = con_send
                         .request(LairWire::ToCliRequestUnlockPassphrase {
                             msg_id: 0,
+                            last_passphrase_failed: false,
+                            attempt: 1,
+                            max_attempts: 1,
                         })
                         .await
                         .unwrap();
@@ -263,7 +740,7 @@ mod tests {
                             passphrase,
                             ..
                         } => {
-                            assert_eq!("test-passphrase", &passphrase);
+                            assert_eq!(b"test-passphrase", &*passphrase);
                         }
                         _ => panic!("unexpected: {:?}", r),
                     }
@@ -299,18 +776,23 @@ mod tests {
             LairResult::<()>::Ok(())
         });
 
-        let (cli_kill, cli_send, mut cli_recv) =
+        let (cli_kill, cli_send, mut cli_recv, protocol) =
             spawn_ipc_connection(config).await?;
+        assert_eq!(PROTOCOL_VERSION, protocol.version);
 
         match cli_recv.next().await.unwrap() {
             IpcWireApi::Request { respond, msg, .. } => {
                 println!("GOT: {:?}", msg);
                 match msg {
-                    LairWire::ToCliRequestUnlockPassphrase { msg_id } => {
+                    LairWire::ToCliRequestUnlockPassphrase {
+                        msg_id, ..
+                    } => {
                         respond.respond(Ok(async move {
                             Ok(LairWire::ToLairRequestUnlockPassphraseResponse {
                                 msg_id,
-                                passphrase: "test-passphrase".to_string(),
+                                passphrase: "test-passphrase"
+                                    .to_string()
+                                    .into(),
                             })
                         }
                         .boxed()
@@ -347,4 +829,229 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn typed_errors_survive_the_wire() {
+        let msg = |err: LairError| error_from_message(error_message(&err));
+        assert!(matches!(
+            msg(LairError::StoreLocked),
+            LairError::StoreLocked
+        ));
+        assert!(matches!(
+            msg(LairError::WrongPassphrase),
+            LairError::WrongPassphrase
+        ));
+        assert!(matches!(
+            msg(LairError::SigningDenied),
+            LairError::SigningDenied
+        ));
+        assert!(matches!(
+            msg(LairError::SigningApprovalTimeout),
+            LairError::SigningApprovalTimeout
+        ));
+        assert!(matches!(
+            msg(LairError::NoSigningApprover),
+            LairError::NoSigningApprover
+        ));
+        let retry_after = std::time::Duration::from_millis(250);
+        match msg(LairError::RateLimited { retry_after }) {
+            LairError::RateLimited { retry_after: r } => {
+                assert_eq!(retry_after, r)
+            }
+            e => panic!("unexpected: {:?}", e),
+        }
+        assert!(matches!(msg("nope".into()), LairError::Other(_)));
+    }
+
+    #[test]
+    fn typed_errors_are_rebuilt_as_the_variant_produced() {
+        let errors = vec![
+            LairError::ProcessAlreadyExists(Some(42)),
+            LairError::ProcessAlreadyExists(None),
+            LairError::ProtocolVersionMismatch { ours: 1, theirs: 2 },
+            LairError::Timeout(std::time::Duration::from_millis(1500)),
+            LairError::StoreLocked,
+            LairError::EntryNotFound(42),
+            LairError::WrongEntryType,
+            LairError::EntryTagNotFound("test-tag".to_string()),
+            LairError::PubKeyNotFound,
+            LairError::wrong_length("secp256k1 priv key", 32, 31),
+            LairError::PermissionDenied {
+                request: "ToLairSignEd25519SignByIndex".to_string(),
+                scope: ConnectionScope::ReadOnly,
+            },
+            LairError::RateLimited {
+                retry_after: std::time::Duration::from_millis(250),
+            },
+            LairError::TlsCertDigestPrefixAmbiguous(vec![
+                "1:abcd".to_string(),
+                "2:abce".to_string(),
+            ]),
+            LairError::StoreCorrupt("compacted entries are invalid".into()),
+            LairError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no store file",
+            )),
+            LairError::Protocol("unexpected: Busy".to_string()),
+            "nope".into(),
+        ];
+        for err in errors {
+            let wire = typed_error_response(42, &err).encode().unwrap();
+            let rebuilt = match LairWire::decode(&wire).unwrap() {
+                LairWire::TypedErrorResponse {
+                    message,
+                    code,
+                    value,
+                    detail,
+                    ..
+                } => error_from_typed(message, &code, value, detail),
+                o => panic!("unexpected: {:?}", o),
+            };
+            assert_eq!(err.code(), rebuilt.code());
+            assert_eq!(err.to_string(), rebuilt.to_string());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn requests_time_out() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_request_timeout(std::time::Duration::from_millis(200))
+            .build();
+
+        // responds to odd requests slowly, with their count
+        let (srv_kill, mut srv_recv) =
+            spawn_bind_ipc(config.clone(), None).await?;
+        err_spawn("test-srv", async move {
+            let (_con_kill, _con_send, mut con_recv, _) =
+                srv_recv.next().await.unwrap();
+            let mut count = 0;
+            while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                con_recv.next().await
+            {
+                count += 1;
+                let msg_id = msg.get_msg_id();
+                respond.respond(Ok(async move {
+                    if count % 2 == 1 {
+                        tokio::time::sleep(std::time::Duration::from_millis(
+                            500,
+                        ))
+                        .await;
+                    }
+                    Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                        msg_id,
+                        last_keystore_index: count.into(),
+                    })
+                }
+                .boxed()
+                .into()));
+            }
+            Ok(())
+        });
+
+        let (_cli_kill, cli_send, _cli_recv, _) =
+            spawn_ipc_connection(config).await?;
+        let request = || {
+            cli_send.request(LairWire::ToLairLairGetLastEntryIndex {
+                msg_id: next_msg_id(),
+            })
+        };
+        let index = |res: LairResult<LairWire>| match res {
+            Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                last_keystore_index,
+                ..
+            }) => last_keystore_index.0,
+            o => panic!("unexpected: {:?}", o),
+        };
+
+        match request().await {
+            Err(LairError::Timeout(timeout)) => {
+                assert_eq!(std::time::Duration::from_millis(200), timeout)
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+        // the late response is discarded
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        assert_eq!(2, index(request().await));
+
+        // slow requests can be given longer
+        let res =
+            with_timeout(Some(std::time::Duration::from_secs(5)), request())
+                .await;
+        assert_eq!(3, index(res));
+
+        drop(srv_kill);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn requests_are_pipelined() -> LairResult<()> {
+        init_tracing();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::builder().set_root_path(tmpdir.path()).build();
+
+        // responds to requests with their count after 200ms,
+        // to the first one after a second
+        let (srv_kill, mut srv_recv) =
+            spawn_bind_ipc(config.clone(), None).await?;
+        err_spawn("test-srv", async move {
+            let (_con_kill, _con_send, mut con_recv, _) =
+                srv_recv.next().await.unwrap();
+            let mut count = 0;
+            while let Some(IpcWireApi::Request { respond, msg, .. }) =
+                con_recv.next().await
+            {
+                count += 1;
+                let msg_id = msg.get_msg_id();
+                let delay = if count == 1 { 1000 } else { 200 };
+                respond.respond(Ok(async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(delay))
+                        .await;
+                    Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                        msg_id,
+                        last_keystore_index: count.into(),
+                    })
+                }
+                .boxed()
+                .into()));
+            }
+            Ok(())
+        });
+
+        let (_cli_kill, cli_send, _cli_recv, _) =
+            spawn_ipc_connection(config).await?;
+        let start = std::time::Instant::now();
+        let responses = (0..20)
+            .map(|_| {
+                cli_send.request(LairWire::ToLairLairGetLastEntryIndex {
+                    msg_id: next_msg_id(),
+                })
+            })
+            .collect::<futures::stream::FuturesUnordered<_>>()
+            .map(|res| match res {
+                Ok(LairWire::ToCliLairGetLastEntryIndexResponse {
+                    last_keystore_index,
+                    ..
+                }) => last_keystore_index.0,
+                o => panic!("unexpected: {:?}", o),
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        // all in flight at once, the slow one overtaken by the others
+        // (one after the other, they'd take close to 5s)
+        assert!(start.elapsed() < std::time::Duration::from_secs(3));
+        assert_eq!(20, responses.len());
+        assert_eq!(Some(&1), responses.last());
+        let mut sorted = responses.clone();
+        sorted.sort_unstable();
+        assert_eq!((1..=20).collect::<Vec<_>>(), sorted);
+
+        drop(srv_kill);
+        Ok(())
+    }
 }
@@ -0,0 +1,227 @@
+//! The first frame both sides of a connection send: the protocol versions
+//! and features each speaks. The connection proceeds with the highest
+//! version both support, or fails if there is none.
+
+use super::*;
+
+/// The protocol version spoken by this lair.
+/// Peers from before the handshake speak version 0.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The oldest protocol version this lair still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Protocol features, as bit flags of `IpcProtocol::features`.
+pub mod feature {
+    /// Connection tokens, and the connection scopes presented with them.
+    pub const CONNECTION_TOKEN: u64 = 1;
+
+    /// Keepalive pings, see `LairClientApi::lair_ping()`.
+    pub const PING: u64 = 2;
+
+    /// Client names, see `ConfigBuilder::set_client_name()`.
+    pub const CLIENT_NAME: u64 = 4;
+
+    /// Errors responded as `TypedErrorResponse`, rebuilt as the
+    /// `LairError` variant the peer produced.
+    pub const TYPED_ERRORS: u64 = 8;
+}
+
+/// The features spoken by this lair.
+pub const PROTOCOL_FEATURES: u64 = feature::CONNECTION_TOKEN
+    | feature::PING
+    | feature::CLIENT_NAME
+    | feature::TYPED_ERRORS;
+
+/// How long a peer may take to send its handshake.
+pub const HANDSHAKE_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(3);
+
+/// Largest first frame read while waiting for the handshake.
+const HANDSHAKE_MAX_BYTES: usize = 1024;
+
+/// The protocol negotiated for a connection: wire changes are gated on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpcProtocol {
+    /// The highest version both sides speak.
+    pub version: u32,
+
+    /// The features both sides speak.
+    pub features: u64,
+}
+
+impl IpcProtocol {
+    /// Do both sides speak `feature`?
+    pub fn has_feature(&self, feature: u64) -> bool {
+        self.features & feature == feature
+    }
+}
+
+/// Send our handshake, and negotiate a protocol with the one received.
+pub(crate) async fn handshake<R, W>(
+    read_half: &mut R,
+    write_half: &mut W,
+) -> LairResult<IpcProtocol>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let ours = LairWire::Handshake {
+        msg_id: 0,
+        version: PROTOCOL_VERSION,
+        min_version: MIN_PROTOCOL_VERSION,
+        features: PROTOCOL_FEATURES,
+    };
+    let fut = async {
+        write_half
+            .write_all(&ours.encode()?)
+            .await
+            .map_err(LairError::other)?;
+        read_handshake(read_half).await
+    };
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, fut).await {
+        Ok(theirs) => negotiate(theirs?),
+        Err(_) => Err("timed out waiting for the protocol handshake".into()),
+    }
+}
+
+/// Turn a peer away, as the server has too many connections: send
+/// `LairWire::Busy` in place of our handshake. Its handshake is read
+/// first, so the peer gets to write it, and reads why it was turned away.
+pub(crate) async fn turn_away_busy<R, W>(
+    read_half: &mut R,
+    write_half: &mut W,
+) -> LairResult<()>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let fut = async {
+        read_handshake(read_half).await?;
+        write_half
+            .write_all(&LairWire::Busy { msg_id: 0 }.encode()?)
+            .await
+            .map_err(LairError::other)
+    };
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, fut).await {
+        Ok(res) => res,
+        Err(_) => Err("timed out waiting for the protocol handshake".into()),
+    }
+}
+
+/// Read the first frame of the peer, if it isn't a handshake, the peer is
+/// from before the handshake, so speaks version 0. Busy servers send
+/// `LairWire::Busy` instead, and hang up.
+async fn read_handshake<R>(read_half: &mut R) -> LairResult<LairWire>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let closed = |e: std::io::Error| {
+        LairError::other(format!(
+            "connection closed during the protocol handshake: {}",
+            e
+        ))
+    };
+    let mut data = vec![0; 4];
+    read_half.read_exact(&mut data).await.map_err(closed)?;
+    let size = LairWire::peek_size(&data)?;
+    if size < data.len() || size > HANDSHAKE_MAX_BYTES {
+        return Ok(version_0());
+    }
+    data.resize(size, 0);
+    read_half.read_exact(&mut data[4..]).await.map_err(closed)?;
+    match LairWire::decode(&data) {
+        Ok(msg @ LairWire::Handshake { .. }) => Ok(msg),
+        Ok(LairWire::Busy { .. }) => Err(LairError::Busy),
+        _ => Ok(version_0()),
+    }
+}
+
+fn version_0() -> LairWire {
+    LairWire::Handshake {
+        msg_id: 0,
+        version: 0,
+        min_version: 0,
+        features: 0,
+    }
+}
+
+fn negotiate(theirs: LairWire) -> LairResult<IpcProtocol> {
+    let (their_version, their_min_version, their_features) = match theirs {
+        LairWire::Handshake {
+            version,
+            min_version,
+            features,
+            ..
+        } => (version, min_version, features),
+        o => return Err(format!("unexpected: {:?}", o).into()),
+    };
+    let version = std::cmp::min(PROTOCOL_VERSION, their_version);
+    if version < MIN_PROTOCOL_VERSION || version < their_min_version {
+        return Err(LairError::ProtocolVersionMismatch {
+            ours: PROTOCOL_VERSION,
+            theirs: their_version,
+        });
+    }
+    Ok(IpcProtocol {
+        version,
+        features: PROTOCOL_FEATURES & their_features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(version: u32, min_version: u32, features: u64) -> LairWire {
+        LairWire::Handshake {
+            msg_id: 0,
+            version,
+            min_version,
+            features,
+        }
+    }
+
+    #[test]
+    fn negotiates_the_highest_common_version() {
+        let protocol = negotiate(handshake(
+            PROTOCOL_VERSION,
+            MIN_PROTOCOL_VERSION,
+            PROTOCOL_FEATURES,
+        ))
+        .unwrap();
+        assert_eq!(PROTOCOL_VERSION, protocol.version);
+        assert!(protocol.has_feature(feature::CONNECTION_TOKEN));
+        assert!(protocol.has_feature(feature::PING));
+        assert!(protocol.has_feature(feature::CLIENT_NAME));
+        assert!(protocol.has_feature(feature::TYPED_ERRORS));
+
+        // newer peers still speaking our version
+        let protocol =
+            negotiate(handshake(PROTOCOL_VERSION + 1, MIN_PROTOCOL_VERSION, 0))
+                .unwrap();
+        assert_eq!(PROTOCOL_VERSION, protocol.version);
+        assert!(!protocol.has_feature(feature::CONNECTION_TOKEN));
+
+        for (version, min_version) in
+            &[(0, 0), (PROTOCOL_VERSION + 2, PROTOCOL_VERSION + 1)]
+        {
+            match negotiate(handshake(*version, *min_version, 0)) {
+                Err(LairError::ProtocolVersionMismatch { ours, theirs }) => {
+                    assert_eq!(PROTOCOL_VERSION, ours);
+                    assert_eq!(*version, theirs);
+                }
+                o => panic!("unexpected: {:?}", o),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn peers_from_before_the_handshake_speak_version_0() {
+        let (mut a, mut b) = tokio::io::duplex(1024);
+        // their first frame is a request
+        let req = LairWire::ToLairLairGetLastEntryIndex { msg_id: 1 };
+        a.write_all(&req.encode().unwrap()).await.unwrap();
+        assert_eq!(version_0(), read_handshake(&mut b).await.unwrap());
+    }
+}
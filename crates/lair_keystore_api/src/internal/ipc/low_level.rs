@@ -17,10 +17,13 @@ pub(crate) type LowLevelWireReceiver =
     futures::channel::mpsc::Receiver<LowLevelWireApi>;
 
 #[allow(clippy::unnecessary_wraps)]
-pub(crate) fn spawn_low_level_write_half(
+pub(crate) fn spawn_low_level_write_half<W>(
     kill_switch: KillSwitch,
-    mut write_half: IpcWrite,
-) -> LairResult<LowLevelWireSender> {
+    mut write_half: W,
+) -> LairResult<LowLevelWireSender>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
     let (s, mut r) = futures::channel::mpsc::channel(10);
 
     err_spawn("ll-write", async move {
@@ -61,21 +64,53 @@ pub(crate) fn spawn_low_level_write_half(
     Ok(s)
 }
 
+/// Bytes read at once, unless more of a large frame is pending.
+const READ_CHUNK: usize = 4096;
+
+/// Largest read of the rest of a frame. Past this, large frames grow
+/// the buffer as they arrive, not by the size the peer claims.
+const READ_CHUNK_MAX: usize = 1024 * 1024;
+
+/// The free space to reserve before reading into `pending_data`:
+/// the rest of the pending frame (capped), so it's read in one go.
+fn read_reserve(pending_data: &[u8]) -> usize {
+    let rest = match LairWire::peek_size(pending_data) {
+        Ok(size) => size.saturating_sub(pending_data.len()),
+        Err(_) => 0,
+    };
+    rest.clamp(READ_CHUNK, READ_CHUNK_MAX)
+}
+
+/// Read messages, handling at most `max_in_flight` at once: past that,
+/// reading stops until one is done, so a peer sending faster than its
+/// messages are handled is held up at the socket rather than buffered.
+/// Requests being handled are added to the `counts` of a server.
 #[allow(clippy::unnecessary_wraps)]
-pub(crate) fn spawn_low_level_read_half(
+pub(crate) fn spawn_low_level_read_half<R>(
     kill_switch: KillSwitch,
-    mut read_half: IpcRead,
-) -> LairResult<LowLevelWireReceiver> {
+    mut read_half: R,
+    max_in_flight: usize,
+    counts: Option<Arc<ConnectionCounts>>,
+) -> LairResult<LowLevelWireReceiver>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
     let (s, r) = futures::channel::mpsc::channel(10);
+    let in_flight = Arc::new(tokio::sync::Semaphore::new(max_in_flight));
 
     err_spawn("ll-read", async move {
-        let mut pending_data = Vec::new();
-        let mut buffer = [0_u8; 4096];
+        // frames are split off this buffer without copying,
+        // their payloads are decoded as slices of them
+        let mut pending_data = bytes::BytesMut::new();
         loop {
             trace!("ll read tick");
+            pending_data.reserve(read_reserve(&pending_data));
             let read = kill_switch
                 .mix(async {
-                    read_half.read(&mut buffer).await.map_err(LairError::other)
+                    read_half
+                        .read_buf(&mut pending_data)
+                        .await
+                        .map_err(LairError::other)
                 })
                 .await?;
             trace!(?read, "ll read count");
@@ -83,15 +118,32 @@ pub(crate) fn spawn_low_level_read_half(
                 trace!("ll read end");
                 return Err("read returned 0 bytes".into());
             }
-            pending_data.extend_from_slice(&buffer[..read]);
             while let Ok(size) = LairWire::peek_size(&pending_data) {
                 trace!(?size, "ll read peek size");
                 if pending_data.len() < size {
                     break;
                 }
-                let msg = LairWire::decode(&pending_data)?;
-                let _ = pending_data.drain(..size);
+                let frame = pending_data.split_to(size).freeze();
+                let msg = LairWire::decode_frame(&frame)?;
                 trace!("ll read {:?}", msg);
+                let permit = kill_switch
+                    .mix(async {
+                        in_flight
+                            .clone()
+                            .acquire_owned()
+                            .await
+                            .map_err(LairError::other)
+                    })
+                    .await?;
+                let counts = match &counts {
+                    Some(counts) if msg.is_req() => {
+                        counts
+                            .requests_in_flight
+                            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        Some(counts.clone())
+                    }
+                    _ => None,
+                };
                 // run this in a task so we don't hold up the read loop
                 let weak_kill_switch = kill_switch.weak();
                 let task_sender = s.clone();
@@ -100,6 +152,12 @@ pub(crate) fn spawn_low_level_read_half(
                         .mix(task_sender.low_level_send(msg))
                         .await;
                     trace!("ll read send done");
+                    if let Some(counts) = counts {
+                        counts
+                            .requests_in_flight
+                            .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                    drop(permit);
                 });
             }
         }
@@ -0,0 +1,86 @@
+//! in-process ipc channels, standing in for the socket of ephemeral configs
+
+use crate::*;
+use futures::{sink::SinkExt, stream::StreamExt};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// How much a connection buffers in each direction before writes wait
+/// for the other end to read.
+const CHANNEL_BUFFER: usize = 64 * 1024;
+
+type Incoming = futures::channel::mpsc::Sender<tokio::io::DuplexStream>;
+
+/// The servers listening in this process, by socket path.
+static LISTENING: Lazy<std::sync::Mutex<HashMap<PathBuf, Incoming>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+pub(crate) type MemRead = tokio::io::ReadHalf<tokio::io::DuplexStream>;
+pub(crate) type MemWrite = tokio::io::WriteHalf<tokio::io::DuplexStream>;
+
+pub(crate) async fn mem_connect(
+    config: Arc<Config>,
+) -> LairResult<(MemRead, MemWrite)> {
+    let path = config.get_socket_path();
+    let incoming = LISTENING.lock().unwrap().get(path).cloned();
+    let (client, server) = tokio::io::duplex(CHANNEL_BUFFER);
+    let connected = match incoming {
+        Some(mut incoming) => incoming.send(server).await.is_ok(),
+        None => false,
+    };
+    if !connected {
+        return Err(LairError::IpcClientConnectError(
+            path.to_string_lossy().to_string(),
+            "no lair server is listening in this process".into(),
+        ));
+    }
+    Ok(tokio::io::split(client))
+}
+
+/// Stop accepting connections on the in-process channel of `config`.
+pub(crate) fn mem_unbind(config: &Config) {
+    LISTENING.lock().unwrap().remove(config.get_socket_path());
+}
+
+pub(crate) struct MemServer {
+    path: PathBuf,
+    recv: futures::channel::mpsc::Receiver<tokio::io::DuplexStream>,
+}
+
+impl MemServer {
+    pub fn bind(config: Arc<Config>) -> LairResult<Self> {
+        let path = config.get_socket_path().to_owned();
+        let mut listening = LISTENING.lock().unwrap();
+        if let Some(incoming) = listening.get(&path) {
+            if !incoming.is_closed() {
+                return Err(format!(
+                    "a lair server is already listening on {:?}",
+                    path
+                )
+                .into());
+            }
+        }
+        let (send, recv) = futures::channel::mpsc::channel(10);
+        listening.insert(path.clone(), send);
+        Ok(Self { path, recv })
+    }
+
+    pub async fn accept(&mut self) -> LairResult<(MemRead, MemWrite)> {
+        match self.recv.next().await {
+            Some(con) => Ok(tokio::io::split(con)),
+            None => Err("in-process ipc channel closed".into()),
+        }
+    }
+}
+
+impl Drop for MemServer {
+    fn drop(&mut self) {
+        self.recv.close();
+        let mut listening = LISTENING.lock().unwrap();
+        // unless a new server took over the path already
+        if listening.get(&self.path).map(|i| i.is_closed()) == Some(true) {
+            listening.remove(&self.path);
+        }
+    }
+}
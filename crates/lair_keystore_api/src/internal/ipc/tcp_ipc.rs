@@ -0,0 +1,99 @@
+//! tcp ipc streams, for configs with a tcp address
+
+use crate::*;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+type Closer = futures::channel::oneshot::Sender<()>;
+
+/// The tcp servers listening in this process, by configured address,
+/// so shutting down can close the listener.
+static LISTENING: Lazy<std::sync::Mutex<HashMap<SocketAddr, Closer>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+pub(crate) type TcpRead = tokio::net::tcp::OwnedReadHalf;
+pub(crate) type TcpWrite = tokio::net::tcp::OwnedWriteHalf;
+
+fn tcp_bind_addr(config: &Config) -> LairResult<SocketAddr> {
+    config
+        .get_tcp_bind()
+        .ok_or_else(|| "config has no tcp address".into())
+}
+
+pub(crate) async fn tcp_connect(
+    config: Arc<Config>,
+) -> LairResult<(TcpRead, TcpWrite)> {
+    let addr = tcp_bind_addr(&config)?;
+    let con = tokio::net::TcpStream::connect(addr).await.map_err(|e| {
+        LairError::IpcClientConnectError(addr.to_string(), e.into())
+    })?;
+    con.set_nodelay(true).map_err(LairError::other)?;
+    Ok(con.into_split())
+}
+
+/// Stop accepting tcp connections for `config`, closing the listener.
+pub(crate) fn tcp_unbind(config: &Config) {
+    if let Some(addr) = config.get_tcp_bind() {
+        if let Some(closer) = LISTENING.lock().unwrap().remove(&addr) {
+            let _ = closer.send(());
+        }
+    }
+}
+
+pub(crate) struct TcpServer {
+    addr: SocketAddr,
+    socket: tokio::net::TcpListener,
+    closed: futures::channel::oneshot::Receiver<()>,
+}
+
+impl TcpServer {
+    pub fn bind(config: Arc<Config>) -> LairResult<Self> {
+        let addr = tcp_bind_addr(&config)?;
+        if !addr.ip().is_loopback() {
+            warn!(
+                %addr,
+                "LISTENING ON A NON-LOOPBACK TCP ADDRESS: anyone able to \
+                reach it can use the keys in this keystore"
+            );
+        }
+        let socket =
+            std::net::TcpListener::bind(addr).map_err(LairError::other)?;
+        socket.set_nonblocking(true).map_err(LairError::other)?;
+        let socket = tokio::net::TcpListener::from_std(socket)
+            .map_err(LairError::other)?;
+        let (closer, closed) = futures::channel::oneshot::channel();
+        LISTENING.lock().unwrap().insert(addr, closer);
+        Ok(Self {
+            addr,
+            socket,
+            closed,
+        })
+    }
+
+    pub async fn accept(&mut self) -> LairResult<(TcpRead, TcpWrite)> {
+        let accept = self.socket.accept();
+        futures::pin_mut!(accept);
+        match futures::future::select(accept, &mut self.closed).await {
+            futures::future::Either::Left((res, _)) => {
+                let (con, _) = res.map_err(LairError::other)?;
+                con.set_nodelay(true).map_err(LairError::other)?;
+                Ok(con.into_split())
+            }
+            futures::future::Either::Right(_) => {
+                Err("tcp listener closed".into())
+            }
+        }
+    }
+}
+
+impl Drop for TcpServer {
+    fn drop(&mut self) {
+        self.closed.close();
+        let mut listening = LISTENING.lock().unwrap();
+        // unless a new server took over the address already
+        if listening.get(&self.addr).map(|c| c.is_canceled()) == Some(true) {
+            listening.remove(&self.addr);
+        }
+    }
+}
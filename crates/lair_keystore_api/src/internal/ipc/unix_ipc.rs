@@ -80,6 +80,10 @@ pub(crate) async fn ipc_connect(
     ))
 }
 
+/// A listening unix socket bound outside of lair,
+/// e.g. handed to us by systemd socket activation.
+pub type IpcListener = std::os::unix::net::UnixListener;
+
 #[allow(dead_code)]
 pub(crate) struct IpcServer {
     config: Arc<Config>,
@@ -88,14 +92,39 @@ pub(crate) struct IpcServer {
 
 impl IpcServer {
     pub fn bind(config: Arc<Config>) -> LairResult<Self> {
-        let _ = std::fs::remove_file(config.get_socket_path());
-        let socket = tokio::net::UnixListener::bind(config.get_socket_path())
+        let path = config.get_socket_path();
+        // e.g. a socket dir in /run, gone since the last boot
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let _ = std::fs::remove_file(path);
+        let socket =
+            tokio::net::UnixListener::bind(path).map_err(LairError::other)?;
+        set_socket_permissions(&config)?;
+        Ok(Self { config, socket })
+    }
+
+    /// Serve on an already bound listener.
+    /// The socket file belongs to whoever bound it, we don't touch it.
+    pub fn from_listener(
+        config: Arc<Config>,
+        listener: IpcListener,
+    ) -> LairResult<Self> {
+        listener.set_nonblocking(true).map_err(LairError::other)?;
+        let socket = tokio::net::UnixListener::from_std(listener)
             .map_err(LairError::other)?;
         Ok(Self { config, socket })
     }
 
     pub async fn accept(&mut self) -> LairResult<(IpcRead, IpcWrite)> {
-        let (con, _) = self.socket.accept().await.map_err(LairError::other)?;
+        let con = loop {
+            let (con, _) =
+                self.socket.accept().await.map_err(LairError::other)?;
+            if check_peer(&self.config, &con) {
+                break con;
+            }
+            // dropping the connection closes it
+        };
         //let (read_half, write_half) = con.into_split();
         let (read_half, write_half) = tokio::io::split(con);
         Ok((
@@ -110,3 +139,270 @@ impl IpcServer {
         ))
     }
 }
+
+/// Give the bound socket file the configured mode and group.
+fn set_socket_permissions(config: &Config) -> LairResult<()> {
+    use std::os::unix::{ffi::OsStrExt, fs::PermissionsExt};
+    let path = config.get_socket_path();
+    if let Some(group) = config.get_socket_group() {
+        let gid = group_id(group)?;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(LairError::other)?;
+        // (uid_t)-1 leaves the owner as it is
+        if unsafe { libc::chown(c_path.as_ptr(), libc::uid_t::MAX, gid) } != 0 {
+            return Err(LairError::other(format!(
+                "failed to give socket {} to group {}: {}",
+                path.display(),
+                group,
+                std::io::Error::last_os_error(),
+            )));
+        }
+    }
+    std::fs::set_permissions(
+        path,
+        std::fs::Permissions::from_mode(config.get_socket_mode()),
+    )?;
+    Ok(())
+}
+
+/// The gid of `group`, a group name or a gid.
+fn group_id(group: &str) -> LairResult<libc::gid_t> {
+    if let Ok(gid) = group.parse() {
+        return Ok(gid);
+    }
+    let name = std::ffi::CString::new(group).map_err(LairError::other)?;
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut found = std::ptr::null_mut();
+        let res = unsafe {
+            libc::getgrnam_r(
+                name.as_ptr(),
+                &mut grp,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut found,
+            )
+        };
+        match res {
+            0 if !found.is_null() => return Ok(grp.gr_gid),
+            0 => {
+                return Err(LairError::other(format!(
+                    "unknown socket group {}",
+                    group
+                )))
+            }
+            libc::ERANGE if buf.len() < 1024 * 1024 => {
+                buf.resize(buf.len() * 2, 0)
+            }
+            _ => return Err(std::io::Error::from_raw_os_error(res).into()),
+        }
+    }
+}
+
+/// Credentials of the process at the other end of a unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PeerCred {
+    pid: Option<i32>,
+    uid: u32,
+    gid: u32,
+}
+
+/// Whether the peer of `con` may connect: it runs as the server's own
+/// (effective) uid, or as one of the uids or in one of the gids allowed
+/// in `config`. Rejected peers are logged. Where peer credentials are
+/// unsupported every peer is allowed, with a warning logged once.
+fn check_peer(config: &Config, con: &tokio::net::UnixStream) -> bool {
+    use std::os::unix::io::AsRawFd;
+    let cred = match sys::peer_cred(con.as_raw_fd()) {
+        Ok(Some(cred)) => cred,
+        Ok(None) => {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| {
+                warn!(
+                    "peer credentials are not supported on this platform, \
+                    any user able to open the socket can connect"
+                )
+            });
+            return true;
+        }
+        Err(err) => {
+            warn!(%err, "rejecting a connection: no peer credentials");
+            return false;
+        }
+    };
+    let own_uid = unsafe { libc::geteuid() };
+    if peer_allowed(config, own_uid, &cred) {
+        return true;
+    }
+    warn!(
+        pid = ?cred.pid,
+        uid = cred.uid,
+        gid = cred.gid,
+        "rejecting a connection from a peer not allowed to connect"
+    );
+    false
+}
+
+fn peer_allowed(config: &Config, own_uid: u32, cred: &PeerCred) -> bool {
+    cred.uid == own_uid
+        || config.get_allowed_peer_uids().contains(&cred.uid)
+        || config.get_allowed_peer_gids().contains(&cred.gid)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use super::PeerCred;
+
+    pub(super) fn peer_cred(
+        fd: std::os::unix::io::RawFd,
+    ) -> std::io::Result<Option<PeerCred>> {
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        match unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        } {
+            0 => Ok(Some(PeerCred {
+                pid: Some(cred.pid),
+                uid: cred.uid,
+                gid: cred.gid,
+            })),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd",
+))]
+mod sys {
+    use super::PeerCred;
+
+    pub(super) fn peer_cred(
+        fd: std::os::unix::io::RawFd,
+    ) -> std::io::Result<Option<PeerCred>> {
+        // LOCAL_PEERCRED (or the like) behind a portable call
+        let mut uid: libc::uid_t = 0;
+        let mut gid: libc::gid_t = 0;
+        if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Some(PeerCred {
+            pid: peer_pid(fd),
+            uid,
+            gid,
+        }))
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn peer_pid(fd: std::os::unix::io::RawFd) -> Option<i32> {
+        let mut pid: libc::pid_t = 0;
+        let mut len = std::mem::size_of::<libc::pid_t>() as libc::socklen_t;
+        match unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_LOCAL,
+                libc::LOCAL_PEERPID,
+                &mut pid as *mut libc::pid_t as *mut libc::c_void,
+                &mut len,
+            )
+        } {
+            0 => Some(pid),
+            _ => None,
+        }
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    fn peer_pid(_fd: std::os::unix::io::RawFd) -> Option<i32> {
+        None
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd",
+)))]
+mod sys {
+    use super::PeerCred;
+
+    pub(super) fn peer_cred(
+        _fd: std::os::unix::io::RawFd,
+    ) -> std::io::Result<Option<PeerCred>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_allowed_peers_may_connect() {
+        let cred = |uid, gid| PeerCred {
+            pid: None,
+            uid,
+            gid,
+        };
+        let config = Config::builder().set_ephemeral(true).build();
+        assert!(peer_allowed(&config, 1000, &cred(1000, 1000)));
+        assert!(!peer_allowed(&config, 1000, &cred(1001, 1001)));
+        assert!(!peer_allowed(&config, 1000, &cred(0, 0)));
+
+        let config = Config::builder()
+            .set_ephemeral(true)
+            .add_allowed_peer_uid(1001)
+            .add_allowed_peer_gid(100)
+            .build();
+        assert!(peer_allowed(&config, 1000, &cred(1001, 1001)));
+        assert!(peer_allowed(&config, 1000, &cred(1002, 100)));
+        assert!(!peer_allowed(&config, 1000, &cred(1002, 1002)));
+    }
+
+    #[test]
+    fn groups_are_given_by_name_or_gid() {
+        assert_eq!(0, group_id("0").unwrap());
+        assert_eq!(4242, group_id("4242").unwrap());
+        // the name of gid 0 varies ("root", "wheel"), not its existence
+        let root = unsafe { libc::getgrgid(0) };
+        if !root.is_null() {
+            let name = unsafe { std::ffi::CStr::from_ptr((*root).gr_name) };
+            assert_eq!(0, group_id(name.to_str().unwrap()).unwrap());
+        }
+        assert!(group_id("no-such-lair-group").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn peers_of_our_own_uid_may_connect() {
+        let (a, _b) = tokio::net::UnixStream::pair().unwrap();
+        let config = Config::builder().set_ephemeral(true).build();
+        assert!(check_peer(&config, &a));
+        use std::os::unix::io::AsRawFd;
+        if let Some(cred) = sys::peer_cred(a.as_raw_fd()).unwrap() {
+            assert_eq!(unsafe { libc::geteuid() }, cred.uid);
+            if cfg!(target_os = "linux") {
+                assert_eq!(Some(std::process::id() as i32), cred.pid);
+            }
+        }
+    }
+}
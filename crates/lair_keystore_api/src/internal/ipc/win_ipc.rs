@@ -1,2 +1,432 @@
-//! windows version of ipc stream tools
-//! TODO : )
+//! windows version of ipc stream tools, over named pipes
+//!
+//! Pipe handles are driven with blocking overlapped io, a thread for each
+//! direction of a connection, bridged to the async side by an in-memory
+//! duplex stream - the same streams the in-process channel hands out.
+
+use crate::*;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::ptr::{null, null_mut};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
+use winapi::shared::sddl::{
+    ConvertSidToStringSidW,
+    ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+};
+use winapi::shared::winerror::{
+    ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED,
+};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::fileapi::{CreateFileW, ReadFile, WriteFile, OPEN_EXISTING};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::ioapiset::{CancelIoEx, GetOverlappedResult};
+use winapi::um::minwinbase::{LPOVERLAPPED, OVERLAPPED, SECURITY_ATTRIBUTES};
+use winapi::um::namedpipeapi::{
+    ConnectNamedPipe, CreateNamedPipeW, WaitNamedPipeW,
+};
+use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::synchapi::CreateEventW;
+use winapi::um::winbase::{
+    LocalFree, FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED,
+    PIPE_ACCESS_DUPLEX, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    SECURITY_IDENTIFICATION, SECURITY_SQOS_PRESENT,
+};
+use winapi::um::winnt::{
+    TokenUser, GENERIC_READ, GENERIC_WRITE, HANDLE, PSECURITY_DESCRIPTOR,
+    TOKEN_QUERY, TOKEN_USER,
+};
+
+/// How much a connection buffers in each direction.
+const PIPE_BUFFER: DWORD = 64 * 1024;
+
+/// How long a client waits for the server to offer a free pipe instance.
+const CONNECT_WAIT_MS: DWORD = 5000;
+
+/// The pipes served by this process, unbinding one removes it here.
+static LISTENING: Lazy<std::sync::Mutex<HashSet<PathBuf>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashSet::new()));
+
+pub(crate) type IpcRead = tokio::io::ReadHalf<tokio::io::DuplexStream>;
+pub(crate) type IpcWrite = tokio::io::WriteHalf<tokio::io::DuplexStream>;
+
+/// There is no socket activation on windows, so nothing hands lair a
+/// listener: this type has no values.
+pub enum IpcListener {}
+
+fn wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(Some(0)).collect()
+}
+
+fn last_error() -> io::Error {
+    io::Error::last_os_error()
+}
+
+/// An owned windows handle, closed on drop.
+struct Handle(HANDLE);
+
+// handles may be used from any thread, overlapped io on a pipe handle
+// from several at once
+unsafe impl Send for Handle {}
+unsafe impl Sync for Handle {}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.0);
+        }
+    }
+}
+
+impl Handle {
+    /// Start overlapped io on this handle with `op`,
+    /// blocking until it completes.
+    fn overlapped<F>(&self, op: F) -> io::Result<DWORD>
+    where
+        F: FnOnce(LPOVERLAPPED) -> BOOL,
+    {
+        unsafe {
+            let event = CreateEventW(null_mut(), TRUE, FALSE, null());
+            if event.is_null() {
+                return Err(last_error());
+            }
+            let event = Handle(event);
+            let mut ov: OVERLAPPED = std::mem::zeroed();
+            ov.hEvent = event.0;
+            if op(&mut ov as LPOVERLAPPED) == FALSE {
+                let err = GetLastError();
+                if err != ERROR_IO_PENDING {
+                    return Err(io::Error::from_raw_os_error(err as i32));
+                }
+            }
+            // `ov` must outlive the io, so always wait for it
+            let mut n = 0;
+            if GetOverlappedResult(self.0, &mut ov, &mut n, TRUE) == FALSE {
+                return Err(last_error());
+            }
+            Ok(n)
+        }
+    }
+
+    /// Wait for a client to connect to this server pipe instance.
+    fn connect(&self) -> io::Result<()> {
+        match self.overlapped(|ov| unsafe { ConnectNamedPipe(self.0, ov) }) {
+            Ok(_) => Ok(()),
+            // it connected before we started waiting
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) => {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read some bytes, 0 once the other end closed the pipe.
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.overlapped(|ov| unsafe {
+            ReadFile(
+                self.0,
+                buf.as_mut_ptr() as *mut _,
+                buf.len() as DWORD,
+                null_mut(),
+                ov,
+            )
+        });
+        match res {
+            Ok(n) => Ok(n as usize),
+            Err(e) if e.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) => {
+                Ok(0)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let n = self.overlapped(|ov| unsafe {
+                WriteFile(
+                    self.0,
+                    buf.as_ptr() as *const _,
+                    buf.len() as DWORD,
+                    null_mut(),
+                    ov,
+                )
+            })?;
+            buf = &buf[n as usize..];
+        }
+        Ok(())
+    }
+
+    /// Abort io other threads are blocked in.
+    fn cancel(&self) {
+        unsafe {
+            CancelIoEx(self.0, null_mut());
+        }
+    }
+}
+
+/// Serve a connected pipe as a pair of async streams.
+fn spawn_pipe_bridge(pipe: Handle) -> (IpcRead, IpcWrite) {
+    let pipe = Arc::new(pipe);
+    let (ours, theirs) = tokio::io::duplex(PIPE_BUFFER as usize);
+    let (mut from_ipc, mut to_ipc) = tokio::io::split(theirs);
+
+    let read_pipe = pipe.clone();
+    std::thread::spawn(move || {
+        let mut buf = vec![0; PIPE_BUFFER as usize];
+        while let Ok(n) = read_pipe.read(&mut buf) {
+            if n == 0
+                || futures::executor::block_on(to_ipc.write_all(&buf[..n]))
+                    .is_err()
+            {
+                break;
+            }
+        }
+        // the ipc side reads the end of the stream
+        let _ = futures::executor::block_on(to_ipc.shutdown());
+    });
+
+    std::thread::spawn(move || {
+        let mut buf = vec![0; PIPE_BUFFER as usize];
+        while let Ok(n) = futures::executor::block_on(from_ipc.read(&mut buf)) {
+            if n == 0 || pipe.write_all(&buf[..n]).is_err() {
+                break;
+            }
+        }
+        // the ipc side is done with the connection, stop reading it too
+        pipe.cancel();
+    });
+
+    tokio::io::split(ours)
+}
+
+pub(crate) async fn ipc_connect(
+    config: Arc<Config>,
+) -> LairResult<(IpcRead, IpcWrite)> {
+    let path = config.get_socket_path().to_owned();
+    let pipe = tokio::task::spawn_blocking(move || open_pipe(&path))
+        .await
+        .map_err(LairError::other)?
+        .map_err(|e| {
+            LairError::IpcClientConnectError(
+                config.get_socket_path().to_string_lossy().to_string(),
+                e.into(),
+            )
+        })?;
+    Ok(spawn_pipe_bridge(pipe))
+}
+
+fn open_pipe(path: &Path) -> io::Result<Handle> {
+    let name = wide(path.as_os_str());
+    loop {
+        let pipe = unsafe {
+            CreateFileW(
+                name.as_ptr(),
+                GENERIC_READ | GENERIC_WRITE,
+                0,
+                null_mut(),
+                OPEN_EXISTING,
+                // the server may learn who we are, not act as us
+                FILE_FLAG_OVERLAPPED
+                    | SECURITY_SQOS_PRESENT
+                    | SECURITY_IDENTIFICATION,
+                null_mut(),
+            )
+        };
+        if pipe != INVALID_HANDLE_VALUE {
+            return Ok(Handle(pipe));
+        }
+        let err = last_error();
+        if err.raw_os_error() != Some(ERROR_PIPE_BUSY as i32) {
+            return Err(err);
+        }
+        // every instance is taken until the server creates the next one
+        if unsafe { WaitNamedPipeW(name.as_ptr(), CONNECT_WAIT_MS) } == FALSE {
+            return Err(last_error());
+        }
+    }
+}
+
+/// Stop accepting connections on the pipe of `config`.
+pub(crate) fn pipe_unbind(config: &Config) {
+    let path = config.get_socket_path();
+    if LISTENING.lock().unwrap().remove(path) {
+        // wake the server waiting for a client, it sees it was unbound
+        let _ = open_pipe(path);
+    }
+}
+
+/// A security descriptor granting the current user, and nobody else,
+/// access to what it is set on.
+struct CurrentUserOnly(PSECURITY_DESCRIPTOR);
+
+unsafe impl Send for CurrentUserOnly {}
+unsafe impl Sync for CurrentUserOnly {}
+
+impl Drop for CurrentUserOnly {
+    fn drop(&mut self) {
+        unsafe {
+            LocalFree(self.0);
+        }
+    }
+}
+
+impl CurrentUserOnly {
+    fn new() -> io::Result<Self> {
+        // a protected dacl, inheriting no other entries
+        let sddl = format!("D:P(A;;GA;;;{})", current_user_sid()?);
+        let sddl = wide(OsStr::new(&sddl));
+        let mut sd = null_mut();
+        let ok = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1 as DWORD,
+                &mut sd,
+                null_mut(),
+            )
+        };
+        if ok == FALSE {
+            return Err(last_error());
+        }
+        Ok(Self(sd))
+    }
+
+    fn attributes(&self) -> SECURITY_ATTRIBUTES {
+        SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as DWORD,
+            lpSecurityDescriptor: self.0,
+            bInheritHandle: FALSE,
+        }
+    }
+}
+
+/// The sid of the user this process runs as, e.g. "S-1-5-21-...".
+fn current_user_sid() -> io::Result<String> {
+    unsafe {
+        let mut token = null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)
+            == FALSE
+        {
+            return Err(last_error());
+        }
+        let token = Handle(token);
+
+        let mut len = 0;
+        GetTokenInformation(token.0, TokenUser, null_mut(), 0, &mut len);
+        // (u64s, for the alignment of TOKEN_USER)
+        let mut buf = vec![0_u64; (len as usize).div_ceil(8)];
+        if GetTokenInformation(
+            token.0,
+            TokenUser,
+            buf.as_mut_ptr() as *mut _,
+            len,
+            &mut len,
+        ) == FALSE
+        {
+            return Err(last_error());
+        }
+        let user = &*(buf.as_ptr() as *const TOKEN_USER);
+
+        let mut sid = null_mut();
+        if ConvertSidToStringSidW(user.User.Sid, &mut sid) == FALSE {
+            return Err(last_error());
+        }
+        let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+        let out =
+            String::from_utf16_lossy(std::slice::from_raw_parts(sid, len));
+        LocalFree(sid as *mut _);
+        Ok(out)
+    }
+}
+
+pub(crate) struct IpcServer {
+    path: PathBuf,
+    name: Vec<u16>,
+    security: CurrentUserOnly,
+    /// the instance the next client connects to
+    pending: Arc<Handle>,
+}
+
+impl IpcServer {
+    pub fn bind(config: Arc<Config>) -> LairResult<Self> {
+        let path = config.get_socket_path().to_owned();
+        let name = wide(path.as_os_str());
+        let security = CurrentUserOnly::new().map_err(LairError::other)?;
+        // failing if another server has this pipe
+        let pending = create_pipe_instance(&name, &security, true)
+            .map_err(|e| LairError::other(format!("{:?}: {}", path, e)))?;
+        LISTENING.lock().unwrap().insert(path.clone());
+        Ok(Self {
+            path,
+            name,
+            security,
+            pending: Arc::new(pending),
+        })
+    }
+
+    /// There are no listeners on windows, see `IpcListener`.
+    pub fn from_listener(
+        _config: Arc<Config>,
+        listener: IpcListener,
+    ) -> LairResult<Self> {
+        match listener {}
+    }
+
+    pub async fn accept(&mut self) -> LairResult<(IpcRead, IpcWrite)> {
+        let pending = self.pending.clone();
+        tokio::task::spawn_blocking(move || pending.connect())
+            .await
+            .map_err(LairError::other)?
+            .map_err(LairError::other)?;
+        if !LISTENING.lock().unwrap().contains(&self.path) {
+            return Err("named pipe closed".into());
+        }
+
+        // offer the next client an instance of its own
+        let next = create_pipe_instance(&self.name, &self.security, false)
+            .map_err(LairError::other)?;
+        let con = std::mem::replace(&mut self.pending, Arc::new(next));
+        let con = match Arc::try_unwrap(con) {
+            Ok(con) => con,
+            Err(_) => return Err("pipe instance still in use".into()),
+        };
+        Ok(spawn_pipe_bridge(con))
+    }
+}
+
+fn create_pipe_instance(
+    name: &[u16],
+    security: &CurrentUserOnly,
+    first: bool,
+) -> io::Result<Handle> {
+    let mut attributes = security.attributes();
+    let first = if first {
+        FILE_FLAG_FIRST_PIPE_INSTANCE
+    } else {
+        0
+    };
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED | first,
+            PIPE_TYPE_BYTE
+                | PIPE_READMODE_BYTE
+                | PIPE_WAIT
+                | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            PIPE_BUFFER,
+            PIPE_BUFFER,
+            0,
+            &mut attributes,
+        )
+    };
+    if pipe == INVALID_HANDLE_VALUE {
+        return Err(last_error());
+    }
+    Ok(Handle(pipe))
+}
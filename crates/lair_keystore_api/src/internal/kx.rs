@@ -0,0 +1,127 @@
+//! Key exchange producing a pair of directional session keys,
+//! bit for bit compatible with libsodium's `crypto_kx_client_session_keys`
+//! and `crypto_kx_server_session_keys`.
+//!
+//! Unlike the x25519 private keys they are derived from, session keys are
+//! ephemeral and are handed back to the caller.
+
+use crate::*;
+use derive_more::*;
+use internal::x25519;
+
+/// Byte length of each session key.
+pub const SESSION_KEY_BYTES: usize = 32;
+
+/// A kx session key, used for receiving (rx) or transmitting (tx).
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+#[allow(clippy::rc_buffer)]
+pub struct KxSessionKey(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for KxSessionKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// Derive the `(rx, tx)` session keys for the local keypair talking to
+/// `remote_pub`. The client's rx key is the server's tx key and vice versa.
+pub async fn kx_session_keys(
+    priv_key: x25519::X25519PrivKey,
+    local_pub: x25519::X25519PubKey,
+    remote_pub: x25519::X25519PubKey,
+    is_server: bool,
+) -> LairResult<(KxSessionKey, KxSessionKey)> {
+    rayon_exec(move || {
+        let shared = x25519::x25519_shared_secret(&priv_key, &remote_pub)?;
+        let (client_pub, server_pub) = if is_server {
+            (&remote_pub, &local_pub)
+        } else {
+            (&local_pub, &remote_pub)
+        };
+        let keys = blake2b_simd::Params::new()
+            .hash_length(2 * SESSION_KEY_BYTES)
+            .to_state()
+            .update(&*shared)
+            .update(client_pub.as_ref())
+            .update(server_pub.as_ref())
+            .finalize();
+        let (first, second) = keys.as_bytes().split_at(SESSION_KEY_BYTES);
+        let (first, second): (KxSessionKey, KxSessionKey) =
+            (first.to_vec().into(), second.to_vec().into());
+        Ok(if is_server {
+            (second, first)
+        } else {
+            (first, second)
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypair(
+        priv_key: [u8; 32],
+        pub_key: &str,
+    ) -> (x25519::X25519PrivKey, x25519::X25519PubKey) {
+        let mut pub_bytes = [0; 32];
+        for (i, b) in pub_bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&pub_key[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        (priv_key.into(), pub_bytes.into())
+    }
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_libsodium_kx() {
+        // vectors generated with libsodium
+        // crypto_kx_{client,server}_session_keys
+        let client = keypair(
+            [0x77; 32],
+            "1cf579aba45a10ba1d1ef06d91fca2aa9ed0a1150515653155405d0b18cb9a67",
+        );
+        let server = keypair(
+            [0x5d; 32],
+            "b795bc45da876a734d4dd92a67e4dbcefab44bb99e8d15afb52aeae80c296a0c",
+        );
+        let expect_client_rx =
+            "e0ba2d7025675ab8b940c29b058006dafdde36564e49d6ff4a57fc9dbc7fff12";
+        let expect_client_tx =
+            "c5b58b082e6c813ed24f746911e4ba6ddac4a90dde329f912eee1df589ac0778";
+
+        let (client_rx, client_tx) = kx_session_keys(
+            client.0.clone(),
+            client.1.clone(),
+            server.1.clone(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(expect_client_rx, hex(&client_rx));
+        assert_eq!(expect_client_tx, hex(&client_tx));
+
+        let (server_rx, server_tx) =
+            kx_session_keys(server.0, server.1, client.1, true)
+                .await
+                .unwrap();
+        assert_eq!(client_tx, server_rx);
+        assert_eq!(client_rx, server_tx);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_rejects_low_order_remote_pub() {
+        let client = keypair(
+            [0x77; 32],
+            "1cf579aba45a10ba1d1ef06d91fca2aa9ed0a1150515653155405d0b18cb9a67",
+        );
+        assert!(kx_session_keys(client.0, client.1, [0; 32].into(), false,)
+            .await
+            .is_err());
+    }
+}
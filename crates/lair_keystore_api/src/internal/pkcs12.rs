@@ -0,0 +1,373 @@
+//! PKCS#12 (.p12 / .pfx) bundles of a tls cert and its private key,
+//! as used by Java and Windows tooling.
+//!
+//! The private key is shrouded with pbeWithSHAAnd3-KeyTripleDES-CBC and
+//! the bundle authenticated with an HMAC-SHA1 mac (RFC 7292), the
+//! algorithms every PKCS#12 consumer (including OpenSSL 3 without its
+//! legacy provider) can read. The certificate itself is not secret and
+//! is stored unencrypted. Without a password the key is stored
+//! unencrypted too, and the bundle has no mac.
+
+use crate::*;
+use cbc::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit};
+use internal::util::SecretBytes;
+use yasna::models::ObjectIdentifier;
+use yasna::{DERWriter, Tag};
+
+/// Maximum byte length of a PKCS#12 bundle password.
+pub const MAX_PASSWORD_BYTES: usize = 128;
+
+/// Key derivation / mac iterations, matching OpenSSL's default.
+const ITERATIONS: u64 = 2048;
+
+const SALT_BYTES: usize = 8;
+
+// pkcs7 data
+const OID_DATA: &[u64] = &[1, 2, 840, 113_549, 1, 7, 1];
+// pkcs12 bag types
+const OID_KEY_BAG: &[u64] = &[1, 2, 840, 113_549, 1, 12, 10, 1, 1];
+const OID_SHROUDED_KEY_BAG: &[u64] = &[1, 2, 840, 113_549, 1, 12, 10, 1, 2];
+const OID_CERT_BAG: &[u64] = &[1, 2, 840, 113_549, 1, 12, 10, 1, 3];
+const OID_X509_CERT: &[u64] = &[1, 2, 840, 113_549, 1, 9, 22, 1];
+// bag attributes
+const OID_FRIENDLY_NAME: &[u64] = &[1, 2, 840, 113_549, 1, 9, 20];
+const OID_LOCAL_KEY_ID: &[u64] = &[1, 2, 840, 113_549, 1, 9, 21];
+// algorithms
+const OID_PBE_SHA1_3DES: &[u64] = &[1, 2, 840, 113_549, 1, 12, 1, 3];
+const OID_SHA1: &[u64] = &[1, 3, 14, 3, 2, 26];
+
+/// Validate a PKCS#12 bundle password.
+pub fn check_password(password: &Option<String>) -> LairResult<()> {
+    if let Some(password) = password {
+        if password.len() > MAX_PASSWORD_BYTES {
+            return Err(format!(
+                "pkcs12 password exceeds {} byte maximum",
+                MAX_PASSWORD_BYTES
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Bundle the DER encoded `cert_der` and pkcs8 `priv_key_der` as a DER
+/// encoded PKCS#12 PFX, named `friendly_name`, encrypted with `password`.
+pub async fn pkcs12_bundle(
+    cert_der: bytes::Bytes,
+    priv_key_der: SecretBytes,
+    friendly_name: String,
+    password: Option<String>,
+) -> LairResult<Arc<Vec<u8>>> {
+    check_password(&password)?;
+    rayon_exec(move || {
+        let password = password.as_deref().map(bmp_password);
+        let local_key_id = ring::digest::digest(
+            &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+            &cert_der,
+        );
+        let local_key_id = local_key_id.as_ref();
+
+        let key_bag = match &password {
+            Some(password) => {
+                let salt = random_salt()?;
+                let encrypted =
+                    pbe_sha1_3des_encrypt(password, &salt, &priv_key_der);
+                let value = yasna::construct_der(|w| {
+                    w.write_sequence(|w| {
+                        write_pbe_algorithm(w.next(), &salt);
+                        w.next().write_bytes(&encrypted);
+                    })
+                });
+                (OID_SHROUDED_KEY_BAG, value)
+            }
+            None => (OID_KEY_BAG, priv_key_der.to_vec()),
+        };
+        let cert_bag = yasna::construct_der(|w| {
+            w.write_sequence(|w| {
+                w.next().write_oid(&oid(OID_X509_CERT));
+                w.next().write_tagged(Tag::context(0), |w| {
+                    w.write_bytes(&cert_der)
+                });
+            })
+        });
+
+        let safe_contents = |bag_id: &[u64], value: &[u8]| {
+            yasna::construct_der(|w| {
+                w.write_sequence(|w| {
+                    write_safe_bag(
+                        w.next(),
+                        bag_id,
+                        value,
+                        &friendly_name,
+                        local_key_id,
+                    );
+                })
+            })
+        };
+        let auth_safe = yasna::construct_der(|w| {
+            w.write_sequence(|w| {
+                write_data_content_info(
+                    w.next(),
+                    &safe_contents(OID_CERT_BAG, &cert_bag),
+                );
+                write_data_content_info(
+                    w.next(),
+                    &safe_contents(key_bag.0, &key_bag.1),
+                );
+            })
+        });
+
+        let mac = match &password {
+            Some(password) => {
+                let salt = random_salt()?;
+                let key = pkcs12_kdf(password, &salt, ITERATIONS, 3, 20);
+                let key = ring::hmac::Key::new(
+                    ring::hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+                    &key,
+                );
+                Some((ring::hmac::sign(&key, &auth_safe), salt))
+            }
+            None => None,
+        };
+
+        Ok(Arc::new(yasna::construct_der(|w| {
+            w.write_sequence(|w| {
+                w.next().write_u8(3);
+                write_data_content_info(w.next(), &auth_safe);
+                if let Some((tag, salt)) = &mac {
+                    w.next().write_sequence(|w| {
+                        w.next().write_sequence(|w| {
+                            w.next().write_sequence(|w| {
+                                w.next().write_oid(&oid(OID_SHA1));
+                                w.next().write_null();
+                            });
+                            w.next().write_bytes(tag.as_ref());
+                        });
+                        w.next().write_bytes(salt);
+                        w.next().write_u64(ITERATIONS);
+                    });
+                }
+            })
+        })))
+    })
+    .await
+}
+
+fn oid(components: &[u64]) -> ObjectIdentifier {
+    ObjectIdentifier::from_slice(components)
+}
+
+fn random_salt() -> LairResult<[u8; SALT_BYTES]> {
+    let mut salt = [0; SALT_BYTES];
+    ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut salt)
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(salt)
+}
+
+/// A pkcs7 `data` ContentInfo wrapping `content`.
+fn write_data_content_info(w: DERWriter, content: &[u8]) {
+    w.write_sequence(|w| {
+        w.next().write_oid(&oid(OID_DATA));
+        w.next()
+            .write_tagged(Tag::context(0), |w| w.write_bytes(content));
+    })
+}
+
+fn write_safe_bag(
+    w: DERWriter,
+    bag_id: &[u64],
+    value: &[u8],
+    friendly_name: &str,
+    local_key_id: &[u8],
+) {
+    w.write_sequence(|w| {
+        w.next().write_oid(&oid(bag_id));
+        w.next()
+            .write_tagged(Tag::context(0), |w| w.write_der(value));
+        w.next().write_set_of(|w| {
+            w.next().write_sequence(|w| {
+                w.next().write_oid(&oid(OID_FRIENDLY_NAME));
+                w.next()
+                    .write_set(|w| w.next().write_bmp_string(friendly_name));
+            });
+            w.next().write_sequence(|w| {
+                w.next().write_oid(&oid(OID_LOCAL_KEY_ID));
+                w.next().write_set(|w| w.next().write_bytes(local_key_id));
+            });
+        });
+    })
+}
+
+fn write_pbe_algorithm(w: DERWriter, salt: &[u8]) {
+    w.write_sequence(|w| {
+        w.next().write_oid(&oid(OID_PBE_SHA1_3DES));
+        w.next().write_sequence(|w| {
+            w.next().write_bytes(salt);
+            w.next().write_u64(ITERATIONS);
+        });
+    })
+}
+
+/// The password as a null terminated big endian utf16 string.
+fn bmp_password(password: &str) -> Vec<u8> {
+    password
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(|c| c.to_be_bytes().to_vec())
+        .collect()
+}
+
+fn pbe_sha1_3des_encrypt(password: &[u8], salt: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = pkcs12_kdf(password, salt, ITERATIONS, 1, 24);
+    let iv = pkcs12_kdf(password, salt, ITERATIONS, 2, 8);
+    cbc::Encryptor::<des::TdesEde3>::new_from_slices(&key, &iv)
+        .expect("3des key and iv lengths")
+        .encrypt_padded_vec_mut::<Pkcs7>(data)
+}
+
+/// The PKCS#12 key derivation function (RFC 7292 appendix B) over sha1,
+/// `id` 1 derives encryption keys, 2 ivs and 3 mac keys.
+fn pkcs12_kdf(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u64,
+    id: u8,
+    len: usize,
+) -> Vec<u8> {
+    const U: usize = 20; // sha1 output bytes
+    const V: usize = 64; // sha1 block bytes
+
+    let fill = |src: &[u8]| -> Vec<u8> {
+        let len = V * src.len().div_ceil(V);
+        src.iter().cycle().take(len).copied().collect()
+    };
+    let d = [id; V];
+    let mut i = fill(salt);
+    i.extend(fill(password));
+
+    let mut out = Vec::with_capacity(len + U);
+    while out.len() < len {
+        let mut a = {
+            let mut ctx = ring::digest::Context::new(
+                &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+            );
+            ctx.update(&d);
+            ctx.update(&i);
+            ctx.finish()
+        };
+        for _ in 1..iterations {
+            a = ring::digest::digest(
+                &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+                a.as_ref(),
+            );
+        }
+        out.extend_from_slice(a.as_ref());
+
+        // I_j = (I_j + B + 1) mod 2^(v*8), for each v byte block of I
+        let b = fill(a.as_ref());
+        for block in i.chunks_mut(V) {
+            let mut carry = 1_u16;
+            for (x, y) in block.iter_mut().zip(b.iter()).rev() {
+                let sum = *x as u16 + *y as u16 + carry;
+                *x = sum as u8;
+                carry = sum >> 8;
+            }
+        }
+    }
+    out.truncate(len);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn it_matches_pkcs12_kdf_vectors() {
+        let smeg = bmp_password("smeg");
+        let salt = [0x0a, 0x58, 0xcf, 0x64, 0x53, 0x0d, 0x82, 0x3f];
+        assert_eq!(
+            "8aaae6297b6cb04642ab5b077851284eb7128f1a2a7fbca3",
+            hex(&pkcs12_kdf(&smeg, &salt, 1, 1, 24)),
+        );
+        assert_eq!("79993dfe048d3b76", hex(&pkcs12_kdf(&smeg, &salt, 1, 2, 8)));
+        let salt = [0x3d, 0x83, 0xc0, 0xe4, 0x54, 0x6a, 0xc1, 0x40];
+        assert_eq!(
+            "8d967d88f6caa9d714800ab3d48051d63f73a312",
+            hex(&pkcs12_kdf(&smeg, &salt, 1, 3, 20)),
+        );
+        let queeg = bmp_password("queeg");
+        let salt = [0x16, 0x82, 0xc0, 0xfc, 0x5b, 0x3f, 0x7e, 0xc5];
+        assert_eq!(
+            "483dd6e919d7de2e8e648ba8f862f3fbfbdc2bcb2c02957f",
+            hex(&pkcs12_kdf(&queeg, &salt, 1000, 1, 24)),
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_round_trips_through_an_independent_parser() {
+        let cert = internal::tls::tls_cert_self_signed_new_from_entropy(
+            actor::TlsCertOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let p12 = pkcs12_bundle(
+            cert.cert_der.0.clone(),
+            cert.priv_key_der.0.clone(),
+            cert.sni.to_string(),
+            Some("pässword".to_string()),
+        )
+        .await
+        .unwrap();
+        let pfx = p12::PFX::parse(&p12).unwrap();
+        assert!(pfx.verify_mac("pässword"));
+        assert!(!pfx.verify_mac("password"));
+        assert_eq!(
+            vec![cert.cert_der.to_vec()],
+            pfx.cert_x509_bags("pässword").unwrap()
+        );
+        assert_eq!(
+            vec![cert.priv_key_der.to_vec()],
+            pfx.key_bags("pässword").unwrap()
+        );
+
+        // without a password the key is in a plain key bag
+        let p12 = pkcs12_bundle(
+            cert.cert_der.0.clone(),
+            cert.priv_key_der.0.clone(),
+            cert.sni.to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+        let pfx = p12::PFX::parse(&p12).unwrap();
+        assert!(pfx.mac_data.is_none());
+        let bags = pfx.bags("").unwrap();
+        assert_eq!(2, bags.len());
+        assert_eq!(Some(cert.cert_der.to_vec()), bags[0].bag.get_x509_cert());
+        match &bags[1].bag {
+            p12::SafeBagKind::OtherBagKind(bag) => {
+                assert_eq!(
+                    oid(OID_KEY_BAG).to_string(),
+                    bag.bag_id.to_string()
+                );
+                assert_eq!(cert.priv_key_der.to_vec(), bag.bag_value);
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+
+        assert!(pkcs12_bundle(
+            cert.cert_der.0.clone(),
+            cert.priv_key_der.0.clone(),
+            cert.sni.to_string(),
+            Some("p".repeat(MAX_PASSWORD_BYTES + 1)),
+        )
+        .await
+        .is_err());
+    }
+}
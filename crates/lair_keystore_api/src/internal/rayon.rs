@@ -1,13 +1,15 @@
 //! internal static globals
 
 use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 /// This is an Arc to make it easy to initialize things like sodoken.
 static RAYON: OnceCell<Arc<rayon::ThreadPool>> = OnceCell::new();
 
 /// Call this function before any other lair api if you wish to initialize
-/// with a custom rayon pool. A default pool will be created if not.
+/// with a custom rayon pool. A default pool will be created if not
+/// (see `new_crypto_thread_pool()` and `default_crypto_threads()`).
 /// Returns true if the lair rayon pool was previously uninitialized
 /// and now holds the pool that was passed in to this function.
 ///
@@ -30,30 +32,53 @@ where
     did_init
 }
 
+/// The crypto thread count of servers by default
+/// (see `ConfigBuilder::set_crypto_threads()`): the number of cpus,
+/// at most 4, leaving the rest to tokio and the embedding application.
+pub fn default_crypto_threads() -> usize {
+    std::cmp::min(num_cpus::get(), 4)
+}
+
+/// Build a pool of `threads` threads (at least one) for lair's crypto
+/// work, named `lair-crypto-N`, as `init_once_rayon_thread_pool()`
+/// takes it.
+pub fn new_crypto_thread_pool(threads: usize) -> Arc<rayon::ThreadPool> {
+    Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(std::cmp::max(1, threads))
+            .thread_name(|i| format!("lair-crypto-{}", i))
+            .build()
+            .expect("failed to build rayon thread pool"),
+    )
+}
+
 fn get_rayon() -> &'static Arc<rayon::ThreadPool> {
-    RAYON.get_or_init(|| {
-        // as we're looking to provide fairly consistant experience on
-        // potentially high-throughput workload, we try to balance
-        // between os support for time slicing on low cpu count systems
-        // and less context switching overhead on high cpu-count systems
-        // (with the assumption that tokio is also running threads)
-        const THREAD_MIN: usize = 4;
-        const THREAD_MAX: usize = 8;
-        let thread_count = std::cmp::min(
-            THREAD_MIN, // don't go below this thread count
-            std::cmp::max(
-                THREAD_MAX,      // don't go above this thread count
-                num_cpus::get(), // otherwise use the number of cpus
-            ),
-        );
-
-        Arc::new(
-            rayon::ThreadPoolBuilder::new()
-                .num_threads(thread_count)
-                .build()
-                .expect("failed to build rayon thread pool"),
-        )
-    })
+    RAYON.get_or_init(|| new_crypto_thread_pool(default_crypto_threads()))
+}
+
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static BUSY: AtomicUsize = AtomicUsize::new(0);
+
+/// A snapshot of the crypto work of this process (see
+/// `crypto_pool_stats()`), to size the crypto thread pool by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CryptoPoolStats {
+    /// The threads of the pool.
+    pub threads: usize,
+    /// The tasks waiting for a thread.
+    pub queue_depth: usize,
+    /// The threads working on a task.
+    pub busy_threads: usize,
+}
+
+/// Get the crypto pool stats of this process. A queue that does not
+/// drain while all threads are busy calls for more threads.
+pub fn crypto_pool_stats() -> CryptoPoolStats {
+    CryptoPoolStats {
+        threads: get_rayon().current_num_threads(),
+        queue_depth: QUEUED.load(Ordering::Relaxed),
+        busy_threads: BUSY.load(Ordering::Relaxed),
+    }
 }
 
 /// Executes `f` on the rayon thread pool and awaits the result.
@@ -63,9 +88,60 @@ where
     F: 'static + Send + FnOnce() -> T,
 {
     let (s, r) = tokio::sync::oneshot::channel();
+    QUEUED.fetch_add(1, Ordering::Relaxed);
     get_rayon().spawn(move || {
+        QUEUED.fetch_sub(1, Ordering::Relaxed);
+        BUSY.fetch_add(1, Ordering::Relaxed);
+        // rayon aborts on panics, the count cannot be left behind
         let result = f();
+        BUSY.fetch_sub(1, Ordering::Relaxed);
         let _ = s.send(result);
     });
     r.await.expect("threadpool task shutdown prematurely")
 }
+
+/// Payloads smaller than this many bytes are worked on inline by default,
+/// see `set_inline_crypto_threshold`.
+pub const DEFAULT_INLINE_CRYPTO_THRESHOLD: usize = 4096;
+
+static INLINE_CRYPTO_THRESHOLD: AtomicUsize =
+    AtomicUsize::new(DEFAULT_INLINE_CRYPTO_THRESHOLD);
+
+/// Set the payload size (in bytes) below which cheap crypto (signing and
+/// verifying, boxing and opening, pub key derivations) runs inline on
+/// the calling task, where handing it to the rayon pool and back would
+/// cost more than the work itself. 0 sends everything to the pool.
+/// The first server of a process sets this from
+/// `Config::get_inline_crypto_threshold()`.
+/// Expensive work (argon2, keypair generation) always goes to the pool.
+pub fn set_inline_crypto_threshold(bytes: usize) {
+    INLINE_CRYPTO_THRESHOLD.store(bytes, Ordering::Relaxed);
+}
+
+/// Executes `f`, working on `len` bytes of payload, inline if that is
+/// below the inline crypto threshold, on the rayon thread pool otherwise.
+pub(crate) async fn rayon_exec_sized<T, F>(len: usize, f: F) -> T
+where
+    T: 'static + Send,
+    F: 'static + Send + FnOnce() -> T,
+{
+    if len < INLINE_CRYPTO_THRESHOLD.load(Ordering::Relaxed) {
+        return f();
+    }
+    rayon_exec(f).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_small_payloads_are_worked_on_inline() {
+        let caller = std::thread::current().id();
+        let worker =
+            |len| rayon_exec_sized(len, || std::thread::current().id());
+        assert_eq!(caller, worker(0).await);
+        assert_eq!(caller, worker(DEFAULT_INLINE_CRYPTO_THRESHOLD - 1).await);
+        assert_ne!(caller, worker(DEFAULT_INLINE_CRYPTO_THRESHOLD).await);
+    }
+}
@@ -0,0 +1,372 @@
+//! Stored symmetric secret keys, for XChaCha20-Poly1305 authenticated
+//! encryption where the key never leaves lair.
+//! Ciphertexts are the encrypted data followed by the 16 byte mac,
+//! exactly as libsodium's `crypto_aead_xchacha20poly1305_ietf_encrypt`.
+
+use crate::*;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use derive_more::*;
+use internal::util::SecretBytes;
+
+/// Byte length of secret keys.
+pub const SECRET_KEY_BYTES: usize = 32;
+
+/// Byte length of the random nonce generated for each encryption.
+pub const NONCE_BYTES: usize = 24;
+
+/// Bytes of mac appended to each ciphertext.
+pub const MAC_BYTES: usize = 16;
+
+/// Minimum byte length of a derived subkey.
+pub const SUBKEY_MIN_BYTES: usize = 16;
+
+/// Maximum byte length of a derived subkey.
+pub const SUBKEY_MAX_BYTES: usize = 64;
+
+/// Byte length of the context subkeys are derived in.
+pub const SUBKEY_CONTEXT_BYTES: usize = 8;
+
+/// The private bytes of a secret key.
+#[derive(Clone, Debug, PartialEq, Eq, Deref, From, Into)]
+pub struct SecretKey(pub SecretBytes);
+
+impl From<Vec<u8>> for SecretKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(d.into())
+    }
+}
+
+/// The 32 byte public identifier of a secret key.
+/// (A hash of the key, it reveals nothing about the key.)
+#[derive(Clone, Debug, PartialOrd, Ord, Deref, From, Into)]
+#[allow(clippy::rc_buffer)]
+pub struct SecretKeyTag(pub Arc<Vec<u8>>);
+
+internal::util::impl_ct_eq!(SecretKeyTag);
+
+impl From<Vec<u8>> for SecretKeyTag {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// The nonce an encryption was made with,
+/// needed (along with the aad) to decrypt it.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SecretNonce(pub [u8; NONCE_BYTES]);
+
+/// Compute the public identifier of a secret key.
+pub fn secret_key_tag(secret_key: &[u8]) -> SecretKeyTag {
+    blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"lair-secret-key")
+        .hash(secret_key)
+        .as_bytes()
+        .to_vec()
+        .into()
+}
+
+/// Generate a new random secret key.
+pub async fn secret_key_new_from_entropy() -> LairResult<entry::EntrySecretKey>
+{
+    rayon_exec(move || {
+        let mut secret_key = zeroize::Zeroizing::new(vec![0; SECRET_KEY_BYTES]);
+        fill_random(&mut secret_key)?;
+        Ok(entry::EntrySecretKey::new(
+            SecretBytes::from(secret_key).into(),
+        ))
+    })
+    .await
+}
+
+/// Encrypt data under a secret key, authenticating (but not encrypting)
+/// `aad` along with it. Every call uses a fresh random nonce.
+#[allow(clippy::rc_buffer)]
+pub async fn secret_encrypt(
+    secret_key: SecretKey,
+    aad: Arc<Vec<u8>>,
+    data: Arc<Vec<u8>>,
+) -> LairResult<(SecretNonce, Arc<Vec<u8>>)> {
+    rayon_exec(move || {
+        let mut nonce = [0; NONCE_BYTES];
+        fill_random(&mut nonce)?;
+        let ciphertext = cipher(&secret_key)?
+            .encrypt(
+                (&nonce).into(),
+                Payload {
+                    msg: &data,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| LairError::from("secret key encryption failed"))?;
+        Ok((SecretNonce(nonce), Arc::new(ciphertext)))
+    })
+    .await
+}
+
+/// Decrypt data encrypted by `secret_encrypt`. Resolves to `None` if the
+/// ciphertext was not encrypted under this key with this nonce and aad,
+/// or has been tampered with.
+#[allow(clippy::rc_buffer)]
+pub async fn secret_decrypt(
+    secret_key: SecretKey,
+    aad: Arc<Vec<u8>>,
+    nonce: SecretNonce,
+    ciphertext: Arc<Vec<u8>>,
+) -> LairResult<Option<Arc<Vec<u8>>>> {
+    rayon_exec(move || {
+        match cipher(&secret_key)?.decrypt(
+            (&nonce.0).into(),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        ) {
+            Ok(data) => Ok(Some(Arc::new(data))),
+            Err(_) => Ok(None),
+        }
+    })
+    .await
+}
+
+/// Fails with `LairError::SubkeyLength` unless
+/// `SUBKEY_MIN_BYTES <= subkey_len <= SUBKEY_MAX_BYTES`.
+pub fn check_subkey_len(subkey_len: usize) -> LairResult<()> {
+    if !(SUBKEY_MIN_BYTES..=SUBKEY_MAX_BYTES).contains(&subkey_len) {
+        return Err(LairError::SubkeyLength(subkey_len));
+    }
+    Ok(())
+}
+
+/// Derive the `subkey_id`th subkey of a 32 byte key (a secret key or a
+/// seed) in `context`, bit for bit as libsodium's
+/// `crypto_kdf_derive_from_key`: blake2b keyed by the key, with the
+/// subkey id (little endian) as salt and the context as personal.
+/// Deterministic, and subkeys reveal nothing about the key or each other.
+pub async fn derive_subkey(
+    key: SecretBytes,
+    subkey_len: usize,
+    context: [u8; SUBKEY_CONTEXT_BYTES],
+    subkey_id: u64,
+) -> LairResult<Arc<Vec<u8>>> {
+    check_subkey_len(subkey_len)?;
+    if key.len() != SECRET_KEY_BYTES {
+        return Err(LairError::wrong_length(
+            "subkey derivation key",
+            SECRET_KEY_BYTES,
+            key.len(),
+        ));
+    }
+    rayon_exec(move || {
+        Ok(Arc::new(
+            blake2b_simd::Params::new()
+                .hash_length(subkey_len)
+                .key(&key)
+                .salt(&subkey_id.to_le_bytes())
+                .personal(&context)
+                .hash(&[])
+                .as_bytes()
+                .to_vec(),
+        ))
+    })
+    .await
+}
+
+fn cipher(
+    secret_key: &SecretKey,
+) -> LairResult<chacha20poly1305::XChaCha20Poly1305> {
+    if secret_key.len() != SECRET_KEY_BYTES {
+        return Err(LairError::wrong_length(
+            "secret key",
+            SECRET_KEY_BYTES,
+            secret_key.len(),
+        ));
+    }
+    Ok(chacha20poly1305::XChaCha20Poly1305::new(
+        chacha20poly1305::Key::from_slice(secret_key),
+    ))
+}
+
+fn fill_random(buf: &mut [u8]) -> LairResult<()> {
+    let sys_rand = ring::rand::SystemRandom::new();
+    ring::rand::SecureRandom::fill(&sys_rand, buf)
+        .map_err(|e| format!("{:?}", e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// draft-irtf-cfrg-xchacha-03 A.3.1, which libsodium also produces.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_xchacha20poly1305_test_vector() {
+        let secret_key = SecretKey::from((0x80..0xa0).collect::<Vec<u8>>());
+        let mut nonce = [0; NONCE_BYTES];
+        nonce.copy_from_slice(&(0x40..0x58).collect::<Vec<u8>>());
+        let aad = Arc::new(unhex("50515253c0c1c2c3c4c5c6c7"));
+        let data = b"Ladies and Gentlemen of the class of '99: If I could \
+            offer you only one tip for the future, sunscreen would be it."
+            .to_vec();
+        let ciphertext = unhex(
+            "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+            731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+            2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+            21f9664c97637da9768812f615c68b13b52ec0875924c1c7987947deafd8780a\
+            cf49",
+        );
+        let encrypted = cipher(&secret_key)
+            .unwrap()
+            .encrypt(
+                (&nonce).into(),
+                Payload {
+                    msg: &data,
+                    aad: &aad,
+                },
+            )
+            .unwrap();
+        assert_eq!(ciphertext, encrypted);
+        assert_eq!(
+            Some(Arc::new(data)),
+            secret_decrypt(secret_key, aad, nonce.into(), Arc::new(ciphertext))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_encrypt_and_decrypt() {
+        let entry = secret_key_new_from_entropy().await.unwrap();
+        assert_eq!(SECRET_KEY_BYTES, entry.secret_key.len());
+        assert_eq!(secret_key_tag(&entry.secret_key), entry.tag);
+        let other = secret_key_new_from_entropy().await.unwrap();
+        let key = entry.secret_key;
+
+        let aad = Arc::new(b"aad".to_vec());
+        for data in [vec![], vec![0x42], vec![0x42; 1000]] {
+            let data = Arc::new(data);
+            let (nonce, ciphertext) =
+                secret_encrypt(key.clone(), aad.clone(), data.clone())
+                    .await
+                    .unwrap();
+            assert_eq!(data.len() + MAC_BYTES, ciphertext.len());
+
+            // fresh nonce every time
+            let (nonce2, _) =
+                secret_encrypt(key.clone(), aad.clone(), data.clone())
+                    .await
+                    .unwrap();
+            assert_ne!(nonce, nonce2);
+
+            let decrypt = |key: SecretKey, aad: &[u8], nonce, ciphertext| {
+                secret_decrypt(key, Arc::new(aad.to_vec()), nonce, ciphertext)
+            };
+            assert_eq!(
+                Some(data.clone()),
+                decrypt(key.clone(), &aad, nonce, ciphertext.clone())
+                    .await
+                    .unwrap()
+            );
+
+            // wrong key, aad or nonce, or tampering, just don't decrypt
+            assert_eq!(
+                None,
+                decrypt(
+                    other.secret_key.clone(),
+                    &aad,
+                    nonce,
+                    ciphertext.clone()
+                )
+                .await
+                .unwrap()
+            );
+            assert_eq!(
+                None,
+                decrypt(key.clone(), b"aae", nonce, ciphertext.clone())
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                None,
+                decrypt(key.clone(), b"", nonce, ciphertext.clone())
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                None,
+                decrypt(key.clone(), &aad, nonce2, ciphertext.clone())
+                    .await
+                    .unwrap()
+            );
+            let mut tampered = ciphertext.to_vec();
+            tampered[0] ^= 1;
+            assert_eq!(
+                None,
+                decrypt(key.clone(), &aad, nonce, Arc::new(tampered))
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                None,
+                decrypt(key.clone(), &aad, nonce, Arc::new(vec![0; 15]))
+                    .await
+                    .unwrap()
+            );
+        }
+
+        assert!(secret_encrypt(vec![0; 16].into(), aad.clone(), aad.clone())
+            .await
+            .is_err());
+    }
+
+    /// Made with libsodium's `crypto_kdf_derive_from_key`.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_crypto_kdf_vectors() {
+        let key = SecretBytes::from((0..32).collect::<Vec<u8>>());
+        let context = *b"KDF test";
+        for (subkey_len, subkey_id, subkey) in [
+            (16, 0, "e9136a52b9690eb4df4e9665e819a6d3"),
+            (
+                32,
+                1,
+                "13fea52bb8cba063f3ed93de27ed07e06d8c6367474e6ae4c9282913ac3c3a03",
+            ),
+            (
+                64,
+                2,
+                "1944da61ff18dc2028c3578ac85be904931b83860896598f62468f1cb5471c6a\
+                 344c945dbc62c9aaf70feb62472d17775ea5db6ed5494c68b7a9a59761f39614",
+            ),
+            (
+                32,
+                0x0123_4567_89ab_cdef,
+                "2d72e9b2536083250780bc14e1b0f6a5f21eb7af90f62f5ded9f80f6867e5396",
+            ),
+        ] {
+            assert_eq!(
+                unhex(subkey),
+                *derive_subkey(key.clone(), subkey_len, context, subkey_id)
+                    .await
+                    .unwrap()
+            );
+        }
+
+        for subkey_len in [0, SUBKEY_MIN_BYTES - 1, SUBKEY_MAX_BYTES + 1] {
+            assert!(matches!(
+                derive_subkey(key.clone(), subkey_len, context, 0).await,
+                Err(LairError::SubkeyLength(l)) if l == subkey_len
+            ));
+        }
+        assert!(derive_subkey(vec![0; 16].into(), 32, context, 0)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,672 @@
+//! Secretstream sessions, for encrypting data too large to send to lair
+//! in one message as a stream of authenticated chunks.
+//! The stream format is exactly libsodium's
+//! `crypto_secretstream_xchacha20poly1305` (without additional data), so
+//! sodium peers can push or pull the other end of a stream.
+//! The stream key is the `crypto_box_beforenm` shared key of a stored
+//! x25519 keypair and the remote pub key, it never leaves lair.
+//! Session state is held by lair between chunks, sessions are dropped
+//! on their final chunk, on close, or after sitting idle too long.
+
+use crate::*;
+use chacha20::cipher::{
+    NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek,
+};
+use derive_more::*;
+use internal::x25519;
+use poly1305::universal_hash::NewUniversalHash;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Byte length of the header that starts every stream.
+pub const HEADER_BYTES: usize = 24;
+
+/// Bytes each encrypted chunk is longer than the data pushed.
+pub const ABYTES: usize = 1 + MAC_BYTES;
+
+/// The most data that can be pushed as one chunk.
+pub const CHUNK_MAX_BYTES: usize = 64 * 1024;
+
+/// Sessions left untouched this long are dropped.
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The most sessions that can be open at once.
+pub const SESSIONS_MAX: usize = 1024;
+
+const KEY_BYTES: usize = 32;
+const MAC_BYTES: usize = 16;
+const INONCE_BYTES: usize = 8;
+const COUNTER_BYTES: usize = 4;
+
+const TAG_MESSAGE: u8 = 0;
+const TAG_REKEY: u8 = 2;
+const TAG_FINAL: u8 = 3;
+
+/// Identifies an open secretstream session.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SecretstreamSessionId(pub [u8; 32]);
+
+/// The header a stream was pushed with,
+/// needed to start pulling it.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+pub struct SecretstreamHeader(pub [u8; HEADER_BYTES]);
+
+/// The error for sessions that don't exist (any more),
+/// or belong to another connection.
+pub fn secretstream_unknown_session() -> LairError {
+    "unknown secretstream session".into()
+}
+
+/// Check the size of a chunk of data to push.
+pub fn check_chunk_len(len: usize) -> LairResult<()> {
+    if len > CHUNK_MAX_BYTES {
+        return Err(format!(
+            "secretstream chunk of {} bytes exceeds {} bytes",
+            len, CHUNK_MAX_BYTES,
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Check the size of an encrypted chunk to pull.
+pub fn check_ciphertext_len(len: usize) -> LairResult<()> {
+    check_chunk_len(len.saturating_sub(ABYTES))
+}
+
+/// The open secretstream sessions of a keystore.
+#[derive(Clone)]
+pub struct SecretstreamSessions(Arc<tokio::sync::Mutex<SessionsInner>>);
+
+struct SessionsInner {
+    idle_timeout: Duration,
+    sessions: HashMap<SecretstreamSessionId, SessionEntry>,
+}
+
+struct SessionEntry {
+    is_push: bool,
+    last_used: Instant,
+    session: Arc<tokio::sync::Mutex<Session>>,
+}
+
+struct Session {
+    state: State,
+    done: bool,
+}
+
+impl Default for SecretstreamSessions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretstreamSessions {
+    /// No sessions yet, idle sessions are dropped after
+    /// `SESSION_IDLE_TIMEOUT`.
+    pub fn new() -> Self {
+        Self::with_idle_timeout(SESSION_IDLE_TIMEOUT)
+    }
+
+    /// No sessions yet, idle sessions are dropped after `idle_timeout`.
+    pub fn with_idle_timeout(idle_timeout: Duration) -> Self {
+        Self(Arc::new(tokio::sync::Mutex::new(SessionsInner {
+            idle_timeout,
+            sessions: HashMap::new(),
+        })))
+    }
+
+    /// Start pushing a new stream to the holder of `remote_pub`.
+    /// The header must reach them to pull it.
+    pub async fn init_push(
+        &self,
+        priv_key: x25519::X25519PrivKey,
+        remote_pub: x25519::X25519PubKey,
+    ) -> LairResult<(SecretstreamSessionId, SecretstreamHeader)> {
+        let (state, header) = rayon_exec(move || {
+            let key = shared_key(&priv_key, &remote_pub)?;
+            let mut header = [0; HEADER_BYTES];
+            fill_random(&mut header)?;
+            LairResult::Ok((State::new(&key, &header), header))
+        })
+        .await?;
+        let session_id = self.insert(state, true).await?;
+        Ok((session_id, SecretstreamHeader(header)))
+    }
+
+    /// Start pulling a stream pushed by the holder of `remote_pub`.
+    pub async fn init_pull(
+        &self,
+        priv_key: x25519::X25519PrivKey,
+        remote_pub: x25519::X25519PubKey,
+        header: SecretstreamHeader,
+    ) -> LairResult<SecretstreamSessionId> {
+        let state = rayon_exec(move || {
+            let key = shared_key(&priv_key, &remote_pub)?;
+            LairResult::Ok(State::new(&key, &header))
+        })
+        .await?;
+        self.insert(state, false).await
+    }
+
+    /// Encrypt the next chunk of a stream.
+    /// The session is closed after the final chunk.
+    #[allow(clippy::rc_buffer)]
+    pub async fn push(
+        &self,
+        session_id: SecretstreamSessionId,
+        chunk: Arc<Vec<u8>>,
+        is_final: bool,
+    ) -> LairResult<Arc<Vec<u8>>> {
+        check_chunk_len(chunk.len())?;
+        let mut session = self.get(session_id, true).await?.lock_owned().await;
+        if session.done {
+            return Err(secretstream_unknown_session());
+        }
+        let ciphertext = rayon_exec(move || {
+            let tag = if is_final { TAG_FINAL } else { TAG_MESSAGE };
+            session.done = is_final;
+            session.state.push(&chunk, tag)
+        })
+        .await;
+        if is_final {
+            self.close(session_id).await;
+        }
+        Ok(Arc::new(ciphertext))
+    }
+
+    /// Decrypt the next chunk of a stream, along with whether it was the
+    /// final chunk (the session is closed after it).
+    /// Resolves to `None` if the chunk was not the next one pushed to
+    /// this stream, or has been tampered with. The session can carry on
+    /// pulling the right chunk.
+    #[allow(clippy::rc_buffer)]
+    pub async fn pull(
+        &self,
+        session_id: SecretstreamSessionId,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairResult<Option<(Arc<Vec<u8>>, bool)>> {
+        check_ciphertext_len(ciphertext.len())?;
+        let mut session = self.get(session_id, false).await?.lock_owned().await;
+        if session.done {
+            return Err(secretstream_unknown_session());
+        }
+        let data = rayon_exec(move || {
+            let (data, tag) = session.state.pull(&ciphertext)?;
+            let is_final = tag == TAG_FINAL;
+            session.done = is_final;
+            Some((Arc::new(data), is_final))
+        })
+        .await;
+        if let Some((_, true)) = data {
+            self.close(session_id).await;
+        }
+        Ok(data)
+    }
+
+    /// Drop a session, whether or not its stream was finished.
+    pub async fn close(&self, session_id: SecretstreamSessionId) {
+        self.0.lock().await.sessions.remove(&session_id);
+    }
+
+    async fn insert(
+        &self,
+        state: State,
+        is_push: bool,
+    ) -> LairResult<SecretstreamSessionId> {
+        let mut session_id = [0; 32];
+        fill_random(&mut session_id)?;
+        let session_id = SecretstreamSessionId(session_id);
+        let mut inner = self.0.lock().await;
+        inner.prune();
+        if inner.sessions.len() >= SESSIONS_MAX {
+            return Err("too many open secretstream sessions".into());
+        }
+        inner.sessions.insert(
+            session_id,
+            SessionEntry {
+                is_push,
+                last_used: Instant::now(),
+                session: Arc::new(tokio::sync::Mutex::new(Session {
+                    state,
+                    done: false,
+                })),
+            },
+        );
+        Ok(session_id)
+    }
+
+    async fn get(
+        &self,
+        session_id: SecretstreamSessionId,
+        is_push: bool,
+    ) -> LairResult<Arc<tokio::sync::Mutex<Session>>> {
+        let mut inner = self.0.lock().await;
+        inner.prune();
+        match inner.sessions.get_mut(&session_id) {
+            Some(entry) if entry.is_push == is_push => {
+                entry.last_used = Instant::now();
+                Ok(entry.session.clone())
+            }
+            _ => Err(secretstream_unknown_session()),
+        }
+    }
+}
+
+impl SessionsInner {
+    fn prune(&mut self) {
+        let idle_timeout = self.idle_timeout;
+        self.sessions
+            .retain(|_, entry| entry.last_used.elapsed() < idle_timeout);
+    }
+}
+
+/// `crypto_box_beforenm`: hsalsa20 of the x25519 shared secret.
+fn shared_key(
+    priv_key: &x25519::X25519PrivKey,
+    remote_pub: &x25519::X25519PubKey,
+) -> LairResult<zeroize::Zeroizing<[u8; KEY_BYTES]>> {
+    let shared = x25519::x25519_shared_secret(priv_key, remote_pub)?;
+    let mut key = zeroize::Zeroizing::new([0; KEY_BYTES]);
+    key.copy_from_slice(&salsa20::hsalsa20(
+        (&*shared).into(),
+        (&[0; 16]).into(),
+    ));
+    Ok(key)
+}
+
+fn fill_random(buf: &mut [u8]) -> LairResult<()> {
+    let sys_rand = ring::rand::SystemRandom::new();
+    ring::rand::SecureRandom::fill(&sys_rand, buf)
+        .map_err(|e| format!("{:?}", e).into())
+}
+
+/// The chacha20 core as a hash, derives the stream subkey.
+pub(crate) fn hchacha20(
+    key: &[u8; KEY_BYTES],
+    input: &[u8; 16],
+) -> [u8; KEY_BYTES] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&[
+        0x6170_7865,
+        0x3320_646e,
+        0x7962_2d32,
+        0x6b20_6574,
+    ]);
+    for (i, word) in key.chunks_exact(4).enumerate() {
+        state[4 + i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for (i, word) in input.chunks_exact(4).enumerate() {
+        state[12 + i] =
+            u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    fn quarter_round(
+        s: &mut [u32; 16],
+        a: usize,
+        b: usize,
+        c: usize,
+        d: usize,
+    ) {
+        s[a] = s[a].wrapping_add(s[b]);
+        s[d] = (s[d] ^ s[a]).rotate_left(16);
+        s[c] = s[c].wrapping_add(s[d]);
+        s[b] = (s[b] ^ s[c]).rotate_left(12);
+        s[a] = s[a].wrapping_add(s[b]);
+        s[d] = (s[d] ^ s[a]).rotate_left(8);
+        s[c] = s[c].wrapping_add(s[d]);
+        s[b] = (s[b] ^ s[c]).rotate_left(7);
+    }
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    let mut out = [0; KEY_BYTES];
+    for (i, word) in state[..4].iter().chain(&state[12..]).enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    zeroize::Zeroize::zeroize(&mut state);
+    out
+}
+
+/// `crypto_secretstream_xchacha20poly1305_state`.
+struct State {
+    key: [u8; KEY_BYTES],
+    /// counter (little endian) || inonce
+    nonce: [u8; COUNTER_BYTES + INONCE_BYTES],
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.key);
+        zeroize::Zeroize::zeroize(&mut self.nonce);
+    }
+}
+
+impl State {
+    fn new(key: &[u8; KEY_BYTES], header: &[u8; HEADER_BYTES]) -> Self {
+        let mut input = [0; 16];
+        input.copy_from_slice(&header[..16]);
+        let mut nonce = [0; COUNTER_BYTES + INONCE_BYTES];
+        nonce[COUNTER_BYTES..].copy_from_slice(&header[16..]);
+        let mut out = Self {
+            key: hchacha20(key, &input),
+            nonce,
+        };
+        out.reset_counter();
+        out
+    }
+
+    fn push(&mut self, data: &[u8], tag: u8) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + ABYTES);
+        let mut block = [0; 64];
+        block[0] = tag;
+        self.xor(1, &mut block);
+        out.push(block[0]);
+        out.extend_from_slice(data);
+        self.xor(2, &mut out[1..]);
+        let mac = self.mac(&block, &out[1..]);
+        out.extend_from_slice(&mac);
+        self.advance(&mac, tag);
+        out
+    }
+
+    fn pull(&mut self, ciphertext: &[u8]) -> Option<(Vec<u8>, u8)> {
+        use subtle::ConstantTimeEq;
+        if ciphertext.len() < ABYTES {
+            return None;
+        }
+        let (encrypted, mac) =
+            ciphertext.split_at(ciphertext.len() - MAC_BYTES);
+        let mut block = [0; 64];
+        block[0] = encrypted[0];
+        self.xor(1, &mut block);
+        let tag = block[0];
+        block[0] = encrypted[0];
+        let expected = self.mac(&block, &encrypted[1..]);
+        if !bool::from(expected.ct_eq(mac)) {
+            return None;
+        }
+        let mut data = encrypted[1..].to_vec();
+        self.xor(2, &mut data);
+        self.advance(&expected, tag);
+        Some((data, tag))
+    }
+
+    /// chacha20-ietf keystream from block `counter` xored into `buf`.
+    fn xor(&self, counter: u64, buf: &mut [u8]) {
+        let mut cipher =
+            chacha20::ChaCha20::new((&self.key).into(), (&self.nonce).into());
+        cipher.seek(counter * 64);
+        cipher.apply_keystream(buf);
+    }
+
+    /// Poly1305 keyed from block 0, over the (encrypted) tag block and
+    /// the encrypted data. Note libsodium pads the data by its length
+    /// mod 16, so we do too.
+    fn mac(&self, block: &[u8; 64], encrypted: &[u8]) -> [u8; MAC_BYTES] {
+        let mut poly_key = zeroize::Zeroizing::new([0; 64]);
+        self.xor(0, &mut *poly_key);
+        let pad = encrypted.len() & 0xf;
+        let mut input =
+            Vec::with_capacity(block.len() + encrypted.len() + pad + 16);
+        input.extend_from_slice(block);
+        input.extend_from_slice(encrypted);
+        input.resize(input.len() + pad, 0);
+        // no additional data
+        input.extend_from_slice(&0u64.to_le_bytes());
+        input.extend_from_slice(
+            &((block.len() + encrypted.len()) as u64).to_le_bytes(),
+        );
+        let mut mac = [0; MAC_BYTES];
+        mac.copy_from_slice(
+            &poly1305::Poly1305::new((&poly_key[..32]).into())
+                .compute_unpadded(&input)
+                .into_bytes(),
+        );
+        mac
+    }
+
+    fn advance(&mut self, mac: &[u8; MAC_BYTES], tag: u8) {
+        for (n, m) in self.nonce[COUNTER_BYTES..].iter_mut().zip(mac.iter()) {
+            *n ^= m;
+        }
+        let mut counter = [0; COUNTER_BYTES];
+        counter.copy_from_slice(&self.nonce[..COUNTER_BYTES]);
+        let counter = u32::from_le_bytes(counter).wrapping_add(1);
+        self.nonce[..COUNTER_BYTES].copy_from_slice(&counter.to_le_bytes());
+        if tag & TAG_REKEY != 0 || counter == 0 {
+            self.rekey();
+        }
+    }
+
+    fn rekey(&mut self) {
+        let mut buf = zeroize::Zeroizing::new([0; KEY_BYTES + INONCE_BYTES]);
+        buf[..KEY_BYTES].copy_from_slice(&self.key);
+        buf[KEY_BYTES..].copy_from_slice(&self.nonce[COUNTER_BYTES..]);
+        self.xor(0, &mut *buf);
+        self.key.copy_from_slice(&buf[..KEY_BYTES]);
+        self.nonce[COUNTER_BYTES..].copy_from_slice(&buf[KEY_BYTES..]);
+        self.reset_counter();
+    }
+
+    fn reset_counter(&mut self) {
+        self.nonce[..COUNTER_BYTES].copy_from_slice(&1u32.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// draft-irtf-cfrg-xchacha-03 2.2.1
+    #[test]
+    fn it_matches_hchacha20_test_vector() {
+        let mut key = [0; KEY_BYTES];
+        key.copy_from_slice(&(0..32).collect::<Vec<u8>>());
+        let mut input = [0; 16];
+        input.copy_from_slice(&unhex("000000090000004a0000000031415927"));
+        assert_eq!(
+            unhex(
+                "82413b4227b27bfed30e42508a877d73\
+                a0f9e4d58a74a853c12ec41326d3ecdc"
+            ),
+            hchacha20(&key, &input).to_vec(),
+        );
+    }
+
+    /// A stream pushed by libsodium 1.0.18, with a rekey in the middle.
+    #[test]
+    fn it_matches_libsodium_secretstream() {
+        let mut key = [0; KEY_BYTES];
+        key.copy_from_slice(&(0..32).collect::<Vec<u8>>());
+        let mut header = [0; HEADER_BYTES];
+        header.copy_from_slice(&unhex(
+            "aae5c05ea395a7ec4ea701ab75843fe903dbe05714b0b69c",
+        ));
+        let chunks: Vec<(Vec<u8>, u8, Vec<u8>)> = vec![
+            (
+                b"Arbitrary data to encrypt".to_vec(),
+                TAG_MESSAGE,
+                unhex(
+                    "629ef6c6f8cf9fbf6bced964f0b2dcf4d39a1567988c8fe968\
+                    891ee67a1752b0623d0efe819d2766b395",
+                ),
+            ),
+            (
+                (0..100).collect(),
+                TAG_REKEY,
+                unhex(
+                    "53c0b1ef1edd500f66e9b627cb7bbd173fc2f5c0776bbb5846\
+                    71462a46b3f542c00e1f23be95ebd32ce38b5d1368e14d9a93\
+                    61d027937cfbe8d5c699581074dce3f10880316922efb30f56\
+                    58a1d33e9538b3fa7d1564828e525468e01ccb7c36056b451a\
+                    3b3035c7e0c2216d34e85f295be438dde6",
+                ),
+            ),
+            (
+                b"split into chunks".to_vec(),
+                1,
+                unhex(
+                    "6ef9f979979688f90d8429886fd233585ceaa05747acaa50e5\
+                    394a084a3002262908",
+                ),
+            ),
+            (
+                vec![],
+                TAG_MESSAGE,
+                unhex("75432e547d0cefe28a8e1b07fa99fb3488"),
+            ),
+            (
+                b"the end".to_vec(),
+                TAG_FINAL,
+                unhex("da81fa4996eab54c755d031e109027e288c08cc059ff5ac0"),
+            ),
+        ];
+
+        let mut push = State::new(&key, &header);
+        let mut pull = State::new(&key, &header);
+        for (data, tag, ciphertext) in chunks {
+            assert_eq!(ciphertext, push.push(&data, tag));
+            assert_eq!(Some((data, tag)), pull.pull(&ciphertext));
+        }
+    }
+
+    /// `crypto_box_beforenm` from libsodium 1.0.18.
+    #[test]
+    fn it_matches_libsodium_beforenm() {
+        let priv_key = x25519::X25519PrivKey::from([0x77; 32]);
+        let mut remote_pub = [0; 32];
+        remote_pub.copy_from_slice(&unhex(
+            "b795bc45da876a734d4dd92a67e4dbcefab44bb99e8d15afb52aeae80c296a0c",
+        ));
+        assert_eq!(
+            unhex(
+                "39b08fa32616c66a448779192da66b91\
+                398574f3e6771261664e24f2fd1210b8"
+            ),
+            shared_key(&priv_key, &remote_pub.into()).unwrap().to_vec(),
+        );
+        assert!(shared_key(&priv_key, &[0; 32].into()).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_push_and_pull_sessions() {
+        let alice = x25519::x25519_keypair_new_from_entropy().await.unwrap();
+        let bob = x25519::x25519_keypair_new_from_entropy().await.unwrap();
+        let sessions = SecretstreamSessions::new();
+
+        let (push_id, header) = sessions
+            .init_push(alice.priv_key.clone(), bob.pub_key.clone())
+            .await
+            .unwrap();
+        let pull_id = sessions
+            .init_pull(bob.priv_key.clone(), alice.pub_key.clone(), header)
+            .await
+            .unwrap();
+        assert_ne!(push_id, pull_id);
+
+        // sessions only go the way they were opened
+        assert!(sessions.pull(push_id, Arc::new(vec![0; 17])).await.is_err());
+        assert!(sessions
+            .push(pull_id, Arc::new(vec![]), false)
+            .await
+            .is_err());
+
+        let data = [vec![0x42; CHUNK_MAX_BYTES], vec![], vec![0x43; 3]];
+        let mut ciphertexts = Vec::new();
+        for (i, chunk) in data.iter().enumerate() {
+            let ciphertext = sessions
+                .push(push_id, Arc::new(chunk.clone()), i == data.len() - 1)
+                .await
+                .unwrap();
+            assert_eq!(chunk.len() + ABYTES, ciphertext.len());
+            ciphertexts.push(ciphertext);
+        }
+        // the final chunk closed the session
+        assert!(sessions
+            .push(push_id, Arc::new(vec![]), false)
+            .await
+            .is_err());
+
+        // out of order or tampered chunks don't pull, but can be retried
+        assert_eq!(
+            None,
+            sessions
+                .pull(pull_id, ciphertexts[1].clone())
+                .await
+                .unwrap()
+        );
+        let mut tampered = ciphertexts[0].to_vec();
+        tampered[3] ^= 1;
+        assert_eq!(
+            None,
+            sessions.pull(pull_id, Arc::new(tampered)).await.unwrap()
+        );
+        for (i, ciphertext) in ciphertexts.into_iter().enumerate() {
+            assert_eq!(
+                Some((Arc::new(data[i].clone()), i == data.len() - 1)),
+                sessions.pull(pull_id, ciphertext).await.unwrap()
+            );
+        }
+        assert!(sessions.pull(pull_id, Arc::new(vec![0; 17])).await.is_err());
+
+        // the wrong keys don't pull
+        let (push_id, header) = sessions
+            .init_push(alice.priv_key.clone(), bob.pub_key.clone())
+            .await
+            .unwrap();
+        let pull_id = sessions
+            .init_pull(bob.priv_key.clone(), bob.pub_key.clone(), header)
+            .await
+            .unwrap();
+        let ciphertext = sessions
+            .push(push_id, Arc::new(vec![1]), true)
+            .await
+            .unwrap();
+        assert_eq!(None, sessions.pull(pull_id, ciphertext).await.unwrap());
+
+        assert!(sessions
+            .push(push_id, Arc::new(vec![0; CHUNK_MAX_BYTES + 1]), false)
+            .await
+            .is_err());
+        sessions.close(pull_id).await;
+        assert!(sessions.pull(pull_id, Arc::new(vec![0; 17])).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_drops_idle_sessions() {
+        let alice = x25519::x25519_keypair_new_from_entropy().await.unwrap();
+        let sessions =
+            SecretstreamSessions::with_idle_timeout(Duration::from_millis(50));
+        let (session_id, _) = sessions
+            .init_push(alice.priv_key.clone(), alice.pub_key.clone())
+            .await
+            .unwrap();
+        sessions
+            .push(session_id, Arc::new(vec![]), false)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(sessions
+            .push(session_id, Arc::new(vec![]), false)
+            .await
+            .is_err());
+        assert!(sessions.0.lock().await.sessions.is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! Master seed utilities, and SLIP-0010 ed25519 key derivation.
+//! NOTE - temporarily using RING crate until we switch to sodoken
+
+use crate::*;
+use derive_more::*;
+use internal::bip39;
+use internal::sign_ed25519;
+use internal::util::SecretBytes;
+
+/// Byte length of master seeds generated by lair.
+pub const SEED_BYTES: usize = 32;
+
+/// Maximum number of levels in a derivation path.
+pub const MAX_DERIVATION_DEPTH: usize = 16;
+
+/// SLIP-0010 only defines hardened derivation for ed25519, so this bit
+/// is set on every derivation path index, which must be below it.
+pub const HARDENED_BIT: u32 = 0x8000_0000;
+
+/// The private bytes of a master seed.
+#[derive(Clone, Debug, PartialEq, Eq, Deref, From, Into)]
+pub struct Seed(pub SecretBytes);
+
+impl From<Vec<u8>> for Seed {
+    fn from(d: Vec<u8>) -> Self {
+        Self(d.into())
+    }
+}
+
+/// The 32 byte public identifier of a master seed.
+/// (A hash of the seed, it reveals nothing about derived keys.)
+#[derive(Clone, Debug, PartialOrd, Ord, Deref, From, Into)]
+#[allow(clippy::rc_buffer)]
+pub struct SeedId(pub Arc<Vec<u8>>);
+
+internal::util::impl_ct_eq!(SeedId);
+
+impl From<Vec<u8>> for SeedId {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// Compute the public identifier of a master seed.
+pub fn seed_id(seed: &[u8]) -> SeedId {
+    blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"lair-seed-id")
+        .hash(seed)
+        .as_bytes()
+        .to_vec()
+        .into()
+}
+
+/// Generate a new random master seed.
+/// Only `exportable` seeds can ever be exported as a mnemonic.
+pub async fn seed_new_from_entropy(
+    exportable: bool,
+) -> LairResult<entry::EntrySeed> {
+    rayon_exec(move || {
+        let sys_rand = ring::rand::SystemRandom::new();
+        let mut seed = zeroize::Zeroizing::new(vec![0; SEED_BYTES]);
+        ring::rand::SecureRandom::fill(&sys_rand, &mut seed)
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(entry::EntrySeed::new(
+            SecretBytes::from(seed).into(),
+            exportable,
+        ))
+    })
+    .await
+}
+
+/// Encode a master seed as a 24 word BIP39 mnemonic.
+/// (The seed is the mnemonic entropy, see `bip39`.)
+pub fn seed_to_mnemonic(seed: &Seed) -> LairResult<String> {
+    bip39::entropy_to_mnemonic(seed)
+}
+
+/// Decode a 24 word BIP39 mnemonic back into a master seed,
+/// validating the words and checksum.
+pub fn seed_from_mnemonic(mnemonic: &str) -> LairResult<Seed> {
+    let seed = bip39::mnemonic_to_entropy(mnemonic)?;
+    if seed.len() != SEED_BYTES {
+        return Err(format!(
+            "seed mnemonics are {} words",
+            bip39::MNEMONIC_WORD_COUNT
+        )
+        .into());
+    }
+    Ok(seed.into())
+}
+
+/// Check `path` is usable as a derivation path.
+pub fn check_derivation_path(path: &[u32]) -> LairResult<()> {
+    if path.is_empty() {
+        return Err("derivation path cannot be empty".into());
+    }
+    if path.len() > MAX_DERIVATION_DEPTH {
+        return Err(format!(
+            "derivation path exceeds {} level maximum",
+            MAX_DERIVATION_DEPTH
+        )
+        .into());
+    }
+    if let Some(index) = path.iter().find(|i| **i >= HARDENED_BIT) {
+        return Err(format!(
+            "derivation path index {} is out of range, \
+            all levels are implicitly hardened",
+            index
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Deterministically derive the child ed25519 signature keypair at
+/// `path` (every level hardened) from a master seed, per SLIP-0010.
+pub async fn sign_ed25519_keypair_derive(
+    seed: Seed,
+    path: Vec<u32>,
+) -> LairResult<entry::EntrySignEd25519> {
+    check_derivation_path(&path)?;
+    rayon_exec(move || {
+        let priv_key = slip10_ed25519_derive(&seed, &path);
+        sign_ed25519::keypair_from_seed(priv_key.to_vec().into())
+    })
+    .await
+}
+
+/// SLIP-0010 ed25519 private key derivation, all levels hardened.
+fn slip10_ed25519_derive(
+    seed: &[u8],
+    path: &[u32],
+) -> zeroize::Zeroizing<[u8; 32]> {
+    use ring::hmac;
+
+    // (key, chain code)
+    let mut node = zeroize::Zeroizing::new([0_u8; 64]);
+    node.copy_from_slice(
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA512, b"ed25519 seed"), seed)
+            .as_ref(),
+    );
+
+    for index in path {
+        let mut data = zeroize::Zeroizing::new([0_u8; 37]);
+        data[1..33].copy_from_slice(&node[..32]);
+        data[33..].copy_from_slice(&(index | HARDENED_BIT).to_be_bytes());
+        let chain_key = hmac::Key::new(hmac::HMAC_SHA512, &node[32..]);
+        node.copy_from_slice(hmac::sign(&chain_key, &*data).as_ref());
+    }
+
+    let mut priv_key = zeroize::Zeroizing::new([0_u8; 32]);
+    priv_key.copy_from_slice(&node[..32]);
+    priv_key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// SLIP-0010 ed25519 test vector 1 (the empty path is the master key),
+    /// with the resulting ed25519 pub keys. These must never change.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_slip10_test_vectors() {
+        let seed = unhex("000102030405060708090a0b0c0d0e0f");
+        let vectors: &[(&[u32], &str, &str)] = &[
+            (
+                &[],
+                "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+                "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed",
+            ),
+            (
+                &[0],
+                "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+                "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c",
+            ),
+            (
+                &[0, 1],
+                "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+                "1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187",
+            ),
+            (
+                &[0, 1, 2],
+                "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9",
+                "ae98736566d30ed0e9d2f4486a64bc95740d89c7db33f52121f8ea8f76ff0fc1",
+            ),
+            (
+                &[0, 1, 2, 2],
+                "30d1dc7e5fc04c31219ab25a27ae00b50f6fd66622f6e9c913253d6511d1e662",
+                "8abae2d66361c879b900d204ad2cc4984fa2aa344dd7ddc46007329ac76c429c",
+            ),
+            (
+                &[0, 1, 2, 2, 1000000000],
+                "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+                "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a",
+            ),
+        ];
+        for (path, priv_key, pub_key) in vectors {
+            let derived = slip10_ed25519_derive(&seed, path);
+            assert_eq!(*priv_key, hex(&*derived), "path {:?}", path);
+            if path.is_empty() {
+                let master =
+                    sign_ed25519::keypair_from_seed(derived.to_vec().into())
+                        .unwrap();
+                assert_eq!(*pub_key, hex(&master.pub_key));
+                continue;
+            }
+            let keypair =
+                sign_ed25519_keypair_derive(seed.clone().into(), path.to_vec())
+                    .await
+                    .unwrap();
+            assert_eq!(*priv_key, hex(&keypair.priv_key));
+            assert_eq!(*pub_key, hex(&keypair.pub_key), "path {:?}", path);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_rejects_bad_derivation_paths() {
+        let seed: Seed = vec![0xdb; SEED_BYTES].into();
+        for path in [
+            vec![],
+            vec![HARDENED_BIT],
+            vec![1; MAX_DERIVATION_DEPTH + 1],
+        ] {
+            assert!(sign_ed25519_keypair_derive(seed.clone(), path)
+                .await
+                .is_err());
+        }
+        assert!(sign_ed25519_keypair_derive(
+            seed,
+            vec![HARDENED_BIT - 1; MAX_DERIVATION_DEPTH]
+        )
+        .await
+        .is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_generates_distinct_seeds() {
+        let a = seed_new_from_entropy(false).await.unwrap();
+        let b = seed_new_from_entropy(true).await.unwrap();
+        assert!(!a.exportable);
+        assert!(b.exportable);
+        assert_eq!(SEED_BYTES, a.seed.len());
+        assert_ne!(a.seed, b.seed);
+        assert_eq!(a.seed_id, seed_id(&a.seed));
+        assert_ne!(a.seed_id, b.seed_id);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_reconstructs_seeds_from_mnemonics() {
+        let entry = seed_new_from_entropy(true).await.unwrap();
+        let mnemonic = seed_to_mnemonic(&entry.seed).unwrap();
+        let seed = seed_from_mnemonic(&mnemonic.to_uppercase()).unwrap();
+        assert_eq!(entry.seed, seed);
+
+        // the same seed derives the same keys
+        let a = sign_ed25519_keypair_derive(entry.seed, vec![0, 1])
+            .await
+            .unwrap();
+        let b = sign_ed25519_keypair_derive(seed, vec![0, 1]).await.unwrap();
+        assert_eq!(a.pub_key, b.pub_key);
+
+        // valid 12 word mnemonics don't make seeds
+        assert!(seed_from_mnemonic(
+            "legal winner thank year wave sausage worth useful legal winner \
+            thank yellow"
+        )
+        .is_err());
+    }
+}
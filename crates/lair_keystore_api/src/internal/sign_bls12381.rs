@@ -0,0 +1,372 @@
+//! BLS12-381 Signature Utilities, for aggregatable signatures.
+//! Uses the minimal pub key size variant (48 byte G1 pub keys, 96 byte G2
+//! signatures) of the proof of possession ciphersuite, as eth2 does.
+//! The key / signature types are always available, so clients can use
+//! bls keypairs held by a lair server without any extra dependencies.
+//! Generating, signing, verifying and aggregating need the `bls` feature.
+
+use crate::*;
+use derive_more::*;
+use internal::util::SecretBytes;
+
+/// Byte length of bls12-381 private keys.
+pub const PRIV_KEY_BYTES: usize = 32;
+
+/// Byte length of (compressed G1) bls12-381 public keys.
+pub const PUB_KEY_BYTES: usize = 48;
+
+/// Byte length of (compressed G2) bls12-381 signatures.
+pub const SIGNATURE_BYTES: usize = 96;
+
+/// Domain separation tag of the ciphersuite messages are signed under.
+pub const SIGN_BLS12381_DST: &[u8] =
+    b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// The 32 byte bls12-381 private key.
+#[derive(Clone, Debug, PartialEq, Eq, Deref, From, Into)]
+pub struct SignBls12381PrivKey(pub SecretBytes);
+
+impl From<Vec<u8>> for SignBls12381PrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(d.into())
+    }
+}
+
+/// The 48 byte compressed bls12-381 public key.
+#[derive(Clone, Debug, PartialOrd, Ord, Deref, From, Into)]
+#[allow(clippy::rc_buffer)]
+pub struct SignBls12381PubKey(pub Arc<Vec<u8>>);
+
+internal::util::impl_ct_eq!(SignBls12381PubKey);
+
+impl From<Vec<u8>> for SignBls12381PubKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+impl SignBls12381PubKey {
+    /// The 32 byte public identifier of this key within lair
+    /// (for `lair_find_entry`, `lair_list_entries`):
+    /// a hash of the compressed pub key.
+    #[allow(clippy::rc_buffer)]
+    pub fn pub_id(&self) -> Arc<Vec<u8>> {
+        Arc::new(
+            blake2b_simd::Params::new()
+                .hash_length(32)
+                .personal(b"lair-bls12381")
+                .hash(&self.0)
+                .as_bytes()
+                .to_vec(),
+        )
+    }
+
+    /// Verify a signature on the given message with this public key.
+    #[cfg(feature = "bls")]
+    #[allow(clippy::rc_buffer)]
+    pub async fn verify(
+        &self,
+        message: Arc<Vec<u8>>,
+        signature: SignBls12381Signature,
+    ) -> LairResult<bool> {
+        sign_bls12381_aggregate_verify(
+            vec![self.clone()],
+            vec![message],
+            signature,
+        )
+        .await
+    }
+}
+
+/// The 96 byte compressed bls12-381 signature.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+#[allow(clippy::rc_buffer)]
+pub struct SignBls12381Signature(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignBls12381Signature {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// The error for bls12-381 operations in builds without the `bls` feature.
+pub fn bls_not_enabled() -> LairError {
+    "lair was built without bls12-381 support (the `bls` feature)".into()
+}
+
+/// Generate a new random bls12-381 signature keypair.
+#[cfg(feature = "bls")]
+pub async fn sign_bls12381_keypair_new_from_entropy(
+) -> LairResult<entry::EntrySignBls12381> {
+    rayon_exec(move || {
+        let sys_rand = ring::rand::SystemRandom::new();
+        let mut ikm = zeroize::Zeroizing::new([0; 32]);
+        ring::rand::SecureRandom::fill(&sys_rand, &mut *ikm)
+            .map_err(|e| format!("{:?}", e))?;
+        let priv_key = blst::min_pk::SecretKey::key_gen(&*ikm, &[])
+            .map_err(|e| format!("{:?}", e))?;
+        let priv_bytes = zeroize::Zeroizing::new(priv_key.to_bytes());
+        Ok(entry::EntrySignBls12381 {
+            pub_key: priv_key.sk_to_pk().compress().to_vec().into(),
+            priv_key: priv_bytes.to_vec().into(),
+        })
+    })
+    .await
+}
+
+/// Compute the keypair of a 32 byte private key.
+/// The buffer is zeroized as it is dropped, even for invalid keys.
+#[cfg(feature = "bls")]
+pub(crate) fn keypair_from_priv_key(
+    priv_key: Vec<u8>,
+) -> LairResult<entry::EntrySignBls12381> {
+    let priv_key = SecretBytes::from(priv_key);
+    let pub_key = secret_key(&priv_key)?.sk_to_pk().compress().to_vec();
+    Ok(entry::EntrySignBls12381 {
+        priv_key: priv_key.into(),
+        pub_key: pub_key.into(),
+    })
+}
+
+/// Sign a message with a bls12-381 private key.
+#[cfg(feature = "bls")]
+#[allow(clippy::rc_buffer)]
+pub async fn sign_bls12381(
+    priv_key: SignBls12381PrivKey,
+    message: Arc<Vec<u8>>,
+) -> LairResult<SignBls12381Signature> {
+    rayon_exec(move || {
+        let sig = secret_key(&priv_key)?.sign(&message, SIGN_BLS12381_DST, &[]);
+        Ok(sig.compress().to_vec().into())
+    })
+    .await
+}
+
+/// Aggregate signatures (by any keys, over any messages) into a single
+/// signature, verifiable with `sign_bls12381_aggregate_verify`.
+#[cfg(feature = "bls")]
+pub fn sign_bls12381_aggregate(
+    signatures: &[SignBls12381Signature],
+) -> LairResult<SignBls12381Signature> {
+    let signatures = signatures
+        .iter()
+        .map(|s| signature(s))
+        .collect::<LairResult<Vec<_>>>()?;
+    let signatures = signatures.iter().collect::<Vec<_>>();
+    let agg = blst::min_pk::AggregateSignature::aggregate(&signatures, true)
+        .map_err(|e| format!("invalid bls12-381 signature: {:?}", e))?;
+    Ok(agg.to_signature().compress().to_vec().into())
+}
+
+/// Verify an aggregate signature, where `messages[i]` was signed by
+/// `pub_keys[i]`. Messages may repeat, as long as each key is known to be
+/// held by its claimed owner (lair generated keys are): the proof of
+/// possession ciphersuite relies on that to rule out rogue key attacks.
+#[cfg(feature = "bls")]
+#[allow(clippy::rc_buffer)]
+pub async fn sign_bls12381_aggregate_verify(
+    pub_keys: Vec<SignBls12381PubKey>,
+    messages: Vec<Arc<Vec<u8>>>,
+    signature: SignBls12381Signature,
+) -> LairResult<bool> {
+    if pub_keys.is_empty() || pub_keys.len() != messages.len() {
+        return Err(
+            "bls12-381 aggregate verify needs one message per pub key".into()
+        );
+    }
+    rayon_exec(move || {
+        let pub_keys = pub_keys
+            .iter()
+            .map(|k| {
+                blst::min_pk::PublicKey::key_validate(k).map_err(|e| {
+                    format!("invalid bls12-381 pub key: {:?}", e).into()
+                })
+            })
+            .collect::<LairResult<Vec<_>>>()?;
+        let pub_keys = pub_keys.iter().collect::<Vec<_>>();
+        let messages = messages.iter().map(|m| &m[..]).collect::<Vec<_>>();
+        let signature = match signature_checked(&signature) {
+            Ok(signature) => signature,
+            Err(_) => return Ok(false),
+        };
+        Ok(signature.aggregate_verify(
+            true,
+            &messages,
+            SIGN_BLS12381_DST,
+            &pub_keys,
+            false,
+        ) == blst::BLST_ERROR::BLST_SUCCESS)
+    })
+    .await
+}
+
+#[cfg(feature = "bls")]
+fn secret_key(priv_key: &[u8]) -> LairResult<blst::min_pk::SecretKey> {
+    if priv_key.len() != PRIV_KEY_BYTES {
+        return Err(format!(
+            "invalid bls12-381 private key length {}",
+            priv_key.len()
+        )
+        .into());
+    }
+    blst::min_pk::SecretKey::from_bytes(priv_key)
+        .map_err(|_| "bls12-381 private key out of range".into())
+}
+
+#[cfg(feature = "bls")]
+fn signature(sig: &[u8]) -> LairResult<blst::min_pk::Signature> {
+    if sig.len() != SIGNATURE_BYTES {
+        return Err(LairError::wrong_length(
+            "bls12-381 signature",
+            SIGNATURE_BYTES,
+            sig.len(),
+        ));
+    }
+    blst::min_pk::Signature::from_bytes(sig)
+        .map_err(|e| format!("invalid bls12-381 signature: {:?}", e).into())
+}
+
+/// Decode a signature, and check it is in the G2 subgroup.
+#[cfg(feature = "bls")]
+fn signature_checked(sig: &[u8]) -> LairResult<blst::min_pk::Signature> {
+    let sig = signature(sig)?;
+    sig.validate(false)
+        .map_err(|e| format!("invalid bls12-381 signature: {:?}", e))?;
+    Ok(sig)
+}
+
+#[cfg(all(test, feature = "bls"))]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// eth2 `sign` test vector, pinning the ciphersuite.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_eth2_test_vector() {
+        let priv_key = unhex(
+            "263dbd792f5b1be47ed85f8938c0f29586af0d3ac7b977f21c278fe1462040e3",
+        );
+        let keypair = keypair_from_priv_key(priv_key).unwrap();
+        let message = Arc::new(vec![0; 32]);
+        let sig = sign_bls12381(keypair.priv_key, message.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            unhex(
+                "b6ed936746e01f8ecf281f020953fbf1f01debd5657c4a383940b020b26507\
+                f6076334f91e2366c96e9ab279fb5158090352ea1c5b0c9274504f4f0e7053\
+                af24802e51e4568d164fe986834f41e55c8e850ce1f98458c0cfc9ab380b55\
+                285a55"
+            ),
+            *sig.0
+        );
+        assert!(keypair.pub_key.verify(message, sig).await.unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_sign_and_verify() {
+        let keypair = sign_bls12381_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(PRIV_KEY_BYTES, keypair.priv_key.len());
+        assert_eq!(PUB_KEY_BYTES, keypair.pub_key.len());
+
+        let message = Arc::new(b"hello".to_vec());
+        let sig = sign_bls12381(keypair.priv_key.clone(), message.clone())
+            .await
+            .unwrap();
+        assert_eq!(SIGNATURE_BYTES, sig.len());
+        assert!(keypair.pub_key.verify(message, sig.clone()).await.unwrap());
+        assert!(!keypair
+            .pub_key
+            .verify(Arc::new(b"world".to_vec()), sig.clone())
+            .await
+            .unwrap());
+
+        // garbage signatures just don't verify
+        let garbage = vec![0x42; SIGNATURE_BYTES].into();
+        assert!(!keypair
+            .pub_key
+            .verify(Arc::new(b"hello".to_vec()), garbage)
+            .await
+            .unwrap());
+
+        // zero is not a valid private key
+        assert!(keypair_from_priv_key(vec![0; PRIV_KEY_BYTES]).is_err());
+        assert!(keypair_from_priv_key(vec![1; 31]).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_aggregate_signatures() {
+        let mut pub_keys = Vec::new();
+        let mut messages = Vec::new();
+        let mut sigs = Vec::new();
+        for i in 0..3_u8 {
+            let keypair =
+                sign_bls12381_keypair_new_from_entropy().await.unwrap();
+            // the last two sign the same message
+            let message = Arc::new(vec![i.min(1); 8]);
+            sigs.push(
+                sign_bls12381(keypair.priv_key, message.clone())
+                    .await
+                    .unwrap(),
+            );
+            pub_keys.push(keypair.pub_key);
+            messages.push(message);
+        }
+
+        let agg = sign_bls12381_aggregate(&sigs).unwrap();
+        assert_eq!(SIGNATURE_BYTES, agg.len());
+        assert!(sign_bls12381_aggregate_verify(
+            pub_keys.clone(),
+            messages.clone(),
+            agg.clone(),
+        )
+        .await
+        .unwrap());
+
+        // a single signature does not verify for all of them
+        assert!(!sign_bls12381_aggregate_verify(
+            pub_keys.clone(),
+            messages.clone(),
+            sigs[0].clone(),
+        )
+        .await
+        .unwrap());
+
+        // nor does the aggregate with the messages swapped around
+        let mut swapped = messages.clone();
+        swapped.swap(0, 2);
+        assert!(!sign_bls12381_aggregate_verify(
+            pub_keys.clone(),
+            swapped,
+            agg.clone(),
+        )
+        .await
+        .unwrap());
+
+        // mismatched inputs are errors
+        assert!(sign_bls12381_aggregate_verify(
+            pub_keys[..2].to_vec(),
+            messages.clone(),
+            agg.clone(),
+        )
+        .await
+        .is_err());
+        assert!(sign_bls12381_aggregate_verify(vec![], vec![], agg.clone())
+            .await
+            .is_err());
+        let mut bad_keys = pub_keys;
+        bad_keys[1] = vec![0x42; PUB_KEY_BYTES].into();
+        assert!(sign_bls12381_aggregate_verify(bad_keys, messages, agg)
+            .await
+            .is_err());
+        assert!(sign_bls12381_aggregate(&[vec![0; 32].into()]).is_err());
+    }
+}
@@ -2,28 +2,27 @@
 //! NOTE - temporarily using RING crate until we switch to sodoken
 
 use crate::*;
+use actor::KeystoreIndex;
 use derive_more::*;
+use internal::util::SecretBytes;
 
-/// The 64 byte signature ed25519 public key.
-#[derive(
-    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
-)]
-#[allow(clippy::rc_buffer)]
-pub struct SignEd25519PrivKey(pub Arc<Vec<u8>>);
+/// The 32 byte signature ed25519 private key (seed).
+#[derive(Clone, Debug, PartialEq, Eq, Deref, From, Into)]
+pub struct SignEd25519PrivKey(pub SecretBytes);
 
 impl From<Vec<u8>> for SignEd25519PrivKey {
     fn from(d: Vec<u8>) -> Self {
-        Self(Arc::new(d))
+        Self(d.into())
     }
 }
 
 /// The 32 byte signature ed25519 public key.
-#[derive(
-    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
-)]
+#[derive(Clone, Debug, PartialOrd, Ord, Deref, From, Into)]
 #[allow(clippy::rc_buffer)]
 pub struct SignEd25519PubKey(pub Arc<Vec<u8>>);
 
+internal::util::impl_ct_eq!(SignEd25519PubKey);
+
 impl From<Vec<u8>> for SignEd25519PubKey {
     fn from(d: Vec<u8>) -> Self {
         Self(Arc::new(d))
@@ -32,10 +31,9 @@ impl From<Vec<u8>> for SignEd25519PubKey {
 
 impl SignEd25519PubKey {
     /// Verify signature on given message with given public key.
-    #[allow(clippy::rc_buffer)]
     pub async fn verify(
         &self,
-        message: Arc<Vec<u8>>,
+        message: bytes::Bytes,
         signature: SignEd25519Signature,
     ) -> LairResult<bool> {
         internal::sign_ed25519::sign_ed25519_verify(
@@ -45,6 +43,40 @@ impl SignEd25519PubKey {
         )
         .await
     }
+
+    /// Verify a signature made under a domain separation `context`
+    /// (see `sign_ed25519_sign_by_index_with_context`). Signatures made
+    /// without a context, or under any other context, never verify.
+    #[allow(clippy::rc_buffer)]
+    pub async fn verify_with_context(
+        &self,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+        signature: SignEd25519Signature,
+    ) -> LairResult<bool> {
+        check_context(&context)?;
+        internal::sign_ed25519::sign_ed25519_verify(
+            self.clone(),
+            context_message(&context, &message).into(),
+            signature,
+        )
+        .await
+    }
+
+    /// Verify a prehashed signature (see `sign_ed25519_sign_prehashed`)
+    /// on the given 32 byte prehash digest.
+    pub async fn verify_prehashed(
+        &self,
+        digest: [u8; 32],
+        signature: SignEd25519Signature,
+    ) -> LairResult<bool> {
+        internal::sign_ed25519::sign_ed25519_verify(
+            self.clone(),
+            prehashed_message(&digest).into(),
+            signature,
+        )
+        .await
+    }
 }
 
 /// The 64 byte detached ed25519 signature data.
@@ -60,52 +92,222 @@ impl From<Vec<u8>> for SignEd25519Signature {
     }
 }
 
+/// Prefix of the message actually signed for prehashed signatures,
+/// followed by the 32 byte prehash digest.
+/// Direct signing refuses messages starting with this prefix, so a
+/// prehashed signature can never pass for a direct one, or vice versa.
+pub const SIGN_ED25519_PREHASH_DOMAIN: &[u8] =
+    b"lair-ed25519-prehashed-blake2b-256-v1\0";
+
+/// Prefix of the message actually signed for context signatures,
+/// followed by the 1 byte context length, the context, then the message.
+/// Direct signing refuses messages starting with this prefix, so a
+/// context signature can never pass for a direct one, or vice versa.
+pub const SIGN_ED25519_CONTEXT_DOMAIN: &[u8] = b"lair-ed25519-context-v1\0";
+
+/// Maximum byte length of a signing context.
+pub const SIGN_ED25519_CONTEXT_MAX_BYTES: usize = 255;
+
+/// Check `context` is usable as a signing context.
+pub fn check_context(context: &[u8]) -> LairResult<()> {
+    if context.len() > SIGN_ED25519_CONTEXT_MAX_BYTES {
+        return Err(format!(
+            "signing context exceeds {} byte maximum",
+            SIGN_ED25519_CONTEXT_MAX_BYTES
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Incrementally computes the 32 byte blake2b prehash digest of a large
+/// payload, for `sign_ed25519_sign_prehashed`.
+#[derive(Clone)]
+pub struct SignEd25519Prehasher(blake2b_simd::State);
+
+impl Default for SignEd25519Prehasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignEd25519Prehasher {
+    /// Start a new prehash digest.
+    pub fn new() -> Self {
+        Self(blake2b_simd::Params::new().hash_length(32).to_state())
+    }
+
+    /// Feed the next chunk of the payload.
+    pub fn update(&mut self, chunk: &[u8]) -> &mut Self {
+        self.0.update(chunk);
+        self
+    }
+
+    /// Get the 32 byte digest of everything fed so far.
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(self.0.finalize().as_bytes());
+        digest
+    }
+}
+
+/// Prehash a payload held in memory, see `SignEd25519Prehasher`.
+pub fn sign_ed25519_prehash(payload: &[u8]) -> [u8; 32] {
+    SignEd25519Prehasher::new().update(payload).finalize()
+}
+
+fn prehashed_message(digest: &[u8; 32]) -> Vec<u8> {
+    let mut message = SIGN_ED25519_PREHASH_DOMAIN.to_vec();
+    message.extend_from_slice(digest);
+    message
+}
+
+fn context_message(context: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(
+        SIGN_ED25519_CONTEXT_DOMAIN.len() + 1 + context.len() + message.len(),
+    );
+    out.extend_from_slice(SIGN_ED25519_CONTEXT_DOMAIN);
+    out.push(context.len() as u8);
+    out.extend_from_slice(context);
+    out.extend_from_slice(message);
+    out
+}
+
+fn check_not_reserved_domain(message: &[u8]) -> LairResult<()> {
+    if message.starts_with(SIGN_ED25519_PREHASH_DOMAIN) {
+        return Err("message uses the reserved ed25519 prehash domain".into());
+    }
+    if message.starts_with(SIGN_ED25519_CONTEXT_DOMAIN) {
+        return Err("message uses the reserved ed25519 context domain".into());
+    }
+    Ok(())
+}
+
 /// Generate a new random ed25519 signature keypair.
 pub async fn sign_ed25519_keypair_new_from_entropy(
 ) -> LairResult<entry::EntrySignEd25519> {
     rayon_exec(move || {
         let sys_rand = ring::rand::SystemRandom::new();
-        let mut priv_key = vec![0; 32];
+        let mut priv_key = zeroize::Zeroizing::new(vec![0; 32]);
         ring::rand::SecureRandom::fill(&sys_rand, &mut priv_key)
             .map_err(|e| format!("{:?}", e))?;
-        let keypair =
-            ring::signature::Ed25519KeyPair::from_seed_unchecked(&priv_key)
-                .map_err(|e| format!("{:?}", e))?;
-        let pub_key = ring::signature::KeyPair::public_key(&keypair)
-            .as_ref()
-            .to_vec();
-        Ok(entry::EntrySignEd25519 {
-            priv_key: priv_key.into(),
-            pub_key: pub_key.into(),
-        })
+        keypair_from_seed(priv_key.into())
     })
     .await
 }
 
+/// Derive an ed25519 signature keypair from an existing 32 byte seed.
+/// The seed buffer is zeroized once the keypair is dropped.
+pub async fn sign_ed25519_keypair_from_seed(
+    seed: Vec<u8>,
+) -> LairResult<entry::EntrySignEd25519> {
+    let seed = SecretBytes::from(seed);
+    rayon_exec_sized(0, move || keypair_from_seed(seed)).await
+}
+
+pub(crate) fn keypair_from_seed(
+    priv_key: SecretBytes,
+) -> LairResult<entry::EntrySignEd25519> {
+    if priv_key.len() != 32 {
+        return Err(LairError::SignEd25519SeedLength(priv_key.len()));
+    }
+    let keypair = keypair_for_signing(&priv_key)?;
+    let pub_key = ring::signature::KeyPair::public_key(&keypair)
+        .as_ref()
+        .to_vec();
+    Ok(entry::EntrySignEd25519 {
+        priv_key: priv_key.into(),
+        pub_key: pub_key.into(),
+    })
+}
+
+/// The ring keypair of a 32 byte ed25519 seed.
+fn keypair_for_signing(
+    priv_key: &[u8],
+) -> LairResult<ring::signature::Ed25519KeyPair> {
+    ring::signature::Ed25519KeyPair::from_seed_unchecked(priv_key)
+        .map_err(|_| LairError::SignEd25519SeedLength(priv_key.len()))
+}
+
 /// Generate detached signature bytes for given ed25519 priv key / message.
-#[allow(clippy::rc_buffer)]
+/// Fails for messages starting with `SIGN_ED25519_PREHASH_DOMAIN`
+/// or `SIGN_ED25519_CONTEXT_DOMAIN`.
 pub async fn sign_ed25519(
     priv_key: SignEd25519PrivKey,
-    message: Arc<Vec<u8>>,
+    message: bytes::Bytes,
 ) -> LairResult<SignEd25519Signature> {
-    rayon_exec(move || {
-        let keypair =
-            ring::signature::Ed25519KeyPair::from_seed_unchecked(&priv_key)
-                .map_err(|e| format!("{:?}", e))?;
+    check_not_reserved_domain(&message)?;
+    rayon_exec_sized(message.len(), move || {
+        let keypair = keypair_for_signing(&priv_key)?;
         let signature = keypair.sign(&message);
         Ok(signature.as_ref().to_vec().into())
     })
     .await
 }
 
-/// Verify signature on given message with given public key.
+/// Generate a domain separated signature, over
+/// `SIGN_ED25519_CONTEXT_DOMAIN` followed by the length framed `context`
+/// and the message. Contexts are at most
+/// `SIGN_ED25519_CONTEXT_MAX_BYTES` long, the empty context is valid
+/// (and distinct from signing without a context).
 #[allow(clippy::rc_buffer)]
+pub async fn sign_ed25519_with_context(
+    priv_key: SignEd25519PrivKey,
+    context: Arc<Vec<u8>>,
+    message: bytes::Bytes,
+) -> LairResult<SignEd25519Signature> {
+    check_context(&context)?;
+    rayon_exec_sized(context.len() + message.len(), move || {
+        let keypair = keypair_for_signing(&priv_key)?;
+        let signature = keypair.sign(&context_message(&context, &message));
+        Ok(signature.as_ref().to_vec().into())
+    })
+    .await
+}
+
+/// Generate a prehashed signature, over `SIGN_ED25519_PREHASH_DOMAIN`
+/// followed by the 32 byte prehash digest of the actual payload.
+pub async fn sign_ed25519_prehashed(
+    priv_key: SignEd25519PrivKey,
+    digest: [u8; 32],
+) -> LairResult<SignEd25519Signature> {
+    rayon_exec_sized(digest.len(), move || {
+        let keypair = keypair_for_signing(&priv_key)?;
+        let signature = keypair.sign(&prehashed_message(&digest));
+        Ok(signature.as_ref().to_vec().into())
+    })
+    .await
+}
+
+/// Generate detached signatures for each of the given messages, in order,
+/// from a single rayon task (which spreads the work over the lair pool).
+/// Fails if any message starts with `SIGN_ED25519_PREHASH_DOMAIN`
+/// or `SIGN_ED25519_CONTEXT_DOMAIN`.
+pub async fn sign_ed25519_batch(
+    priv_key: SignEd25519PrivKey,
+    messages: Vec<bytes::Bytes>,
+) -> LairResult<Vec<SignEd25519Signature>> {
+    use rayon::prelude::*;
+    for message in messages.iter() {
+        check_not_reserved_domain(message)?;
+    }
+    rayon_exec(move || {
+        let keypair = keypair_for_signing(&priv_key)?;
+        Ok(messages
+            .par_iter()
+            .map(|message| keypair.sign(message).as_ref().to_vec().into())
+            .collect())
+    })
+    .await
+}
+
+/// Verify signature on given message with given public key.
 pub async fn sign_ed25519_verify(
     pub_key: SignEd25519PubKey,
-    message: Arc<Vec<u8>>,
+    message: bytes::Bytes,
     signature: SignEd25519Signature,
 ) -> LairResult<bool> {
-    rayon_exec(move || {
+    rayon_exec_sized(message.len(), move || {
         let pub_key = ring::signature::UnparsedPublicKey::new(
             &ring::signature::ED25519,
             &**pub_key,
@@ -115,13 +317,162 @@ pub async fn sign_ed25519_verify(
     .await
 }
 
+/// A parsed ring keypair, mlocked, and zeroized when dropped.
+struct CachedKeypair {
+    keypair: Box<ring::signature::Ed25519KeyPair>,
+    // dropped after the keypair is zeroized
+    _lock: internal::util::MemLock,
+}
+
+impl CachedKeypair {
+    fn new(priv_key: &[u8]) -> LairResult<Self> {
+        let keypair = Box::new(keypair_for_signing(priv_key)?);
+        let lock = internal::util::MemLock::new(&*keypair);
+        Ok(Self {
+            keypair,
+            _lock: lock,
+        })
+    }
+}
+
+impl Drop for CachedKeypair {
+    fn drop(&mut self) {
+        // ring keeps no way to zeroize them, but its keypairs are plain
+        // key bytes without drop glue, which are zeroized in place
+        let keypair = &mut *self.keypair as *mut _ as *mut u8;
+        let len = std::mem::size_of::<ring::signature::Ed25519KeyPair>();
+        zeroize::Zeroize::zeroize(unsafe {
+            std::slice::from_raw_parts_mut(keypair, len)
+        });
+    }
+}
+
+/// Parsed keypairs by keystore index, so repeated signatures with the
+/// same key skip deriving its public key from the seed (a scalar mult,
+/// costing about as much as the signature itself).
+/// Entries must be invalidated when their keystore index is deleted,
+/// and all of them cleared when the store is locked.
+#[derive(Clone, Default)]
+pub struct SignEd25519KeypairCache(Arc<tokio::sync::Mutex<KeypairCacheInner>>);
+
+#[derive(Default)]
+struct KeypairCacheInner {
+    keypairs: std::collections::HashMap<KeystoreIndex, Arc<CachedKeypair>>,
+    // bumped by every invalidation, so keypairs parsed meanwhile
+    // are not cached after them
+    generation: u64,
+}
+
+impl SignEd25519KeypairCache {
+    /// An empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// As `sign_ed25519`, for the private key at `keystore_index`.
+    pub async fn sign_ed25519(
+        &self,
+        keystore_index: KeystoreIndex,
+        priv_key: SignEd25519PrivKey,
+        message: bytes::Bytes,
+    ) -> LairResult<SignEd25519Signature> {
+        check_not_reserved_domain(&message)?;
+        let keypair = self.get(keystore_index, priv_key).await?;
+        rayon_exec_sized(message.len(), move || {
+            Ok(keypair.keypair.sign(&message).as_ref().to_vec().into())
+        })
+        .await
+    }
+
+    /// Drop the keypair of the private key at `keystore_index`.
+    pub async fn invalidate(&self, keystore_index: KeystoreIndex) {
+        let mut inner = self.0.lock().await;
+        inner.generation += 1;
+        inner.keypairs.remove(&keystore_index);
+    }
+
+    /// Drop every keypair.
+    pub async fn clear(&self) {
+        let mut inner = self.0.lock().await;
+        inner.generation += 1;
+        inner.keypairs.clear();
+    }
+
+    async fn get(
+        &self,
+        keystore_index: KeystoreIndex,
+        priv_key: SignEd25519PrivKey,
+    ) -> LairResult<Arc<CachedKeypair>> {
+        let generation = {
+            let inner = self.0.lock().await;
+            if let Some(keypair) = inner.keypairs.get(&keystore_index) {
+                return Ok(keypair.clone());
+            }
+            inner.generation
+        };
+        // parsed outside the lock, so a miss costs the same as
+        // without the cache
+        let keypair = rayon_exec_sized(0, move || {
+            CachedKeypair::new(&priv_key).map(Arc::new)
+        })
+        .await?;
+        let mut inner = self.0.lock().await;
+        if inner.generation == generation {
+            inner.keypairs.insert(keystore_index, keypair.clone());
+        }
+        Ok(keypair)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cached_keypairs_sign_as_uncached_ones() {
+        let msg = bytes::Bytes::from(vec![0, 1, 2, 3]);
+        let a = sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        let b = sign_ed25519_keypair_new_from_entropy().await.unwrap();
+        let cache = SignEd25519KeypairCache::new();
+        let cached = |cache: &SignEd25519KeypairCache| {
+            let cache = cache.clone();
+            async move { cache.0.lock().await.keypairs.len() }
+        };
+
+        for _ in 0..2 {
+            for (index, e) in [(1.into(), &a), (2.into(), &b)].iter() {
+                let sig = cache
+                    .sign_ed25519(*index, e.priv_key.clone(), msg.clone())
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    sign_ed25519(e.priv_key.clone(), msg.clone())
+                        .await
+                        .unwrap(),
+                    sig
+                );
+                assert!(e.pub_key.verify(msg.clone(), sig).await.unwrap());
+            }
+        }
+        assert_eq!(2, cached(&cache).await);
+        assert!(cache
+            .sign_ed25519(
+                1.into(),
+                a.priv_key.clone(),
+                SIGN_ED25519_PREHASH_DOMAIN.into(),
+            )
+            .await
+            .is_err());
+
+        cache.invalidate(1.into()).await;
+        assert_eq!(1, cached(&cache).await);
+        cache.clear().await;
+        assert_eq!(0, cached(&cache).await);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn it_can_sign_and_verify() {
-        let msg = Arc::new(vec![0, 1, 2, 3]);
+        let msg = bytes::Bytes::from(vec![0, 1, 2, 3]);
 
         let entry::EntrySignEd25519 { priv_key, pub_key } =
             sign_ed25519_keypair_new_from_entropy().await.unwrap();
@@ -145,4 +496,158 @@ mod tests {
         .await
         .unwrap());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_sign_batches_in_order() {
+        let entry::EntrySignEd25519 { priv_key, .. } =
+            sign_ed25519_keypair_new_from_entropy().await.unwrap();
+
+        let messages = (0..5_u8)
+            .map(|i| vec![i; i as usize].into())
+            .collect::<Vec<_>>();
+        let sigs = sign_ed25519_batch(priv_key.clone(), messages.clone())
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), sigs.len());
+        for (message, sig) in messages.into_iter().zip(sigs) {
+            assert_eq!(
+                sign_ed25519(priv_key.clone(), message).await.unwrap(),
+                sig
+            );
+        }
+
+        assert!(sign_ed25519_batch(priv_key, Vec::new())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_keeps_prehashed_and_direct_signatures_apart() {
+        let entry::EntrySignEd25519 { priv_key, pub_key } =
+            sign_ed25519_keypair_new_from_entropy().await.unwrap();
+
+        let payload = vec![0xdb; 1000];
+        let mut prehasher = SignEd25519Prehasher::new();
+        for chunk in payload.chunks(300) {
+            prehasher.update(chunk);
+        }
+        let digest = prehasher.finalize();
+        assert_eq!(sign_ed25519_prehash(&payload), digest);
+
+        let sig = sign_ed25519_prehashed(priv_key.clone(), digest)
+            .await
+            .unwrap();
+        assert!(pub_key.verify_prehashed(digest, sig.clone()).await.unwrap());
+        assert!(!pub_key
+            .verify(payload.clone().into(), sig.clone())
+            .await
+            .unwrap());
+        assert!(!pub_key.verify(digest.to_vec().into(), sig).await.unwrap());
+
+        let direct = sign_ed25519(priv_key.clone(), digest.to_vec().into())
+            .await
+            .unwrap();
+        assert!(!pub_key.verify_prehashed(digest, direct).await.unwrap());
+
+        // the prehash domain is off limits for direct signatures
+        let forged = bytes::Bytes::from(prehashed_message(&digest));
+        assert!(sign_ed25519(priv_key.clone(), forged.clone())
+            .await
+            .is_err());
+        assert!(sign_ed25519_batch(priv_key, vec![forged]).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_keeps_context_and_direct_signatures_apart() {
+        let entry::EntrySignEd25519 { priv_key, pub_key } =
+            sign_ed25519_keypair_new_from_entropy().await.unwrap();
+
+        let msg = bytes::Bytes::from_static(b"message");
+        let ctx = Arc::new(b"holochain-action".to_vec());
+        let sig = sign_ed25519_with_context(
+            priv_key.clone(),
+            ctx.clone(),
+            msg.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(pub_key
+            .verify_with_context(ctx.clone(), msg.clone(), sig.clone())
+            .await
+            .unwrap());
+
+        // no context, another context, or the empty context: all fail
+        assert!(!pub_key.verify(msg.clone(), sig.clone()).await.unwrap());
+        for other in [b"holochain-entry".to_vec(), Vec::new()] {
+            assert!(!pub_key
+                .verify_with_context(Arc::new(other), msg.clone(), sig.clone())
+                .await
+                .unwrap());
+        }
+
+        // the length framing keeps context and message apart
+        let shifted = sign_ed25519_with_context(
+            priv_key.clone(),
+            Arc::new(b"holochain-".to_vec()),
+            bytes::Bytes::from_static(b"actionmessage"),
+        )
+        .await
+        .unwrap();
+        assert_ne!(sig, shifted);
+        assert!(!pub_key
+            .verify_with_context(ctx.clone(), msg.clone(), shifted)
+            .await
+            .unwrap());
+
+        // direct signatures never verify under any context
+        let direct = sign_ed25519(priv_key.clone(), msg.clone()).await.unwrap();
+        for other in [ctx.to_vec(), Vec::new()] {
+            assert!(!pub_key
+                .verify_with_context(
+                    Arc::new(other),
+                    msg.clone(),
+                    direct.clone()
+                )
+                .await
+                .unwrap());
+        }
+
+        // the context domain is off limits for direct signatures
+        let forged = bytes::Bytes::from(context_message(&ctx, &msg));
+        assert!(sign_ed25519(priv_key.clone(), forged.clone())
+            .await
+            .is_err());
+        assert!(sign_ed25519_batch(priv_key.clone(), vec![forged])
+            .await
+            .is_err());
+
+        // contexts are bounded
+        let max = Arc::new(vec![0xdb; SIGN_ED25519_CONTEXT_MAX_BYTES]);
+        assert!(
+            sign_ed25519_with_context(priv_key.clone(), max, msg.clone())
+                .await
+                .is_ok()
+        );
+        let over = Arc::new(vec![0xdb; SIGN_ED25519_CONTEXT_MAX_BYTES + 1]);
+        assert!(
+            sign_ed25519_with_context(priv_key, over.clone(), msg.clone())
+                .await
+                .is_err()
+        );
+        assert!(pub_key.verify_with_context(over, msg, sig).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_derives_same_keypair_from_seed() {
+        let entry::EntrySignEd25519 { priv_key, pub_key } =
+            sign_ed25519_keypair_new_from_entropy().await.unwrap();
+
+        let derived = sign_ed25519_keypair_from_seed((*priv_key).to_vec())
+            .await
+            .unwrap();
+        assert_eq!(pub_key, derived.pub_key);
+
+        assert!(sign_ed25519_keypair_from_seed(vec![0; 31]).await.is_err());
+    }
 }
@@ -0,0 +1,791 @@
+//! Secp256k1 ECDSA Signature Utilities
+//! NOTE - minimal self contained implementation, NOT constant time,
+//!        until we can depend on a vetted secp256k1 crate.
+
+use crate::*;
+use derive_more::*;
+use internal::util::SecretBytes;
+use std::cmp::Ordering;
+
+/// Byte length of secp256k1 private keys.
+pub const PRIV_KEY_BYTES: usize = 32;
+
+/// Byte length of (SEC1 compressed) secp256k1 public keys.
+pub const PUB_KEY_BYTES: usize = 33;
+
+/// Byte length of recoverable secp256k1 signatures.
+pub const SIGNATURE_BYTES: usize = 65;
+
+/// The 32 byte secp256k1 private key.
+#[derive(Clone, Debug, PartialEq, Eq, Deref, From, Into)]
+pub struct SignSecp256k1PrivKey(pub SecretBytes);
+
+impl From<Vec<u8>> for SignSecp256k1PrivKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(d.into())
+    }
+}
+
+/// The 33 byte SEC1 compressed secp256k1 public key.
+#[derive(Clone, Debug, PartialOrd, Ord, Deref, From, Into)]
+#[allow(clippy::rc_buffer)]
+pub struct SignSecp256k1PubKey(pub Arc<Vec<u8>>);
+
+internal::util::impl_ct_eq!(SignSecp256k1PubKey);
+
+impl From<Vec<u8>> for SignSecp256k1PubKey {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+impl SignSecp256k1PubKey {
+    /// The 32 byte public identifier of this key within lair
+    /// (for `lair_find_entry`, `lair_list_entries`):
+    /// a hash of the compressed pub key.
+    #[allow(clippy::rc_buffer)]
+    pub fn pub_id(&self) -> Arc<Vec<u8>> {
+        Arc::new(
+            blake2b_simd::Params::new()
+                .hash_length(32)
+                .personal(b"lair-secp256k1")
+                .hash(&self.0)
+                .as_bytes()
+                .to_vec(),
+        )
+    }
+
+    /// The 65 byte SEC1 uncompressed form of this key
+    /// (`0x04`, x, y), as used e.g. for ethereum addresses.
+    pub fn to_uncompressed(&self) -> LairResult<Vec<u8>> {
+        let (x, y) = decode_pub_key(&self.0)?;
+        let mut out = vec![0x04];
+        out.extend_from_slice(&to_be(&x));
+        out.extend_from_slice(&to_be(&y));
+        Ok(out)
+    }
+
+    /// Verify a recoverable signature (see `sign_secp256k1`) on the given
+    /// 32 byte digest with this public key. The recovery id must match.
+    pub async fn verify(
+        &self,
+        digest: [u8; 32],
+        signature: SignSecp256k1Signature,
+    ) -> LairResult<bool> {
+        let pub_key = self.clone();
+        rayon_exec(move || match recover(&digest, &signature) {
+            Ok(recovered) => Ok(recovered == pub_key),
+            Err(_) => Ok(false),
+        })
+        .await
+    }
+}
+
+/// The 65 byte recoverable secp256k1 signature:
+/// 32 byte r, 32 byte s (always "low s"), then the 1 byte recovery id
+/// (`0` or `1`, add 27 for legacy ethereum `v` values).
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From, Into,
+)]
+#[allow(clippy::rc_buffer)]
+pub struct SignSecp256k1Signature(pub Arc<Vec<u8>>);
+
+impl From<Vec<u8>> for SignSecp256k1Signature {
+    fn from(d: Vec<u8>) -> Self {
+        Self(Arc::new(d))
+    }
+}
+
+/// Generate a new random secp256k1 signature keypair.
+pub async fn sign_secp256k1_keypair_new_from_entropy(
+) -> LairResult<entry::EntrySignSecp256k1> {
+    rayon_exec(move || {
+        let sys_rand = ring::rand::SystemRandom::new();
+        loop {
+            let mut priv_key = vec![0; PRIV_KEY_BYTES];
+            ring::rand::SecureRandom::fill(&sys_rand, &mut priv_key)
+                .map_err(|e| format!("{:?}", e))?;
+            // (all but ~2^-128 of random values are valid keys)
+            if let Ok(keypair) = keypair_from_priv_key(priv_key) {
+                return Ok(keypair);
+            }
+        }
+    })
+    .await
+}
+
+/// Compute the keypair of a 32 byte big-endian private key,
+/// which must be in `[1, n)`.
+/// The buffer is zeroized as it is dropped, even for invalid keys.
+pub(crate) fn keypair_from_priv_key(
+    priv_key: Vec<u8>,
+) -> LairResult<entry::EntrySignSecp256k1> {
+    let priv_key = SecretBytes::from(priv_key);
+    let d = priv_scalar(&priv_key)?;
+    let (x, y) = G.mul(&d).to_affine().ok_or("invalid private key")?;
+    let pub_key = encode_pub_key(&x, &y);
+    Ok(entry::EntrySignSecp256k1 {
+        priv_key: priv_key.into(),
+        pub_key: pub_key.into(),
+    })
+}
+
+/// Generate a recoverable signature over a caller provided 32 byte digest
+/// (e.g. keccak256 for ethereum), with RFC 6979 deterministic nonces.
+pub async fn sign_secp256k1(
+    priv_key: SignSecp256k1PrivKey,
+    digest: [u8; 32],
+) -> LairResult<SignSecp256k1Signature> {
+    rayon_exec(move || sign(&priv_key, &digest)).await
+}
+
+/// Recover the public key that made a recoverable signature
+/// over the given 32 byte digest.
+pub async fn sign_secp256k1_recover(
+    digest: [u8; 32],
+    signature: SignSecp256k1Signature,
+) -> LairResult<SignSecp256k1PubKey> {
+    rayon_exec(move || recover(&digest, &signature)).await
+}
+
+fn priv_scalar(priv_key: &[u8]) -> LairResult<U256> {
+    if priv_key.len() != PRIV_KEY_BYTES {
+        return Err(format!(
+            "invalid secp256k1 private key length {}",
+            priv_key.len()
+        )
+        .into());
+    }
+    let d = from_be(priv_key);
+    if is_zero(&d) || cmp(&d, &FN.m) != Ordering::Less {
+        return Err("secp256k1 private key out of range".into());
+    }
+    Ok(d)
+}
+
+fn sign(
+    priv_key: &[u8],
+    digest: &[u8; 32],
+) -> LairResult<SignSecp256k1Signature> {
+    use ring::hmac;
+
+    let d = priv_scalar(priv_key)?;
+    let z = FN.reduce(from_be(digest));
+
+    // RFC 6979 section 3.2, with HMAC-SHA256
+    let mut seed = zeroize::Zeroizing::new(Vec::with_capacity(97));
+    seed.extend_from_slice(&[0x01; 32]);
+    seed.push(0x00);
+    seed.extend_from_slice(&to_be(&d));
+    seed.extend_from_slice(&to_be(&z));
+    let mut k_key = hmac::Key::new(hmac::HMAC_SHA256, &[0x00; 32]);
+    let mut v = zeroize::Zeroizing::new([0x01_u8; 32]);
+    for round in &[0x00_u8, 0x01] {
+        seed[..32].copy_from_slice(&*v);
+        seed[32] = *round;
+        k_key = hmac::Key::new(
+            hmac::HMAC_SHA256,
+            hmac::sign(&k_key, &seed).as_ref(),
+        );
+        let next = hmac::sign(&k_key, &*v);
+        v.copy_from_slice(next.as_ref());
+    }
+
+    loop {
+        let next = hmac::sign(&k_key, &*v);
+        v.copy_from_slice(next.as_ref());
+        let k = from_be(&*v);
+        if !is_zero(&k) && cmp(&k, &FN.m) == Ordering::Less {
+            if let Some(sig) = sign_with_nonce(&d, &z, &k) {
+                return Ok(sig.to_vec().into());
+            }
+        }
+        let mut retry = v.to_vec();
+        retry.push(0x00);
+        k_key = hmac::Key::new(
+            hmac::HMAC_SHA256,
+            hmac::sign(&k_key, &retry).as_ref(),
+        );
+        let next = hmac::sign(&k_key, &*v);
+        v.copy_from_slice(next.as_ref());
+    }
+}
+
+fn sign_with_nonce(
+    d: &U256,
+    z: &U256,
+    k: &U256,
+) -> Option<[u8; SIGNATURE_BYTES]> {
+    let (rx, ry) = G.mul(k).to_affine()?;
+    let r = FN.reduce(rx);
+    if is_zero(&r) {
+        return None;
+    }
+    let mut s = FN.mul(&FN.inv(k), &FN.add(z, &FN.mul(&r, d)));
+    if is_zero(&s) {
+        return None;
+    }
+    let mut recid = (ry[0] & 1) as u8;
+    if cmp(&rx, &FN.m) != Ordering::Less {
+        recid |= 2;
+    }
+    // normalize to "low s", flipping the parity of R
+    if cmp(&s, &half_n()) == Ordering::Greater {
+        s = FN.neg(&s);
+        recid ^= 1;
+    }
+    let mut out = [0; SIGNATURE_BYTES];
+    out[..32].copy_from_slice(&to_be(&r));
+    out[32..64].copy_from_slice(&to_be(&s));
+    out[64] = recid;
+    Some(out)
+}
+
+fn recover(
+    digest: &[u8; 32],
+    signature: &[u8],
+) -> LairResult<SignSecp256k1PubKey> {
+    if signature.len() != SIGNATURE_BYTES {
+        return Err(format!(
+            "invalid secp256k1 signature length {}",
+            signature.len()
+        )
+        .into());
+    }
+    let r = from_be(&signature[..32]);
+    let s = from_be(&signature[32..64]);
+    let recid = signature[64];
+    for v in [&r, &s] {
+        if is_zero(v) || cmp(v, &FN.m) != Ordering::Less {
+            return Err("secp256k1 signature out of range".into());
+        }
+    }
+    if recid > 3 {
+        return Err("invalid secp256k1 recovery id".into());
+    }
+
+    // the x coordinate of R was r, or r + n
+    let rx = if recid & 2 == 2 {
+        let (rx, carry) = add(&r, &FN.m);
+        if carry || cmp(&rx, &FP.m) != Ordering::Less {
+            return Err("invalid secp256k1 recovery id".into());
+        }
+        rx
+    } else {
+        r
+    };
+    let ry =
+        lift_x(&rx, recid & 1 == 1).ok_or("invalid secp256k1 signature")?;
+    let big_r = Point::from_affine(rx, ry);
+
+    // Q = r^-1 (sR - zG)
+    let z = FN.reduce(from_be(digest));
+    let r_inv = FN.inv(&r);
+    let u1 = FN.neg(&FN.mul(&z, &r_inv));
+    let u2 = FN.mul(&s, &r_inv);
+    let (x, y) = G
+        .mul(&u1)
+        .add(&big_r.mul(&u2))
+        .to_affine()
+        .ok_or("invalid secp256k1 signature")?;
+    Ok(encode_pub_key(&x, &y).into())
+}
+
+fn encode_pub_key(x: &U256, y: &U256) -> Vec<u8> {
+    let mut out = vec![0x02 | (y[0] & 1) as u8];
+    out.extend_from_slice(&to_be(x));
+    out
+}
+
+fn decode_pub_key(pub_key: &[u8]) -> LairResult<(U256, U256)> {
+    if pub_key.len() != PUB_KEY_BYTES || (pub_key[0] & !1) != 0x02 {
+        return Err("invalid secp256k1 pub key".into());
+    }
+    let x = from_be(&pub_key[1..]);
+    let y =
+        lift_x(&x, pub_key[0] & 1 == 1).ok_or("invalid secp256k1 pub key")?;
+    Ok((x, y))
+}
+
+/// Find the y coordinate of the curve point at x, with the given parity.
+fn lift_x(x: &U256, odd: bool) -> Option<U256> {
+    if cmp(x, &FP.m) != Ordering::Less {
+        return None;
+    }
+    let a = FP.add(&FP.mul(&FP.mul(x, x), x), &SEVEN);
+    // p = 3 mod 4, so the square root is a^((p + 1) / 4)
+    let (p1, _) = add(&FP.m, &ONE);
+    let y = FP.pow(&a, &shr1(&shr1(&p1)));
+    if FP.mul(&y, &y) != a {
+        return None;
+    }
+    Some(if (y[0] & 1 == 1) == odd {
+        y
+    } else {
+        FP.neg(&y)
+    })
+}
+
+fn half_n() -> U256 {
+    shr1(&FN.m)
+}
+
+// -- 256 bit arithmetic (little-endian u64 limbs) -- //
+
+type U256 = [u64; 4];
+
+const ONE: U256 = [1, 0, 0, 0];
+const SEVEN: U256 = [7, 0, 0, 0];
+
+fn from_be(b: &[u8]) -> U256 {
+    let mut out = [0; 4];
+    for (i, limb) in out.iter_mut().enumerate() {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&b[24 - i * 8..32 - i * 8]);
+        *limb = u64::from_be_bytes(bytes);
+    }
+    out
+}
+
+fn to_be(a: &U256) -> [u8; 32] {
+    let mut out = [0; 32];
+    for (i, limb) in a.iter().enumerate() {
+        out[24 - i * 8..32 - i * 8].copy_from_slice(&limb.to_be_bytes());
+    }
+    out
+}
+
+fn is_zero(a: &U256) -> bool {
+    a.iter().all(|l| *l == 0)
+}
+
+fn cmp(a: &U256, b: &U256) -> Ordering {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i].cmp(&b[i]);
+        }
+    }
+    Ordering::Equal
+}
+
+fn add(a: &U256, b: &U256) -> (U256, bool) {
+    let mut out = [0; 4];
+    let mut carry = 0_u128;
+    for i in 0..4 {
+        let t = a[i] as u128 + b[i] as u128 + carry;
+        out[i] = t as u64;
+        carry = t >> 64;
+    }
+    (out, carry == 1)
+}
+
+fn sub(a: &U256, b: &U256) -> (U256, bool) {
+    let mut out = [0; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u64);
+        out[i] = d;
+        borrow = b1 || b2;
+    }
+    (out, borrow)
+}
+
+fn shr1(a: &U256) -> U256 {
+    let mut out = [0; 4];
+    for i in 0..4 {
+        out[i] = a[i] >> 1;
+        if i < 3 {
+            out[i] |= a[i + 1] << 63;
+        }
+    }
+    out
+}
+
+fn mul_wide(a: &U256, b: &U256) -> [u64; 8] {
+    let mut out = [0; 8];
+    for i in 0..4 {
+        let mut carry = 0_u128;
+        for j in 0..4 {
+            let t = a[i] as u128 * b[j] as u128 + out[i + j] as u128 + carry;
+            out[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        out[i + 4] = carry as u64;
+    }
+    out
+}
+
+/// Arithmetic modulo `m = 2^256 - c`.
+/// Both the secp256k1 field prime and group order have this form.
+struct Modulus {
+    m: U256,
+    c: U256,
+}
+
+/// The secp256k1 field prime p.
+const FP: Modulus = Modulus {
+    m: [
+        0xfffffffefffffc2f,
+        0xffffffffffffffff,
+        0xffffffffffffffff,
+        0xffffffffffffffff,
+    ],
+    c: [0x1000003d1, 0, 0, 0],
+};
+
+/// The secp256k1 group order n.
+const FN: Modulus = Modulus {
+    m: [
+        0xbfd25e8cd0364141,
+        0xbaaedce6af48a03b,
+        0xfffffffffffffffe,
+        0xffffffffffffffff,
+    ],
+    c: [0x402da1732fc9bebf, 0x4551231950b75fc4, 1, 0],
+};
+
+impl Modulus {
+    /// Fully reduce a 256 bit value.
+    fn reduce(&self, mut a: U256) -> U256 {
+        while cmp(&a, &self.m) != Ordering::Less {
+            a = sub(&a, &self.m).0;
+        }
+        a
+    }
+
+    /// Reduce a 512 bit value, folding `hi * 2^256` into `hi * c`.
+    fn reduce_wide(&self, mut w: [u64; 8]) -> U256 {
+        loop {
+            let lo = [w[0], w[1], w[2], w[3]];
+            let hi = [w[4], w[5], w[6], w[7]];
+            if is_zero(&hi) {
+                return self.reduce(lo);
+            }
+            w = mul_wide(&hi, &self.c);
+            let mut carry = 0_u128;
+            for (i, w) in w.iter_mut().enumerate() {
+                let t = *w as u128 + *lo.get(i).unwrap_or(&0) as u128 + carry;
+                *w = t as u64;
+                carry = t >> 64;
+            }
+        }
+    }
+
+    fn add(&self, a: &U256, b: &U256) -> U256 {
+        match add(a, b) {
+            // a + b - 2^256 + c = a + b - m, which is below m
+            (s, true) => add(&s, &self.c).0,
+            (s, false) => self.reduce(s),
+        }
+    }
+
+    fn sub(&self, a: &U256, b: &U256) -> U256 {
+        match sub(a, b) {
+            (d, true) => add(&d, &self.m).0,
+            (d, false) => d,
+        }
+    }
+
+    fn neg(&self, a: &U256) -> U256 {
+        self.sub(&[0; 4], a)
+    }
+
+    fn mul(&self, a: &U256, b: &U256) -> U256 {
+        self.reduce_wide(mul_wide(a, b))
+    }
+
+    fn pow(&self, a: &U256, e: &U256) -> U256 {
+        let mut out = ONE;
+        for i in (0..256).rev() {
+            out = self.mul(&out, &out);
+            if (e[i / 64] >> (i % 64)) & 1 == 1 {
+                out = self.mul(&out, a);
+            }
+        }
+        out
+    }
+
+    /// Inverse by Fermat's little theorem, both moduli are prime.
+    fn inv(&self, a: &U256) -> U256 {
+        self.pow(a, &sub(&self.m, &[2, 0, 0, 0]).0)
+    }
+}
+
+// -- curve points (jacobian coordinates, y^2 = x^3 + 7) -- //
+
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+/// The secp256k1 generator point.
+const G: Point = Point {
+    x: [
+        0x59f2815b16f81798,
+        0x029bfcdb2dce28d9,
+        0x55a06295ce870b07,
+        0x79be667ef9dcbbac,
+    ],
+    y: [
+        0x9c47d08ffb10d4b8,
+        0xfd17b448a6855419,
+        0x5da4fbfc0e1108a8,
+        0x483ada7726a3c465,
+    ],
+    z: ONE,
+};
+
+const INFINITY: Point = Point {
+    x: ONE,
+    y: ONE,
+    z: [0; 4],
+};
+
+impl Point {
+    fn from_affine(x: U256, y: U256) -> Self {
+        Self { x, y, z: ONE }
+    }
+
+    fn is_infinity(&self) -> bool {
+        is_zero(&self.z)
+    }
+
+    fn to_affine(self) -> Option<(U256, U256)> {
+        if self.is_infinity() {
+            return None;
+        }
+        let z_inv = FP.inv(&self.z);
+        let z_inv2 = FP.mul(&z_inv, &z_inv);
+        let x = FP.mul(&self.x, &z_inv2);
+        let y = FP.mul(&self.y, &FP.mul(&z_inv2, &z_inv));
+        Some((x, y))
+    }
+
+    fn double(&self) -> Self {
+        if self.is_infinity() || is_zero(&self.y) {
+            return INFINITY;
+        }
+        let a = FP.mul(&self.x, &self.x);
+        let b = FP.mul(&self.y, &self.y);
+        let c = FP.mul(&b, &b);
+        let xb = FP.add(&self.x, &b);
+        let d = FP.sub(&FP.sub(&FP.mul(&xb, &xb), &a), &c);
+        let d = FP.add(&d, &d);
+        let e = FP.add(&FP.add(&a, &a), &a);
+        let f = FP.mul(&e, &e);
+        let x = FP.sub(&f, &FP.add(&d, &d));
+        let c8 = FP.add(&c, &c);
+        let c8 = FP.add(&c8, &c8);
+        let c8 = FP.add(&c8, &c8);
+        let y = FP.sub(&FP.mul(&e, &FP.sub(&d, &x)), &c8);
+        let yz = FP.mul(&self.y, &self.z);
+        let z = FP.add(&yz, &yz);
+        Self { x, y, z }
+    }
+
+    fn add(&self, o: &Self) -> Self {
+        if self.is_infinity() {
+            return *o;
+        }
+        if o.is_infinity() {
+            return *self;
+        }
+        let z1z1 = FP.mul(&self.z, &self.z);
+        let z2z2 = FP.mul(&o.z, &o.z);
+        let u1 = FP.mul(&self.x, &z2z2);
+        let u2 = FP.mul(&o.x, &z1z1);
+        let s1 = FP.mul(&self.y, &FP.mul(&o.z, &z2z2));
+        let s2 = FP.mul(&o.y, &FP.mul(&self.z, &z1z1));
+        let h = FP.sub(&u2, &u1);
+        let r = FP.sub(&s2, &s1);
+        if is_zero(&h) {
+            if is_zero(&r) {
+                return self.double();
+            }
+            return INFINITY;
+        }
+        let hh = FP.mul(&h, &h);
+        let hhh = FP.mul(&h, &hh);
+        let v = FP.mul(&u1, &hh);
+        let x = FP.sub(&FP.sub(&FP.mul(&r, &r), &hhh), &FP.add(&v, &v));
+        let y = FP.sub(&FP.mul(&r, &FP.sub(&v, &x)), &FP.mul(&s1, &hhh));
+        let z = FP.mul(&FP.mul(&self.z, &o.z), &h);
+        Self { x, y, z }
+    }
+
+    fn mul(&self, k: &U256) -> Self {
+        let mut out = INFINITY;
+        for i in (0..256).rev() {
+            out = out.double();
+            if (k[i / 64] >> (i % 64)) & 1 == 1 {
+                out = out.add(self);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut out = [0; 32];
+        out.copy_from_slice(
+            ring::digest::digest(&ring::digest::SHA256, data).as_ref(),
+        );
+        out
+    }
+
+    #[test]
+    fn it_has_consistent_curve_constants() {
+        for m in [&FP, &FN] {
+            assert_eq!(m.c, sub(&[0; 4], &m.m).0);
+        }
+        let (x, y) = G.to_affine().unwrap();
+        assert_eq!(lift_x(&x, y[0] & 1 == 1), Some(y));
+
+        let two_g = G.double().to_affine().unwrap();
+        assert_eq!(
+            "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+            hex(&to_be(&two_g.0)),
+        );
+        assert_eq!(
+            "1ae168fea63dc339a3c58419466ceaeef7f632653266d0e1236431a950cfe52a",
+            hex(&to_be(&two_g.1)),
+        );
+        assert_eq!(Some(two_g), G.add(&G).to_affine());
+        assert_eq!(Some(two_g), G.mul(&[2, 0, 0, 0]).to_affine());
+        assert_eq!(
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+            hex(&to_be(&G.mul(&[3, 0, 0, 0]).to_affine().unwrap().0)),
+        );
+
+        // n is the order of G
+        assert!(G.mul(&FN.m).is_infinity());
+        let n_minus_1 = sub(&FN.m, &ONE).0;
+        assert_eq!(Some((x, FP.neg(&y))), G.mul(&n_minus_1).to_affine(),);
+    }
+
+    /// RFC 6979 secp256k1 / sha256 vectors (as widely used by bitcoin
+    /// libraries), normalized to low s.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_rfc6979_vectors() {
+        let one = {
+            let mut k = vec![0; 32];
+            k[31] = 1;
+            k
+        };
+        let vectors: &[(&[u8], &str, &str)] = &[
+            (
+                b"Satoshi Nakamoto",
+                "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8",
+                "2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+            ),
+            (
+                b"All those moments will be lost in time, \
+                like tears in rain. Time to die...",
+                "8600dbd41e348fe5c9465ab92d23e3db8b98b873beecd930736488696438cb6b",
+                "547fe64427496db33bf66019dacbf0039c04199abb0122918601db38a72cfc21",
+            ),
+        ];
+        let keypair = keypair_from_priv_key(one.clone()).unwrap();
+        assert_eq!(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            hex(&keypair.pub_key),
+        );
+        for (message, r, s) in vectors {
+            let digest = sha256(message);
+            let sig = sign_secp256k1(keypair.priv_key.clone(), digest)
+                .await
+                .unwrap();
+            assert_eq!(*r, hex(&sig[..32]));
+            assert_eq!(*s, hex(&sig[32..64]));
+            assert!(keypair.pub_key.verify(digest, sig).await.unwrap());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_sign_verify_and_recover() {
+        let entry::EntrySignSecp256k1 { priv_key, pub_key } =
+            sign_secp256k1_keypair_new_from_entropy().await.unwrap();
+        assert_eq!(PUB_KEY_BYTES, pub_key.len());
+
+        for i in 0..8_u8 {
+            let digest = sha256(&[i]);
+            let sig = sign_secp256k1(priv_key.clone(), digest).await.unwrap();
+            assert_eq!(SIGNATURE_BYTES, sig.len());
+            assert!(sig[64] < 2);
+            assert_ne!(
+                Ordering::Greater,
+                cmp(&from_be(&sig[32..64]), &half_n())
+            );
+            assert_eq!(
+                pub_key,
+                sign_secp256k1_recover(digest, sig.clone()).await.unwrap()
+            );
+            assert!(pub_key.verify(digest, sig.clone()).await.unwrap());
+
+            // deterministic
+            assert_eq!(
+                sig,
+                sign_secp256k1(priv_key.clone(), digest).await.unwrap()
+            );
+
+            // wrong digest, tampered signature, wrong recovery id
+            assert!(!pub_key.verify(sha256(b"x"), sig.clone()).await.unwrap());
+            let mut bad = sig.to_vec();
+            bad[5] ^= 1;
+            assert!(!pub_key.verify(digest, bad.into()).await.unwrap());
+            let mut bad = sig.to_vec();
+            bad[64] ^= 1;
+            assert!(!pub_key.verify(digest, bad.into()).await.unwrap());
+        }
+
+        let uncompressed = pub_key.to_uncompressed().unwrap();
+        assert_eq!(65, uncompressed.len());
+        assert_eq!(0x04, uncompressed[0]);
+        assert_eq!(pub_key[1..], uncompressed[1..33]);
+        assert_eq!(32, pub_key.pub_id().len());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_rejects_bad_keys_and_signatures() {
+        assert!(keypair_from_priv_key(vec![0; 32]).is_err());
+        assert!(keypair_from_priv_key(to_be(&FN.m).to_vec()).is_err());
+        assert!(keypair_from_priv_key(vec![1; 31]).is_err());
+        assert!(
+            keypair_from_priv_key(to_be(&sub(&FN.m, &ONE).0).to_vec()).is_ok()
+        );
+
+        let digest = sha256(b"test");
+        for sig in [
+            vec![0; 65],
+            vec![0; 64],
+            [to_be(&ONE), to_be(&FN.m), [0; 32]].concat()[..65].to_vec(),
+            [to_be(&ONE), to_be(&ONE), [4; 32]].concat()[..65].to_vec(),
+        ] {
+            assert!(sign_secp256k1_recover(digest, sig.into()).await.is_err());
+        }
+
+        assert!(SignSecp256k1PubKey::from(vec![0x02; 32])
+            .to_uncompressed()
+            .is_err());
+        assert!(SignSecp256k1PubKey::from(vec![0x04; 33])
+            .to_uncompressed()
+            .is_err());
+        // x = 5 is not on the curve
+        let mut off_curve = vec![0x02];
+        off_curve.extend_from_slice(&to_be(&[5, 0, 0, 0]));
+        assert!(SignSecp256k1PubKey::from(off_curve)
+            .to_uncompressed()
+            .is_err());
+    }
+}
@@ -1,8 +1,13 @@
 //! Utilities for generating / managing TLS certificates and keypairs.
 
 use crate::*;
-use actor::{TlsCertAlg, TlsCertOptions};
+use actor::{
+    Cert, CertPrivKey, CertSni, KeystoreIndex, TlsCertAlg, TlsCertDigestAlg,
+    TlsCertOptions, TlsCertSignatureScheme,
+};
+use chrono::Timelike;
 use once_cell::sync::Lazy;
+use std::convert::TryFrom;
 
 /// The well-known CA keypair in plaintext pem format.
 /// Some TLS clients require CA roots to validate client-side certificates.
@@ -47,17 +52,292 @@ pub static WK_CA_CERT_DER: Lazy<Arc<Vec<u8>>> = Lazy::new(|| {
     Arc::new(cert)
 });
 
+/// Maximum combined byte length of the sni and subject alt names of a
+/// tls cert. Generation may still reject names within this limit if the
+/// resulting cert would not fit in a keystore entry.
+pub const TLS_CERT_NAMES_MAX_BYTES: usize = 128;
+
+/// Maximum count of subject alt names of a tls cert.
+pub const TLS_CERT_ALT_NAMES_MAX: usize = 16;
+
+/// Check the sni and subject alt names of `options` are valid.
+/// The sni must be a DNS hostname, alt names may be DNS hostnames
+/// (optionally wildcard, e.g. `*.example.com`) or IP addresses.
+/// Fails with `LairError::TlsCertName`.
+pub fn check_tls_cert_options(options: &TlsCertOptions) -> LairResult<()> {
+    if options.alt_names.len() > TLS_CERT_ALT_NAMES_MAX {
+        return Err(LairError::TlsCertName(format!(
+            "more than {} alt names",
+            TLS_CERT_ALT_NAMES_MAX
+        )));
+    }
+    let mut names_len = 0;
+    if let Some(sni) = &options.sni {
+        if sni.parse::<std::net::IpAddr>().is_ok() || !is_dns_name(sni, false) {
+            return Err(LairError::TlsCertName(format!(
+                "{:?} is not a valid sni hostname",
+                sni
+            )));
+        }
+        names_len += sni.len();
+    }
+    for alt_name in options.alt_names.iter() {
+        if alt_name.parse::<std::net::IpAddr>().is_err()
+            && !is_dns_name(alt_name, true)
+        {
+            return Err(LairError::TlsCertName(format!(
+                "{:?} is not a valid hostname or ip address",
+                alt_name
+            )));
+        }
+        names_len += alt_name.len();
+    }
+    if names_len > TLS_CERT_NAMES_MAX_BYTES {
+        return Err(LairError::TlsCertName(format!(
+            "names exceed {} bytes",
+            TLS_CERT_NAMES_MAX_BYTES
+        )));
+    }
+    Ok(())
+}
+
+/// RFC 1123 hostname: dot separated labels of 1 to 63 letters, digits
+/// and (not leading or trailing) hyphens.
+fn is_dns_name(name: &str, allow_wildcard: bool) -> bool {
+    let name = match name.strip_prefix("*.") {
+        Some(name) if allow_wildcard => name,
+        _ => name,
+    };
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+/// Check the options for importing a tls cert are valid.
+/// As with `check_tls_cert_options`, but the alt names of an imported
+/// cert are fixed, so `options.alt_names` must be empty.
+pub fn check_tls_cert_import_options(
+    options: &TlsCertOptions,
+) -> LairResult<()> {
+    check_tls_cert_options(options)?;
+    if !options.alt_names.is_empty() {
+        return Err(LairError::TlsCertName(
+            "the alt names of an imported cert cannot be changed".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// True if the cert name `pattern` (optionally wildcard) covers `name`.
+fn name_covers(pattern: &str, name: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(parent) => match name.split_once('.') {
+            Some((label, rest)) => {
+                !label.is_empty() && rest.eq_ignore_ascii_case(parent)
+            }
+            None => false,
+        },
+        None => pattern.eq_ignore_ascii_case(name),
+    }
+}
+
 /// Generate a new random Tls keypair and self signed certificate.
+/// The sni is random unless `options.sni` is set, `options.alt_names`
+/// are added to the cert's subject alt names alongside it.
 pub async fn tls_cert_self_signed_new_from_entropy(
     options: TlsCertOptions,
 ) -> LairResult<entry::EntryTlsCert> {
+    tls_cert_generate(options, None, Signer::WellKnownCa).await
+}
+
+/// Generate a new random Tls keypair and a truly self signed CA
+/// certificate for it, to issue certs with `tls_cert_new_signed_by_ca`.
+/// The sni and alt names are as for `tls_cert_self_signed_new_from_entropy`.
+pub async fn tls_ca_new_self_signed(
+    options: TlsCertOptions,
+) -> LairResult<entry::EntryTlsCert> {
+    tls_cert_generate(options, None, Signer::SelfCa).await
+}
+
+/// Generate a new random Tls keypair and a certificate for it,
+/// signed by the CA cert (and private key) of `ca`.
+/// The sni and alt names are as for `tls_cert_self_signed_new_from_entropy`.
+/// Fails with `LairError::TlsCertNotCa` if `ca` is not a CA cert.
+pub async fn tls_cert_new_signed_by_ca(
+    ca: &entry::EntryTlsCert,
+    options: TlsCertOptions,
+) -> LairResult<entry::EntryTlsCert> {
+    if !is_ca(&ca.cert_der)? {
+        return Err(LairError::TlsCertNotCa);
+    }
+    tls_cert_generate(
+        options,
+        None,
+        Signer::Ca(ca.cert_der.clone(), ca.priv_key_der.clone()),
+    )
+    .await
+}
+
+/// Issue a new self signed certificate, valid from now, for the keypair
+/// of an existing tls cert, so peers pinning the key keep trusting it.
+/// The sni is kept unless `options.sni` is set, `options.alt_names`
+/// replace any previous alt names, and `options.alg` is ignored
+/// in favor of the algorithm of the existing key.
+/// CA certs renew as CA certs, any other cert renews signed by the
+/// well-known lair CA.
+/// The key of a non-exportable cert stays non-exportable.
+pub async fn tls_cert_self_signed_renew(
+    cert: &entry::EntryTlsCert,
+    mut options: TlsCertOptions,
+) -> LairResult<entry::EntryTlsCert> {
+    // unset keeps the flag, and a key not exportable stays so
+    options.exportable =
+        Some(cert.exportable && options.exportable.unwrap_or(true));
+    tls_cert_generate(
+        options,
+        Some((cert.sni.clone(), cert.priv_key_der.clone())),
+        if is_ca(&cert.cert_der)? {
+            Signer::SelfCa
+        } else {
+            Signer::WellKnownCa
+        },
+    )
+    .await
+}
+
+/// Import an externally issued certificate and its pkcs8 private key.
+/// The sni is the first (non-wildcard) dns name of the cert's subject
+/// alt names unless `options.sni` is set, which must be covered by one.
+/// `options.alg` is ignored in favor of the algorithm of the key.
+/// Fails with `LairError::TlsCertDer` if either cannot be parsed (or
+/// the pair won't fit in a keystore entry), `LairError::TlsCertKeyMismatch`
+/// if the key is not the one certified, and `LairError::TlsCertName`
+/// if there is no usable sni.
+pub async fn tls_cert_import(
+    cert_der: Cert,
+    priv_key_der: CertPrivKey,
+    options: TlsCertOptions,
+) -> LairResult<entry::EntryTlsCert> {
+    check_tls_cert_import_options(&options)?;
     rayon_exec(move || {
-        let sni = format!("a{}a.a{}a", nanoid::nanoid!(), nanoid::nanoid!());
+        let fields = parse_cert_der(&cert_der)
+            .map_err(|e| LairError::TlsCertDer(format!("cert: {}", e)))?;
+        let key_pair =
+            rcgen::KeyPair::try_from(&priv_key_der[..]).map_err(|e| {
+                LairError::TlsCertDer(format!("private key: {}", e))
+            })?;
+        if key_pair.public_key_raw() != &fields.pub_key[..] {
+            return Err(LairError::TlsCertKeyMismatch);
+        }
+
+        let sni = match options.sni {
+            Some(sni) => {
+                if !fields.dns_names.iter().any(|n| name_covers(n, &sni)) {
+                    return Err(LairError::TlsCertName(format!(
+                        "{:?} is not a name of the cert",
+                        sni
+                    )));
+                }
+                sni
+            }
+            None => fields
+                .dns_names
+                .into_iter()
+                .find(|n| {
+                    !n.is_empty()
+                        && !n.starts_with("*.")
+                        && n.len() <= TLS_CERT_NAMES_MAX_BYTES
+                })
+                .ok_or_else(|| {
+                    LairError::TlsCertName(
+                        "the cert has no dns name to use as sni".into(),
+                    )
+                })?,
+        };
+
+        let cert = entry::EntryTlsCert {
+            sni: sni.into(),
+            cert_digest: tls_cert_digest(options.digest_alg, &cert_der).into(),
+            digest_alg: options.digest_alg,
+            alg: tls_cert_alg_of_key_pair(&key_pair)?,
+            exportable: options.exportable.unwrap_or(true),
+            priv_key_der,
+            cert_der,
+        };
+        check_fits_entry(&cert).map_err(|_| {
+            LairError::TlsCertDer(
+                "the cert and key are too large to fit in an entry".into(),
+            )
+        })?;
+
+        Ok(cert)
+    })
+    .await
+}
+
+/// A random serial for a generated cert, kept in its entry with the
+/// rest of the cert: positive, and always 8 bytes long, so an entry
+/// has the same room for it whatever the serial (x509 allows up to 20).
+fn random_cert_serial() -> u64 {
+    use rand::Rng;
+    // the high bit clear keeps it positive without a leading zero byte,
+    // the next bit set keeps it from being any shorter
+    rand::thread_rng().gen::<u64>() >> 1 | 1 << 62
+}
+
+/// The signer of a generated tls cert.
+enum Signer {
+    /// The well-known lair CA, the cert is pseudo-self-signed.
+    WellKnownCa,
+    /// The cert's own key, the cert is a self signed CA cert.
+    SelfCa,
+    /// A CA cert and its private key.
+    Ca(Cert, CertPrivKey),
+}
+
+async fn tls_cert_generate(
+    options: TlsCertOptions,
+    renew: Option<(CertSni, CertPrivKey)>,
+    signer: Signer,
+) -> LairResult<entry::EntryTlsCert> {
+    check_tls_cert_options(&options)?;
+    // the issuer name of a CA signed cert is of any length
+    let custom_names = options.sni.is_some()
+        || !options.alt_names.is_empty()
+        || renew.is_some()
+        || matches!(signer, Signer::Ca(..));
+    rayon_exec(move || {
+        let sni = match (options.sni, &renew) {
+            (Some(sni), _) => sni,
+            (None, Some((sni, _))) => sni.to_string(),
+            (None, None) => {
+                format!("a{}a.a{}a", nanoid::nanoid!(), nanoid::nanoid!())
+            }
+        };
 
         let mut params = rcgen::CertificateParams::new(vec![sni.clone()]);
+        params.serial_number = Some(random_cert_serial());
+        for alt_name in options.alt_names {
+            let alt_name = match alt_name.parse() {
+                Ok(ip) => rcgen::SanType::IpAddress(ip),
+                Err(_) => rcgen::SanType::DnsName(alt_name),
+            };
+            if !params.subject_alt_names.contains(&alt_name) {
+                params.subject_alt_names.push(alt_name);
+            }
+        }
 
+        let mut alg = options.alg;
         #[allow(unreachable_patterns)]
-        match options.alg {
+        match alg {
             TlsCertAlg::PkcsEd25519 => params.alg = &rcgen::PKCS_ED25519,
             TlsCertAlg::PkcsEcdsaP256Sha256 => {
                 params.alg = &rcgen::PKCS_ECDSA_P256_SHA256
@@ -65,7 +345,11 @@ pub async fn tls_cert_self_signed_new_from_entropy(
             TlsCertAlg::PkcsEcdsaP384Sha384 => {
                 params.alg = &rcgen::PKCS_ECDSA_P384_SHA384
             }
-            TlsCertAlg::PkcsEd25519 => params.alg = &rcgen::PKCS_ED25519,
+            // ring cannot generate rsa keys, but can renew imported ones
+            TlsCertAlg::PkcsRsaSha256 if renew.is_some() => (),
+            TlsCertAlg::PkcsRsaSha256 => {
+                return Err("rsa tls certs can only be imported".into())
+            }
             _ => {
                 return Err(
                     format!("unhandled cert alg: {:?}", options.alg).into()
@@ -73,19 +357,49 @@ pub async fn tls_cert_self_signed_new_from_entropy(
             }
         };
 
-        params
-            .extended_key_usages
-            .push(rcgen::ExtendedKeyUsagePurpose::Any);
-        params
-            .extended_key_usages
-            .push(rcgen::ExtendedKeyUsagePurpose::ServerAuth);
-        params
-            .extended_key_usages
-            .push(rcgen::ExtendedKeyUsagePurpose::ClientAuth);
+        if let Signer::SelfCa = signer {
+            params.is_ca =
+                rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            params.key_usages = vec![
+                rcgen::KeyUsagePurpose::KeyCertSign,
+                rcgen::KeyUsagePurpose::CrlSign,
+                rcgen::KeyUsagePurpose::DigitalSignature,
+            ];
+        } else {
+            params
+                .extended_key_usages
+                .push(rcgen::ExtendedKeyUsagePurpose::Any);
+            params
+                .extended_key_usages
+                .push(rcgen::ExtendedKeyUsagePurpose::ServerAuth);
+            params
+                .extended_key_usages
+                .push(rcgen::ExtendedKeyUsagePurpose::ClientAuth);
+        }
+        if let Some((_, priv_key_der)) = renew {
+            let key_pair = rcgen::KeyPair::try_from(&priv_key_der[..])
+                .map_err(LairError::other)?;
+            params.alg = key_pair
+                .compatible_algs()
+                .next()
+                .ok_or("no algorithm for tls cert keypair")?;
+            alg = tls_cert_alg_of_key_pair(&key_pair)?;
+            params.key_pair = Some(key_pair);
+            // x509 validity has whole second precision
+            params.not_before = chrono::Utc::now()
+                .with_nanosecond(0)
+                .ok_or("invalid tls cert validity start")?;
+        }
         params.distinguished_name = rcgen::DistinguishedName::new();
         params.distinguished_name.push(
             rcgen::DnType::CommonName,
-            format!("Lair Pseudo-Self-Signed Cert {}", &sni),
+            match signer {
+                Signer::WellKnownCa => {
+                    format!("Lair Pseudo-Self-Signed Cert {}", &sni)
+                }
+                Signer::SelfCa => format!("Lair Self-Signed CA {}", &sni),
+                Signer::Ca(..) => format!("Lair CA Signed Cert {}", &sni),
+            },
         );
 
         let cert = rcgen::Certificate::from_params(params)
@@ -93,29 +407,383 @@ pub async fn tls_cert_self_signed_new_from_entropy(
 
         let priv_key_der = cert.serialize_private_key_der();
 
-        let root_cert = &**WK_CA_RCGEN_CERT;
-        let cert_der = cert
-            .serialize_der_with_signer(root_cert)
-            .map_err(LairError::other)?;
+        let cert_der = match signer {
+            Signer::WellKnownCa => {
+                let root_cert = &**WK_CA_RCGEN_CERT;
+                cert.serialize_der_with_signer(root_cert)
+                    .map_err(LairError::other)?
+            }
+            Signer::SelfCa => cert.serialize_der().map_err(LairError::other)?,
+            Signer::Ca(ca_cert_der, ca_priv_key_der) => {
+                let key_pair = rcgen::KeyPair::try_from(&ca_priv_key_der[..])
+                    .map_err(LairError::other)?;
+                let ca_params = rcgen::CertificateParams::from_ca_cert_der(
+                    &ca_cert_der,
+                    key_pair,
+                )
+                .map_err(LairError::other)?;
+                let ca_cert = rcgen::Certificate::from_params(ca_params)
+                    .map_err(LairError::other)?;
+                let cert_der = cert
+                    .serialize_der_with_signer(&ca_cert)
+                    .map_err(LairError::other)?;
+                // verifiers match the issuer to the CA subject byte for
+                // byte, rcgen re-encodes the name it read from the CA
+                let issuer =
+                    parse_cert_der(&cert_der).map_err(LairError::other)?.issuer;
+                let subject = parse_cert_der(&ca_cert_der)
+                    .map_err(LairError::other)?
+                    .subject;
+                if issuer != subject {
+                    return Err("the CA cert subject cannot be reproduced \
+                        as the issuer of a new cert"
+                        .into());
+                }
+                cert_der
+            }
+        };
 
-        let cert_digest = blake2b_simd::Params::new()
-            .hash_length(32)
-            .to_state()
-            .update(&cert_der)
-            .finalize()
-            .as_bytes()
-            .to_vec();
+        let cert_digest = tls_cert_digest(options.digest_alg, &cert_der);
 
-        Ok(entry::EntryTlsCert {
+        let cert = entry::EntryTlsCert {
             sni: sni.into(),
             priv_key_der: priv_key_der.into(),
             cert_der: cert_der.into(),
             cert_digest: cert_digest.into(),
-        })
+            digest_alg: options.digest_alg,
+            alg,
+            exportable: options.exportable.unwrap_or(true),
+        };
+
+        // custom (or renewed) names lengthen the cert, it must still fit
+        if custom_names {
+            check_fits_entry(&cert).map_err(|_| {
+                LairError::TlsCertName(
+                    "names too long to fit the cert in an entry".into(),
+                )
+            })?;
+        }
+
+        Ok(cert)
+    })
+    .await
+}
+
+/// The algorithm of a DER encoded pkcs #8 tls cert private key.
+pub fn tls_cert_alg_of_key(priv_key_der: &[u8]) -> LairResult<TlsCertAlg> {
+    let key_pair =
+        rcgen::KeyPair::try_from(priv_key_der).map_err(LairError::other)?;
+    tls_cert_alg_of_key_pair(&key_pair)
+}
+
+fn tls_cert_alg_of_key_pair(
+    key_pair: &rcgen::KeyPair,
+) -> LairResult<TlsCertAlg> {
+    let alg = key_pair
+        .compatible_algs()
+        .next()
+        .ok_or("no algorithm for tls cert keypair")?;
+    Ok(if alg == &rcgen::PKCS_ED25519 {
+        TlsCertAlg::PkcsEd25519
+    } else if alg == &rcgen::PKCS_ECDSA_P256_SHA256 {
+        TlsCertAlg::PkcsEcdsaP256Sha256
+    } else if alg == &rcgen::PKCS_ECDSA_P384_SHA384 {
+        TlsCertAlg::PkcsEcdsaP384Sha384
+    } else if alg == &rcgen::PKCS_RSA_SHA256 {
+        TlsCertAlg::PkcsRsaSha256
+    } else {
+        return Err(format!("unhandled cert alg: {:?}", alg).into());
+    })
+}
+
+/// Sign a tls handshake `message` with a DER encoded pkcs #8 private key
+/// of algorithm `alg`, which `scheme` must be a scheme for.
+#[allow(clippy::rc_buffer)]
+pub async fn tls_cert_sign(
+    alg: TlsCertAlg,
+    priv_key_der: CertPrivKey,
+    scheme: TlsCertSignatureScheme,
+    message: Arc<Vec<u8>>,
+) -> LairResult<Arc<Vec<u8>>> {
+    if scheme.alg() != alg {
+        return Err(format!(
+            "signature scheme {:?} is not for {:?} tls cert keys",
+            scheme, alg
+        )
+        .into());
+    }
+    rayon_exec(move || {
+        use ring::signature::*;
+        let rng = ring::rand::SystemRandom::new();
+        let ecdsa = |alg| {
+            EcdsaKeyPair::from_pkcs8(alg, &priv_key_der)
+                .map_err(|e| format!("{:?}", e))?
+                .sign(&rng, &message)
+                .map(|sig| sig.as_ref().to_vec())
+                .map_err(|e| format!("{:?}", e))
+        };
+        let rsa = |padding| -> Result<Vec<u8>, String> {
+            let key_pair = RsaKeyPair::from_pkcs8(&priv_key_der)
+                .map_err(|e| format!("{:?}", e))?;
+            let mut sig = vec![0; key_pair.public_modulus_len()];
+            key_pair
+                .sign(padding, &rng, &message, &mut sig)
+                .map_err(|e| format!("{:?}", e))?;
+            Ok(sig)
+        };
+        let sig = match scheme {
+            TlsCertSignatureScheme::Ed25519 => {
+                Ed25519KeyPair::from_pkcs8_maybe_unchecked(&priv_key_der)
+                    .map_err(|e| format!("{:?}", e))?
+                    .sign(&message)
+                    .as_ref()
+                    .to_vec()
+            }
+            TlsCertSignatureScheme::EcdsaSecp256r1Sha256 => {
+                ecdsa(&ECDSA_P256_SHA256_ASN1_SIGNING)?
+            }
+            TlsCertSignatureScheme::EcdsaSecp384r1Sha384 => {
+                ecdsa(&ECDSA_P384_SHA384_ASN1_SIGNING)?
+            }
+            TlsCertSignatureScheme::RsaPkcs1Sha256 => rsa(&RSA_PKCS1_SHA256)?,
+            TlsCertSignatureScheme::RsaPssRsaeSha256 => rsa(&RSA_PSS_SHA256)?,
+        };
+        Ok(Arc::new(sig))
     })
     .await
 }
 
+/// Find the single cert among `certs` whose digest starts with `prefix`.
+/// Fails with `LairError::TlsCertDigestPrefixAmbiguous`, listing the
+/// matches in index order, if more than one matches.
+pub fn tls_cert_find_by_digest_prefix<'a>(
+    prefix: &[u8],
+    certs: impl Iterator<Item = (KeystoreIndex, &'a entry::EntryTlsCert)>,
+) -> LairResult<(KeystoreIndex, &'a entry::EntryTlsCert)> {
+    let mut found = certs
+        .filter(|(_, cert)| cert.cert_digest.starts_with(prefix))
+        .collect::<Vec<_>>();
+    found.sort_by_key(|(index, _)| *index);
+    match found.len() {
+        0 => Err("no tls cert digest starts with the prefix".into()),
+        1 => Ok(found.remove(0)),
+        _ => Err(LairError::TlsCertDigestPrefixAmbiguous(
+            found
+                .into_iter()
+                .map(|(index, cert)| {
+                    let digest = cert
+                        .cert_digest
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>();
+                    format!("{}:{}", index, digest)
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// The 32 byte digest identifying a DER encoded certificate.
+pub fn tls_cert_digest(
+    digest_alg: TlsCertDigestAlg,
+    cert_der: &[u8],
+) -> Vec<u8> {
+    match digest_alg {
+        TlsCertDigestAlg::Blake2b256 => blake2b_simd::Params::new()
+            .hash_length(32)
+            .to_state()
+            .update(cert_der)
+            .finalize()
+            .as_bytes()
+            .to_vec(),
+        TlsCertDigestAlg::Sha256 => {
+            ring::digest::digest(&ring::digest::SHA256, cert_der)
+                .as_ref()
+                .to_vec()
+        }
+    }
+}
+
+/// Check a tls cert fits in an entry beside the largest metadata
+/// the store may record, sealed or not.
+fn check_fits_entry(cert: &entry::EntryTlsCert) -> LairResult<()> {
+    let mut meta = entry::EntryMeta::new_created_now(Some(
+        "t".repeat(entry::MAX_TAG_BYTES),
+    ));
+    meta.record_use_now();
+    let key = entry::EntryStoreKey::new_random()?;
+    entry::LairEntry::TlsCert(cert.clone()).seal_with_meta(&meta, &key)?;
+    Ok(())
+}
+
+/// The parts of an x509 certificate lair reads back out of its DER.
+struct CertFields {
+    /// the content bytes of the serialNumber INTEGER
+    serial: Vec<u8>,
+    /// DER encoded issuer name
+    issuer: Vec<u8>,
+    /// notAfter, in unix epoch milliseconds
+    not_after: i64,
+    /// DER encoded subject name
+    subject: Vec<u8>,
+    /// raw subjectPublicKey bits of the subjectPublicKeyInfo
+    pub_key: Vec<u8>,
+    /// dNSName subject alt names
+    dns_names: Vec<String>,
+    /// the basic constraints extension marks this a CA cert
+    is_ca: bool,
+}
+
+/// x509 subject alt name extension
+const OID_SUBJECT_ALT_NAME: &[u64] = &[2, 5, 29, 17];
+
+/// x509 basic constraints extension
+const OID_BASIC_CONSTRAINTS: &[u64] = &[2, 5, 29, 19];
+
+fn parse_cert_der(cert_der: &[u8]) -> yasna::ASN1Result<CertFields> {
+    use yasna::tags::{TAG_GENERALIZEDTIME, TAG_UTCTIME};
+    use yasna::Tag;
+
+    yasna::parse_der(cert_der, |r| {
+        r.read_sequence(|r| {
+            // tbsCertificate
+            let fields = r.next().read_sequence(|r| {
+                // version [0] EXPLICIT DEFAULT v1
+                r.read_optional(|r| {
+                    r.read_tagged(Tag::context(0), |r| r.read_der())
+                })?;
+                let serial = r.next().read_tagged_der()?.value().to_vec();
+                // signature
+                r.next().read_der()?;
+                let issuer = r.next().read_der()?;
+                let not_after = r.next().read_sequence(|r| {
+                    let mut read_time = || {
+                        let r = r.next();
+                        if r.lookahead_tag()? == TAG_UTCTIME {
+                            Ok(r.read_utctime()?.datetime().timestamp_millis())
+                        } else if r.lookahead_tag()? == TAG_GENERALIZEDTIME {
+                            Ok(r.read_generalized_time()?
+                                .datetime()
+                                .timestamp_millis())
+                        } else {
+                            Err(yasna::ASN1Error::new(
+                                yasna::ASN1ErrorKind::Invalid,
+                            ))
+                        }
+                    };
+                    let _not_before = read_time()?;
+                    read_time()
+                })?;
+                let subject = r.next().read_der()?;
+                let pub_key = r.next().read_sequence(|r| {
+                    r.next().read_der()?;
+                    Ok(r.next().read_bitvec_bytes()?.0)
+                })?;
+                // optional uniqueIDs, then extensions [3] EXPLICIT
+                let mut dns_names = Vec::new();
+                let mut is_ca = false;
+                while let Some(der) = r.read_optional(|r| r.read_der())? {
+                    if der.first() != Some(&0xa3) {
+                        continue;
+                    }
+                    yasna::parse_der(&der, |r| {
+                        r.read_tagged(Tag::context(3), |r| {
+                            r.read_sequence_of(|r| {
+                                read_extension(r, &mut dns_names, &mut is_ca)
+                            })
+                        })
+                    })?;
+                }
+                Ok(CertFields {
+                    serial,
+                    issuer,
+                    not_after,
+                    subject,
+                    pub_key,
+                    dns_names,
+                    is_ca,
+                })
+            })?;
+            // signatureAlgorithm, signatureValue
+            r.next().read_der()?;
+            r.next().read_der()?;
+            Ok(fields)
+        })
+    })
+}
+
+/// Collect the dNSNames of the subject alt name extension,
+/// and the CA flag of the basic constraints extension.
+/// Other x509 extensions are skipped.
+fn read_extension(
+    r: yasna::BERReader,
+    dns_names: &mut Vec<String>,
+    is_ca: &mut bool,
+) -> yasna::ASN1Result<()> {
+    use yasna::models::ObjectIdentifier;
+    use yasna::Tag;
+
+    r.read_sequence(|r| {
+        let oid = r.next().read_oid()?;
+        // critical BOOLEAN DEFAULT FALSE
+        r.read_optional(|r| r.read_bool())?;
+        let value = r.next().read_bytes()?;
+        if oid == ObjectIdentifier::from_slice(OID_BASIC_CONSTRAINTS) {
+            // cA BOOLEAN DEFAULT FALSE, pathLenConstraint OPTIONAL
+            *is_ca = yasna::parse_der(&value, |r| {
+                r.read_sequence(|r| {
+                    let ca = r.read_optional(|r| r.read_bool())?;
+                    r.read_optional(|r| r.read_der())?;
+                    Ok(ca.unwrap_or(false))
+                })
+            })?;
+            return Ok(());
+        }
+        if oid != ObjectIdentifier::from_slice(OID_SUBJECT_ALT_NAME) {
+            return Ok(());
+        }
+        yasna::parse_der(&value, |r| {
+            r.read_sequence_of(|r| {
+                if r.lookahead_tag()? == Tag::context(2) {
+                    dns_names.push(
+                        r.read_tagged_implicit(Tag::context(2), |r| {
+                            r.read_ia5_string()
+                        })?,
+                    );
+                } else {
+                    r.read_der()?;
+                }
+                Ok(())
+            })
+        })
+    })
+}
+
+/// True if the basic constraints of a DER encoded x509 certificate
+/// mark it a CA cert.
+fn is_ca(cert_der: &[u8]) -> LairResult<bool> {
+    Ok(parse_cert_der(cert_der).map_err(LairError::other)?.is_ca)
+}
+
+/// Extract the notAfter validity date of a DER encoded x509 certificate,
+/// in unix epoch milliseconds.
+pub fn tls_cert_not_after(cert_der: &[u8]) -> LairResult<u64> {
+    let not_after = parse_cert_der(cert_der)
+        .map_err(LairError::other)?
+        .not_after;
+
+    if not_after < 0 {
+        return Err("tls cert not_after predates the unix epoch".into());
+    }
+    Ok(not_after as u64)
+}
+
+/// Extract the serial number of a DER encoded x509 certificate,
+/// as the big-endian bytes of the (two's complement) integer.
+pub fn tls_cert_serial(cert_der: &[u8]) -> LairResult<Vec<u8>> {
+    Ok(parse_cert_der(cert_der).map_err(LairError::other)?.serial)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +800,491 @@ mod tests {
         // that takes the generated cert and makes sure it is usable
         // to encrypt / decrypt
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_read_tls_cert_not_after() {
+        let cert =
+            tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+                .await
+                .unwrap();
+        // rcgen certs are valid until 4096-01-01T00:00:00Z
+        assert_eq!(
+            67_090_118_400_000,
+            tls_cert_not_after(&cert.cert_der).unwrap()
+        );
+        assert!(tls_cert_not_after(&[0x30, 0x00]).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_gives_certs_random_serials() {
+        let mut serials = std::collections::HashSet::new();
+        for _ in 0..8 {
+            let cert = tls_cert_self_signed_new_from_entropy(
+                TlsCertOptions::default(),
+            )
+            .await
+            .unwrap();
+            let serial = tls_cert_serial(&cert.cert_der).unwrap();
+            // positive, and as long whatever the serial
+            assert_eq!(8, serial.len());
+            assert_eq!(0x40, serial[0] & 0xc0);
+
+            // renewed certs get a serial of their own
+            let renewed =
+                tls_cert_self_signed_renew(&cert, TlsCertOptions::default())
+                    .await
+                    .unwrap();
+            assert!(serials.insert(serial));
+            assert!(serials.insert(tls_cert_serial(&renewed.cert_der).unwrap()));
+        }
+
+        // the serial is kept in the entry, with the cert
+        let cert =
+            tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+                .await
+                .unwrap();
+        let key = entry::EntryStoreKey::new_random().unwrap();
+        let d = entry::LairEntry::from(cert.clone())
+            .seal_with_meta(&entry::EntryMeta::default(), &key)
+            .unwrap();
+        match entry::LairEntry::unseal_with_meta(&d, &key).unwrap().0 {
+            entry::LairEntry::TlsCert(e) => assert_eq!(
+                tls_cert_serial(&cert.cert_der).unwrap(),
+                tls_cert_serial(&e.cert_der).unwrap()
+            ),
+            e => panic!("unexpected type: {:?}", e),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_records_the_alg_of_generated_certs() {
+        for alg in [
+            TlsCertAlg::PkcsEd25519,
+            TlsCertAlg::PkcsEcdsaP256Sha256,
+            TlsCertAlg::PkcsEcdsaP384Sha384,
+        ] {
+            let cert = tls_cert_self_signed_new_from_entropy(TlsCertOptions {
+                alg,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+            assert_eq!(alg, cert.alg);
+            assert_eq!(alg, tls_cert_alg_of_key(&cert.priv_key_der).unwrap());
+        }
+        assert!(tls_cert_self_signed_new_from_entropy(TlsCertOptions {
+            alg: TlsCertAlg::PkcsRsaSha256,
+            ..Default::default()
+        })
+        .await
+        .is_err());
+        assert!(tls_cert_alg_of_key(&[0x30, 0x00]).is_err());
+    }
+
+    fn options(sni: Option<&str>, alt_names: &[&str]) -> TlsCertOptions {
+        TlsCertOptions {
+            sni: sni.map(|sni| sni.to_string()),
+            alt_names: alt_names.iter().map(|n| n.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_honors_sni_and_alt_names() {
+        let cert = tls_cert_self_signed_new_from_entropy(options(
+            Some("lair.example.com"),
+            &["*.example.com", "127.0.0.1", "::1", "lair.example.com"],
+        ))
+        .await
+        .unwrap();
+        assert_eq!("lair.example.com", cert.sni.as_str());
+        let contains = |needle: &[u8]| {
+            cert.cert_der.windows(needle.len()).any(|w| w == needle)
+        };
+        assert!(contains(b"*.example.com"));
+        assert!(contains(&[127, 0, 0, 1]));
+    }
+
+    #[test]
+    fn it_rejects_invalid_names() {
+        for (sni, alt_names) in [
+            (Some("127.0.0.1"), vec![]),
+            (Some("*.example.com"), vec![]),
+            (Some("bad_host.com"), vec![]),
+            (Some("-bad.com"), vec![]),
+            (Some("bad..com"), vec![]),
+            (Some(""), vec![]),
+            (None, vec!["bad host"]),
+            (None, vec!["*"]),
+            (None, vec!["a.*.com"]),
+            (None, vec!["a"; TLS_CERT_ALT_NAMES_MAX + 1]),
+        ] {
+            assert!(
+                matches!(
+                    check_tls_cert_options(&options(sni, &alt_names)),
+                    Err(LairError::TlsCertName(_))
+                ),
+                "{:?} {:?}",
+                sni,
+                alt_names
+            );
+        }
+        let long = "a".repeat(63);
+        let long = [&long[..], &long[..], &long[..]].join(".");
+        assert!(matches!(
+            check_tls_cert_options(&options(Some(&long), &[])),
+            Err(LairError::TlsCertName(_))
+        ));
+        check_tls_cert_options(&options(
+            Some("lair.example.com"),
+            &["*.example.com", "10.0.0.1", "fe80::1"],
+        ))
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_renew_keeping_the_priv_key() {
+        for alg in [
+            TlsCertAlg::PkcsEd25519,
+            TlsCertAlg::PkcsEcdsaP256Sha256,
+            TlsCertAlg::PkcsEcdsaP384Sha384,
+        ] {
+            let cert = tls_cert_self_signed_new_from_entropy(TlsCertOptions {
+                alg,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+            // the alg of the existing key wins over the options
+            let renewed =
+                tls_cert_self_signed_renew(&cert, TlsCertOptions::default())
+                    .await
+                    .unwrap();
+            assert_eq!(cert.sni, renewed.sni);
+            assert_eq!(cert.priv_key_der.0, renewed.priv_key_der.0);
+            assert_eq!(alg, renewed.alg);
+            assert_ne!(cert.cert_digest, renewed.cert_digest);
+
+            let renamed = tls_cert_self_signed_renew(
+                &renewed,
+                options(Some("renewed.example.com"), &[]),
+            )
+            .await
+            .unwrap();
+            assert_eq!("renewed.example.com", renamed.sni.as_str());
+            assert_eq!(cert.priv_key_der.0, renamed.priv_key_der.0);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_keeps_keys_not_exportable_on_renew() {
+        let cert = tls_cert_self_signed_new_from_entropy(TlsCertOptions {
+            exportable: Some(false),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(!cert.exportable);
+        let renewed =
+            tls_cert_self_signed_renew(&cert, TlsCertOptions::default())
+                .await
+                .unwrap();
+        assert!(!renewed.exportable);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_sign_with_tls_cert_keys() {
+        use ring::signature::{self, KeyPair};
+        let message = Arc::new(b"tls handshake transcript".to_vec());
+        for (scheme, verify_alg) in [
+            (
+                TlsCertSignatureScheme::Ed25519,
+                &signature::ED25519 as &dyn signature::VerificationAlgorithm,
+            ),
+            (
+                TlsCertSignatureScheme::EcdsaSecp256r1Sha256,
+                &signature::ECDSA_P256_SHA256_ASN1,
+            ),
+            (
+                TlsCertSignatureScheme::EcdsaSecp384r1Sha384,
+                &signature::ECDSA_P384_SHA384_ASN1,
+            ),
+        ] {
+            let cert = tls_cert_self_signed_new_from_entropy(TlsCertOptions {
+                alg: scheme.alg(),
+                exportable: Some(false),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+            let sig = cert.sign(scheme, message.clone()).await.unwrap();
+
+            let pub_key = match scheme {
+                TlsCertSignatureScheme::Ed25519 => {
+                    signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(
+                        &cert.priv_key_der,
+                    )
+                    .unwrap()
+                    .public_key()
+                    .as_ref()
+                    .to_vec()
+                }
+                _ => signature::EcdsaKeyPair::from_pkcs8(
+                    if scheme == TlsCertSignatureScheme::EcdsaSecp256r1Sha256 {
+                        &signature::ECDSA_P256_SHA256_ASN1_SIGNING
+                    } else {
+                        &signature::ECDSA_P384_SHA384_ASN1_SIGNING
+                    },
+                    &cert.priv_key_der,
+                )
+                .unwrap()
+                .public_key()
+                .as_ref()
+                .to_vec(),
+            };
+            signature::UnparsedPublicKey::new(verify_alg, pub_key)
+                .verify(&message, &sig)
+                .unwrap();
+
+            // a scheme for another keypair algorithm is refused
+            let other = if scheme == TlsCertSignatureScheme::Ed25519 {
+                TlsCertSignatureScheme::RsaPssRsaeSha256
+            } else {
+                TlsCertSignatureScheme::Ed25519
+            };
+            assert!(cert.sign(other, message.clone()).await.is_err());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_issue_certs_signed_by_a_ca() {
+        let ca = tls_ca_new_self_signed(options(Some("ca.example.com"), &[]))
+            .await
+            .unwrap();
+        let ca_fields = parse_cert_der(&ca.cert_der).unwrap();
+        assert!(ca_fields.is_ca);
+        assert_eq!(ca_fields.issuer, ca_fields.subject);
+
+        for alg in [
+            TlsCertAlg::PkcsEd25519,
+            TlsCertAlg::PkcsEcdsaP256Sha256,
+            TlsCertAlg::PkcsEcdsaP384Sha384,
+        ] {
+            let cert = tls_cert_new_signed_by_ca(
+                &ca,
+                TlsCertOptions {
+                    alg,
+                    ..options(None, &["*.example.com"])
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(alg, cert.alg);
+            let fields = parse_cert_der(&cert.cert_der).unwrap();
+            assert!(!fields.is_ca);
+            assert_eq!(ca_fields.subject, fields.issuer);
+            assert_eq!(
+                vec![cert.sni.to_string(), "*.example.com".to_string()],
+                fields.dns_names,
+            );
+
+            // issued certs cannot issue certs
+            assert!(matches!(
+                tls_cert_new_signed_by_ca(&cert, TlsCertOptions::default())
+                    .await,
+                Err(LairError::TlsCertNotCa)
+            ));
+        }
+
+        // nor can pseudo-self-signed certs
+        let cert =
+            tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+                .await
+                .unwrap();
+        assert!(!parse_cert_der(&cert.cert_der).unwrap().is_ca);
+        assert!(matches!(
+            tls_cert_new_signed_by_ca(&cert, TlsCertOptions::default()).await,
+            Err(LairError::TlsCertNotCa)
+        ));
+
+        // a renewed CA is still a CA, under the same name
+        let renewed =
+            tls_cert_self_signed_renew(&ca, TlsCertOptions::default())
+                .await
+                .unwrap();
+        let renewed_fields = parse_cert_der(&renewed.cert_der).unwrap();
+        assert!(renewed_fields.is_ca);
+        assert_eq!(ca_fields.subject, renewed_fields.subject);
+        assert_eq!(ca.priv_key_der, renewed.priv_key_der);
+    }
+
+    fn external_cert(names: &[&str]) -> (Cert, CertPrivKey) {
+        let mut params = rcgen::CertificateParams::new(
+            names.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+        );
+        params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        (
+            cert.serialize_der().unwrap().into(),
+            cert.serialize_private_key_der().into(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_can_import_certs() {
+        let (cert_der, priv_key_der) =
+            external_cert(&["*.example.com", "lair.example.com"]);
+        let cert = tls_cert_import(
+            cert_der.clone(),
+            priv_key_der.clone(),
+            TlsCertOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!("lair.example.com", cert.sni.as_str());
+        assert_eq!(cert_der, cert.cert_der);
+        assert_eq!(priv_key_der, cert.priv_key_der);
+        assert_eq!(TlsCertAlg::PkcsEcdsaP256Sha256, cert.alg);
+        assert_eq!(
+            tls_cert_digest(TlsCertDigestAlg::Blake2b256, &cert_der),
+            cert.cert_digest.to_vec()
+        );
+        assert_eq!(TlsCertDigestAlg::Blake2b256, cert.digest_alg);
+
+        let cert = tls_cert_import(
+            cert_der,
+            priv_key_der,
+            options(Some("api.example.com"), &[]),
+        )
+        .await
+        .unwrap();
+        assert_eq!("api.example.com", cert.sni.as_str());
+
+        // generated certs import as themselves
+        let generated =
+            tls_cert_self_signed_new_from_entropy(TlsCertOptions::default())
+                .await
+                .unwrap();
+        let imported = tls_cert_import(
+            generated.cert_der.clone(),
+            generated.priv_key_der.clone(),
+            TlsCertOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(generated.sni, imported.sni);
+        assert_eq!(generated.cert_digest, imported.cert_digest);
+
+        // the sha256 digest is the cert fingerprint other tools print
+        let imported = tls_cert_import(
+            generated.cert_der.clone(),
+            generated.priv_key_der.clone(),
+            TlsCertOptions {
+                digest_alg: TlsCertDigestAlg::Sha256,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(TlsCertDigestAlg::Sha256, imported.digest_alg);
+        assert_eq!(
+            ring::digest::digest(&ring::digest::SHA256, &generated.cert_der)
+                .as_ref(),
+            &imported.cert_digest[..]
+        );
+    }
+
+    #[test]
+    fn it_finds_certs_by_digest_prefix() {
+        let cert = |digest: &[u8]| entry::EntryTlsCert {
+            sni: "test".to_string().into(),
+            priv_key_der: vec![].into(),
+            cert_der: vec![].into(),
+            cert_digest: digest.to_vec().into(),
+            digest_alg: TlsCertDigestAlg::Sha256,
+            alg: TlsCertAlg::PkcsEd25519,
+            exportable: true,
+        };
+        let certs = [
+            (KeystoreIndex(1), cert(&[0xab, 0xcd, 0x01])),
+            (KeystoreIndex(2), cert(&[0xab, 0xcd, 0x02])),
+            (KeystoreIndex(3), cert(&[0xef, 0x00, 0x00])),
+        ];
+        let find = |prefix: &[u8]| {
+            tls_cert_find_by_digest_prefix(
+                prefix,
+                certs.iter().map(|(idx, cert)| (*idx, cert)),
+            )
+            .map(|(idx, _)| idx)
+        };
+
+        assert_eq!(KeystoreIndex(3), find(&[0xef]).unwrap());
+        assert_eq!(KeystoreIndex(2), find(&[0xab, 0xcd, 0x02]).unwrap());
+        assert!(find(&[0x00]).is_err());
+        match find(&[0xab, 0xcd]) {
+            Err(LairError::TlsCertDigestPrefixAmbiguous(candidates)) => {
+                assert_eq!(
+                    vec!["1:abcd01".to_string(), "2:abcd02".to_string()],
+                    candidates
+                );
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_rejects_invalid_imports() {
+        let (cert_der, priv_key_der) = external_cert(&["*.example.com"]);
+        let (other_cert_der, other_priv_key_der) =
+            external_cert(&["lair.example.com"]);
+        let import = |cert_der: &Cert, priv_key_der: &CertPrivKey, options| {
+            tls_cert_import(cert_der.clone(), priv_key_der.clone(), options)
+        };
+
+        assert!(matches!(
+            import(&vec![0x30, 0x00].into(), &priv_key_der, options(None, &[]))
+                .await,
+            Err(LairError::TlsCertDer(_))
+        ));
+        assert!(matches!(
+            import(&cert_der, &vec![0x30, 0x00].into(), options(None, &[]))
+                .await,
+            Err(LairError::TlsCertDer(_))
+        ));
+        assert!(matches!(
+            import(&cert_der, &other_priv_key_der, options(None, &[])).await,
+            Err(LairError::TlsCertKeyMismatch)
+        ));
+        assert!(matches!(
+            import(&other_cert_der, &priv_key_der, options(None, &[])).await,
+            Err(LairError::TlsCertKeyMismatch)
+        ));
+
+        for options in [
+            // only a wildcard name, no sni to default to
+            options(None, &[]),
+            options(Some("lair.example.org"), &[]),
+            // wildcards cover a single label
+            options(Some("a.lair.example.com"), &[]),
+            options(Some("lair.example.com"), &["*.example.com"]),
+        ] {
+            assert!(matches!(
+                import(&cert_der, &priv_key_der, options).await,
+                Err(LairError::TlsCertName(_))
+            ));
+        }
+
+        let long = "a".repeat(60);
+        let names = (0..TLS_CERT_ALT_NAMES_MAX)
+            .map(|i| format!("{}{}.example.com", long, i))
+            .collect::<Vec<_>>();
+        let (cert_der, priv_key_der) = external_cert(
+            &names.iter().map(|n| n.as_str()).collect::<Vec<_>>(),
+        );
+        assert!(matches!(
+            import(&cert_der, &priv_key_der, options(None, &[])).await,
+            Err(LairError::TlsCertDer(_))
+        ));
+    }
 }
@@ -1,8 +1,14 @@
 //! Internal utility logic.
 
+mod ct_eq;
+pub use ct_eq::*;
 mod err_spawn;
 pub use err_spawn::*;
 mod kill_switch;
 pub use kill_switch::*;
+mod mem_lock;
+pub use mem_lock::*;
 mod msg_id;
 pub use msg_id::*;
+mod secret_bytes;
+pub use secret_bytes::*;
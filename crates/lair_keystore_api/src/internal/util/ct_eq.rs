@@ -0,0 +1,77 @@
+/// Compare byte strings in constant time. Only their contents are
+/// protected, strings of different lengths compare unequal right away.
+///
+/// Comparisons considered security relevant, of values a client can
+/// probe for through the timing of the responses, all go through this
+/// (or through `subtle` directly):
+/// - connection tokens (`auth_token::token_eq()`)
+/// - the unlock passphrase hash (`entry::EntryUnlock`)
+/// - secret key material (`SecretBytes`, `x25519::X25519PrivKey`)
+/// - secretstream MACs
+/// - the ids entries are looked up by: pub keys, cert digests,
+///   seed ids and secret key tags (see `impl_ct_eq!`)
+///
+/// Lookups of those ids in the store go through `HashMap`s, whose
+/// randomly keyed SipHash keeps clients from choosing which stored ids
+/// their probes collide with. Store indexes must keep a keyed hasher.
+///
+/// Not considered security relevant: keystore indexes, snis, entry
+/// tags and digest prefixes (see `tls::tls_cert_find_by_digest_prefix()`),
+/// which clients name openly, and the orderings of ids.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+    a.ct_eq(b).into()
+}
+
+/// Implement `PartialEq` and `Eq` for a newtype around bytes,
+/// comparing them with `ct_eq()`, and `Hash` to match.
+macro_rules! impl_ct_eq {
+    ($t:ty) => {
+        impl PartialEq for $t {
+            fn eq(&self, other: &Self) -> bool {
+                $crate::internal::util::ct_eq(&self.0, &other.0)
+            }
+        }
+
+        impl Eq for $t {}
+
+        impl std::hash::Hash for $t {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.0.hash(state)
+            }
+        }
+    };
+}
+pub(crate) use impl_ct_eq;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_compares_byte_strings() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(&[0xdb; 32], &[0xdb; 32]));
+        let mut other = [0xdb; 32];
+        other[31] = 0;
+        assert!(!ct_eq(&[0xdb; 32], &other));
+        other[0] = 0;
+        assert!(!ct_eq(&[0xdb; 32], &other));
+        assert!(!ct_eq(&[0xdb; 32], &[0xdb; 31]));
+    }
+
+    #[test]
+    fn ct_eq_newtypes_hash_as_they_compare() {
+        use crate::internal::sign_ed25519::SignEd25519PubKey;
+        use std::collections::HashSet;
+
+        let a = SignEd25519PubKey::from(vec![0xdb; 32]);
+        let b = SignEd25519PubKey::from(vec![0xdb; 32]);
+        let c = SignEd25519PubKey::from(vec![0xdc; 32]);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        let set = vec![a, c].into_iter().collect::<HashSet<_>>();
+        assert!(set.contains(&b));
+        assert_eq!(2, set.len());
+    }
+}
@@ -49,21 +49,23 @@ macro_rules! inner_mix {
     }};
 }
 
+fn kill(inner: &InnerArc) {
+    inner.0.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = inner.1.send(());
+    let inner = inner.clone();
+    tokio::task::spawn(async move {
+        let mut lock = inner.2.lock().await;
+        let all = lock.drain(..).map(|cb| cb());
+        futures::future::join_all(all).await;
+    });
+}
+
 impl Drop for KillSwitch {
     fn drop(&mut self) {
         if self.is_weak {
             return;
         }
-        (self.inner)
-            .0
-            .store(false, std::sync::atomic::Ordering::SeqCst);
-        let _ = (self.inner).1.send(());
-        let inner = self.inner.clone();
-        tokio::task::spawn(async move {
-            let mut lock = inner.2.lock().await;
-            let all = lock.drain(..).map(|cb| cb());
-            futures::future::join_all(all).await;
-        });
+        kill(&self.inner);
     }
 }
 
@@ -102,6 +104,12 @@ impl KillSwitch {
         lock.push(cb);
     }
 
+    /// Trigger this kill switch, as dropping a strong instance would,
+    /// even from a weak instance.
+    pub fn kill(&self) {
+        kill(&self.inner);
+    }
+
     /// Should we continue?
     pub fn cont(&self) -> bool {
         cont(&self.inner)
@@ -0,0 +1,223 @@
+use crate::*;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Locks are per page, not per buffer: small secrets share pages,
+/// which stay locked until the last secret on them is dropped.
+static LOCKED_PAGES: Lazy<std::sync::Mutex<HashMap<usize, usize>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+static PAGE_SIZE: Lazy<usize> = Lazy::new(sys::page_size);
+
+fn locked_pages() -> std::sync::MutexGuard<'static, HashMap<usize, usize>> {
+    // no panic can happen while the counts are half updated
+    LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set whether the memory backing secrets allocated from now on
+/// is mlocked, kept off swap. The first server of a process sets this
+/// from `Config::get_mlock_secrets()`, it is off otherwise.
+pub fn set_mem_lock_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether the memory backing secrets allocated from now on is mlocked,
+/// see `set_mem_lock_enabled()`.
+pub fn mem_lock_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Whether the secrets held by this process are all mlocked:
+/// mlocking is enabled, and has not failed since.
+pub fn mem_lock_active() -> bool {
+    ENABLED.load(Ordering::SeqCst) && !FAILED.load(Ordering::SeqCst)
+}
+
+/// Keeps the pages of a buffer locked in memory until dropped.
+/// The buffer must neither move nor be freed before this is dropped,
+/// so it should be declared after the (zeroizing) buffer it locks.
+/// If mlocking fails, a warning is logged once and secrets are
+/// kept unlocked instead.
+pub struct MemLock {
+    first_page: usize,
+    page_count: usize,
+}
+
+impl MemLock {
+    /// Lock the pages of `data` (not of anything it points to,
+    /// pass `&vec[..]` for the contents of a vec), if mlocking is enabled.
+    pub fn new<T: ?Sized>(data: &T) -> Self {
+        let mut out = Self {
+            first_page: 0,
+            page_count: 0,
+        };
+        let len = std::mem::size_of_val(data);
+        if len == 0 || !ENABLED.load(Ordering::SeqCst) {
+            return out;
+        }
+
+        let page_size = *PAGE_SIZE;
+        let start = data as *const T as *const u8 as usize;
+        let first_page = start - start % page_size;
+        let end = start + len;
+        let page_count = (end - first_page).div_ceil(page_size);
+
+        let mut locked = locked_pages();
+        // re-locking the pages already locked does no harm
+        if let Err(err) = sys::lock(first_page, page_count * page_size) {
+            if !FAILED.swap(true, Ordering::SeqCst) {
+                warn!(
+                    "failed to mlock secret memory ({}), secrets may be \
+                    swapped to disk - raise RLIMIT_MEMLOCK (ulimit -l) \
+                    or set mlock_secrets = false",
+                    err
+                );
+            }
+            return out;
+        }
+        for page in 0..page_count {
+            *locked.entry(first_page + page * page_size).or_insert(0) += 1;
+        }
+        out.first_page = first_page;
+        out.page_count = page_count;
+        out
+    }
+
+    /// Whether this buffer was locked.
+    pub fn is_locked(&self) -> bool {
+        self.page_count > 0
+    }
+}
+
+impl Drop for MemLock {
+    fn drop(&mut self) {
+        if self.page_count == 0 {
+            return;
+        }
+        let page_size = *PAGE_SIZE;
+        let mut locked = locked_pages();
+        for page in 0..self.page_count {
+            let page = self.first_page + page * page_size;
+            if let Some(count) = locked.get_mut(&page) {
+                *count -= 1;
+                if *count == 0 {
+                    locked.remove(&page);
+                    let _ = sys::unlock(page, page_size);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    pub(super) fn page_size() -> usize {
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    }
+
+    pub(super) fn lock(addr: usize, len: usize) -> std::io::Result<()> {
+        match unsafe { libc::mlock(addr as *const libc::c_void, len) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    pub(super) fn unlock(addr: usize, len: usize) -> std::io::Result<()> {
+        match unsafe { libc::munlock(addr as *const libc::c_void, len) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use winapi::um::memoryapi::{VirtualLock, VirtualUnlock};
+    use winapi::um::sysinfoapi::{GetSystemInfo, SYSTEM_INFO};
+
+    pub(super) fn page_size() -> usize {
+        unsafe {
+            let mut info: SYSTEM_INFO = std::mem::zeroed();
+            GetSystemInfo(&mut info);
+            info.dwPageSize as usize
+        }
+    }
+
+    pub(super) fn lock(addr: usize, len: usize) -> std::io::Result<()> {
+        match unsafe { VirtualLock(addr as *mut _, len) } {
+            0 => Err(std::io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(super) fn unlock(addr: usize, len: usize) -> std::io::Result<()> {
+        match unsafe { VirtualUnlock(addr as *mut _, len) } {
+            0 => Err(std::io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    pub(super) fn page_size() -> usize {
+        4096
+    }
+
+    pub(super) fn lock(_addr: usize, _len: usize) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "mlock is not supported on this platform",
+        ))
+    }
+
+    pub(super) fn unlock(_addr: usize, _len: usize) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enables mlocking until dropped, then restores the previous
+    /// setting, as other tests of this process share it.
+    struct EnabledGuard(bool);
+
+    impl EnabledGuard {
+        fn new() -> Self {
+            let prev = mem_lock_enabled();
+            set_mem_lock_enabled(true);
+            Self(prev)
+        }
+    }
+
+    impl Drop for EnabledGuard {
+        fn drop(&mut self) {
+            set_mem_lock_enabled(self.0);
+        }
+    }
+
+    #[test]
+    fn it_counts_locks_on_shared_pages() {
+        let _enabled = EnabledGuard::new();
+        let buf = [0xdb_u8; 64];
+        let a = MemLock::new(&buf[..32]);
+        let b = MemLock::new(&buf[32..]);
+        if !a.is_locked() {
+            // e.g. RLIMIT_MEMLOCK is too low where the tests run
+            assert!(!mem_lock_active());
+            return;
+        }
+        let page = a.first_page;
+        let count = |page| locked_pages().get(&page).copied().unwrap_or(0);
+        assert!(count(page) >= 2);
+        drop(a);
+        assert!(count(page) >= 1);
+        drop(b);
+    }
+}
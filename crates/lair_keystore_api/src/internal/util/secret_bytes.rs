@@ -0,0 +1,86 @@
+use super::{ct_eq, MemLock};
+use crate::*;
+
+/// Secret material (private keys, seeds, passphrases) held in memory.
+/// Clones share one buffer, which is zeroized once the last of them
+/// is dropped, and mlocked while mlocking is enabled (see `MemLock`).
+/// Debug output is redacted as `<secret>`, and equality is checked in
+/// constant time.
+#[derive(Clone)]
+pub struct SecretBytes(Arc<Inner>);
+
+struct Inner {
+    data: zeroize::Zeroizing<Vec<u8>>,
+    // dropped after the data is zeroized
+    _lock: MemLock,
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(d: Vec<u8>) -> Self {
+        zeroize::Zeroizing::new(d).into()
+    }
+}
+
+/// Takes over the buffer of the string, e.g. a passphrase as typed.
+impl From<String> for SecretBytes {
+    fn from(s: String) -> Self {
+        s.into_bytes().into()
+    }
+}
+
+impl From<zeroize::Zeroizing<Vec<u8>>> for SecretBytes {
+    fn from(d: zeroize::Zeroizing<Vec<u8>>) -> Self {
+        let lock = MemLock::new(&d[..]);
+        Self(Arc::new(Inner {
+            data: d,
+            _lock: lock,
+        }))
+    }
+}
+
+impl std::ops::Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0.data
+    }
+}
+
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0.data
+    }
+}
+
+impl std::fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<secret>")
+    }
+}
+
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq(&self.0.data, &other.0.data)
+    }
+}
+
+impl Eq for SecretBytes {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_redacts_debug_output() {
+        let secret = SecretBytes::from(vec![0xdb; 32]);
+        assert_eq!("<secret>", format!("{:?}", secret));
+        assert_eq!("Some(<secret>)", format!("{:?}", Some(secret.clone())));
+        assert_eq!(&[0xdb; 32][..], &*secret);
+        assert_eq!(secret, SecretBytes::from(vec![0xdb; 32]));
+        assert_ne!(secret, SecretBytes::from(vec![0xdb; 31]));
+        assert_eq!(
+            b"passphrase",
+            &*SecretBytes::from("passphrase".to_string())
+        );
+    }
+}
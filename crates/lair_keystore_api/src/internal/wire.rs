@@ -1,9 +1,13 @@
 //! Lair Wire Protocol Utilities
 
 use crate::{
-    actor::*, internal::codec, internal::crypto_box, internal::sign_ed25519,
-    internal::x25519, *,
+    actor::*, internal::auth_token, internal::codec, internal::crypto_box,
+    internal::kx, internal::pkcs12, internal::secret_key,
+    internal::secretstream, internal::seed, internal::sign_bls12381,
+    internal::sign_ed25519, internal::sign_secp256k1, internal::tls,
+    internal::util::SecretBytes, internal::x25519, *,
 };
+use bytes::Bytes;
 use std::convert::TryInto;
 
 macro_rules! default_encode_setup {
@@ -16,6 +20,187 @@ macro_rules! default_encode_setup {
     }};
 }
 
+/// Write a derivation path as a u64 length followed by the u32 indexes.
+fn write_derivation_path(
+    writer: &mut codec::CodecWriter,
+    path: &[u32],
+) -> LairResult<()> {
+    if path.len() > seed::MAX_DERIVATION_DEPTH {
+        return Err("derivation path too long".into());
+    }
+    writer.write_u64(path.len() as u64)?;
+    for index in path {
+        writer.write_u32(*index)?;
+    }
+    Ok(())
+}
+
+fn read_derivation_path(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<Vec<u32>> {
+    let len = reader.read_u64()?;
+    if len > seed::MAX_DERIVATION_DEPTH as u64 {
+        return Err("derivation path too long".into());
+    }
+    (0..len).map(|_| reader.read_u32()).collect()
+}
+
+/// Byte length of tls cert names as written by `write_tls_cert_names`.
+fn tls_cert_names_size(sni: &Option<String>, alt_names: &[String]) -> usize {
+    1 // is some?
+        + 8 // sni length
+        + sni.as_ref().map(|sni| sni.len()).unwrap_or(0) // sni
+        + 8 // alt name count
+        + alt_names.iter().map(|n| 8 + n.len()).sum::<usize>() // alt names
+}
+
+/// Write tls cert names as a u8 "is some" flag, the sized sni
+/// (empty for none), then a u64 count followed by the sized alt names.
+fn write_tls_cert_names(
+    writer: &mut codec::CodecWriter,
+    sni: &Option<String>,
+    alt_names: &[String],
+) -> LairResult<()> {
+    if alt_names.len() > tls::TLS_CERT_ALT_NAMES_MAX {
+        return Err("too many tls cert alt names".into());
+    }
+    writer.write_bytes_exact(&[sni.is_some() as u8], 1)?;
+    writer.write_str(
+        sni.as_deref().unwrap_or(""),
+        tls::TLS_CERT_NAMES_MAX_BYTES,
+    )?;
+    writer.write_u64(alt_names.len() as u64)?;
+    for alt_name in alt_names {
+        writer.write_str(alt_name, tls::TLS_CERT_NAMES_MAX_BYTES)?;
+    }
+    Ok(())
+}
+
+fn read_tls_cert_names(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<(Option<String>, Vec<String>)> {
+    let some_byte = reader.read_bytes(1)?[0];
+    let sni = reader.read_str()?;
+    let sni = if some_byte == 1 { Some(sni) } else { None };
+    let len = reader.read_u64()?;
+    if len > tls::TLS_CERT_ALT_NAMES_MAX as u64 {
+        return Err("too many tls cert alt names".into());
+    }
+    let alt_names = (0..len)
+        .map(|_| reader.read_str())
+        .collect::<LairResult<_>>()?;
+    Ok((sni, alt_names))
+}
+
+/// The exportable flag of tls cert requests as a u8:
+/// `0` not exportable, `1` exportable, `2` the server's default.
+fn exportable_flag(exportable: &Option<bool>) -> u8 {
+    match exportable {
+        Some(false) => 0,
+        Some(true) => 1,
+        None => 2,
+    }
+}
+
+fn parse_exportable_flag(flag: u8) -> LairResult<Option<bool>> {
+    match flag {
+        0 => Ok(Some(false)),
+        1 => Ok(Some(true)),
+        2 => Ok(None),
+        _ => Err("invalid tls cert exportable flag".into()),
+    }
+}
+
+/// Encode an (opened) crypto box response: a u8 "is some" flag,
+/// then the sized data (empty for none).
+fn encode_crypto_box_data_option(
+    msg_id: &u64,
+    wire_type: u32,
+    data: &Option<crypto_box::CryptoBoxData>,
+) -> LairResult<Vec<u8>> {
+    let inner_data: &[u8] = match data {
+        Some(inner) => &inner.data,
+        None => &[],
+    };
+    let size = 4 // msg len
+        + 4 // msg type
+        + 8 // msg id
+        + 1 // is some?
+        + 8 // data length
+        + inner_data.len(); // data
+    let mut writer = codec::CodecWriter::new_zeroed(size)?;
+    writer.write_u32(size as u32)?;
+    writer.write_u32(wire_type)?;
+    writer.write_u64(*msg_id)?;
+    writer.write_bytes_exact(&[data.is_some() as u8], 1)?;
+    writer.write_sized_bytes(inner_data, inner_data.len())?;
+    Ok(writer.into_vec())
+}
+
+fn decode_crypto_box_data_option(
+    reader: &mut WireReader<'_>,
+) -> LairResult<Option<crypto_box::CryptoBoxData>> {
+    let some_byte = reader.read_bytes(1)?[0];
+    let data_bytes = reader.read_sized_shared()?;
+    Ok(if some_byte == 1 {
+        Some(data_bytes.into())
+    } else {
+        None
+    })
+}
+
+fn encode_crypto_box_encrypted_data(
+    msg_id: &u64,
+    wire_type: u32,
+    encrypted_data: &crypto_box::CryptoBoxEncryptedData,
+) -> LairResult<Vec<u8>> {
+    let size = 4 // msg len
+        + 4 // msg type
+        + 8 // msg id
+        + 24 // nonce length
+        + 8 // encrypted data length
+        + encrypted_data.encrypted_data.len(); // encrypted data
+    let mut writer = codec::CodecWriter::new_zeroed(size)?;
+    writer.write_u32(size as u32)?;
+    writer.write_u32(wire_type)?;
+    writer.write_u64(*msg_id)?;
+    writer
+        .write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
+    writer.write_sized_bytes(
+        &encrypted_data.encrypted_data,
+        encrypted_data.encrypted_data.len(),
+    )?;
+    Ok(writer.into_vec())
+}
+
+fn decode_crypto_box_encrypted_data(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<crypto_box::CryptoBoxEncryptedData> {
+    let nonce = reader.read_bytes(24)?.try_into()?;
+    let encrypted_data = Arc::new(reader.read_sized_bytes()?);
+    Ok(crypto_box::CryptoBoxEncryptedData {
+        nonce,
+        encrypted_data,
+    })
+}
+
+/// Maximum byte length of the code of a typed error response.
+pub(crate) const ERROR_CODE_MAX_BYTES: usize = 64;
+
+/// Maximum byte length of the detail of a typed error response.
+pub(crate) const ERROR_DETAIL_MAX_BYTES: usize = 1024;
+
+/// Maximum byte length of a mnemonic on the wire.
+/// (24 words of at most 8 letters, plus separators, is 215.)
+pub(crate) const MNEMONIC_MAX_BYTES: usize = 216;
+
+fn read_connection_scope(
+    reader: &mut codec::CodecReader<'_>,
+) -> LairResult<ConnectionScope> {
+    ConnectionScope::from_u32(reader.read_u32()?)
+        .ok_or_else(|| "invalid connection scope".into())
+}
+
 macro_rules! wire_type_meta_macro {
     ($macro_name:ident) => {
         $macro_name! {
@@ -34,171 +219,206 @@ macro_rules! wire_type_meta_macro {
                     message,
                 }
             },
-            ToCliRequestUnlockPassphrase 0xff000010 true true {
-            } |msg_id, wire_type| {
-                let writer = default_encode_setup!(msg_id, wire_type);
+            TypedErrorResponse 0xfffffffe false false {
+                message: String,
+                code: String,
+                value: u64,
+                detail: String,
+            }
+            |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 + message.len() // message
+                    + 8 + code.len() // code
+                    + 8 // value
+                    + 8 + detail.len(); // detail
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_str(message, 128)?;
+                writer.write_str(code, ERROR_CODE_MAX_BYTES)?;
+                writer.write_u64(*value)?;
+                writer.write_str(detail, ERROR_DETAIL_MAX_BYTES)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToCliRequestUnlockPassphrase { msg_id }
+                let message = reader.read_str()?;
+                let code = reader.read_str()?;
+                let value = reader.read_u64()?;
+                let detail = reader.read_str()?;
+                LairWire::TypedErrorResponse {
+                    msg_id,
+                    message,
+                    code,
+                    value,
+                    detail,
+                }
             },
-            ToLairRequestUnlockPassphraseResponse 0xff000011 true false {
-                passphrase: String,
+            ToLairAuthenticate 0x00000001 false true {
+                token: String,
+                scope: ConnectionScope,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(passphrase, 128)?;
+                writer.write_str(token, auth_token::TOKEN_MAX_BYTES)?;
+                writer.write_u32(*scope as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let passphrase = reader.read_str()?;
-                LairWire::ToLairRequestUnlockPassphraseResponse {
+                let token = reader.read_str()?;
+                let scope = read_connection_scope(&mut reader)?;
+                LairWire::ToLairAuthenticate {
                     msg_id,
-                    passphrase,
+                    token,
+                    scope,
                 }
             },
-            ToLairLairGetLastEntryIndex 0x00000010 false true {
+            ToCliAuthenticateResponse 0x00000002 false false {
+                scope: ConnectionScope,
             } |msg_id, wire_type| {
-                let writer = default_encode_setup!(msg_id, wire_type);
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*scope as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairLairGetLastEntryIndex { msg_id }
+                let scope = read_connection_scope(&mut reader)?;
+                LairWire::ToCliAuthenticateResponse { msg_id, scope }
             },
-            ToCliLairGetLastEntryIndexResponse 0x00000011 false false {
-                last_keystore_index: KeystoreIndex,
+            Handshake 0x00000003 false false {
+                version: u32,
+                min_version: u32,
+                features: u64,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**last_keystore_index)?;
+                writer.write_u32(*version)?;
+                writer.write_u32(*min_version)?;
+                writer.write_u64(*features)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let last_keystore_index = reader.read_u32()?;
-                LairWire::ToCliLairGetLastEntryIndexResponse {
+                let version = reader.read_u32()?;
+                let min_version = reader.read_u32()?;
+                let features = reader.read_u64()?;
+                LairWire::Handshake {
                     msg_id,
-                    last_keystore_index: last_keystore_index.into(),
+                    version,
+                    min_version,
+                    features,
                 }
             },
-            ToLairLairGetEntryType 0x00000020 false true {
-                keystore_index: KeystoreIndex,
+            ToLairLairPing 0x00000004 false true {
+                nonce: u64,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
+                writer.write_u64(*nonce)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairLairGetEntryType {
-                    msg_id,
-                    keystore_index: keystore_index.into(),
-                }
+                let nonce = reader.read_u64()?;
+                LairWire::ToLairLairPing { msg_id, nonce }
             },
-            ToCliLairGetEntryTypeResponse 0x00000021 false false {
-                lair_entry_type: LairEntryType,
+            ToCliLairPingResponse 0x00000005 false false {
+                nonce: u64,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(*lair_entry_type as u32)?;
+                writer.write_u64(*nonce)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
-                LairWire::ToCliLairGetEntryTypeResponse {
-                    msg_id,
-                    lair_entry_type,
-                }
+                let nonce = reader.read_u64()?;
+                LairWire::ToCliLairPingResponse { msg_id, nonce }
             },
-            ToLairLairGetServerInfo 0x00000030 false true {
+            Busy 0x00000006 false false {
             } |msg_id, wire_type| {
                 let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairLairGetServerInfo { msg_id }
+                LairWire::Busy { msg_id }
             },
-            ToCliLairGetServerInfoResponse 0x00000031 false false {
-                info: LairServerInfo,
+            ToLairIdentify 0x00000007 false true {
+                client_name: String,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(&info.name, 64)?;
-                writer.write_str(&info.version, 64)?;
+                writer.write_str(client_name, CLIENT_NAME_MAX_BYTES)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let name = reader.read_str()?;
-                let version = reader.read_str()?;
-                LairWire::ToCliLairGetServerInfoResponse {
-                    msg_id,
-                    info: LairServerInfo { name, version },
-                }
+                let client_name = reader.read_str()?;
+                LairWire::ToLairIdentify { msg_id, client_name }
             },
-            ToLairTlsCertNewSelfSignedFromEntropy 0x00000110 false true {
-                cert_alg: TlsCertAlg,
+            ToCliIdentifyResponse 0x00000008 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliIdentifyResponse { msg_id }
+            },
+            ToCliRequestUnlockPassphrase 0xff000010 true true {
+                last_passphrase_failed: bool,
+                attempt: u32,
+                max_attempts: u32,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(*cert_alg as u32)?;
+                writer.write_bytes_exact(&[*last_passphrase_failed as u8], 1)?;
+                writer.write_u32(*attempt)?;
+                writer.write_u32(*max_attempts)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
-                LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                let last_passphrase_failed = reader.read_bytes(1)?[0] == 1;
+                let attempt = reader.read_u32()?;
+                let max_attempts = reader.read_u32()?;
+                LairWire::ToCliRequestUnlockPassphrase {
                     msg_id,
-                    cert_alg,
+                    last_passphrase_failed,
+                    attempt,
+                    max_attempts,
                 }
             },
-            ToCliTlsCertNewSelfSignedFromEntropyResponse 0x00000111 false false {
-                keystore_index: KeystoreIndex,
-                cert_sni: CertSni,
-                cert_digest: CertDigest,
+            ToLairRequestUnlockPassphraseResponse 0xff000011 true false {
+                passphrase: SecretBytes,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
-                writer.write_str(cert_sni, 128)?;
-                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_sized_bytes(passphrase, 128)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                let cert_sni = reader.read_str()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                let passphrase = reader.read_sized_secret()?;
+                LairWire::ToLairRequestUnlockPassphraseResponse {
                     msg_id,
-                    keystore_index: keystore_index.into(),
-                    cert_sni: cert_sni.into(),
-                    cert_digest: cert_digest.into(),
+                    passphrase,
                 }
             },
-            ToLairTlsCertGet 0x00000120 false true {
+            ToCliEntryCreated 0xff000020 true true {
                 keystore_index: KeystoreIndex,
+                lair_entry_type: LairEntryType,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
                 writer.write_u32(**keystore_index)?;
+                writer.write_u32(*lair_entry_type as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let keystore_index = reader.read_u32()?;
-                LairWire::ToLairTlsCertGet {
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliEntryCreated {
                     msg_id,
                     keystore_index: keystore_index.into(),
+                    lair_entry_type,
                 }
             },
-            ToCliTlsCertGetResponse 0x00000121 false false {
-                cert_sni: CertSni,
-                cert_digest: CertDigest,
+            ToLairEntryCreatedResponse 0xff000021 true false {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(cert_sni, 128)?;
-                writer.write_bytes_exact(cert_digest, 32)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_sni = reader.read_str()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliTlsCertGetResponse {
-                    msg_id,
-                    cert_sni: cert_sni.into(),
-                    cert_digest: cert_digest.into(),
-                }
+                LairWire::ToLairEntryCreatedResponse { msg_id }
             },
-            ToLairTlsCertGetCertByIndex 0x00000130 false true {
+            ToCliEntryDeleted 0xff000030 true true {
                 keystore_index: KeystoreIndex,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
@@ -207,339 +427,383 @@ macro_rules! wire_type_meta_macro {
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let keystore_index = reader.read_u32()?;
-                LairWire::ToLairTlsCertGetCertByIndex {
+                LairWire::ToCliEntryDeleted {
                     msg_id,
                     keystore_index: keystore_index.into(),
                 }
             },
-            ToCliTlsCertGetCertByIndexResponse 0x00000131 false false {
-                cert: Cert,
+            ToLairEntryDeletedResponse 0xff000031 true false {
             } |msg_id, wire_type| {
-                let mut writer = codec::CodecWriter::new(1024)?;
-                writer.write_u32(1024)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_sized_bytes(cert, 968)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetCertByIndexResponse {
-                    msg_id,
-                    cert: cert.into(),
-                }
+                LairWire::ToLairEntryDeletedResponse { msg_id }
             },
-            ToLairTlsCertGetCertByDigest 0x00000140 false true {
-                cert_digest: CertDigest,
+            ToCliLocked 0xff000040 true true {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(cert_digest, 32)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToLairTlsCertGetCertByDigest {
-                    msg_id,
-                    cert_digest: cert_digest.into(),
-                }
+                LairWire::ToCliLocked { msg_id }
             },
-            ToCliTlsCertGetCertByDigestResponse 0x00000141 false false {
-                cert: Cert,
+            ToLairLockedResponse 0xff000041 true false {
             } |msg_id, wire_type| {
-                let mut writer = codec::CodecWriter::new(1024)?;
-                writer.write_u32(1024)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_sized_bytes(cert, 968)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetCertByDigestResponse {
-                    msg_id,
-                    cert: cert.into(),
-                }
+                LairWire::ToLairLockedResponse { msg_id }
             },
-            ToLairTlsCertGetCertBySni 0x00000150 false true {
-                cert_sni: CertSni,
+            ToCliUnlocked 0xff000050 true true {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(cert_sni, 128)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_sni = reader.read_str()?;
-                LairWire::ToLairTlsCertGetCertBySni {
-                    msg_id,
-                    cert_sni: cert_sni.into(),
-                }
+                LairWire::ToCliUnlocked { msg_id }
             },
-            ToCliTlsCertGetCertBySniResponse 0x00000151 false false {
-                cert: Cert,
+            ToLairUnlockedResponse 0xff000051 true false {
             } |msg_id, wire_type| {
-                let mut writer = codec::CodecWriter::new(1024)?;
-                writer.write_u32(1024)?;
-                writer.write_u32(wire_type)?;
-                writer.write_u64(*msg_id)?;
-                writer.write_sized_bytes(cert, 968)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetCertBySniResponse {
-                    msg_id,
-                    cert: cert.into(),
-                }
+                LairWire::ToLairUnlockedResponse { msg_id }
             },
-            ToLairTlsCertGetPrivKeyByIndex 0x00000160 false true {
+            ToCliRequestSigningApproval 0xff000060 true true {
                 keystore_index: KeystoreIndex,
+                client_id: String,
+                message_digest: [u8; 32],
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
                 writer.write_u32(**keystore_index)?;
+                writer.write_str(client_id, 128)?;
+                writer.write_bytes_exact(message_digest, 32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let keystore_index = reader.read_u32()?;
-                LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                let client_id = reader.read_str()?;
+                let mut message_digest = [0; 32];
+                message_digest.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToCliRequestSigningApproval {
                     msg_id,
                     keystore_index: keystore_index.into(),
+                    client_id,
+                    message_digest,
                 }
             },
-            ToCliTlsCertGetPrivKeyByIndexResponse 0x00000161 false false {
-                cert_priv_key: CertPrivKey,
+            ToLairRequestSigningApprovalResponse 0xff000061 true false {
+                approved: bool,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_sized_bytes(cert_priv_key, 220)?;
+                writer.write_bytes_exact(&[*approved as u8], 1)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_priv_key = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                let approved = reader.read_bytes(1)?[0] == 1;
+                LairWire::ToLairRequestSigningApprovalResponse {
                     msg_id,
-                    cert_priv_key: cert_priv_key.into(),
+                    approved,
                 }
             },
-            ToLairTlsCertGetPrivKeyByDigest 0x00000170 false true {
-                cert_digest: CertDigest,
+            ToLairLairGetLastEntryIndex 0x00000010 false true {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(cert_digest, 32)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_digest = reader.read_bytes(32)?.to_vec();
-                LairWire::ToLairTlsCertGetPrivKeyByDigest {
-                    msg_id,
-                    cert_digest: cert_digest.into(),
-                }
+                LairWire::ToLairLairGetLastEntryIndex { msg_id }
             },
-            ToCliTlsCertGetPrivKeyByDigestResponse 0x00000171 false false {
-                cert_priv_key: CertPrivKey,
+            ToCliLairGetLastEntryIndexResponse 0x00000011 false false {
+                last_keystore_index: KeystoreIndex,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_sized_bytes(cert_priv_key, 220)?;
+                writer.write_u32(**last_keystore_index)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_priv_key = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                let last_keystore_index = reader.read_u32()?;
+                LairWire::ToCliLairGetLastEntryIndexResponse {
                     msg_id,
-                    cert_priv_key: cert_priv_key.into(),
+                    last_keystore_index: last_keystore_index.into(),
                 }
             },
-            ToLairTlsCertGetPrivKeyBySni 0x00000180 false true {
-                cert_sni: CertSni,
+            ToLairLairGetEntryType 0x00000020 false true {
+                keystore_index: KeystoreIndex,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_str(cert_sni, 128)?;
+                writer.write_u32(**keystore_index)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_sni = reader.read_str()?;
-                LairWire::ToLairTlsCertGetPrivKeyBySni {
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairLairGetEntryType {
                     msg_id,
-                    cert_sni: cert_sni.into(),
+                    keystore_index: keystore_index.into(),
                 }
             },
-            ToCliTlsCertGetPrivKeyBySniResponse 0x00000181 false false {
-                cert_priv_key: CertPrivKey,
+            ToCliLairGetEntryTypeResponse 0x00000021 false false {
+                lair_entry_type: LairEntryType,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_sized_bytes(cert_priv_key, 220)?;
+                writer.write_u32(*lair_entry_type as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let cert_priv_key = reader.read_sized_bytes()?;
-                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetEntryTypeResponse {
                     msg_id,
-                    cert_priv_key: cert_priv_key.into(),
+                    lair_entry_type,
                 }
             },
-            ToLairSignEd25519NewFromEntropy 0x00000210 false true {
+            ToLairLairGetServerInfo 0x00000030 false true {
             } |msg_id, wire_type| {
                 let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairSignEd25519NewFromEntropy { msg_id }
+                LairWire::ToLairLairGetServerInfo { msg_id }
             },
-            ToCliSignEd25519NewFromEntropyResponse 0x00000211 false false {
-                keystore_index: KeystoreIndex,
-                pub_key: sign_ed25519::SignEd25519PubKey,
+            ToCliLairGetServerInfoResponse 0x00000031 false false {
+                info: LairServerInfo,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
-                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_str(&info.name, 64)?;
+                writer.write_str(&info.version, 64)?;
+                writer.write_u64(info.corrupt_entry_count)?;
+                writer.write_u64(info.connection_count)?;
+                writer.write_u64(info.requests_in_flight)?;
+                writer.write_u64(info.failed_unlock_count)?;
+                writer.write_bytes_exact(&[info.secrets_mlocked as u8], 1)?;
+                writer.write_bytes_exact(&[info.core_dumps_disabled as u8], 1)?;
+                writer.write_bytes_exact(&[info.ptrace_disabled as u8], 1)?;
+                writer.write_bytes_exact(&[info.umask_restricted as u8], 1)?;
+                writer.write_u64(info.crypto_threads)?;
+                writer.write_u64(info.crypto_queue_depth)?;
+                writer.write_u64(info.crypto_busy_threads)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                let pub_key = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                let name = reader.read_str()?;
+                let version = reader.read_str()?;
+                let corrupt_entry_count = reader.read_u64()?;
+                let connection_count = reader.read_u64()?;
+                let requests_in_flight = reader.read_u64()?;
+                let failed_unlock_count = reader.read_u64()?;
+                let secrets_mlocked = reader.read_bytes(1)?[0] == 1;
+                let core_dumps_disabled = reader.read_bytes(1)?[0] == 1;
+                let ptrace_disabled = reader.read_bytes(1)?[0] == 1;
+                let umask_restricted = reader.read_bytes(1)?[0] == 1;
+                let crypto_threads = reader.read_u64()?;
+                let crypto_queue_depth = reader.read_u64()?;
+                let crypto_busy_threads = reader.read_u64()?;
+                LairWire::ToCliLairGetServerInfoResponse {
                     msg_id,
-                    keystore_index: keystore_index.into(),
-                    pub_key: pub_key.into(),
+                    info: LairServerInfo {
+                        name,
+                        version,
+                        corrupt_entry_count,
+                        connection_count,
+                        requests_in_flight,
+                        failed_unlock_count,
+                        secrets_mlocked,
+                        core_dumps_disabled,
+                        ptrace_disabled,
+                        umask_restricted,
+                        crypto_threads,
+                        crypto_queue_depth,
+                        crypto_busy_threads,
+                    },
                 }
             },
-            ToLairSignEd25519Get 0x00000220 false true {
-                keystore_index: KeystoreIndex,
+            ToLairLairShutdown 0x00000040 false true {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_u32(**keystore_index)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                LairWire::ToLairSignEd25519Get {
-                    msg_id,
-                    keystore_index: keystore_index.into(),
-                }
+                LairWire::ToLairLairShutdown { msg_id }
             },
-            ToCliSignEd25519GetResponse 0x00000221 false false {
-                pub_key: sign_ed25519::SignEd25519PubKey,
+            ToCliLairShutdownResponse 0x00000041 false false {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(pub_key, 32)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let pub_key = reader.read_bytes(32)?.to_vec();
-                LairWire::ToCliSignEd25519GetResponse {
-                    msg_id,
-                    pub_key: pub_key.into(),
-                }
+                LairWire::ToCliLairShutdownResponse { msg_id }
             },
-            ToLairSignEd25519SignByIndex 0x00000230 false true {
-                keystore_index: KeystoreIndex,
-                message: Arc<Vec<u8>>,
+            ToLairLairChangeUnlockPassphrase 0x00000050 false true {
+                old_passphrase: String,
+                new_passphrase: String,
             } |msg_id, wire_type| {
-                // outgoing sig requests just need to be the right size...
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
-                    + 4 // keystore index
-                    + 8 // message length
-                    + message.len(); // message content
+                    + 8 // old passphrase length
+                    + old_passphrase.len() // old passphrase content
+                    + 8 // new passphrase length
+                    + new_passphrase.len(); // new passphrase content
                 let mut writer = codec::CodecWriter::new_zeroed(size)?;
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
-                writer.write_u32(**keystore_index)?;
-                writer.write_sized_bytes(message, message.len())?;
+                writer.write_str(old_passphrase, 128)?;
+                writer.write_str(new_passphrase, 128)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?;
-                let message = Arc::new(reader.read_sized_bytes()?);
-                LairWire::ToLairSignEd25519SignByIndex {
+                let old_passphrase = reader.read_str()?;
+                let new_passphrase = reader.read_str()?;
+                LairWire::ToLairLairChangeUnlockPassphrase {
                     msg_id,
-                    keystore_index: keystore_index.into(),
-                    message,
+                    old_passphrase,
+                    new_passphrase,
                 }
             },
-            ToCliSignEd25519SignByIndexResponse 0x00000231 false false {
-                signature: sign_ed25519::SignEd25519Signature,
+            ToCliLairChangeUnlockPassphraseResponse 0x00000051 false false {
             } |msg_id, wire_type| {
-                let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(signature, 64)?;
+                let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let signature = reader.read_bytes(64)?.to_vec();
-                LairWire::ToCliSignEd25519SignByIndexResponse {
-                    msg_id,
-                    signature: signature.into(),
-                }
+                LairWire::ToCliLairChangeUnlockPassphraseResponse { msg_id }
             },
-            ToLairSignEd25519SignByPubKey 0x00000240 false true {
-                pub_key: sign_ed25519::SignEd25519PubKey,
-                message: Arc<Vec<u8>>,
+            ToLairLairListEntries 0x00000060 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairListEntries { msg_id }
+            },
+            ToCliLairListEntriesResponse 0x00000061 false false {
+                entries: Vec<EntryListItem>,
             } |msg_id, wire_type| {
-                // outgoing sig requests just need to be the right size...
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
-                    + 32 // pub_key
-                    + 8 // message length
-                    + message.len(); // message content
+                    + 8 // entry count
+                    + entries.iter().map(|e| {
+                        4 // keystore index
+                        + 4 // entry type
+                        + 32 // pub id
+                        + 4 // cert digest alg
+                        + 8 // sni length
+                        + e.cert_sni.as_ref().map(|s| s.len()).unwrap_or(0)
+                        + 8 // tag length
+                        + e.tag.as_ref().map(|t| t.len()).unwrap_or(0)
+                        + 8 // created at
+                    }).sum::<usize>();
                 let mut writer = codec::CodecWriter::new_zeroed(size)?;
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
-                writer.write_bytes_exact(pub_key, 32)?;
-                writer.write_sized_bytes(message, message.len())?;
+                writer.write_u64(entries.len() as u64)?;
+                for entry in entries.iter() {
+                    writer.write_u32(*entry.keystore_index)?;
+                    writer.write_u32(entry.entry_type as u32)?;
+                    writer.write_bytes_exact(&entry.pub_id, 32)?;
+                    let digest_alg = entry.cert_digest_alg.map(|a| a as u32);
+                    writer.write_u32(digest_alg.unwrap_or(0))?;
+                    let sni = entry.cert_sni.as_ref().map(|s| s.as_str());
+                    writer.write_str(sni.unwrap_or(""), 128)?;
+                    let tag = entry.tag.as_deref();
+                    writer.write_str(tag.unwrap_or(""), 128)?;
+                    writer.write_u64(entry.created_at.unwrap_or(0))?;
+                }
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let pub_key = reader.read_bytes(32)?.to_vec();
-                let message = Arc::new(reader.read_sized_bytes()?);
-                LairWire::ToLairSignEd25519SignByPubKey {
+                let count = reader.read_u64()?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let keystore_index = reader.read_u32()?;
+                    let entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                    let pub_id = reader.read_bytes(32)?.to_vec();
+                    let cert_digest_alg = match reader.read_u32()? {
+                        0 => None,
+                        a => Some(TlsCertDigestAlg::parse(a)?),
+                    };
+                    let cert_sni = reader.read_str()?;
+                    let tag = reader.read_str()?;
+                    let created_at = reader.read_u64()?;
+                    entries.push(EntryListItem {
+                        keystore_index: keystore_index.into(),
+                        entry_type,
+                        pub_id: Arc::new(pub_id),
+                        // keypairs are encoded with a zero digest alg
+                        cert_digest_alg,
+                        // keypairs are encoded with an empty sni
+                        cert_sni: if cert_sni.is_empty() {
+                            None
+                        } else {
+                            Some(cert_sni.into())
+                        },
+                        tag: if tag.is_empty() { None } else { Some(tag) },
+                        // older servers did not record timestamps
+                        created_at: Some(created_at).filter(|t| *t != 0),
+                    });
+                }
+                LairWire::ToCliLairListEntriesResponse {
                     msg_id,
-                    pub_key: pub_key.into(),
-                    message,
+                    entries,
                 }
             },
-            ToCliSignEd25519SignByPubKeyResponse 0x00000241 false false {
-                signature: sign_ed25519::SignEd25519Signature,
+            ToLairLairDeleteEntry 0x00000070 false true {
+                keystore_index: KeystoreIndex,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(signature, 64)?;
+                writer.write_u32(**keystore_index)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let signature = reader.read_bytes(64)?.to_vec();
-                LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairLairDeleteEntry {
                     msg_id,
-                    signature: signature.into(),
+                    keystore_index: keystore_index.into(),
                 }
             },
-            ToLairX25519NewFromEntropy 0x00000242 false true {
+            ToCliLairDeleteEntryResponse 0x00000071 false false {
             } |msg_id, wire_type| {
                 let writer = default_encode_setup!(msg_id, wire_type);
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                LairWire::ToLairX25519NewFromEntropy { msg_id }
+                LairWire::ToCliLairDeleteEntryResponse { msg_id }
             },
-            ToCliX25519NewFromEntropyResponse 0x00000243 false false {
+            ToLairLairGetEntryByTag 0x00000080 false true {
+                tag: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(tag, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let tag = reader.read_str()?;
+                LairWire::ToLairLairGetEntryByTag { msg_id, tag }
+            },
+            ToCliLairGetEntryByTagResponse 0x00000081 false false {
                 keystore_index: KeystoreIndex,
-                pub_key: x25519::X25519PubKey,
+                lair_entry_type: LairEntryType,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
                 writer.write_u32(**keystore_index)?;
-                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                writer.write_u32(*lair_entry_type as u32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let keystore_index = reader.read_u32()?;
-                let pub_key = reader.read_bytes(32)?.try_into()?;
-                LairWire::ToCliX25519NewFromEntropyResponse {
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetEntryByTagResponse {
                     msg_id,
                     keystore_index: keystore_index.into(),
-                    pub_key,
+                    lair_entry_type,
                 }
             },
-            ToLairX25519Get 0x00000244 false true {
+            ToLairLairGetEntryMeta 0x00000090 false true {
                 keystore_index: KeystoreIndex,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
@@ -548,95 +812,3010 @@ macro_rules! wire_type_meta_macro {
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let keystore_index = reader.read_u32()?;
-                LairWire::ToLairX25519Get {
+                LairWire::ToLairLairGetEntryMeta {
                     msg_id,
                     keystore_index: keystore_index.into(),
                 }
             },
-            ToCliX25519GetResponse 0x00000245 false false {
-                pub_key: x25519::X25519PubKey,
+            ToCliLairGetEntryMetaResponse 0x00000091 false false {
+                entry_meta: entry::EntryMeta,
             } |msg_id, wire_type| {
                 let mut writer = default_encode_setup!(msg_id, wire_type);
-                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                let tag = entry_meta.tag.as_deref();
+                writer.write_str(tag.unwrap_or(""), 128)?;
+                writer.write_u64(entry_meta.created_at.unwrap_or(0))?;
+                writer.write_u64(entry_meta.use_count)?;
+                writer.write_u64(entry_meta.last_used.unwrap_or(0))?;
+                match &entry_meta.derived_from {
+                    Some(d) => {
+                        writer.write_u32(*d.seed_index)?;
+                        write_derivation_path(&mut writer, &d.path)?;
+                    }
+                    None => {
+                        writer.write_u32(0)?;
+                        write_derivation_path(&mut writer, &[])?;
+                    }
+                }
+                let converted_from = entry_meta.converted_from;
+                writer.write_u32(converted_from.map(|i| i.0).unwrap_or(0))?;
+                let renewed_from = entry_meta.renewed_from;
+                writer.write_u32(renewed_from.map(|i| i.0).unwrap_or(0))?;
+                writer.write_u32(entry_meta.rate_limit.unwrap_or(0))?;
+                let requires_approval = entry_meta.requires_approval as u8;
+                writer.write_bytes_exact(&[requires_approval], 1)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let pub_key = reader.read_bytes(32)?.try_into()?;
-                LairWire::ToCliX25519GetResponse {
+                let tag = reader.read_str()?;
+                let created_at = reader.read_u64()?;
+                let use_count = reader.read_u64()?;
+                let last_used = reader.read_u64()?;
+                let seed_index = reader.read_u32()?;
+                let path = read_derivation_path(&mut reader)?;
+                let converted_from = reader.read_u32()?;
+                let renewed_from = reader.read_u32()?;
+                let rate_limit = reader.read_u32()?;
+                let requires_approval = reader.read_bytes(1)?[0] == 1;
+                let derived_from = if path.is_empty() {
+                    None
+                } else {
+                    Some(entry::EntryDerivation {
+                        seed_index: seed_index.into(),
+                        path,
+                    })
+                };
+                LairWire::ToCliLairGetEntryMetaResponse {
                     msg_id,
-                    pub_key,
+                    entry_meta: entry::EntryMeta {
+                        tag: if tag.is_empty() { None } else { Some(tag) },
+                        created_at: Some(created_at).filter(|t| *t != 0),
+                        use_count,
+                        last_used: Some(last_used).filter(|t| *t != 0),
+                        derived_from,
+                        converted_from: Some(converted_from)
+                            .filter(|i| *i != 0)
+                            .map(Into::into),
+                        renewed_from: Some(renewed_from)
+                            .filter(|i| *i != 0)
+                            .map(Into::into),
+                        rate_limit: Some(rate_limit).filter(|n| *n != 0),
+                        requires_approval,
+                    },
                 }
             },
-            ToLairCryptoBoxByIndex 0x00000246 false true {
+            ToLairLairFindEntry 0x000000a0 false true {
+                id: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(id, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let id = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairLairFindEntry { msg_id, id }
+            },
+            ToCliLairFindEntryResponse 0x000000a1 false false {
+                entry: Option<(KeystoreIndex, LairEntryType)>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                let (some_byte, keystore_index, lair_entry_type) = match entry {
+                    Some((idx, entry_type)) => (1, **idx, *entry_type as u32),
+                    None => (0, 0, 0),
+                };
+                writer.write_bytes_exact(&[some_byte], 1)?;
+                writer.write_u32(keystore_index)?;
+                writer.write_u32(lair_entry_type)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let some_byte = reader.read_bytes(1)?[0];
+                let keystore_index = reader.read_u32()?;
+                let lair_entry_type = LairEntryType::parse(reader.read_u32()?)?;
+                let entry = if some_byte == 1 {
+                    Some((keystore_index.into(), lair_entry_type))
+                } else {
+                    None
+                };
+                LairWire::ToCliLairFindEntryResponse { msg_id, entry }
+            },
+            ToLairLairUpdateEntryTag 0x000000b0 false true {
                 keystore_index: KeystoreIndex,
-                recipient: x25519::X25519PubKey,
-                data: Arc<crypto_box::CryptoBoxData>,
+                tag: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(tag, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let tag = reader.read_str()?;
+                LairWire::ToLairLairUpdateEntryTag {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    tag,
+                }
+            },
+            ToCliLairUpdateEntryTagResponse 0x000000b1 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairUpdateEntryTagResponse { msg_id }
+            },
+            ToLairLairExportEntryPublic 0x000000c0 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairLairExportEntryPublic {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliLairExportEntryPublicResponse 0x000000c1 false false {
+                entry_public: EntryPublic,
             } |msg_id, wire_type| {
+                let body_size = match entry_public {
+                    EntryPublic::SignEd25519 { .. }
+                    | EntryPublic::X25519 { .. }
+                    | EntryPublic::Seed { .. }
+                    | EntryPublic::SecretKey { .. } => 32,
+                    EntryPublic::SignSecp256k1 { .. } => {
+                        sign_secp256k1::PUB_KEY_BYTES
+                    }
+                    #[cfg(feature = "bls")]
+                    EntryPublic::SignBls12381 { .. } => {
+                        sign_bls12381::PUB_KEY_BYTES
+                    }
+                    EntryPublic::TlsCert { sni, cert_der, .. } => {
+                        8 + sni.len() + 32 + 4 + 8 + 4 + 8 + cert_der.len()
+                    }
+                };
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
-                    + 4 // keystore index
-                    + 32 // recipient pub key
-                    + 8 // data length
-                    + data.len(); // data content
+                    + 4 // entry type
+                    + body_size;
                 let mut writer = codec::CodecWriter::new_zeroed(size)?;
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
-                writer.write_u32(**keystore_index)?;
-                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(recipient), 32)?;
-                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**data), data.len())?;
+                match entry_public {
+                    EntryPublic::SignEd25519 { pub_key } => {
+                        writer.write_u32(LairEntryType::SignEd25519 as u32)?;
+                        writer.write_bytes_exact(pub_key, 32)?;
+                    }
+                    EntryPublic::X25519 { pub_key } => {
+                        writer.write_u32(LairEntryType::X25519 as u32)?;
+                        writer.write_bytes_exact(pub_key, 32)?;
+                    }
+                    EntryPublic::Seed { seed_id } => {
+                        writer.write_u32(LairEntryType::Seed as u32)?;
+                        writer.write_bytes_exact(seed_id, 32)?;
+                    }
+                    EntryPublic::SecretKey { tag } => {
+                        writer.write_u32(LairEntryType::SecretKey as u32)?;
+                        writer.write_bytes_exact(tag, 32)?;
+                    }
+                    EntryPublic::SignSecp256k1 { pub_key } => {
+                        writer.write_u32(LairEntryType::SignSecp256k1 as u32)?;
+                        writer.write_bytes_exact(
+                            pub_key,
+                            sign_secp256k1::PUB_KEY_BYTES,
+                        )?;
+                    }
+                    #[cfg(feature = "bls")]
+                    EntryPublic::SignBls12381 { pub_key } => {
+                        writer.write_u32(LairEntryType::SignBls12381 as u32)?;
+                        writer.write_bytes_exact(
+                            pub_key,
+                            sign_bls12381::PUB_KEY_BYTES,
+                        )?;
+                    }
+                    EntryPublic::TlsCert {
+                        sni,
+                        cert_digest,
+                        digest_alg,
+                        cert_der,
+                        not_after,
+                        alg,
+                    } => {
+                        writer.write_u32(LairEntryType::TlsCert as u32)?;
+                        writer.write_str(sni, sni.len())?;
+                        writer.write_bytes_exact(cert_digest, 32)?;
+                        writer.write_u32(*digest_alg as u32)?;
+                        writer.write_u64(*not_after)?;
+                        writer.write_u32(*alg as u32)?;
+                        writer.write_sized_bytes(cert_der, cert_der.len())?;
+                    }
+                }
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?.into();
-                let recipient = reader.read_bytes(32)?.try_into()?;
-                let data = Arc::new(reader.read_sized_bytes()?.into());
-                LairWire::ToLairCryptoBoxByIndex {
+                let entry_public = match LairEntryType::parse(reader.read_u32()?)? {
+                    LairEntryType::SignEd25519 => EntryPublic::SignEd25519 {
+                        pub_key: reader.read_bytes(32)?.to_vec(),
+                    },
+                    LairEntryType::X25519 => EntryPublic::X25519 {
+                        pub_key: reader.read_bytes(32)?.to_vec(),
+                    },
+                    LairEntryType::Seed => EntryPublic::Seed {
+                        seed_id: reader.read_bytes(32)?.to_vec(),
+                    },
+                    LairEntryType::SecretKey => EntryPublic::SecretKey {
+                        tag: reader.read_bytes(32)?.to_vec(),
+                    },
+                    LairEntryType::SignSecp256k1 => EntryPublic::SignSecp256k1 {
+                        pub_key: reader
+                            .read_bytes(sign_secp256k1::PUB_KEY_BYTES as _)?
+                            .to_vec(),
+                    },
+                    #[cfg(feature = "bls")]
+                    LairEntryType::SignBls12381 => EntryPublic::SignBls12381 {
+                        pub_key: reader
+                            .read_bytes(sign_bls12381::PUB_KEY_BYTES as _)?
+                            .to_vec(),
+                    },
+                    LairEntryType::TlsCert => {
+                        let sni = reader.read_str()?;
+                        let cert_digest = reader.read_bytes(32)?.to_vec();
+                        let digest_alg =
+                            TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                        let not_after = reader.read_u64()?;
+                        let alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                        let cert_der = reader.read_sized_bytes()?;
+                        EntryPublic::TlsCert {
+                            sni,
+                            cert_digest,
+                            digest_alg,
+                            cert_der,
+                            not_after,
+                            alg,
+                        }
+                    }
+                    t => {
+                        return Err(format!(
+                            "no public material for entry type {:?}",
+                            t
+                        )
+                        .into())
+                    }
+                };
+                LairWire::ToCliLairExportEntryPublicResponse {
                     msg_id,
-                    keystore_index,
-                    recipient,
-                    data,
+                    entry_public,
                 }
             },
-            ToCliCryptoBoxByIndexResponse 0x00000247 false false {
-                encrypted_data: crypto_box::CryptoBoxEncryptedData,
+            ToLairTlsCertNewSelfSignedFromEntropy 0x00000110 false true {
+                cert_alg: TlsCertAlg,
+                digest_alg: TlsCertDigestAlg,
+                exportable: Option<bool>,
+                sni: Option<String>,
+                alt_names: Vec<String>,
             } |msg_id, wire_type| {
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
-                    + 24 // nonce length
-                    + 8 // encrypted data length
-                    + encrypted_data.encrypted_data.len(); // encrypted data
+                    + 4 // cert alg
+                    + 4 // digest alg
+                    + 1 // exportable
+                    + tls_cert_names_size(sni, alt_names); // names
                 let mut writer = codec::CodecWriter::new_zeroed(size)?;
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
-                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
-                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**encrypted_data.encrypted_data), encrypted_data.encrypted_data.len())?;
+                writer.write_u32(*cert_alg as u32)?;
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(&[exportable_flag(exportable)], 1)?;
+                write_tls_cert_names(&mut writer, sni, alt_names)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                let digest_alg = TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                let exportable =
+                    parse_exportable_flag(reader.read_bytes(1)?[0])?;
+                let (sni, alt_names) = read_tls_cert_names(&mut reader)?;
+                LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                    msg_id,
+                    cert_alg,
+                    digest_alg,
+                    exportable,
+                    sni,
+                    alt_names,
+                }
+            },
+            ToCliTlsCertNewSelfSignedFromEntropyResponse 0x00000111 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let nonce = reader.read_bytes(24)?.try_into()?;
-                let encrypted_data = Arc::new(reader.read_sized_bytes()?.into());
-                LairWire::ToCliCryptoBoxByIndexResponse {
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertGet 0x00000120 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGet {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetResponse 0x00000121 false false {
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertGetResponse {
+                    msg_id,
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertGetCertByIndex 0x00000130 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGetCertByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetCertByIndexResponse 0x00000131 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = codec::CodecWriter::new(1024)?;
+                writer.write_u32(1024)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_shared()?;
+                LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairTlsCertGetCertByDigest 0x00000140 false true {
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairTlsCertGetCertByDigest {
+                    msg_id,
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToCliTlsCertGetCertByDigestResponse 0x00000141 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = codec::CodecWriter::new(1024)?;
+                writer.write_u32(1024)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_shared()?;
+                LairWire::ToCliTlsCertGetCertByDigestResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairTlsCertGetCertBySni 0x00000150 false true {
+                cert_sni: CertSni,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_sni = reader.read_str()?;
+                LairWire::ToLairTlsCertGetCertBySni {
+                    msg_id,
+                    cert_sni: cert_sni.into(),
+                }
+            },
+            ToCliTlsCertGetCertBySniResponse 0x00000151 false false {
+                cert: Cert,
+            } |msg_id, wire_type| {
+                let mut writer = codec::CodecWriter::new(1024)?;
+                writer.write_u32(1024)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(cert, 968)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert = reader.read_sized_shared()?;
+                LairWire::ToCliTlsCertGetCertBySniResponse {
+                    msg_id,
+                    cert: cert.into(),
+                }
+            },
+            ToLairTlsCertGetPrivKeyByIndex 0x00000160 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliTlsCertGetPrivKeyByIndexResponse 0x00000161 false false {
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                    msg_id,
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertGetPrivKeyByDigest 0x00000170 false true {
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToLairTlsCertGetPrivKeyByDigest {
+                    msg_id,
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToCliTlsCertGetPrivKeyByDigestResponse 0x00000171 false false {
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                    msg_id,
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertGetPrivKeyBySni 0x00000180 false true {
+                cert_sni: CertSni,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(cert_sni, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_sni = reader.read_str()?;
+                LairWire::ToLairTlsCertGetPrivKeyBySni {
+                    msg_id,
+                    cert_sni: cert_sni.into(),
+                }
+            },
+            ToCliTlsCertGetPrivKeyBySniResponse 0x00000181 false false {
+                cert_priv_key: CertPrivKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(cert_priv_key, 220)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_priv_key = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                    msg_id,
+                    cert_priv_key: cert_priv_key.into(),
+                }
+            },
+            ToLairTlsCertNewSelfSignedTagged 0x00000190 false true {
+                cert_alg: TlsCertAlg,
+                digest_alg: TlsCertDigestAlg,
+                exportable: Option<bool>,
+                sni: Option<String>,
+                alt_names: Vec<String>,
+                tag: String,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // cert alg
+                    + 4 // digest alg
+                    + 1 // exportable
+                    + tls_cert_names_size(sni, alt_names) // names
+                    + 8 // tag length
+                    + tag.len(); // tag
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(*cert_alg as u32)?;
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(&[exportable_flag(exportable)], 1)?;
+                write_tls_cert_names(&mut writer, sni, alt_names)?;
+                writer.write_str(tag, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                let digest_alg = TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                let exportable =
+                    parse_exportable_flag(reader.read_bytes(1)?[0])?;
+                let (sni, alt_names) = read_tls_cert_names(&mut reader)?;
+                let tag = reader.read_str()?;
+                LairWire::ToLairTlsCertNewSelfSignedTagged {
+                    msg_id,
+                    cert_alg,
+                    digest_alg,
+                    exportable,
+                    sni,
+                    alt_names,
+                    tag,
+                }
+            },
+            ToCliTlsCertNewSelfSignedTaggedResponse 0x00000191 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertNewSelfSignedTaggedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertRenew 0x000001a0 false true {
+                keystore_index: KeystoreIndex,
+                digest_alg: TlsCertDigestAlg,
+                exportable: Option<bool>,
+                sni: Option<String>,
+                alt_names: Vec<String>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 4 // digest alg
+                    + 1 // exportable
+                    + tls_cert_names_size(sni, alt_names); // names
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(&[exportable_flag(exportable)], 1)?;
+                write_tls_cert_names(&mut writer, sni, alt_names)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let digest_alg = TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                let exportable =
+                    parse_exportable_flag(reader.read_bytes(1)?[0])?;
+                let (sni, alt_names) = read_tls_cert_names(&mut reader)?;
+                LairWire::ToLairTlsCertRenew {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    digest_alg,
+                    exportable,
+                    sni,
+                    alt_names,
+                }
+            },
+            ToCliTlsCertRenewResponse 0x000001a1 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertRenewResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertImport 0x000001c0 false true {
+                cert_der: Cert,
+                priv_key_der: CertPrivKey,
+                sni: Option<String>,
+                digest_alg: TlsCertDigestAlg,
+                exportable: Option<bool>,
+            } |msg_id, wire_type| {
+                let inner_sni = match sni {
+                    Some(inner) => inner.as_str(),
+                    None => "",
+                };
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // cert length
+                    + cert_der.len() // cert
+                    + 8 // priv key length
+                    + priv_key_der.len() // priv key
+                    + 1 // is some?
+                    + 8 // sni length
+                    + inner_sni.len() // sni
+                    + 4 // digest alg
+                    + 1; // exportable
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(cert_der, entry::ENTRY_SIZE)?;
+                writer.write_sized_bytes(priv_key_der, entry::ENTRY_SIZE)?;
+                writer.write_bytes_exact(&[sni.is_some() as u8], 1)?;
+                writer.write_str(inner_sni, tls::TLS_CERT_NAMES_MAX_BYTES)?;
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(&[exportable_flag(exportable)], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_der = reader.read_sized_shared()?;
+                let priv_key_der = reader.read_sized_bytes()?;
+                let some_byte = reader.read_bytes(1)?[0];
+                let sni = reader.read_str()?;
+                let sni = if some_byte == 1 {
+                    Some(sni)
+                } else {
+                    None
+                };
+                let digest_alg = TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                let exportable =
+                    parse_exportable_flag(reader.read_bytes(1)?[0])?;
+                LairWire::ToLairTlsCertImport {
+                    msg_id,
+                    cert_der: cert_der.into(),
+                    priv_key_der: priv_key_der.into(),
+                    sni,
+                    digest_alg,
+                    exportable,
+                }
+            },
+            ToCliTlsCertImportResponse 0x000001c1 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertImportResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCaNewSelfSigned 0x000001d0 false true {
+                cert_alg: TlsCertAlg,
+                digest_alg: TlsCertDigestAlg,
+                exportable: Option<bool>,
+                sni: Option<String>,
+                alt_names: Vec<String>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // cert alg
+                    + 4 // digest alg
+                    + 1 // exportable
+                    + tls_cert_names_size(sni, alt_names); // names
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(*cert_alg as u32)?;
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(&[exportable_flag(exportable)], 1)?;
+                write_tls_cert_names(&mut writer, sni, alt_names)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                let digest_alg = TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                let exportable =
+                    parse_exportable_flag(reader.read_bytes(1)?[0])?;
+                let (sni, alt_names) = read_tls_cert_names(&mut reader)?;
+                LairWire::ToLairTlsCaNewSelfSigned {
+                    msg_id,
+                    cert_alg,
+                    digest_alg,
+                    exportable,
+                    sni,
+                    alt_names,
+                }
+            },
+            ToCliTlsCaNewSelfSignedResponse 0x000001d1 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCaNewSelfSignedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertNewSignedByCa 0x000001e0 false true {
+                ca_index: KeystoreIndex,
+                cert_alg: TlsCertAlg,
+                digest_alg: TlsCertDigestAlg,
+                exportable: Option<bool>,
+                sni: Option<String>,
+                alt_names: Vec<String>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // ca keystore index
+                    + 4 // cert alg
+                    + 4 // digest alg
+                    + 1 // exportable
+                    + tls_cert_names_size(sni, alt_names); // names
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**ca_index)?;
+                writer.write_u32(*cert_alg as u32)?;
+                writer.write_u32(*digest_alg as u32)?;
+                writer.write_bytes_exact(&[exportable_flag(exportable)], 1)?;
+                write_tls_cert_names(&mut writer, sni, alt_names)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let ca_index = reader.read_u32()?;
+                let cert_alg = TlsCertAlg::parse(reader.read_u32()?)?;
+                let digest_alg = TlsCertDigestAlg::parse(reader.read_u32()?)?;
+                let exportable =
+                    parse_exportable_flag(reader.read_bytes(1)?[0])?;
+                let (sni, alt_names) = read_tls_cert_names(&mut reader)?;
+                LairWire::ToLairTlsCertNewSignedByCa {
+                    msg_id,
+                    ca_index: ca_index.into(),
+                    cert_alg,
+                    digest_alg,
+                    exportable,
+                    sni,
+                    alt_names,
+                }
+            },
+            ToCliTlsCertNewSignedByCaResponse 0x000001e1 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+                cert_chain: Vec<Cert>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 8 // sni length
+                    + cert_sni.len() // sni
+                    + 32 // digest
+                    + 8 // chain length
+                    + cert_chain.iter().map(|c| 8 + c.len()).sum::<usize>(); // certs
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                writer.write_u64(cert_chain.len() as u64)?;
+                for cert in cert_chain {
+                    writer.write_sized_bytes(cert, entry::ENTRY_SIZE)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                let len = reader.read_u64()?;
+                let cert_chain = (0..len)
+                    .map(|_| Ok(reader.read_sized_shared()?.into()))
+                    .collect::<LairResult<_>>()?;
+                LairWire::ToCliTlsCertNewSignedByCaResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                    cert_chain,
+                }
+            },
+            ToLairTlsCertFindByDigestPrefix 0x000001f0 false true {
+                prefix: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(prefix, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let prefix = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairTlsCertFindByDigestPrefix { msg_id, prefix }
+            },
+            ToCliTlsCertFindByDigestPrefixResponse 0x000001f1 false false {
+                keystore_index: KeystoreIndex,
+                cert_sni: CertSni,
+                cert_digest: CertDigest,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_str(cert_sni, 128)?;
+                writer.write_bytes_exact(cert_digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let cert_sni = reader.read_str()?;
+                let cert_digest = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliTlsCertFindByDigestPrefixResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    cert_sni: cert_sni.into(),
+                    cert_digest: cert_digest.into(),
+                }
+            },
+            ToLairTlsCertExportPkcs12 0x000001b0 false true {
+                keystore_index: KeystoreIndex,
+                password: Option<String>,
+            } |msg_id, wire_type| {
+                let inner_password = match password {
+                    Some(inner) => inner.as_str(),
+                    None => "",
+                };
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 1 // is some?
+                    + 8 // password length
+                    + inner_password.len(); // password
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(&[password.is_some() as u8], 1)?;
+                writer.write_str(inner_password, pkcs12::MAX_PASSWORD_BYTES)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let some_byte = reader.read_bytes(1)?[0];
+                let password = reader.read_str()?;
+                let password = if some_byte == 1 {
+                    Some(password)
+                } else {
+                    None
+                };
+                LairWire::ToLairTlsCertExportPkcs12 {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    password,
+                }
+            },
+            ToCliTlsCertExportPkcs12Response 0x000001b1 false false {
+                pkcs12: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // pkcs12 length
+                    + pkcs12.len(); // pkcs12
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(pkcs12, pkcs12.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pkcs12 = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertExportPkcs12Response {
+                    msg_id,
+                    pkcs12: Arc::new(pkcs12),
+                }
+            },
+            ToLairLairCompactStore 0x000000d0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairCompactStore { msg_id }
+            },
+            ToCliLairCompactStoreResponse 0x000000d1 false false {
+                info: LairCompactInfo,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u64(info.entries_removed)?;
+                writer.write_u64(info.bytes_reclaimed)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let entries_removed = reader.read_u64()?;
+                let bytes_reclaimed = reader.read_u64()?;
+                LairWire::ToCliLairCompactStoreResponse {
+                    msg_id,
+                    info: LairCompactInfo {
+                        entries_removed,
+                        bytes_reclaimed,
+                    },
+                }
+            },
+            ToLairLairSubscribeStoreEvents 0x000000e0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairSubscribeStoreEvents { msg_id }
+            },
+            ToCliLairSubscribeStoreEventsResponse 0x000000e1 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairSubscribeStoreEventsResponse { msg_id }
+            },
+            ToLairLairLock 0x000000f0 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairLock { msg_id }
+            },
+            ToCliLairLockResponse 0x000000f1 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairLockResponse { msg_id }
+            },
+            ToLairLairUnlock 0x000000f2 false true {
+                passphrase: SecretBytes,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(passphrase, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let passphrase = reader.read_sized_secret()?;
+                LairWire::ToLairLairUnlock { msg_id, passphrase }
+            },
+            ToCliLairUnlockResponse 0x000000f3 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairUnlockResponse { msg_id }
+            },
+            ToLairLairGetLockState 0x000000f4 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairGetLockState { msg_id }
+            },
+            ToCliLairGetLockStateResponse 0x000000f5 false false {
+                lock_state: LairLockState,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(*lock_state as u32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let lock_state = LairLockState::parse(reader.read_u32()?)?;
+                LairWire::ToCliLairGetLockStateResponse { msg_id, lock_state }
+            },
+            ToLairLairRegisterPassphraseProvider 0x000000f6 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairRegisterPassphraseProvider { msg_id }
+            },
+            ToCliLairRegisterPassphraseProviderResponse 0x000000f7 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairRegisterPassphraseProviderResponse { msg_id }
+            },
+            ToLairLairSetEntryRateLimit 0x000000f8 false true {
+                keystore_index: KeystoreIndex,
+                rate_limit: Option<u32>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(rate_limit.unwrap_or(0))?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let rate_limit = Some(reader.read_u32()?).filter(|n| *n != 0);
+                LairWire::ToLairLairSetEntryRateLimit {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    rate_limit,
+                }
+            },
+            ToCliLairSetEntryRateLimitResponse 0x000000f9 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairSetEntryRateLimitResponse { msg_id }
+            },
+            ToLairLairRegisterSigningApprover 0x000000fa false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairLairRegisterSigningApprover { msg_id }
+            },
+            ToCliLairRegisterSigningApproverResponse 0x000000fb false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliLairRegisterSigningApproverResponse { msg_id }
+            },
+            ToLairTlsCertSignByIndex 0x00000100 false true {
+                keystore_index: KeystoreIndex,
+                scheme: TlsCertSignatureScheme,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 4 // signature scheme
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*scheme as u32)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let scheme = TlsCertSignatureScheme::parse(reader.read_u32()?)?;
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairTlsCertSignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    scheme,
+                    message,
+                }
+            },
+            ToCliTlsCertSignByIndexResponse 0x00000101 false false {
+                signature: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // signature length
+                    + signature.len(); // signature
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(signature, signature.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_sized_bytes()?;
+                LairWire::ToCliTlsCertSignByIndexResponse {
+                    msg_id,
+                    signature: Arc::new(signature),
+                }
+            },
+            ToLairSignEd25519NewFromEntropy 0x00000210 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignEd25519NewFromEntropy { msg_id }
+            },
+            ToCliSignEd25519NewFromEntropyResponse 0x00000211 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_ed25519::SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519Get 0x00000220 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignEd25519Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignEd25519GetResponse 0x00000221 false false {
+                pub_key: sign_ed25519::SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519GetResponse {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519SignByIndex 0x00000230 false true {
+                keystore_index: KeystoreIndex,
+                message: Bytes,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let message = reader.read_sized_shared()?;
+                LairWire::ToLairSignEd25519SignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByIndexResponse 0x00000231 false false {
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByIndexResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByPubKey 0x00000240 false true {
+                pub_key: sign_ed25519::SignEd25519PubKey,
+                message: Bytes,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // pub_key
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let message = reader.read_sized_shared()?;
+                LairWire::ToLairSignEd25519SignByPubKey {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByPubKeyResponse 0x00000241 false false {
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519NewFromEntropyTagged 0x00000260 false true {
+                tag: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(tag, 128)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let tag = reader.read_str()?;
+                LairWire::ToLairSignEd25519NewFromEntropyTagged { msg_id, tag }
+            },
+            ToCliSignEd25519NewFromEntropyTaggedResponse 0x00000261 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_ed25519::SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewFromEntropyTaggedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519NewFromEntropyRequiringApproval 0x0000026a false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignEd25519NewFromEntropyRequiringApproval {
+                    msg_id,
+                }
+            },
+            ToCliSignEd25519NewFromEntropyRequiringApprovalResponse 0x0000026b false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_ed25519::SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewFromEntropyRequiringApprovalResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519SignByTag 0x00000270 false true {
+                tag: String,
+                message: Bytes,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // tag length
+                    + tag.len() // tag content
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_str(tag, 128)?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let tag = reader.read_str()?;
+                let message = reader.read_sized_shared()?;
+                LairWire::ToLairSignEd25519SignByTag {
+                    msg_id,
+                    tag,
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByTagResponse 0x00000271 false false {
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByTagResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519ListPubKeys 0x00000280 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSignEd25519ListPubKeys { msg_id }
+            },
+            ToCliSignEd25519ListPubKeysResponse 0x00000281 false false {
+                pub_keys: Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // pub key count
+                    + pub_keys.len() * (4 + 32);
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u64(pub_keys.len() as u64)?;
+                for (keystore_index, pub_key) in pub_keys.iter() {
+                    writer.write_u32(**keystore_index)?;
+                    writer.write_bytes_exact(&pub_key, 32)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u64()?;
+                let mut pub_keys = Vec::new();
+                for _ in 0..count {
+                    let keystore_index = reader.read_u32()?;
+                    let pub_key = reader.read_bytes(32)?.to_vec();
+                    pub_keys.push((keystore_index.into(), pub_key.into()));
+                }
+                LairWire::ToCliSignEd25519ListPubKeysResponse {
+                    msg_id,
+                    pub_keys,
+                }
+            },
+            ToLairSignEd25519NewFromSeed 0x00000290 false true {
+                seed: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(seed, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let seed = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSignEd25519NewFromSeed { msg_id, seed }
+            },
+            ToCliSignEd25519NewFromSeedResponse 0x00000291 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_ed25519::SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519NewFromSeedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSignEd25519SignBatchByIndex 0x000002a0 false true {
+                keystore_index: KeystoreIndex,
+                messages: Vec<Bytes>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore_index
+                    + 8 // message count
+                    + messages.iter().map(|m| 8 + m.len()).sum::<usize>();
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_u64(messages.len() as u64)?;
+                for message in messages.iter() {
+                    writer.write_sized_bytes(message, message.len())?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let count = reader.read_u64()?;
+                let mut messages = Vec::new();
+                for _ in 0..count {
+                    messages.push(reader.read_sized_shared()?);
+                }
+                LairWire::ToLairSignEd25519SignBatchByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    messages,
+                }
+            },
+            ToCliSignEd25519SignBatchByIndexResponse 0x000002a1 false false {
+                signatures: Vec<sign_ed25519::SignEd25519Signature>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // signature count
+                    + signatures.len() * 64;
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u64(signatures.len() as u64)?;
+                for signature in signatures.iter() {
+                    writer.write_bytes_exact(signature, 64)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u64()?;
+                let mut signatures = Vec::new();
+                for _ in 0..count {
+                    signatures.push(reader.read_bytes(64)?.to_vec().into());
+                }
+                LairWire::ToCliSignEd25519SignBatchByIndexResponse {
+                    msg_id,
+                    signatures,
+                }
+            },
+            ToLairSignEd25519SignBatchByPubKey 0x000002b0 false true {
+                pub_key: sign_ed25519::SignEd25519PubKey,
+                messages: Vec<Bytes>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // pub_key
+                    + 8 // message count
+                    + messages.iter().map(|m| 8 + m.len()).sum::<usize>();
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_u64(messages.len() as u64)?;
+                for message in messages.iter() {
+                    writer.write_sized_bytes(message, message.len())?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let count = reader.read_u64()?;
+                let mut messages = Vec::new();
+                for _ in 0..count {
+                    messages.push(reader.read_sized_shared()?);
+                }
+                LairWire::ToLairSignEd25519SignBatchByPubKey {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    messages,
+                }
+            },
+            ToCliSignEd25519SignBatchByPubKeyResponse 0x000002b1 false false {
+                signatures: Vec<sign_ed25519::SignEd25519Signature>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // signature count
+                    + signatures.len() * 64;
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u64(signatures.len() as u64)?;
+                for signature in signatures.iter() {
+                    writer.write_bytes_exact(signature, 64)?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u64()?;
+                let mut signatures = Vec::new();
+                for _ in 0..count {
+                    signatures.push(reader.read_bytes(64)?.to_vec().into());
+                }
+                LairWire::ToCliSignEd25519SignBatchByPubKeyResponse {
+                    msg_id,
+                    signatures,
+                }
+            },
+            ToLairSignEd25519Verify 0x000002c0 false true {
+                pub_key: sign_ed25519::SignEd25519PubKey,
+                message: Bytes,
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // pub_key
+                    + 8 // message length
+                    + message.len() // message content
+                    + 64; // signature
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_sized_bytes(message, message.len())?;
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let message = reader.read_sized_shared()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToLairSignEd25519Verify {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    message,
+                    signature: signature.into(),
+                }
+            },
+            ToCliSignEd25519VerifyResponse 0x000002c1 false false {
+                valid: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(&[*valid as u8], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let valid = reader.read_bytes(1)?[0] == 1;
+                LairWire::ToCliSignEd25519VerifyResponse { msg_id, valid }
+            },
+            ToLairSignEd25519SignPrehashed 0x000002d0 false true {
+                keystore_index: KeystoreIndex,
+                digest: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let mut digest = [0; 32];
+                digest.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToLairSignEd25519SignPrehashed {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    digest,
+                }
+            },
+            ToCliSignEd25519SignPrehashedResponse 0x000002d1 false false {
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignPrehashedResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByIndexWithContext 0x000002e0 false true {
+                keystore_index: KeystoreIndex,
+                context: Arc<Vec<u8>>,
+                message: Bytes,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore_index
+                    + 8 // context length
+                    + context.len() // context content
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(
+                    context,
+                    sign_ed25519::SIGN_ED25519_CONTEXT_MAX_BYTES,
+                )?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let context = Arc::new(reader.read_sized_bytes()?);
+                let message = reader.read_sized_shared()?;
+                LairWire::ToLairSignEd25519SignByIndexWithContext {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    context,
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByIndexWithContextResponse 0x000002e1 false false {
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByIndexWithContextResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSignEd25519SignByPubKeyWithContext 0x000002f0 false true {
+                pub_key: sign_ed25519::SignEd25519PubKey,
+                context: Arc<Vec<u8>>,
+                message: Bytes,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // pub_key
+                    + 8 // context length
+                    + context.len() // context content
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                writer.write_sized_bytes(
+                    context,
+                    sign_ed25519::SIGN_ED25519_CONTEXT_MAX_BYTES,
+                )?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                let context = Arc::new(reader.read_sized_bytes()?);
+                let message = reader.read_sized_shared()?;
+                LairWire::ToLairSignEd25519SignByPubKeyWithContext {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    context,
+                    message,
+                }
+            },
+            ToCliSignEd25519SignByPubKeyWithContextResponse 0x000002f1 false false {
+                signature: sign_ed25519::SignEd25519Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(signature, 64)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader.read_bytes(64)?.to_vec();
+                LairWire::ToCliSignEd25519SignByPubKeyWithContextResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSeedNewFromEntropy 0x00000410 false true {
+                exportable: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(&[*exportable as u8], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let exportable = reader.read_bytes(1)?[0] == 1;
+                LairWire::ToLairSeedNewFromEntropy { msg_id, exportable }
+            },
+            ToCliSeedNewFromEntropyResponse 0x00000411 false false {
+                keystore_index: KeystoreIndex,
+                seed_id: seed::SeedId,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(seed_id, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let seed_id = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSeedNewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    seed_id: seed_id.into(),
+                }
+            },
+            ToLairSignEd25519DeriveFromSeed 0x00000420 false true {
+                seed_index: KeystoreIndex,
+                derivation_path: Vec<u32>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**seed_index)?;
+                write_derivation_path(&mut writer, derivation_path)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let seed_index = reader.read_u32()?;
+                let derivation_path = read_derivation_path(&mut reader)?;
+                LairWire::ToLairSignEd25519DeriveFromSeed {
+                    msg_id,
+                    seed_index: seed_index.into(),
+                    derivation_path,
+                }
+            },
+            ToCliSignEd25519DeriveFromSeedResponse 0x00000421 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_ed25519::SignEd25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(pub_key, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSignEd25519DeriveFromSeedResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSeedExportMnemonic 0x00000430 false true {
+                seed_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**seed_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let seed_index = reader.read_u32()?;
+                LairWire::ToLairSeedExportMnemonic {
+                    msg_id,
+                    seed_index: seed_index.into(),
+                }
+            },
+            ToCliSeedExportMnemonicResponse 0x00000431 false false {
+                mnemonic: String,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(&mnemonic, MNEMONIC_MAX_BYTES)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mnemonic = reader.read_str()?;
+                LairWire::ToCliSeedExportMnemonicResponse {
+                    msg_id,
+                    mnemonic,
+                }
+            },
+            ToLairSeedImportMnemonic 0x00000440 false true {
+                mnemonic: String,
+                exportable: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_str(&mnemonic, MNEMONIC_MAX_BYTES)?;
+                writer.write_bytes_exact(&[*exportable as u8], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let mnemonic = reader.read_str()?;
+                let exportable = reader.read_bytes(1)?[0] == 1;
+                LairWire::ToLairSeedImportMnemonic {
+                    msg_id,
+                    mnemonic,
+                    exportable,
+                }
+            },
+            ToCliSeedImportMnemonicResponse 0x00000441 false false {
+                keystore_index: KeystoreIndex,
+                seed_id: seed::SeedId,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(seed_id, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let seed_id = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSeedImportMnemonicResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    seed_id: seed_id.into(),
+                }
+            },
+            ToLairSecp256k1NewFromEntropy 0x00000510 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSecp256k1NewFromEntropy { msg_id }
+            },
+            ToCliSecp256k1NewFromEntropyResponse 0x00000511 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_secp256k1::SignSecp256k1PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(
+                    pub_key,
+                    sign_secp256k1::PUB_KEY_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader
+                    .read_bytes(sign_secp256k1::PUB_KEY_BYTES as _)?
+                    .to_vec();
+                LairWire::ToCliSecp256k1NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSecp256k1Get 0x00000520 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSecp256k1Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSecp256k1GetResponse 0x00000521 false false {
+                pub_key: sign_secp256k1::SignSecp256k1PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(
+                    pub_key,
+                    sign_secp256k1::PUB_KEY_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader
+                    .read_bytes(sign_secp256k1::PUB_KEY_BYTES as _)?
+                    .to_vec();
+                LairWire::ToCliSecp256k1GetResponse {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairSecp256k1SignByIndex 0x00000530 false true {
+                keystore_index: KeystoreIndex,
+                digest: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let mut digest = [0; 32];
+                digest.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToLairSecp256k1SignByIndex {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    digest,
+                }
+            },
+            ToCliSecp256k1SignByIndexResponse 0x00000531 false false {
+                signature: sign_secp256k1::SignSecp256k1Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(
+                    signature,
+                    sign_secp256k1::SIGNATURE_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader
+                    .read_bytes(sign_secp256k1::SIGNATURE_BYTES as _)?
+                    .to_vec();
+                LairWire::ToCliSecp256k1SignByIndexResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSecp256k1SignByPubKey 0x00000540 false true {
+                pub_key: sign_secp256k1::SignSecp256k1PubKey,
+                digest: [u8; 32],
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(
+                    pub_key,
+                    sign_secp256k1::PUB_KEY_BYTES,
+                )?;
+                writer.write_bytes_exact(digest, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader
+                    .read_bytes(sign_secp256k1::PUB_KEY_BYTES as _)?
+                    .to_vec();
+                let mut digest = [0; 32];
+                digest.copy_from_slice(reader.read_bytes(32)?);
+                LairWire::ToLairSecp256k1SignByPubKey {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    digest,
+                }
+            },
+            ToCliSecp256k1SignByPubKeyResponse 0x00000541 false false {
+                signature: sign_secp256k1::SignSecp256k1Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(
+                    signature,
+                    sign_secp256k1::SIGNATURE_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader
+                    .read_bytes(sign_secp256k1::SIGNATURE_BYTES as _)?
+                    .to_vec();
+                LairWire::ToCliSecp256k1SignByPubKeyResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairBlsNewFromEntropy 0x00000610 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairBlsNewFromEntropy { msg_id }
+            },
+            ToCliBlsNewFromEntropyResponse 0x00000611 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: sign_bls12381::SignBls12381PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(
+                    pub_key,
+                    sign_bls12381::PUB_KEY_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader
+                    .read_bytes(sign_bls12381::PUB_KEY_BYTES as _)?
+                    .to_vec();
+                LairWire::ToCliBlsNewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key: pub_key.into(),
+                }
+            },
+            ToLairBlsSignByPubKey 0x00000620 false true {
+                pub_key: sign_bls12381::SignBls12381PubKey,
+                message: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                // outgoing sig requests just need to be the right size...
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + sign_bls12381::PUB_KEY_BYTES // pub_key
+                    + 8 // message length
+                    + message.len(); // message content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(
+                    pub_key,
+                    sign_bls12381::PUB_KEY_BYTES,
+                )?;
+                writer.write_sized_bytes(message, message.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader
+                    .read_bytes(sign_bls12381::PUB_KEY_BYTES as _)?
+                    .to_vec();
+                let message = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairBlsSignByPubKey {
+                    msg_id,
+                    pub_key: pub_key.into(),
+                    message,
+                }
+            },
+            ToCliBlsSignByPubKeyResponse 0x00000621 false false {
+                signature: sign_bls12381::SignBls12381Signature,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(
+                    signature,
+                    sign_bls12381::SIGNATURE_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let signature = reader
+                    .read_bytes(sign_bls12381::SIGNATURE_BYTES as _)?
+                    .to_vec();
+                LairWire::ToCliBlsSignByPubKeyResponse {
+                    msg_id,
+                    signature: signature.into(),
+                }
+            },
+            ToLairSecretNewFromEntropy 0x00000710 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairSecretNewFromEntropy { msg_id }
+            },
+            ToCliSecretNewFromEntropyResponse 0x00000711 false false {
+                keystore_index: KeystoreIndex,
+                tag: secret_key::SecretKeyTag,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(tag, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let tag = reader.read_bytes(32)?.to_vec();
+                LairWire::ToCliSecretNewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    tag: tag.into(),
+                }
+            },
+            ToLairSecretEncryptByIndex 0x00000720 false true {
+                keystore_index: KeystoreIndex,
+                aad: Arc<Vec<u8>>,
+                data: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 8 // aad length
+                    + aad.len() // aad
+                    + 8 // data length
+                    + data.len(); // data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(aad, aad.len())?;
+                writer.write_sized_bytes(data, data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let aad = Arc::new(reader.read_sized_bytes()?);
+                let data = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSecretEncryptByIndex {
+                    msg_id,
+                    keystore_index,
+                    aad,
+                    data,
+                }
+            },
+            ToCliSecretEncryptByIndexResponse 0x00000721 false false {
+                nonce: secret_key::SecretNonce,
+                ciphertext: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + secret_key::NONCE_BYTES // nonce
+                    + 8 // ciphertext length
+                    + ciphertext.len(); // ciphertext
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(&nonce.0, secret_key::NONCE_BYTES)?;
+                writer.write_sized_bytes(ciphertext, ciphertext.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let nonce = reader
+                    .read_bytes(secret_key::NONCE_BYTES as _)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                let ciphertext = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliSecretEncryptByIndexResponse {
+                    msg_id,
+                    nonce: secret_key::SecretNonce(nonce),
+                    ciphertext,
+                }
+            },
+            ToLairSecretDecryptByIndex 0x00000730 false true {
+                keystore_index: KeystoreIndex,
+                aad: Arc<Vec<u8>>,
+                nonce: secret_key::SecretNonce,
+                ciphertext: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 8 // aad length
+                    + aad.len() // aad
+                    + secret_key::NONCE_BYTES // nonce
+                    + 8 // ciphertext length
+                    + ciphertext.len(); // ciphertext
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(aad, aad.len())?;
+                writer.write_bytes_exact(&nonce.0, secret_key::NONCE_BYTES)?;
+                writer.write_sized_bytes(ciphertext, ciphertext.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let aad = Arc::new(reader.read_sized_bytes()?);
+                let nonce = reader
+                    .read_bytes(secret_key::NONCE_BYTES as _)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                let ciphertext = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSecretDecryptByIndex {
+                    msg_id,
+                    keystore_index,
+                    aad,
+                    nonce: secret_key::SecretNonce(nonce),
+                    ciphertext,
+                }
+            },
+            ToCliSecretDecryptByIndexResponse 0x00000731 false false {
+                data: Option<Arc<Vec<u8>>>,
+            } |msg_id, wire_type| {
+                let inner_data: &[u8] = match data {
+                    Some(inner) => inner,
+                    None => &[],
+                };
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 1 // is some?
+                    + 8 // data length
+                    + inner_data.len(); // data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(&[data.is_some() as u8], 1)?;
+                writer.write_sized_bytes(inner_data, inner_data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let some_byte = reader.read_bytes(1)?[0];
+                let data_bytes = reader.read_sized_bytes()?;
+                let data = if some_byte == 1 {
+                    Some(Arc::new(data_bytes))
+                } else {
+                    None
+                };
+                LairWire::ToCliSecretDecryptByIndexResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairDeriveSubkeyByIndex 0x00000740 false true {
+                keystore_index: KeystoreIndex,
+                subkey_len: usize,
+                context: [u8; secret_key::SUBKEY_CONTEXT_BYTES],
+                subkey_id: u64,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_u32(*subkey_len as u32)?;
+                writer.write_bytes_exact(
+                    context,
+                    secret_key::SUBKEY_CONTEXT_BYTES,
+                )?;
+                writer.write_u64(*subkey_id)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let subkey_len = reader.read_u32()? as usize;
+                let context = reader
+                    .read_bytes(secret_key::SUBKEY_CONTEXT_BYTES as _)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                let subkey_id = reader.read_u64()?;
+                LairWire::ToLairDeriveSubkeyByIndex {
+                    msg_id,
+                    keystore_index,
+                    subkey_len,
+                    context,
+                    subkey_id,
+                }
+            },
+            ToCliDeriveSubkeyByIndexResponse 0x00000741 false false {
+                subkey: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(subkey, subkey.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let subkey = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliDeriveSubkeyByIndexResponse {
+                    msg_id,
+                    subkey,
+                }
+            },
+            ToLairSecretstreamInitPushByIndex 0x00000810 false true {
+                keystore_index: KeystoreIndex,
+                remote_pub: x25519::X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(remote_pub), 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let remote_pub = reader.read_bytes(32)?.try_into()?;
+                LairWire::ToLairSecretstreamInitPushByIndex {
+                    msg_id,
+                    keystore_index,
+                    remote_pub,
+                }
+            },
+            ToCliSecretstreamInitPushByIndexResponse 0x00000811 false false {
+                session_id: secretstream::SecretstreamSessionId,
+                header: secretstream::SecretstreamHeader,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(&session_id.0, 32)?;
+                writer.write_bytes_exact(
+                    &header.0,
+                    secretstream::HEADER_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let session_id = reader
+                    .read_bytes(32)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                let header = reader
+                    .read_bytes(secretstream::HEADER_BYTES as _)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                LairWire::ToCliSecretstreamInitPushByIndexResponse {
+                    msg_id,
+                    session_id: secretstream::SecretstreamSessionId(session_id),
+                    header: secretstream::SecretstreamHeader(header),
+                }
+            },
+            ToLairSecretstreamPush 0x00000820 false true {
+                session_id: secretstream::SecretstreamSessionId,
+                chunk: Arc<Vec<u8>>,
+                is_final: bool,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // session id
+                    + 8 // chunk length
+                    + chunk.len() // chunk
+                    + 1; // is final?
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(&session_id.0, 32)?;
+                writer.write_sized_bytes(chunk, chunk.len())?;
+                writer.write_bytes_exact(&[*is_final as u8], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let session_id = reader
+                    .read_bytes(32)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                let chunk = Arc::new(reader.read_sized_bytes()?);
+                let is_final = reader.read_bytes(1)?[0] == 1;
+                LairWire::ToLairSecretstreamPush {
+                    msg_id,
+                    session_id: secretstream::SecretstreamSessionId(session_id),
+                    chunk,
+                    is_final,
+                }
+            },
+            ToCliSecretstreamPushResponse 0x00000821 false false {
+                ciphertext: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // ciphertext length
+                    + ciphertext.len(); // ciphertext
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_sized_bytes(ciphertext, ciphertext.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let ciphertext = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToCliSecretstreamPushResponse {
+                    msg_id,
+                    ciphertext,
+                }
+            },
+            ToLairSecretstreamInitPullByIndex 0x00000830 false true {
+                keystore_index: KeystoreIndex,
+                remote_pub: x25519::X25519PubKey,
+                header: secretstream::SecretstreamHeader,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(remote_pub), 32)?;
+                writer.write_bytes_exact(
+                    &header.0,
+                    secretstream::HEADER_BYTES,
+                )?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let remote_pub = reader.read_bytes(32)?.try_into()?;
+                let header = reader
+                    .read_bytes(secretstream::HEADER_BYTES as _)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                LairWire::ToLairSecretstreamInitPullByIndex {
+                    msg_id,
+                    keystore_index,
+                    remote_pub,
+                    header: secretstream::SecretstreamHeader(header),
+                }
+            },
+            ToCliSecretstreamInitPullByIndexResponse 0x00000831 false false {
+                session_id: secretstream::SecretstreamSessionId,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(&session_id.0, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let session_id = reader
+                    .read_bytes(32)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                LairWire::ToCliSecretstreamInitPullByIndexResponse {
+                    msg_id,
+                    session_id: secretstream::SecretstreamSessionId(session_id),
+                }
+            },
+            ToLairSecretstreamPull 0x00000840 false true {
+                session_id: secretstream::SecretstreamSessionId,
+                ciphertext: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // session id
+                    + 8 // ciphertext length
+                    + ciphertext.len(); // ciphertext
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(&session_id.0, 32)?;
+                writer.write_sized_bytes(ciphertext, ciphertext.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let session_id = reader
+                    .read_bytes(32)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                let ciphertext = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairSecretstreamPull {
+                    msg_id,
+                    session_id: secretstream::SecretstreamSessionId(session_id),
+                    ciphertext,
+                }
+            },
+            ToCliSecretstreamPullResponse 0x00000841 false false {
+                data: Option<(Arc<Vec<u8>>, bool)>,
+            } |msg_id, wire_type| {
+                let (inner_data, is_final): (&[u8], bool) = match data {
+                    Some((inner, is_final)) => (inner, *is_final),
+                    None => (&[], false),
+                };
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 1 // is some?
+                    + 8 // data length
+                    + inner_data.len() // data
+                    + 1; // is final?
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(&[data.is_some() as u8], 1)?;
+                writer.write_sized_bytes(inner_data, inner_data.len())?;
+                writer.write_bytes_exact(&[is_final as u8], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let some_byte = reader.read_bytes(1)?[0];
+                let data_bytes = reader.read_sized_bytes()?;
+                let is_final = reader.read_bytes(1)?[0] == 1;
+                let data = if some_byte == 1 {
+                    Some((Arc::new(data_bytes), is_final))
+                } else {
+                    None
+                };
+                LairWire::ToCliSecretstreamPullResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairSecretstreamClose 0x00000850 false true {
+                session_id: secretstream::SecretstreamSessionId,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(&session_id.0, 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let session_id = reader
+                    .read_bytes(32)?
+                    .try_into()
+                    .map_err(LairError::other)?;
+                LairWire::ToLairSecretstreamClose {
+                    msg_id,
+                    session_id: secretstream::SecretstreamSessionId(session_id),
+                }
+            },
+            ToCliSecretstreamCloseResponse 0x00000851 false false {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToCliSecretstreamCloseResponse { msg_id }
+            },
+            ToLairSignEd25519ToX25519 0x00000310 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairSignEd25519ToX25519 {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliSignEd25519ToX25519Response 0x00000311 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: x25519::X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.try_into()?;
+                LairWire::ToCliSignEd25519ToX25519Response {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                }
+            },
+            ToLairX25519NewFromEntropy 0x00000242 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairX25519NewFromEntropy { msg_id }
+            },
+            ToCliX25519NewFromEntropyResponse 0x00000243 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: x25519::X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.try_into()?;
+                LairWire::ToCliX25519NewFromEntropyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                }
+            },
+            ToLairX25519NewFromPrivKey 0x00000256 false true {
+                priv_key: Arc<Vec<u8>>,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_sized_bytes(priv_key, x25519::PRIV_KEY_BYTES)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let priv_key = Arc::new(reader.read_sized_bytes()?);
+                LairWire::ToLairX25519NewFromPrivKey { msg_id, priv_key }
+            },
+            ToCliX25519NewFromPrivKeyResponse 0x00000257 false false {
+                keystore_index: KeystoreIndex,
+                pub_key: x25519::X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                let pub_key = reader.read_bytes(32)?.try_into()?;
+                LairWire::ToCliX25519NewFromPrivKeyResponse {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                    pub_key,
+                }
+            },
+            ToLairX25519Get 0x00000244 false true {
+                keystore_index: KeystoreIndex,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?;
+                LairWire::ToLairX25519Get {
+                    msg_id,
+                    keystore_index: keystore_index.into(),
+                }
+            },
+            ToCliX25519GetResponse 0x00000245 false false {
+                pub_key: x25519::X25519PubKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.try_into()?;
+                LairWire::ToCliX25519GetResponse {
+                    msg_id,
+                    pub_key,
+                }
+            },
+            ToLairCryptoBoxByIndex 0x00000246 false true {
+                keystore_index: KeystoreIndex,
+                recipient: x25519::X25519PubKey,
+                data: Arc<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 32 // recipient pub key
+                    + 8 // data length
+                    + data.len(); // data content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(recipient), 32)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**data), data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let recipient = reader.read_bytes(32)?.try_into()?;
+                let data = Arc::new(reader.read_sized_shared()?.into());
+                LairWire::ToLairCryptoBoxByIndex {
+                    msg_id,
+                    keystore_index,
+                    recipient,
+                    data,
+                }
+            },
+            ToCliCryptoBoxByIndexResponse 0x00000247 false false {
+                encrypted_data: crypto_box::CryptoBoxEncryptedData,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 24 // nonce length
+                    + 8 // encrypted data length
+                    + encrypted_data.encrypted_data.len(); // encrypted data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**encrypted_data.encrypted_data), encrypted_data.encrypted_data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let nonce = reader.read_bytes(24)?.try_into()?;
+                let encrypted_data = Arc::new(reader.read_sized_bytes()?.into());
+                LairWire::ToCliCryptoBoxByIndexResponse {
+                    msg_id,
+                    encrypted_data: crypto_box::CryptoBoxEncryptedData{
+                        nonce,
+                        encrypted_data,
+                    }
+                }
+            },
+            ToLairCryptoBoxByPubKey 0x00000248 false true {
+                pub_key: x25519::X25519PubKey,
+                recipient: x25519::X25519PubKey,
+                data: Arc<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // pub key
+                    + 32 // recipient
+                    + 8 // data length
+                    + data.len(); // data content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(recipient), 32)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**data), data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let pub_key = reader.read_bytes(32)?.try_into()?;
+                let recipient = reader.read_bytes(32)?.try_into()?;
+                let data = Arc::new(reader.read_sized_shared()?.into());
+                LairWire::ToLairCryptoBoxByPubKey {
+                    msg_id,
+                    pub_key,
+                    recipient,
+                    data,
+                }
+            },
+            ToCliCryptoBoxByPubKeyResponse 0x00000249 false false {
+                encrypted_data: crypto_box::CryptoBoxEncryptedData,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 24 // nonce length
+                    + 8 // encrypted data length
+                    + encrypted_data.encrypted_data.len(); // encrypted data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**encrypted_data.encrypted_data), encrypted_data.encrypted_data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let nonce = reader.read_bytes(24)?.try_into()?;
+                let encrypted_data = Arc::new(reader.read_sized_bytes()?.into());
+                LairWire::ToCliCryptoBoxByPubKeyResponse {
+                    msg_id,
+                    encrypted_data: crypto_box::CryptoBoxEncryptedData {
+                        nonce,
+                        encrypted_data,
+                    }
+                }
+            },
+            ToLairCryptoBoxOpenByIndex 0x00000250 false true {
+                keystore_index: KeystoreIndex,
+                sender: x25519::X25519PubKey,
+                encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 32 // sender pub key
+                    + 24 // nonce length
+                    + 8 // encrypted data length
+                    + encrypted_data.encrypted_data.len(); // encrypted data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(sender), 32)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**encrypted_data.encrypted_data), encrypted_data.encrypted_data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let sender = reader.read_bytes(32)?.try_into()?;
+                let nonce = reader.read_bytes(24)?.try_into()?;
+                let data = Arc::new(reader.read_sized_bytes()?.into());
+                LairWire::ToLairCryptoBoxOpenByIndex {
+                    msg_id,
+                    keystore_index,
+                    sender,
+                    encrypted_data: Arc::new(crypto_box::CryptoBoxEncryptedData {
+                        nonce,
+                        encrypted_data: data,
+                    }),
+                }
+            },
+            ToCliCryptoBoxOpenByIndexResponse 0x00000251 false false {
+                data: Option<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                let inner_data = match data {
+                    Some(inner) => inner.data.to_vec(),
+                    None => vec![],
+                };
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 1 // is some?
+                    + 8 // data length
+                    + inner_data.len(); // data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+
+                let some_byte = if data.is_some() {
+                    1
+                }
+                else {
+                    0
+                };
+                writer.write_bytes_exact(&[some_byte], 1)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&inner_data), inner_data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let some_byte = reader.read_bytes(1)?[0];
+                let data_bytes = reader.read_sized_bytes()?.into();
+                let data = if some_byte == 1 {
+                    Some(data_bytes)
+                }
+                else {
+                    None
+                };
+                LairWire::ToCliCryptoBoxOpenByIndexResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairCryptoBoxByIndexWithAad 0x00000262 false true {
+                keystore_index: KeystoreIndex,
+                recipient: x25519::X25519PubKey,
+                aad: Arc<Vec<u8>>,
+                data: Arc<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 4 // keystore index
+                    + 32 // recipient pub key
+                    + 8 // aad length
+                    + aad.len() // aad content
+                    + 8 // data length
+                    + data.len(); // data content
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(recipient), 32)?;
+                writer.write_sized_bytes(aad, aad.len())?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**data), data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let recipient = reader.read_bytes(32)?.try_into()?;
+                let aad = Arc::new(reader.read_sized_bytes()?);
+                let data = Arc::new(reader.read_sized_shared()?.into());
+                LairWire::ToLairCryptoBoxByIndexWithAad {
+                    msg_id,
+                    keystore_index,
+                    recipient,
+                    aad,
+                    data,
+                }
+            },
+            ToCliCryptoBoxByIndexWithAadResponse 0x00000263 false false {
+                encrypted_data: crypto_box::CryptoBoxEncryptedData,
+            } |msg_id, wire_type| {
+                encode_crypto_box_encrypted_data(msg_id, wire_type, encrypted_data)
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let encrypted_data = decode_crypto_box_encrypted_data(&mut reader)?;
+                LairWire::ToCliCryptoBoxByIndexWithAadResponse {
                     msg_id,
-                    encrypted_data: crypto_box::CryptoBoxEncryptedData{
-                        nonce,
-                        encrypted_data,
-                    }
+                    encrypted_data,
                 }
             },
-            ToLairCryptoBoxByPubKey 0x00000248 false true {
+            ToLairCryptoBoxByPubKeyWithAad 0x00000264 false true {
                 pub_key: x25519::X25519PubKey,
                 recipient: x25519::X25519PubKey,
+                aad: Arc<Vec<u8>>,
                 data: Arc<crypto_box::CryptoBoxData>,
             } |msg_id, wire_type| {
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
                     + 32 // pub key
-                    + 32 // recipient
+                    + 32 // recipient pub key
+                    + 8 // aad length
+                    + aad.len() // aad content
                     + 8 // data length
                     + data.len(); // data content
                 let mut writer = codec::CodecWriter::new_zeroed(size)?;
@@ -645,26 +3824,48 @@ macro_rules! wire_type_meta_macro {
                 writer.write_u64(*msg_id)?;
                 writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
                 writer.write_bytes_exact(AsRef::<[u8]>::as_ref(recipient), 32)?;
+                writer.write_sized_bytes(aad, aad.len())?;
                 writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**data), data.len())?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
                 let pub_key = reader.read_bytes(32)?.try_into()?;
                 let recipient = reader.read_bytes(32)?.try_into()?;
-                let data = Arc::new(reader.read_sized_bytes()?.into());
-                LairWire::ToLairCryptoBoxByPubKey {
+                let aad = Arc::new(reader.read_sized_bytes()?);
+                let data = Arc::new(reader.read_sized_shared()?.into());
+                LairWire::ToLairCryptoBoxByPubKeyWithAad {
                     msg_id,
                     pub_key,
                     recipient,
+                    aad,
                     data,
                 }
             },
-            ToCliCryptoBoxByPubKeyResponse 0x00000249 false false {
+            ToCliCryptoBoxByPubKeyWithAadResponse 0x00000265 false false {
                 encrypted_data: crypto_box::CryptoBoxEncryptedData,
+            } |msg_id, wire_type| {
+                encode_crypto_box_encrypted_data(msg_id, wire_type, encrypted_data)
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let encrypted_data = decode_crypto_box_encrypted_data(&mut reader)?;
+                LairWire::ToCliCryptoBoxByPubKeyWithAadResponse {
+                    msg_id,
+                    encrypted_data,
+                }
+            },
+            ToLairCryptoBoxOpenByIndexWithAad 0x00000266 false true {
+                keystore_index: KeystoreIndex,
+                sender: x25519::X25519PubKey,
+                aad: Arc<Vec<u8>>,
+                encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
             } |msg_id, wire_type| {
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
+                    + 4 // keystore index
+                    + 32 // sender pub key
+                    + 8 // aad length
+                    + aad.len() // aad content
                     + 24 // nonce length
                     + 8 // encrypted data length
                     + encrypted_data.encrypted_data.len(); // encrypted data
@@ -672,31 +3873,51 @@ macro_rules! wire_type_meta_macro {
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(sender), 32)?;
+                writer.write_sized_bytes(aad, aad.len())?;
                 writer.write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
-                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**encrypted_data.encrypted_data), encrypted_data.encrypted_data.len())?;
+                writer.write_sized_bytes(&encrypted_data.encrypted_data, encrypted_data.encrypted_data.len())?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let nonce = reader.read_bytes(24)?.try_into()?;
-                let encrypted_data = Arc::new(reader.read_sized_bytes()?.into());
-                LairWire::ToCliCryptoBoxByPubKeyResponse {
+                let keystore_index = reader.read_u32()?.into();
+                let sender = reader.read_bytes(32)?.try_into()?;
+                let aad = Arc::new(reader.read_sized_bytes()?);
+                let encrypted_data = Arc::new(decode_crypto_box_encrypted_data(&mut reader)?);
+                LairWire::ToLairCryptoBoxOpenByIndexWithAad {
                     msg_id,
-                    encrypted_data: crypto_box::CryptoBoxEncryptedData {
-                        nonce,
-                        encrypted_data,
-                    }
+                    keystore_index,
+                    sender,
+                    aad,
+                    encrypted_data,
                 }
             },
-            ToLairCryptoBoxOpenByIndex 0x00000250 false true {
-                keystore_index: KeystoreIndex,
+            ToCliCryptoBoxOpenByIndexWithAadResponse 0x00000267 false false {
+                data: Option<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                encode_crypto_box_data_option(msg_id, wire_type, data)
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = decode_crypto_box_data_option(&mut reader)?;
+                LairWire::ToCliCryptoBoxOpenByIndexWithAadResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairCryptoBoxOpenByPubKeyWithAad 0x00000268 false true {
+                pub_key: x25519::X25519PubKey,
                 sender: x25519::X25519PubKey,
+                aad: Arc<Vec<u8>>,
                 encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
             } |msg_id, wire_type| {
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
-                    + 4 // keystore index
+                    + 32 // pub key
                     + 32 // sender pub key
+                    + 8 // aad length
+                    + aad.len() // aad content
                     + 24 // nonce length
                     + 8 // encrypted data length
                     + encrypted_data.encrypted_data.len(); // encrypted data
@@ -704,69 +3925,199 @@ macro_rules! wire_type_meta_macro {
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
-                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
                 writer.write_bytes_exact(AsRef::<[u8]>::as_ref(sender), 32)?;
+                writer.write_sized_bytes(aad, aad.len())?;
                 writer.write_bytes_exact(AsRef::<[u8]>::as_ref(&encrypted_data.nonce), 24)?;
-                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**encrypted_data.encrypted_data), encrypted_data.encrypted_data.len())?;
+                writer.write_sized_bytes(&encrypted_data.encrypted_data, encrypted_data.encrypted_data.len())?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let keystore_index = reader.read_u32()?.into();
+                let pub_key = reader.read_bytes(32)?.try_into()?;
                 let sender = reader.read_bytes(32)?.try_into()?;
-                let nonce = reader.read_bytes(24)?.try_into()?;
-                let data = Arc::new(reader.read_sized_bytes()?.into());
-                LairWire::ToLairCryptoBoxOpenByIndex {
+                let aad = Arc::new(reader.read_sized_bytes()?);
+                let encrypted_data = Arc::new(decode_crypto_box_encrypted_data(&mut reader)?);
+                LairWire::ToLairCryptoBoxOpenByPubKeyWithAad {
                     msg_id,
-                    keystore_index,
+                    pub_key,
                     sender,
-                    encrypted_data: Arc::new(crypto_box::CryptoBoxEncryptedData {
-                        nonce,
-                        encrypted_data: data,
-                    }),
+                    aad,
+                    encrypted_data,
                 }
             },
-            ToCliCryptoBoxOpenByIndexResponse 0x00000251 false false {
+            ToCliCryptoBoxOpenByPubKeyWithAadResponse 0x00000269 false false {
                 data: Option<crypto_box::CryptoBoxData>,
             } |msg_id, wire_type| {
-                let inner_data = match data {
-                    Some(inner) => inner.data.to_vec(),
-                    None => vec![],
-                };
+                encode_crypto_box_data_option(msg_id, wire_type, data)
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = decode_crypto_box_data_option(&mut reader)?;
+                LairWire::ToCliCryptoBoxOpenByPubKeyWithAadResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairCryptoBoxSealOpenByIndex 0x00000258 false true {
+                keystore_index: KeystoreIndex,
+                sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+            } |msg_id, wire_type| {
                 let size = 4 // msg len
                     + 4 // msg type
                     + 8 // msg id
-                    + 1 // is some?
-                    + 8 // data length
-                    + inner_data.len(); // data
+                    + 4 // keystore index
+                    + 8 // sealed data length
+                    + sealed_data.len(); // sealed data
                 let mut writer = codec::CodecWriter::new_zeroed(size)?;
                 writer.write_u32(size as u32)?;
                 writer.write_u32(wire_type)?;
                 writer.write_u64(*msg_id)?;
-
-                let some_byte = if data.is_some() {
-                    1
+                writer.write_u32(**keystore_index)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**sealed_data), sealed_data.len())?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let sealed_data = Arc::new(reader.read_sized_bytes()?.into());
+                LairWire::ToLairCryptoBoxSealOpenByIndex {
+                    msg_id,
+                    keystore_index,
+                    sealed_data,
                 }
-                else {
-                    0
-                };
-                writer.write_bytes_exact(&[some_byte], 1)?;
-                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&inner_data), inner_data.len())?;
+            },
+            ToCliCryptoBoxSealOpenByIndexResponse 0x00000259 false false {
+                data: Option<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                encode_crypto_box_data_option(msg_id, wire_type, data)
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = decode_crypto_box_data_option(&mut reader)?;
+                LairWire::ToCliCryptoBoxSealOpenByIndexResponse {
+                    msg_id,
+                    data,
+                }
+            },
+            ToLairCryptoBoxSealOpenByPubKey 0x0000025a false true {
+                pub_key: x25519::X25519PubKey,
+                sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 32 // recipient pub key
+                    + 8 // sealed data length
+                    + sealed_data.len(); // sealed data
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(pub_key), 32)?;
+                writer.write_sized_bytes(AsRef::<[u8]>::as_ref(&**sealed_data), sealed_data.len())?;
                 Ok(writer.into_vec())
             } |reader| {
                 let msg_id = reader.read_u64()?;
-                let some_byte = reader.read_bytes(1)?[0];
-                let data_bytes = reader.read_sized_bytes()?.into();
-                let data = if some_byte == 1 {
-                    Some(data_bytes)
+                let pub_key = reader.read_bytes(32)?.try_into()?;
+                let sealed_data = Arc::new(reader.read_sized_bytes()?.into());
+                LairWire::ToLairCryptoBoxSealOpenByPubKey {
+                    msg_id,
+                    pub_key,
+                    sealed_data,
                 }
-                else {
-                    None
-                };
-                LairWire::ToCliCryptoBoxOpenByIndexResponse {
+            },
+            ToCliCryptoBoxSealOpenByPubKeyResponse 0x0000025b false false {
+                data: Option<crypto_box::CryptoBoxData>,
+            } |msg_id, wire_type| {
+                encode_crypto_box_data_option(msg_id, wire_type, data)
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let data = decode_crypto_box_data_option(&mut reader)?;
+                LairWire::ToCliCryptoBoxSealOpenByPubKeyResponse {
                     msg_id,
                     data,
                 }
             },
+            ToLairKxSessionKeysByIndex 0x0000025c false true {
+                keystore_index: KeystoreIndex,
+                remote_pub: x25519::X25519PubKey,
+                is_server: bool,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_u32(**keystore_index)?;
+                writer.write_bytes_exact(AsRef::<[u8]>::as_ref(remote_pub), 32)?;
+                writer.write_bytes_exact(&[*is_server as u8], 1)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let keystore_index = reader.read_u32()?.into();
+                let remote_pub = reader.read_bytes(32)?.try_into()?;
+                let is_server = reader.read_bytes(1)?[0] == 1;
+                LairWire::ToLairKxSessionKeysByIndex {
+                    msg_id,
+                    keystore_index,
+                    remote_pub,
+                    is_server,
+                }
+            },
+            ToCliKxSessionKeysByIndexResponse 0x0000025d false false {
+                rx: kx::KxSessionKey,
+                tx: kx::KxSessionKey,
+            } |msg_id, wire_type| {
+                let mut writer = default_encode_setup!(msg_id, wire_type);
+                writer.write_bytes_exact(rx, kx::SESSION_KEY_BYTES)?;
+                writer.write_bytes_exact(tx, kx::SESSION_KEY_BYTES)?;
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let rx = reader.read_bytes(kx::SESSION_KEY_BYTES as _)?.to_vec().into();
+                let tx = reader.read_bytes(kx::SESSION_KEY_BYTES as _)?.to_vec().into();
+                LairWire::ToCliKxSessionKeysByIndexResponse {
+                    msg_id,
+                    rx,
+                    tx,
+                }
+            },
+            ToLairX25519ListPubKeys 0x00000254 false true {
+            } |msg_id, wire_type| {
+                let writer = default_encode_setup!(msg_id, wire_type);
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                LairWire::ToLairX25519ListPubKeys { msg_id }
+            },
+            ToCliX25519ListPubKeysResponse 0x00000255 false false {
+                pub_keys: Vec<(KeystoreIndex, x25519::X25519PubKey)>,
+            } |msg_id, wire_type| {
+                let size = 4 // msg len
+                    + 4 // msg type
+                    + 8 // msg id
+                    + 8 // pub key count
+                    + pub_keys.len() * (4 + 32);
+                let mut writer = codec::CodecWriter::new_zeroed(size)?;
+                writer.write_u32(size as u32)?;
+                writer.write_u32(wire_type)?;
+                writer.write_u64(*msg_id)?;
+                writer.write_u64(pub_keys.len() as u64)?;
+                for (keystore_index, pub_key) in pub_keys.iter() {
+                    writer.write_u32(**keystore_index)?;
+                    writer.write_bytes_exact(
+                        AsRef::<[u8]>::as_ref(pub_key),
+                        32,
+                    )?;
+                }
+                Ok(writer.into_vec())
+            } |reader| {
+                let msg_id = reader.read_u64()?;
+                let count = reader.read_u64()?;
+                let mut pub_keys = Vec::new();
+                for _ in 0..count {
+                    let keystore_index = reader.read_u32()?;
+                    let pub_key = reader.read_bytes(32)?.try_into()?;
+                    pub_keys.push((keystore_index.into(), pub_key));
+                }
+                LairWire::ToCliX25519ListPubKeysResponse {
+                    msg_id,
+                    pub_keys,
+                }
+            },
             ToLairCryptoBoxOpenByPubKey 0x00000252 false true {
                 pub_key: x25519::X25519PubKey,
                 sender: x25519::X25519PubKey,
@@ -927,6 +4278,15 @@ macro_rules! lair_wire_enum {
                 )*}
             }
 
+            /// The name of this variant.
+            pub fn name(&self) -> &'static str {
+                match self {$(
+                    LairWire::$variant {
+                        ..
+                    } => stringify!($variant),
+                )*}
+            }
+
             /// Get the msg_id associated with this variant.
             pub fn get_msg_id(&self) -> u64 {
                 match self {$(
@@ -976,13 +4336,28 @@ macro_rules! lair_wire_enum {
             }
 
             /// Decode lair wire protocol binary data into enum variant.
-            #[allow(unused_mut)]
-            #[allow(unused_variables)]
+            /// Copies the frame, see `decode_frame` to decode without.
             pub fn decode(data: &[u8]) -> LairResult<Self> {
                 if !Self::peek_size_ok(data) {
                     return Err("not enough data to decode".into());
                 }
-                let mut reader = codec::CodecReader::new(data);
+                let size = Self::peek_size(data)?;
+                Self::decode_frame(&Bytes::copy_from_slice(&data[..size]))
+            }
+
+            /// Decode a frame of lair wire protocol binary data into
+            /// enum variant. Payloads (messages to sign, data to box,
+            /// certs) are slices of the frame, not copies.
+            #[allow(unused_mut)]
+            #[allow(unused_variables)]
+            pub fn decode_frame(frame: &Bytes) -> LairResult<Self> {
+                if !Self::peek_size_ok(frame) {
+                    return Err("not enough data to decode".into());
+                }
+                let mut reader = WireReader {
+                    reader: codec::CodecReader::new(frame),
+                    frame,
+                };
                 let _size = reader.read_u32()?;
 
                 let wire_type = LairWireType::parse(reader.read_u32()?)?;
@@ -1042,9 +4417,39 @@ impl WriterExt for codec::CodecWriter {
     }
 }
 
+/// Reads a frame, see `LairWire::decode_frame`.
+struct WireReader<'lt> {
+    reader: codec::CodecReader<'lt>,
+    frame: &'lt Bytes,
+}
+
+impl<'lt> std::ops::Deref for WireReader<'lt> {
+    type Target = codec::CodecReader<'lt>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl std::ops::DerefMut for WireReader<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
+impl WireReader<'_> {
+    /// Read sized bytes as a slice of the frame.
+    fn read_sized_shared(&mut self) -> LairResult<Bytes> {
+        let len = self.read_u64()?;
+        let bytes = self.reader.read_bytes(len)?;
+        Ok(self.frame.slice_ref(bytes))
+    }
+}
+
 trait ReaderExt {
     fn read_str(&mut self) -> LairResult<String>;
     fn read_sized_bytes(&mut self) -> LairResult<Vec<u8>>;
+    fn read_sized_secret(&mut self) -> LairResult<SecretBytes>;
 }
 
 impl ReaderExt for codec::CodecReader<'_> {
@@ -1057,6 +4462,11 @@ impl ReaderExt for codec::CodecReader<'_> {
         let len = self.read_u64()?;
         Ok(self.read_bytes(len)?.to_vec())
     }
+
+    fn read_sized_secret(&mut self) -> LairResult<SecretBytes> {
+        let len = self.read_u64()?;
+        Ok(self.read_secret(len)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -1080,25 +4490,169 @@ pub(crate) mod tests {
             Arc::new(TestVal::test_val())
         }
     }
+    test_val!(bool, true);
+    test_val!(Option<bool>, Some(false));
     test_val!(String, "test-val".to_string());
+    test_val!(SecretBytes, "test-val".to_string().into());
     test_val!(Vec<u8>, vec![0x42; 32]);
-    test_val!(LairServerInfo, Default::default());
+    test_val!([u8; 32], [0x42; 32]);
+    test_val!([u8; 8], [0x42; 8]);
+    test_val!(usize, secret_key::SUBKEY_MAX_BYTES);
+    test_val!(u32, 42);
+    test_val!(Option<u32>, Some(42));
+    test_val!(u64, 42);
+    test_val!(ConnectionScope, ConnectionScope::SignOnly);
+    test_val!(
+        LairServerInfo,
+        LairServerInfo {
+            name: "test-val".to_string(),
+            version: "0.0.1".to_string(),
+            corrupt_entry_count: 42,
+            connection_count: 3,
+            requests_in_flight: 1,
+            failed_unlock_count: 2,
+            secrets_mlocked: true,
+            core_dumps_disabled: true,
+            ptrace_disabled: false,
+            umask_restricted: true,
+            crypto_threads: 4,
+            crypto_queue_depth: 7,
+            crypto_busy_threads: 4,
+        }
+    );
+    test_val!(
+        LairCompactInfo,
+        LairCompactInfo {
+            entries_removed: 3,
+            bytes_reclaimed: 3072,
+        }
+    );
     test_val!(LairEntryType, Default::default());
+    test_val!(LairLockState, LairLockState::Locked);
     test_val!(TlsCertAlg, Default::default());
+    test_val!(TlsCertDigestAlg, TlsCertDigestAlg::Sha256);
+    test_val!(TlsCertSignatureScheme, TlsCertSignatureScheme::Ed25519);
     test_val!(KeystoreIndex, 42.into());
+    test_val!(Vec<u32>, vec![0, 1, 0x7fff_ffff]);
+    test_val!(Option<String>, Some("test-val".to_string()));
+    test_val!(
+        Vec<String>,
+        vec!["test-val".to_string(), "127.0.0.1".to_string()]
+    );
+    test_val!(seed::SeedId, vec![0x42; 32].into());
+    test_val!(secret_key::SecretKeyTag, vec![0x42; 32].into());
+    test_val!(secret_key::SecretNonce, [0x42; 24].into());
+    test_val!(Option<Arc<Vec<u8>>>, Some(Arc::new(vec![0x42; 20])));
+    test_val!(secretstream::SecretstreamSessionId, [0x42; 32].into());
+    test_val!(secretstream::SecretstreamHeader, [0x42; 24].into());
+    test_val!(
+        Option<(Arc<Vec<u8>>, bool)>,
+        Some((Arc::new(vec![0x42; 20]), true))
+    );
+    test_val!(
+        Vec<EntryListItem>,
+        vec![
+            EntryListItem {
+                keystore_index: 1.into(),
+                entry_type: LairEntryType::TlsCert,
+                pub_id: Arc::new(vec![0x42; 32]),
+                cert_digest_alg: Some(TlsCertDigestAlg::Sha256),
+                cert_sni: Some("test-val".to_string().into()),
+                tag: None,
+                created_at: None,
+            },
+            EntryListItem {
+                keystore_index: 2.into(),
+                entry_type: LairEntryType::SignEd25519,
+                pub_id: Arc::new(vec![0x43; 32]),
+                cert_digest_alg: None,
+                cert_sni: None,
+                tag: Some("test-val".to_string()),
+                created_at: Some(1_600_000_000_000),
+            },
+        ]
+    );
+    test_val!(
+        Option<(KeystoreIndex, LairEntryType)>,
+        Some((42.into(), LairEntryType::SignEd25519))
+    );
+    test_val!(
+        entry::EntryMeta,
+        entry::EntryMeta {
+            tag: Some("test-val".to_string()),
+            created_at: Some(1_600_000_000_000),
+            use_count: 42,
+            last_used: Some(1_600_000_000_042),
+            derived_from: Some(entry::EntryDerivation {
+                seed_index: 3.into(),
+                path: vec![0x42; seed::MAX_DERIVATION_DEPTH],
+            }),
+            converted_from: Some(7.into()),
+            renewed_from: Some(9.into()),
+            rate_limit: Some(100),
+            requires_approval: true,
+        }
+    );
+    test_val!(
+        EntryPublic,
+        EntryPublic::TlsCert {
+            sni: "test-val".to_string(),
+            cert_digest: vec![0x42; 32],
+            digest_alg: TlsCertDigestAlg::Sha256,
+            cert_der: vec![0x42; 32],
+            not_after: 42,
+            alg: TlsCertAlg::PkcsEcdsaP256Sha256,
+        }
+    );
     test_val!(Cert, vec![0x42; 32].into());
+    test_val!(Vec<Cert>, vec![Cert::test_val(), Cert::test_val()]);
     test_val!(CertPrivKey, vec![0x42; 32].into());
     test_val!(CertSni, "test-val".to_string().into());
     test_val!(CertDigest, vec![0x42; 32].into());
     test_val!(sign_ed25519::SignEd25519PubKey, vec![0x42; 32].into());
     test_val!(sign_ed25519::SignEd25519Signature, vec![0x42; 64].into());
+    test_val!(Bytes, vec![0x42; 32].into());
+    test_val!(Vec<Bytes>, vec![Bytes::test_val(); 3]);
+    test_val!(
+        Vec<sign_ed25519::SignEd25519Signature>,
+        vec![vec![0x42; 64].into(); 3]
+    );
+    test_val!(
+        sign_secp256k1::SignSecp256k1PubKey,
+        vec![0x42; sign_secp256k1::PUB_KEY_BYTES].into()
+    );
+    test_val!(
+        sign_secp256k1::SignSecp256k1Signature,
+        vec![0x42; sign_secp256k1::SIGNATURE_BYTES].into()
+    );
+    test_val!(
+        sign_bls12381::SignBls12381PubKey,
+        vec![0x42; sign_bls12381::PUB_KEY_BYTES].into()
+    );
+    test_val!(
+        sign_bls12381::SignBls12381Signature,
+        vec![0x42; sign_bls12381::SIGNATURE_BYTES].into()
+    );
     test_val!(x25519::X25519PubKey, [0x42; 32].into());
     test_val!(x25519::X25519PrivKey, [0x42; 32].into());
+    test_val!(kx::KxSessionKey, vec![0x42; kx::SESSION_KEY_BYTES].into());
+    test_val!(
+        Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>,
+        vec![
+            (1.into(), vec![0x42; 32].into()),
+            (3.into(), vec![0x43; 32].into())
+        ]
+    );
+    test_val!(
+        Vec<(KeystoreIndex, x25519::X25519PubKey)>,
+        vec![(2.into(), [0x42; 32].into()), (4.into(), [0x43; 32].into())]
+    );
     test_val!(crypto_box::CryptoBoxData, vec![42_u8; 20].into());
     test_val!(
         Option<crypto_box::CryptoBoxData>,
         Some(vec![42_u8; 20].into())
     );
+    test_val!(crypto_box::CryptoBoxSealedData, vec![42_u8; 60].into());
     test_val!(
         crypto_box::CryptoBoxEncryptedData,
         crypto_box::CryptoBoxEncryptedData {
@@ -1132,4 +4686,47 @@ pub(crate) mod tests {
     }
 
     wire_type_meta_macro!(lair_wire_enum_test);
+
+    #[test]
+    fn payloads_are_slices_of_the_frame() {
+        let in_frame = |frame: &Bytes, payload: &[u8]| {
+            frame.as_ptr_range().contains(&payload.as_ptr())
+        };
+
+        let frame = Bytes::from(
+            LairWire::ToLairSignEd25519SignByIndex {
+                msg_id: 0,
+                keystore_index: TestVal::test_val(),
+                message: TestVal::test_val(),
+            }
+            .encode()
+            .unwrap(),
+        );
+        match LairWire::decode_frame(&frame).unwrap() {
+            LairWire::ToLairSignEd25519SignByIndex { message, .. } => {
+                assert!(in_frame(&frame, &message));
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+
+        let frame = Bytes::from(
+            LairWire::ToCliTlsCertNewSignedByCaResponse {
+                msg_id: 0,
+                keystore_index: TestVal::test_val(),
+                cert_sni: TestVal::test_val(),
+                cert_digest: TestVal::test_val(),
+                cert_chain: TestVal::test_val(),
+            }
+            .encode()
+            .unwrap(),
+        );
+        match LairWire::decode_frame(&frame).unwrap() {
+            LairWire::ToCliTlsCertNewSignedByCaResponse {
+                cert_chain, ..
+            } => {
+                assert!(cert_chain.iter().all(|c| in_frame(&frame, c)));
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+    }
 }
@@ -4,6 +4,8 @@
 use crate::*;
 use crypto_box as lib_crypto_box;
 use derive_more::*;
+use internal::sign_ed25519;
+use internal::util::{self, MemLock};
 
 /// Length of an x25519 private key in bytes.
 pub const PRIV_KEY_BYTES: usize = lib_crypto_box::KEY_SIZE;
@@ -11,17 +13,56 @@ pub const PRIV_KEY_BYTES: usize = lib_crypto_box::KEY_SIZE;
 /// Length of an x25519 public key in bytes.
 pub const PUB_KEY_BYTES: usize = lib_crypto_box::KEY_SIZE;
 
-/// Newtype for the private key.
-// Almost all these derives seem dangerous to me...
-// @todo Do we really need to be cloning and debugging secrets?
-#[derive(Debug, Clone, Deref, From, Into)]
-pub struct X25519PrivKey(lib_crypto_box::SecretKey);
+/// Newtype for the private key, boxed to be mlocked in place.
+/// The upstream secret zeroizes itself on drop.
+pub struct X25519PrivKey(
+    Box<lib_crypto_box::SecretKey>,
+    // held for its Drop only, after the key is zeroized
+    #[allow(dead_code)]
+    MemLock,
+);
+
+// @todo Do we really need to be cloning secrets?
+impl Clone for X25519PrivKey {
+    fn clone(&self) -> Self {
+        (*self.0).clone().into()
+    }
+}
+
+impl std::ops::Deref for X25519PrivKey {
+    type Target = lib_crypto_box::SecretKey;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<lib_crypto_box::SecretKey> for X25519PrivKey {
+    fn from(key: lib_crypto_box::SecretKey) -> Self {
+        let key = Box::new(key);
+        let lock = MemLock::new(&*key);
+        Self(key, lock)
+    }
+}
+
+impl From<X25519PrivKey> for lib_crypto_box::SecretKey {
+    fn from(key: X25519PrivKey) -> Self {
+        (*key.0).clone()
+    }
+}
+
+impl std::fmt::Debug for X25519PrivKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("X25519PrivKey(<secret>)")
+    }
+}
 
 /// @todo Do we really need to be comparing secrets?
 impl PartialEq for X25519PrivKey {
     fn eq(&self, other: &Self) -> bool {
-        use subtle::ConstantTimeEq;
-        self.to_bytes().ct_eq(&other.to_bytes()).into()
+        let this = zeroize::Zeroizing::new(self.to_bytes());
+        let other = zeroize::Zeroizing::new(other.to_bytes());
+        util::ct_eq(&*this, &*other)
     }
 }
 
@@ -29,7 +70,7 @@ impl Eq for X25519PrivKey {}
 
 impl From<[u8; PRIV_KEY_BYTES]> for X25519PrivKey {
     fn from(bytes: [u8; PRIV_KEY_BYTES]) -> Self {
-        Self(bytes.into())
+        lib_crypto_box::SecretKey::from(bytes).into()
     }
 }
 
@@ -62,8 +103,7 @@ impl core::convert::TryFrom<&[u8]> for X25519PrivKey {
 /// @todo Do we really need to be ordering secrets?
 impl PartialOrd for X25519PrivKey {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        // @todo i assume there is a timing attack here?
-        self.to_bytes().partial_cmp(&other.to_bytes())
+        Some(self.cmp(other))
     }
 }
 
@@ -71,14 +111,16 @@ impl PartialOrd for X25519PrivKey {
 impl Ord for X25519PrivKey {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         // @todo i assume there is a timing attack here?
-        self.to_bytes().cmp(&other.to_bytes())
+        let this = zeroize::Zeroizing::new(self.to_bytes());
+        let other = zeroize::Zeroizing::new(other.to_bytes());
+        this.cmp(&other)
     }
 }
 
 /// @todo Is hashing secrets a problem?
 impl core::hash::Hash for X25519PrivKey {
     fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
-        self.to_bytes().hash(state)
+        zeroize::Zeroizing::new(self.to_bytes()).hash(state)
     }
 }
 
@@ -113,7 +155,7 @@ impl AsRef<lib_crypto_box::PublicKey> for X25519PubKey {
 
 impl AsRef<[u8; PUB_KEY_BYTES]> for X25519PubKey {
     fn as_ref(&self) -> &[u8; PUB_KEY_BYTES] {
-        &self.0.as_bytes()
+        self.0.as_bytes()
     }
 }
 
@@ -125,7 +167,7 @@ impl AsRef<[u8]> for X25519PubKey {
 
 impl PartialEq for X25519PubKey {
     fn eq(&self, other: &Self) -> bool {
-        self.to_bytes() == other.to_bytes()
+        util::ct_eq(&self.to_bytes(), &other.to_bytes())
     }
 }
 
@@ -133,7 +175,7 @@ impl Eq for X25519PubKey {}
 
 impl PartialOrd for X25519PubKey {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.to_bytes().partial_cmp(&other.to_bytes())
+        Some(self.cmp(other))
     }
 }
 
@@ -168,3 +210,213 @@ pub async fn x25519_keypair_new_from_entropy() -> LairResult<entry::EntryX25519>
     })
     .await
 }
+
+/// The raw x25519 shared secret of a private key and a remote pub key,
+/// as libsodium's `crypto_scalarmult`. Like libsodium, fails for low
+/// order remote pub keys (the shared secret would be all zeros).
+pub fn x25519_shared_secret(
+    priv_key: &X25519PrivKey,
+    remote_pub: &X25519PubKey,
+) -> LairResult<zeroize::Zeroizing<[u8; 32]>> {
+    let mut scalar = zeroize::Zeroizing::new(priv_key.to_bytes());
+    scalar[0] &= 248;
+    scalar[31] &= 127;
+    scalar[31] |= 64;
+    let remote_pub: &[u8; PUB_KEY_BYTES] = remote_pub.as_ref();
+    let shared = zeroize::Zeroizing::new(
+        (curve25519_dalek::montgomery::MontgomeryPoint(*remote_pub)
+            * curve25519_dalek::scalar::Scalar::from_bits(*scalar))
+        .to_bytes(),
+    );
+    if *shared == [0; 32] {
+        return Err("invalid x25519 pub key".into());
+    }
+    Ok(shared)
+}
+
+/// Convert an ed25519 signature pub key to the x25519 pub key of the same
+/// identity, as libsodium's `crypto_sign_ed25519_pk_to_curve25519`.
+/// Fails for keys that are not valid points in the prime order subgroup.
+pub fn x25519_pub_key_from_sign_ed25519(
+    pub_key: &sign_ed25519::SignEd25519PubKey,
+) -> LairResult<X25519PubKey> {
+    let invalid = || LairError::from("invalid ed25519 pub key");
+    if pub_key.len() != 32 {
+        return Err(invalid());
+    }
+    let mut compressed = [0; 32];
+    compressed.copy_from_slice(pub_key);
+    let point = curve25519_dalek::edwards::CompressedEdwardsY(compressed)
+        .decompress()
+        .ok_or_else(invalid)?;
+    if point.is_small_order() || !point.is_torsion_free() {
+        return Err(invalid());
+    }
+    Ok(point.to_montgomery().to_bytes().into())
+}
+
+/// Compute the x25519 keypair of an existing 32 byte private key,
+/// e.g. one exported from libsodium. The private key is clamped per
+/// RFC 7748 before it is stored, which does not change the pub key.
+pub async fn x25519_keypair_from_priv_key(
+    priv_key: Vec<u8>,
+) -> LairResult<entry::EntryX25519> {
+    let priv_key = zeroize::Zeroizing::new(priv_key);
+    if priv_key.len() != PRIV_KEY_BYTES {
+        return Err(LairError::X25519PrivKeyImportLength(priv_key.len()));
+    }
+    rayon_exec_sized(0, move || {
+        let mut scalar = zeroize::Zeroizing::new([0; PRIV_KEY_BYTES]);
+        scalar.copy_from_slice(&priv_key);
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        let priv_key = lib_crypto_box::SecretKey::from(*scalar);
+        Ok(entry::EntryX25519 {
+            pub_key: priv_key.public_key().into(),
+            priv_key: priv_key.into(),
+        })
+    })
+    .await
+}
+
+/// Convert an ed25519 signature keypair to the x25519 keypair of the same
+/// identity, as libsodium's `crypto_sign_ed25519_sk_to_curve25519`.
+/// (The x25519 private key is the clamped ed25519 secret scalar.)
+pub async fn x25519_keypair_from_sign_ed25519(
+    priv_key: sign_ed25519::SignEd25519PrivKey,
+) -> LairResult<entry::EntryX25519> {
+    rayon_exec_sized(0, move || {
+        if priv_key.len() != 32 {
+            return Err(LairError::SignEd25519SeedLength(priv_key.len()));
+        }
+        let hash = ring::digest::digest(&ring::digest::SHA512, &priv_key);
+        let mut scalar = zeroize::Zeroizing::new([0; PRIV_KEY_BYTES]);
+        scalar.copy_from_slice(&hash.as_ref()[..PRIV_KEY_BYTES]);
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        let priv_key = lib_crypto_box::SecretKey::from(*scalar);
+        Ok(entry::EntryX25519 {
+            pub_key: priv_key.public_key().into(),
+            priv_key: priv_key.into(),
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// libsodium's ed25519_convert test vector. This must never change,
+    /// peers using libsodium directly derive the same keys.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_matches_libsodium_ed25519_conversion() {
+        let sign = sign_ed25519::sign_ed25519_keypair_from_seed(unhex(
+            "421151a459faeade3d247115f94aedae42318124095afabe4d1451a559faedee",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(
+            unhex(
+                "b5076a8474a832daee4dd5b4040983b6623b5f344aca57d4d6ee4baf3f259e6e"
+            ),
+            *sign.pub_key.0
+        );
+
+        let expect_pub_key = unhex(
+            "f1814f0e8ff1043d8a44d25babff3cedcae6c22c3edaa48f857ae70de2baae50",
+        );
+        let pub_key = x25519_pub_key_from_sign_ed25519(&sign.pub_key).unwrap();
+        assert_eq!(expect_pub_key, pub_key.to_bytes().to_vec());
+
+        let keypair = x25519_keypair_from_sign_ed25519(sign.priv_key)
+            .await
+            .unwrap();
+        assert_eq!(
+            unhex(
+                "8052030376d47112be7f73ed7a019293dd12ad910b654455798b4667d73de166"
+            ),
+            keypair.priv_key.to_bytes().to_vec()
+        );
+        assert_eq!(pub_key, keypair.pub_key);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_converts_random_keypairs() {
+        for _ in 0..8 {
+            let sign = sign_ed25519::sign_ed25519_keypair_new_from_entropy()
+                .await
+                .unwrap();
+            let keypair = x25519_keypair_from_sign_ed25519(sign.priv_key)
+                .await
+                .unwrap();
+            assert_eq!(
+                keypair.pub_key,
+                x25519_pub_key_from_sign_ed25519(&sign.pub_key).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn it_rejects_bad_ed25519_pub_keys() {
+        let mut identity = vec![0; 32];
+        identity[0] = 1;
+        // order 8 torsion point
+        let torsion = unhex(
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+        );
+        // y = 2 is not on the curve
+        let mut off_curve = vec![0; 32];
+        off_curve[0] = 2;
+        for bad in [identity, torsion, off_curve, vec![0x42; 31]] {
+            assert!(x25519_pub_key_from_sign_ed25519(&bad.into()).is_err());
+        }
+    }
+
+    /// RFC 7748 section 6.1 test vector (Alice's keypair).
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_imports_priv_keys() {
+        let keypair = x25519_keypair_from_priv_key(unhex(
+            "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(
+            unhex(
+                "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"
+            ),
+            keypair.pub_key.to_bytes().to_vec()
+        );
+        // stored clamped
+        assert_eq!(
+            unhex(
+                "70076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c6a"
+            ),
+            keypair.priv_key.to_bytes().to_vec()
+        );
+
+        // importing a clamped key again is a no-op
+        let again =
+            x25519_keypair_from_priv_key(keypair.priv_key.to_bytes().to_vec())
+                .await
+                .unwrap();
+        assert_eq!(keypair.priv_key, again.priv_key);
+        assert_eq!(keypair.pub_key, again.pub_key);
+
+        for len in [0, 31, 33, 64] {
+            assert!(matches!(
+                x25519_keypair_from_priv_key(vec![0x42; len]).await,
+                Err(LairError::X25519PrivKeyImportLength(l)) if l == len
+            ));
+        }
+    }
+}
@@ -7,6 +7,9 @@ use crate::*;
 mod spawn_client_ipc;
 
 /// Spawn a client Ipc connection.
+/// If the connection breaks, the client reconnects with backoff in the
+/// background, failing requests meanwhile with `LairError::Disconnected`,
+/// and emits `LairClientEvent::Reconnected` once connected again.
 pub async fn spawn_client_ipc(
     config: Arc<Config>,
 ) -> LairResult<(
@@ -26,11 +29,28 @@ pub type IncomingIpcConnectionReceiver =
 
 mod spawn_bind_server_ipc;
 
+pub use crate::internal::ipc::with_timeout;
+pub use crate::internal::ipc::IpcListener;
+
 /// Bind a server Ipc connection.
 pub async fn spawn_bind_server_ipc<S>(
     config: Arc<Config>,
     api_sender: S,
 ) -> LairResult<IncomingIpcConnectionReceiver>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    spawn_bind_server_ipc_with_listener(config, api_sender, None).await
+}
+
+/// Serve a server Ipc connection on an already bound `listener`
+/// (e.g. from systemd socket activation), or bind the configured
+/// socket path if `None`.
+pub async fn spawn_bind_server_ipc_with_listener<S>(
+    config: Arc<Config>,
+    api_sender: S,
+    listener: Option<IpcListener>,
+) -> LairResult<IncomingIpcConnectionReceiver>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
@@ -40,6 +60,7 @@ where
         config,
         api_sender,
         incoming_send,
+        listener,
     )
     .await?;
 
@@ -50,7 +71,14 @@ where
 mod tests {
     use super::*;
     use crate::internal::crypto_box;
+    use crate::internal::kx;
+    use crate::internal::pkcs12;
+    use crate::internal::secret_key;
+    use crate::internal::secretstream;
+    use crate::internal::seed;
+    use crate::internal::sign_bls12381;
     use crate::internal::sign_ed25519;
+    use crate::internal::sign_secp256k1;
     use crate::internal::wire::tests::TestVal;
     use crate::internal::x25519;
     use futures::{future::FutureExt, stream::StreamExt};
@@ -72,7 +100,11 @@ mod tests {
         init_tracing();
 
         let tmpdir = tempfile::tempdir().unwrap();
-        let config = Config::builder().set_root_path(tmpdir.path()).build();
+        // the client names itself as it connects
+        let config = Config::builder()
+            .set_root_path(tmpdir.path())
+            .set_client_name("test-client")?
+            .build();
 
         struct TestServer;
         impl ghost_actor::GhostControlHandler for TestServer {}
@@ -83,6 +115,41 @@ mod tests {
             ) -> LairClientApiHandlerResult<LairServerInfo> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_lair_ping(
+                &mut self,
+                nonce: u64,
+            ) -> LairClientApiHandlerResult<u64> {
+                Ok(async move { Ok(nonce) }.boxed().into())
+            }
+            fn handle_lair_lock(&mut self) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_unlock(
+                &mut self,
+                _passphrase: SecretBytes,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_subscribe_store_events(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_get_lock_state(
+                &mut self,
+            ) -> LairClientApiHandlerResult<LairLockState> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_register_passphrase_provider(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_register_signing_approver(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
             fn handle_lair_get_last_entry_index(
                 &mut self,
             ) -> LairClientApiHandlerResult<KeystoreIndex> {
@@ -94,6 +161,86 @@ mod tests {
             ) -> LairClientApiHandlerResult<LairEntryType> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_lair_list_entries(
+                &mut self,
+            ) -> LairClientApiHandlerResult<Vec<EntryListItem>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_get_entry_by_tag(
+                &mut self,
+                _tag: String,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_lair_get_entry_meta(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<entry::EntryMeta> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_find_entry(
+                &mut self,
+                _id: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<
+                Option<(KeystoreIndex, LairEntryType)>,
+            > {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_update_entry_tag(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _tag: String,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_set_entry_rate_limit(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _rate_limit: Option<u32>,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_export_entry_public(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<EntryPublic> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_delete_entry(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_compact_store(
+                &mut self,
+            ) -> LairClientApiHandlerResult<LairCompactInfo> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_lair_shutdown(
+                &mut self,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+            fn handle_lair_change_unlock_passphrase(
+                &mut self,
+                old_passphrase: String,
+                _new_passphrase: String,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move {
+                    if old_passphrase != String::test_val() {
+                        return Err(LairError::WrongPassphrase);
+                    }
+                    Ok(())
+                }
+                .boxed()
+                .into())
+            }
             fn handle_tls_cert_new_self_signed_from_entropy(
                 &mut self,
                 _options: TlsCertOptions,
@@ -109,6 +256,123 @@ mod tests {
                 .boxed()
                 .into())
             }
+            fn handle_tls_cert_new_self_signed_tagged(
+                &mut self,
+                options: TlsCertOptions,
+                _tag: String,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)>
+            {
+                assert_eq!(Some("lair.example.com".to_string()), options.sni);
+                assert_eq!(
+                    vec!["*.example.com".to_string(), "127.0.0.1".to_string()],
+                    options.alt_names,
+                );
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_tls_cert_renew(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                options: TlsCertOptions,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)>
+            {
+                assert_eq!(Some("lair.example.com".to_string()), options.sni);
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_tls_ca_new_self_signed(
+                &mut self,
+                options: TlsCertOptions,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)>
+            {
+                assert_eq!(TlsCertAlg::PkcsEcdsaP256Sha256, options.alg);
+                assert_eq!(Some("ca.example.com".to_string()), options.sni);
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_tls_cert_new_signed_by_ca(
+                &mut self,
+                ca_index: KeystoreIndex,
+                options: TlsCertOptions,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                CertSni,
+                CertDigest,
+                Vec<Cert>,
+            )> {
+                assert_eq!(KeystoreIndex(3), ca_index);
+                assert_eq!(Some("lair.example.com".to_string()), options.sni);
+                assert_eq!(2, options.alt_names.len());
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_tls_cert_import(
+                &mut self,
+                cert_der: Cert,
+                priv_key_der: CertPrivKey,
+                options: TlsCertOptions,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)>
+            {
+                assert_eq!(Cert::test_val(), cert_der);
+                assert_eq!(CertPrivKey::test_val(), priv_key_der);
+                assert_eq!(Some("lair.example.com".to_string()), options.sni);
+                assert_eq!(TlsCertDigestAlg::Sha256, options.digest_alg);
+                assert_eq!(Some(false), options.exportable);
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_tls_cert_find_by_digest_prefix(
+                &mut self,
+                prefix: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)>
+            {
+                assert_eq!(vec![0x42; 4], *prefix);
+                Ok(async move {
+                    Ok((
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                        TestVal::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
             fn handle_tls_cert_get(
                 &mut self,
                 _keystore_index: KeystoreIndex,
@@ -154,6 +418,24 @@ mod tests {
             ) -> LairClientApiHandlerResult<CertPrivKey> {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
+            fn handle_tls_cert_export_pkcs12(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                password: Option<String>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                assert_eq!(Some("test-val".to_string()), password);
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_tls_cert_sign_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                scheme: TlsCertSignatureScheme,
+                message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                assert_eq!(TlsCertSignatureScheme::test_val(), scheme);
+                assert_eq!(Arc::<Vec<u8>>::test_val(), message);
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
             fn handle_sign_ed25519_new_from_entropy(
                 &mut self,
             ) -> LairClientApiHandlerResult<(
@@ -165,6 +447,44 @@ mod tests {
                     TestVal::test_val(),
                 )) }.boxed().into())
             }
+            fn handle_sign_ed25519_new_from_entropy_tagged(
+                &mut self,
+                _tag: String,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                sign_ed25519::SignEd25519PubKey,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_new_from_entropy_requiring_approval(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                sign_ed25519::SignEd25519PubKey,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_sign_ed25519_new_from_seed(
+                &mut self,
+                _seed: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                sign_ed25519::SignEd25519PubKey,
+            )> {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
             fn handle_sign_ed25519_get(
                 &mut self,
                 _keystore_index: KeystoreIndex,
@@ -175,7 +495,7 @@ mod tests {
             fn handle_sign_ed25519_sign_by_index(
                 &mut self,
                 _keystore_index: KeystoreIndex,
-                _message: Arc<Vec<u8>>,
+                _message: bytes::Bytes,
             ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature>
             {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
@@ -183,88 +503,438 @@ mod tests {
             fn handle_sign_ed25519_sign_by_pub_key(
                 &mut self,
                 _pub_key: sign_ed25519::SignEd25519PubKey,
-                _message: Arc<Vec<u8>>,
+                _message: bytes::Bytes,
             ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature>
             {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            fn handle_x25519_new_from_entropy(
+            fn handle_sign_ed25519_sign_by_index_with_context(
                 &mut self,
-            ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)>
+                _keystore_index: KeystoreIndex,
+                _context: Arc<Vec<u8>>,
+                _message: bytes::Bytes,
+            ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature>
             {
-                Ok(async move { Ok((
-                    TestVal::test_val(),
-                    TestVal::test_val(),
-                )) }.boxed().into())
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            fn handle_x25519_get(
+            fn handle_sign_ed25519_sign_by_pub_key_with_context(
+                &mut self,
+                _pub_key: sign_ed25519::SignEd25519PubKey,
+                _context: Arc<Vec<u8>>,
+                _message: bytes::Bytes,
+            ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed25519_sign_batch_by_index(
                 &mut self,
                 _keystore_index: KeystoreIndex,
-            ) -> LairClientApiHandlerResult<x25519::X25519PubKey> {
+                _messages: Vec<bytes::Bytes>,
+            ) -> LairClientApiHandlerResult<
+                Vec<sign_ed25519::SignEd25519Signature>,
+            > {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            fn handle_crypto_box_by_index(
+            fn handle_sign_ed25519_sign_batch_by_pub_key(
+                &mut self,
+                _pub_key: sign_ed25519::SignEd25519PubKey,
+                _messages: Vec<bytes::Bytes>,
+            ) -> LairClientApiHandlerResult<
+                Vec<sign_ed25519::SignEd25519Signature>,
+            > {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed25519_sign_prehashed(
                 &mut self,
                 _keystore_index: KeystoreIndex,
-                _recipient: x25519::X25519PubKey,
-                _data: Arc<crypto_box::CryptoBoxData>,
-            ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData>
+                _digest: [u8; 32],
+            ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature>
             {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            fn handle_crypto_box_by_pub_key(
+            fn handle_sign_ed25519_verify(
                 &mut self,
-                _pub_key: x25519::X25519PubKey,
-                _recipient: x25519::X25519PubKey,
-                _data: Arc<crypto_box::CryptoBoxData>,
-            ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData>
+                _pub_key: sign_ed25519::SignEd25519PubKey,
+                _message: bytes::Bytes,
+                _signature: sign_ed25519::SignEd25519Signature,
+            ) -> LairClientApiHandlerResult<bool> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_sign_ed25519_sign_by_tag(
+                &mut self,
+                _tag: String,
+                _message: bytes::Bytes,
+            ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature>
             {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            fn handle_crypto_box_open_by_index(
+            fn handle_sign_ed25519_list_pub_keys(
+                &mut self,
+            ) -> LairClientApiHandlerResult<
+                Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>,
+            > {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_secp256k1_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                sign_secp256k1::SignSecp256k1PubKey,
+            )> {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_secp256k1_get(
                 &mut self,
                 _keystore_index: KeystoreIndex,
-                _recipient: x25519::X25519PubKey,
-                _data: Arc<crypto_box::CryptoBoxEncryptedData>,
-            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1PubKey>
             {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            fn handle_crypto_box_open_by_pub_key(
+            fn handle_secp256k1_sign_by_index(
                 &mut self,
-                _pub_key: x25519::X25519PubKey,
-                _recipient: x25519::X25519PubKey,
-                _data: Arc<crypto_box::CryptoBoxEncryptedData>,
-            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
-            {
+                _keystore_index: KeystoreIndex,
+                _digest: [u8; 32],
+            ) -> LairClientApiHandlerResult<
+                sign_secp256k1::SignSecp256k1Signature,
+            > {
                 Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-        }
-
-        let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
-        let api_sender = builder
-            .channel_factory()
-            .create_channel::<LairClientApi>()
-            .await?;
-        err_spawn("test-api-actor", async move {
-            builder.spawn(TestServer).await.map_err(LairError::other)
-        });
-
-        let mut incoming_recv =
-            spawn_bind_server_ipc(config.clone(), api_sender).await?;
-
-        err_spawn("test-incoming-loop", async move {
-            let mut keep_em = Vec::new();
-            while let Some(evt_send) = incoming_recv.next().await {
-                let passphrase = evt_send.request_unlock_passphrase().await?;
-                assert_eq!("test-val", passphrase);
-                keep_em.push(evt_send);
+            fn handle_secp256k1_sign_by_pub_key(
+                &mut self,
+                _pub_key: sign_secp256k1::SignSecp256k1PubKey,
+                _digest: [u8; 32],
+            ) -> LairClientApiHandlerResult<
+                sign_secp256k1::SignSecp256k1Signature,
+            > {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
             }
-            Ok(())
+            fn handle_bls_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                sign_bls12381::SignBls12381PubKey,
+            )> {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_bls_sign_by_pub_key(
+                &mut self,
+                _pub_key: sign_bls12381::SignBls12381PubKey,
+                _message: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<sign_bls12381::SignBls12381Signature>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_seed_new_from_entropy(
+                &mut self,
+                _exportable: bool,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)>
+            {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_seed_export_mnemonic(
+                &mut self,
+                _seed_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<String> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_seed_import_mnemonic(
+                &mut self,
+                _mnemonic: String,
+                _exportable: bool,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)>
+            {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_sign_ed25519_derive_from_seed(
+                &mut self,
+                _seed_index: KeystoreIndex,
+                _derivation_path: Vec<u32>,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                sign_ed25519::SignEd25519PubKey,
+            )> {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_sign_ed25519_to_x25519(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)>
+            {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_x25519_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)>
+            {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_x25519_new_from_priv_key(
+                &mut self,
+                _priv_key: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)>
+            {
+                Ok(async move { Ok((
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )) }.boxed().into())
+            }
+            fn handle_x25519_get(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+            ) -> LairClientApiHandlerResult<x25519::X25519PubKey> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_x25519_list_pub_keys(
+                &mut self,
+            ) -> LairClientApiHandlerResult<
+                Vec<(KeystoreIndex, x25519::X25519PubKey)>,
+            > {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_kx_session_keys_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _remote_pub: x25519::X25519PubKey,
+                _is_server: bool,
+            ) -> LairClientApiHandlerResult<(kx::KxSessionKey, kx::KxSessionKey)>
+            {
+                Ok(async move {
+                    Ok((TestVal::test_val(), TestVal::test_val()))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_crypto_box_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _recipient: x25519::X25519PubKey,
+                _data: Arc<crypto_box::CryptoBoxData>,
+            ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_by_pub_key(
+                &mut self,
+                _pub_key: x25519::X25519PubKey,
+                _recipient: x25519::X25519PubKey,
+                _data: Arc<crypto_box::CryptoBoxData>,
+            ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_open_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _recipient: x25519::X25519PubKey,
+                _data: Arc<crypto_box::CryptoBoxEncryptedData>,
+            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_open_by_pub_key(
+                &mut self,
+                _pub_key: x25519::X25519PubKey,
+                _recipient: x25519::X25519PubKey,
+                _data: Arc<crypto_box::CryptoBoxEncryptedData>,
+            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_by_index_with_aad(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _recipient: x25519::X25519PubKey,
+                _aad: Arc<Vec<u8>>,
+                _data: Arc<crypto_box::CryptoBoxData>,
+            ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_by_pub_key_with_aad(
+                &mut self,
+                _pub_key: x25519::X25519PubKey,
+                _recipient: x25519::X25519PubKey,
+                _aad: Arc<Vec<u8>>,
+                _data: Arc<crypto_box::CryptoBoxData>,
+            ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_open_by_index_with_aad(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _sender: x25519::X25519PubKey,
+                _aad: Arc<Vec<u8>>,
+                _encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_open_by_pub_key_with_aad(
+                &mut self,
+                _pub_key: x25519::X25519PubKey,
+                _sender: x25519::X25519PubKey,
+                _aad: Arc<Vec<u8>>,
+                _encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_seal_open_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_crypto_box_seal_open_by_pub_key(
+                &mut self,
+                _pub_key: x25519::X25519PubKey,
+                _sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+            ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_secret_new_from_entropy(
+                &mut self,
+            ) -> LairClientApiHandlerResult<(
+                KeystoreIndex,
+                secret_key::SecretKeyTag,
+            )> {
+                Ok(async move {
+                    Ok((
+                        KeystoreIndex::test_val(),
+                        secret_key::SecretKeyTag::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_secret_encrypt_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _aad: Arc<Vec<u8>>,
+                _data: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<(
+                secret_key::SecretNonce,
+                Arc<Vec<u8>>,
+            )> {
+                Ok(async move {
+                    Ok((
+                        secret_key::SecretNonce::test_val(),
+                        Arc::<Vec<u8>>::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_secret_decrypt_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _aad: Arc<Vec<u8>>,
+                _nonce: secret_key::SecretNonce,
+                _ciphertext: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Option<Arc<Vec<u8>>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_derive_subkey_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _subkey_len: usize,
+                _context: [u8; secret_key::SUBKEY_CONTEXT_BYTES],
+                _subkey_id: u64,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_secretstream_init_push_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _remote_pub: x25519::X25519PubKey,
+            ) -> LairClientApiHandlerResult<(
+                secretstream::SecretstreamSessionId,
+                secretstream::SecretstreamHeader,
+            )> {
+                Ok(async move {
+                    Ok((
+                        secretstream::SecretstreamSessionId::test_val(),
+                        secretstream::SecretstreamHeader::test_val(),
+                    ))
+                }
+                .boxed()
+                .into())
+            }
+            fn handle_secretstream_push(
+                &mut self,
+                _session_id: secretstream::SecretstreamSessionId,
+                _chunk: Arc<Vec<u8>>,
+                _is_final: bool,
+            ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_secretstream_init_pull_by_index(
+                &mut self,
+                _keystore_index: KeystoreIndex,
+                _remote_pub: x25519::X25519PubKey,
+                _header: secretstream::SecretstreamHeader,
+            ) -> LairClientApiHandlerResult<secretstream::SecretstreamSessionId>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_secretstream_pull(
+                &mut self,
+                _session_id: secretstream::SecretstreamSessionId,
+                _ciphertext: Arc<Vec<u8>>,
+            ) -> LairClientApiHandlerResult<Option<(Arc<Vec<u8>>, bool)>>
+            {
+                Ok(async move { Ok(TestVal::test_val()) }.boxed().into())
+            }
+            fn handle_secretstream_close(
+                &mut self,
+                _session_id: secretstream::SecretstreamSessionId,
+            ) -> LairClientApiHandlerResult<()> {
+                Ok(async move { Ok(()) }.boxed().into())
+            }
+        }
+
+        let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+        let api_sender = builder
+            .channel_factory()
+            .create_channel::<LairClientApi>()
+            .await?;
+        err_spawn("test-api-actor", async move {
+            builder.spawn(TestServer).await.map_err(LairError::other)
         });
 
+        let mut incoming_recv =
+            spawn_bind_server_ipc(config.clone(), api_sender).await?;
+
         let (cli_send, mut cli_recv) = spawn_client_ipc(config).await?;
+        let evt_send = incoming_recv.next().await.unwrap();
 
+        let (locked_send, mut locked_recv) =
+            tokio::sync::mpsc::unbounded_channel();
         err_spawn("test-evt-loop", async move {
             while let Some(msg) = cli_recv.next().await {
                 match msg {
@@ -278,23 +948,245 @@ mod tests {
                                 .into(),
                         ));
                     }
+                    LairClientEvent::Locked { respond, .. } => {
+                        let _ = locked_send.send(());
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    // the test entry meta requires approval
+                    LairClientEvent::RequestSigningApproval {
+                        respond, ..
+                    } => {
+                        respond.respond(Ok(async move { Ok(true) }
+                            .boxed()
+                            .into()));
+                    }
+                    LairClientEvent::Reconnected { respond, .. }
+                    | LairClientEvent::EntryCreated { respond, .. }
+                    | LairClientEvent::EntryDeleted { respond, .. }
+                    | LairClientEvent::Unlocked { respond, .. } => {
+                        respond
+                            .respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
                 }
             }
             Ok(())
         });
 
+        // every client hears of the store locking
+        evt_send.locked().await?;
+        locked_recv.recv().await.unwrap();
+
+        // only passphrase providers are asked for the passphrase
+        assert!(evt_send
+            .request_unlock_passphrase(false, 1, 1)
+            .await
+            .is_err());
+        cli_send.lair_register_passphrase_provider().await?;
         assert_eq!(
-            LairServerInfo::test_val(),
-            cli_send.lair_get_server_info().await?
+            b"test-val",
+            &*evt_send.request_unlock_passphrase(false, 1, 1).await?
+        );
+        assert_eq!(
+            LairLockState::test_val(),
+            cli_send.lair_get_lock_state().await?
+        );
+        cli_send.lair_register_signing_approver().await?;
+
+        // the ipc server counts its connections and requests in flight
+        let info = cli_send.lair_get_server_info().await?;
+        assert_eq!(
+            LairServerInfo {
+                connection_count: 1,
+                requests_in_flight: 1,
+                ..LairServerInfo::test_val()
+            },
+            info
         );
         assert_eq!(
             KeystoreIndex::test_val(),
             cli_send.lair_get_last_entry_index().await?
         );
         assert_eq!(
-            LairEntryType::test_val(),
-            cli_send.lair_get_entry_type(0.into()).await?
+            LairEntryType::test_val(),
+            cli_send.lair_get_entry_type(0.into()).await?
+        );
+        assert_eq!(
+            Vec::<EntryListItem>::test_val(),
+            cli_send.lair_list_entries().await?
+        );
+        assert_eq!(
+            (KeystoreIndex::test_val(), LairEntryType::test_val()),
+            cli_send.lair_get_entry_by_tag(TestVal::test_val()).await?
+        );
+        assert_eq!(
+            entry::EntryMeta::test_val(),
+            cli_send
+                .lair_get_entry_meta(KeystoreIndex::test_val())
+                .await?
+        );
+        assert_eq!(
+            Option::<(KeystoreIndex, LairEntryType)>::test_val(),
+            cli_send.lair_find_entry(TestVal::test_val()).await?
+        );
+        cli_send
+            .lair_update_entry_tag(
+                KeystoreIndex::test_val(),
+                TestVal::test_val(),
+            )
+            .await?;
+        cli_send
+            .lair_set_entry_rate_limit(
+                KeystoreIndex::test_val(),
+                TestVal::test_val(),
+            )
+            .await?;
+        assert_eq!(
+            EntryPublic::test_val(),
+            cli_send
+                .lair_export_entry_public(KeystoreIndex::test_val())
+                .await?
+        );
+        cli_send
+            .lair_delete_entry(KeystoreIndex::test_val())
+            .await?;
+        assert_eq!(
+            LairCompactInfo::test_val(),
+            cli_send.lair_compact_store().await?
+        );
+        cli_send.lair_shutdown().await?;
+        cli_send
+            .lair_change_unlock_passphrase(
+                TestVal::test_val(),
+                TestVal::test_val(),
+            )
+            .await?;
+        // errors make it back to the client
+        match cli_send
+            .lair_change_unlock_passphrase(
+                "nope".to_string(),
+                TestVal::test_val(),
+            )
+            .await
+        {
+            Err(LairError::WrongPassphrase) => (),
+            o => panic!("unexpected: {:?}", o),
+        }
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_cert_new_self_signed_from_entropy(
+                    TlsCertOptions::default(),
+                )
+                .await?,
+        );
+        let tls_options = |sni: &str| TlsCertOptions {
+            sni: Some(sni.to_string()),
+            alt_names: vec![
+                "*.example.com".to_string(),
+                "127.0.0.1".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_cert_new_self_signed_tagged(
+                    tls_options("lair.example.com"),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert!(matches!(
+            cli_send
+                .tls_cert_new_self_signed_tagged(
+                    tls_options("bad_host"),
+                    TestVal::test_val()
+                )
+                .await,
+            Err(LairError::TlsCertName(_))
+        ));
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_cert_renew(0.into(), tls_options("lair.example.com"))
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_ca_new_self_signed(TlsCertOptions {
+                    alg: TlsCertAlg::PkcsEcdsaP256Sha256,
+                    sni: Some("ca.example.com".to_string()),
+                    ..Default::default()
+                })
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+                <Vec<Cert>>::test_val(),
+            ),
+            cli_send
+                .tls_cert_new_signed_by_ca(
+                    3.into(),
+                    tls_options("lair.example.com"),
+                )
+                .await?,
+        );
+        assert!(matches!(
+            cli_send
+                .tls_cert_new_signed_by_ca(3.into(), tls_options("bad_host"))
+                .await,
+            Err(LairError::TlsCertName(_))
+        ));
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                CertSni::test_val(),
+                CertDigest::test_val(),
+            ),
+            cli_send
+                .tls_cert_import(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TlsCertOptions {
+                        sni: Some("lair.example.com".to_string()),
+                        digest_alg: TlsCertDigestAlg::Sha256,
+                        exportable: Some(false),
+                        ..Default::default()
+                    },
+                )
+                .await?,
         );
+        assert!(matches!(
+            cli_send
+                .tls_cert_import(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    tls_options("lair.example.com"),
+                )
+                .await,
+            Err(LairError::TlsCertName(_))
+        ));
         assert_eq!(
             (
                 KeystoreIndex::test_val(),
@@ -302,11 +1194,13 @@ mod tests {
                 CertDigest::test_val(),
             ),
             cli_send
-                .tls_cert_new_self_signed_from_entropy(
-                    TlsCertOptions::default(),
-                )
+                .tls_cert_find_by_digest_prefix(Arc::new(vec![0x42; 4]))
                 .await?,
         );
+        assert!(cli_send
+            .tls_cert_find_by_digest_prefix(Arc::new(Vec::new()))
+            .await
+            .is_err());
         assert_eq!(
             (CertSni::test_val(), CertDigest::test_val(),),
             cli_send.tls_cert_get(0.into()).await?,
@@ -343,6 +1237,29 @@ mod tests {
                 .tls_cert_get_priv_key_by_sni(CertSni::test_val())
                 .await?,
         );
+        assert_eq!(
+            Arc::<Vec<u8>>::test_val(),
+            cli_send
+                .tls_cert_export_pkcs12(0.into(), TestVal::test_val())
+                .await?,
+        );
+        assert!(cli_send
+            .tls_cert_export_pkcs12(
+                0.into(),
+                Some("p".repeat(pkcs12::MAX_PASSWORD_BYTES + 1)),
+            )
+            .await
+            .is_err());
+        assert_eq!(
+            Arc::<Vec<u8>>::test_val(),
+            cli_send
+                .tls_cert_sign_by_index(
+                    0.into(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
         assert_eq!(
             (
                 KeystoreIndex::test_val(),
@@ -350,6 +1267,39 @@ mod tests {
             ),
             cli_send.sign_ed25519_new_from_entropy().await?,
         );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                sign_ed25519::SignEd25519PubKey::test_val(),
+            ),
+            cli_send
+                .sign_ed25519_new_from_entropy_tagged(TestVal::test_val())
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                sign_ed25519::SignEd25519PubKey::test_val(),
+            ),
+            cli_send
+                .sign_ed25519_new_from_entropy_requiring_approval()
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                sign_ed25519::SignEd25519PubKey::test_val(),
+            ),
+            cli_send
+                .sign_ed25519_new_from_seed(TestVal::test_val())
+                .await?,
+        );
+        assert!(matches!(
+            cli_send
+                .sign_ed25519_new_from_seed(Arc::new(vec![0x42; 16]))
+                .await,
+            Err(LairError::SignEd25519SeedLength(16))
+        ));
         assert_eq!(
             sign_ed25519::SignEd25519PubKey::test_val(),
             cli_send.sign_ed25519_get(0.into()).await?,
@@ -360,6 +1310,46 @@ mod tests {
                 .sign_ed25519_sign_by_index(0.into(), b"".to_vec().into())
                 .await?,
         );
+        assert_eq!(
+            sign_ed25519::SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_prehashed(0.into(), TestVal::test_val())
+                .await?,
+        );
+        assert!(
+            cli_send
+                .sign_ed25519_verify(
+                    sign_ed25519::SignEd25519PubKey::test_val(),
+                    b"".to_vec().into(),
+                    sign_ed25519::SignEd25519Signature::test_val(),
+                )
+                .await?
+        );
+        // malformed signatures are rejected without a round trip
+        assert!(
+            !cli_send
+                .sign_ed25519_verify(
+                    sign_ed25519::SignEd25519PubKey::test_val(),
+                    b"".to_vec().into(),
+                    vec![0x42; 63].into(),
+                )
+                .await?
+        );
+        assert_eq!(
+            Vec::<sign_ed25519::SignEd25519Signature>::test_val(),
+            cli_send
+                .sign_ed25519_sign_batch_by_index(0.into(), TestVal::test_val())
+                .await?,
+        );
+        assert_eq!(
+            Vec::<sign_ed25519::SignEd25519Signature>::test_val(),
+            cli_send
+                .sign_ed25519_sign_batch_by_pub_key(
+                    sign_ed25519::SignEd25519PubKey::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
         assert_eq!(
             sign_ed25519::SignEd25519Signature::test_val(),
             cli_send
@@ -369,14 +1359,319 @@ mod tests {
                 )
                 .await?,
         );
+        assert_eq!(
+            sign_ed25519::SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_by_index_with_context(
+                    0.into(),
+                    b"ctx".to_vec().into(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        assert_eq!(
+            sign_ed25519::SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_by_pub_key_with_context(
+                    sign_ed25519::SignEd25519PubKey::test_val(),
+                    b"ctx".to_vec().into(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        // over long contexts are rejected without a round trip
+        assert!(cli_send
+            .sign_ed25519_sign_by_index_with_context(
+                0.into(),
+                vec![0; 256].into(),
+                b"".to_vec().into()
+            )
+            .await
+            .is_err());
+        assert_eq!(
+            sign_ed25519::SignEd25519Signature::test_val(),
+            cli_send
+                .sign_ed25519_sign_by_tag(
+                    TestVal::test_val(),
+                    b"".to_vec().into()
+                )
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                sign_secp256k1::SignSecp256k1PubKey::test_val(),
+            ),
+            cli_send.secp256k1_new_from_entropy().await?,
+        );
+        assert_eq!(
+            sign_secp256k1::SignSecp256k1PubKey::test_val(),
+            cli_send.secp256k1_get(0.into()).await?,
+        );
+        assert_eq!(
+            sign_secp256k1::SignSecp256k1Signature::test_val(),
+            cli_send.secp256k1_sign_by_index(0.into(), [0; 32]).await?,
+        );
+        assert_eq!(
+            sign_secp256k1::SignSecp256k1Signature::test_val(),
+            cli_send
+                .secp256k1_sign_by_pub_key(TestVal::test_val(), [0; 32])
+                .await?,
+        );
+        // malformed pub keys are rejected without a round trip
+        assert!(cli_send
+            .secp256k1_sign_by_pub_key(vec![0; 32].into(), [0; 32])
+            .await
+            .is_err());
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                sign_bls12381::SignBls12381PubKey::test_val(),
+            ),
+            cli_send.bls_new_from_entropy().await?,
+        );
+        assert_eq!(
+            sign_bls12381::SignBls12381Signature::test_val(),
+            cli_send
+                .bls_sign_by_pub_key(TestVal::test_val(), b"".to_vec().into())
+                .await?,
+        );
+        // malformed pub keys are rejected without a round trip
+        assert!(cli_send
+            .bls_sign_by_pub_key(vec![0; 32].into(), b"".to_vec().into())
+            .await
+            .is_err());
+        assert_eq!(
+            (KeystoreIndex::test_val(), seed::SeedId::test_val()),
+            cli_send.seed_new_from_entropy(true).await?,
+        );
+        assert_eq!(
+            String::test_val(),
+            cli_send.seed_export_mnemonic(0.into()).await?,
+        );
+        let mnemonic = seed::seed_to_mnemonic(&vec![0; 32].into())?;
+        assert_eq!(
+            (KeystoreIndex::test_val(), seed::SeedId::test_val()),
+            cli_send.seed_import_mnemonic(mnemonic, true).await?,
+        );
+        // bad mnemonics are rejected without a round trip
+        assert!(cli_send
+            .seed_import_mnemonic("abandon abandon".to_string(), true)
+            .await
+            .is_err());
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                sign_ed25519::SignEd25519PubKey::test_val(),
+            ),
+            cli_send
+                .sign_ed25519_derive_from_seed(0.into(), TestVal::test_val())
+                .await?,
+        );
+        // bad derivation paths are rejected without a round trip
+        assert!(cli_send
+            .sign_ed25519_derive_from_seed(0.into(), vec![])
+            .await
+            .is_err());
+        assert_eq!(
+            (KeystoreIndex::test_val(), x25519::X25519PubKey::test_val(),),
+            cli_send.sign_ed25519_to_x25519(0.into()).await?,
+        );
         assert_eq!(
             (KeystoreIndex::test_val(), x25519::X25519PubKey::test_val(),),
             cli_send.x25519_new_from_entropy().await?,
         );
+        assert_eq!(
+            (KeystoreIndex::test_val(), x25519::X25519PubKey::test_val(),),
+            cli_send
+                .x25519_new_from_priv_key(Arc::new(vec![0x42; 32]))
+                .await?,
+        );
+        assert!(matches!(
+            cli_send
+                .x25519_new_from_priv_key(Arc::new(vec![0x42; 16]))
+                .await,
+            Err(LairError::X25519PrivKeyImportLength(16))
+        ));
         assert_eq!(
             x25519::X25519PubKey::test_val(),
             cli_send.x25519_get(0.into()).await?,
         );
+        assert_eq!(
+            Vec::<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>::test_val(),
+            cli_send.sign_ed25519_list_pub_keys().await?,
+        );
+        assert_eq!(
+            Vec::<(KeystoreIndex, x25519::X25519PubKey)>::test_val(),
+            cli_send.x25519_list_pub_keys().await?,
+        );
+        assert_eq!(
+            (kx::KxSessionKey::test_val(), kx::KxSessionKey::test_val()),
+            cli_send
+                .kx_session_keys_by_index(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            crypto_box::CryptoBoxEncryptedData::test_val(),
+            cli_send
+                .crypto_box_by_index_with_aad(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            crypto_box::CryptoBoxEncryptedData::test_val(),
+            cli_send
+                .crypto_box_by_pub_key_with_aad(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            Option::<crypto_box::CryptoBoxData>::test_val(),
+            cli_send
+                .crypto_box_open_by_index_with_aad(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            Option::<crypto_box::CryptoBoxData>::test_val(),
+            cli_send
+                .crypto_box_open_by_pub_key_with_aad(
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert_eq!(
+            Option::<crypto_box::CryptoBoxData>::test_val(),
+            cli_send
+                .crypto_box_seal_open_by_index(0.into(), TestVal::test_val())
+                .await?,
+        );
+        assert_eq!(
+            Option::<crypto_box::CryptoBoxData>::test_val(),
+            cli_send
+                .crypto_box_seal_open_by_pub_key(
+                    TestVal::test_val(),
+                    TestVal::test_val()
+                )
+                .await?,
+        );
+        assert_eq!(
+            (
+                KeystoreIndex::test_val(),
+                secret_key::SecretKeyTag::test_val()
+            ),
+            cli_send.secret_new_from_entropy().await?,
+        );
+        assert_eq!(
+            (
+                secret_key::SecretNonce::test_val(),
+                Arc::<Vec<u8>>::test_val()
+            ),
+            cli_send
+                .secret_encrypt_by_index(
+                    0.into(),
+                    TestVal::test_val(),
+                    TestVal::test_val()
+                )
+                .await?,
+        );
+        assert_eq!(
+            Option::<Arc<Vec<u8>>>::test_val(),
+            cli_send
+                .secret_decrypt_by_index(
+                    0.into(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val()
+                )
+                .await?,
+        );
+        assert_eq!(
+            Arc::<Vec<u8>>::test_val(),
+            cli_send
+                .derive_subkey_by_index(
+                    0.into(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                    TestVal::test_val(),
+                )
+                .await?,
+        );
+        assert!(matches!(
+            cli_send
+                .derive_subkey_by_index(0.into(), 15, TestVal::test_val(), 0)
+                .await,
+            Err(LairError::SubkeyLength(15))
+        ));
+        // sessions not opened over this connection are unknown
+        let other_session_id =
+            secretstream::SecretstreamSessionId::from([0x43; 32]);
+        assert!(cli_send
+            .secretstream_push(other_session_id, TestVal::test_val(), false)
+            .await
+            .is_err());
+        let session_id = secretstream::SecretstreamSessionId::test_val();
+        assert_eq!(
+            (session_id, secretstream::SecretstreamHeader::test_val()),
+            cli_send
+                .secretstream_init_push_by_index(0.into(), TestVal::test_val())
+                .await?,
+        );
+        assert_eq!(
+            Arc::<Vec<u8>>::test_val(),
+            cli_send
+                .secretstream_push(session_id, TestVal::test_val(), false)
+                .await?,
+        );
+        assert_eq!(
+            session_id,
+            cli_send
+                .secretstream_init_pull_by_index(
+                    0.into(),
+                    TestVal::test_val(),
+                    TestVal::test_val()
+                )
+                .await?,
+        );
+        assert_eq!(
+            Option::<(Arc<Vec<u8>>, bool)>::test_val(),
+            cli_send
+                .secretstream_pull(session_id, TestVal::test_val())
+                .await?,
+        );
+        // the final chunk pulled ended the session
+        assert!(cli_send.secretstream_close(session_id).await.is_err());
+        assert!(cli_send
+            .secretstream_pull(other_session_id, TestVal::test_val())
+            .await
+            .is_err());
+        assert!(cli_send
+            .secretstream_push(
+                session_id,
+                Arc::new(vec![0; secretstream::CHUNK_MAX_BYTES + 1]),
+                false
+            )
+            .await
+            .is_err());
 
         cli_send.ghost_actor_shutdown().await?;
         drop(tmpdir);
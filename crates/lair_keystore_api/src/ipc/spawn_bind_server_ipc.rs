@@ -1,5 +1,8 @@
 use super::*;
+use crate::internal::audit;
+use crate::internal::auth_token;
 use crate::internal::ipc::*;
+use crate::internal::secretstream;
 use crate::internal::wire::*;
 use futures::{future::FutureExt, sink::SinkExt, stream::StreamExt};
 
@@ -7,17 +10,37 @@ pub(crate) async fn spawn_bind_server_ipc<S>(
     config: Arc<Config>,
     api_sender: S,
     incoming_send: futures::channel::mpsc::Sender<LairClientEventSenderType>,
+    listener: Option<IpcListener>,
 ) -> LairResult<()>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
-    let (kill_switch, mut incoming_ipc_recv) = spawn_bind_ipc(config).await?;
+    let token = if config.get_require_token() {
+        Some(Arc::new(auth_token::load_or_create_token(&config)?))
+    } else {
+        None
+    };
 
-    let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+    let audit_log = if config.get_audit_log() {
+        Some(Arc::new(audit::AuditLog::open(
+            config.get_audit_log_path(),
+            config.get_audit_log_max_bytes(),
+        )?))
+    } else {
+        None
+    };
+
+    let counts: Arc<ConnectionCounts> = Default::default();
+    let (kill_switch, mut incoming_ipc_recv) =
+        spawn_bind_ipc_counted(config.clone(), listener, counts.clone())
+            .await?;
 
-    let channel_factory = builder.channel_factory().clone();
+    let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
-    let i_s = channel_factory.create_channel::<InternalApi>().await?;
+    let i_s = builder
+        .channel_factory()
+        .create_channel::<InternalApi>()
+        .await?;
 
     let kill_sender = i_s.clone();
     kill_switch
@@ -33,7 +56,7 @@ where
 
     let i_kill_switch = kill_switch.clone();
     err_spawn("srv-ipc-incoming-loop", async move {
-        while let Ok((k, s, r)) = i_kill_switch
+        while let Ok((k, s, r, _)) = i_kill_switch
             .mix(async {
                 incoming_ipc_recv
                     .next()
@@ -52,10 +75,15 @@ where
     err_spawn("srv-ipc-actor", async move {
         builder
             .spawn(Internal {
+                config,
                 kill_switch,
-                channel_factory,
                 api_sender,
                 incoming_send,
+                token,
+                audit_log,
+                counts,
+                signing_approvers: Default::default(),
+                next_con_id: 0,
             })
             .await
             .map_err(LairError::other)
@@ -78,10 +106,71 @@ struct Internal<S>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
+    config: Arc<Config>,
     kill_switch: KillSwitch,
-    channel_factory: ghost_actor::actor_builder::GhostActorChannelFactory<Self>,
     api_sender: S,
     incoming_send: futures::channel::mpsc::Sender<LairClientEventSenderType>,
+    /// the connection token clients must present, if required
+    token: Option<Arc<String>>,
+    audit_log: Option<Arc<audit::AuditLog>>,
+    counts: Arc<ConnectionCounts>,
+    signing_approvers: SigningApprovers,
+    /// numbers connections, naming those of unnamed clients
+    next_con_id: u64,
+}
+
+/// The connections registered as signing approvers, by connection id,
+/// the latest last. Connections are dropped from it as they close.
+type SigningApprovers = Arc<std::sync::Mutex<Vec<(u64, IpcSender)>>>;
+
+/// The secretstream sessions opened over one connection,
+/// only that connection may use them.
+type ConSecretstreamSessions = Arc<
+    tokio::sync::Mutex<
+        std::collections::HashSet<secretstream::SecretstreamSessionId>,
+    >,
+>;
+
+/// Handles the requests of a single connection.
+struct Con<S>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    config: Arc<Config>,
+    kill_switch: KillSwitch,
+    api_sender: S,
+    secretstream_sessions: ConSecretstreamSessions,
+    token: Option<Arc<String>>,
+    /// what this connection may do
+    scope: ConnectionScope,
+    /// the name the client identified itself by, or `anon-<id>`
+    client_name: String,
+    /// numbers the connection among the signing approvers
+    con_id: u64,
+    /// where secret key operations are recorded, if anywhere
+    audit_log: Option<Arc<audit::AuditLog>>,
+    /// of all connections, for the server info
+    counts: Arc<ConnectionCounts>,
+    /// whether to forward store events to the client
+    subscribed: Arc<std::sync::atomic::AtomicBool>,
+    /// whether the client may be asked for the unlock passphrase
+    passphrase_provider: Arc<std::sync::atomic::AtomicBool>,
+    /// to ask the client for the unlock passphrase
+    ipc_send: IpcSender,
+    /// who is asked to approve signatures with keys that require it
+    signing_approvers: SigningApprovers,
+    authenticated: Arc<std::sync::atomic::AtomicBool>,
+    /// the event sender of this connection, registered with the server
+    /// once it presents the token
+    pending_evt_send: Option<(
+        futures::channel::mpsc::Sender<LairClientEventSenderType>,
+        LairClientEventSenderType,
+    )>,
+}
+
+impl<S> ghost_actor::GhostControlHandler for Con<S> where
+    S: ghost_actor::GhostChannelSender<LairClientApi>
+{
 }
 
 impl<S> ghost_actor::GhostControlHandler for Internal<S> where
@@ -109,21 +198,54 @@ where
         // decides to drop the event sender. Make this kill switch weak.
         con_kill_switch.make_weak();
 
+        self.next_con_id += 1;
+        let client_name = format!("anon-{}", self.next_con_id);
+        debug!(client = %client_name, "new connection");
+
         let (evt_send, mut evt_recv) = futures::channel::mpsc::channel(10);
-        let evt_ipc_send = ipc_send;
+        let evt_ipc_send = ipc_send.clone();
+        let subscribed: Arc<std::sync::atomic::AtomicBool> = Default::default();
+        let evt_subscribed = subscribed.clone();
+        let passphrase_provider: Arc<std::sync::atomic::AtomicBool> =
+            Default::default();
+        let evt_passphrase_provider = passphrase_provider.clone();
+        let evt_kill_switch = con_kill_switch.clone();
         err_spawn("srv-con-evt-loop", async move {
-            while let Ok(msg) = evt_recv
-                .next()
+            // ends with the connection, so the server stops sending events
+            while let Ok(msg) = evt_kill_switch
+                .mix(async {
+                    evt_recv
+                        .next()
+                        .await
+                        .ok_or_else::<LairError, _>(|| "stream end".into())
+                })
                 .await
-                .ok_or_else::<LairError, _>(|| "stream end".into())
             {
                 match msg {
                     LairClientEvent::RequestUnlockPassphrase {
                         respond,
                         ..
+                    } if !evt_passphrase_provider
+                        .load(std::sync::atomic::Ordering::SeqCst) =>
+                    {
+                        respond.respond(Ok(async move {
+                            Err("not a passphrase provider".into())
+                        }
+                        .boxed()
+                        .into()));
+                    }
+                    LairClientEvent::RequestUnlockPassphrase {
+                        respond,
+                        last_passphrase_failed,
+                        attempt,
+                        max_attempts,
+                        ..
                     } => {
                         match evt_ipc_send.request(LairWire::ToCliRequestUnlockPassphrase {
                             msg_id: next_msg_id(),
+                            last_passphrase_failed,
+                            attempt,
+                            max_attempts,
                         }).await {
                             Ok(LairWire::ToLairRequestUnlockPassphraseResponse {
                                 passphrase,
@@ -136,18 +258,153 @@ where
                             _ => (),
                         }
                     }
+                    // only clients emit these to their application,
+                    // signing approvers are asked by the connection
+                    // making the request, see `check_signing_approval`
+                    LairClientEvent::Reconnected { .. }
+                    | LairClientEvent::RequestSigningApproval { .. } => (),
+                    LairClientEvent::EntryCreated {
+                        respond,
+                        index,
+                        entry_type,
+                        ..
+                    } => {
+                        let res = if evt_subscribed
+                            .load(std::sync::atomic::Ordering::SeqCst)
+                        {
+                            evt_ipc_send
+                                .request(LairWire::ToCliEntryCreated {
+                                    msg_id: next_msg_id(),
+                                    keystore_index: index,
+                                    lair_entry_type: entry_type,
+                                })
+                                .await
+                                .map(|_| ())
+                        } else {
+                            Ok(())
+                        };
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairClientEvent::EntryDeleted {
+                        respond, index, ..
+                    } => {
+                        let res = if evt_subscribed
+                            .load(std::sync::atomic::Ordering::SeqCst)
+                        {
+                            evt_ipc_send
+                                .request(LairWire::ToCliEntryDeleted {
+                                    msg_id: next_msg_id(),
+                                    keystore_index: index,
+                                })
+                                .await
+                                .map(|_| ())
+                        } else {
+                            Ok(())
+                        };
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    // every client learns of lock state changes
+                    LairClientEvent::Locked { respond, .. } => {
+                        let res = evt_ipc_send
+                            .request(LairWire::ToCliLocked {
+                                msg_id: next_msg_id(),
+                            })
+                            .await
+                            .map(|_| ());
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairClientEvent::Unlocked { respond, .. } => {
+                        let res = evt_ipc_send
+                            .request(LairWire::ToCliUnlocked {
+                                msg_id: next_msg_id(),
+                            })
+                            .await
+                            .map(|_| ());
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
                 }
             }
             Ok(())
         });
-        let channel_factory = self.channel_factory.clone();
-        let mut in_send_clone = self.incoming_send.clone();
+        let mut con = Con {
+            config: self.config.clone(),
+            kill_switch: self.kill_switch.weak(),
+            api_sender: self.api_sender.clone(),
+            secretstream_sessions: Default::default(),
+            token: self.token.clone(),
+            scope: self.config.get_connection_scope(),
+            client_name,
+            con_id: self.next_con_id,
+            audit_log: self.audit_log.clone(),
+            counts: self.counts.clone(),
+            subscribed,
+            passphrase_provider,
+            ipc_send,
+            signing_approvers: self.signing_approvers.clone(),
+            authenticated: Default::default(),
+            pending_evt_send: None,
+        };
+        // connections that must present a token get no events until
+        // they do, and are dropped if they take too long
+        let mut register_evt_send = None;
+        if con.token.is_some() {
+            con.pending_evt_send = Some((self.incoming_send.clone(), evt_send));
+            let authenticated = con.authenticated.clone();
+            let timeout_kill_switch = con_kill_switch.clone();
+            tokio::task::spawn(async move {
+                tokio::time::sleep(auth_token::AUTH_TIMEOUT).await;
+                if !authenticated.load(std::sync::atomic::Ordering::SeqCst) {
+                    trace!("dropping unauthenticated connection");
+                    timeout_kill_switch.kill();
+                }
+            });
+        } else {
+            register_evt_send = Some((self.incoming_send.clone(), evt_send));
+        }
         Ok(async move {
-            channel_factory.attach_receiver(ipc_recv).await?;
-            in_send_clone
-                .send(evt_send)
-                .await
-                .map_err(LairError::other)?;
+            let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+            let con_sender = builder
+                .channel_factory()
+                .create_channel::<IpcWireApi>()
+                .await?;
+            builder.channel_factory().attach_receiver(ipc_recv).await?;
+
+            // when the connection goes away, so do its secretstream
+            // sessions, its actor, and its place among signing approvers
+            let sessions = con.secretstream_sessions.clone();
+            let api_sender = con.api_sender.clone();
+            let signing_approvers = con.signing_approvers.clone();
+            let con_id = con.con_id;
+            con_kill_switch
+                .register_kill_callback(Box::new(move || {
+                    Box::pin(async move {
+                        use ghost_actor::GhostControlSender;
+                        signing_approvers
+                            .lock()
+                            .unwrap()
+                            .retain(|(id, _)| *id != con_id);
+                        let session_ids: Vec<_> =
+                            sessions.lock().await.drain().collect();
+                        for session_id in session_ids {
+                            let _ =
+                                api_sender.secretstream_close(session_id).await;
+                        }
+                        if let Err(err) =
+                            con_sender.ghost_actor_shutdown().await
+                        {
+                            ghost_actor::dependencies::tracing::error!(?err);
+                        }
+                    })
+                }))
+                .await;
+
+            err_spawn("srv-con-actor", async move {
+                builder.spawn(con).await.map_err(LairError::other)
+            });
+
+            if let Some((mut in_send, evt_send)) = register_evt_send {
+                in_send.send(evt_send).await.map_err(LairError::other)?;
+            }
             Ok(())
         }
         .boxed()
@@ -155,26 +412,615 @@ where
     }
 }
 
-impl<S> ghost_actor::GhostHandler<IpcWireApi> for Internal<S> where
+impl<S> ghost_actor::GhostHandler<IpcWireApi> for Con<S> where
     S: ghost_actor::GhostChannelSender<LairClientApi>
 {
 }
 
-impl<S> IpcWireApiHandler for Internal<S>
+impl<S> Con<S>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    /// Name the connection after the client, e.g. in the span of its
+    /// requests. Clients identify themselves before authenticating.
+    fn identify(
+        &mut self,
+        msg_id: u64,
+        client_name: String,
+    ) -> IpcWireApiHandlerResult<LairWire> {
+        if let Err(err) = check_client_name(&client_name) {
+            return Ok(async move { Err(err) }.boxed().into());
+        }
+        debug!(client = %client_name, was = %self.client_name, "identified");
+        self.client_name = client_name;
+        Ok(
+            async move { Ok(LairWire::ToCliIdentifyResponse { msg_id }) }
+                .boxed()
+                .into(),
+        )
+    }
+
+    /// Check the token a client presents (anything goes if none is
+    /// required), then let it make requests in the scope it asks for,
+    /// up to the scope of its token, and receive events.
+    fn authenticate(
+        &mut self,
+        msg_id: u64,
+        token: &str,
+        scope: ConnectionScope,
+    ) -> IpcWireApiHandlerResult<LairWire> {
+        let allowed = match &self.token {
+            Some(expected) if auth_token::token_eq(expected, token) => {
+                self.config.get_connection_scope()
+            }
+            Some(_) => match self.config.get_token_scope(token) {
+                Some(allowed) => allowed,
+                None => {
+                    return Ok(
+                        async move { Err("invalid connection token".into()) }
+                            .boxed()
+                            .into(),
+                    )
+                }
+            },
+            None => self.config.get_connection_scope(),
+        };
+        self.scope = std::cmp::min(scope, allowed);
+        let scope = self.scope;
+        self.authenticated
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let register_evt_send = self.pending_evt_send.take();
+        Ok(async move {
+            if let Some((mut in_send, evt_send)) = register_evt_send {
+                in_send.send(evt_send).await.map_err(LairError::other)?;
+            }
+            Ok(LairWire::ToCliAuthenticateResponse { msg_id, scope })
+        }
+        .boxed()
+        .into())
+    }
+}
+
+/// Unlock a locked store with the passphrase of a provider that just
+/// registered, asking it again while the passphrases it gives are wrong.
+async fn unlock_with_provider<S>(
+    api_sender: S,
+    ipc_send: IpcSender,
+    max_attempts: u32,
+) -> LairResult<()>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    let mut last_passphrase_failed = false;
+    for attempt in 1..=max_attempts {
+        // another provider may have unlocked it meanwhile
+        match api_sender.lair_get_lock_state().await? {
+            LairLockState::Locked => (),
+            _ => return Ok(()),
+        }
+        let passphrase = match ipc_send
+            .request(LairWire::ToCliRequestUnlockPassphrase {
+                msg_id: next_msg_id(),
+                last_passphrase_failed,
+                attempt,
+                max_attempts,
+            })
+            .await?
+        {
+            LairWire::ToLairRequestUnlockPassphraseResponse {
+                passphrase,
+                ..
+            } => passphrase,
+            o => return Err(LairError::unexpected(o)),
+        };
+        match api_sender.lair_unlock(passphrase).await {
+            Err(LairError::WrongPassphrase) => last_passphrase_failed = true,
+            res => return res,
+        }
+    }
+    Err(format!(
+        "gave up on the provider after {} wrong unlock passphrases",
+        max_attempts
+    )
+    .into())
+}
+
+/// The scope a connection needs to make a request: reading entries and
+/// public material needs read-only, using existing keys sign-only, and
+/// anything creating, exporting or deleting entries full access.
+fn required_scope(msg: &LairWire) -> ConnectionScope {
+    match msg {
+        LairWire::ToLairLairPing { .. }
+        | LairWire::ToLairLairSubscribeStoreEvents { .. }
+        | LairWire::ToLairLairGetLockState { .. }
+        | LairWire::ToLairLairGetLastEntryIndex { .. }
+        | LairWire::ToLairLairGetEntryType { .. }
+        | LairWire::ToLairLairGetServerInfo { .. }
+        | LairWire::ToLairLairListEntries { .. }
+        | LairWire::ToLairLairGetEntryByTag { .. }
+        | LairWire::ToLairLairGetEntryMeta { .. }
+        | LairWire::ToLairLairFindEntry { .. }
+        | LairWire::ToLairLairExportEntryPublic { .. }
+        | LairWire::ToLairTlsCertGet { .. }
+        | LairWire::ToLairTlsCertGetCertByIndex { .. }
+        | LairWire::ToLairTlsCertGetCertByDigest { .. }
+        | LairWire::ToLairTlsCertGetCertBySni { .. }
+        | LairWire::ToLairTlsCertFindByDigestPrefix { .. }
+        | LairWire::ToLairSignEd25519Get { .. }
+        | LairWire::ToLairSignEd25519ListPubKeys { .. }
+        | LairWire::ToLairSignEd25519Verify { .. }
+        | LairWire::ToLairSecp256k1Get { .. }
+        | LairWire::ToLairX25519Get { .. }
+        | LairWire::ToLairX25519ListPubKeys { .. } => ConnectionScope::ReadOnly,
+        LairWire::ToLairTlsCertSignByIndex { .. }
+        | LairWire::ToLairSignEd25519SignByIndex { .. }
+        | LairWire::ToLairSignEd25519SignByPubKey { .. }
+        | LairWire::ToLairSignEd25519SignByTag { .. }
+        | LairWire::ToLairSignEd25519SignBatchByIndex { .. }
+        | LairWire::ToLairSignEd25519SignBatchByPubKey { .. }
+        | LairWire::ToLairSignEd25519SignPrehashed { .. }
+        | LairWire::ToLairSignEd25519SignByIndexWithContext { .. }
+        | LairWire::ToLairSignEd25519SignByPubKeyWithContext { .. }
+        | LairWire::ToLairSecp256k1SignByIndex { .. }
+        | LairWire::ToLairSecp256k1SignByPubKey { .. }
+        | LairWire::ToLairBlsSignByPubKey { .. }
+        | LairWire::ToLairSecretEncryptByIndex { .. }
+        | LairWire::ToLairSecretDecryptByIndex { .. }
+        | LairWire::ToLairDeriveSubkeyByIndex { .. }
+        | LairWire::ToLairSecretstreamInitPushByIndex { .. }
+        | LairWire::ToLairSecretstreamPush { .. }
+        | LairWire::ToLairSecretstreamInitPullByIndex { .. }
+        | LairWire::ToLairSecretstreamPull { .. }
+        | LairWire::ToLairSecretstreamClose { .. }
+        | LairWire::ToLairCryptoBoxByIndex { .. }
+        | LairWire::ToLairCryptoBoxByPubKey { .. }
+        | LairWire::ToLairCryptoBoxOpenByIndex { .. }
+        | LairWire::ToLairCryptoBoxOpenByPubKey { .. }
+        | LairWire::ToLairCryptoBoxByIndexWithAad { .. }
+        | LairWire::ToLairCryptoBoxByPubKeyWithAad { .. }
+        | LairWire::ToLairCryptoBoxOpenByIndexWithAad { .. }
+        | LairWire::ToLairCryptoBoxOpenByPubKeyWithAad { .. }
+        | LairWire::ToLairCryptoBoxSealOpenByIndex { .. }
+        | LairWire::ToLairCryptoBoxSealOpenByPubKey { .. }
+        | LairWire::ToLairKxSessionKeysByIndex { .. } => {
+            ConnectionScope::SignOnly
+        }
+        _ => ConnectionScope::Full,
+    }
+}
+
+/// The operation and entry of requests using secret keys, recorded in
+/// the audit log: signing, decrypting, and getting private key material
+/// or secrets derived from it.
+fn audit_request(
+    msg: &LairWire,
+) -> Option<(audit::AuditOp, audit::AuditEntry)> {
+    use audit::{AuditEntry, AuditOp};
+    let index = |index: &KeystoreIndex| AuditEntry::Index(**index);
+    let pub_key = |pub_key: &[u8]| AuditEntry::PubKey(base64::encode(pub_key));
+    Some(match msg {
+        LairWire::ToLairTlsCertSignByIndex { keystore_index, .. }
+        | LairWire::ToLairSignEd25519SignByIndex { keystore_index, .. }
+        | LairWire::ToLairSignEd25519SignBatchByIndex {
+            keystore_index, ..
+        }
+        | LairWire::ToLairSignEd25519SignPrehashed { keystore_index, .. }
+        | LairWire::ToLairSignEd25519SignByIndexWithContext {
+            keystore_index,
+            ..
+        }
+        | LairWire::ToLairSecp256k1SignByIndex { keystore_index, .. } => {
+            (AuditOp::Sign, index(keystore_index))
+        }
+        LairWire::ToLairSignEd25519SignByPubKey { pub_key: k, .. }
+        | LairWire::ToLairSignEd25519SignBatchByPubKey { pub_key: k, .. }
+        | LairWire::ToLairSignEd25519SignByPubKeyWithContext {
+            pub_key: k,
+            ..
+        } => (AuditOp::Sign, pub_key(k)),
+        LairWire::ToLairSecp256k1SignByPubKey { pub_key: k, .. } => {
+            (AuditOp::Sign, pub_key(k))
+        }
+        LairWire::ToLairBlsSignByPubKey { pub_key: k, .. } => {
+            (AuditOp::Sign, pub_key(k))
+        }
+        LairWire::ToLairSignEd25519SignByTag { tag, .. } => {
+            (AuditOp::Sign, AuditEntry::Tag(tag.clone()))
+        }
+        LairWire::ToLairSecretDecryptByIndex { keystore_index, .. }
+        | LairWire::ToLairSecretstreamInitPullByIndex {
+            keystore_index, ..
+        }
+        | LairWire::ToLairCryptoBoxOpenByIndex { keystore_index, .. }
+        | LairWire::ToLairCryptoBoxOpenByIndexWithAad {
+            keystore_index, ..
+        }
+        | LairWire::ToLairCryptoBoxSealOpenByIndex { keystore_index, .. } => {
+            (AuditOp::Decrypt, index(keystore_index))
+        }
+        LairWire::ToLairCryptoBoxOpenByPubKey { pub_key: k, .. }
+        | LairWire::ToLairCryptoBoxOpenByPubKeyWithAad { pub_key: k, .. }
+        | LairWire::ToLairCryptoBoxSealOpenByPubKey { pub_key: k, .. } => {
+            (AuditOp::Decrypt, pub_key(k.as_ref()))
+        }
+        LairWire::ToLairSecretstreamPull { session_id, .. } => (
+            AuditOp::Decrypt,
+            AuditEntry::Session(base64::encode(session_id.0)),
+        ),
+        LairWire::ToLairTlsCertGetPrivKeyByIndex { keystore_index, .. }
+        | LairWire::ToLairTlsCertExportPkcs12 { keystore_index, .. }
+        | LairWire::ToLairDeriveSubkeyByIndex { keystore_index, .. }
+        | LairWire::ToLairKxSessionKeysByIndex { keystore_index, .. } => {
+            (AuditOp::Export, index(keystore_index))
+        }
+        LairWire::ToLairSeedExportMnemonic { seed_index, .. } => {
+            (AuditOp::Export, index(seed_index))
+        }
+        LairWire::ToLairTlsCertGetPrivKeyByDigest { cert_digest, .. } => (
+            AuditOp::Export,
+            AuditEntry::CertDigest(base64::encode(&**cert_digest.0)),
+        ),
+        LairWire::ToLairTlsCertGetPrivKeyBySni { cert_sni, .. } => {
+            (AuditOp::Export, AuditEntry::CertSni(cert_sni.0.to_string()))
+        }
+        _ => return None,
+    })
+}
+
+/// The entry of a signing request, as the request names it.
+#[derive(Debug, PartialEq)]
+enum SigningEntry {
+    Index(KeystoreIndex),
+    PubKey(Arc<Vec<u8>>),
+    Tag(String),
+}
+
+/// The entry and message digest (see `signing_approval_digest()`) of
+/// requests signing with a private key, which may require approval.
+fn signing_approval_request(
+    msg: &LairWire,
+) -> Option<(SigningEntry, [u8; 32])> {
+    let digest = |message: &[u8]| signing_approval_digest(&[message]);
+    Some(match msg {
+        LairWire::ToLairSignEd25519SignByIndex {
+            keystore_index,
+            message,
+            ..
+        }
+        | LairWire::ToLairSignEd25519SignByIndexWithContext {
+            keystore_index,
+            message,
+            ..
+        } => (SigningEntry::Index(*keystore_index), digest(message)),
+        LairWire::ToLairSignEd25519SignPrehashed {
+            keystore_index,
+            digest: prehashed,
+            ..
+        } => (SigningEntry::Index(*keystore_index), digest(prehashed)),
+        LairWire::ToLairSignEd25519SignBatchByIndex {
+            keystore_index,
+            messages,
+            ..
+        } => {
+            let messages: Vec<&[u8]> =
+                messages.iter().map(|m| &m[..]).collect();
+            (
+                SigningEntry::Index(*keystore_index),
+                signing_approval_digest(&messages),
+            )
+        }
+        LairWire::ToLairSignEd25519SignByPubKey {
+            pub_key, message, ..
+        }
+        | LairWire::ToLairSignEd25519SignByPubKeyWithContext {
+            pub_key,
+            message,
+            ..
+        } => (SigningEntry::PubKey(pub_key.0.clone()), digest(message)),
+        LairWire::ToLairSignEd25519SignBatchByPubKey {
+            pub_key,
+            messages,
+            ..
+        } => {
+            let messages: Vec<&[u8]> =
+                messages.iter().map(|m| &m[..]).collect();
+            (
+                SigningEntry::PubKey(pub_key.0.clone()),
+                signing_approval_digest(&messages),
+            )
+        }
+        LairWire::ToLairSignEd25519SignByTag { tag, message, .. } => {
+            (SigningEntry::Tag(tag.clone()), digest(message))
+        }
+        LairWire::ToLairSecp256k1SignByIndex {
+            keystore_index,
+            digest: prehashed,
+            ..
+        } => (SigningEntry::Index(*keystore_index), digest(prehashed)),
+        LairWire::ToLairSecp256k1SignByPubKey {
+            pub_key,
+            digest: prehashed,
+            ..
+        } => (SigningEntry::PubKey(pub_key.pub_id()), digest(prehashed)),
+        LairWire::ToLairBlsSignByPubKey {
+            pub_key, message, ..
+        } => (SigningEntry::PubKey(pub_key.pub_id()), digest(message)),
+        LairWire::ToLairTlsCertSignByIndex {
+            keystore_index,
+            message,
+            ..
+        } => (SigningEntry::Index(*keystore_index), digest(message)),
+        _ => return None,
+    })
+}
+
+/// If the entry requires approval, ask the latest signing approver to
+/// approve `client_name` signing the message of `message_digest` with it.
+/// Fails closed: with `LairError::NoSigningApprover` if none is
+/// connected, `SigningApprovalTimeout` if it does not answer within
+/// `timeout`, and `SigningDenied` if it denies or fails to answer.
+async fn check_signing_approval<S>(
+    api_sender: S,
+    approvers: SigningApprovers,
+    timeout: std::time::Duration,
+    client_name: String,
+    entry: SigningEntry,
+    message_digest: [u8; 32],
+) -> LairResult<()>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    let keystore_index = match entry {
+        SigningEntry::Index(keystore_index) => keystore_index,
+        SigningEntry::PubKey(pub_key) => {
+            match api_sender.lair_find_entry(pub_key).await? {
+                Some((keystore_index, _)) => keystore_index,
+                // there is no key to approve, signing fails by itself
+                None => return Ok(()),
+            }
+        }
+        SigningEntry::Tag(tag) => {
+            api_sender.lair_get_entry_by_tag(tag).await?.0
+        }
+    };
+    if !api_sender
+        .lair_get_entry_meta(keystore_index)
+        .await?
+        .requires_approval
+    {
+        return Ok(());
+    }
+    let approver = approvers.lock().unwrap().last().map(|(_, s)| s.clone());
+    let approver = match approver {
+        Some(approver) => approver,
+        None => return Err(LairError::NoSigningApprover),
+    };
+    let request = approver.request(LairWire::ToCliRequestSigningApproval {
+        msg_id: next_msg_id(),
+        keystore_index,
+        client_id: client_name,
+        message_digest,
+    });
+    match tokio::time::timeout(timeout, request).await {
+        Err(_) => Err(LairError::SigningApprovalTimeout),
+        Ok(Ok(LairWire::ToLairRequestSigningApprovalResponse {
+            approved: true,
+            ..
+        })) => Ok(()),
+        Ok(Ok(LairWire::ToLairRequestSigningApprovalResponse { .. })) => {
+            Err(LairError::SigningDenied)
+        }
+        Ok(res) => {
+            debug!(?res, "signing approver failed to answer");
+            Err(LairError::SigningDenied)
+        }
+    }
+}
+
+/// Fails for sessions this connection didn't open.
+async fn check_con_secretstream_session(
+    sessions: &ConSecretstreamSessions,
+    session_id: &secretstream::SecretstreamSessionId,
+) -> LairResult<()> {
+    if sessions.lock().await.contains(session_id) {
+        Ok(())
+    } else {
+        Err(secretstream::secretstream_unknown_session())
+    }
+}
+
+impl<S> IpcWireApiHandler for Con<S>
 where
     S: ghost_actor::GhostChannelSender<LairClientApi>,
 {
+    /// Every request is handled in a span naming the client.
+    /// Secret key operations are recorded in the audit log, if kept,
+    /// denied ones included.
     fn handle_request(
         &mut self,
         msg: LairWire,
+    ) -> IpcWireApiHandlerResult<LairWire> {
+        let span = debug_span!(
+            "lair_request",
+            client = %self.client_name,
+            request = msg.name(),
+        );
+        let audit = match (&self.audit_log, audit_request(&msg)) {
+            (Some(log), Some((op, entry))) => Some((
+                log.clone(),
+                self.client_name.clone(),
+                op,
+                msg.name(),
+                entry,
+            )),
+            _ => None,
+        };
+        let fut = span.in_scope(|| {
+            debug!("handling request");
+            self.dispatch(msg)
+        })?;
+        let fut = async move {
+            let res = fut.await;
+            if let Some((log, client, op, request, entry)) = audit {
+                let error = res.as_ref().err().map(|e| e.to_string());
+                // the result is withheld unless it is on record
+                tokio::task::spawn_blocking(move || {
+                    log.append(&client, op, request, entry, error)
+                })
+                .await
+                .map_err(LairError::other)??;
+            }
+            res
+        };
+        Ok(fut.instrument(span).boxed().into())
+    }
+}
+
+impl<S> Con<S>
+where
+    S: ghost_actor::GhostChannelSender<LairClientApi>,
+{
+    fn dispatch(&mut self, msg: LairWire) -> IpcWireApiHandlerResult<LairWire> {
+        if let LairWire::ToLairIdentify {
+            msg_id,
+            client_name,
+        } = msg
+        {
+            return self.identify(msg_id, client_name);
+        }
+        if let LairWire::ToLairAuthenticate {
+            msg_id,
+            token,
+            scope,
+        } = &msg
+        {
+            return self.authenticate(*msg_id, token, *scope);
+        }
+        if self.token.is_some()
+            && !self.authenticated.load(std::sync::atomic::Ordering::SeqCst)
+        {
+            return Ok(async move {
+                Err("this lair-keystore requires a connection token".into())
+            }
+            .boxed()
+            .into());
+        }
+        if required_scope(&msg) > self.scope {
+            let err = LairError::PermissionDenied {
+                request: msg.name().to_string(),
+                scope: self.scope,
+            };
+            return Ok(async move { Err(err) }.boxed().into());
+        }
+        if let Some((entry, message_digest)) = signing_approval_request(&msg) {
+            let approval = check_signing_approval(
+                self.api_sender.clone(),
+                self.signing_approvers.clone(),
+                self.config.get_signing_approval_timeout(),
+                self.client_name.clone(),
+                entry,
+                message_digest,
+            );
+            // the signing request is only sent as the future is polled,
+            // once approved
+            let fut = self.handle_allowed(msg)?;
+            return Ok(async move {
+                approval.await?;
+                fut.await
+            }
+            .boxed()
+            .into());
+        }
+        self.handle_allowed(msg)
+    }
+
+    /// Handle a request the connection may make.
+    fn handle_allowed(
+        &mut self,
+        msg: LairWire,
     ) -> IpcWireApiHandlerResult<LairWire> {
         match msg {
+            LairWire::ToLairLairPing { msg_id, nonce } => {
+                // answered here, a locked or busy store still pongs
+                Ok(async move {
+                    Ok(LairWire::ToCliLairPingResponse { msg_id, nonce })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairSubscribeStoreEvents { msg_id } => {
+                // the server sends its store events to every connection,
+                // the events loop forwards them from now on
+                self.subscribed
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(async move {
+                    Ok(LairWire::ToCliLairSubscribeStoreEventsResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairGetLockState { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_get_lock_state());
+                Ok(async move {
+                    fut.await.map(|lock_state| {
+                        LairWire::ToCliLairGetLockStateResponse {
+                            msg_id,
+                            lock_state,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairRegisterPassphraseProvider { msg_id } => {
+                self.passphrase_provider
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
+                err_spawn(
+                    "srv-con-unlock",
+                    unlock_with_provider(
+                        self.api_sender.clone(),
+                        self.ipc_send.clone(),
+                        self.config.get_unlock_passphrase_attempts(),
+                    ),
+                );
+                Ok(async move {
+                    Ok(LairWire::ToCliLairRegisterPassphraseProviderResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairRegisterSigningApprover { msg_id } => {
+                let mut approvers = self.signing_approvers.lock().unwrap();
+                if !approvers.iter().any(|(id, _)| *id == self.con_id) {
+                    approvers.push((self.con_id, self.ipc_send.clone()));
+                }
+                Ok(async move {
+                    Ok(LairWire::ToCliLairRegisterSigningApproverResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
             LairWire::ToLairLairGetServerInfo { msg_id } => {
                 let fut = self
                     .kill_switch
                     .mix_static(self.api_sender.lair_get_server_info());
+                let counts = self.counts.clone();
                 Ok(async move {
-                    fut.await.map(|info| {
+                    fut.await.map(|mut info| {
+                        info.connection_count = counts
+                            .connections
+                            .load(std::sync::atomic::Ordering::SeqCst);
+                        info.requests_in_flight = counts
+                            .requests_in_flight
+                            .load(std::sync::atomic::Ordering::SeqCst);
                         LairWire::ToCliLairGetServerInfoResponse {
                             msg_id,
                             info,
@@ -217,273 +1063,1269 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
-                msg_id,
-                cert_alg,
-            } => {
-                let options = TlsCertOptions { alg: cert_alg };
-                let fut = self.kill_switch.mix_static(
-                    self.api_sender
-                        .tls_cert_new_self_signed_from_entropy(options),
-                );
+            LairWire::ToLairLairListEntries { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_list_entries());
                 Ok(async move {
-                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
-                        LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                    fut.await.map(|entries| {
+                        LairWire::ToCliLairListEntriesResponse {
                             msg_id,
-                            keystore_index,
-                            cert_sni,
-                            cert_digest,
+                            entries,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGet {
-                msg_id,
-                keystore_index,
-            } => {
+            LairWire::ToLairLairGetEntryByTag { msg_id, tag } => {
                 let fut = self
                     .kill_switch
-                    .mix_static(self.api_sender.tls_cert_get(keystore_index));
+                    .mix_static(self.api_sender.lair_get_entry_by_tag(tag));
                 Ok(async move {
-                    fut.await.map(|(cert_sni, cert_digest)| {
-                        LairWire::ToCliTlsCertGetResponse {
+                    fut.await.map(|(keystore_index, lair_entry_type)| {
+                        LairWire::ToCliLairGetEntryByTagResponse {
                             msg_id,
-                            cert_sni,
-                            cert_digest,
+                            keystore_index,
+                            lair_entry_type,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetCertByIndex {
+            LairWire::ToLairLairGetEntryMeta {
                 msg_id,
                 keystore_index,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_cert_by_index(keystore_index),
+                    self.api_sender.lair_get_entry_meta(keystore_index),
                 );
                 Ok(async move {
-                    fut.await.map(|cert| {
-                        LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    fut.await.map(|entry_meta| {
+                        LairWire::ToCliLairGetEntryMetaResponse {
                             msg_id,
-                            cert,
+                            entry_meta,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetCertByDigest {
-                msg_id,
-                cert_digest,
-            } => {
-                let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_cert_by_digest(cert_digest),
-                );
+            LairWire::ToLairLairFindEntry { msg_id, id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_find_entry(id));
                 Ok(async move {
-                    fut.await.map(|cert| {
-                        LairWire::ToCliTlsCertGetCertByDigestResponse {
-                            msg_id,
-                            cert,
-                        }
+                    fut.await.map(|entry| {
+                        LairWire::ToCliLairFindEntryResponse { msg_id, entry }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetCertBySni { msg_id, cert_sni } => {
+            LairWire::ToLairLairUpdateEntryTag {
+                msg_id,
+                keystore_index,
+                tag,
+            } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_cert_by_sni(cert_sni),
+                    self.api_sender.lair_update_entry_tag(keystore_index, tag),
                 );
                 Ok(async move {
-                    fut.await.map(|cert| {
-                        LairWire::ToCliTlsCertGetCertBySniResponse {
-                            msg_id,
-                            cert,
-                        }
+                    fut.await.map(|_| {
+                        LairWire::ToCliLairUpdateEntryTagResponse { msg_id }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetPrivKeyByIndex {
+            LairWire::ToLairLairSetEntryRateLimit {
                 msg_id,
                 keystore_index,
+                rate_limit,
             } => {
                 let fut = self.kill_switch.mix_static(
                     self.api_sender
-                        .tls_cert_get_priv_key_by_index(keystore_index),
+                        .lair_set_entry_rate_limit(keystore_index, rate_limit),
                 );
                 Ok(async move {
-                    fut.await.map(|cert_priv_key| {
-                        LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
-                            msg_id,
-                            cert_priv_key,
-                        }
+                    fut.await.map(|_| {
+                        LairWire::ToCliLairSetEntryRateLimitResponse { msg_id }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetPrivKeyByDigest {
+            LairWire::ToLairLairExportEntryPublic {
                 msg_id,
-                cert_digest,
+                keystore_index,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender
-                        .tls_cert_get_priv_key_by_digest(cert_digest),
+                    self.api_sender.lair_export_entry_public(keystore_index),
                 );
                 Ok(async move {
-                    fut.await.map(|cert_priv_key| {
-                        LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                    fut.await.map(|entry_public| {
+                        LairWire::ToCliLairExportEntryPublicResponse {
                             msg_id,
-                            cert_priv_key,
+                            entry_public,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairTlsCertGetPrivKeyBySni { msg_id, cert_sni } => {
+            LairWire::ToLairLairDeleteEntry {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_delete_entry(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|_| LairWire::ToCliLairDeleteEntryResponse {
+                        msg_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairCompactStore { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_compact_store());
+                Ok(async move {
+                    fut.await.map(|info| {
+                        LairWire::ToCliLairCompactStoreResponse { msg_id, info }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairShutdown { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_shutdown());
+                Ok(async move {
+                    fut.await
+                        .map(|_| LairWire::ToCliLairShutdownResponse { msg_id })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairLock { msg_id } => {
+                let fut =
+                    self.kill_switch.mix_static(self.api_sender.lair_lock());
+                Ok(async move {
+                    fut.await
+                        .map(|_| LairWire::ToCliLairLockResponse { msg_id })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairUnlock { msg_id, passphrase } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.lair_unlock(passphrase));
+                Ok(async move {
+                    fut.await
+                        .map(|_| LairWire::ToCliLairUnlockResponse { msg_id })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairLairChangeUnlockPassphrase {
+                msg_id,
+                old_passphrase,
+                new_passphrase,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.lair_change_unlock_passphrase(
+                        old_passphrase,
+                        new_passphrase,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|_| {
+                        LairWire::ToCliLairChangeUnlockPassphraseResponse {
+                            msg_id,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                msg_id,
+                cert_alg,
+                digest_alg,
+                exportable,
+                sni,
+                alt_names,
+            } => {
+                let options = TlsCertOptions {
+                    alg: cert_alg,
+                    sni,
+                    alt_names,
+                    digest_alg,
+                    exportable,
+                };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_new_self_signed_from_entropy(options),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertNewSelfSignedTagged {
+                msg_id,
+                cert_alg,
+                digest_alg,
+                exportable,
+                sni,
+                alt_names,
+                tag,
+            } => {
+                let options = TlsCertOptions {
+                    alg: cert_alg,
+                    sni,
+                    alt_names,
+                    digest_alg,
+                    exportable,
+                };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_new_self_signed_tagged(options, tag),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertNewSelfSignedTaggedResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertRenew {
+                msg_id,
+                keystore_index,
+                digest_alg,
+                exportable,
+                sni,
+                alt_names,
+            } => {
+                let options = TlsCertOptions {
+                    sni,
+                    alt_names,
+                    digest_alg,
+                    exportable,
+                    ..Default::default()
+                };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_renew(keystore_index, options),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertRenewResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertImport {
+                msg_id,
+                cert_der,
+                priv_key_der,
+                sni,
+                digest_alg,
+                exportable,
+            } => {
+                let options = TlsCertOptions {
+                    sni,
+                    digest_alg,
+                    exportable,
+                    ..Default::default()
+                };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_import(
+                        cert_der,
+                        priv_key_der,
+                        options,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertImportResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCaNewSelfSigned {
+                msg_id,
+                cert_alg,
+                digest_alg,
+                exportable,
+                sni,
+                alt_names,
+            } => {
+                let options = TlsCertOptions {
+                    alg: cert_alg,
+                    sni,
+                    alt_names,
+                    digest_alg,
+                    exportable,
+                };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_ca_new_self_signed(options),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCaNewSelfSignedResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertNewSignedByCa {
+                msg_id,
+                ca_index,
+                cert_alg,
+                digest_alg,
+                exportable,
+                sni,
+                alt_names,
+            } => {
+                let options = TlsCertOptions {
+                    alg: cert_alg,
+                    sni,
+                    alt_names,
+                    digest_alg,
+                    exportable,
+                };
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_new_signed_by_ca(ca_index, options),
+                );
+                Ok(async move {
+                    fut.await.map(
+                        |(
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                            cert_chain,
+                        )| {
+                            LairWire::ToCliTlsCertNewSignedByCaResponse {
+                                msg_id,
+                                keystore_index,
+                                cert_sni,
+                                cert_digest,
+                                cert_chain,
+                            }
+                        },
+                    )
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertFindByDigestPrefix { msg_id, prefix } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_find_by_digest_prefix(prefix),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertFindByDigestPrefixResponse {
+                            msg_id,
+                            keystore_index,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGet {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.tls_cert_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|(cert_sni, cert_digest)| {
+                        LairWire::ToCliTlsCertGetResponse {
+                            msg_id,
+                            cert_sni,
+                            cert_digest,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertByIndex {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_cert_by_index(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertByIndexResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertByDigest {
+                msg_id,
+                cert_digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_cert_by_digest(cert_digest),
+                );
+                Ok(async move {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertByDigestResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetCertBySni { msg_id, cert_sni } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_cert_by_sni(cert_sni),
+                );
+                Ok(async move {
+                    fut.await.map(|cert| {
+                        LairWire::ToCliTlsCertGetCertBySniResponse {
+                            msg_id,
+                            cert,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetPrivKeyByIndex {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_get_priv_key_by_index(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|cert_priv_key| {
+                        LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                            msg_id,
+                            cert_priv_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetPrivKeyByDigest {
+                msg_id,
+                cert_digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_get_priv_key_by_digest(cert_digest),
+                );
+                Ok(async move {
+                    fut.await.map(|cert_priv_key| {
+                        LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                            msg_id,
+                            cert_priv_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertGetPrivKeyBySni { msg_id, cert_sni } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_get_priv_key_by_sni(cert_sni),
+                );
+                Ok(async move {
+                    fut.await.map(|cert_priv_key| {
+                        LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                            msg_id,
+                            cert_priv_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertExportPkcs12 {
+                msg_id,
+                keystore_index,
+                password,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .tls_cert_export_pkcs12(keystore_index, password),
+                );
+                Ok(async move {
+                    fut.await.map(|pkcs12| {
+                        LairWire::ToCliTlsCertExportPkcs12Response {
+                            msg_id,
+                            pkcs12,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairTlsCertSignByIndex {
+                msg_id,
+                keystore_index,
+                scheme,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.tls_cert_sign_by_index(
+                        keystore_index,
+                        scheme,
+                        message,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliTlsCertSignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromEntropy { msg_id } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_new_from_entropy(),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromEntropyTagged { msg_id, tag } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_new_from_entropy_tagged(tag),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewFromEntropyTaggedResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromEntropyRequiringApproval {
+                msg_id,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_new_from_entropy_requiring_approval(),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewFromEntropyRequiringApprovalResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519NewFromSeed { msg_id, seed } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_new_from_seed(seed),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519NewFromSeedResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_get(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|pub_key| {
+                        LairWire::ToCliSignEd25519GetResponse {
+                            msg_id,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByIndex {
+                msg_id,
+                keystore_index,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_index(keystore_index, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByPubKey {
+                msg_id,
+                pub_key,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_by_pub_key(pub_key, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByIndexWithContext {
+                msg_id,
+                keystore_index,
+                context,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_sign_by_index_with_context(
+                        keystore_index,
+                        context,
+                        message,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByIndexWithContextResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByPubKeyWithContext {
+                msg_id,
+                pub_key,
+                context,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_sign_by_pub_key_with_context(
+                        pub_key, context, message,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByPubKeyWithContextResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignBatchByIndex {
+                msg_id,
+                keystore_index,
+                messages,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_sign_batch_by_index(
+                        keystore_index,
+                        messages,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|signatures| {
+                        LairWire::ToCliSignEd25519SignBatchByIndexResponse {
+                            msg_id,
+                            signatures,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignBatchByPubKey {
+                msg_id,
+                pub_key,
+                messages,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .sign_ed25519_sign_batch_by_pub_key(pub_key, messages),
+                );
+                Ok(async move {
+                    fut.await.map(|signatures| {
+                        LairWire::ToCliSignEd25519SignBatchByPubKeyResponse {
+                            msg_id,
+                            signatures,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignPrehashed {
+                msg_id,
+                keystore_index,
+                digest,
+            } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.tls_cert_get_priv_key_by_sni(cert_sni),
+                    self.api_sender
+                        .sign_ed25519_sign_prehashed(keystore_index, digest),
                 );
                 Ok(async move {
-                    fut.await.map(|cert_priv_key| {
-                        LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignPrehashedResponse {
                             msg_id,
-                            cert_priv_key,
+                            signature,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519NewFromEntropy { msg_id } => {
+            LairWire::ToLairSignEd25519Verify {
+                msg_id,
+                pub_key,
+                message,
+                signature,
+            } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.sign_ed25519_new_from_entropy(),
+                    self.api_sender
+                        .sign_ed25519_verify(pub_key, message, signature),
+                );
+                Ok(async move {
+                    fut.await.map(|valid| {
+                        LairWire::ToCliSignEd25519VerifyResponse {
+                            msg_id,
+                            valid,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519SignByTag {
+                msg_id,
+                tag,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_sign_by_tag(tag, message),
                 );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSignEd25519SignByTagResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519ListPubKeys { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.sign_ed25519_list_pub_keys());
+                Ok(async move {
+                    fut.await.map(|pub_keys| {
+                        LairWire::ToCliSignEd25519ListPubKeysResponse {
+                            msg_id,
+                            pub_keys,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecp256k1NewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.secp256k1_new_from_entropy());
                 Ok(async move {
                     fut.await.map(|(keystore_index, pub_key)| {
-                        LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                        LairWire::ToCliSecp256k1NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecp256k1Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.secp256k1_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|pub_key| {
+                        LairWire::ToCliSecp256k1GetResponse { msg_id, pub_key }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecp256k1SignByIndex {
+                msg_id,
+                keystore_index,
+                digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .secp256k1_sign_by_index(keystore_index, digest),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSecp256k1SignByIndexResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecp256k1SignByPubKey {
+                msg_id,
+                pub_key,
+                digest,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.secp256k1_sign_by_pub_key(pub_key, digest),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliSecp256k1SignByPubKeyResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairBlsNewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.bls_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliBlsNewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairBlsSignByPubKey {
+                msg_id,
+                pub_key,
+                message,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.bls_sign_by_pub_key(pub_key, message),
+                );
+                Ok(async move {
+                    fut.await.map(|signature| {
+                        LairWire::ToCliBlsSignByPubKeyResponse {
+                            msg_id,
+                            signature,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSeedNewFromEntropy { msg_id, exportable } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.seed_new_from_entropy(exportable),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, seed_id)| {
+                        LairWire::ToCliSeedNewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            seed_id,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519DeriveFromSeed {
+                msg_id,
+                seed_index,
+                derivation_path,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_derive_from_seed(
+                        seed_index,
+                        derivation_path,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519DeriveFromSeedResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSeedExportMnemonic { msg_id, seed_index } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.seed_export_mnemonic(seed_index),
+                );
+                Ok(async move {
+                    fut.await.map(|mnemonic| {
+                        LairWire::ToCliSeedExportMnemonicResponse {
+                            msg_id,
+                            mnemonic,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSeedImportMnemonic {
+                msg_id,
+                mnemonic,
+                exportable,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.seed_import_mnemonic(mnemonic, exportable),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, seed_id)| {
+                        LairWire::ToCliSeedImportMnemonicResponse {
+                            msg_id,
+                            keystore_index,
+                            seed_id,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSignEd25519ToX25519 {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.sign_ed25519_to_x25519(keystore_index),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliSignEd25519ToX25519Response {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairX25519NewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.x25519_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliX25519NewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairX25519NewFromPrivKey { msg_id, priv_key } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.x25519_new_from_priv_key(priv_key),
+                );
+                Ok(async move {
+                    fut.await.map(|(keystore_index, pub_key)| {
+                        LairWire::ToCliX25519NewFromPrivKeyResponse {
+                            msg_id,
+                            keystore_index,
+                            pub_key,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairX25519Get {
+                msg_id,
+                keystore_index,
+            } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.x25519_get(keystore_index));
+                Ok(async move {
+                    fut.await.map(|pub_key| LairWire::ToCliX25519GetResponse {
+                        msg_id,
+                        pub_key,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairX25519ListPubKeys { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.x25519_list_pub_keys());
+                Ok(async move {
+                    fut.await.map(|pub_keys| {
+                        LairWire::ToCliX25519ListPubKeysResponse {
                             msg_id,
-                            keystore_index,
-                            pub_key,
+                            pub_keys,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519Get {
+            LairWire::ToLairKxSessionKeysByIndex {
                 msg_id,
                 keystore_index,
+                remote_pub,
+                is_server,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.sign_ed25519_get(keystore_index),
+                    self.api_sender.kx_session_keys_by_index(
+                        keystore_index,
+                        remote_pub,
+                        is_server,
+                    ),
                 );
                 Ok(async move {
-                    fut.await.map(|pub_key| {
-                        LairWire::ToCliSignEd25519GetResponse {
+                    fut.await.map(|(rx, tx)| {
+                        LairWire::ToCliKxSessionKeysByIndexResponse {
                             msg_id,
-                            pub_key,
+                            rx,
+                            tx,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519SignByIndex {
+            LairWire::ToLairCryptoBoxByIndex {
                 msg_id,
                 keystore_index,
-                message,
+                recipient,
+                data,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender
-                        .sign_ed25519_sign_by_index(keystore_index, message),
+                    self.api_sender.crypto_box_by_index(
+                        keystore_index,
+                        recipient,
+                        data,
+                    ),
                 );
                 Ok(async move {
-                    fut.await.map(|signature| {
-                        LairWire::ToCliSignEd25519SignByIndexResponse {
+                    fut.await.map(|encrypted_data| {
+                        LairWire::ToCliCryptoBoxByIndexResponse {
                             msg_id,
-                            signature,
+                            encrypted_data,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairSignEd25519SignByPubKey {
+            LairWire::ToLairCryptoBoxByPubKey {
                 msg_id,
                 pub_key,
-                message,
+                recipient,
+                data,
             } => {
                 let fut = self.kill_switch.mix_static(
                     self.api_sender
-                        .sign_ed25519_sign_by_pub_key(pub_key, message),
+                        .crypto_box_by_pub_key(pub_key, recipient, data),
                 );
                 Ok(async move {
-                    fut.await.map(|signature| {
-                        LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                    fut.await.map(|encrypted_data| {
+                        LairWire::ToCliCryptoBoxByPubKeyResponse {
                             msg_id,
-                            signature,
+                            encrypted_data,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairX25519NewFromEntropy { msg_id } => {
-                let fut = self
-                    .kill_switch
-                    .mix_static(self.api_sender.x25519_new_from_entropy());
+            LairWire::ToLairCryptoBoxOpenByIndex {
+                msg_id,
+                keystore_index,
+                sender,
+                encrypted_data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.crypto_box_open_by_index(
+                        keystore_index,
+                        sender,
+                        encrypted_data,
+                    ),
+                );
                 Ok(async move {
-                    fut.await.map(|(keystore_index, pub_key)| {
-                        LairWire::ToCliX25519NewFromEntropyResponse {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxOpenByIndexResponse {
                             msg_id,
-                            keystore_index,
-                            pub_key,
+                            data,
                         }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairX25519Get {
+            LairWire::ToLairCryptoBoxOpenByPubKey {
                 msg_id,
-                keystore_index,
+                pub_key,
+                sender,
+                encrypted_data,
             } => {
-                let fut = self
-                    .kill_switch
-                    .mix_static(self.api_sender.x25519_get(keystore_index));
-                Ok(async move {
-                    fut.await.map(|pub_key| LairWire::ToCliX25519GetResponse {
-                        msg_id,
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.crypto_box_open_by_pub_key(
                         pub_key,
+                        sender,
+                        encrypted_data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxOpenByPubKeyResponse {
+                            msg_id,
+                            data,
+                        }
                     })
                 }
                 .boxed()
                 .into())
             }
-            LairWire::ToLairCryptoBoxByIndex {
+            LairWire::ToLairCryptoBoxByIndexWithAad {
                 msg_id,
                 keystore_index,
                 recipient,
+                aad,
                 data,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.crypto_box_by_index(
+                    self.api_sender.crypto_box_by_index_with_aad(
                         keystore_index,
                         recipient,
+                        aad,
                         data,
                     ),
                 );
                 Ok(async move {
                     fut.await.map(|encrypted_data| {
-                        LairWire::ToCliCryptoBoxByIndexResponse {
+                        LairWire::ToCliCryptoBoxByIndexWithAadResponse {
                             msg_id,
                             encrypted_data,
                         }
@@ -492,19 +2334,21 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairCryptoBoxByPubKey {
+            LairWire::ToLairCryptoBoxByPubKeyWithAad {
                 msg_id,
                 pub_key,
                 recipient,
+                aad,
                 data,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender
-                        .crypto_box_by_pub_key(pub_key, recipient, data),
+                    self.api_sender.crypto_box_by_pub_key_with_aad(
+                        pub_key, recipient, aad, data,
+                    ),
                 );
                 Ok(async move {
                     fut.await.map(|encrypted_data| {
-                        LairWire::ToCliCryptoBoxByPubKeyResponse {
+                        LairWire::ToCliCryptoBoxByPubKeyWithAadResponse {
                             msg_id,
                             encrypted_data,
                         }
@@ -513,22 +2357,24 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairCryptoBoxOpenByIndex {
+            LairWire::ToLairCryptoBoxOpenByIndexWithAad {
                 msg_id,
                 keystore_index,
                 sender,
+                aad,
                 encrypted_data,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.crypto_box_open_by_index(
+                    self.api_sender.crypto_box_open_by_index_with_aad(
                         keystore_index,
                         sender,
+                        aad,
                         encrypted_data,
                     ),
                 );
                 Ok(async move {
                     fut.await.map(|data| {
-                        LairWire::ToCliCryptoBoxOpenByIndexResponse {
+                        LairWire::ToCliCryptoBoxOpenByIndexWithAadResponse {
                             msg_id,
                             data,
                         }
@@ -537,22 +2383,133 @@ where
                 .boxed()
                 .into())
             }
-            LairWire::ToLairCryptoBoxOpenByPubKey {
+            LairWire::ToLairCryptoBoxOpenByPubKeyWithAad {
                 msg_id,
                 pub_key,
                 sender,
+                aad,
                 encrypted_data,
             } => {
                 let fut = self.kill_switch.mix_static(
-                    self.api_sender.crypto_box_open_by_pub_key(
+                    self.api_sender.crypto_box_open_by_pub_key_with_aad(
                         pub_key,
                         sender,
+                        aad,
                         encrypted_data,
                     ),
                 );
                 Ok(async move {
                     fut.await.map(|data| {
-                        LairWire::ToCliCryptoBoxOpenByPubKeyResponse {
+                        LairWire::ToCliCryptoBoxOpenByPubKeyWithAadResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairCryptoBoxSealOpenByIndex {
+                msg_id,
+                keystore_index,
+                sealed_data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.crypto_box_seal_open_by_index(
+                        keystore_index,
+                        sealed_data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxSealOpenByIndexResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairCryptoBoxSealOpenByPubKey {
+                msg_id,
+                pub_key,
+                sealed_data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender
+                        .crypto_box_seal_open_by_pub_key(pub_key, sealed_data),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliCryptoBoxSealOpenByPubKeyResponse {
+                            msg_id,
+                            data,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretNewFromEntropy { msg_id } => {
+                let fut = self
+                    .kill_switch
+                    .mix_static(self.api_sender.secret_new_from_entropy());
+                Ok(async move {
+                    fut.await.map(|(keystore_index, tag)| {
+                        LairWire::ToCliSecretNewFromEntropyResponse {
+                            msg_id,
+                            keystore_index,
+                            tag,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretEncryptByIndex {
+                msg_id,
+                keystore_index,
+                aad,
+                data,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.secret_encrypt_by_index(
+                        keystore_index,
+                        aad,
+                        data,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|(nonce, ciphertext)| {
+                        LairWire::ToCliSecretEncryptByIndexResponse {
+                            msg_id,
+                            nonce,
+                            ciphertext,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretDecryptByIndex {
+                msg_id,
+                keystore_index,
+                aad,
+                nonce,
+                ciphertext,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.secret_decrypt_by_index(
+                        keystore_index,
+                        aad,
+                        nonce,
+                        ciphertext,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|data| {
+                        LairWire::ToCliSecretDecryptByIndexResponse {
                             msg_id,
                             data,
                         }
@@ -561,7 +2518,214 @@ where
                 .boxed()
                 .into())
             }
-            o => Err(format!("unexpected: {:?}", o).into()),
+            LairWire::ToLairDeriveSubkeyByIndex {
+                msg_id,
+                keystore_index,
+                subkey_len,
+                context,
+                subkey_id,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.derive_subkey_by_index(
+                        keystore_index,
+                        subkey_len,
+                        context,
+                        subkey_id,
+                    ),
+                );
+                Ok(async move {
+                    fut.await.map(|subkey| {
+                        LairWire::ToCliDeriveSubkeyByIndexResponse {
+                            msg_id,
+                            subkey,
+                        }
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretstreamInitPushByIndex {
+                msg_id,
+                keystore_index,
+                remote_pub,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.secretstream_init_push_by_index(
+                        keystore_index,
+                        remote_pub,
+                    ),
+                );
+                let sessions = self.secretstream_sessions.clone();
+                Ok(async move {
+                    let (session_id, header) = fut.await?;
+                    sessions.lock().await.insert(session_id);
+                    Ok(LairWire::ToCliSecretstreamInitPushByIndexResponse {
+                        msg_id,
+                        session_id,
+                        header,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretstreamPush {
+                msg_id,
+                session_id,
+                chunk,
+                is_final,
+            } => {
+                secretstream::check_chunk_len(chunk.len())?;
+                let kill_switch = self.kill_switch.clone();
+                let api_sender = self.api_sender.clone();
+                let sessions = self.secretstream_sessions.clone();
+                Ok(async move {
+                    check_con_secretstream_session(&sessions, &session_id)
+                        .await?;
+                    let ciphertext = kill_switch
+                        .mix(
+                            api_sender
+                                .secretstream_push(session_id, chunk, is_final),
+                        )
+                        .await?;
+                    if is_final {
+                        sessions.lock().await.remove(&session_id);
+                    }
+                    Ok(LairWire::ToCliSecretstreamPushResponse {
+                        msg_id,
+                        ciphertext,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretstreamInitPullByIndex {
+                msg_id,
+                keystore_index,
+                remote_pub,
+                header,
+            } => {
+                let fut = self.kill_switch.mix_static(
+                    self.api_sender.secretstream_init_pull_by_index(
+                        keystore_index,
+                        remote_pub,
+                        header,
+                    ),
+                );
+                let sessions = self.secretstream_sessions.clone();
+                Ok(async move {
+                    let session_id = fut.await?;
+                    sessions.lock().await.insert(session_id);
+                    Ok(LairWire::ToCliSecretstreamInitPullByIndexResponse {
+                        msg_id,
+                        session_id,
+                    })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretstreamPull {
+                msg_id,
+                session_id,
+                ciphertext,
+            } => {
+                secretstream::check_ciphertext_len(ciphertext.len())?;
+                let kill_switch = self.kill_switch.clone();
+                let api_sender = self.api_sender.clone();
+                let sessions = self.secretstream_sessions.clone();
+                Ok(async move {
+                    check_con_secretstream_session(&sessions, &session_id)
+                        .await?;
+                    let data = kill_switch
+                        .mix(
+                            api_sender
+                                .secretstream_pull(session_id, ciphertext),
+                        )
+                        .await?;
+                    if let Some((_, true)) = data {
+                        sessions.lock().await.remove(&session_id);
+                    }
+                    Ok(LairWire::ToCliSecretstreamPullResponse { msg_id, data })
+                }
+                .boxed()
+                .into())
+            }
+            LairWire::ToLairSecretstreamClose { msg_id, session_id } => {
+                let kill_switch = self.kill_switch.clone();
+                let api_sender = self.api_sender.clone();
+                let sessions = self.secretstream_sessions.clone();
+                Ok(async move {
+                    check_con_secretstream_session(&sessions, &session_id)
+                        .await?;
+                    kill_switch
+                        .mix(api_sender.secretstream_close(session_id))
+                        .await?;
+                    sessions.lock().await.remove(&session_id);
+                    Ok(LairWire::ToCliSecretstreamCloseResponse { msg_id })
+                }
+                .boxed()
+                .into())
+            }
+            o => Err(LairError::unexpected(o)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::wire::tests::TestVal;
+    use crate::internal::{sign_bls12381, sign_secp256k1};
+
+    #[test]
+    fn every_signing_request_may_require_approval() {
+        let entry =
+            |msg| signing_approval_request(&msg).map(|(entry, _)| entry);
+        let secp256k1_pub_key = sign_secp256k1::SignSecp256k1PubKey::test_val();
+        let bls_pub_key = sign_bls12381::SignBls12381PubKey::test_val();
+
+        assert_eq!(
+            Some(SigningEntry::Index(3.into())),
+            entry(LairWire::ToLairSecp256k1SignByIndex {
+                msg_id: 0,
+                keystore_index: 3.into(),
+                digest: [0xdb; 32],
+            })
+        );
+        // entries are found by the pub id of their key
+        assert_eq!(
+            Some(SigningEntry::PubKey(secp256k1_pub_key.pub_id())),
+            entry(LairWire::ToLairSecp256k1SignByPubKey {
+                msg_id: 0,
+                pub_key: secp256k1_pub_key,
+                digest: [0xdb; 32],
+            })
+        );
+        assert_eq!(
+            Some(SigningEntry::PubKey(bls_pub_key.pub_id())),
+            entry(LairWire::ToLairBlsSignByPubKey {
+                msg_id: 0,
+                pub_key: bls_pub_key,
+                message: Arc::new(b"message".to_vec()),
+            })
+        );
+        assert_eq!(
+            Some(SigningEntry::Index(4.into())),
+            entry(LairWire::ToLairTlsCertSignByIndex {
+                msg_id: 0,
+                keystore_index: 4.into(),
+                scheme: TestVal::test_val(),
+                message: Arc::new(b"message".to_vec()),
+            })
+        );
+        // verifying uses no private key
+        assert_eq!(
+            None,
+            entry(LairWire::ToLairSignEd25519Verify {
+                msg_id: 0,
+                pub_key: TestVal::test_val(),
+                message: TestVal::test_val(),
+                signature: TestVal::test_val(),
+            })
+        );
+    }
+}
@@ -1,17 +1,168 @@
 use super::*;
+use crate::internal::auth_token;
 use crate::internal::crypto_box;
 use crate::internal::ipc::*;
+use crate::internal::kx;
+use crate::internal::pkcs12;
+use crate::internal::secret_key;
+use crate::internal::secretstream;
+use crate::internal::seed;
+use crate::internal::sign_bls12381;
 use crate::internal::sign_ed25519;
+use crate::internal::sign_secp256k1;
+use crate::internal::tls;
+use crate::internal::util::SecretBytes;
 use crate::internal::wire::*;
 use crate::internal::x25519;
 use futures::{future::FutureExt, stream::StreamExt};
 
-#[allow(clippy::single_match)]
+/// Shortest and longest wait between reconnection attempts.
+const RECONNECT_DELAY: (std::time::Duration, std::time::Duration) = (
+    std::time::Duration::from_millis(100),
+    std::time::Duration::from_secs(10),
+);
+
 pub(crate) async fn spawn_client_ipc(
     config: Arc<Config>,
     evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
 ) -> LairResult<ghost_actor::GhostSender<LairClientApi>> {
-    let (kill_switch, ipc_send, mut ipc_recv) =
+    let registered: Arc<Registered> = Default::default();
+    let con =
+        connect(config.clone(), evt_send.clone(), registered.clone()).await?;
+    let kill_switch = con.kill_switch.weak();
+    let con = Arc::new(std::sync::Mutex::new(con));
+    reconnect_on_kill(
+        &kill_switch,
+        config,
+        evt_send,
+        registered.clone(),
+        Arc::downgrade(&con),
+    )
+    .await;
+
+    let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+
+    let sender = builder
+        .channel_factory()
+        .create_channel::<LairClientApi>()
+        .await?;
+
+    err_spawn("client-ipc-actor", async move {
+        builder
+            .spawn(Internal { con, registered })
+            .await
+            .map_err(LairError::other)
+    });
+
+    Ok(sender)
+}
+
+/// What the client registered for, it registers again on reconnecting.
+#[derive(Default)]
+struct Registered {
+    store_events: std::sync::atomic::AtomicBool,
+    passphrase_provider: std::sync::atomic::AtomicBool,
+    signing_approver: std::sync::atomic::AtomicBool,
+}
+
+/// Once the connection breaks, reconnect, unless the client is gone.
+async fn reconnect_on_kill(
+    kill_switch: &KillSwitch,
+    config: Arc<Config>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    registered: Arc<Registered>,
+    con: std::sync::Weak<std::sync::Mutex<Connection>>,
+) {
+    kill_switch
+        .register_kill_callback(Box::new(move || {
+            Box::pin(async move {
+                err_spawn(
+                    "client-ipc-reconnect",
+                    reconnect(config, evt_send, registered, con),
+                );
+            })
+        }))
+        .await;
+}
+
+/// Connect again, backing off exponentially, then hand the connection to
+/// the client and let the application know.
+fn reconnect(
+    config: Arc<Config>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    registered: Arc<Registered>,
+    con: std::sync::Weak<std::sync::Mutex<Connection>>,
+) -> futures::future::BoxFuture<'static, LairResult<()>> {
+    async move { reconnect_loop(config, evt_send, registered, con).await }
+        .boxed()
+}
+
+async fn reconnect_loop(
+    config: Arc<Config>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    registered: Arc<Registered>,
+    con: std::sync::Weak<std::sync::Mutex<Connection>>,
+) -> LairResult<()> {
+    let mut delay = RECONNECT_DELAY.0;
+    loop {
+        tokio::time::sleep(delay).await;
+        if con.strong_count() == 0 {
+            // the client is gone
+            return Ok(());
+        }
+        match connect(config.clone(), evt_send.clone(), registered.clone())
+            .await
+        {
+            Ok(new_con) => {
+                let kill_switch = new_con.kill_switch.weak();
+                match con.upgrade() {
+                    Some(con) => *con.lock().unwrap() = new_con,
+                    None => return Ok(()),
+                }
+                reconnect_on_kill(
+                    &kill_switch,
+                    config,
+                    evt_send.clone(),
+                    registered,
+                    con,
+                )
+                .await;
+                trace!("reconnected");
+                evt_send.reconnected().await?;
+                return Ok(());
+            }
+            Err(err) => {
+                trace!(?err, ?delay, "reconnect failed");
+                delay = std::cmp::min(delay * 2, RECONNECT_DELAY.1);
+            }
+        }
+    }
+}
+
+/// Connect to the server, identifying, authenticating and registering for
+/// store events, passphrase and signing approval requests if need be,
+/// and forward the events of the connection to `evt_send`.
+#[allow(clippy::single_match)]
+async fn connect(
+    config: Arc<Config>,
+    evt_send: futures::channel::mpsc::Sender<LairClientEvent>,
+    registered: Arc<Registered>,
+) -> LairResult<Connection> {
+    // picked up from the lair dir, so clients need no extra setup
+    let token = auth_token::read_token(&config)?;
+    if token.is_none() && config.get_tcp_bind().is_some() {
+        return Err(format!(
+            "tcp connections need the connection token, found none at {}",
+            config.get_token_path().display()
+        )
+        .into());
+    }
+
+    let scope = config.get_connection_scope();
+    let ping_interval = config.get_ping_interval();
+    let client_name = config.get_client_name().map(|n| n.to_string());
+
+    let (kill_switch, ipc_send, mut ipc_recv, protocol) =
         spawn_ipc_connection(config).await?;
 
     let evt_kill_switch = kill_switch.clone();
@@ -27,9 +178,18 @@ pub(crate) async fn spawn_client_ipc(
         {
             match msg {
                 IpcWireApi::Request { respond, msg, .. } => match msg {
-                    LairWire::ToCliRequestUnlockPassphrase { msg_id } => {
+                    LairWire::ToCliRequestUnlockPassphrase {
+                        msg_id,
+                        last_passphrase_failed,
+                        attempt,
+                        max_attempts,
+                    } => {
                         let res = evt_kill_switch.mix(evt_send
-                                .request_unlock_passphrase()).await
+                                .request_unlock_passphrase(
+                                    last_passphrase_failed,
+                                    attempt,
+                                    max_attempts,
+                                )).await
                                 .map(|passphrase| {
                                     LairWire::ToLairRequestUnlockPassphraseResponse {
                                         msg_id,
@@ -38,6 +198,73 @@ pub(crate) async fn spawn_client_ipc(
                                 });
                         respond.respond(Ok(async move { res }.boxed().into()));
                     }
+                    LairWire::ToCliEntryCreated {
+                        msg_id,
+                        keystore_index,
+                        lair_entry_type,
+                    } => {
+                        let res = evt_kill_switch
+                            .mix(
+                                evt_send.entry_created(
+                                    keystore_index,
+                                    lair_entry_type,
+                                ),
+                            )
+                            .await
+                            .map(|()| LairWire::ToLairEntryCreatedResponse {
+                                msg_id,
+                            });
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairWire::ToCliEntryDeleted {
+                        msg_id,
+                        keystore_index,
+                    } => {
+                        let res = evt_kill_switch
+                            .mix(evt_send.entry_deleted(keystore_index))
+                            .await
+                            .map(|()| LairWire::ToLairEntryDeletedResponse {
+                                msg_id,
+                            });
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairWire::ToCliLocked { msg_id } => {
+                        let res =
+                            evt_kill_switch.mix(evt_send.locked()).await.map(
+                                |()| LairWire::ToLairLockedResponse { msg_id },
+                            );
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairWire::ToCliUnlocked { msg_id } => {
+                        let res = evt_kill_switch
+                            .mix(evt_send.unlocked())
+                            .await
+                            .map(|()| LairWire::ToLairUnlockedResponse {
+                                msg_id,
+                            });
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
+                    LairWire::ToCliRequestSigningApproval {
+                        msg_id,
+                        keystore_index,
+                        client_id,
+                        message_digest,
+                    } => {
+                        let res = evt_kill_switch
+                            .mix(evt_send.request_signing_approval(
+                                keystore_index,
+                                client_id,
+                                message_digest,
+                            ))
+                            .await
+                            .map(|approved| {
+                                LairWire::ToLairRequestSigningApprovalResponse {
+                                    msg_id,
+                                    approved,
+                                }
+                            });
+                        respond.respond(Ok(async move { res }.boxed().into()));
+                    }
                     _ => (),
                 },
             }
@@ -45,41 +272,215 @@ pub(crate) async fn spawn_client_ipc(
         Ok(())
     });
 
-    let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
+    if let Some(client_name) = client_name {
+        // servers that predate client names log us as anonymous
+        if protocol.has_feature(feature::CLIENT_NAME) {
+            match kill_switch
+                .mix(ipc_send.request(LairWire::ToLairIdentify {
+                    msg_id: next_msg_id(),
+                    client_name,
+                }))
+                .await?
+            {
+                LairWire::ToCliIdentifyResponse { .. } => (),
+                o => return Err(LairError::unexpected(o)),
+            }
+        }
+    }
 
-    let sender = builder
-        .channel_factory()
-        .create_channel::<LairClientApi>()
-        .await?;
+    // without a token, connections are only scoped when asked
+    if token.is_some() || scope < ConnectionScope::Full {
+        if !protocol.has_feature(feature::CONNECTION_TOKEN) {
+            return Err(
+                "the lair-keystore does not support connection tokens".into()
+            );
+        }
+        match kill_switch
+            .mix(ipc_send.request(LairWire::ToLairAuthenticate {
+                msg_id: next_msg_id(),
+                token: token.unwrap_or_default(),
+                scope,
+            }))
+            .await?
+        {
+            LairWire::ToCliAuthenticateResponse { scope, .. } => {
+                trace!(%scope, "connection scope");
+            }
+            o => return Err(LairError::unexpected(o)),
+        }
+    }
 
-    let kill_sender = sender.clone();
-    kill_switch
-        .register_kill_callback(Box::new(move || {
-            Box::pin(async move {
-                use ghost_actor::GhostControlSender;
-                if let Err(err) = kill_sender.ghost_actor_shutdown().await {
-                    ghost_actor::dependencies::tracing::error!(?err);
-                }
-            })
-        }))
-        .await;
+    if registered
+        .store_events
+        .load(std::sync::atomic::Ordering::SeqCst)
+    {
+        match kill_switch
+            .mix(ipc_send.request(LairWire::ToLairLairSubscribeStoreEvents {
+                msg_id: next_msg_id(),
+            }))
+            .await?
+        {
+            LairWire::ToCliLairSubscribeStoreEventsResponse { .. } => (),
+            o => return Err(LairError::unexpected(o)),
+        }
+    }
 
-    err_spawn("client-ipc-actor", async move {
-        builder
-            .spawn(Internal {
-                kill_switch,
-                ipc_send,
-            })
-            .await
-            .map_err(LairError::other)
-    });
+    if registered
+        .passphrase_provider
+        .load(std::sync::atomic::Ordering::SeqCst)
+    {
+        match kill_switch
+            .mix(ipc_send.request(
+                LairWire::ToLairLairRegisterPassphraseProvider {
+                    msg_id: next_msg_id(),
+                },
+            ))
+            .await?
+        {
+            LairWire::ToCliLairRegisterPassphraseProviderResponse {
+                ..
+            } => (),
+            o => return Err(LairError::unexpected(o)),
+        }
+    }
 
-    Ok(sender)
+    if registered
+        .signing_approver
+        .load(std::sync::atomic::Ordering::SeqCst)
+    {
+        match kill_switch
+            .mix(ipc_send.request(
+                LairWire::ToLairLairRegisterSigningApprover {
+                    msg_id: next_msg_id(),
+                },
+            ))
+            .await?
+        {
+            LairWire::ToCliLairRegisterSigningApproverResponse { .. } => (),
+            o => return Err(LairError::unexpected(o)),
+        }
+    }
+
+    let activity = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    if let Some(interval) = ping_interval {
+        if protocol.has_feature(feature::PING) {
+            spawn_keepalive(
+                kill_switch.weak(),
+                ipc_send.clone(),
+                activity.clone(),
+                interval,
+            );
+        }
+    }
+
+    Ok(Connection {
+        kill_switch,
+        ipc_send,
+        activity,
+    })
 }
 
-struct Internal {
+/// Ping the server whenever the connection has been idle for `interval`,
+/// and kill the connection, so the client reconnects, once two pings in
+/// a row go unanswered for `interval`.
+fn spawn_keepalive(
+    kill_switch: KillSwitch,
+    ipc_send: IpcSender,
+    activity: Arc<std::sync::atomic::AtomicU64>,
+    interval: std::time::Duration,
+) {
+    err_spawn("client-ipc-keepalive", async move {
+        let mut seen = activity.load(std::sync::atomic::Ordering::SeqCst);
+        let mut missed = 0;
+        loop {
+            if missed == 0 {
+                kill_switch
+                    .mix(async {
+                        tokio::time::sleep(interval).await;
+                        Ok(())
+                    })
+                    .await?;
+                let now = activity.load(std::sync::atomic::Ordering::SeqCst);
+                if now != seen {
+                    // responses came in, it is alive
+                    seen = now;
+                    continue;
+                }
+            }
+            let nonce = next_msg_id();
+            let ping = ipc_send.request(LairWire::ToLairLairPing {
+                msg_id: next_msg_id(),
+                nonce,
+            });
+            match kill_switch
+                .mix(async { Ok(tokio::time::timeout(interval, ping).await) })
+                .await?
+            {
+                Ok(Ok(LairWire::ToCliLairPingResponse {
+                    nonce: pong, ..
+                })) if pong == nonce => missed = 0,
+                res => {
+                    missed += 1;
+                    trace!(?res, missed, "ping unanswered");
+                    if missed >= 2 {
+                        warn!("lair-keystore connection unresponsive");
+                        kill_switch.kill();
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// The current connection to the server.
+struct Connection {
     kill_switch: KillSwitch,
     ipc_send: IpcSender,
+    /// counts responses, so idle connections can be told apart
+    activity: Arc<std::sync::atomic::AtomicU64>,
+}
+
+struct Internal {
+    con: Arc<std::sync::Mutex<Connection>>,
+    registered: Arc<Registered>,
+}
+
+impl Internal {
+    /// Make a request over the current connection, failing with
+    /// `LairError::Disconnected` if the connection breaks first.
+    fn request(
+        &self,
+        msg: LairWire,
+    ) -> impl std::future::Future<Output = LairResult<LairWire>> + 'static {
+        let con = self.con.lock().unwrap();
+        let fut = con.kill_switch.mix_static(con.ipc_send.request(msg));
+        let weak_kill_switch = con.kill_switch.weak();
+        let activity = con.activity.clone();
+        async move {
+            let res = fut.await.map_err(|err| {
+                if weak_kill_switch.cont() {
+                    err
+                } else {
+                    LairError::Disconnected
+                }
+            });
+            if res.is_ok() {
+                activity.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            res
+        }
+    }
+}
+
+impl Drop for Internal {
+    fn drop(&mut self) {
+        // hang up once the client is shut down, the connection tasks
+        // would keep it open otherwise
+        if let Ok(con) = self.con.lock() {
+            con.kill_switch.kill();
+        }
+    }
 }
 
 impl ghost_actor::GhostControlHandler for Internal {}
@@ -90,11 +491,9 @@ impl LairClientApiHandler for Internal {
     fn handle_lair_get_server_info(
         &mut self,
     ) -> LairClientApiHandlerResult<LairServerInfo> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairLairGetServerInfo {
-                msg_id: next_msg_id(),
-            },
-        ));
+        let fut = self.request(LairWire::ToLairLairGetServerInfo {
+            msg_id: next_msg_id(),
+        });
         Ok(async move {
             trace!("awaiting server info");
             match fut.await? {
@@ -102,7 +501,117 @@ impl LairClientApiHandler for Internal {
                     trace!(?info, "GOT SERVER INFO");
                     Ok(info)
                 }
-                o => Err(format!("unexpected: {:?}", o).into()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_ping(
+        &mut self,
+        nonce: u64,
+    ) -> LairClientApiHandlerResult<u64> {
+        let fut = self.request(LairWire::ToLairLairPing {
+            msg_id: next_msg_id(),
+            nonce,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairPingResponse { nonce, .. } => Ok(nonce),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_subscribe_store_events(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairSubscribeStoreEvents {
+            msg_id: next_msg_id(),
+        });
+        let registered = self.registered.clone();
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairSubscribeStoreEventsResponse { .. } => {
+                    // and again on reconnecting
+                    registered
+                        .store_events
+                        .store(true, std::sync::atomic::Ordering::SeqCst);
+                    Ok(())
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_lock_state(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairLockState> {
+        let fut = self.request(LairWire::ToLairLairGetLockState {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetLockStateResponse {
+                    lock_state, ..
+                } => Ok(lock_state),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_register_passphrase_provider(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut =
+            self.request(LairWire::ToLairLairRegisterPassphraseProvider {
+                msg_id: next_msg_id(),
+            });
+        let registered = self.registered.clone();
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairRegisterPassphraseProviderResponse {
+                    ..
+                } => {
+                    // and again on reconnecting
+                    registered
+                        .passphrase_provider
+                        .store(true, std::sync::atomic::Ordering::SeqCst);
+                    Ok(())
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_register_signing_approver(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairRegisterSigningApprover {
+            msg_id: next_msg_id(),
+        });
+        let registered = self.registered.clone();
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairRegisterSigningApproverResponse {
+                    ..
+                } => {
+                    // and again on reconnecting
+                    registered
+                        .signing_approver
+                        .store(true, std::sync::atomic::Ordering::SeqCst);
+                    Ok(())
+                }
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
@@ -112,470 +621,1889 @@ impl LairClientApiHandler for Internal {
     fn handle_lair_get_last_entry_index(
         &mut self,
     ) -> LairClientApiHandlerResult<KeystoreIndex> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairLairGetLastEntryIndex {
-                msg_id: next_msg_id(),
-            },
-        ));
+        let fut = self.request(LairWire::ToLairLairGetLastEntryIndex {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetLastEntryIndexResponse {
+                    last_keystore_index,
+                    ..
+                } => Ok(last_keystore_index),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_type(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<LairEntryType> {
+        let fut = self.request(LairWire::ToLairLairGetEntryType {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetEntryTypeResponse {
+                    lair_entry_type,
+                    ..
+                } => Ok(lair_entry_type),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_list_entries(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<EntryListItem>> {
+        let fut = self.request(LairWire::ToLairLairListEntries {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairListEntriesResponse { entries, .. } => {
+                    Ok(entries)
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)> {
+        let fut = self.request(LairWire::ToLairLairGetEntryByTag {
+            msg_id: next_msg_id(),
+            tag,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetEntryByTagResponse {
+                    keystore_index,
+                    lair_entry_type,
+                    ..
+                } => Ok((keystore_index, lair_entry_type)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_get_entry_meta(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<entry::EntryMeta> {
+        let fut = self.request(LairWire::ToLairLairGetEntryMeta {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairGetEntryMetaResponse {
+                    entry_meta, ..
+                } => Ok(entry_meta),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_find_entry(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<(KeystoreIndex, LairEntryType)>>
+    {
+        let fut = self.request(LairWire::ToLairLairFindEntry {
+            msg_id: next_msg_id(),
+            id,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairFindEntryResponse { entry, .. } => Ok(entry),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_update_entry_tag(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: String,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairUpdateEntryTag {
+            msg_id: next_msg_id(),
+            keystore_index,
+            tag,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairUpdateEntryTagResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_set_entry_rate_limit(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        rate_limit: Option<u32>,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairSetEntryRateLimit {
+            msg_id: next_msg_id(),
+            keystore_index,
+            rate_limit,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairSetEntryRateLimitResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_export_entry_public(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<EntryPublic> {
+        let fut = self.request(LairWire::ToLairLairExportEntryPublic {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairExportEntryPublicResponse {
+                    entry_public,
+                    ..
+                } => Ok(entry_public),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_delete_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairDeleteEntry {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairDeleteEntryResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_compact_store(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairCompactInfo> {
+        let fut = self.request(LairWire::ToLairLairCompactStore {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairCompactStoreResponse { info, .. } => {
+                    Ok(info)
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_shutdown(&mut self) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairShutdown {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairShutdownResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_lock(&mut self) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairLock {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairLockResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_unlock(
+        &mut self,
+        passphrase: SecretBytes,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairUnlock {
+            msg_id: next_msg_id(),
+            passphrase,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairUnlockResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_lair_change_unlock_passphrase(
+        &mut self,
+        old_passphrase: String,
+        new_passphrase: String,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairLairChangeUnlockPassphrase {
+            msg_id: next_msg_id(),
+            old_passphrase,
+            new_passphrase,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliLairChangeUnlockPassphraseResponse {
+                    ..
+                } => Ok(()),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_self_signed_from_entropy(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        tls::check_tls_cert_options(&options)?;
+        let fut =
+            self.request(LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
+                msg_id: next_msg_id(),
+                cert_alg: options.alg,
+                digest_alg: options.digest_alg,
+                exportable: options.exportable,
+                sni: options.sni,
+                alt_names: options.alt_names,
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_self_signed_tagged(
+        &mut self,
+        options: TlsCertOptions,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        tls::check_tls_cert_options(&options)?;
+        let fut = self.request(LairWire::ToLairTlsCertNewSelfSignedTagged {
+            msg_id: next_msg_id(),
+            cert_alg: options.alg,
+            digest_alg: options.digest_alg,
+            exportable: options.exportable,
+            sni: options.sni,
+            alt_names: options.alt_names,
+            tag,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertNewSelfSignedTaggedResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_renew(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        tls::check_tls_cert_options(&options)?;
+        let fut = self.request(LairWire::ToLairTlsCertRenew {
+            msg_id: next_msg_id(),
+            keystore_index,
+            digest_alg: options.digest_alg,
+            exportable: options.exportable,
+            sni: options.sni,
+            alt_names: options.alt_names,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertRenewResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_import(
+        &mut self,
+        cert_der: Cert,
+        priv_key_der: CertPrivKey,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        tls::check_tls_cert_import_options(&options)?;
+        let fut = self.request(LairWire::ToLairTlsCertImport {
+            msg_id: next_msg_id(),
+            cert_der,
+            priv_key_der,
+            sni: options.sni,
+            digest_alg: options.digest_alg,
+            exportable: options.exportable,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertImportResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_ca_new_self_signed(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        tls::check_tls_cert_options(&options)?;
+        let fut = self.request(LairWire::ToLairTlsCaNewSelfSigned {
+            msg_id: next_msg_id(),
+            cert_alg: options.alg,
+            digest_alg: options.digest_alg,
+            exportable: options.exportable,
+            sni: options.sni,
+            alt_names: options.alt_names,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCaNewSelfSignedResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_signed_by_ca(
+        &mut self,
+        ca_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        CertSni,
+        CertDigest,
+        Vec<Cert>,
+    )> {
+        tls::check_tls_cert_options(&options)?;
+        let fut = self.request(LairWire::ToLairTlsCertNewSignedByCa {
+            msg_id: next_msg_id(),
+            ca_index,
+            cert_alg: options.alg,
+            digest_alg: options.digest_alg,
+            exportable: options.exportable,
+            sni: options.sni,
+            alt_names: options.alt_names,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertNewSignedByCaResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    cert_chain,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest, cert_chain)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_find_by_digest_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        if prefix.is_empty() || prefix.len() > 32 {
+            return Err("cert digest prefix must be 1 to 32 bytes".into());
+        }
+        let fut = self.request(LairWire::ToLairTlsCertFindByDigestPrefix {
+            msg_id: next_msg_id(),
+            prefix,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertFindByDigestPrefixResponse {
+                    keystore_index,
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((keystore_index, cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
+        let fut = self.request(LairWire::ToLairTlsCertGet {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetResponse {
+                    cert_sni,
+                    cert_digest,
+                    ..
+                } => Ok((cert_sni, cert_digest)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.request(LairWire::ToLairTlsCertGetCertByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertByIndexResponse {
+                    cert, ..
+                } => Ok(cert),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.request(LairWire::ToLairTlsCertGetCertByDigest {
+            msg_id: next_msg_id(),
+            cert_digest,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertByDigestResponse {
+                    cert, ..
+                } => Ok(cert),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_cert_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<Cert> {
+        let fut = self.request(LairWire::ToLairTlsCertGetCertBySni {
+            msg_id: next_msg_id(),
+            cert_sni,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetCertBySniResponse { cert, .. } => {
+                    Ok(cert)
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.request(LairWire::ToLairTlsCertGetPrivKeyByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
+                    cert_priv_key,
+                    ..
+                } => Ok(cert_priv_key),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_digest(
+        &mut self,
+        cert_digest: CertDigest,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.request(LairWire::ToLairTlsCertGetPrivKeyByDigest {
+            msg_id: next_msg_id(),
+            cert_digest,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
+                    cert_priv_key,
+                    ..
+                } => Ok(cert_priv_key),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_get_priv_key_by_sni(
+        &mut self,
+        cert_sni: CertSni,
+    ) -> LairClientApiHandlerResult<CertPrivKey> {
+        let fut = self.request(LairWire::ToLairTlsCertGetPrivKeyBySni {
+            msg_id: next_msg_id(),
+            cert_sni,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
+                    cert_priv_key,
+                    ..
+                } => Ok(cert_priv_key),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_export_pkcs12(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        password: Option<String>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        pkcs12::check_password(&password)?;
+        let fut = self.request(LairWire::ToLairTlsCertExportPkcs12 {
+            msg_id: next_msg_id(),
+            keystore_index,
+            password,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertExportPkcs12Response {
+                    pkcs12, ..
+                } => Ok(pkcs12),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        scheme: TlsCertSignatureScheme,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = self.request(LairWire::ToLairTlsCertSignByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            scheme,
+            message,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliTlsCertSignByIndexResponse {
+                    signature, ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let fut = self.request(LairWire::ToLairSignEd25519NewFromEntropy {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_tagged(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let fut =
+            self.request(LairWire::ToLairSignEd25519NewFromEntropyTagged {
+                msg_id: next_msg_id(),
+                tag,
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519NewFromEntropyTaggedResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_requiring_approval(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let fut = self.request(
+            LairWire::ToLairSignEd25519NewFromEntropyRequiringApproval {
+                msg_id: next_msg_id(),
+            },
+        );
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519NewFromEntropyRequiringApprovalResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_seed(
+        &mut self,
+        seed: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        if seed.len() != 32 {
+            return Err(LairError::SignEd25519SeedLength(seed.len()));
+        }
+        let fut = self.request(LairWire::ToLairSignEd25519NewFromSeed {
+            msg_id: next_msg_id(),
+            seed,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519NewFromSeedResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519PubKey> {
+        let fut = self.request(LairWire::ToLairSignEd25519Get {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519GetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let fut = self.request(LairWire::ToLairSignEd25519SignByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            message,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let fut = self.request(LairWire::ToLairSignEd25519SignByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            message,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByPubKeyResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_index_with_context(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        sign_ed25519::check_context(&context)?;
+        let fut =
+            self.request(LairWire::ToLairSignEd25519SignByIndexWithContext {
+                msg_id: next_msg_id(),
+                keystore_index,
+                context,
+                message,
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByIndexWithContextResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key_with_context(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        sign_ed25519::check_context(&context)?;
+        let fut =
+            self.request(LairWire::ToLairSignEd25519SignByPubKeyWithContext {
+                msg_id: next_msg_id(),
+                pub_key,
+                context,
+                message,
+            });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByPubKeyWithContextResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_batch_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        messages: Vec<bytes::Bytes>,
+    ) -> LairClientApiHandlerResult<Vec<sign_ed25519::SignEd25519Signature>>
+    {
+        let fut = self.request(LairWire::ToLairSignEd25519SignBatchByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            messages,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignBatchByIndexResponse {
+                    signatures,
+                    ..
+                } => Ok(signatures),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_batch_by_pub_key(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        messages: Vec<bytes::Bytes>,
+    ) -> LairClientApiHandlerResult<Vec<sign_ed25519::SignEd25519Signature>>
+    {
+        let fut = self.request(LairWire::ToLairSignEd25519SignBatchByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            messages,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignBatchByPubKeyResponse {
+                    signatures,
+                    ..
+                } => Ok(signatures),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_prehashed(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let fut = self.request(LairWire::ToLairSignEd25519SignPrehashed {
+            msg_id: next_msg_id(),
+            keystore_index,
+            digest,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignPrehashedResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_verify(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        message: bytes::Bytes,
+        signature: sign_ed25519::SignEd25519Signature,
+    ) -> LairClientApiHandlerResult<bool> {
+        // these could never verify, and would fail to encode
+        if pub_key.len() != 32 || signature.len() != 64 {
+            return Ok(async move { Ok(false) }.boxed().into());
+        }
+        let fut = self.request(LairWire::ToLairSignEd25519Verify {
+            msg_id: next_msg_id(),
+            pub_key,
+            message,
+            signature,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519VerifyResponse { valid, .. } => {
+                    Ok(valid)
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_tag(
+        &mut self,
+        tag: String,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let fut = self.request(LairWire::ToLairSignEd25519SignByTag {
+            msg_id: next_msg_id(),
+            tag,
+            message,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519SignByTagResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_list_pub_keys(
+        &mut self,
+    ) -> LairClientApiHandlerResult<
+        Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>,
+    > {
+        let fut = self.request(LairWire::ToLairSignEd25519ListPubKeys {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519ListPubKeysResponse {
+                    pub_keys,
+                    ..
+                } => Ok(pub_keys),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_secp256k1::SignSecp256k1PubKey,
+    )> {
+        let fut = self.request(LairWire::ToLairSecp256k1NewFromEntropy {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSecp256k1NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1PubKey> {
+        let fut = self.request(LairWire::ToLairSecp256k1Get {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSecp256k1GetResponse { pub_key, .. } => {
+                    Ok(pub_key)
+                }
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1Signature>
+    {
+        let fut = self.request(LairWire::ToLairSecp256k1SignByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            digest,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSecp256k1SignByIndexResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_secp256k1::SignSecp256k1PubKey,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1Signature>
+    {
+        if pub_key.len() != sign_secp256k1::PUB_KEY_BYTES {
+            return Err("invalid secp256k1 pub key".into());
+        }
+        let fut = self.request(LairWire::ToLairSecp256k1SignByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            digest,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSecp256k1SignByPubKeyResponse {
+                    signature,
+                    ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_bls_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_bls12381::SignBls12381PubKey,
+    )> {
+        let fut = self.request(LairWire::ToLairBlsNewFromEntropy {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliBlsNewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_bls_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_bls12381::SignBls12381PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<sign_bls12381::SignBls12381Signature> {
+        if pub_key.len() != sign_bls12381::PUB_KEY_BYTES {
+            return Err("invalid bls12-381 pub key".into());
+        }
+        let fut = self.request(LairWire::ToLairBlsSignByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            message,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliBlsSignByPubKeyResponse {
+                    signature, ..
+                } => Ok(signature),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_new_from_entropy(
+        &mut self,
+        exportable: bool,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)> {
+        let fut = self.request(LairWire::ToLairSeedNewFromEntropy {
+            msg_id: next_msg_id(),
+            exportable,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSeedNewFromEntropyResponse {
+                    keystore_index,
+                    seed_id,
+                    ..
+                } => Ok((keystore_index, seed_id)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_derive_from_seed(
+        &mut self,
+        seed_index: KeystoreIndex,
+        derivation_path: Vec<u32>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        seed::check_derivation_path(&derivation_path)?;
+        let fut = self.request(LairWire::ToLairSignEd25519DeriveFromSeed {
+            msg_id: next_msg_id(),
+            seed_index,
+            derivation_path,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519DeriveFromSeedResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_export_mnemonic(
+        &mut self,
+        seed_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<String> {
+        let fut = self.request(LairWire::ToLairSeedExportMnemonic {
+            msg_id: next_msg_id(),
+            seed_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSeedExportMnemonicResponse {
+                    mnemonic, ..
+                } => Ok(mnemonic),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_import_mnemonic(
+        &mut self,
+        mnemonic: String,
+        exportable: bool,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)> {
+        // validate and normalize locally, a bad mnemonic never goes out
+        let mnemonic = {
+            let mnemonic = zeroize::Zeroizing::new(mnemonic);
+            seed::seed_to_mnemonic(&seed::seed_from_mnemonic(&mnemonic)?)?
+        };
+        let fut = self.request(LairWire::ToLairSeedImportMnemonic {
+            msg_id: next_msg_id(),
+            mnemonic,
+            exportable,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSeedImportMnemonicResponse {
+                    keystore_index,
+                    seed_id,
+                    ..
+                } => Ok((keystore_index, seed_id)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_to_x25519(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        let fut = self.request(LairWire::ToLairSignEd25519ToX25519 {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliSignEd25519ToX25519Response {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        let fut = self.request(LairWire::ToLairX25519NewFromEntropy {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliX25519NewFromEntropyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_new_from_priv_key(
+        &mut self,
+        priv_key: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        if priv_key.len() != x25519::PRIV_KEY_BYTES {
+            return Err(LairError::X25519PrivKeyImportLength(priv_key.len()));
+        }
+        let fut = self.request(LairWire::ToLairX25519NewFromPrivKey {
+            msg_id: next_msg_id(),
+            priv_key,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliX25519NewFromPrivKeyResponse {
+                    keystore_index,
+                    pub_key,
+                    ..
+                } => Ok((keystore_index, pub_key)),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<x25519::X25519PubKey> {
+        let fut = self.request(LairWire::ToLairX25519Get {
+            msg_id: next_msg_id(),
+            keystore_index,
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliX25519GetResponse { pub_key, .. } => Ok(pub_key),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_x25519_list_pub_keys(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, x25519::X25519PubKey)>>
+    {
+        let fut = self.request(LairWire::ToLairX25519ListPubKeys {
+            msg_id: next_msg_id(),
+        });
+        Ok(async move {
+            match fut.await? {
+                LairWire::ToCliX25519ListPubKeysResponse {
+                    pub_keys, ..
+                } => Ok(pub_keys),
+                o => Err(LairError::unexpected(o)),
+            }
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_kx_session_keys_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        remote_pub: x25519::X25519PubKey,
+        is_server: bool,
+    ) -> LairClientApiHandlerResult<(kx::KxSessionKey, kx::KxSessionKey)> {
+        let fut = self.request(LairWire::ToLairKxSessionKeysByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            remote_pub,
+            is_server,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliLairGetLastEntryIndexResponse {
-                    last_keystore_index,
-                    ..
-                } => Ok(last_keystore_index),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliKxSessionKeysByIndexResponse {
+                    rx, tx, ..
+                } => Ok((rx, tx)),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_lair_get_entry_type(
+    fn handle_crypto_box_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<LairEntryType> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairLairGetEntryType {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+        recipient: x25519::X25519PubKey,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let fut = self.request(LairWire::ToLairCryptoBoxByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            recipient,
+            data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliLairGetEntryTypeResponse {
-                    lair_entry_type,
+                LairWire::ToCliCryptoBoxByIndexResponse {
+                    encrypted_data,
                     ..
-                } => Ok(lair_entry_type),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(encrypted_data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_new_self_signed_from_entropy(
+    fn handle_crypto_box_by_pub_key(
         &mut self,
-        options: TlsCertOptions,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertNewSelfSignedFromEntropy {
-                msg_id: next_msg_id(),
-                cert_alg: options.alg,
-            },
-        ));
+        pub_key: x25519::X25519PubKey,
+        recipient: x25519::X25519PubKey,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let fut = self.request(LairWire::ToLairCryptoBoxByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            recipient,
+            data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertNewSelfSignedFromEntropyResponse {
-                    keystore_index,
-                    cert_sni,
-                    cert_digest,
+                LairWire::ToCliCryptoBoxByPubKeyResponse {
+                    encrypted_data,
                     ..
-                } => Ok((keystore_index, cert_sni, cert_digest)),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(encrypted_data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get(
+    fn handle_crypto_box_open_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<(CertSni, CertDigest)> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGet {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+        sender: x25519::X25519PubKey,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let fut = self.request(LairWire::ToLairCryptoBoxOpenByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            sender,
+            encrypted_data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetResponse {
-                    cert_sni,
-                    cert_digest,
-                    ..
-                } => Ok((cert_sni, cert_digest)),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliCryptoBoxOpenByIndexResponse {
+                    data, ..
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_index(
+    fn handle_crypto_box_open_by_pub_key(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetCertByIndex {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+        pub_key: x25519::X25519PubKey,
+        sender: x25519::X25519PubKey,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let fut = self.request(LairWire::ToLairCryptoBoxOpenByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            sender,
+            encrypted_data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetCertByIndexResponse {
-                    cert, ..
-                } => Ok(cert),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliCryptoBoxOpenByPubKeyResponse {
+                    data, ..
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_digest(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_by_index_with_aad(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetCertByDigest {
-                msg_id: next_msg_id(),
-                cert_digest,
-            },
-        ));
+        keystore_index: KeystoreIndex,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let fut = self.request(LairWire::ToLairCryptoBoxByIndexWithAad {
+            msg_id: next_msg_id(),
+            keystore_index,
+            recipient,
+            aad,
+            data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetCertByDigestResponse {
-                    cert, ..
-                } => Ok(cert),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliCryptoBoxByIndexWithAadResponse {
+                    encrypted_data,
+                    ..
+                } => Ok(encrypted_data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_cert_by_sni(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_by_pub_key_with_aad(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<Cert> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetCertBySni {
-                msg_id: next_msg_id(),
-                cert_sni,
-            },
-        ));
+        pub_key: x25519::X25519PubKey,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let fut = self.request(LairWire::ToLairCryptoBoxByPubKeyWithAad {
+            msg_id: next_msg_id(),
+            pub_key,
+            recipient,
+            aad,
+            data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetCertBySniResponse { cert, .. } => {
-                    Ok(cert)
-                }
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliCryptoBoxByPubKeyWithAadResponse {
+                    encrypted_data,
+                    ..
+                } => Ok(encrypted_data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_index(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_open_by_index_with_aad(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetPrivKeyByIndex {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let fut = self.request(LairWire::ToLairCryptoBoxOpenByIndexWithAad {
+            msg_id: next_msg_id(),
+            keystore_index,
+            sender,
+            aad,
+            encrypted_data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetPrivKeyByIndexResponse {
-                    cert_priv_key,
+                LairWire::ToCliCryptoBoxOpenByIndexWithAadResponse {
+                    data,
                     ..
-                } => Ok(cert_priv_key),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_digest(
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_open_by_pub_key_with_aad(
         &mut self,
-        cert_digest: CertDigest,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetPrivKeyByDigest {
-                msg_id: next_msg_id(),
-                cert_digest,
-            },
-        ));
+        pub_key: x25519::X25519PubKey,
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let fut = self.request(LairWire::ToLairCryptoBoxOpenByPubKeyWithAad {
+            msg_id: next_msg_id(),
+            pub_key,
+            sender,
+            aad,
+            encrypted_data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetPrivKeyByDigestResponse {
-                    cert_priv_key,
+                LairWire::ToCliCryptoBoxOpenByPubKeyWithAadResponse {
+                    data,
                     ..
-                } => Ok(cert_priv_key),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_tls_cert_get_priv_key_by_sni(
+    fn handle_crypto_box_seal_open_by_index(
         &mut self,
-        cert_sni: CertSni,
-    ) -> LairClientApiHandlerResult<CertPrivKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairTlsCertGetPrivKeyBySni {
-                msg_id: next_msg_id(),
-                cert_sni,
-            },
-        ));
+        keystore_index: KeystoreIndex,
+        sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let fut = self.request(LairWire::ToLairCryptoBoxSealOpenByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            sealed_data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliTlsCertGetPrivKeyBySniResponse {
-                    cert_priv_key,
+                LairWire::ToCliCryptoBoxSealOpenByIndexResponse {
+                    data,
                     ..
-                } => Ok(cert_priv_key),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_new_from_entropy(
+    fn handle_crypto_box_seal_open_by_pub_key(
         &mut self,
-    ) -> LairClientApiHandlerResult<(
-        KeystoreIndex,
-        sign_ed25519::SignEd25519PubKey,
-    )> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519NewFromEntropy {
-                msg_id: next_msg_id(),
-            },
-        ));
+        pub_key: x25519::X25519PubKey,
+        sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let fut = self.request(LairWire::ToLairCryptoBoxSealOpenByPubKey {
+            msg_id: next_msg_id(),
+            pub_key,
+            sealed_data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliSignEd25519NewFromEntropyResponse {
-                    keystore_index,
-                    pub_key,
+                LairWire::ToCliCryptoBoxSealOpenByPubKeyResponse {
+                    data,
                     ..
-                } => Ok((keystore_index, pub_key)),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
-
-    fn handle_sign_ed25519_get(
+    fn handle_secret_new_from_entropy(
         &mut self,
-        keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519PubKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519Get {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, secret_key::SecretKeyTag)>
+    {
+        let fut = self.request(LairWire::ToLairSecretNewFromEntropy {
+            msg_id: next_msg_id(),
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliSignEd25519GetResponse { pub_key, .. } => {
-                    Ok(pub_key)
-                }
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliSecretNewFromEntropyResponse {
+                    keystore_index,
+                    tag,
+                    ..
+                } => Ok((keystore_index, tag)),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_index(
+    fn handle_secret_encrypt_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519SignByIndex {
-                msg_id: next_msg_id(),
-                keystore_index,
-                message,
-            },
-        ));
+        aad: Arc<Vec<u8>>,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(secret_key::SecretNonce, Arc<Vec<u8>>)>
+    {
+        let fut = self.request(LairWire::ToLairSecretEncryptByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            aad,
+            data,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliSignEd25519SignByIndexResponse {
-                    signature,
+                LairWire::ToCliSecretEncryptByIndexResponse {
+                    nonce,
+                    ciphertext,
                     ..
-                } => Ok(signature),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok((nonce, ciphertext)),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_sign_ed25519_sign_by_pub_key(
+    fn handle_secret_decrypt_by_index(
         &mut self,
-        pub_key: sign_ed25519::SignEd25519PubKey,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairSignEd25519SignByPubKey {
-                msg_id: next_msg_id(),
-                pub_key,
-                message,
-            },
-        ));
+        keystore_index: KeystoreIndex,
+        aad: Arc<Vec<u8>>,
+        nonce: secret_key::SecretNonce,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<Arc<Vec<u8>>>> {
+        let fut = self.request(LairWire::ToLairSecretDecryptByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            aad,
+            nonce,
+            ciphertext,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliSignEd25519SignByPubKeyResponse {
-                    signature,
-                    ..
-                } => Ok(signature),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliSecretDecryptByIndexResponse {
+                    data, ..
+                } => Ok(data),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_x25519_new_from_entropy(
+    fn handle_derive_subkey_by_index(
         &mut self,
-    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairX25519NewFromEntropy {
-                msg_id: next_msg_id(),
-            },
-        ));
+        keystore_index: KeystoreIndex,
+        subkey_len: usize,
+        context: [u8; secret_key::SUBKEY_CONTEXT_BYTES],
+        subkey_id: u64,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        secret_key::check_subkey_len(subkey_len)?;
+        let fut = self.request(LairWire::ToLairDeriveSubkeyByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            subkey_len,
+            context,
+            subkey_id,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliX25519NewFromEntropyResponse {
-                    keystore_index,
-                    pub_key,
-                    ..
-                } => Ok((keystore_index, pub_key)),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliDeriveSubkeyByIndexResponse {
+                    subkey, ..
+                } => Ok(subkey),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_x25519_get(
+    fn handle_secretstream_init_push_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-    ) -> LairClientApiHandlerResult<x25519::X25519PubKey> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairX25519Get {
-                msg_id: next_msg_id(),
-                keystore_index,
-            },
-        ));
+        remote_pub: x25519::X25519PubKey,
+    ) -> LairClientApiHandlerResult<(
+        secretstream::SecretstreamSessionId,
+        secretstream::SecretstreamHeader,
+    )> {
+        let fut = self.request(LairWire::ToLairSecretstreamInitPushByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            remote_pub,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliX25519GetResponse { pub_key, .. } => Ok(pub_key),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliSecretstreamInitPushByIndexResponse {
+                    session_id,
+                    header,
+                    ..
+                } => Ok((session_id, header)),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_by_index(
+    fn handle_secretstream_push(
         &mut self,
-        keystore_index: KeystoreIndex,
-        recipient: x25519::X25519PubKey,
-        data: Arc<crypto_box::CryptoBoxData>,
-    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairCryptoBoxByIndex {
-                msg_id: next_msg_id(),
-                keystore_index,
-                recipient,
-                data,
-            },
-        ));
+        session_id: secretstream::SecretstreamSessionId,
+        chunk: Arc<Vec<u8>>,
+        is_final: bool,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        secretstream::check_chunk_len(chunk.len())?;
+        let fut = self.request(LairWire::ToLairSecretstreamPush {
+            msg_id: next_msg_id(),
+            session_id,
+            chunk,
+            is_final,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliCryptoBoxByIndexResponse {
-                    encrypted_data,
-                    ..
-                } => Ok(encrypted_data),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliSecretstreamPushResponse {
+                    ciphertext, ..
+                } => Ok(ciphertext),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_by_pub_key(
+    fn handle_secretstream_init_pull_by_index(
         &mut self,
-        pub_key: x25519::X25519PubKey,
-        recipient: x25519::X25519PubKey,
-        data: Arc<crypto_box::CryptoBoxData>,
-    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairCryptoBoxByPubKey {
-                msg_id: next_msg_id(),
-                pub_key,
-                recipient,
-                data,
-            },
-        ));
+        keystore_index: KeystoreIndex,
+        remote_pub: x25519::X25519PubKey,
+        header: secretstream::SecretstreamHeader,
+    ) -> LairClientApiHandlerResult<secretstream::SecretstreamSessionId> {
+        let fut = self.request(LairWire::ToLairSecretstreamInitPullByIndex {
+            msg_id: next_msg_id(),
+            keystore_index,
+            remote_pub,
+            header,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliCryptoBoxByPubKeyResponse {
-                    encrypted_data,
+                LairWire::ToCliSecretstreamInitPullByIndexResponse {
+                    session_id,
                     ..
-                } => Ok(encrypted_data),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                } => Ok(session_id),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_open_by_index(
+    fn handle_secretstream_pull(
         &mut self,
-        keystore_index: KeystoreIndex,
-        sender: x25519::X25519PubKey,
-        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
-    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairCryptoBoxOpenByIndex {
-                msg_id: next_msg_id(),
-                keystore_index,
-                sender,
-                encrypted_data,
-            },
-        ));
+        session_id: secretstream::SecretstreamSessionId,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<(Arc<Vec<u8>>, bool)>> {
+        secretstream::check_ciphertext_len(ciphertext.len())?;
+        let fut = self.request(LairWire::ToLairSecretstreamPull {
+            msg_id: next_msg_id(),
+            session_id,
+            ciphertext,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliCryptoBoxOpenByIndexResponse {
-                    data, ..
-                } => Ok(data),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliSecretstreamPullResponse { data, .. } => {
+                    Ok(data)
+                }
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
         .into())
     }
 
-    fn handle_crypto_box_open_by_pub_key(
+    fn handle_secretstream_close(
         &mut self,
-        pub_key: x25519::X25519PubKey,
-        sender: x25519::X25519PubKey,
-        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
-    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
-        let fut = self.kill_switch.mix_static(self.ipc_send.request(
-            LairWire::ToLairCryptoBoxOpenByPubKey {
-                msg_id: next_msg_id(),
-                pub_key,
-                sender,
-                encrypted_data,
-            },
-        ));
+        session_id: secretstream::SecretstreamSessionId,
+    ) -> LairClientApiHandlerResult<()> {
+        let fut = self.request(LairWire::ToLairSecretstreamClose {
+            msg_id: next_msg_id(),
+            session_id,
+        });
         Ok(async move {
             match fut.await? {
-                LairWire::ToCliCryptoBoxOpenByPubKeyResponse {
-                    data, ..
-                } => Ok(data),
-                o => Err(format!("unexpected: {:?}", o).into()),
+                LairWire::ToCliSecretstreamCloseResponse { .. } => Ok(()),
+                o => Err(LairError::unexpected(o)),
             }
         }
         .boxed()
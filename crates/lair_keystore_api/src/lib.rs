@@ -14,8 +14,12 @@ mod config;
 pub use config::*;
 
 pub mod internal;
-pub use internal::rayon::init_once_rayon_thread_pool;
-pub(crate) use internal::rayon::rayon_exec;
+pub use internal::rayon::{
+    crypto_pool_stats, default_crypto_threads, init_once_rayon_thread_pool,
+    new_crypto_thread_pool, set_inline_crypto_threshold, CryptoPoolStats,
+    DEFAULT_INLINE_CRYPTO_THRESHOLD,
+};
+pub(crate) use internal::rayon::{rayon_exec, rayon_exec_sized};
 
 pub mod entry;
 
@@ -23,4 +27,7 @@ pub mod actor;
 
 pub mod ipc;
 
+#[cfg(feature = "rustls")]
+pub mod tls;
+
 pub mod test;
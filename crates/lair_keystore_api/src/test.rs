@@ -1,10 +1,11 @@
 //! Test keystore implementation. DANGER - Not for production!
 
 use crate::actor::*;
+use crate::internal::tls;
 use crate::internal::*;
 use crate::*;
 use futures::future::FutureExt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 static NEXT_KEYSTORE_ID: std::sync::atomic::AtomicU32 =
     std::sync::atomic::AtomicU32::new(1);
@@ -83,10 +84,14 @@ pub async fn spawn_test_keystore(
         fixture_tls_certs,
         fixture_x25519_keypairs,
         by_idx: HashMap::new(),
+        deleted: HashSet::new(),
+        by_tag: HashMap::new(),
+        meta_by_idx: HashMap::new(),
         cert_by_digest: HashMap::new(),
         cert_by_sni: HashMap::new(),
         sign_by_pub: HashMap::new(),
         x25519_by_pub: HashMap::new(),
+        secretstream_sessions: secretstream::SecretstreamSessions::new(),
         last_idx: 0.into(),
     }));
 
@@ -99,11 +104,15 @@ struct Internal {
     fixture_tls_certs: Vec<FixtureTlsCert>,
     fixture_x25519_keypairs: Vec<FixtureX25519Keypair>,
     by_idx: HashMap<KeystoreIndex, entry::LairEntry>,
+    deleted: HashSet<KeystoreIndex>,
+    by_tag: HashMap<String, KeystoreIndex>,
+    meta_by_idx: HashMap<KeystoreIndex, entry::EntryMeta>,
     cert_by_digest: HashMap<CertDigest, entry::EntryTlsCert>,
     cert_by_sni: HashMap<CertSni, entry::EntryTlsCert>,
     sign_by_pub:
         HashMap<sign_ed25519::SignEd25519PubKey, entry::EntrySignEd25519>,
     x25519_by_pub: HashMap<x25519::X25519PubKey, entry::EntryX25519>,
+    secretstream_sessions: secretstream::SecretstreamSessions,
     last_idx: KeystoreIndex,
 }
 
@@ -115,6 +124,25 @@ ghost_actor::ghost_chan! {
             idx: KeystoreIndex,
             entry: entry::LairEntry,
         ) -> ();
+
+        fn tag_entry(idx: KeystoreIndex, tag: String) -> ();
+
+        fn set_entry_derivation(
+            idx: KeystoreIndex,
+            derivation: entry::EntryDerivation,
+        ) -> ();
+
+        fn set_entry_conversion(
+            idx: KeystoreIndex,
+            source_index: KeystoreIndex,
+        ) -> ();
+
+        fn set_entry_renewal(
+            idx: KeystoreIndex,
+            source_index: KeystoreIndex,
+        ) -> ();
+
+        fn set_entry_requires_approval(idx: KeystoreIndex) -> ();
     }
 }
 
@@ -130,6 +158,8 @@ impl InternalApiHandler for Internal {
             self.last_idx = idx;
         }
         self.by_idx.insert(idx, entry.clone());
+        self.meta_by_idx
+            .insert(idx, entry::EntryMeta::new_created_now(None));
         match entry {
             entry::LairEntry::TlsCert(cert) => {
                 self.cert_by_digest
@@ -142,11 +172,82 @@ impl InternalApiHandler for Internal {
             entry::LairEntry::X25519(keypair) => {
                 self.x25519_by_pub.insert(keypair.pub_key.clone(), keypair);
             }
+            entry::LairEntry::Seed(_)
+            | entry::LairEntry::SignSecp256k1(_)
+            | entry::LairEntry::SecretKey(_) => {}
+            #[cfg(feature = "bls")]
+            entry::LairEntry::SignBls12381(_) => {}
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_tag_entry(
+        &mut self,
+        idx: KeystoreIndex,
+        tag: String,
+    ) -> InternalApiHandlerResult<()> {
+        self.check_tag_free(&tag)?;
+        self.by_tag.insert(tag.clone(), idx);
+        if let Some(meta) = self.meta_by_idx.get_mut(&idx) {
+            meta.tag = Some(tag);
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_set_entry_derivation(
+        &mut self,
+        idx: KeystoreIndex,
+        derivation: entry::EntryDerivation,
+    ) -> InternalApiHandlerResult<()> {
+        if let Some(meta) = self.meta_by_idx.get_mut(&idx) {
+            meta.derived_from = Some(derivation);
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_set_entry_conversion(
+        &mut self,
+        idx: KeystoreIndex,
+        source_index: KeystoreIndex,
+    ) -> InternalApiHandlerResult<()> {
+        if let Some(meta) = self.meta_by_idx.get_mut(&idx) {
+            meta.converted_from = Some(source_index);
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_set_entry_renewal(
+        &mut self,
+        idx: KeystoreIndex,
+        source_index: KeystoreIndex,
+    ) -> InternalApiHandlerResult<()> {
+        if let Some(meta) = self.meta_by_idx.get_mut(&idx) {
+            meta.renewed_from = Some(source_index);
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_set_entry_requires_approval(
+        &mut self,
+        idx: KeystoreIndex,
+    ) -> InternalApiHandlerResult<()> {
+        if let Some(meta) = self.meta_by_idx.get_mut(&idx) {
+            meta.requires_approval = true;
         }
         Ok(async move { Ok(()) }.boxed().into())
     }
 }
 
+impl Internal {
+    fn check_tag_free(&self, tag: &str) -> LairResult<()> {
+        entry::EntryMeta::check_tag(tag)?;
+        if self.by_tag.contains_key(tag) {
+            return Err(LairError::EntryTagInUse(tag.to_string()));
+        }
+        Ok(())
+    }
+}
+
 impl ghost_actor::GhostHandler<LairClientApi> for Internal {}
 
 impl LairClientApiHandler for Internal {
@@ -156,11 +257,67 @@ impl LairClientApiHandler for Internal {
         let out = LairServerInfo {
             name: "[LAIR-TEST-KEYSTORE]".to_string(),
             version: crate::LAIR_VER.to_string(),
+            corrupt_entry_count: 0,
+            connection_count: 0,
+            requests_in_flight: 0,
+            failed_unlock_count: 0,
+            secrets_mlocked: false,
+            core_dumps_disabled: false,
+            ptrace_disabled: false,
+            umask_restricted: false,
+            crypto_threads: 0,
+            crypto_queue_depth: 0,
+            crypto_busy_threads: 0,
         };
 
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_lair_ping(
+        &mut self,
+        nonce: u64,
+    ) -> LairClientApiHandlerResult<u64> {
+        Ok(async move { Ok(nonce) }.boxed().into())
+    }
+
+    fn handle_lair_lock(&mut self) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_unlock(
+        &mut self,
+        _passphrase: util::SecretBytes,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_subscribe_store_events(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_get_lock_state(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairLockState> {
+        // the test keystore keeps its entries in memory, unsealed
+        Ok(async move { Ok(LairLockState::NoPassphrase) }
+            .boxed()
+            .into())
+    }
+
+    fn handle_lair_register_passphrase_provider(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_register_signing_approver(
+        &mut self,
+    ) -> LairClientApiHandlerResult<()> {
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
     fn handle_lair_get_last_entry_index(
         &mut self,
     ) -> LairClientApiHandlerResult<KeystoreIndex> {
@@ -174,16 +331,182 @@ impl LairClientApiHandler for Internal {
     ) -> LairClientApiHandlerResult<LairEntryType> {
         let entry = match self.by_idx.get(&keystore_index) {
             Some(entry) => entry,
+            None if self.deleted.contains(&keystore_index) => {
+                return Ok(async move { Ok(LairEntryType::Deleted) }
+                    .boxed()
+                    .into())
+            }
             None => return Err("bad index".into()),
         };
         let t = match entry {
             entry::LairEntry::TlsCert(_) => LairEntryType::TlsCert,
             entry::LairEntry::SignEd25519(_) => LairEntryType::SignEd25519,
             entry::LairEntry::X25519(_) => LairEntryType::X25519,
+            entry::LairEntry::Seed(_) => LairEntryType::Seed,
+            entry::LairEntry::SignSecp256k1(_) => LairEntryType::SignSecp256k1,
+            #[cfg(feature = "bls")]
+            entry::LairEntry::SignBls12381(_) => LairEntryType::SignBls12381,
+            entry::LairEntry::SecretKey(_) => LairEntryType::SecretKey,
         };
         Ok(async move { Ok(t) }.boxed().into())
     }
 
+    fn handle_lair_list_entries(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<EntryListItem>> {
+        let mut out = self
+            .by_idx
+            .iter()
+            .map(|(idx, entry)| {
+                let meta =
+                    self.meta_by_idx.get(idx).cloned().unwrap_or_default();
+                entry.list_item(*idx, &meta)
+            })
+            .collect::<Vec<_>>();
+        out.sort_by_key(|item| item.keystore_index);
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_lair_get_entry_by_tag(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, LairEntryType)> {
+        let idx = match self.by_tag.get(&tag) {
+            Some(idx) => *idx,
+            None => return Err(LairError::EntryTagNotFound(tag)),
+        };
+        let fut = self.handle_lair_get_entry_type(idx)?;
+        Ok(async move { Ok((idx, fut.await?)) }.boxed().into())
+    }
+
+    fn handle_lair_get_entry_meta(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<entry::EntryMeta> {
+        let out = match self.meta_by_idx.get(&keystore_index) {
+            Some(meta) => meta.clone(),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_lair_find_entry(
+        &mut self,
+        id: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<(KeystoreIndex, LairEntryType)>>
+    {
+        // a linear scan is fine for the in-memory test keystore
+        let out = self.by_idx.iter().find_map(|(idx, entry)| {
+            let item = entry.list_item(*idx, &Default::default());
+            let sni_match = match &item.cert_sni {
+                Some(sni) => sni.as_str().as_bytes() == &id[..],
+                None => false,
+            };
+            if *item.pub_id == *id || sni_match {
+                Some((*idx, item.entry_type))
+            } else {
+                None
+            }
+        });
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_lair_update_entry_tag(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        tag: String,
+    ) -> LairClientApiHandlerResult<()> {
+        let meta = match self.meta_by_idx.get_mut(&keystore_index) {
+            Some(meta) => meta,
+            None => return Err("bad index".into()),
+        };
+        if meta.tag.as_ref() == Some(&tag) {
+            return Ok(async move { Ok(()) }.boxed().into());
+        }
+        entry::EntryMeta::check_tag(&tag)?;
+        if self.by_tag.contains_key(&tag) {
+            return Err(LairError::EntryTagInUse(tag));
+        }
+        if let Some(old_tag) = meta.tag.replace(tag.clone()) {
+            self.by_tag.remove(&old_tag);
+        }
+        self.by_tag.insert(tag, keystore_index);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_set_entry_rate_limit(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        rate_limit: Option<u32>,
+    ) -> LairClientApiHandlerResult<()> {
+        match self.meta_by_idx.get_mut(&keystore_index) {
+            Some(meta) => meta.rate_limit = rate_limit,
+            None => return Err("bad index".into()),
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_export_entry_public(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<EntryPublic> {
+        let entry_public = match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry.export_public()?,
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { Ok(entry_public) }.boxed().into())
+    }
+
+    fn handle_lair_delete_entry(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<()> {
+        match self.by_idx.remove(&keystore_index) {
+            Some(entry::LairEntry::TlsCert(cert)) => {
+                self.cert_by_digest.remove(&cert.cert_digest);
+                self.cert_by_sni.remove(&cert.sni);
+            }
+            Some(entry::LairEntry::SignEd25519(keypair)) => {
+                self.sign_by_pub.remove(&keypair.pub_key);
+            }
+            Some(entry::LairEntry::X25519(keypair)) => {
+                self.x25519_by_pub.remove(&keypair.pub_key);
+            }
+            Some(entry::LairEntry::Seed(_))
+            | Some(entry::LairEntry::SignSecp256k1(_))
+            | Some(entry::LairEntry::SecretKey(_)) => (),
+            #[cfg(feature = "bls")]
+            Some(entry::LairEntry::SignBls12381(_)) => (),
+            None if self.deleted.contains(&keystore_index) => (),
+            None => return Err("bad index".into()),
+        }
+        self.deleted.insert(keystore_index);
+        self.by_tag.retain(|_, idx| *idx != keystore_index);
+        self.meta_by_idx.remove(&keystore_index);
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_compact_store(
+        &mut self,
+    ) -> LairClientApiHandlerResult<LairCompactInfo> {
+        // the in-memory test keystore keeps no tombstones to drop
+        Ok(async move { Ok(LairCompactInfo::default()) }.boxed().into())
+    }
+
+    fn handle_lair_shutdown(&mut self) -> LairClientApiHandlerResult<()> {
+        // nothing to shut down for the in-memory test keystore
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_lair_change_unlock_passphrase(
+        &mut self,
+        _old_passphrase: String,
+        _new_passphrase: String,
+    ) -> LairClientApiHandlerResult<()> {
+        // the in-memory test keystore has no unlock passphrase
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
     fn handle_tls_cert_new_self_signed_from_entropy(
         &mut self,
         options: TlsCertOptions,
@@ -193,11 +516,15 @@ impl LairClientApiHandler for Internal {
             let i_s = self.i_s.clone();
             return Ok(async move {
                 let idx = next_keystore_idx();
+                let alg = tls::tls_cert_alg_of_key(&cert.priv_key_der)?;
                 let entry = entry::EntryTlsCert {
                     sni: cert.sni.into(),
                     priv_key_der: cert.priv_key_der.into(),
                     cert_der: cert.cert_der.into(),
                     cert_digest: cert.cert_digest.into(),
+                    digest_alg: TlsCertDigestAlg::Blake2b256,
+                    alg,
+                    exportable: true,
                 };
                 let sni = entry.sni.clone();
                 let digest = entry.cert_digest.clone();
@@ -223,6 +550,132 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
+    fn handle_tls_cert_new_self_signed_tagged(
+        &mut self,
+        options: TlsCertOptions,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        self.check_tag_free(&tag)?;
+        let fut = self.handle_tls_cert_new_self_signed_from_entropy(options)?;
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let (idx, sni, digest) = fut.await?;
+            i_s.tag_entry(idx, tag).await?;
+            Ok((idx, sni, digest))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_renew(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let cert = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::TlsCert(cert)) => cert.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = tls::tls_cert_self_signed_renew(&cert, options).await?;
+            let sni = entry.sni.clone();
+            let digest = entry.cert_digest.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            i_s.set_entry_renewal(idx, keystore_index).await?;
+            Ok((idx, sni, digest))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_import(
+        &mut self,
+        cert_der: Cert,
+        priv_key_der: CertPrivKey,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry =
+                tls::tls_cert_import(cert_der, priv_key_der, options).await?;
+            let sni = entry.sni.clone();
+            let digest = entry.cert_digest.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, sni, digest))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_ca_new_self_signed(
+        &mut self,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = tls::tls_ca_new_self_signed(options).await?;
+            let sni = entry.sni.clone();
+            let digest = entry.cert_digest.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, sni, digest))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_new_signed_by_ca(
+        &mut self,
+        ca_index: KeystoreIndex,
+        options: TlsCertOptions,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        CertSni,
+        CertDigest,
+        Vec<Cert>,
+    )> {
+        let ca = match self.by_idx.get(&ca_index) {
+            Some(entry::LairEntry::TlsCert(ca)) => ca.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry = tls::tls_cert_new_signed_by_ca(&ca, options).await?;
+            let sni = entry.sni.clone();
+            let digest = entry.cert_digest.clone();
+            let chain = vec![entry.cert_der.clone(), ca.cert_der];
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, sni, digest, chain))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_find_by_digest_prefix(
+        &mut self,
+        prefix: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, CertSni, CertDigest)> {
+        let (idx, cert) = tls::tls_cert_find_by_digest_prefix(
+            &prefix,
+            self.by_idx.iter().filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::TlsCert(cert) => Some((*idx, cert)),
+                _ => None,
+            }),
+        )?;
+        let out = (idx, cert.sni.clone(), cert.cert_digest.clone());
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
     fn handle_tls_cert_get(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -283,7 +736,7 @@ impl LairClientApiHandler for Internal {
             Some(entry) => entry,
             None => return Err("bad index".into()),
         } {
-            entry::LairEntry::TlsCert(cert) => cert.priv_key_der.clone(),
+            entry::LairEntry::TlsCert(cert) => cert.exportable_priv_key()?,
             _ => return Err("bad type".into()),
         };
         Ok(async move { Ok(out) }.boxed().into())
@@ -294,7 +747,7 @@ impl LairClientApiHandler for Internal {
         cert_digest: CertDigest,
     ) -> LairClientApiHandlerResult<CertPrivKey> {
         let out = match self.cert_by_digest.get(&cert_digest) {
-            Some(cert) => cert.priv_key_der.clone(),
+            Some(cert) => cert.exportable_priv_key()?,
             None => return Err("bad digest".into()),
         };
         Ok(async move { Ok(out) }.boxed().into())
@@ -305,12 +758,51 @@ impl LairClientApiHandler for Internal {
         cert_sni: CertSni,
     ) -> LairClientApiHandlerResult<CertPrivKey> {
         let out = match self.cert_by_sni.get(&cert_sni) {
-            Some(cert) => cert.priv_key_der.clone(),
+            Some(cert) => cert.exportable_priv_key()?,
             None => return Err("bad sni".into()),
         };
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_tls_cert_export_pkcs12(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        password: Option<String>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let (cert, priv_key) = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::TlsCert(cert)) => {
+                (cert.clone(), cert.exportable_priv_key()?)
+            }
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move {
+            pkcs12::pkcs12_bundle(
+                cert.cert_der.0.clone(),
+                priv_key.0,
+                cert.sni.to_string(),
+                password,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_tls_cert_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        scheme: TlsCertSignatureScheme,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let fut = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::TlsCert(cert)) => cert.sign(scheme, message),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(fut.boxed().into())
+    }
+
     fn handle_sign_ed25519_new_from_entropy(
         &mut self,
     ) -> LairClientApiHandlerResult<(
@@ -348,6 +840,79 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
+    fn handle_sign_ed25519_new_from_entropy_tagged(
+        &mut self,
+        tag: String,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        self.check_tag_free(&tag)?;
+        let fut = self.handle_sign_ed25519_new_from_entropy()?;
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let (idx, pk) = fut.await?;
+            i_s.tag_entry(idx, tag).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_entropy_requiring_approval(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let fut = self.handle_sign_ed25519_new_from_entropy()?;
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let (idx, pk) = fut.await?;
+            i_s.set_entry_requires_approval(idx).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_new_from_seed(
+        &mut self,
+        seed: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let seed = seed.to_vec();
+        let by_idx = self
+            .by_idx
+            .iter()
+            .filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::SignEd25519(e) => {
+                    Some((e.pub_key.clone(), *idx))
+                }
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry =
+                sign_ed25519::sign_ed25519_keypair_from_seed(seed).await?;
+            if let Some(idx) = by_idx.get(&entry.pub_key) {
+                return Err(LairError::DuplicatePubKey {
+                    existing_index: **idx,
+                });
+            }
+            let idx = next_keystore_idx();
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
     fn handle_sign_ed25519_get(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -365,7 +930,7 @@ impl LairClientApiHandler for Internal {
     fn handle_sign_ed25519_sign_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
-        message: Arc<Vec<u8>>,
+        message: bytes::Bytes,
     ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
         let priv_key = match match self.by_idx.get(&keystore_index) {
             Some(entry) => entry,
@@ -374,27 +939,444 @@ impl LairClientApiHandler for Internal {
             entry::LairEntry::SignEd25519(keypair) => keypair.priv_key.clone(),
             _ => return Err("bad type".into()),
         };
-        Ok(
-            async move { sign_ed25519::sign_ed25519(priv_key, message).await }
-                .boxed()
-                .into(),
-        )
+        Ok(
+            async move { sign_ed25519::sign_ed25519(priv_key, message).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_sign_ed25519_sign_batch_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        messages: Vec<bytes::Bytes>,
+    ) -> LairClientApiHandlerResult<Vec<sign_ed25519::SignEd25519Signature>>
+    {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(
+            async move {
+                sign_ed25519::sign_ed25519_batch(priv_key, messages).await
+            }
+            .boxed()
+            .into(),
+        )
+    }
+
+    fn handle_sign_ed25519_sign_batch_by_pub_key(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        messages: Vec<bytes::Bytes>,
+    ) -> LairClientApiHandlerResult<Vec<sign_ed25519::SignEd25519Signature>>
+    {
+        let priv_key = match self.sign_by_pub.get(&pub_key) {
+            Some(keypair) => keypair.priv_key.clone(),
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(
+            async move {
+                sign_ed25519::sign_ed25519_batch(priv_key, messages).await
+            }
+            .boxed()
+            .into(),
+        )
+    }
+
+    fn handle_sign_ed25519_sign_prehashed(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            sign_ed25519::sign_ed25519_prehashed(priv_key, digest).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_verify(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        message: bytes::Bytes,
+        signature: sign_ed25519::SignEd25519Signature,
+    ) -> LairClientApiHandlerResult<bool> {
+        Ok(async move {
+            sign_ed25519::sign_ed25519_verify(pub_key, message, signature).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let priv_key = match self.sign_by_pub.get(&pub_key) {
+            Some(keypair) => keypair.priv_key.clone(),
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(
+            async move { sign_ed25519::sign_ed25519(priv_key, message).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_sign_ed25519_sign_by_index_with_context(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::SignEd25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            sign_ed25519::sign_ed25519_with_context(priv_key, context, message)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_pub_key_with_context(
+        &mut self,
+        pub_key: sign_ed25519::SignEd25519PubKey,
+        context: Arc<Vec<u8>>,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let priv_key = match self.sign_by_pub.get(&pub_key) {
+            Some(keypair) => keypair.priv_key.clone(),
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(async move {
+            sign_ed25519::sign_ed25519_with_context(priv_key, context, message)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_sign_by_tag(
+        &mut self,
+        tag: String,
+        message: bytes::Bytes,
+    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
+        let idx = match self.by_tag.get(&tag) {
+            Some(idx) => *idx,
+            None => return Err(LairError::EntryTagNotFound(tag)),
+        };
+        self.handle_sign_ed25519_sign_by_index(idx, message)
+    }
+
+    fn handle_sign_ed25519_list_pub_keys(
+        &mut self,
+    ) -> LairClientApiHandlerResult<
+        Vec<(KeystoreIndex, sign_ed25519::SignEd25519PubKey)>,
+    > {
+        let mut out = self
+            .by_idx
+            .iter()
+            .filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::SignEd25519(e) => {
+                    Some((*idx, e.pub_key.clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        out.sort_by_key(|(idx, _)| *idx);
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_secp256k1_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_secp256k1::SignSecp256k1PubKey,
+    )> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let entry =
+                sign_secp256k1::sign_secp256k1_keypair_new_from_entropy()
+                    .await?;
+            let pk = entry.pub_key.clone();
+            i_s.finalize_entry(idx, entry.into()).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_get(
+        &mut self,
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1PubKey> {
+        let pk = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SignSecp256k1(e)) => e.pub_key.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move { Ok(pk) }.boxed().into())
+    }
+
+    fn handle_secp256k1_sign_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1Signature>
+    {
+        let priv_key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SignSecp256k1(e)) => e.priv_key.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move {
+            sign_secp256k1::sign_secp256k1(priv_key, digest).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secp256k1_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_secp256k1::SignSecp256k1PubKey,
+        digest: [u8; 32],
+    ) -> LairClientApiHandlerResult<sign_secp256k1::SignSecp256k1Signature>
+    {
+        // a linear scan is fine for the in-memory test keystore
+        let priv_key = match self.by_idx.values().find_map(|e| match e {
+            entry::LairEntry::SignSecp256k1(e) if e.pub_key == pub_key => {
+                Some(e.priv_key.clone())
+            }
+            _ => None,
+        }) {
+            Some(priv_key) => priv_key,
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(async move {
+            sign_secp256k1::sign_secp256k1(priv_key, digest).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_bls_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_bls12381::SignBls12381PubKey,
+    )> {
+        #[cfg(not(feature = "bls"))]
+        {
+            Err(sign_bls12381::bls_not_enabled())
+        }
+        #[cfg(feature = "bls")]
+        {
+            let i_s = self.i_s.clone();
+            Ok(async move {
+                let idx = next_keystore_idx();
+                let entry =
+                    sign_bls12381::sign_bls12381_keypair_new_from_entropy()
+                        .await?;
+                let pk = entry.pub_key.clone();
+                i_s.finalize_entry(idx, entry.into()).await?;
+                Ok((idx, pk))
+            }
+            .boxed()
+            .into())
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn handle_bls_sign_by_pub_key(
+        &mut self,
+        pub_key: sign_bls12381::SignBls12381PubKey,
+        message: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<sign_bls12381::SignBls12381Signature> {
+        #[cfg(not(feature = "bls"))]
+        {
+            Err(sign_bls12381::bls_not_enabled())
+        }
+        #[cfg(feature = "bls")]
+        {
+            // a linear scan is fine for the in-memory test keystore
+            let priv_key = match self.by_idx.values().find_map(|e| match e {
+                entry::LairEntry::SignBls12381(e) if e.pub_key == pub_key => {
+                    Some(e.priv_key.clone())
+                }
+                _ => None,
+            }) {
+                Some(priv_key) => priv_key,
+                None => return Err(LairError::PubKeyNotFound),
+            };
+            Ok(async move {
+                sign_bls12381::sign_bls12381(priv_key, message).await
+            }
+            .boxed()
+            .into())
+        }
+    }
+
+    fn handle_seed_new_from_entropy(
+        &mut self,
+        exportable: bool,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)> {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry = seed::seed_new_from_entropy(exportable).await?;
+            let idx = next_keystore_idx();
+            let seed_id = entry.seed_id.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, seed_id))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_seed_export_mnemonic(
+        &mut self,
+        seed_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<String> {
+        let mnemonic = match self.by_idx.get(&seed_index) {
+            Some(entry::LairEntry::Seed(e)) if e.exportable => {
+                seed::seed_to_mnemonic(&e.seed)
+            }
+            Some(entry::LairEntry::Seed(_)) => Err(LairError::NotExportable),
+            _ => Err(LairError::WrongEntryType),
+        };
+        Ok(async move { mnemonic }.boxed().into())
+    }
+
+    fn handle_seed_import_mnemonic(
+        &mut self,
+        mnemonic: String,
+        exportable: bool,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, seed::SeedId)> {
+        let mnemonic = zeroize::Zeroizing::new(mnemonic);
+        let entry = entry::EntrySeed::new(
+            seed::seed_from_mnemonic(&mnemonic)?,
+            exportable,
+        );
+        if let Some(idx) = self.by_idx.iter().find_map(|(idx, e)| match e {
+            entry::LairEntry::Seed(e) if e.seed_id == entry.seed_id => {
+                Some(idx)
+            }
+            _ => None,
+        }) {
+            return Err(LairError::DuplicatePubKey {
+                existing_index: **idx,
+            });
+        }
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let idx = next_keystore_idx();
+            let seed_id = entry.seed_id.clone();
+            i_s.finalize_entry(idx, entry.into()).await?;
+            Ok((idx, seed_id))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_sign_ed25519_derive_from_seed(
+        &mut self,
+        seed_index: KeystoreIndex,
+        derivation_path: Vec<u32>,
+    ) -> LairClientApiHandlerResult<(
+        KeystoreIndex,
+        sign_ed25519::SignEd25519PubKey,
+    )> {
+        let seed = match self.by_idx.get(&seed_index) {
+            Some(entry::LairEntry::Seed(e)) => e.seed.clone(),
+            _ => return Err(LairError::WrongEntryType),
+        };
+        let by_pub = self
+            .by_idx
+            .iter()
+            .filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::SignEd25519(e) => {
+                    Some((e.pub_key.clone(), *idx))
+                }
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry = seed::sign_ed25519_keypair_derive(
+                seed,
+                derivation_path.clone(),
+            )
+            .await?;
+            let pk = entry.pub_key.clone();
+            if let Some(idx) = by_pub.get(&pk) {
+                return Ok((*idx, pk));
+            }
+            let idx = next_keystore_idx();
+            i_s.finalize_entry(idx, entry.into()).await?;
+            i_s.set_entry_derivation(
+                idx,
+                entry::EntryDerivation {
+                    seed_index,
+                    path: derivation_path,
+                },
+            )
+            .await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
     }
 
-    fn handle_sign_ed25519_sign_by_pub_key(
+    fn handle_sign_ed25519_to_x25519(
         &mut self,
-        pub_key: sign_ed25519::SignEd25519PubKey,
-        message: Arc<Vec<u8>>,
-    ) -> LairClientApiHandlerResult<sign_ed25519::SignEd25519Signature> {
-        let priv_key = match self.sign_by_pub.get(&pub_key) {
-            Some(keypair) => keypair.priv_key.clone(),
-            None => return Err(LairError::PubKeyNotFound),
+        keystore_index: KeystoreIndex,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        let priv_key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SignEd25519(e)) => e.priv_key.clone(),
+            _ => return Err(LairError::WrongEntryType),
         };
-        Ok(
-            async move { sign_ed25519::sign_ed25519(priv_key, message).await }
-                .boxed()
-                .into(),
-        )
+        let by_pub = self
+            .by_idx
+            .iter()
+            .filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::X25519(e) => Some((e.pub_key.clone(), *idx)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry =
+                x25519::x25519_keypair_from_sign_ed25519(priv_key).await?;
+            let pk = entry.pub_key.clone();
+            if let Some(idx) = by_pub.get(&pk) {
+                return Ok((*idx, pk));
+            }
+            let idx = next_keystore_idx();
+            i_s.finalize_entry(idx, entry.into()).await?;
+            i_s.set_entry_conversion(idx, keystore_index).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
     }
 
     fn handle_x25519_new_from_entropy(
@@ -430,6 +1412,37 @@ impl LairClientApiHandler for Internal {
         .into())
     }
 
+    fn handle_x25519_new_from_priv_key(
+        &mut self,
+        priv_key: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, x25519::X25519PubKey)> {
+        let priv_key = priv_key.to_vec();
+        let by_idx = self
+            .by_idx
+            .iter()
+            .filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::X25519(e) => Some((e.pub_key.clone(), *idx)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry = x25519::x25519_keypair_from_priv_key(priv_key).await?;
+            if let Some(idx) = by_idx.get(&entry.pub_key) {
+                return Err(LairError::DuplicatePubKey {
+                    existing_index: **idx,
+                });
+            }
+            let idx = next_keystore_idx();
+            let pk = entry.pub_key.clone();
+            let entry = entry::LairEntry::from(entry);
+            i_s.finalize_entry(idx, entry).await?;
+            Ok((idx, pk))
+        }
+        .boxed()
+        .into())
+    }
+
     fn handle_x25519_get(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -444,6 +1457,48 @@ impl LairClientApiHandler for Internal {
         Ok(async move { Ok(out) }.boxed().into())
     }
 
+    fn handle_x25519_list_pub_keys(
+        &mut self,
+    ) -> LairClientApiHandlerResult<Vec<(KeystoreIndex, x25519::X25519PubKey)>>
+    {
+        let mut out = self
+            .by_idx
+            .iter()
+            .filter_map(|(idx, entry)| match entry {
+                entry::LairEntry::X25519(e) => Some((*idx, e.pub_key.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        out.sort_by_key(|(idx, _)| *idx);
+        Ok(async move { Ok(out) }.boxed().into())
+    }
+
+    fn handle_kx_session_keys_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        remote_pub: x25519::X25519PubKey,
+        is_server: bool,
+    ) -> LairClientApiHandlerResult<(kx::KxSessionKey, kx::KxSessionKey)> {
+        let keypair = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            kx::kx_session_keys(
+                keypair.priv_key,
+                keypair.pub_key,
+                remote_pub,
+                is_server,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
     fn handle_crypto_box_by_index(
         &mut self,
         keystore_index: KeystoreIndex,
@@ -509,6 +1564,295 @@ impl LairClientApiHandler for Internal {
         .boxed()
         .into())
     }
+
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_by_index_with_aad(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            crypto_box::crypto_box_with_aad(priv_key, recipient, aad, data)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_by_pub_key_with_aad(
+        &mut self,
+        pub_key: x25519::X25519PubKey,
+        recipient: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        data: Arc<crypto_box::CryptoBoxData>,
+    ) -> LairClientApiHandlerResult<crypto_box::CryptoBoxEncryptedData> {
+        let priv_key = match self.x25519_by_pub.get(&pub_key) {
+            Some(keypair) => keypair.priv_key.clone(),
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(async move {
+            crypto_box::crypto_box_with_aad(priv_key, recipient, aad, data)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_open_by_index_with_aad(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let priv_key = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.priv_key.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            crypto_box::crypto_box_open_with_aad(
+                priv_key,
+                sender,
+                aad,
+                encrypted_data,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    #[allow(clippy::rc_buffer)]
+    fn handle_crypto_box_open_by_pub_key_with_aad(
+        &mut self,
+        pub_key: x25519::X25519PubKey,
+        sender: x25519::X25519PubKey,
+        aad: Arc<Vec<u8>>,
+        encrypted_data: Arc<crypto_box::CryptoBoxEncryptedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let priv_key = match self.x25519_by_pub.get(&pub_key) {
+            Some(keypair) => keypair.priv_key.clone(),
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(async move {
+            crypto_box::crypto_box_open_with_aad(
+                priv_key,
+                sender,
+                aad,
+                encrypted_data,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_seal_open_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let keypair = match match self.by_idx.get(&keystore_index) {
+            Some(entry) => entry,
+            None => return Err("bad index".into()),
+        } {
+            entry::LairEntry::X25519(keypair) => keypair.clone(),
+            _ => return Err("bad type".into()),
+        };
+        Ok(async move {
+            crypto_box::crypto_box_seal_open(
+                keypair.priv_key,
+                keypair.pub_key,
+                sealed_data,
+            )
+            .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_crypto_box_seal_open_by_pub_key(
+        &mut self,
+        pub_key: x25519::X25519PubKey,
+        sealed_data: Arc<crypto_box::CryptoBoxSealedData>,
+    ) -> LairClientApiHandlerResult<Option<crypto_box::CryptoBoxData>> {
+        let priv_key = match self.x25519_by_pub.get(&pub_key) {
+            Some(keypair) => keypair.priv_key.clone(),
+            None => return Err(LairError::PubKeyNotFound),
+        };
+        Ok(async move {
+            crypto_box::crypto_box_seal_open(priv_key, pub_key, sealed_data)
+                .await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secret_new_from_entropy(
+        &mut self,
+    ) -> LairClientApiHandlerResult<(KeystoreIndex, secret_key::SecretKeyTag)>
+    {
+        let i_s = self.i_s.clone();
+        Ok(async move {
+            let entry = secret_key::secret_key_new_from_entropy().await?;
+            let idx = next_keystore_idx();
+            let tag = entry.tag.clone();
+            i_s.finalize_entry(idx, entry.into()).await?;
+            Ok((idx, tag))
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secret_encrypt_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        aad: Arc<Vec<u8>>,
+        data: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<(secret_key::SecretNonce, Arc<Vec<u8>>)>
+    {
+        let key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SecretKey(e)) => e.secret_key.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(
+            async move { secret_key::secret_encrypt(key, aad, data).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_secret_decrypt_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        aad: Arc<Vec<u8>>,
+        nonce: secret_key::SecretNonce,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<Arc<Vec<u8>>>> {
+        let key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SecretKey(e)) => e.secret_key.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move {
+            secret_key::secret_decrypt(key, aad, nonce, ciphertext).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_derive_subkey_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        subkey_len: usize,
+        context: [u8; secret_key::SUBKEY_CONTEXT_BYTES],
+        subkey_id: u64,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::SecretKey(e)) => e.secret_key.0.clone(),
+            Some(entry::LairEntry::Seed(e)) => e.seed.0.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        Ok(async move {
+            secret_key::derive_subkey(key, subkey_len, context, subkey_id).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secretstream_init_push_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        remote_pub: x25519::X25519PubKey,
+    ) -> LairClientApiHandlerResult<(
+        secretstream::SecretstreamSessionId,
+        secretstream::SecretstreamHeader,
+    )> {
+        let priv_key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::X25519(e)) => e.priv_key.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        let sessions = self.secretstream_sessions.clone();
+        Ok(
+            async move { sessions.init_push(priv_key, remote_pub).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_secretstream_push(
+        &mut self,
+        session_id: secretstream::SecretstreamSessionId,
+        chunk: Arc<Vec<u8>>,
+        is_final: bool,
+    ) -> LairClientApiHandlerResult<Arc<Vec<u8>>> {
+        let sessions = self.secretstream_sessions.clone();
+        Ok(
+            async move { sessions.push(session_id, chunk, is_final).await }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_secretstream_init_pull_by_index(
+        &mut self,
+        keystore_index: KeystoreIndex,
+        remote_pub: x25519::X25519PubKey,
+        header: secretstream::SecretstreamHeader,
+    ) -> LairClientApiHandlerResult<secretstream::SecretstreamSessionId> {
+        let priv_key = match self.by_idx.get(&keystore_index) {
+            Some(entry::LairEntry::X25519(e)) => e.priv_key.clone(),
+            Some(_) => return Err("bad type".into()),
+            None => return Err("bad index".into()),
+        };
+        let sessions = self.secretstream_sessions.clone();
+        Ok(async move {
+            sessions.init_pull(priv_key, remote_pub, header).await
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_secretstream_pull(
+        &mut self,
+        session_id: secretstream::SecretstreamSessionId,
+        ciphertext: Arc<Vec<u8>>,
+    ) -> LairClientApiHandlerResult<Option<(Arc<Vec<u8>>, bool)>> {
+        let sessions = self.secretstream_sessions.clone();
+        Ok(async move { sessions.pull(session_id, ciphertext).await }
+            .boxed()
+            .into())
+    }
+
+    fn handle_secretstream_close(
+        &mut self,
+        session_id: secretstream::SecretstreamSessionId,
+    ) -> LairClientApiHandlerResult<()> {
+        let sessions = self.secretstream_sessions.clone();
+        Ok(async move {
+            sessions.close(session_id).await;
+            Ok(())
+        }
+        .boxed()
+        .into())
+    }
 }
 
 #[cfg(test)]
@@ -631,7 +1975,7 @@ mod tests {
 
         assert_eq!(idx3, api.lair_get_last_entry_index().await?);
 
-        let data = std::sync::Arc::new(b"test-data".to_vec());
+        let data = bytes::Bytes::from_static(b"test-data");
 
         let sig1 = api.sign_ed25519_sign_by_index(idx1, data.clone()).await?;
         let sig2 = api
@@ -683,6 +2027,22 @@ mod tests {
         assert_eq!(priv1, priv2);
         assert_eq!(priv2, priv3);
 
+        match api.lair_export_entry_public(idx1).await? {
+            EntryPublic::TlsCert {
+                sni,
+                cert_digest,
+                cert_der,
+                alg,
+                ..
+            } => {
+                assert_eq!(TlsCertAlg::PkcsEd25519, alg);
+                assert_eq!(CERT_SNI, sni);
+                assert_eq!(CERT_DIGEST, cert_digest.as_slice());
+                assert_eq!(&cert1[..], cert_der.as_slice());
+            }
+            o => panic!("unexpected: {:?}", o),
+        }
+
         let (_idx2, sni2, digest2) = api
             .tls_cert_new_self_signed_from_entropy(TlsCertOptions::default())
             .await?;
@@ -0,0 +1,233 @@
+//! Serve rustls connections with lair-held tls certs.
+//! Requires the `rustls` cargo feature.
+
+use crate::actor::*;
+use crate::*;
+use futures::future::{BoxFuture, FutureExt};
+use std::collections::HashMap;
+
+/// Fetch the cert and private key of the tls cert entry at `index`,
+/// ready to hand to rustls.
+/// Fails for certs whose key is not exportable,
+/// see `remote_certified_key_from_lair`.
+pub async fn certified_key_from_lair(
+    api: &ghost_actor::GhostSender<LairClientApi>,
+    index: KeystoreIndex,
+) -> LairResult<rustls::sign::CertifiedKey> {
+    let cert = api.tls_cert_get_cert_by_index(index).await?;
+    let priv_key = api.tls_cert_get_priv_key_by_index(index).await?;
+    let key = rustls::sign::any_supported_type(&rustls::PrivateKey(
+        priv_key.to_vec(),
+    ))
+    .map_err(LairError::other)?;
+    Ok(rustls::sign::CertifiedKey::new(
+        vec![rustls::Certificate(cert.to_vec())],
+        key,
+    ))
+}
+
+/// Fetch the cert of the tls cert entry at `index`, paired with a
+/// `LairSigningKey`, so handshakes are signed by the keystore and the
+/// private key never leaves it. Works whether or not the key is
+/// exportable.
+pub async fn remote_certified_key_from_lair(
+    api: &ghost_actor::GhostSender<LairClientApi>,
+    index: KeystoreIndex,
+) -> LairResult<rustls::sign::CertifiedKey> {
+    let cert = api.tls_cert_get_cert_by_index(index).await?;
+    let key = LairSigningKey::new(api.clone(), index).await?;
+    Ok(rustls::sign::CertifiedKey::new(
+        vec![rustls::Certificate(cert.to_vec())],
+        Arc::new(key),
+    ))
+}
+
+/// A rustls signing key for a lair tls cert entry, signing handshakes
+/// via `tls_cert_sign_by_index`.
+///
+/// rustls signs synchronously, so each handshake blocks its thread
+/// on the keystore. Within a tokio runtime this uses
+/// `tokio::task::block_in_place`, which panics on a `current_thread`
+/// runtime, use a multi threaded one.
+pub struct LairSigningKey {
+    api: ghost_actor::GhostSender<LairClientApi>,
+    index: KeystoreIndex,
+    alg: TlsCertAlg,
+}
+
+impl LairSigningKey {
+    /// Sign with the key of the tls cert entry at `index`.
+    pub async fn new(
+        api: ghost_actor::GhostSender<LairClientApi>,
+        index: KeystoreIndex,
+    ) -> LairResult<Self> {
+        let alg = match api.lair_export_entry_public(index).await? {
+            EntryPublic::TlsCert { alg, .. } => alg,
+            _ => return Err(LairError::WrongEntryType),
+        };
+        Ok(Self { api, index, alg })
+    }
+}
+
+impl rustls::sign::SigningKey for LairSigningKey {
+    fn choose_scheme(
+        &self,
+        offered: &[rustls::SignatureScheme],
+    ) -> Option<Box<dyn rustls::sign::Signer>> {
+        use rustls::SignatureScheme as S;
+        let ours: &[(S, TlsCertSignatureScheme)] = match self.alg {
+            TlsCertAlg::PkcsEd25519 => {
+                &[(S::ED25519, TlsCertSignatureScheme::Ed25519)]
+            }
+            TlsCertAlg::PkcsEcdsaP256Sha256 => &[(
+                S::ECDSA_NISTP256_SHA256,
+                TlsCertSignatureScheme::EcdsaSecp256r1Sha256,
+            )],
+            TlsCertAlg::PkcsEcdsaP384Sha384 => &[(
+                S::ECDSA_NISTP384_SHA384,
+                TlsCertSignatureScheme::EcdsaSecp384r1Sha384,
+            )],
+            TlsCertAlg::PkcsRsaSha256 => &[
+                (S::RSA_PSS_SHA256, TlsCertSignatureScheme::RsaPssRsaeSha256),
+                (S::RSA_PKCS1_SHA256, TlsCertSignatureScheme::RsaPkcs1Sha256),
+            ],
+        };
+        let (rustls_scheme, scheme) =
+            ours.iter().find(|(s, _)| offered.contains(s))?;
+        Some(Box::new(LairSigner {
+            api: self.api.clone(),
+            index: self.index,
+            rustls_scheme: *rustls_scheme,
+            scheme: *scheme,
+        }))
+    }
+
+    fn algorithm(&self) -> rustls::SignatureAlgorithm {
+        match self.alg {
+            TlsCertAlg::PkcsEd25519 => rustls::SignatureAlgorithm::ED25519,
+            TlsCertAlg::PkcsEcdsaP256Sha256
+            | TlsCertAlg::PkcsEcdsaP384Sha384 => {
+                rustls::SignatureAlgorithm::ECDSA
+            }
+            TlsCertAlg::PkcsRsaSha256 => rustls::SignatureAlgorithm::RSA,
+        }
+    }
+}
+
+struct LairSigner {
+    api: ghost_actor::GhostSender<LairClientApi>,
+    index: KeystoreIndex,
+    rustls_scheme: rustls::SignatureScheme,
+    scheme: TlsCertSignatureScheme,
+}
+
+impl rustls::sign::Signer for LairSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, rustls::Error> {
+        let fut = self.api.tls_cert_sign_by_index(
+            self.index,
+            self.scheme,
+            Arc::new(message.to_vec()),
+        );
+        block_on(fut)
+            .map(|sig| sig.to_vec())
+            .map_err(|e| rustls::Error::General(e.to_string()))
+    }
+
+    fn scheme(&self) -> rustls::SignatureScheme {
+        self.rustls_scheme
+    }
+}
+
+/// A rustls server cert resolver, presenting the lair tls cert
+/// matching the sni the client asks for, signing via the keystore
+/// (as `remote_certified_key_from_lair`).
+/// The cert is fetched on the first handshake for an sni, then cached,
+/// `clear_cache` after renewing a cert to pick up the new one.
+/// Clients sending no sni (or one lair has no cert for) are refused.
+///
+/// rustls resolves certs synchronously, so handshakes block their
+/// thread on the keystore. Within a tokio runtime this uses
+/// `tokio::task::block_in_place`, which panics on a `current_thread`
+/// runtime, use a multi threaded one.
+pub struct LairServerCertResolver {
+    api: ghost_actor::GhostSender<LairClientApi>,
+    cache: std::sync::Mutex<HashMap<String, Arc<rustls::sign::CertifiedKey>>>,
+}
+
+impl LairServerCertResolver {
+    /// Resolve certs from the keystore behind `api`.
+    pub fn new(api: ghost_actor::GhostSender<LairClientApi>) -> Self {
+        Self {
+            api,
+            cache: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forget all cached certs, they will be fetched again as needed.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn fetch(
+        &self,
+        sni: String,
+    ) -> BoxFuture<'static, LairResult<rustls::sign::CertifiedKey>> {
+        let api = self.api.clone();
+        async move {
+            let sni = CertSni::from(sni);
+            let cert = api.tls_cert_get_cert_by_sni(sni.clone()).await?;
+
+            // an sni resolves the newest of its certs,
+            // find which entry that is by its digest
+            let index = api
+                .lair_list_entries()
+                .await?
+                .into_iter()
+                .find(|item| {
+                    item.cert_sni.as_ref() == Some(&sni)
+                        && item.cert_digest_alg.map(|alg| {
+                            internal::tls::tls_cert_digest(alg, &cert)
+                        }) == Some(item.pub_id.to_vec())
+                })
+                .ok_or("no tls cert entry for sni")?
+                .keystore_index;
+
+            remote_certified_key_from_lair(&api, index).await
+        }
+        .boxed()
+    }
+}
+
+impl rustls::server::ResolvesServerCert for LairServerCertResolver {
+    fn resolve(
+        &self,
+        client_hello: rustls::server::ClientHello,
+    ) -> Option<Arc<rustls::sign::CertifiedKey>> {
+        let sni = client_hello.server_name()?.to_string();
+        if let Some(key) = self.cache.lock().unwrap().get(&sni) {
+            return Some(key.clone());
+        }
+
+        let key = match block_on(self.fetch(sni.clone())) {
+            Ok(key) => Arc::new(key),
+            Err(err) => {
+                warn!(?err, %sni, "no lair tls cert for sni");
+                return None;
+            }
+        };
+
+        self.cache.lock().unwrap().insert(sni, key.clone());
+        Some(key)
+    }
+}
+
+/// Block the current thread on `fut`, letting other tasks run
+/// meanwhile if it is a tokio runtime thread.
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    match tokio::runtime::Handle::try_current() {
+        Ok(_) => {
+            tokio::task::block_in_place(|| futures::executor::block_on(fut))
+        }
+        Err(_) => futures::executor::block_on(fut),
+    }
+}